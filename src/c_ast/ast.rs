@@ -3,13 +3,33 @@
 //! This module defines the AST produced by the parser, representing the structure
 //! of a C program before any lowering or code generation.
 
-use crate::lexer::Token;
+use crate::{
+    c_ast::{
+        semantic::type_table::{MemberEntry, TypeTable},
+        typedef_table::TypedefTable,
+    },
+    common::{
+        span::Span,
+        symbols::{Symbol, SymbolTable},
+        util::source_name,
+    },
+    lexer::Token,
+};
 
+/// A translation unit. The symbol table is empty until the type checker fills it in.
 #[derive(Clone, Debug)]
-pub struct Program(FunctionDefinition);
+pub struct Program(Vec<Declaration>, SymbolTable);
 
+/// A function declaration. It's also a definition when it has a body.
 #[derive(Clone, Debug)]
-pub struct FunctionDefinition(Identifier, Block);
+pub struct FunctionDeclaration(
+    Identifier,
+    Vec<Identifier>,
+    Option<Block>,
+    Type,
+    Option<StorageClass>,
+    Span,
+);
 
 #[derive(Clone, Debug)]
 pub struct Identifier(String);
@@ -24,33 +44,86 @@ pub enum BlockItem {
 }
 
 #[derive(Clone, Debug)]
-pub struct Declaration(Identifier, Option<Expression>);
+pub enum Declaration {
+    FunDecl(FunctionDeclaration),
+    VarDecl(VariableDeclaration),
+    StructDecl(StructDeclaration),
+    EnumDecl(EnumDeclaration),
+}
+
+#[derive(Clone, Debug)]
+pub struct VariableDeclaration(
+    Identifier,
+    Option<Initializer>,
+    Type,
+    Option<StorageClass>,
+    Span,
+);
+
+/// A structure declaration (e.g. `struct point;`), it's also a definition when it has
+/// members (e.g. `struct point { int x; int y; };`).
+#[derive(Clone, Debug)]
+pub struct StructDeclaration(Identifier, Vec<MemberDeclaration>, Span);
+
+/// An enumeration definition (e.g. `enum color { RED, GREEN = 4 };`), with an optional tag.
+/// Every enumeration type is an `int`, so only its enumerators matter.
+#[derive(Clone, Debug)]
+pub struct EnumDeclaration(Option<Identifier>, Vec<Enumerator>, Span);
+
+/// An enumeration constant and the expression of its value, `None` when it's one more than
+/// the previous one. After type checking every value is an int constant.
+pub type Enumerator = (Identifier, Option<Expression>);
+
+/// A member of a structure definition, with its name and type.
+#[derive(Clone, Debug)]
+pub struct MemberDeclaration(Identifier, Type);
+
+/// The initial value of a variable: a single expression for scalars, or a brace enclosed
+/// list of initializers for arrays and structures (e.g. `{1, 2, 3}`).
+#[derive(Clone, Debug)]
+pub enum Initializer {
+    Single(Expression),
+    Compound(Vec<Initializer>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StorageClass {
+    Static,
+    Extern,
+}
 
 #[derive(Clone, Debug)]
 pub enum ForInit {
-    InitDecl(Box<Declaration>),
+    // InitDecl(declarations), `int i = 0, j = n`
+    InitDecl(Vec<VariableDeclaration>),
     InitExp(Option<Box<Expression>>),
 }
 
+/// A statement, the last field of every variant is its span.
 #[allow(unused)]
 #[derive(Clone, Debug)]
 pub enum Statement {
     // Return(exp)
-    Return(Expression),
+    Return(Expression, Span),
     // Expression(exp)
-    Expression(Expression),
+    Expression(Expression, Span),
     // If (exp condition, statement then, statement? else)
-    If(Box<Expression>, Box<Statement>, Option<Box<Statement>>),
+    If(
+        Box<Expression>,
+        Box<Statement>,
+        Option<Box<Statement>>,
+        Span,
+    ),
     // Compound(block)
-    Compound(Box<Block>),
+    Compound(Box<Block>, Span),
     // Break
-    Break(Identifier),
+    Break(Identifier, Span),
     // Continue
-    Continue(Identifier),
+    Continue(Identifier, Span),
     // While (exp condition, statement body)
-    While(Box<Expression>, Box<Statement>, Identifier),
+    While(Box<Expression>, Box<Statement>, Identifier, Span),
     // DoWhile (statement body, exp condition)
-    DoWhile(Box<Statement>, Box<Expression>, Identifier),
+    DoWhile(Box<Statement>, Box<Expression>, Identifier, Span),
     // For (for_init init, exp? condition, exp? post, statement body)
     For(
         Box<ForInit>,
@@ -58,18 +131,119 @@ pub enum Statement {
         Option<Box<Expression>>,
         Box<Statement>,
         Identifier,
+        Span,
+    ),
+    // Switch (exp controlling, statement body, label, cases)
+    Switch(
+        Box<Expression>,
+        Box<Statement>,
+        Identifier,
+        Vec<SwitchCase>,
+        Span,
     ),
-    Null,
+    // Case (exp constant, statement, label), `case exp: statement`
+    Case(Expression, Box<Statement>, Identifier, Span),
+    // Default (statement, label), `default: statement`
+    Default(Box<Statement>, Identifier, Span),
+    // Goto(label)
+    Goto(Identifier, Span),
+    // Labeled(label, statement), `label: statement`
+    Labeled(Identifier, Box<Statement>, Span),
+    // VaStart(va_list exp, last parameter exp), `__builtin_va_start(ap, last);`
+    VaStart(Expression, Expression, Span),
+    // VaEnd(va_list exp), `__builtin_va_end(ap);`
+    VaEnd(Expression, Span),
+    // VaCopy(va_list exp destination, va_list exp source), `__builtin_va_copy(dst, src);`
+    VaCopy(Expression, Expression, Span),
+    Null(Span),
 }
 
+/// A case of a switch statement: the value it matches, `None` for the default, and the
+/// label of its statement.
+pub type SwitchCase = (Option<Const>, Identifier);
+
+/// An expression, the last field of every variant is its span.
 #[derive(Clone, Debug)]
 pub enum Expression {
-    Constant(i32),
-    Var(Identifier),
-    Unary(UnaryOperator, Box<Expression>),
-    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
-    Assignment(Box<Expression>, Box<Expression>),
-    Conditional(Box<Expression>, Box<Expression>, Box<Expression>), // short circuit evaluation
+    Constant(Const, Span),
+    Var(Identifier, Span),
+    // Cast(target type, exp)
+    Cast(Type, Box<Expression>, Span),
+    Unary(UnaryOperator, Box<Expression>, Span),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>, Span),
+    Assignment(Box<Expression>, Box<Expression>, Span),
+    // CompoundAssignment(op, lvalue exp, exp), `lvalue op= exp`, also `++lvalue`/`--lvalue`
+    CompoundAssignment(BinaryOperator, Box<Expression>, Box<Expression>, Span),
+    // Postfix(Add or Subtract, lvalue exp), `lvalue++`/`lvalue--`, the old value is the result
+    Postfix(BinaryOperator, Box<Expression>, Span),
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>, Span), // short circuit evaluation
+    // Comma(exp, exp), evaluates the left side, then the right side which is the result
+    Comma(Box<Expression>, Box<Expression>, Span),
+    FunctionCall(Identifier, Vec<Expression>, Span),
+    // IndirectCall(function pointer exp, args), `(*fp)(args)` or `fp(args)`
+    IndirectCall(Box<Expression>, Vec<Expression>, Span),
+    // Dereference(pointer exp)
+    Dereference(Box<Expression>, Span),
+    // AddressOf(lvalue exp)
+    AddressOf(Box<Expression>, Span),
+    // Subscript(exp, exp), one of them is a pointer and the other an integer
+    Subscript(Box<Expression>, Box<Expression>, Span),
+    // String(value), a string literal with its escape sequences already processed
+    String(String, Span),
+    // Dot(structure exp, member), `s.member`
+    Dot(Box<Expression>, Identifier, Span),
+    // Arrow(pointer exp, member), `p->member`
+    Arrow(Box<Expression>, Identifier, Span),
+    // VaArg(va_list exp, type), `__builtin_va_arg(ap, type)`, the next variadic argument
+    VaArg(Box<Expression>, Type, Span),
+    // Error, the placeholder of an expression with an error (e.g. an undeclared variable),
+    // so the semantic passes can go on after reporting it. It never gets past them.
+    Error(Span),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Const {
+    ConstInt(i32),
+    ConstLong(i64),
+    ConstUInt(u32),
+    ConstULong(u64),
+    ConstDouble(f64),
+    ConstChar(i8),
+    ConstUChar(u8),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    /// A plain `char`, signed like `signed char` but a different type
+    Char,
+    SChar,
+    UChar,
+    Int,
+    Long,
+    UInt,
+    ULong,
+    Double,
+    /// A pointer to a value of the given type.
+    Pointer(Box<Type>),
+    /// An array of the given number of elements.
+    Array(Box<Type>, usize),
+    /// A function type, described by its parameter types, its return type and whether it
+    /// takes a variable number of arguments after the parameters (`...`).
+    FunType(Vec<Type>, Box<Type>, bool),
+    /// A structure type, identified by its unique tag. Its layout is in the type table.
+    Structure(String),
+    /// A `const` and/or `volatile` version of another type. It's built with
+    /// `Type::qualified`, so it never wraps an array (its elements are qualified instead),
+    /// a function or another qualified type.
+    Qualified(Box<Type>, Qualifiers),
+}
+
+/// The qualifiers of a type: a `const` object can't be modified after its initialization,
+/// and every read and write of a `volatile` object must happen exactly as the program says.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Qualifiers {
+    pub is_const: bool,
+    pub is_volatile: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -108,17 +282,46 @@ pub enum BinaryOperator {
 }
 
 impl Program {
-    pub fn new(function_definition: FunctionDefinition) -> Self {
-        Program(function_definition)
+    pub fn new(declarations: Vec<Declaration>) -> Self {
+        Program(declarations, SymbolTable::new())
+    }
+
+    /// Attaches the symbol table built during semantic analysis.
+    pub fn with_symbols(self, symbols: SymbolTable) -> Self {
+        Program(self.0, symbols)
     }
 
-    pub fn function_definition(&self) -> &FunctionDefinition {
+    pub fn declarations(&self) -> &Vec<Declaration> {
         &self.0
     }
 
-    /// Consumes the program and returns the function definition.
-    pub fn into_function_definition(self) -> FunctionDefinition {
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.1
+    }
+
+    /// Returns the file scope function declarations.
+    pub fn functions(&self) -> Vec<&FunctionDeclaration> {
         self.0
+            .iter()
+            .filter_map(|d| match d {
+                Declaration::FunDecl(f) => Some(f),
+                Declaration::VarDecl(_) | Declaration::StructDecl(_) | Declaration::EnumDecl(_) => {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the function declaration with the given name that has a body.
+    pub fn function_definition(&self, name: &str) -> Option<&FunctionDeclaration> {
+        self.functions()
+            .into_iter()
+            .find(|f| f.name().value() == name && f.body().is_some())
+    }
+
+    /// Consumes the program and returns (declarations, symbols).
+    pub fn into_parts(self) -> (Vec<Declaration>, SymbolTable) {
+        (self.0, self.1)
     }
 }
 
@@ -143,22 +346,57 @@ impl Block {
     }
 }
 
-impl FunctionDefinition {
-    pub fn new(name: Identifier, body: Block) -> Self {
-        FunctionDefinition(name, body)
+impl FunctionDeclaration {
+    pub fn new(
+        name: Identifier,
+        params: Vec<Identifier>,
+        body: Option<Block>,
+        fun_type: Type,
+        storage_class: Option<StorageClass>,
+    ) -> Self {
+        FunctionDeclaration(name, params, body, fun_type, storage_class, Span::default())
+    }
+
+    /// Sets the span, from the declarator to the end of the body, if there is one.
+    pub fn with_span(self, span: Span) -> Self {
+        FunctionDeclaration(self.0, self.1, self.2, self.3, self.4, span)
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.5
     }
 
     pub fn name(&self) -> &Identifier {
         &self.0
     }
 
-    pub fn body(&self) -> &Block {
+    pub fn params(&self) -> &Vec<Identifier> {
         &self.1
     }
 
-    /// Consumes the function definition and returns (name, body).
-    pub fn into_parts(self) -> (Identifier, Block) {
-        (self.0, self.1)
+    pub fn body(&self) -> Option<&Block> {
+        self.2.as_ref()
+    }
+
+    pub fn fun_type(&self) -> &Type {
+        &self.3
+    }
+
+    pub fn storage_class(&self) -> Option<&StorageClass> {
+        self.4.as_ref()
+    }
+
+    /// Consumes the function declaration and returns (name, params, body, fun_type, storage_class).
+    pub fn into_parts(
+        self,
+    ) -> (
+        Identifier,
+        Vec<Identifier>,
+        Option<Block>,
+        Type,
+        Option<StorageClass>,
+    ) {
+        (self.0, self.1, self.2, self.3, self.4)
     }
 }
 
@@ -189,25 +427,818 @@ impl PartialEq for Identifier {
 }
 
 impl Declaration {
-    pub fn new(name: Identifier, initializer: Option<Expression>) -> Self {
-        Declaration(name, initializer)
+    pub fn name(&self) -> &Identifier {
+        match self {
+            Declaration::FunDecl(f) => f.name(),
+            Declaration::VarDecl(v) => v.name(),
+            Declaration::StructDecl(s) => s.tag(),
+            // an enumeration without a tag is named after its first enumerator
+            Declaration::EnumDecl(e) => e.tag().unwrap_or(&e.enumerators()[0].0),
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        match self {
+            Declaration::FunDecl(f) => f.span(),
+            Declaration::VarDecl(v) => v.span(),
+            Declaration::StructDecl(s) => s.span(),
+            Declaration::EnumDecl(e) => e.span(),
+        }
+    }
+
+    /// Whether the token starts a declaration (a type or storage class specifier). An
+    /// identifier only does if it's a typedef name in the current scope.
+    pub fn is_declaration(token: Option<&Token>, typedefs: &TypedefTable) -> bool {
+        if let Some(Token::Identifier(name)) = token {
+            return typedefs.is_typedef(name);
+        }
+
+        matches!(
+            token,
+            Some(
+                Token::Int
+                    | Token::Long
+                    | Token::Double
+                    | Token::Char
+                    | Token::Signed
+                    | Token::Unsigned
+                    | Token::Struct
+                    | Token::Enum
+                    | Token::VaList
+                    | Token::Const
+                    | Token::Volatile
+                    | Token::Static
+                    | Token::Extern
+                    | Token::Typedef
+            )
+        )
+    }
+}
+
+impl VariableDeclaration {
+    pub fn new(
+        name: Identifier,
+        initializer: Option<Initializer>,
+        var_type: Type,
+        storage_class: Option<StorageClass>,
+    ) -> Self {
+        VariableDeclaration(name, initializer, var_type, storage_class, Span::default())
+    }
+
+    /// Sets the span, from the declarator to the end of the initializer.
+    pub fn with_span(self, span: Span) -> Self {
+        VariableDeclaration(self.0, self.1, self.2, self.3, span)
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.4
     }
 
     pub fn name(&self) -> &Identifier {
         &self.0
     }
 
-    pub fn initializer(&self) -> Option<&Expression> {
+    pub fn initializer(&self) -> Option<&Initializer> {
         self.1.as_ref()
     }
 
-    /// Consumes the declaration and returns (name, initializer).
-    pub fn into_parts(self) -> (Identifier, Option<Expression>) {
+    pub fn var_type(&self) -> &Type {
+        &self.2
+    }
+
+    pub fn storage_class(&self) -> Option<&StorageClass> {
+        self.3.as_ref()
+    }
+
+    /// Consumes the declaration and returns (name, initializer, var_type, storage_class).
+    pub fn into_parts(self) -> (Identifier, Option<Initializer>, Type, Option<StorageClass>) {
+        (self.0, self.1, self.2, self.3)
+    }
+}
+
+impl StructDeclaration {
+    pub fn new(tag: Identifier, members: Vec<MemberDeclaration>) -> Self {
+        StructDeclaration(tag, members, Span::default())
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        StructDeclaration(self.0, self.1, span)
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.2
+    }
+
+    pub fn tag(&self) -> &Identifier {
+        &self.0
+    }
+
+    /// The members of the structure, empty when it's only declared.
+    pub fn members(&self) -> &Vec<MemberDeclaration> {
+        &self.1
+    }
+
+    /// Consumes the declaration and returns (tag, members).
+    pub fn into_parts(self) -> (Identifier, Vec<MemberDeclaration>) {
+        (self.0, self.1)
+    }
+}
+
+impl EnumDeclaration {
+    pub fn new(tag: Option<Identifier>, enumerators: Vec<Enumerator>) -> Self {
+        EnumDeclaration(tag, enumerators, Span::default())
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        EnumDeclaration(self.0, self.1, span)
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.2
+    }
+
+    pub fn tag(&self) -> Option<&Identifier> {
+        self.0.as_ref()
+    }
+
+    /// The enumerators, in order. There's always at least one.
+    pub fn enumerators(&self) -> &Vec<Enumerator> {
+        &self.1
+    }
+
+    /// Consumes the declaration and returns (tag, enumerators).
+    pub fn into_parts(self) -> (Option<Identifier>, Vec<Enumerator>) {
+        (self.0, self.1)
+    }
+}
+
+impl MemberDeclaration {
+    pub fn new(name: Identifier, member_type: Type) -> Self {
+        MemberDeclaration(name, member_type)
+    }
+
+    pub fn name(&self) -> &Identifier {
+        &self.0
+    }
+
+    pub fn member_type(&self) -> &Type {
+        &self.1
+    }
+
+    /// Consumes the member and returns (name, member_type).
+    pub fn into_parts(self) -> (Identifier, Type) {
         (self.0, self.1)
     }
+}
+
+impl Expression {
+    /// The part of the source the expression comes from. The casts added by the type
+    /// checker have the span of the expression they convert.
+    pub fn span(&self) -> &Span {
+        match self {
+            Expression::Constant(.., span)
+            | Expression::Var(.., span)
+            | Expression::Cast(.., span)
+            | Expression::Unary(.., span)
+            | Expression::Binary(.., span)
+            | Expression::Assignment(.., span)
+            | Expression::CompoundAssignment(.., span)
+            | Expression::Postfix(.., span)
+            | Expression::Conditional(.., span)
+            | Expression::Comma(.., span)
+            | Expression::FunctionCall(.., span)
+            | Expression::IndirectCall(.., span)
+            | Expression::Dereference(.., span)
+            | Expression::AddressOf(.., span)
+            | Expression::Subscript(.., span)
+            | Expression::String(.., span)
+            | Expression::Dot(.., span)
+            | Expression::Arrow(.., span)
+            | Expression::VaArg(.., span)
+            | Expression::Error(span) => span,
+        }
+    }
+
+    /// Whether the expression is an error placeholder or contains one.
+    pub fn has_error(&self) -> bool {
+        match self {
+            Expression::Error(_) => true,
+            Expression::Constant(..) | Expression::Var(..) | Expression::String(..) => false,
+            Expression::Cast(_, inner, _)
+            | Expression::Unary(_, inner, _)
+            | Expression::Postfix(_, inner, _)
+            | Expression::Dereference(inner, _)
+            | Expression::AddressOf(inner, _)
+            | Expression::Dot(inner, _, _)
+            | Expression::Arrow(inner, _, _)
+            | Expression::VaArg(inner, _, _) => inner.has_error(),
+            Expression::Binary(_, left, right, _)
+            | Expression::Assignment(left, right, _)
+            | Expression::CompoundAssignment(_, left, right, _)
+            | Expression::Comma(left, right, _)
+            | Expression::Subscript(left, right, _) => left.has_error() || right.has_error(),
+            Expression::Conditional(cond, then, el, _) => {
+                cond.has_error() || then.has_error() || el.has_error()
+            }
+            Expression::FunctionCall(_, args, _) => args.iter().any(Expression::has_error),
+            Expression::IndirectCall(callee, args, _) => {
+                callee.has_error() || args.iter().any(Expression::has_error)
+            }
+        }
+    }
+
+    /// Whether the expression designates an object, so it can be assigned or have its
+    /// address taken. A member of a structure is an lvalue when the structure is one.
+    pub fn is_lvalue(&self) -> bool {
+        match self {
+            Expression::Dot(inner, _, _) => inner.is_lvalue(),
+            _ => matches!(
+                self,
+                Expression::Var(_, _)
+                    | Expression::Dereference(_, _)
+                    | Expression::Subscript(..)
+                    | Expression::String(_, _)
+                    | Expression::Arrow(..)
+            ),
+        }
+    }
+
+    /// Whether the expression is an integer constant equal to zero, which converts to a
+    /// null pointer of any type.
+    pub fn is_null_pointer_constant(&self) -> bool {
+        match self {
+            Expression::Constant(Const::ConstDouble(_), _) => false,
+            Expression::Constant(c, _) => c.as_i64() == 0,
+            _ => false,
+        }
+    }
+
+    /// Evaluates an integer constant expression at compile time, `None` if the expression
+    /// isn't one (or divides by zero).
+    ///
+    /// It expects an expression that went through the type checker: the operands of binary
+    /// operators already have their common type, so the type of every intermediate value is
+    /// the type of its constants. Floating-point constants are only allowed as operands of
+    /// casts to an integer type.
+    pub fn integer_constant(&self) -> Option<Const> {
+        let value = self.constant_value()?;
+        value.ty().is_integer().then_some(value)
+    }
+
+    /// Evaluates a constant expression of any arithmetic type, like `integer_constant`, for
+    /// the initializers of static variables. Doubles can also be negated and integers cast
+    /// to double.
+    pub fn arithmetic_constant(&self) -> Option<Const> {
+        match self {
+            Expression::Unary(UnaryOperator::Negate, inner, _) => {
+                match inner.arithmetic_constant()? {
+                    Const::ConstDouble(d) => Some(Const::ConstDouble(-d)),
+                    _ => self.integer_constant(),
+                }
+            }
+            Expression::Cast(Type::Double, inner, _) => {
+                Some(inner.arithmetic_constant()?.convert_to(&Type::Double))
+            }
+            _ => self.constant_value(),
+        }
+    }
+
+    /// Spells an lvalue the way it's written, with the names of the source, for the
+    /// messages about it. The implicit casts are left out, and so are the operands that
+    /// aren't names, members or constants.
+    pub fn lvalue_spelling(&self) -> String {
+        match self {
+            Expression::Var(id, _) => source_name(id.value()).to_string(),
+            Expression::Constant(Const::ConstDouble(d), _) => d.to_string(),
+            Expression::Constant(c, _) => c.as_i64().to_string(),
+            Expression::Cast(_, inner, _) => inner.lvalue_spelling(),
+            Expression::Dereference(inner, _) => format!("*{}", inner.lvalue_spelling()),
+            Expression::Subscript(array, index, _) => {
+                format!("{}[{}]", array.lvalue_spelling(), index.lvalue_spelling())
+            }
+            Expression::Dot(inner, member, _) => {
+                format!("{}.{}", inner.lvalue_spelling(), member.value())
+            }
+            Expression::Arrow(inner, member, _) => {
+                format!("{}->{}", inner.lvalue_spelling(), member.value())
+            }
+            _ => "(...)".to_string(),
+        }
+    }
+
+    fn constant_value(&self) -> Option<Const> {
+        match self {
+            Expression::Constant(c, _) => Some(c.clone()),
+            Expression::Cast(ty, inner, span) if ty.is_integer() => {
+                Some(inner.constant_value()?.convert_to(ty))
+            }
+            Expression::Unary(op, inner, _) => {
+                let c = inner.integer_constant()?;
+                let value = c.as_i64();
+                Some(match op {
+                    UnaryOperator::Negate => {
+                        Const::ConstLong(value.wrapping_neg()).convert_to(&c.ty())
+                    }
+                    UnaryOperator::Complement => Const::ConstLong(!value).convert_to(&c.ty()),
+                    UnaryOperator::Not => Const::ConstInt((value == 0) as i32),
+                })
+            }
+            Expression::Binary(BinaryOperator::And, left, right, _) => {
+                let value = left.integer_constant()?.as_i64() != 0
+                    && right.integer_constant()?.as_i64() != 0;
+                Some(Const::ConstInt(value as i32))
+            }
+            Expression::Binary(BinaryOperator::Or, left, right, _) => {
+                let value = left.integer_constant()?.as_i64() != 0
+                    || right.integer_constant()?.as_i64() != 0;
+                Some(Const::ConstInt(value as i32))
+            }
+            Expression::Binary(op, left, right, _) => {
+                let (left, right) = (left.integer_constant()?, right.integer_constant()?);
+                let ty = left.ty();
+                let (a, b) = (left.as_i64(), right.as_i64());
+                // unsigned values are zero extended, so only ulongs need unsigned operations
+                let unsigned = ty == Type::ULong;
+                let compare = |signed: bool, unsigned: bool| {
+                    Some(Const::ConstInt(
+                        if ty.is_signed() { signed } else { unsigned } as i32,
+                    ))
+                };
+                let value = match op {
+                    BinaryOperator::Add => a.wrapping_add(b),
+                    BinaryOperator::Subtract => a.wrapping_sub(b),
+                    BinaryOperator::Multiply => a.wrapping_mul(b),
+                    BinaryOperator::Divide | BinaryOperator::Remainder if b == 0 => return None,
+                    BinaryOperator::Divide if unsigned => ((a as u64) / (b as u64)) as i64,
+                    BinaryOperator::Divide => a.wrapping_div(b),
+                    BinaryOperator::Remainder if unsigned => ((a as u64) % (b as u64)) as i64,
+                    BinaryOperator::Remainder => a.wrapping_rem(b),
+                    BinaryOperator::BitwiseAnd => a & b,
+                    BinaryOperator::BitwiseOr => a | b,
+                    BinaryOperator::BitwiseXor => a ^ b,
+                    BinaryOperator::LeftShift => a.wrapping_shl(b as u32),
+                    BinaryOperator::RightShift if ty.is_signed() => a.wrapping_shr(b as u32),
+                    BinaryOperator::RightShift => ((a as u64).wrapping_shr(b as u32)) as i64,
+                    BinaryOperator::Equal => return compare(a == b, a == b),
+                    BinaryOperator::NotEqual => return compare(a != b, a != b),
+                    BinaryOperator::LessThan => return compare(a < b, (a as u64) < (b as u64)),
+                    BinaryOperator::LessThanOrEqual => {
+                        return compare(a <= b, (a as u64) <= (b as u64));
+                    }
+                    BinaryOperator::GreaterThan => return compare(a > b, (a as u64) > (b as u64)),
+                    BinaryOperator::GreaterThanOrEqual => {
+                        return compare(a >= b, (a as u64) >= (b as u64));
+                    }
+                    BinaryOperator::And | BinaryOperator::Or => return None,
+                };
+                Some(Const::ConstLong(value).convert_to(&ty))
+            }
+            Expression::Conditional(cond, then, el, _) => {
+                if cond.integer_constant()?.as_i64() != 0 {
+                    then.constant_value()
+                } else {
+                    el.constant_value()
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the type of an expression that went through the type checker.
+    ///
+    /// The type checker makes every implicit conversion explicit with a `Cast`, so the type
+    /// can be read from the expression itself plus the symbol table. It's the type of the
+    /// value, which is never qualified: only the objects are `const` or `volatile` (see
+    /// `qualified_ty`).
+    pub fn ty(&self, symbols: &SymbolTable) -> Type {
+        self.qualified_ty(symbols).unqualified().clone()
+    }
+
+    /// Returns the type of an expression like `ty`, with the qualifiers of the object when
+    /// it's an lvalue (e.g. `const int` for a `const int` variable). A member of a structure
+    /// also has the qualifiers of the structure.
+    pub fn qualified_ty(&self, symbols: &SymbolTable) -> Type {
+        match self {
+            Expression::Constant(c, _) => c.ty(),
+            Expression::Var(id, _) => symbols
+                .get(id.value())
+                .map_or(Type::Int, |symbol| symbol.ty.clone()),
+            Expression::Cast(ty, _, _) => ty.clone(),
+            Expression::Unary(UnaryOperator::Not, _, _) => Type::Int,
+            Expression::Unary(_, inner, _) => inner.ty(symbols),
+            // the difference between two pointers is the number of elements between them
+            Expression::Binary(BinaryOperator::Subtract, left, right, span)
+                if left.ty(symbols).is_pointer() && right.ty(symbols).is_pointer() =>
+            {
+                Type::Long
+            }
+            // in pointer arithmetic the integer may be on either side
+            Expression::Binary(BinaryOperator::Add, left, right, span)
+                if right.ty(symbols).is_pointer() =>
+            {
+                right.ty(symbols)
+            }
+            Expression::Binary(op, left, _, span) if op.is_arithmetic() => left.ty(symbols),
+            // logical and relational operators always produce an int
+            Expression::Binary(..) => Type::Int,
+            Expression::Assignment(left, _, _)
+            | Expression::CompoundAssignment(_, left, _, _)
+            | Expression::Postfix(_, left, _) => left.ty(symbols),
+            Expression::Conditional(_, then, _, _) => then.ty(symbols),
+            Expression::Comma(_, right, _) => right.ty(symbols),
+            Expression::Dereference(inner, _) => match inner.ty(symbols) {
+                Type::Pointer(referenced) => *referenced,
+                _ => Type::Int,
+            },
+            Expression::AddressOf(inner, _) => Type::Pointer(Box::new(inner.qualified_ty(symbols))),
+            Expression::Subscript(left, right, _) => match (left.ty(symbols), right.ty(symbols)) {
+                (Type::Pointer(referenced), _) | (_, Type::Pointer(referenced)) => *referenced,
+                _ => Type::Int,
+            },
+            // the array holds the characters followed by the null terminator
+            Expression::String(s, _) => Type::Array(Box::new(Type::Char), s.len() + 1),
+            Expression::FunctionCall(name, _, _) => match symbols.get(name.value()) {
+                Some(Symbol {
+                    ty: Type::FunType(_, ret, _),
+                    ..
+                }) => *ret.clone(),
+                _ => Type::Int,
+            },
+            Expression::IndirectCall(callee, _, _) => match callee.ty(symbols) {
+                Type::Pointer(referenced) => match *referenced {
+                    Type::FunType(_, ret, _) => *ret,
+                    _ => Type::Int,
+                },
+                _ => Type::Int,
+            },
+            Expression::Dot(inner, member, _) => {
+                let struct_type = inner.qualified_ty(symbols);
+                struct_type
+                    .member(member.value(), symbols.types())
+                    .map_or(Type::Int, |m| {
+                        m.ty.clone().qualified(struct_type.qualifiers())
+                    })
+            }
+            Expression::Arrow(inner, member, _) => match inner.ty(symbols) {
+                Type::Pointer(referenced) => referenced
+                    .member(member.value(), symbols.types())
+                    .map_or(Type::Int, |m| {
+                        m.ty.clone().qualified(referenced.qualifiers())
+                    }),
+                _ => Type::Int,
+            },
+            Expression::VaArg(_, ty, _) => ty.clone(),
+            Expression::Error(_) => Type::Int,
+        }
+    }
+}
+
+impl Statement {
+    pub fn span(&self) -> &Span {
+        match self {
+            Statement::Return(.., span)
+            | Statement::Expression(.., span)
+            | Statement::If(.., span)
+            | Statement::Compound(.., span)
+            | Statement::Break(.., span)
+            | Statement::Continue(.., span)
+            | Statement::While(.., span)
+            | Statement::DoWhile(.., span)
+            | Statement::For(.., span)
+            | Statement::Switch(.., span)
+            | Statement::Case(.., span)
+            | Statement::Default(.., span)
+            | Statement::Goto(.., span)
+            | Statement::Labeled(.., span)
+            | Statement::VaStart(.., span)
+            | Statement::VaEnd(.., span)
+            | Statement::VaCopy(.., span)
+            | Statement::Null(span) => span,
+        }
+    }
+}
+
+impl Const {
+    /// Returns the zero constant of a scalar type, pointers are zeroed with an unsigned long.
+    pub fn zero(ty: &Type) -> Self {
+        match ty.unqualified() {
+            Type::Long => Const::ConstLong(0),
+            Type::UInt => Const::ConstUInt(0),
+            Type::ULong | Type::Pointer(_) => Const::ConstULong(0),
+            Type::Double => Const::ConstDouble(0.0),
+            Type::Char | Type::SChar => Const::ConstChar(0),
+            Type::UChar => Const::ConstUChar(0),
+            _ => Const::ConstInt(0),
+        }
+    }
+
+    pub fn ty(&self) -> Type {
+        match self {
+            Const::ConstInt(_) => Type::Int,
+            Const::ConstLong(_) => Type::Long,
+            Const::ConstUInt(_) => Type::UInt,
+            Const::ConstULong(_) => Type::ULong,
+            Const::ConstDouble(_) => Type::Double,
+            Const::ConstChar(_) => Type::Char,
+            Const::ConstUChar(_) => Type::UChar,
+        }
+    }
+
+    /// Converts the constant to another scalar type at compile time. Conversions between
+    /// integer types keep the low bits, like `as` does.
+    pub fn convert_to(&self, ty: &Type) -> Self {
+        let value = self.as_i64();
+        match (self, ty.unqualified()) {
+            (Const::ConstULong(c), Type::Double) => Const::ConstDouble(*c as f64),
+            (Const::ConstDouble(c), Type::Double) => Const::ConstDouble(*c),
+            (_, Type::Double) => Const::ConstDouble(value as f64),
+            (Const::ConstDouble(c), Type::ULong | Type::Pointer(_)) => Const::ConstULong(*c as u64),
+            (_, Type::Char | Type::SChar) => Const::ConstChar(value as i8),
+            (_, Type::UChar) => Const::ConstUChar(value as u8),
+            (_, Type::Long) => Const::ConstLong(value),
+            (_, Type::UInt) => Const::ConstUInt(value as u32),
+            (_, Type::ULong | Type::Pointer(_)) => Const::ConstULong(value as u64),
+            _ => Const::ConstInt(value as i32),
+        }
+    }
+
+    /// Returns the two's complement bits of the constant, sign or zero extended to 64 bits
+    /// depending on its type. Doubles are truncated towards zero.
+    pub fn as_i64(&self) -> i64 {
+        match self {
+            Const::ConstInt(c) => *c as i64,
+            Const::ConstLong(c) => *c,
+            Const::ConstUInt(c) => *c as i64,
+            Const::ConstULong(c) => *c as i64,
+            Const::ConstDouble(c) => *c as i64,
+            Const::ConstChar(c) => *c as i64,
+            Const::ConstUChar(c) => *c as i64,
+        }
+    }
+}
+
+impl Type {
+    /// Returns the type both operands are converted to before a binary operation.
+    /// Characters are promoted to int first. Double always wins, otherwise the larger type
+    /// wins and between types of the same size the unsigned one wins.
+    pub fn common_type(t1: &Type, t2: &Type) -> Type {
+        let (t1, t2) = (&t1.promote(), &t2.promote());
+        if t1 == t2 {
+            t1.clone()
+        } else if *t1 == Type::Double || *t2 == Type::Double {
+            Type::Double
+        } else if t1.scalar_size() == t2.scalar_size() {
+            if t1.is_signed() {
+                t2.clone()
+            } else {
+                t1.clone()
+            }
+        } else if t1.scalar_size() > t2.scalar_size() {
+            t1.clone()
+        } else {
+            t2.clone()
+        }
+    }
+
+    /// Returns the type of the value after the integer promotions: characters are
+    /// promoted to int, every other type stays the same (without its qualifiers).
+    pub fn promote(&self) -> Type {
+        if self.is_character() {
+            Type::Int
+        } else {
+            self.unqualified().clone()
+        }
+    }
+
+    /// Returns the type with the given qualifiers added to its own. Qualifying an array
+    /// qualifies its elements, and functions can't be qualified so they stay the same.
+    pub fn qualified(self, qualifiers: Qualifiers) -> Type {
+        if qualifiers == Qualifiers::default() {
+            return self;
+        }
+
+        match self {
+            Type::Array(element, size) => {
+                Type::Array(Box::new(element.qualified(qualifiers)), size)
+            }
+            Type::FunType(..) => self,
+            Type::Qualified(inner, own) => Type::Qualified(inner, own.union(qualifiers)),
+            ty => Type::Qualified(Box::new(ty), qualifiers),
+        }
+    }
+
+    /// Returns the type without its own qualifiers, the qualifiers of the types it's made
+    /// of (e.g. what a pointer points to) stay.
+    pub fn unqualified(&self) -> &Type {
+        match self {
+            Type::Qualified(inner, _) => inner,
+            ty => ty,
+        }
+    }
+
+    /// Returns the qualifiers of the type itself, the ones of an array are the ones of its
+    /// elements.
+    pub fn qualifiers(&self) -> Qualifiers {
+        match self {
+            Type::Qualified(_, qualifiers) => *qualifiers,
+            Type::Array(element, _) => element.qualifiers(),
+            _ => Qualifiers::default(),
+        }
+    }
+
+    /// Returns the type that decides whether two types are compatible. The qualifiers of
+    /// the parameters and of the return type of a function don't change its type, so
+    /// `int (*)(const int)` and `int (*)(int)` are the same type.
+    pub fn canonical(&self) -> Type {
+        match self {
+            Type::Pointer(referenced) => Type::Pointer(Box::new(referenced.canonical())),
+            Type::Array(element, size) => Type::Array(Box::new(element.canonical()), *size),
+            Type::FunType(params, ret, variadic) => Type::FunType(
+                params.iter().map(|p| p.unqualified().canonical()).collect(),
+                Box::new(ret.unqualified().canonical()),
+                *variadic,
+            ),
+            Type::Qualified(inner, qualifiers) => {
+                Type::Qualified(Box::new(inner.canonical()), *qualifiers)
+            }
+            ty => ty.clone(),
+        }
+    }
+
+    /// Size in bytes of a value of this type. The size of a structure comes from the type
+    /// table, incomplete structures have no size.
+    pub fn size(&self, types: &TypeTable) -> usize {
+        match self.unqualified() {
+            Type::Array(element, count) => element.size(types) * count,
+            Type::Structure(tag) => types.get(tag).map_or(0, |s| s.size),
+            _ => self.scalar_size(),
+        }
+    }
+
+    /// Size in bytes of a scalar type, zero for any other type.
+    fn scalar_size(&self) -> usize {
+        match self {
+            Type::Char | Type::SChar | Type::UChar => 1,
+            Type::Int | Type::UInt => 4,
+            Type::Long | Type::ULong | Type::Double | Type::Pointer(_) => 8,
+            Type::Array(..) | Type::FunType(..) | Type::Structure(_) => 0,
+            Type::Qualified(inner, _) => inner.scalar_size(),
+        }
+    }
+
+    /// Alignment in bytes of this type as the System V ABI defines it: scalars are aligned
+    /// to their size, arrays to their elements and structures to their most aligned member.
+    /// This is the alignment of a structure member.
+    pub fn natural_alignment(&self, types: &TypeTable) -> usize {
+        match self.unqualified() {
+            Type::Array(element, _) => element.natural_alignment(types),
+            Type::Structure(tag) => types.get(tag).map_or(1, |s| s.alignment),
+            _ => self.scalar_size(),
+        }
+    }
+
+    /// Alignment in bytes of a variable of this type. It's the natural alignment, except
+    /// that array variables of 16 bytes or more are aligned to 16 bytes as the System V ABI
+    /// requires.
+    pub fn alignment(&self, types: &TypeTable) -> usize {
+        match self {
+            Type::Array(..) if self.size(types) >= 16 => 16,
+            _ => self.natural_alignment(types),
+        }
+    }
+
+    /// Returns the member with the given name, if this is a complete structure type that
+    /// has one.
+    pub fn member<'a>(&self, name: &str, types: &'a TypeTable) -> Option<&'a MemberEntry> {
+        match self.unqualified() {
+            Type::Structure(tag) => types.get(tag).and_then(|s| s.member(name)),
+            _ => None,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self.unqualified(),
+            Type::Char | Type::SChar | Type::Int | Type::Long
+        )
+    }
+
+    /// Whether the type is an integer or a double.
+    pub fn is_arithmetic(&self) -> bool {
+        self.is_integer() || self.is_double()
+    }
+
+    pub fn is_double(&self) -> bool {
+        *self.unqualified() == Type::Double
+    }
+
+    /// Whether the type is one of the three character types.
+    pub fn is_character(&self) -> bool {
+        matches!(self.unqualified(), Type::Char | Type::SChar | Type::UChar)
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self.unqualified(), Type::Pointer(_))
+    }
+
+    /// Whether the type is a pointer to a function, which can be called but not used in
+    /// pointer arithmetic.
+    pub fn is_function_pointer(&self) -> bool {
+        matches!(self.unqualified(), Type::Pointer(referenced) if matches!(**referenced, Type::FunType(..)))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Type::Array(..))
+    }
+
+    pub fn is_structure(&self) -> bool {
+        matches!(self.unqualified(), Type::Structure(_))
+    }
+
+    pub fn is_const(&self) -> bool {
+        self.qualifiers().is_const
+    }
+
+    pub fn is_volatile(&self) -> bool {
+        self.qualifiers().is_volatile
+    }
+
+    /// Whether the type is an arithmetic type or a pointer, the only types that can be
+    /// compared against zero.
+    pub fn is_scalar(&self) -> bool {
+        self.is_arithmetic() || self.is_pointer()
+    }
+
+    /// Whether the type is an integer type (not a double, a pointer or an aggregate),
+    /// characters included.
+    pub fn is_integer(&self) -> bool {
+        self.is_character()
+            || matches!(
+                self.unqualified(),
+                Type::Int | Type::Long | Type::UInt | Type::ULong
+            )
+    }
+}
+
+impl Qualifiers {
+    pub const CONST: Qualifiers = Qualifiers {
+        is_const: true,
+        is_volatile: false,
+    };
+
+    pub const VOLATILE: Qualifiers = Qualifiers {
+        is_const: false,
+        is_volatile: true,
+    };
+
+    /// Returns the qualifiers that are in either set.
+    pub fn union(self, other: Qualifiers) -> Qualifiers {
+        Qualifiers {
+            is_const: self.is_const || other.is_const,
+            is_volatile: self.is_volatile || other.is_volatile,
+        }
+    }
+
+    /// Whether every qualifier of `other` is in this set too.
+    pub fn contains(self, other: Qualifiers) -> bool {
+        (self.is_const || !other.is_const) && (self.is_volatile || !other.is_volatile)
+    }
+}
+
+impl BinaryOperator {
+    /// Whether the result has the type of the operands (as opposed to logical and
+    /// relational operators, which produce an int).
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Add
+                | BinaryOperator::Subtract
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Remainder
+                | BinaryOperator::BitwiseAnd
+                | BinaryOperator::BitwiseOr
+                | BinaryOperator::BitwiseXor
+                | BinaryOperator::LeftShift
+                | BinaryOperator::RightShift
+        )
+    }
+
+    /// Whether this is a bitwise shift, the result has the type of the left operand.
+    pub fn is_shift(&self) -> bool {
+        matches!(self, BinaryOperator::LeftShift | BinaryOperator::RightShift)
+    }
 
-    pub fn is_declaration(token: Option<&&Token>) -> bool {
-        // right now we only support only one declaration type which is the 'int' type declaration
-        token == Some(&&Token::Int) // TODO: this is a workaround 
+    /// Whether the operator only accepts integer operands.
+    pub fn is_integer_only(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Remainder
+                | BinaryOperator::BitwiseAnd
+                | BinaryOperator::BitwiseOr
+                | BinaryOperator::BitwiseXor
+                | BinaryOperator::LeftShift
+                | BinaryOperator::RightShift
+        )
     }
 }
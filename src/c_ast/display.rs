@@ -2,8 +2,9 @@ use std::fmt;
 
 use crate::{
     c_ast::ast::{
-        BinaryOperator, Block, BlockItem, Declaration, Expression, ForInit, FunctionDefinition,
-        Identifier, Program, Statement, UnaryOperator,
+        BinaryOperator, Block, BlockItem, Const, Declaration, EnumDeclaration, Expression, ForInit,
+        FunctionDeclaration, Identifier, Initializer, Program, Qualifiers, Statement,
+        StructDeclaration, Type, UnaryOperator, VariableDeclaration,
     },
     common::util::indent,
 };
@@ -20,12 +21,20 @@ impl fmt::Display for Program {
         write!(
             f,
             "{}\n)",
-            indent(&self.function_definition().to_string(), 4)
+            indent(
+                &self
+                    .declarations()
+                    .iter()
+                    .map(|decl| decl.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                4
+            )
         )
     }
 }
 
-impl fmt::Display for FunctionDefinition {
+impl fmt::Display for FunctionDeclaration {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Function(")?;
         writeln!(
@@ -33,16 +42,40 @@ impl fmt::Display for FunctionDefinition {
             "{}",
             indent(&format!("name=\"{}\",", self.name().value()), 4)
         )?;
+        writeln!(
+            f,
+            "{}",
+            indent(
+                &format!(
+                    "params=[{}],",
+                    self.params()
+                        .iter()
+                        .map(|p| p.value())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                4
+            )
+        )?;
+        writeln!(f, "{}", indent(&format!("type={},", self.fun_type()), 4))?;
+        if let Some(storage_class) = self.storage_class() {
+            writeln!(
+                f,
+                "{}",
+                indent(&format!("storage_class={storage_class:?},"), 4)
+            )?;
+        }
+        let Some(body) = self.body() else {
+            return write!(f, "{}", indent("body=None", 4));
+        };
         write!(
             f,
             "{}",
             indent(
                 &format!(
                     "body={}",
-                    self.body()
-                        .block_items()
-                        .clone()
-                        .into_iter()
+                    body.block_items()
+                        .iter()
                         .map(|s| s.to_string())
                         .collect::<Vec<_>>()
                         .join("\n")
@@ -57,7 +90,15 @@ impl fmt::Display for FunctionDefinition {
 impl fmt::Display for ForInit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ForInit::InitDecl(d) => write!(f, "InitDecl({d})"),
+            ForInit::InitDecl(decls) => write!(
+                f,
+                "InitDecl([{}])",
+                decls
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
             ForInit::InitExp(e) => match e.as_ref() {
                 Some(exp) => write!(f, "InitExp({exp})"),
                 None => write!(f, "InitExp()"),
@@ -83,6 +124,50 @@ impl fmt::Display for BlockItem {
 }
 
 impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Declaration::FunDecl(func) => write!(f, "FunDecl({func})"),
+            Declaration::VarDecl(var) => write!(f, "VarDecl({var})"),
+            Declaration::StructDecl(s) => write!(f, "StructDecl({s})"),
+            Declaration::EnumDecl(e) => write!(f, "EnumDecl({e})"),
+        }
+    }
+}
+
+impl fmt::Display for EnumDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tag=\"{}\", enumerators=[{}]",
+            self.tag().map_or("", |tag| tag.value()),
+            self.enumerators()
+                .iter()
+                .map(|(name, value)| match value {
+                    Some(value) => format!("{} = {value}", name.value()),
+                    None => name.value().to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl fmt::Display for StructDeclaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tag=\"{}\", members=[{}]",
+            self.tag().value(),
+            self.members()
+                .iter()
+                .map(|m| format!("{}: {}", m.name().value(), m.member_type()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl fmt::Display for VariableDeclaration {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Declaration(")?;
         writeln!(
@@ -90,9 +175,17 @@ impl fmt::Display for Declaration {
             "{}",
             indent(&format!("name=\"{}\",", self.name().value()), 4)
         )?;
+        writeln!(f, "{}", indent(&format!("type={},", self.var_type()), 4))?;
         if let Some(v) = self.initializer() {
             writeln!(f, "{}", indent(&format!("value=\"{}\",", v.clone()), 4))?;
         }
+        if let Some(storage_class) = self.storage_class() {
+            writeln!(
+                f,
+                "{}",
+                indent(&format!("storage_class={storage_class:?},"), 4)
+            )?;
+        }
         Ok(())
     }
 }
@@ -100,15 +193,15 @@ impl fmt::Display for Declaration {
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Statement::Return(expr) => {
+            Statement::Return(expr, _) => {
                 writeln!(f, "Return(")?;
                 write!(f, "{}\n)", indent(&expr.to_string(), 4))
             }
-            Statement::Expression(expr) => {
+            Statement::Expression(expr, _) => {
                 writeln!(f, "Expression(")?;
                 write!(f, "{}\n)", indent(&expr.to_string(), 4))
             }
-            Statement::If(cond, then, else_) => {
+            Statement::If(cond, then, else_, _) => {
                 writeln!(f, "If(")?;
                 if let Some(e) = else_ {
                     write!(
@@ -121,18 +214,26 @@ impl fmt::Display for Statement {
                 }
                 writeln!(f, ")")
             }
-            Statement::Compound(b) => write!(f, "Compound(\n{b}\n)"),
-            Statement::Break(id) => writeln!(f, "Break({id})"),
-            Statement::Continue(id) => writeln!(f, "Continue({id})"),
-            Statement::While(cond, body, id) => write!(f, "While({cond}, {body}, {id})"),
-            Statement::DoWhile(body, cond, id) => write!(f, "DoWhile({body}, {cond}, {id})"),
-            Statement::For(init, cond, post, body, id) => {
+            Statement::Compound(b, _) => write!(f, "Compound(\n{b}\n)"),
+            Statement::Break(id, _) => writeln!(f, "Break({id})"),
+            Statement::Continue(id, _) => writeln!(f, "Continue({id})"),
+            Statement::While(cond, body, id, _) => write!(f, "While({cond}, {body}, {id})"),
+            Statement::DoWhile(body, cond, id, _) => write!(f, "DoWhile({body}, {cond}, {id})"),
+            Statement::For(init, cond, post, body, id, _) => {
                 let cond_str = cond.as_ref().map_or("None".to_string(), |c| c.to_string());
                 let post_str = post.as_ref().map_or("None".to_string(), |p| p.to_string());
 
                 write!(f, "For({init}, {cond_str}, {post_str}, {body}, {id})")
             }
-            Statement::Null => writeln!(f, "Null"),
+            Statement::Switch(cond, body, id, _, _) => write!(f, "Switch({cond}, {body}, {id})"),
+            Statement::Case(value, st, id, _) => write!(f, "Case({value}, {st}, {id})"),
+            Statement::Default(st, id, _) => write!(f, "Default({st}, {id})"),
+            Statement::Goto(label, _) => writeln!(f, "Goto({label})"),
+            Statement::Labeled(label, st, _) => write!(f, "Labeled({label}, {st})"),
+            Statement::VaStart(ap, last, _) => writeln!(f, "VaStart({ap}, {last})"),
+            Statement::VaEnd(ap, _) => writeln!(f, "VaEnd({ap})"),
+            Statement::VaCopy(dst, src, _) => writeln!(f, "VaCopy({dst}, {src})"),
+            Statement::Null(_) => writeln!(f, "Null"),
         }
     }
 }
@@ -140,20 +241,123 @@ impl fmt::Display for Statement {
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expression::Constant(c) => write!(f, "Constant({c})"),
-            Expression::Unary(u, e) => write!(f, "Unary({u}, {e})"),
-            Expression::Binary(op, exp_1, exp_2) => {
+            Expression::Constant(c, _) => write!(f, "Constant({c})"),
+            Expression::Cast(ty, e, _) => write!(f, "Cast({ty}, {e})"),
+            Expression::Unary(u, e, _) => write!(f, "Unary({u}, {e})"),
+            Expression::Binary(op, exp_1, exp_2, _) => {
                 write!(f, "Binary({op}, {exp_1}, {exp_2})")
             }
-            Expression::Assignment(left, right) => write!(f, "Assignment({left}, {right})"),
-            Expression::Var(id) => write!(f, "Var({})", id.value()),
-            Expression::Conditional(cond, then, el) => {
+            Expression::Assignment(left, right, _) => write!(f, "Assignment({left}, {right})"),
+            Expression::CompoundAssignment(op, left, right, _) => {
+                write!(f, "CompoundAssignment({op}, {left}, {right})")
+            }
+            Expression::Postfix(op, e, _) => write!(f, "Postfix({op}, {e})"),
+            Expression::Var(id, _) => write!(f, "Var({})", id.value()),
+            Expression::Conditional(cond, then, el, _) => {
                 write!(f, "Conditional({cond}, {then}, {el})")
             }
+            Expression::Comma(left, right, _) => write!(f, "Comma({left}, {right})"),
+            Expression::FunctionCall(name, args, _) => write!(
+                f,
+                "FunctionCall({name}, [{}])",
+                args.iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expression::IndirectCall(callee, args, _) => write!(
+                f,
+                "IndirectCall({callee}, [{}])",
+                args.iter()
+                    .map(|a| a.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expression::Dereference(e, _) => write!(f, "Dereference({e})"),
+            Expression::AddressOf(e, _) => write!(f, "AddressOf({e})"),
+            Expression::Subscript(e, index, _) => write!(f, "Subscript({e}, {index})"),
+            Expression::String(s, _) => write!(f, "String({s:?})"),
+            Expression::Dot(e, member, _) => write!(f, "Dot({e}, {member})"),
+            Expression::Arrow(e, member, _) => write!(f, "Arrow({e}, {member})"),
+            Expression::VaArg(ap, ty, _) => write!(f, "VaArg({ap}, {ty})"),
+            Expression::Error(_) => write!(f, "Error"),
+        }
+    }
+}
+
+impl fmt::Display for Initializer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Initializer::Single(e) => write!(f, "{e}"),
+            Initializer::Compound(initializers) => write!(
+                f,
+                "{{{}}}",
+                initializers
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
     }
 }
 
+impl fmt::Display for Const {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Const::ConstInt(c) => write!(f, "{c}"),
+            Const::ConstLong(c) => write!(f, "{c}L"),
+            Const::ConstUInt(c) => write!(f, "{c}U"),
+            Const::ConstULong(c) => write!(f, "{c}UL"),
+            Const::ConstDouble(c) => write!(f, "{c:?}"),
+            Const::ConstChar(c) => write!(f, "{c}C"),
+            Const::ConstUChar(c) => write!(f, "{c}UC"),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Char => write!(f, "Char"),
+            Type::SChar => write!(f, "SChar"),
+            Type::UChar => write!(f, "UChar"),
+            Type::Int => write!(f, "Int"),
+            Type::Long => write!(f, "Long"),
+            Type::UInt => write!(f, "UInt"),
+            Type::ULong => write!(f, "ULong"),
+            Type::Double => write!(f, "Double"),
+            Type::Pointer(referenced) => write!(f, "Pointer({referenced})"),
+            Type::Array(element, count) => write!(f, "Array({element}, {count})"),
+            Type::FunType(params, ret, variadic) => write!(
+                f,
+                "FunType([{}{}], {ret})",
+                params
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                if *variadic { ", ..." } else { "" }
+            ),
+            Type::Structure(tag) => write!(f, "Structure({tag})"),
+            Type::Qualified(inner, qualifiers) => write!(f, "{qualifiers}{inner}"),
+        }
+    }
+}
+
+impl fmt::Display for Qualifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_const {
+            write!(f, "Const ")?;
+        }
+        if self.is_volatile {
+            write!(f, "Volatile ")?;
+        }
+
+        Ok(())
+    }
+}
+
 impl fmt::Display for UnaryOperator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -2,3 +2,5 @@ pub mod ast;
 pub mod display;
 pub mod parser;
 pub mod semantic;
+pub mod token_stream;
+pub mod typedef_table;
@@ -1,71 +1,459 @@
-use std::{iter::Peekable, slice::Iter};
+use std::num::IntErrorKind;
 
-use log::{debug, error, trace};
+use log::{debug, trace};
 
 use crate::{
-    c_ast::ast::{
-        BinaryOperator, Block, BlockItem, Declaration, Expression, ForInit, FunctionDefinition,
-        Identifier, Program, Statement, UnaryOperator,
+    c_ast::token_stream::TokenStream,
+    c_ast::{
+        ast::{
+            BinaryOperator, Block, BlockItem, Const, Declaration, EnumDeclaration, Expression,
+            ForInit, FunctionDeclaration, Identifier, Initializer, MemberDeclaration, Program,
+            Qualifiers, Statement, StorageClass, StructDeclaration, Type, UnaryOperator,
+            VariableDeclaration,
+        },
+        semantic::type_table::VA_LIST_TAG,
+        typedef_table::TypedefTable,
     },
-    common::util::opt_box,
-    lexer::{self, Token},
+    common::{
+        error::{CompileError, Construct, ParseError},
+        folder::FolderC,
+        span::Span,
+        util::opt_box,
+    },
+    lexer::{self, SpannedToken, Token},
 };
 
-type ParseResult<T> = Result<T, String>;
+type ParseResult<T> = Result<T, ParseError>;
 
 impl TryFrom<Vec<Token>> for Program {
-    type Error = String;
+    type Error = CompileError;
+
+    /// Parses tokens without spans, every node gets an unknown span.
+    fn try_from(tokens: Vec<Token>) -> Result<Self, CompileError> {
+        let tokens = tokens
+            .into_iter()
+            .map(|token| SpannedToken {
+                token,
+                span: Span::default(),
+            })
+            .collect::<Vec<_>>();
+
+        Program::try_from(tokens)
+    }
+}
+
+impl TryFrom<Vec<SpannedToken>> for Program {
+    type Error = CompileError;
 
-    fn try_from(tokens: Vec<Token>) -> ParseResult<Self> {
+    /// Parses a translation unit. An error points at the last token read, which is the
+    /// unexpected one when the parser stopped on it. The parser skips the declaration or the
+    /// block item where it found an error and goes on, so it fails with all of them.
+    fn try_from(tokens: Vec<SpannedToken>) -> Result<Self, CompileError> {
         trace!("[parser] <program>");
 
-        let mut tokens_iter = tokens.iter().peekable();
-        let function_definition = FunctionDefinition::parse_fd(&mut tokens_iter)?;
-        let program_ast = Program::new(function_definition);
+        let mut tokens_iter = TokenStream::new(&tokens);
+        let mut typedefs = TypedefTable::new();
+        let mut declarations = vec![];
+        while let Some(next_token) = tokens_iter.peek() {
+            let start = tokens_iter.offset();
+            let result = if Declaration::is_declaration(Some(next_token), &typedefs) {
+                Declaration::parse_decls(&mut tokens_iter, &mut typedefs)
+                    .map_err(|error| error.at(tokens_iter.last_span()))
+            } else {
+                let span = tokens_iter.peek_span();
+                debug!("[parser] {span}: expected a declaration, got {next_token:?}");
+
+                Err(ParseError::ExpectedDeclaration(next_token.clone()).at(span))
+            };
 
-        if tokens_iter.next().is_some() {
-            error!("[parser] unexpected tokens remaining");
+            match result {
+                Ok(decls) => declarations.extend(decls),
+                Err(error) => {
+                    tokens_iter.record(error);
+                    typedefs.exit_to(1);
+                    synchronize(&mut tokens_iter, start, &typedefs);
+                }
+            }
+        }
 
-            return Err(format!(
-                "unexpected tokens remaining: {:?}",
-                tokens_iter.collect::<Vec<_>>()
-            ));
+        let errors = tokens_iter.take_errors();
+        if let Some(error) = CompileError::from_errors(errors) {
+            return Err(error);
         }
 
-        Ok(program_ast)
+        Ok(Program::new(declarations))
     }
 }
 
-impl FunctionDefinition {
-    fn parse_fd(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+impl FunctionDeclaration {
+    /// Parses what follows the declarator of a function: its body, if it has one. The
+    /// parameters are in scope in the body, where they hide typedefs with the same name.
+    fn parse_fun_decl(
+        name: Identifier,
+        params: Vec<Identifier>,
+        fun_type: Type,
+        storage_class: Option<StorageClass>,
+        tokens: &mut TokenStream,
+        typedefs: &mut TypedefTable,
+    ) -> ParseResult<Self> {
         trace!("[parser] <function>");
 
-        token_assert(Token::Int, tokens)?;
-        let identifier = Identifier::parse_id(tokens)?;
+        debug!("[parser] function: {}", name.value());
 
-        debug!("[parser] function: {}", identifier.value());
+        let body = match tokens.peek() {
+            Some(Token::OpenBrace) => {
+                typedefs.enter_scope();
+                for param in &params {
+                    typedefs.declare_ordinary(param.value())?;
+                }
+                let body = Block::parse_block(tokens, typedefs)?;
+                typedefs.exit_scope();
+                Some(body)
+            }
+            _ => None,
+        };
 
-        token_assert(Token::OpenParen, tokens)?;
-        token_assert(Token::Void, tokens)?;
-        token_assert(Token::CloseParen, tokens)?;
-        let block = Block::parse_block(tokens)?;
+        Ok(FunctionDeclaration::new(
+            name,
+            params,
+            body,
+            fun_type,
+            storage_class,
+        ))
+    }
+}
+
+/// A declarator derives the type of a declaration from its base type, e.g. in `int **p`
+/// the declarator `**p` makes `p` a pointer to a pointer to an int.
+#[derive(Debug)]
+enum Declarator {
+    Ident(Identifier),
+    // a parameter may omit its name, e.g. `int (*)(int, int)`
+    Abstract,
+    // the qualifiers after the `*` apply to the pointer, e.g. `int *const p`
+    Pointer(Box<Declarator>, Qualifiers),
+    Array(Box<Declarator>, usize),
+    // Fun(params with their base type, variadic, declarator)
+    Fun(Vec<(Type, Declarator)>, bool, Box<Declarator>),
+}
+
+/// A declarator without an identifier, used in casts (e.g. `(int (*)[3]) p` or
+/// `(int (*)(int)) p`).
+#[derive(Debug)]
+enum AbstractDeclarator {
+    Pointer(Box<AbstractDeclarator>, Qualifiers),
+    Array(Box<AbstractDeclarator>, usize),
+    // Fun(params with their base type, variadic, declarator)
+    Fun(Vec<(Type, Declarator)>, bool, Box<AbstractDeclarator>),
+    Base,
+}
+
+/// Parses the qualifiers that follow the `*` of a pointer declarator, if any. A qualifier
+/// can be repeated, it's the same as writing it once.
+fn parse_qualifiers(tokens: &mut TokenStream) -> Qualifiers {
+    let mut qualifiers = Qualifiers::default();
+    while let Some(token @ (Token::Const | Token::Volatile)) = tokens.peek() {
+        qualifiers = qualifiers.union(match token {
+            Token::Const => Qualifiers::CONST,
+            _ => Qualifiers::VOLATILE,
+        });
+        let _ = tokens.next();
+    }
+
+    qualifiers
+}
+
+/// Parses the size of an array declarator (e.g. `[3]`), which must be a positive integer
+/// constant.
+fn parse_array_size(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<usize> {
+    token_assert(Token::OpenBracket, tokens)?;
+    let first = tokens.peek().cloned();
+    let size = Expression::parse_assignment_exp(tokens, typedefs)?;
+    let Some(size) = constant_value(size, typedefs) else {
+        debug!("[parser] invalid array size starting with {first:?}");
+
+        return Err(ParseError::ArraySizeNotConstant(first));
+    };
+    token_assert(Token::CloseBracket, tokens)?;
+
+    match usize::try_from(size.as_i64()) {
+        Ok(size) if size > 0 => Ok(size),
+        _ => {
+            debug!("[parser] invalid array size: {size:?}");
+
+            Err(ParseError::ArraySizeNotPositive(size.as_i64()))
+        }
+    }
+}
+
+/// Evaluates an integer constant expression while parsing, e.g. an array size, `None` if it
+/// isn't one. Its enumerators are replaced by their values. The operands don't have their
+/// common type yet, which only matters for values that don't fit an int.
+fn constant_value(expr: Expression, typedefs: &TypedefTable) -> Option<Const> {
+    EnumeratorValues(typedefs)
+        .fold_expr(expr)
+        .ok()?
+        .integer_constant()
+}
+
+/// Replaces the enumerators of an expression by their values.
+struct EnumeratorValues<'a>(&'a TypedefTable);
+
+impl FolderC for EnumeratorValues<'_> {
+    fn name(&self) -> &'static str {
+        "enumerator_values"
+    }
+
+    fn fold_expr(&mut self, expr: Expression) -> Result<Expression, CompileError> {
+        match expr {
+            Expression::Var(id, span) => match self.0.enumerator(id.value()) {
+                Some(value) => Ok(Expression::Constant(Const::ConstInt(value), span)),
+                None => Ok(Expression::Var(id, span)),
+            },
+            _ => self.default_fold_expr(expr),
+        }
+    }
+}
+
+impl Declarator {
+    fn parse_declarator(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<Self> {
+        trace!("[parser] <declarator>");
+
+        if let Some(Token::Multiply) = tokens.peek() {
+            token_assert(Token::Multiply, tokens)?;
+            let qualifiers = parse_qualifiers(tokens);
+            return Ok(Declarator::Pointer(
+                Box::new(Self::parse_declarator(tokens, typedefs)?),
+                qualifiers,
+            ));
+        }
+
+        let declarator = match tokens.peek() {
+            Some(Token::OpenParen) => {
+                token_assert(Token::OpenParen, tokens)?;
+                let declarator = Self::parse_declarator(tokens, typedefs)?;
+                token_assert(Token::CloseParen, tokens)?;
+                declarator
+            }
+            // only parameters can omit their name, the other declarations fail in `process`
+            Some(Token::CloseParen | Token::Comma | Token::OpenBracket) => Declarator::Abstract,
+            _ => Declarator::Ident(Identifier::parse_id(tokens)?),
+        };
+
+        if let Some(Token::OpenParen) = tokens.peek() {
+            token_assert(Token::OpenParen, tokens)?;
+            let (params, variadic) = Self::parse_params(tokens, typedefs)?;
+            token_assert(Token::CloseParen, tokens)?;
+            return Ok(Declarator::Fun(params, variadic, Box::new(declarator)));
+        }
+
+        let mut declarator = declarator;
+        while let Some(Token::OpenBracket) = tokens.peek() {
+            declarator =
+                Declarator::Array(Box::new(declarator), parse_array_size(tokens, typedefs)?);
+        }
+
+        Ok(declarator)
+    }
+
+    /// Parses a parameter list, which ends with `...` when the function is variadic (it
+    /// needs at least one parameter before it). Returns the parameters and whether the
+    /// function is variadic.
+    fn parse_params(
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+    ) -> ParseResult<(Vec<(Type, Self)>, bool)> {
+        trace!("[parser] <param_list>");
+
+        if let Some(Token::Void) = tokens.peek() {
+            token_assert(Token::Void, tokens)?;
+            return Ok((vec![], false));
+        }
+
+        let mut params = vec![];
+        loop {
+            let param_type = Type::parse_type(tokens, typedefs)?;
+            params.push((param_type, Self::parse_declarator(tokens, typedefs)?));
+
+            if let Some(Token::Comma) = tokens.peek() {
+                token_assert(Token::Comma, tokens)?;
+            } else {
+                break;
+            }
+
+            if let Some(Token::Ellipsis) = tokens.peek() {
+                token_assert(Token::Ellipsis, tokens)?;
+                return Ok((params, true));
+            }
+        }
+
+        Ok((params, false))
+    }
+
+    /// Applies the declarator to the base type. Returns the declared name, its type and,
+    /// for functions, the names of the parameters (`None` for the omitted ones).
+    fn process(self, base_type: Type) -> ParseResult<(Identifier, Type, Vec<Option<Identifier>>)> {
+        let (name, ty, params) = self.derive(base_type)?;
+        let Some(name) = name else {
+            debug!("[parser] declarator without an identifier: {ty:?}");
+
+            return Err(ParseError::Expected(Construct::Identifier, None));
+        };
+
+        Ok((name, ty, params))
+    }
+
+    /// Applies the declarator to the base type like `process`, but the name of the
+    /// declaration and of the parameters may be missing.
+    fn derive(
+        self,
+        base_type: Type,
+    ) -> ParseResult<(Option<Identifier>, Type, Vec<Option<Identifier>>)> {
+        match self {
+            Declarator::Ident(name) => Ok((Some(name), base_type, vec![])),
+            Declarator::Abstract => Ok((None, base_type, vec![])),
+            Declarator::Pointer(inner, qualifiers) => {
+                inner.derive(Type::Pointer(Box::new(base_type)).qualified(qualifiers))
+            }
+            Declarator::Array(inner, size) => inner.derive(Type::Array(Box::new(base_type), size)),
+            Declarator::Fun(params, variadic, inner) => {
+                let (fun_type, param_names) = Self::fun_type(params, variadic, base_type)?;
+                match *inner {
+                    Declarator::Ident(name) => Ok((Some(name), fun_type, param_names)),
+                    // the parameter names of a function pointer (e.g. `int (*fp)(int x)`)
+                    // aren't declared anywhere
+                    inner => inner.derive(fun_type),
+                }
+            }
+        }
+    }
+
+    /// Builds the type of a function from its parameters and return type, along with the
+    /// names of the parameters.
+    fn fun_type(
+        params: Vec<(Type, Declarator)>,
+        variadic: bool,
+        ret: Type,
+    ) -> ParseResult<(Type, Vec<Option<Identifier>>)> {
+        if let Type::FunType(..) = ret {
+            debug!("[parser] function returning a function: {ret:?}");
+
+            return Err(ParseError::FunctionReturningFunction);
+        }
+
+        let mut param_types = vec![];
+        let mut param_names = vec![];
+        for (param_base_type, param) in params {
+            let (param_name, param_type, _) = param.derive(param_base_type)?;
+            let param_type = match param_type {
+                // function and array parameters are adjusted to pointers
+                fun_type @ Type::FunType(..) => Type::Pointer(Box::new(fun_type)),
+                Type::Array(element, _) => Type::Pointer(element),
+                param_type => param_type,
+            };
+            param_types.push(param_type);
+            param_names.push(param_name);
+        }
 
-        Ok(FunctionDefinition::new(identifier, block))
+        Ok((
+            Type::FunType(param_types, Box::new(ret), variadic),
+            param_names,
+        ))
+    }
+}
+
+impl AbstractDeclarator {
+    fn parse_abstract_declarator(
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+    ) -> ParseResult<Self> {
+        trace!("[parser] <abstract_declarator>");
+
+        let mut lookahead = tokens.clone();
+        let mut declarator = match (lookahead.next(), lookahead.next()) {
+            (Some(Token::Multiply), _) => {
+                token_assert(Token::Multiply, tokens)?;
+                let qualifiers = parse_qualifiers(tokens);
+                return Ok(AbstractDeclarator::Pointer(
+                    Box::new(Self::parse_abstract_declarator(tokens, typedefs)?),
+                    qualifiers,
+                ));
+            }
+            // parentheses must wrap a non-empty declarator, `(int ())` isn't a valid cast
+            (
+                Some(Token::OpenParen),
+                Some(Token::Multiply | Token::OpenParen | Token::OpenBracket),
+            ) => {
+                token_assert(Token::OpenParen, tokens)?;
+                let declarator = Self::parse_abstract_declarator(tokens, typedefs)?;
+                token_assert(Token::CloseParen, tokens)?;
+                declarator
+            }
+            _ => AbstractDeclarator::Base,
+        };
+
+        if let Some(Token::OpenParen) = tokens.peek() {
+            token_assert(Token::OpenParen, tokens)?;
+            let (params, variadic) = Declarator::parse_params(tokens, typedefs)?;
+            token_assert(Token::CloseParen, tokens)?;
+            return Ok(AbstractDeclarator::Fun(
+                params,
+                variadic,
+                Box::new(declarator),
+            ));
+        }
+
+        while let Some(Token::OpenBracket) = tokens.peek() {
+            declarator = AbstractDeclarator::Array(
+                Box::new(declarator),
+                parse_array_size(tokens, typedefs)?,
+            );
+        }
+
+        Ok(declarator)
+    }
+
+    fn process(self, base_type: Type) -> ParseResult<Type> {
+        match self {
+            AbstractDeclarator::Base => Ok(base_type),
+            AbstractDeclarator::Pointer(inner, qualifiers) => {
+                inner.process(Type::Pointer(Box::new(base_type)).qualified(qualifiers))
+            }
+            AbstractDeclarator::Array(inner, size) => {
+                inner.process(Type::Array(Box::new(base_type), size))
+            }
+            AbstractDeclarator::Fun(params, variadic, inner) => {
+                let (fun_type, _) = Declarator::fun_type(params, variadic, base_type)?;
+                inner.process(fun_type)
+            }
+        }
     }
 }
 
 impl Block {
-    fn parse_block(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse_block(tokens: &mut TokenStream, typedefs: &mut TypedefTable) -> ParseResult<Self> {
         trace!("[parser] <block>");
 
         token_assert(Token::OpenBrace, tokens)?;
+        typedefs.enter_scope();
         let mut block_items = vec![];
         while let Some(next_token) = tokens.peek() {
-            if *next_token == &Token::CloseBrace {
+            if *next_token == Token::CloseBrace {
                 break;
             }
-            block_items.push(BlockItem::parse_bi(tokens)?);
+
+            let start = tokens.offset();
+            let depth = typedefs.depth();
+            match BlockItem::parse_bi(tokens, typedefs) {
+                Ok(items) => block_items.extend(items),
+                Err(error) => {
+                    tokens.record(error.at(tokens.last_span()));
+                    typedefs.exit_to(depth);
+                    synchronize(tokens, start, typedefs);
+                }
+            }
         }
+        typedefs.exit_scope();
         token_assert(Token::CloseBrace, tokens)?;
 
         debug!("[parser] block with {} items", block_items.len());
@@ -75,49 +463,622 @@ impl Block {
 }
 
 impl BlockItem {
-    fn parse_bi(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
-        let Some(next_token) = tokens.peek() else {
-            error!("[parser] expected <block_item>");
-
-            return Err("could not parse block item".to_string());
+    /// Parses a statement or a declaration. A declaration with several declarators becomes
+    /// one block item per declarator, in order, and a typedef doesn't become any.
+    fn parse_bi(tokens: &mut TokenStream, typedefs: &mut TypedefTable) -> ParseResult<Vec<Self>> {
+        // a label can have the name of a type
+        let mut lookahead = tokens.clone();
+        let (next_token, is_label) = match (lookahead.next(), lookahead.next()) {
+            (None, _) => {
+                debug!("[parser] expected <block_item>");
+
+                return Err(ParseError::Expected(Construct::BlockItem, None));
+            }
+            (Some(next_token), after) => (
+                next_token,
+                matches!(
+                    (next_token, after),
+                    (Token::Identifier(_), Some(Token::DoubleDot))
+                ),
+            ),
         };
 
-        match next_token {
-            Token::Int => Ok(BlockItem::D(Declaration::parse_decl(tokens)?)),
-            _ => Ok(BlockItem::S(Statement::parse_st(tokens)?)),
+        if !is_label && Declaration::is_declaration(Some(next_token), typedefs) {
+            return Ok(Declaration::parse_decls(tokens, typedefs)?
+                .into_iter()
+                .map(BlockItem::D)
+                .collect());
         }
+
+        Ok(vec![BlockItem::S(Statement::parse_st(tokens, typedefs)?)])
     }
 }
 
+/// The specifiers of a declaration, before any declarator is applied.
+struct Specifiers {
+    base_type: Type,
+    storage_class: Option<StorageClass>,
+    /// `typedef` counts as a storage class, the declarators name types instead of objects
+    is_typedef: bool,
+    /// A structure or an enumeration defined in the specifiers
+    definition: Option<Declaration>,
+}
+
 impl Declaration {
-    fn parse_decl(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    /// Parses a list of variable and function declarators that share the same specifiers
+    /// (e.g. `int a = 1, *p, f(void);`). A function definition must be the only declarator
+    /// of its declaration. A structure or an enumeration defined in the specifiers comes
+    /// first, and it can be declared without declarators (`enum e { A };`).
+    ///
+    /// The declarators of a `typedef` don't become declarations, their names are recorded as
+    /// type names in the current scope instead. Any other declarator hides a type name.
+    fn parse_decls(
+        tokens: &mut TokenStream,
+        typedefs: &mut TypedefTable,
+    ) -> ParseResult<Vec<Self>> {
         trace!("[parser] <declaration>");
 
-        token_assert(Token::Int, tokens)?;
+        // `struct tag;` declares the structure type without defining it
+        let mut lookahead = tokens.clone();
+        if let (Some(Token::Struct), Some(Token::Identifier(_)), Some(Token::Semicolon)) =
+            (lookahead.next(), lookahead.next(), lookahead.next())
+        {
+            let start = tokens.peek_span();
+            token_assert(Token::Struct, tokens)?;
+            let tag = Identifier::parse_id(tokens)?;
+            token_assert(Token::Semicolon, tokens)?;
+
+            return Ok(vec![Declaration::StructDecl(
+                StructDeclaration::new(tag, vec![]).with_span(tokens.span_from(&start)),
+            )]);
+        }
+
+        let specifiers = Type::parse_specifiers(tokens, typedefs)?;
+        let mut declarations = vec![];
+        if let Some(definition) = specifiers.definition {
+            if let Declaration::EnumDecl(e) = &definition {
+                // the values are known when they're constants, the type checker reports the
+                // others
+                let mut next = Some(0_i64);
+                for (name, value) in e.enumerators() {
+                    if let Some(value) = value {
+                        next = constant_value(value.clone(), typedefs).map(|c| c.as_i64());
+                    }
+                    let value = next.and_then(|n| i32::try_from(n).ok());
+                    typedefs.declare_enumerator(name.value(), value)?;
+                    next = value.map(|v| i64::from(v) + 1);
+                }
+            }
+            declarations.push(definition);
+            if let Some(Token::Semicolon) = tokens.peek() {
+                token_assert(Token::Semicolon, tokens)?;
+
+                return Ok(declarations);
+            }
+        }
+        let first_declarator = declarations.len();
+        loop {
+            // every declarator has its own span, from the declarator to the end of the
+            // initializer or of the function body
+            let start = tokens.peek_span();
+            let declarator = Declarator::parse_declarator(tokens, typedefs)?;
+            let (name, ty, params) = declarator.process(specifiers.base_type.clone())?;
+            if specifiers.is_typedef {
+                if let Some(Token::Assignment) = tokens.peek() {
+                    debug!("[parser] typedef initialized: {}", name.value());
+
+                    return Err(ParseError::TypedefInitialized(name.value().to_string()));
+                }
+                debug!("[parser] typedef: {} -> {ty:?}", name.value());
+
+                typedefs.declare_typedef(name.value(), ty)?;
+            } else if let Type::FunType(param_types, _, _) = &ty {
+                // a function type from a typedef has no parameter names
+                if param_types.len() != params.len() {
+                    debug!(
+                        "[parser] function declared with a typedef type: {}",
+                        name.value()
+                    );
+
+                    return Err(ParseError::TypedefFunction(name.value().to_string()));
+                }
+                // only a definition needs the names, a declaration without all of them
+                // keeps none
+                let params = match params.into_iter().collect::<Option<Vec<_>>>() {
+                    Some(params) => params,
+                    None if tokens.peek() == Some(&Token::OpenBrace) => {
+                        debug!("[parser] parameter name omitted in {}", name.value());
+
+                        return Err(ParseError::ParameterNameOmitted(name.value().to_string()));
+                    }
+                    None => vec![],
+                };
+                typedefs.declare_ordinary(name.value())?;
+                let function = FunctionDeclaration::parse_fun_decl(
+                    name,
+                    params,
+                    ty,
+                    specifiers.storage_class.clone(),
+                    tokens,
+                    typedefs,
+                )?
+                .with_span(tokens.span_from(&start));
+                if function.body().is_some() {
+                    if declarations.len() > first_declarator {
+                        debug!(
+                            "[parser] function definition in a declaration list: {}",
+                            function.name().value()
+                        );
+
+                        return Err(ParseError::FunctionDefinitionInList(
+                            function.name().value().to_string(),
+                        ));
+                    }
+
+                    declarations.push(Declaration::FunDecl(function));
+
+                    return Ok(declarations);
+                }
+                declarations.push(Declaration::FunDecl(function));
+            } else {
+                typedefs.declare_ordinary(name.value())?;
+                let variable = VariableDeclaration::parse_var_decl(
+                    name,
+                    ty,
+                    specifiers.storage_class.clone(),
+                    tokens,
+                    typedefs,
+                )?;
+                declarations.push(Declaration::VarDecl(
+                    variable.with_span(tokens.span_from(&start)),
+                ));
+            }
+
+            if let Some(Token::Comma) = tokens.peek() {
+                token_assert(Token::Comma, tokens)?;
+            } else {
+                break;
+            }
+        }
+        token_assert(Token::Semicolon, tokens)?;
+
+        Ok(declarations)
+    }
+}
+
+impl StructDeclaration {
+    /// Parses the members of a structure definition, between braces.
+    fn parse_struct_decl(
+        tag: Identifier,
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+    ) -> ParseResult<Self> {
+        trace!("[parser] <struct_declaration>");
+
+        token_assert(Token::OpenBrace, tokens)?;
+        if let Some(Token::CloseBrace) = tokens.peek() {
+            debug!("[parser] structure without members: {}", tag.value());
+
+            return Err(ParseError::EmptyStructure(tag.value().to_string()));
+        }
+        let mut members = vec![];
+        while tokens.peek() != Some(&Token::CloseBrace) {
+            members.push(MemberDeclaration::parse_member(tokens, typedefs)?);
+        }
+        token_assert(Token::CloseBrace, tokens)?;
+
+        debug!(
+            "[parser] structure: {} ({} members)",
+            tag.value(),
+            members.len()
+        );
+
+        Ok(StructDeclaration::new(tag, members))
+    }
+}
+
+impl MemberDeclaration {
+    fn parse_member(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<Self> {
+        trace!("[parser] <member_declaration>");
+
+        let specifiers = Type::parse_specifiers(tokens, typedefs)?;
+        if let Some(storage_class) = specifiers.storage_class {
+            debug!("[parser] {storage_class:?} structure member");
+
+            return Err(ParseError::StorageClassInMember);
+        }
+        if specifiers.is_typedef {
+            debug!("[parser] typedef in structure member");
+
+            return Err(ParseError::StorageClassInMember);
+        }
+        if specifiers.definition.is_some() {
+            debug!("[parser] type defined in a structure member");
+
+            return Err(ParseError::TypeDefinitionInMember);
+        }
+
+        let (name, member_type, _) =
+            Declarator::parse_declarator(tokens, typedefs)?.process(specifiers.base_type)?;
+        if let Type::FunType(..) = member_type {
+            debug!("[parser] function as structure member: {}", name.value());
+
+            return Err(ParseError::FunctionMember(name.value().to_string()));
+        }
+        token_assert(Token::Semicolon, tokens)?;
+
+        Ok(MemberDeclaration::new(name, member_type))
+    }
+}
+
+/// Parses `struct`, the tag that follows and an optional list of members, `struct` and the
+/// tag are kept as type specifiers. Returns the structure definition, if there's one.
+fn parse_struct_specifier<'a>(
+    tokens: &mut TokenStream<'a>,
+    typedefs: &TypedefTable,
+    type_specifiers: &mut Vec<&'a Token>,
+) -> ParseResult<Option<StructDeclaration>> {
+    let start = tokens.peek_span();
+    let (Some(struct_token @ Token::Struct), Some(tag @ Token::Identifier(name))) =
+        (tokens.next(), tokens.next())
+    else {
+        debug!("[parser] expected a tag after struct");
+
+        return Err(ParseError::ExpectedStructureTag);
+    };
+    type_specifiers.extend([struct_token, tag]);
+
+    let Some(Token::OpenBrace) = tokens.peek() else {
+        return Ok(None);
+    };
+
+    let struct_decl =
+        StructDeclaration::parse_struct_decl(Identifier::new(name.clone()), tokens, typedefs)?;
+
+    Ok(Some(struct_decl.with_span(tokens.span_from(&start))))
+}
+
+/// Parses `enum`, an optional tag and an optional list of enumerators, `enum` is kept as a
+/// type specifier. Returns the enumeration definition, if there's one.
+fn parse_enum_specifier<'a>(
+    tokens: &mut TokenStream<'a>,
+    typedefs: &TypedefTable,
+    type_specifiers: &mut Vec<&'a Token>,
+) -> ParseResult<Option<EnumDeclaration>> {
+    trace!("[parser] <enum_specifier>");
+
+    let start = tokens.peek_span();
+    if let Some(enum_token @ Token::Enum) = tokens.next() {
+        type_specifiers.push(enum_token);
+    }
+    let tag = match tokens.peek() {
+        Some(Token::Identifier(_)) => Some(Identifier::parse_id(tokens)?),
+        _ => None,
+    };
+    let Some(Token::OpenBrace) = tokens.peek() else {
+        if tag.is_none() {
+            debug!("[parser] expected a tag or enumerators after enum");
+
+            return Err(ParseError::ExpectedEnumerationTag);
+        }
+
+        return Ok(None);
+    };
+
+    token_assert(Token::OpenBrace, tokens)?;
+    let mut enumerators = vec![];
+    loop {
         let name = Identifier::parse_id(tokens)?;
+        let mut value = None;
+        if let Some(Token::Assignment) = tokens.peek() {
+            token_assert(Token::Assignment, tokens)?;
+            value = Some(Expression::parse_assignment_exp(tokens, typedefs)?);
+        }
+        enumerators.push((name, value));
+
+        if let Some(Token::CloseBrace) = tokens.peek() {
+            break;
+        }
+        token_assert(Token::Comma, tokens)?;
+        // a trailing comma is allowed before the closing brace
+        if let Some(Token::CloseBrace) = tokens.peek() {
+            break;
+        }
+    }
+    token_assert(Token::CloseBrace, tokens)?;
+
+    debug!("[parser] enumeration: {} enumerators", enumerators.len());
+
+    Ok(Some(
+        EnumDeclaration::new(tag, enumerators).with_span(tokens.span_from(&start)),
+    ))
+}
+
+impl Type {
+    /// Parses the specifiers of a declaration. Specifiers can come in any order, but they
+    /// must name a valid type and there can be at most one storage class. A typedef name
+    /// stands alone, and it's only a specifier if no other type specifier came before it
+    /// (in `T T;` the second `T` is the declarator). The `const` and `volatile` qualifiers
+    /// can be mixed with the specifiers and apply to the base type.
+    fn parse_specifiers(
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+    ) -> ParseResult<Specifiers> {
+        trace!("[parser] <specifiers>");
+
+        let mut type_specifiers = vec![];
+        let mut typedef_type = None;
+        let mut storage_classes = vec![];
+        let mut typedef_count = 0;
+        let mut definition = None;
+        let mut qualifiers = Qualifiers::default();
+        while let Some(token) = tokens.peek() {
+            match token {
+                Token::Int
+                | Token::Long
+                | Token::Double
+                | Token::Char
+                | Token::Signed
+                | Token::Unsigned
+                | Token::VaList => type_specifiers.push(token),
+                Token::Identifier(name) if type_specifiers.is_empty() && typedef_type.is_none() => {
+                    let Some(ty) = typedefs.get(name) else {
+                        break;
+                    };
+                    typedef_type = Some(ty.clone());
+                }
+                Token::Struct => {
+                    let struct_decl =
+                        parse_struct_specifier(tokens, typedefs, &mut type_specifiers)?;
+                    definition = definition.or(struct_decl.map(Declaration::StructDecl));
+                    continue;
+                }
+                Token::Enum => {
+                    let enum_decl = parse_enum_specifier(tokens, typedefs, &mut type_specifiers)?;
+                    definition = definition.or(enum_decl.map(Declaration::EnumDecl));
+                    continue;
+                }
+                Token::Static => storage_classes.push(StorageClass::Static),
+                Token::Extern => storage_classes.push(StorageClass::Extern),
+                Token::Typedef => typedef_count += 1,
+                Token::Const => qualifiers.is_const = true,
+                Token::Volatile => qualifiers.is_volatile = true,
+                _ => break,
+            }
+            let _ = tokens.next();
+        }
+
+        if storage_classes.len() + typedef_count > 1 {
+            debug!(
+                "[parser] more than one storage class: {storage_classes:?} ({typedef_count} typedef)"
+            );
+
+            return Err(ParseError::InvalidStorageClass);
+        }
+
+        let base_type = match typedef_type {
+            Some(ty) if type_specifiers.is_empty() => ty,
+            Some(ty) => {
+                debug!("[parser] typedef type {ty:?} combined with {type_specifiers:?}");
+
+                return Err(ParseError::InvalidTypeSpecifier(
+                    type_specifiers.into_iter().cloned().collect(),
+                ));
+            }
+            None => Self::from_specifiers(&type_specifiers)?,
+        };
+
+        Ok(Specifiers {
+            base_type: base_type.qualified(qualifiers),
+            storage_class: storage_classes.pop(),
+            is_typedef: typedef_count > 0,
+            definition,
+        })
+    }
+
+    /// Parses the type specifiers of a parameter or a type name, without declarators.
+    fn parse_type(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<Self> {
+        trace!("[parser] <type>");
+
+        let specifiers = Self::parse_specifiers(tokens, typedefs)?;
+        if specifiers.storage_class.is_some() || specifiers.is_typedef {
+            debug!("[parser] storage class in a type name");
+
+            return Err(ParseError::StorageClassInTypeName);
+        }
+        if specifiers.definition.is_some() {
+            debug!("[parser] type defined in a type name");
+
+            return Err(ParseError::TypeDefinitionInTypeName);
+        }
+
+        Ok(specifiers.base_type)
+    }
+
+    /// Type specifiers can come in any order, but each one at most once and `signed` and
+    /// `unsigned` can't be used together (e.g. `unsigned long int`, `long signed`).
+    /// `double` can't be combined with any other specifier, `char` only with `signed` or
+    /// `unsigned`. A structure type (`struct` and its tag) stands alone, and so does `enum`,
+    /// every enumeration type is an `int`, and `__builtin_va_list`, an array of one builtin
+    /// structure.
+    fn from_specifiers(type_specifiers: &[&Token]) -> ParseResult<Self> {
+        match type_specifiers {
+            [Token::VaList] => {
+                return Ok(Type::Array(
+                    Box::new(Type::Structure(VA_LIST_TAG.to_string())),
+                    1,
+                ));
+            }
+            [Token::Struct, Token::Identifier(tag)] => return Ok(Type::Structure(tag.clone())),
+            [Token::Enum] => return Ok(Type::Int),
+            [Token::Double] => return Ok(Type::Double),
+            [Token::Char] => return Ok(Type::Char),
+            [Token::Char, Token::Signed] | [Token::Signed, Token::Char] => return Ok(Type::SChar),
+            [Token::Char, Token::Unsigned] | [Token::Unsigned, Token::Char] => {
+                return Ok(Type::UChar);
+            }
+            _ => {}
+        }
+
+        let has = |specifier: Token| type_specifiers.contains(&&specifier);
+        let has_duplicates = type_specifiers
+            .iter()
+            .enumerate()
+            .any(|(i, specifier)| type_specifiers[..i].contains(specifier));
+
+        if type_specifiers.is_empty()
+            || has_duplicates
+            || has(Token::Double)
+            || has(Token::Char)
+            || has(Token::Struct)
+            || has(Token::Enum)
+            || has(Token::VaList)
+            || (has(Token::Signed) && has(Token::Unsigned))
+        {
+            debug!("[parser] invalid type specifiers: {type_specifiers:?}");
+
+            return Err(ParseError::InvalidTypeSpecifier(
+                type_specifiers.iter().map(|t| (*t).clone()).collect(),
+            ));
+        }
+
+        match (has(Token::Unsigned), has(Token::Long)) {
+            (true, true) => Ok(Type::ULong),
+            (true, false) => Ok(Type::UInt),
+            (false, true) => Ok(Type::Long),
+            (false, false) => Ok(Type::Int),
+        }
+    }
+
+    /// Whether the token is a type specifier (or a qualifier), including the typedef names
+    /// in scope.
+    fn is_type_specifier(token: Option<&Token>, typedefs: &TypedefTable) -> bool {
+        if let Some(Token::Identifier(name)) = token {
+            return typedefs.is_typedef(name);
+        }
+
+        matches!(
+            token,
+            Some(
+                Token::Int
+                    | Token::Long
+                    | Token::Double
+                    | Token::Char
+                    | Token::Signed
+                    | Token::Unsigned
+                    | Token::Struct
+                    | Token::Enum
+                    | Token::VaList
+                    | Token::Const
+                    | Token::Volatile
+            )
+        )
+    }
+}
+
+impl VariableDeclaration {
+    fn parse_var_decl(
+        name: Identifier,
+        var_type: Type,
+        storage_class: Option<StorageClass>,
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+    ) -> ParseResult<Self> {
+        trace!("[parser] <variable_declaration>");
+
         let mut initializer = None;
         if let Some(Token::Assignment) = tokens.peek() {
             token_assert(Token::Assignment, tokens)?;
-            initializer = Expression::parse_opt_exp(tokens, Token::Semicolon)?;
+            initializer = Some(Initializer::parse_initializer(tokens, typedefs)?);
         }
-        token_assert(Token::Semicolon, tokens)?;
 
         debug!("[parser] declaration: {}", name.value());
 
-        Ok(Declaration::new(name, initializer))
+        Ok(VariableDeclaration::new(
+            name,
+            initializer,
+            var_type,
+            storage_class,
+        ))
+    }
+}
+
+impl Initializer {
+    fn parse_initializer(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<Self> {
+        trace!("[parser] <initializer>");
+
+        let Some(Token::OpenBrace) = tokens.peek() else {
+            return Ok(Initializer::Single(Expression::parse_assignment_exp(
+                tokens, typedefs,
+            )?));
+        };
+
+        token_assert(Token::OpenBrace, tokens)?;
+        let mut initializers = vec![];
+        loop {
+            initializers.push(Self::parse_initializer(tokens, typedefs)?);
+
+            if let Some(Token::CloseBrace) = tokens.peek() {
+                break;
+            }
+            token_assert(Token::Comma, tokens)?;
+            // a trailing comma is allowed before the closing brace
+            if let Some(Token::CloseBrace) = tokens.peek() {
+                break;
+            }
+        }
+        token_assert(Token::CloseBrace, tokens)?;
+
+        Ok(Initializer::Compound(initializers))
     }
 }
 
 impl ForInit {
-    fn parse_for_init(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse_for_init(tokens: &mut TokenStream, typedefs: &mut TypedefTable) -> ParseResult<Self> {
         trace!("[parser] <for_init>");
 
-        if Declaration::is_declaration(tokens.peek()) {
-            return Ok(ForInit::InitDecl(Box::new(Declaration::parse_decl(
-                tokens,
-            )?)));
+        if Declaration::is_declaration(tokens.peek(), typedefs) {
+            let declarations = Declaration::parse_decls(tokens, typedefs)?;
+            // only a typedef declares nothing
+            if declarations.is_empty() {
+                debug!("[parser] typedef in for loop initializer");
+
+                return Err(ParseError::StorageClassInForInit);
+            }
+            let mut decls = vec![];
+            for decl in declarations {
+                let decl = match decl {
+                    Declaration::VarDecl(decl) => decl,
+                    Declaration::FunDecl(f) => {
+                        debug!("[parser] function declaration in for loop initializer");
+
+                        return Err(ParseError::FunctionInForInit(f.name().value().to_string()));
+                    }
+                    Declaration::StructDecl(s) => {
+                        debug!("[parser] structure declaration in for loop initializer");
+
+                        return Err(ParseError::StructureInForInit(s.tag().value().to_string()));
+                    }
+                    Declaration::EnumDecl(e) => {
+                        debug!("[parser] enumeration declaration in for loop initializer");
+
+                        return Err(ParseError::EnumerationInForInit(
+                            e.tag().map(|tag| tag.value().to_string()),
+                        ));
+                    }
+                };
+
+                if let Some(storage_class) = decl.storage_class() {
+                    debug!("[parser] {storage_class:?} declaration in for loop initializer");
+
+                    return Err(ParseError::StorageClassInForInit);
+                }
+                decls.push(decl);
+            }
+
+            return Ok(ForInit::InitDecl(decls));
         }
-        let opt_exp = Expression::parse_opt_exp(tokens, Token::Semicolon)?.map(|e| Box::new(e));
+        let opt_exp = Expression::parse_opt_exp(tokens, typedefs, Token::Semicolon)?.map(Box::new);
         token_assert(Token::Semicolon, tokens)?;
 
         Ok(ForInit::InitExp(opt_exp))
@@ -125,11 +1086,19 @@ impl ForInit {
 }
 
 impl Statement {
-    fn parse_st(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse_st(tokens: &mut TokenStream, typedefs: &mut TypedefTable) -> ParseResult<Self> {
+        // a label is an identifier followed by a colon, which needs a second token of lookahead
+        let mut lookahead = tokens.clone();
+        let is_label = matches!(
+            (lookahead.next(), lookahead.next()),
+            (Some(Token::Identifier(_)), Some(Token::DoubleDot))
+        );
+
+        let start = tokens.peek_span();
         let Some(next_token) = tokens.peek() else {
-            error!("[parser] expected <statement>");
+            debug!("[parser] {start}: expected <statement>");
 
-            return Err("could not parse statement".to_string());
+            return Err(ParseError::Expected(Construct::Statement, None));
         };
 
         let statement = match next_token {
@@ -137,7 +1106,7 @@ impl Statement {
                 trace!("[parser] <statement> null");
 
                 token_assert(Token::Semicolon, tokens)?;
-                Statement::Null
+                Statement::Null(tokens.span_from(&start))
             }
             Token::Return => {
                 trace!("[parser] <statement> return");
@@ -145,76 +1114,169 @@ impl Statement {
                 // start with a minimum precedence of zero so
                 // the result includes operators at every precedence level
                 token_assert(Token::Return, tokens)?;
-                let expr = Expression::parse_exp(tokens, Token::Semicolon)?;
+                let expr = Expression::parse_exp(tokens, typedefs, Token::Semicolon)?;
                 token_assert(Token::Semicolon, tokens)?;
-                Statement::Return(expr)
+                Statement::Return(expr, tokens.span_from(&start))
             }
             Token::If => {
                 trace!("[parser] <statement> if");
 
                 token_assert(Token::If, tokens)?;
                 token_assert(Token::OpenParen, tokens)?;
-                let expr = Expression::parse_exp(tokens, Token::CloseParen)?;
+                let expr = Expression::parse_exp(tokens, typedefs, Token::CloseParen)?;
                 token_assert(Token::CloseParen, tokens)?;
-                let then = Statement::parse_st(tokens)?;
+                let then = Statement::parse_st(tokens, typedefs)?;
                 let el = if let Some(Token::Else) = tokens.peek() {
                     debug!("[parser] found else branch");
 
                     token_assert(Token::Else, tokens)?;
-                    Some(Box::new(Statement::parse_st(tokens)?))
+                    Some(Box::new(Statement::parse_st(tokens, typedefs)?))
                 } else {
                     None
                 };
-                Statement::If(Box::new(expr), Box::new(then), el)
+                Statement::If(Box::new(expr), Box::new(then), el, tokens.span_from(&start))
             }
             Token::OpenBrace => {
                 // parsing compound statement
                 trace!("[parser] <statement> compound");
 
-                Statement::Compound(Box::new(Block::parse_block(tokens)?))
+                Statement::Compound(
+                    Box::new(Block::parse_block(tokens, typedefs)?),
+                    tokens.span_from(&start),
+                )
             }
             Token::Break => {
                 trace!("[parser] <statement> break");
 
                 token_assert(Token::Break, tokens)?;
                 token_assert(Token::Semicolon, tokens)?;
-                Statement::Break(Identifier::new("dummy".to_string()))
+                Statement::Break(
+                    Identifier::new("dummy".to_string()),
+                    tokens.span_from(&start),
+                )
             }
             Token::Continue => {
                 trace!("[parser] <statement> continue");
 
                 token_assert(Token::Continue, tokens)?;
                 token_assert(Token::Semicolon, tokens)?;
-                Statement::Continue(Identifier::new("dummy".to_string()))
+                Statement::Continue(
+                    Identifier::new("dummy".to_string()),
+                    tokens.span_from(&start),
+                )
+            }
+            Token::Switch => {
+                trace!("[parser] <statement> switch");
+
+                token_assert(Token::Switch, tokens)?;
+                token_assert(Token::OpenParen, tokens)?;
+                let cond = Expression::parse_exp(tokens, typedefs, Token::CloseParen)?;
+                token_assert(Token::CloseParen, tokens)?;
+                let body = Statement::parse_st(tokens, typedefs)?;
+                Statement::Switch(
+                    Box::new(cond),
+                    Box::new(body),
+                    Identifier::new("dummy".to_string()),
+                    vec![],
+                    tokens.span_from(&start),
+                )
+            }
+            Token::Case => {
+                trace!("[parser] <statement> case");
+
+                token_assert(Token::Case, tokens)?;
+                let value = Expression::parse_exp(tokens, typedefs, Token::DoubleDot)?;
+                token_assert(Token::DoubleDot, tokens)?;
+                let st = Statement::parse_st(tokens, typedefs)?;
+                Statement::Case(
+                    value,
+                    Box::new(st),
+                    Identifier::new("dummy".to_string()),
+                    tokens.span_from(&start),
+                )
+            }
+            Token::Default => {
+                trace!("[parser] <statement> default");
+
+                token_assert(Token::Default, tokens)?;
+                token_assert(Token::DoubleDot, tokens)?;
+                let st = Statement::parse_st(tokens, typedefs)?;
+                Statement::Default(
+                    Box::new(st),
+                    Identifier::new("dummy".to_string()),
+                    tokens.span_from(&start),
+                )
+            }
+            Token::Goto => {
+                trace!("[parser] <statement> goto");
+
+                token_assert(Token::Goto, tokens)?;
+                let label = Identifier::parse_id(tokens)?;
+                token_assert(Token::Semicolon, tokens)?;
+                Statement::Goto(label, tokens.span_from(&start))
+            }
+            Token::VaStart => {
+                trace!("[parser] <statement> va_start");
+
+                token_assert(Token::VaStart, tokens)?;
+                let (ap, last) = Self::parse_va_args(tokens, typedefs)?;
+                Statement::VaStart(ap, last, tokens.span_from(&start))
+            }
+            Token::VaEnd => {
+                trace!("[parser] <statement> va_end");
+
+                token_assert(Token::VaEnd, tokens)?;
+                token_assert(Token::OpenParen, tokens)?;
+                let ap = Expression::parse_assignment_exp(tokens, typedefs)?;
+                token_assert(Token::CloseParen, tokens)?;
+                token_assert(Token::Semicolon, tokens)?;
+                Statement::VaEnd(ap, tokens.span_from(&start))
+            }
+            Token::VaCopy => {
+                trace!("[parser] <statement> va_copy");
+
+                token_assert(Token::VaCopy, tokens)?;
+                let (dst, src) = Self::parse_va_args(tokens, typedefs)?;
+                Statement::VaCopy(dst, src, tokens.span_from(&start))
+            }
+            Token::Identifier(_) if is_label => {
+                trace!("[parser] <statement> labeled");
+
+                let label = Identifier::parse_id(tokens)?;
+                token_assert(Token::DoubleDot, tokens)?;
+                let st = Statement::parse_st(tokens, typedefs)?;
+                Statement::Labeled(label, Box::new(st), tokens.span_from(&start))
             }
             Token::While => {
                 trace!("[parser] <statement> while");
 
                 token_assert(Token::While, tokens)?;
                 token_assert(Token::OpenParen, tokens)?;
-                let cond = Expression::parse_exp(tokens, Token::CloseParen)?;
+                let cond = Expression::parse_exp(tokens, typedefs, Token::CloseParen)?;
                 token_assert(Token::CloseParen, tokens)?;
-                let body = Statement::parse_st(tokens)?;
+                let body = Statement::parse_st(tokens, typedefs)?;
                 Statement::While(
                     Box::new(cond),
                     Box::new(body),
                     Identifier::new("dummy".to_string()),
+                    tokens.span_from(&start),
                 )
             }
             Token::Do => {
                 trace!("[parser] <statement> do-while");
 
                 token_assert(Token::Do, tokens)?;
-                let body = Statement::parse_st(tokens)?;
+                let body = Statement::parse_st(tokens, typedefs)?;
                 token_assert(Token::While, tokens)?;
                 token_assert(Token::OpenParen, tokens)?;
-                let cond = Expression::parse_exp(tokens, Token::CloseParen)?;
+                let cond = Expression::parse_exp(tokens, typedefs, Token::CloseParen)?;
                 token_assert(Token::CloseParen, tokens)?;
                 token_assert(Token::Semicolon, tokens)?;
                 Statement::DoWhile(
                     Box::new(body),
                     Box::new(cond),
                     Identifier::new("dummy".to_string()),
+                    tokens.span_from(&start),
                 )
             }
             Token::For => {
@@ -222,65 +1284,104 @@ impl Statement {
 
                 token_assert(Token::For, tokens)?;
                 token_assert(Token::OpenParen, tokens)?;
-                let for_init = ForInit::parse_for_init(tokens)?;
-                let cond = Expression::parse_opt_exp(tokens, Token::Semicolon)?;
+                // the declarations of the initializer are in scope until the end of the body
+                typedefs.enter_scope();
+                let for_init = ForInit::parse_for_init(tokens, typedefs)?;
+                let cond = Expression::parse_opt_exp(tokens, typedefs, Token::Semicolon)?;
                 token_assert(Token::Semicolon, tokens)?;
-                let post = Expression::parse_opt_exp(tokens, Token::CloseParen)?;
+                let post = Expression::parse_opt_exp(tokens, typedefs, Token::CloseParen)?;
                 token_assert(Token::CloseParen, tokens)?;
-                let body = Statement::parse_st(tokens)?;
+                let body = Statement::parse_st(tokens, typedefs)?;
+                typedefs.exit_scope();
                 Statement::For(
                     Box::new(for_init),
                     opt_box(cond),
                     opt_box(post),
                     Box::new(body),
                     Identifier::new("dummy".to_string()),
+                    tokens.span_from(&start),
                 )
             }
             _ => {
                 trace!("[parser] <statement> expression");
 
-                let exp = Expression::parse_exp(tokens, Token::Semicolon)?;
+                let exp = Expression::parse_exp(tokens, typedefs, Token::Semicolon)?;
                 token_assert(Token::Semicolon, tokens)?;
-                Statement::Expression(exp)
+                Statement::Expression(exp, tokens.span_from(&start))
             }
         };
 
         Ok(statement)
     }
+
+    /// Parses the two arguments of `__builtin_va_start` or `__builtin_va_copy` and the
+    /// semicolon after them.
+    fn parse_va_args(
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+    ) -> ParseResult<(Expression, Expression)> {
+        token_assert(Token::OpenParen, tokens)?;
+        let first = Expression::parse_assignment_exp(tokens, typedefs)?;
+        token_assert(Token::Comma, tokens)?;
+        let second = Expression::parse_assignment_exp(tokens, typedefs)?;
+        token_assert(Token::CloseParen, tokens)?;
+        token_assert(Token::Semicolon, tokens)?;
+
+        Ok((first, second))
+    }
 }
 
 impl Expression {
-    pub fn parse_exp(tokens: &mut Peekable<Iter<Token>>, until: Token) -> ParseResult<Self> {
-        Self::parse_opt_exp(tokens, until)?.ok_or_else(|| {
-            error!("[parser] expected <exp>");
+    pub fn parse_exp(
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+        until: Token,
+    ) -> ParseResult<Self> {
+        Self::parse_opt_exp(tokens, typedefs, until)?.ok_or_else(|| {
+            debug!("[parser] expected <exp>");
 
-            "expected expression".to_string()
+            ParseError::Expected(Construct::Expression, tokens.peek().cloned())
         })
     }
 
     pub fn parse_opt_exp(
-        tokens: &mut Peekable<Iter<Token>>,
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
         until: Token,
     ) -> ParseResult<Option<Self>> {
         let Some(next_token) = tokens.peek() else {
-            error!("[parser] no tokens left for <exp>");
+            debug!("[parser] no tokens left for <exp>");
 
-            return Err("no token left to parse".to_string());
+            return Err(ParseError::UnexpectedEnd(None));
         };
-        if *next_token == &until {
+        if *next_token == until {
             return Ok(None);
         }
 
-        Ok(Some(Self::parse_exp_with_prec(tokens, 0)?))
+        Ok(Some(Self::parse_exp_with_prec(tokens, typedefs, 0)?))
+    }
+
+    /// Parses an expression without a top level comma operator, where a comma separates
+    /// arguments or initializers instead.
+    fn parse_assignment_exp(
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+    ) -> ParseResult<Self> {
+        Self::parse_exp_with_prec(tokens, typedefs, precedence(&Token::Assignment))
     }
 
-    fn parse_exp_with_prec(tokens: &mut Peekable<Iter<Token>>, min_prec: i32) -> ParseResult<Self> {
+    fn parse_exp_with_prec(
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+        min_prec: i32,
+    ) -> ParseResult<Self> {
         trace!("[parser] <exp> prec={min_prec}");
 
-        let mut left = Expression::parse_fact(tokens)?;
+        let start = tokens.peek_span();
+        let mut left = Expression::parse_fact(tokens, typedefs)?;
         let is_binary_op = |t: &Token| lexer::binary_operators().contains(t);
 
-        while let Some(token) = tokens.peek().copied() {
+        while let Some(token) = tokens.peek() {
             if !is_binary_op(token) || precedence(token) < min_prec {
                 break;
             }
@@ -289,20 +1390,67 @@ impl Expression {
                     trace!("[parser] <exp> assignment");
 
                     let _ = tokens.next(); // consume '='
-                    let right = Expression::parse_exp_with_prec(tokens, precedence(token))?;
-                    Expression::Assignment(Box::new(left), Box::new(right))
+                    let right =
+                        Expression::parse_exp_with_prec(tokens, typedefs, precedence(token))?;
+                    Expression::Assignment(
+                        Box::new(left),
+                        Box::new(right),
+                        tokens.span_from(&start),
+                    )
+                }
+                Token::AddAssign
+                | Token::SubtractAssign
+                | Token::MultiplyAssign
+                | Token::DivideAssign
+                | Token::RemainderAssign
+                | Token::BitwiseAndAssign
+                | Token::BitwiseOrAssign
+                | Token::BitwiseXorAssign
+                | Token::LeftShiftAssign
+                | Token::RightShiftAssign => {
+                    trace!("[parser] <exp> compound assignment");
+
+                    let op = BinaryOperator::parse_compound_assign(tokens)?;
+                    let right =
+                        Expression::parse_exp_with_prec(tokens, typedefs, precedence(token))?;
+                    Expression::CompoundAssignment(
+                        op,
+                        Box::new(left),
+                        Box::new(right),
+                        tokens.span_from(&start),
+                    )
+                }
+                Token::Comma => {
+                    trace!("[parser] <exp> comma");
+
+                    token_assert(Token::Comma, tokens)?;
+                    let right =
+                        Expression::parse_exp_with_prec(tokens, typedefs, precedence(token) + 1)?;
+                    Expression::Comma(Box::new(left), Box::new(right), tokens.span_from(&start))
                 }
                 Token::QuestionMark => {
                     trace!("[parser] <exp> ternary");
 
-                    let middle = Expression::parse_conditional_middle(tokens)?;
-                    let right = Expression::parse_exp_with_prec(tokens, precedence(token))?;
-                    Expression::Conditional(Box::new(left), Box::new(middle), Box::new(right))
+                    let middle = Expression::parse_conditional_middle(tokens, typedefs)?;
+                    let right =
+                        Expression::parse_exp_with_prec(tokens, typedefs, precedence(token))?;
+                    Expression::Conditional(
+                        Box::new(left),
+                        Box::new(middle),
+                        Box::new(right),
+                        tokens.span_from(&start),
+                    )
                 }
                 _ => {
                     let op = BinaryOperator::parse_bin(tokens)?;
-                    let right = Expression::parse_exp_with_prec(tokens, precedence(token) + 1)?;
-                    Expression::Binary(op, Box::new(left), Box::new(right))
+                    let right =
+                        Expression::parse_exp_with_prec(tokens, typedefs, precedence(token) + 1)?;
+                    Expression::Binary(
+                        op,
+                        Box::new(left),
+                        Box::new(right),
+                        tokens.span_from(&start),
+                    )
                 }
             };
         }
@@ -310,51 +1458,283 @@ impl Expression {
         Ok(left)
     }
 
-    fn parse_conditional_middle(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse_conditional_middle(
+        tokens: &mut TokenStream,
+        typedefs: &TypedefTable,
+    ) -> ParseResult<Self> {
         token_assert(Token::QuestionMark, tokens)?;
-        let middle = Expression::parse_exp(tokens, Token::DoubleDot)?;
+        let middle = Expression::parse_exp(tokens, typedefs, Token::DoubleDot)?;
         token_assert(Token::DoubleDot, tokens)?;
 
         Ok(middle)
     }
 
-    fn parse_fact(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse_args(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<Vec<Self>> {
+        trace!("[parser] <argument_list>");
+
+        token_assert(Token::OpenParen, tokens)?;
+        let mut args = vec![];
+        if let Some(Token::CloseParen) = tokens.peek() {
+            token_assert(Token::CloseParen, tokens)?;
+            return Ok(args);
+        }
+
+        loop {
+            args.push(Expression::parse_assignment_exp(tokens, typedefs)?);
+
+            if let Some(Token::Comma) = tokens.peek() {
+                token_assert(Token::Comma, tokens)?;
+            } else {
+                break;
+            }
+        }
+        token_assert(Token::CloseParen, tokens)?;
+
+        Ok(args)
+    }
+
+    fn parse_fact(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<Self> {
+        // a parenthesized type name starts a cast
+        let is_cast = Type::is_type_specifier(tokens.clone().nth(1), typedefs);
+        let start = tokens.peek_span();
         let Some(next_token) = tokens.peek() else {
-            error!("[parser] expected <factor>");
+            debug!("[parser] {start}: expected <factor>");
 
-            return Err("could not parse factor".to_string());
+            return Err(ParseError::Expected(Construct::Factor, None));
+        };
+
+        match next_token {
+            Token::Complement | Token::Negate | Token::Not => {
+                let unary = UnaryOperator::parse_un(tokens)?;
+                let exp = Expression::parse_fact(tokens, typedefs)?;
+
+                Ok(Expression::Unary(
+                    unary,
+                    Box::new(exp),
+                    tokens.span_from(&start),
+                ))
+            }
+            // `++x` and `--x` are the same as `x += 1` and `x -= 1`
+            Token::Increment | Token::Decrement => {
+                trace!("[parser] <exp> prefix increment/decrement");
+
+                let op = match tokens.next() {
+                    Some(Token::Increment) => BinaryOperator::Add,
+                    _ => BinaryOperator::Subtract,
+                };
+                let exp = Expression::parse_fact(tokens, typedefs)?;
+                let span = tokens.span_from(&start);
+
+                Ok(Expression::CompoundAssignment(
+                    op,
+                    Box::new(exp),
+                    Box::new(Expression::Constant(Const::ConstInt(1), span.clone())),
+                    span,
+                ))
+            }
+            Token::Multiply => {
+                trace!("[parser] <exp> dereference");
+
+                token_assert(Token::Multiply, tokens)?;
+                let exp = Expression::parse_fact(tokens, typedefs)?;
+
+                Ok(Expression::Dereference(
+                    Box::new(exp),
+                    tokens.span_from(&start),
+                ))
+            }
+            Token::BitwiseAnd => {
+                trace!("[parser] <exp> address of");
+
+                token_assert(Token::BitwiseAnd, tokens)?;
+                let exp = Expression::parse_fact(tokens, typedefs)?;
+
+                Ok(Expression::AddressOf(
+                    Box::new(exp),
+                    tokens.span_from(&start),
+                ))
+            }
+            Token::OpenParen if is_cast => {
+                trace!("[parser] <exp> cast");
+
+                token_assert(Token::OpenParen, tokens)?;
+                let base_type = Type::parse_type(tokens, typedefs)?;
+                let target_type = AbstractDeclarator::parse_abstract_declarator(tokens, typedefs)?
+                    .process(base_type)?;
+                token_assert(Token::CloseParen, tokens)?;
+                let exp = Expression::parse_fact(tokens, typedefs)?;
+
+                Ok(Expression::Cast(
+                    target_type,
+                    Box::new(exp),
+                    tokens.span_from(&start),
+                ))
+            }
+            _ => Expression::parse_postfix(tokens, typedefs),
+        }
+    }
+
+    /// Parses a primary expression followed by any number of subscripts, calls, member
+    /// accesses and postfix `++`/`--` (e.g. `a[1].x[2]`, `p->next->x`, `ops[i](x)` or `a[i]++`).
+    fn parse_postfix(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<Self> {
+        let start = tokens.peek_span();
+        let mut exp = Expression::parse_primary(tokens, typedefs)?;
+        loop {
+            exp = match tokens.peek() {
+                Some(Token::OpenBracket) => {
+                    trace!("[parser] <exp> subscript");
+
+                    token_assert(Token::OpenBracket, tokens)?;
+                    let index = Expression::parse_exp(tokens, typedefs, Token::CloseBracket)?;
+                    token_assert(Token::CloseBracket, tokens)?;
+                    Expression::Subscript(Box::new(exp), Box::new(index), tokens.span_from(&start))
+                }
+                // a call through an expression other than a function name, e.g. `(*fp)(x)`
+                Some(Token::OpenParen) => {
+                    trace!("[parser] <exp> indirect call");
+
+                    let args = Expression::parse_args(tokens, typedefs)?;
+                    Expression::IndirectCall(Box::new(exp), args, tokens.span_from(&start))
+                }
+                Some(Token::Dot) => {
+                    trace!("[parser] <exp> member access");
+
+                    token_assert(Token::Dot, tokens)?;
+                    let member = Identifier::parse_id(tokens)?;
+                    Expression::Dot(Box::new(exp), member, tokens.span_from(&start))
+                }
+                Some(Token::Arrow) => {
+                    trace!("[parser] <exp> pointer member access");
+
+                    token_assert(Token::Arrow, tokens)?;
+                    let member = Identifier::parse_id(tokens)?;
+                    Expression::Arrow(Box::new(exp), member, tokens.span_from(&start))
+                }
+                Some(Token::Increment) => {
+                    trace!("[parser] <exp> postfix increment");
+
+                    token_assert(Token::Increment, tokens)?;
+                    Expression::Postfix(
+                        BinaryOperator::Add,
+                        Box::new(exp),
+                        tokens.span_from(&start),
+                    )
+                }
+                Some(Token::Decrement) => {
+                    trace!("[parser] <exp> postfix decrement");
+
+                    token_assert(Token::Decrement, tokens)?;
+                    Expression::Postfix(
+                        BinaryOperator::Subtract,
+                        Box::new(exp),
+                        tokens.span_from(&start),
+                    )
+                }
+                _ => break,
+            };
+        }
+
+        Ok(exp)
+    }
+
+    fn parse_primary(tokens: &mut TokenStream, typedefs: &TypedefTable) -> ParseResult<Self> {
+        let start = tokens.peek_span();
+        let Some(next_token) = tokens.peek() else {
+            debug!("[parser] {start}: expected <primary>");
+
+            return Err(ParseError::Expected(Construct::Factor, None));
         };
 
         match next_token {
             Token::Constant(n) => {
                 let n = n.clone();
                 token_assert(Token::Constant(n.clone()), tokens)?;
-                n.parse::<i32>().map(Expression::Constant).map_err(|_| {
-                    error!("[parser] invalid constant: {n}");
-
-                    "could not parse constant".to_string()
-                })
+                Const::parse_int_const(&n, false, false)
+                    .map(|c| Expression::Constant(c, tokens.span_from(&start)))
             }
-            Token::Complement | Token::Negate | Token::Not => {
-                let unary = UnaryOperator::parse_un(tokens)?;
-                let exp = Expression::parse_fact(tokens)?;
+            Token::LongConstant(n) => {
+                let n = n.clone();
+                token_assert(Token::LongConstant(n.clone()), tokens)?;
+                Const::parse_int_const(&n, false, true)
+                    .map(|c| Expression::Constant(c, tokens.span_from(&start)))
+            }
+            Token::UnsignedConstant(n) => {
+                let n = n.clone();
+                token_assert(Token::UnsignedConstant(n.clone()), tokens)?;
+                Const::parse_int_const(&n, true, false)
+                    .map(|c| Expression::Constant(c, tokens.span_from(&start)))
+            }
+            Token::UnsignedLongConstant(n) => {
+                let n = n.clone();
+                token_assert(Token::UnsignedLongConstant(n.clone()), tokens)?;
+                Const::parse_int_const(&n, true, true)
+                    .map(|c| Expression::Constant(c, tokens.span_from(&start)))
+            }
+            Token::DoubleConstant(n) => {
+                let n = n.clone();
+                token_assert(Token::DoubleConstant(n.clone()), tokens)?;
+                Const::parse_double_const(&n)
+                    .map(|c| Expression::Constant(c, tokens.span_from(&start)))
+            }
+            Token::CharConstant(c) => {
+                let c = c.clone();
+                token_assert(Token::CharConstant(c.clone()), tokens)?;
+                Const::parse_char_const(&c)
+                    .map(|c| Expression::Constant(c, tokens.span_from(&start)))
+            }
+            // adjacent string literals are concatenated
+            Token::StringLiteral(_) => {
+                let mut value = String::new();
+                while let Some(Token::StringLiteral(s)) = tokens.peek() {
+                    value.push_str(&unescape(s)?);
+                    let _ = tokens.next();
+                }
 
-                Ok(Expression::Unary(unary, Box::new(exp)))
+                Ok(Expression::String(value, tokens.span_from(&start)))
             }
             Token::OpenParen => {
                 token_assert(Token::OpenParen, tokens)?;
-                let exp = Expression::parse_exp(tokens, Token::CloseParen)?;
+                let exp = Expression::parse_exp(tokens, typedefs, Token::CloseParen)?;
                 token_assert(Token::CloseParen, tokens)?;
 
                 Ok(exp)
             }
-            Token::Identifier(_) => Ok(Expression::Var(Identifier::parse_id(tokens)?)),
+            Token::VaArg => {
+                trace!("[parser] <exp> va_arg");
+
+                token_assert(Token::VaArg, tokens)?;
+                token_assert(Token::OpenParen, tokens)?;
+                let ap = Expression::parse_assignment_exp(tokens, typedefs)?;
+                token_assert(Token::Comma, tokens)?;
+                let base_type = Type::parse_type(tokens, typedefs)?;
+                let ty = AbstractDeclarator::parse_abstract_declarator(tokens, typedefs)?
+                    .process(base_type)?;
+                token_assert(Token::CloseParen, tokens)?;
+
+                Ok(Expression::VaArg(
+                    Box::new(ap),
+                    ty,
+                    tokens.span_from(&start),
+                ))
+            }
+            Token::Identifier(_) => {
+                let id = Identifier::parse_id(tokens)?;
+                if let Some(Token::OpenParen) = tokens.peek() {
+                    let args = Expression::parse_args(tokens, typedefs)?;
+                    return Ok(Expression::FunctionCall(id, args, tokens.span_from(&start)));
+                }
+
+                Ok(Expression::Var(id, tokens.span_from(&start)))
+            }
             _ => {
-                error!("[parser] unexpected token in <factor>: {next_token:?}");
+                debug!("[parser] {start}: unexpected token in <factor>: {next_token:?}");
 
                 let _ = tokens.next();
 
-                Err("could not parse expression".to_string())
+                Err(ParseError::Expected(
+                    Construct::Expression,
+                    Some(next_token.clone()),
+                ))
             }
         }
     }
@@ -380,17 +1760,185 @@ fn precedence(token: &Token) -> i32 {
         Token::And => 10,
         Token::Or => 5,
         Token::QuestionMark => 3,
-        Token::Assignment => 1,
-        _ => 0,
+        Token::Assignment
+        | Token::AddAssign
+        | Token::SubtractAssign
+        | Token::MultiplyAssign
+        | Token::DivideAssign
+        | Token::RemainderAssign
+        | Token::BitwiseAndAssign
+        | Token::BitwiseOrAssign
+        | Token::BitwiseXorAssign
+        | Token::LeftShiftAssign
+        | Token::RightShiftAssign => 1,
+        Token::Comma => 0,
+        _ => -1,
     }
 }
 
+impl Const {
+    /// Parses an integer constant, in decimal, octal (`017`), hexadecimal (`0x1F`) or
+    /// binary (`0b101`), without its suffix. Its type is the first one of this list that
+    /// can represent the value:
+    ///
+    /// - no suffix: `int`, `long` for decimal constants, `int`, `unsigned int`, `long`,
+    ///   `unsigned long` for the others
+    /// - `u`: `unsigned int`, `unsigned long`
+    /// - `l`: `long` for decimal constants, `long`, `unsigned long` for the others
+    /// - `ul`: `unsigned long`
+    fn parse_int_const(n: &str, is_unsigned: bool, is_long: bool) -> ParseResult<Self> {
+        let (digits, radix) = match n.as_bytes() {
+            [b'0', b'x' | b'X', ..] => (&n[2..], 16),
+            [b'0', b'b' | b'B', ..] => (&n[2..], 2),
+            [b'0', _, ..] => (&n[1..], 8),
+            _ => (n, 10),
+        };
+        let value = match u64::from_str_radix(digits, radix) {
+            Ok(value) => value,
+            Err(e) if *e.kind() == IntErrorKind::InvalidDigit => {
+                debug!("[parser] invalid digit in constant: {n}");
+
+                return Err(ParseError::InvalidConstant(n.to_string()));
+            }
+            Err(_) => {
+                debug!("[parser] constant is too large: {n}");
+
+                return Err(ParseError::InvalidConstant(n.to_string()));
+            }
+        };
+
+        let is_decimal = radix == 10;
+        if !is_unsigned
+            && !is_long
+            && let Ok(value) = i32::try_from(value)
+        {
+            return Ok(Const::ConstInt(value));
+        }
+        if (is_unsigned || !is_decimal)
+            && !is_long
+            && let Ok(value) = u32::try_from(value)
+        {
+            return Ok(Const::ConstUInt(value));
+        }
+        if !is_unsigned && let Ok(value) = i64::try_from(value) {
+            return Ok(Const::ConstLong(value));
+        }
+        if is_unsigned || !is_decimal {
+            return Ok(Const::ConstULong(value));
+        }
+
+        debug!("[parser] constant is too large for a long: {n}");
+
+        Err(ParseError::InvalidConstant(n.to_string()))
+    }
+
+    /// Parses a floating-point constant, rounded to the nearest double.
+    fn parse_double_const(n: &str) -> ParseResult<Self> {
+        let Ok(value) = n.parse::<f64>() else {
+            debug!("[parser] invalid floating-point constant: {n}");
+
+            return Err(ParseError::InvalidConstant(n.to_string()));
+        };
+
+        Ok(Const::ConstDouble(value))
+    }
+
+    /// Parses a character constant, which is an int holding the code of the character.
+    fn parse_char_const(c: &str) -> ParseResult<Self> {
+        let value = unescape(c)?;
+        let [byte] = value.as_bytes() else {
+            debug!("[parser] invalid character constant: '{c}'");
+
+            return Err(ParseError::InvalidCharacterConstant(c.to_string()));
+        };
+
+        Ok(Const::ConstInt(*byte as i32))
+    }
+}
+
+/// Replaces the escape sequences in the contents of a character constant or a string
+/// literal with the characters they stand for. Octal and hexadecimal escapes must be ASCII
+/// characters.
+pub fn unescape(s: &str) -> ParseResult<String> {
+    let mut value = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        let escaped = match chars.next() {
+            Some(c @ ('\'' | '"' | '?' | '\\')) => c,
+            Some('a') => '\x07',
+            Some('b') => '\x08',
+            Some('f') => '\x0c',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('v') => '\x0b',
+            Some(c @ '0'..='7') => {
+                let mut code = c.to_digit(8).unwrap_or_default();
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            code = code * 8 + digit;
+                            let _ = chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                match char::from_u32(code) {
+                    Some(c) if c.is_ascii() => c,
+                    _ => {
+                        debug!("[parser] octal escape sequence out of range: {code:o}");
+
+                        return Err(ParseError::UnsupportedEscape(format!("{code:o}")));
+                    }
+                }
+            }
+            Some('x') => {
+                let mut code = 0u32;
+                let mut digits = 0;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(16)) {
+                    code = code.saturating_mul(16).saturating_add(digit);
+                    digits += 1;
+                    let _ = chars.next();
+                }
+                match char::from_u32(code) {
+                    Some(c) if digits > 0 && c.is_ascii() => c,
+                    _ if digits == 0 => {
+                        debug!("[parser] \\x used with no following hex digits");
+
+                        return Err(ParseError::InvalidEscape("x".to_string()));
+                    }
+                    _ => {
+                        debug!("[parser] hex escape sequence out of range: {code:x}");
+
+                        return Err(ParseError::UnsupportedEscape(format!("x{code:x}")));
+                    }
+                }
+            }
+            c => {
+                debug!("[parser] invalid escape sequence: \\{c:?}");
+
+                return Err(ParseError::InvalidEscape(
+                    c.map(String::from).unwrap_or_default(),
+                ));
+            }
+        };
+        value.push(escaped);
+    }
+
+    Ok(value)
+}
+
 impl BinaryOperator {
-    fn parse_bin(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse_bin(tokens: &mut TokenStream) -> ParseResult<Self> {
         let Some(token) = tokens.next() else {
-            error!("[parser] expected <binop>");
+            debug!("[parser] expected <binop>");
 
-            return Err("could not parse binary operator".to_string());
+            return Err(ParseError::Expected(Construct::BinaryOperator, None));
         };
 
         match token {
@@ -413,20 +1961,53 @@ impl BinaryOperator {
             Token::GreaterThanOrEqual => Ok(BinaryOperator::GreaterThanOrEqual),
             Token::LessThanOrEqual => Ok(BinaryOperator::LessThanOrEqual),
             _ => {
-                error!("[parser] invalid <binop>: {token:?}");
+                debug!("[parser] invalid <binop>: {token:?}");
+
+                Err(ParseError::Expected(
+                    Construct::BinaryOperator,
+                    Some(token.clone()),
+                ))
+            }
+        }
+    }
+
+    /// Parses a compound assignment operator into the binary operator it applies.
+    fn parse_compound_assign(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let Some(token) = tokens.next() else {
+            debug!("[parser] expected <compound_assign>");
+
+            return Err(ParseError::Expected(Construct::CompoundAssignment, None));
+        };
+
+        match token {
+            Token::AddAssign => Ok(BinaryOperator::Add),
+            Token::SubtractAssign => Ok(BinaryOperator::Subtract),
+            Token::MultiplyAssign => Ok(BinaryOperator::Multiply),
+            Token::DivideAssign => Ok(BinaryOperator::Divide),
+            Token::RemainderAssign => Ok(BinaryOperator::Remainder),
+            Token::BitwiseAndAssign => Ok(BinaryOperator::BitwiseAnd),
+            Token::BitwiseOrAssign => Ok(BinaryOperator::BitwiseOr),
+            Token::BitwiseXorAssign => Ok(BinaryOperator::BitwiseXor),
+            Token::LeftShiftAssign => Ok(BinaryOperator::LeftShift),
+            Token::RightShiftAssign => Ok(BinaryOperator::RightShift),
+            _ => {
+                debug!("[parser] invalid <compound_assign>: {token:?}");
 
-                Err("could not parse binary operator".to_string())
+                Err(ParseError::Expected(
+                    Construct::CompoundAssignment,
+                    Some(token.clone()),
+                ))
             }
         }
     }
 }
 
 impl UnaryOperator {
-    fn parse_un(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
+    fn parse_un(tokens: &mut TokenStream) -> ParseResult<Self> {
         let Some(token) = tokens.next() else {
-            error!("[parser] expected <unop>");
+            debug!("[parser] expected <unop>");
 
-            return Err("could not parse unary operator".to_string());
+            return Err(ParseError::Expected(Construct::UnaryOperator, None));
         };
 
         match token {
@@ -434,37 +2015,126 @@ impl UnaryOperator {
             Token::Negate => Ok(UnaryOperator::Negate),
             Token::Not => Ok(UnaryOperator::Not),
             _ => {
-                error!("[parser] invalid <unop>: {token:?}");
+                debug!("[parser] invalid <unop>: {token:?}");
 
-                Err("could not parse unary operator".to_string())
+                Err(ParseError::Expected(
+                    Construct::UnaryOperator,
+                    Some(token.clone()),
+                ))
             }
         }
     }
 }
 
 impl Identifier {
-    fn parse_id(tokens: &mut Peekable<Iter<Token>>) -> ParseResult<Self> {
-        let Some(Token::Identifier(n)) = tokens.next() else {
-            error!("[parser] expected <identifier>");
+    fn parse_id(tokens: &mut TokenStream) -> ParseResult<Self> {
+        let token = tokens.next();
+        let Some(Token::Identifier(n)) = token else {
+            debug!("[parser] expected <identifier>");
 
-            return Err("could not parse identifier".to_string());
+            return Err(ParseError::Expected(Construct::Identifier, token.cloned()));
         };
 
         Ok(Identifier::new(n.clone()))
     }
 }
 
-fn token_assert(expected: Token, tokens: &mut Peekable<Iter<Token>>) -> Result<(), String> {
+/// Skips the rest of a declaration or a block item that failed to parse (panic mode), so the
+/// parser can go on with the next one. `start` is where it started: the braces and the
+/// parentheses opened since then are skipped up to where they're closed.
+///
+/// It stops after a `;` (but not the ones of a `for` header), and before a `}` closing the
+/// enclosing block, a statement keyword or the start of a declaration. The unexpected token
+/// may be one of them, e.g. the `}` found instead of the `;` of `return 1 }`.
+fn synchronize(tokens: &mut TokenStream, start: usize, typedefs: &TypedefTable) {
+    let end = tokens.offset();
+    tokens.rewind(start);
+
+    let mut braces = 0_usize;
+    let mut parens = 0_usize;
+    // the nesting of the parentheses of a `for` header, once it's open
+    let mut for_header = None;
+    let mut after_for = false;
+    while let Some(token) = tokens.peek() {
+        let position = tokens.offset();
+        // the tokens before the unexpected one were parsed, they're just skipped
+        if position > start && position + 1 >= end {
+            let outermost = braces == 0 && parens == 0;
+            match token {
+                Token::Semicolon if braces == 0 && for_header.is_none() => {
+                    tokens.next();
+                    break;
+                }
+                Token::CloseBrace if braces == 0 => break,
+                Token::If
+                | Token::Do
+                | Token::While
+                | Token::For
+                | Token::Return
+                | Token::Break
+                | Token::Continue
+                | Token::Goto
+                | Token::Switch
+                | Token::Case
+                | Token::Default
+                    if braces == 0 =>
+                {
+                    break;
+                }
+                _ if outermost && Declaration::is_declaration(Some(token), typedefs) => break,
+                _ => {}
+            }
+        }
+
+        match token {
+            Token::OpenBrace => braces += 1,
+            Token::CloseBrace => {
+                braces = braces.saturating_sub(1);
+                // a body closed at the outermost level ends the parentheses left open before
+                // it, e.g. by a missing `)` in a declarator
+                if braces == 0 {
+                    parens = 0;
+                    for_header = None;
+                }
+            }
+            Token::OpenParen | Token::OpenBracket => {
+                parens += 1;
+                if after_for && *token == Token::OpenParen {
+                    for_header = Some(parens);
+                }
+            }
+            Token::CloseParen | Token::CloseBracket => {
+                if for_header == Some(parens) {
+                    for_header = None;
+                }
+                parens = parens.saturating_sub(1);
+            }
+            _ => {}
+        }
+        after_for = *token == Token::For;
+        tokens.next();
+    }
+
+    debug!(
+        "[parser] skipped {} tokens after an error",
+        tokens.offset().saturating_sub(start)
+    );
+}
+
+fn token_assert(expected: Token, tokens: &mut TokenStream) -> ParseResult<()> {
     let Some(t) = tokens.next() else {
-        error!("[parser] unexpected end of tokens, expected {expected:?}");
+        debug!("[parser] unexpected end of tokens, expected {expected:?}");
 
-        return Err(String::from("empty tokens"));
+        return Err(ParseError::UnexpectedEnd(Some(expected)));
     };
 
     if *t != expected {
-        error!("[parser] expected {expected:?}, got {t:?}");
+        debug!("[parser] expected {expected:?}, got {t:?}");
 
-        return Err(format!("expected {expected:?}, got {t:?}"));
+        return Err(ParseError::UnexpectedToken {
+            expected,
+            found: Box::new(t.clone()),
+        });
     }
 
     Ok(())
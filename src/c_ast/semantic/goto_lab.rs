@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicUsize;
+
+use log::{debug, trace};
+
+use crate::{
+    c_ast::ast::{FunctionDeclaration, Identifier, Statement},
+    common::{
+        error::{CompileError, SemanticError},
+        folder::FolderC,
+        span::Span,
+        util::temporary_name,
+    },
+};
+
+static GOTO_LABEL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Gives every label a unique name and points `goto` statements at it.
+///
+/// Labels have function scope, so a `goto` can come before the statement it jumps to:
+/// the unique name is made on first sight and the label is only checked to be defined
+/// once the whole function body has been seen. An undefined label is reported where it's
+/// first used.
+///
+/// It goes on after an error: a label defined twice is dropped the second time.
+#[derive(Default)]
+pub struct GotoLabeler(HashMap<String, (Identifier, bool, Span)>, Vec<CompileError>);
+
+impl GotoLabeler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn unique_label(&mut self, label: &Identifier, span: &Span) -> Identifier {
+        self.0
+            .entry(label.value().to_string())
+            .or_insert_with(|| {
+                let unique = temporary_name(label.value(), &GOTO_LABEL_COUNT);
+                (Identifier::new(unique), false, span.clone())
+            })
+            .0
+            .clone()
+    }
+}
+
+impl FolderC for GotoLabeler {
+    fn name(&self) -> &'static str {
+        "goto_lab"
+    }
+
+    fn recover(&mut self, error: CompileError) -> Result<(), CompileError> {
+        self.1.push(error);
+        Ok(())
+    }
+
+    fn take_errors(&mut self) -> Vec<CompileError> {
+        std::mem::take(&mut self.1)
+    }
+
+    fn fold_fun_decl(
+        &mut self,
+        function: FunctionDeclaration,
+    ) -> Result<FunctionDeclaration, CompileError> {
+        trace!("[semantic] <function> {}", function.name().value());
+
+        self.0.clear();
+        let span = function.span().clone();
+        let (name, params, body, fun_type, storage_class) = function.into_parts();
+        let body = body.map(|b| self.fold_block(b)).transpose()?;
+
+        let mut undefined = self
+            .0
+            .iter()
+            .filter(|(_, (_, defined, _))| !defined)
+            .map(|(label, (_, _, first_use))| (label.clone(), first_use.clone()))
+            .collect::<Vec<_>>();
+        undefined.sort_by_key(|(_, first_use)| first_use.start);
+        for (label, first_use) in undefined {
+            debug!("[semantic] undefined label {label} in {}", name.value());
+
+            self.recover(SemanticError::UndefinedLabel(label).at(first_use))?;
+        }
+
+        Ok(FunctionDeclaration::new(name, params, body, fun_type, storage_class).with_span(span))
+    }
+
+    fn fold_st(&mut self, statement: Statement) -> Result<Statement, CompileError> {
+        let res = match statement {
+            Statement::Goto(label, span) => Statement::Goto(self.unique_label(&label, &span), span),
+            Statement::Labeled(label, st, span) => {
+                if let Some((_, true, previous)) = self.0.get(label.value()) {
+                    debug!("[semantic] duplicate label: {}", label.value());
+
+                    let error = SemanticError::DuplicateLabel(
+                        label.value().to_string(),
+                        Box::new(previous.clone()),
+                    );
+                    self.recover(error.at(span))?;
+                    return self.fold_st(*st);
+                }
+
+                let unique = self.unique_label(&label, &span);
+                self.0.insert(
+                    label.value().to_string(),
+                    (unique.clone(), true, span.clone()),
+                );
+                Statement::Labeled(unique, Box::new(self.fold_st(*st)?), span)
+            }
+            _ => self.default_fold_st(statement)?,
+        };
+        Ok(res)
+    }
+}
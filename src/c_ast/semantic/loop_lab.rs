@@ -1,13 +1,40 @@
+use std::collections::HashMap;
 use std::sync::atomic::AtomicUsize;
 
+use log::debug;
+
 use crate::{
-    c_ast::ast::{Identifier, Statement},
-    common::{folder::FolderC, util::temporary_name},
+    c_ast::ast::{Expression, Identifier, Statement, SwitchCase},
+    common::{
+        error::{CompileError, SemanticError},
+        folder::FolderC,
+        span::Span,
+        util::temporary_name,
+    },
 };
 
 static LOOP_LABEL_COUNT: AtomicUsize = AtomicUsize::new(0);
 
-pub struct LoopLabeler(Identifier);
+/// Labels loops and switch statements, and points `break` and `continue` at them.
+///
+/// `break` exits the innermost loop or switch, while `continue` always goes to the next
+/// iteration of the innermost loop, even from inside a switch. The cases of a switch are
+/// labeled and collected on the switch statement, so it can jump to them. A case belongs to
+/// the innermost switch even when it's nested in a loop inside it.
+///
+/// It goes on after an error: a `break` or a `continue` with nothing to go to becomes a null
+/// statement, and a misplaced case loses its label.
+pub struct LoopLabeler {
+    /// Label of the loop or switch `break` exits
+    break_label: Identifier,
+    /// Label of the loop `continue` goes back to
+    continue_label: Identifier,
+    /// Cases of the innermost switch, `None` outside of a switch
+    cases: Option<Vec<SwitchCase>>,
+    /// Where each case label was written, to point a duplicate back at the first one
+    case_spans: HashMap<String, Span>,
+    errors: Vec<CompileError>,
+}
 
 impl Default for LoopLabeler {
     fn default() -> Self {
@@ -17,11 +44,108 @@ impl Default for LoopLabeler {
 
 impl LoopLabeler {
     pub fn new(current_label: Identifier) -> Self {
-        Self(current_label)
+        Self {
+            break_label: current_label.clone(),
+            continue_label: current_label,
+            cases: None,
+            case_spans: HashMap::new(),
+            errors: vec![],
+        }
+    }
+
+    /// Labels a statement nested in this one with new `break` and `continue` targets. The
+    /// cases of the enclosing switch, where they were written and the errors are handed to
+    /// the nested labeler and taken back after.
+    fn fold_nested<T>(
+        &mut self,
+        break_label: Identifier,
+        continue_label: Identifier,
+        cases: Option<Vec<SwitchCase>>,
+        fold: impl FnOnce(&mut Self) -> Result<T, CompileError>,
+    ) -> Result<(T, Option<Vec<SwitchCase>>), CompileError> {
+        let mut nested = Self {
+            break_label,
+            continue_label,
+            cases,
+            case_spans: std::mem::take(&mut self.case_spans),
+            errors: std::mem::take(&mut self.errors),
+        };
+        let res = fold(&mut nested);
+        self.case_spans = nested.case_spans;
+        self.errors = nested.errors;
+        Ok((res?, nested.cases))
     }
 
-    fn _current_label(&self) -> &Identifier {
-        &self.0
+    /// Labels a loop, which keeps the cases of the enclosing switch.
+    fn fold_loop<T>(
+        &mut self,
+        label: &Identifier,
+        fold: impl FnOnce(&mut Self) -> Result<T, CompileError>,
+    ) -> Result<T, CompileError> {
+        let cases = self.cases.take();
+        let (res, cases) = self.fold_nested(label.clone(), label.clone(), cases, fold)?;
+        self.cases = cases;
+        Ok(res)
+    }
+
+    /// Records a case of the innermost switch, `None` for the default, and returns its label.
+    fn add_case(
+        &mut self,
+        value: Option<&Expression>,
+        span: &Span,
+    ) -> Result<Identifier, SemanticError> {
+        let Some(cases) = self.cases.as_mut() else {
+            debug!("[semantic] case or default outside of switch");
+
+            return Err(SemanticError::CaseOutsideSwitch);
+        };
+
+        let value = match value {
+            Some(Expression::Constant(c, _)) => Some(c.clone()),
+            // its error is already reported, the case can't be jumped to
+            Some(Expression::Error(_)) => {
+                return Ok(Identifier::new(temporary_name(
+                    "case_st",
+                    &LOOP_LABEL_COUNT,
+                )));
+            }
+            Some(_) => panic!("non-constant case, rejected by the type checker"),
+            None => None,
+        };
+        let bits = value.as_ref().map(|c| c.as_i64());
+        if let Some((_, first)) = cases
+            .iter()
+            .find(|(v, _)| v.as_ref().map(|c| c.as_i64()) == bits)
+        {
+            let previous = self
+                .case_spans
+                .get(first.value())
+                .cloned()
+                .unwrap_or_else(|| span.clone());
+            return Err(match bits {
+                Some(bits) => {
+                    debug!("[semantic] duplicate case value: {bits}");
+
+                    SemanticError::DuplicateCase(bits, Box::new(previous))
+                }
+                None => {
+                    debug!("[semantic] multiple default labels in one switch");
+
+                    SemanticError::MultipleDefaults(Box::new(previous))
+                }
+            });
+        }
+
+        let name = if value.is_some() {
+            "case_st"
+        } else {
+            "default_st"
+        };
+        let label = Identifier::new(temporary_name(name, &LOOP_LABEL_COUNT));
+        cases.push((value, label.clone()));
+        self.case_spans
+            .insert(label.value().to_string(), span.clone());
+        Ok(label)
     }
 }
 
@@ -30,58 +154,102 @@ impl FolderC for LoopLabeler {
         "loop_lab"
     }
 
-    fn fold_st(&mut self, statement: crate::c_ast::ast::Statement) -> Result<Statement, String> {
+    fn recover(&mut self, error: CompileError) -> Result<(), CompileError> {
+        self.errors.push(error);
+        Ok(())
+    }
+
+    fn take_errors(&mut self) -> Vec<CompileError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn fold_st(
+        &mut self,
+        statement: crate::c_ast::ast::Statement,
+    ) -> Result<Statement, CompileError> {
         let res = match statement {
-            Statement::Break(_) => {
-                if self._current_label().is_dummy_label() {
-                    return Err("statement outside of loop".to_string());
+            Statement::Break(_, span) => {
+                if self.break_label.is_dummy_label() {
+                    debug!("[semantic] break outside of a loop or switch");
+
+                    self.recover(SemanticError::BreakOutsideLoop.at(span.clone()))?;
+                    return Ok(Statement::Null(span));
                 }
-                Statement::Break(self._current_label().clone())
+                Statement::Break(self.break_label.clone(), span)
             }
 
-            Statement::Continue(_) => {
-                if self._current_label().is_dummy_label() {
-                    return Err("statement outside of loop".to_string());
+            Statement::Continue(_, span) => {
+                if self.continue_label.is_dummy_label() {
+                    debug!("[semantic] continue outside of a loop");
+
+                    self.recover(SemanticError::ContinueOutsideLoop.at(span.clone()))?;
+                    return Ok(Statement::Null(span));
                 }
-                Statement::Continue(self._current_label().clone())
+                Statement::Continue(self.continue_label.clone(), span)
             }
-            Statement::While(cond, body, _) => {
+            Statement::While(cond, body, _, span) => {
                 let label = _unique_loop_label();
-                let mut new_labeler = Self::new(label);
-                let lab_cond = new_labeler.fold_expr(*cond)?;
-                let lab_body = new_labeler.fold_st(*body)?;
-                Statement::While(
-                    Box::new(lab_cond),
-                    Box::new(lab_body),
-                    new_labeler._current_label().clone(),
-                )
+                let (lab_cond, lab_body) = self.fold_loop(&label, |labeler| {
+                    Ok((labeler.fold_expr(*cond)?, labeler.fold_st(*body)?))
+                })?;
+                Statement::While(Box::new(lab_cond), Box::new(lab_body), label, span)
             }
-            Statement::DoWhile(body, cond, _) => {
+            Statement::DoWhile(body, cond, _, span) => {
                 let label = _unique_loop_label();
-                let mut new_labeler = Self::new(label);
-                let lab_body = new_labeler.fold_st(*body)?;
-                let lab_cond = new_labeler.fold_expr(*cond)?;
-                Statement::DoWhile(
-                    Box::new(lab_body),
-                    Box::new(lab_cond),
-                    new_labeler._current_label().clone(),
-                )
+                let (lab_body, lab_cond) = self.fold_loop(&label, |labeler| {
+                    Ok((labeler.fold_st(*body)?, labeler.fold_expr(*cond)?))
+                })?;
+                Statement::DoWhile(Box::new(lab_body), Box::new(lab_cond), label, span)
             }
-            Statement::For(for_init, cond, post, body, _) => {
+            Statement::For(for_init, cond, post, body, _, span) => {
                 let label = _unique_loop_label();
-                let mut new_labeler = Self::new(label);
-                let lab_for_init = new_labeler.fold_for_init(*for_init)?;
-                let lab_cond = new_labeler.fold_opt_expr(cond)?;
-                let lab_post = new_labeler.fold_opt_expr(post)?;
-                let lab_body = new_labeler.fold_st(*body)?;
+                let (lab_for_init, lab_cond, lab_post, lab_body) =
+                    self.fold_loop(&label, |labeler| {
+                        Ok((
+                            labeler.fold_for_init(*for_init)?,
+                            labeler.fold_opt_expr(cond)?,
+                            labeler.fold_opt_expr(post)?,
+                            labeler.fold_st(*body)?,
+                        ))
+                    })?;
                 Statement::For(
                     Box::new(lab_for_init),
                     lab_cond,
                     lab_post,
                     Box::new(lab_body),
-                    new_labeler._current_label().clone(),
+                    label,
+                    span,
                 )
             }
+            Statement::Switch(cond, body, _, _, span) => {
+                let label = Identifier::new(temporary_name("switch_st", &LOOP_LABEL_COUNT));
+                let continue_label = self.continue_label.clone();
+                let ((lab_cond, lab_body), cases) =
+                    self.fold_nested(label.clone(), continue_label, Some(vec![]), |labeler| {
+                        Ok((labeler.fold_expr(*cond)?, labeler.fold_st(*body)?))
+                    })?;
+                Statement::Switch(
+                    Box::new(lab_cond),
+                    Box::new(lab_body),
+                    label,
+                    cases.unwrap_or_default(),
+                    span,
+                )
+            }
+            Statement::Case(value, st, _, span) => match self.add_case(Some(&value), &span) {
+                Ok(label) => Statement::Case(value, Box::new(self.fold_st(*st)?), label, span),
+                Err(error) => {
+                    self.recover(error.at(span))?;
+                    self.fold_st(*st)?
+                }
+            },
+            Statement::Default(st, _, span) => match self.add_case(None, &span) {
+                Ok(label) => Statement::Default(Box::new(self.fold_st(*st)?), label, span),
+                Err(error) => {
+                    self.recover(error.at(span))?;
+                    self.fold_st(*st)?
+                }
+            },
             _ => self.default_fold_st(statement)?,
         };
         Ok(res)
@@ -1,2 +1,5 @@
+pub mod goto_lab;
 pub mod loop_lab;
+pub mod type_check;
+pub mod type_table;
 pub mod var_res;
@@ -0,0 +1,1813 @@
+use std::collections::HashMap;
+
+use log::{debug, trace};
+
+use crate::{
+    c_ast::{
+        ast::{
+            BinaryOperator, Const, EnumDeclaration, Expression, FunctionDeclaration, Initializer,
+            Qualifiers, Statement, StorageClass, StructDeclaration, Type, UnaryOperator,
+            VariableDeclaration,
+        },
+        semantic::type_table::{StructEntry, VA_LIST_TAG},
+    },
+    common::{
+        error::{CompileError, SemanticError},
+        folder::FolderC,
+        span::Span,
+        symbols::{IdentifierAttrs, InitialValue, StaticInit, Symbol, SymbolTable},
+        util::source_name,
+    },
+};
+
+/// This pass checks that every identifier is used according to its type.
+///
+/// It runs after `VariableResolver`, so every local variable already has a unique name and
+/// the symbol table can be keyed by it. Function names are kept as they are because they have
+/// linkage, which means every declaration of the same function shares a single entry.
+///
+/// # Checks
+///
+/// - All the declarations of a function agree on its type
+/// - A function is defined at most once
+/// - Functions are called with the right number of arguments, directly or through a pointer
+///   to their type
+/// - Only functions and pointers to functions are called, functions are never assigned
+/// - All the declarations of an identifier agree on its linkage and type
+/// - Variables with static storage duration are initialized with constants
+/// - `~`, `%`, bitwise and shift operators are only applied to integers
+/// - Only lvalues are assigned to, incremented, decremented or have their address taken, only
+///   pointers are dereferenced
+/// - Pointers are only converted implicitly from pointers of the same type or null constants,
+///   the referenced type can gain `const` and `volatile` qualifiers but not lose them
+/// - Pointer arithmetic, subscripts and pointer comparisons only mix pointers with integers
+///   or with pointers of the same type (whatever their qualifiers), and never use pointers
+///   to functions
+/// - `const` lvalues, members of `const` structures included, and structures with a `const`
+///   member are never assigned, incremented or decremented
+/// - Arrays are only initialized with brace enclosed lists (or string literals for character
+///   arrays), no longer than the array
+/// - Structures are defined at most once per tag, with distinct members of complete types
+/// - Variables and function definitions only use structures once they are complete
+/// - `.` and `->` name a member of a complete structure, structures are only assigned,
+///   returned, passed or selected by `?:` with their own type, and never used as operands
+///   of arithmetic or as conditions
+/// - A switch is controlled by an integer and its cases are integer constant expressions
+/// - Enumerators are initialized with integer constant expressions that fit in an `int`
+/// - Variadic functions get at least as many arguments as they have parameters, the
+///   `__builtin_va_*` builtins are only applied to a `va_list`, `__builtin_va_start` only
+///   in a variadic function with its last parameter and `__builtin_va_arg` only reads
+///   scalars
+///
+/// # Conversions
+///
+/// Every implicit conversion is made explicit with a `Cast`: the operands of binary operators
+/// are converted to their common type, and assigned values, initializers, arguments and
+/// returned values are converted to the type they end up in, except the arguments matching
+/// the `...` of a variadic function which are only promoted. Character operands of `-`, `~`
+/// and shifts are promoted to `int`, and so is the controlling expression of a switch. After
+/// this pass the type of any expression can be computed with `Expression::ty`.
+///
+/// The right operand of a compound assignment is converted to the type the operation is done
+/// in, so only the conversion of the result back to the type of the lvalue is left implicit.
+///
+/// Case values are evaluated and converted to the type of their switch, so they're
+/// constants of that type after this pass.
+///
+/// Arrays decay to a pointer to their first element, which is made explicit with a `Cast`
+/// as well, unless they're the operand of `&`. Functions decay to a pointer to themselves,
+/// made explicit with `&`, and a call through a variable holding a function pointer becomes
+/// an `IndirectCall`. The integer in pointer arithmetic and
+/// subscripts is converted to a long, and compound initializers of arrays and structures are
+/// padded with zeros up to the length of the array or the last member.
+///
+/// `__builtin_va_copy` becomes an assignment of the structure behind the source `va_list` to
+/// the one behind the destination.
+///
+/// # Qualifiers
+///
+/// Declarations keep their qualified types, and so do the variables in the symbol table.
+/// `Expression::qualified_ty` is the type of the object an lvalue designates, with its
+/// qualifiers, while the values, casts included, are unqualified. Functions are recorded
+/// without the qualifiers of their parameters and return type, which don't change their
+/// type, but the parameters keep them inside the definition.
+///
+/// # Enumerations
+///
+/// Every enumerator is recorded with `EnumeratorAttr` and its value, and every use of it is
+/// replaced by an int constant, so later stages never see it. Enumeration types are `int`.
+///
+/// # Structures
+///
+/// The layout of every structure definition is recorded in the type table of the symbol
+/// table, keyed by the unique tag from `VariableResolver`.
+///
+/// # Static Variables
+///
+/// File scope variables and block scope `static`/`extern` variables are recorded with
+/// `StaticAttr`, which keeps the initial value the variable ends up with once every
+/// declaration in the translation unit has been seen. Later stages use it to emit the
+/// variable in the `.data` or `.bss` sections. String literals that initialize a static
+/// `char *` are recorded as constants with `ConstantAttr`, under a fresh name.
+///
+/// # Errors
+///
+/// The checker goes on after an error: an expression with an error becomes a placeholder, so
+/// the rest of its statement is still checked and labeled by the later passes, and any other
+/// block item with an error becomes a null statement. An expression with an error
+/// placeholder, from `VariableResolver` or from an operand, becomes a placeholder as a whole
+/// and is accepted wherever a value is, so its error isn't reported again.
+#[derive(Default)]
+pub struct TypeChecker {
+    symbols: SymbolTable,
+    /// Return type of the function being checked
+    return_type: Option<Type>,
+    /// Promoted type of the controlling expression of the innermost switch
+    switch_type: Option<Type>,
+    /// Last parameter of the function being checked, if it's variadic
+    last_param: Option<String>,
+    /// Where each function with a body was defined, to point a redefinition back at it
+    definitions: HashMap<String, Span>,
+    errors: Vec<CompileError>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Consumes the type checker and returns the symbol table.
+    pub fn into_symbols(self) -> SymbolTable {
+        self.symbols
+    }
+
+    /// Returns the initial value of a variable with static storage duration.
+    /// The constants are converted to the type of the variable at compile time. `span` is
+    /// the span of the declaration, which the errors about the initializer point at.
+    fn static_initial_value(
+        &mut self,
+        name: &str,
+        initializer: Option<&Initializer>,
+        var_type: &Type,
+        default: InitialValue,
+        span: &Span,
+    ) -> Result<InitialValue, CompileError> {
+        let Some(initializer) = initializer else {
+            return Ok(default);
+        };
+
+        let init = match (initializer, var_type.unqualified()) {
+            (
+                Initializer::Compound(_) | Initializer::Single(Expression::String(..)),
+                Type::Array(..),
+            )
+            | (Initializer::Compound(_), Type::Structure(_)) => {
+                let mut inits = vec![];
+                self.static_aggregate_init(name, initializer, var_type, &mut inits, span)?;
+                StaticInit::ArrayInit(inits)
+            }
+            _ => self.static_scalar_init(name, initializer, var_type, span)?,
+        };
+
+        Ok(InitialValue::Initial(init))
+    }
+
+    /// Appends the flattened initial values of the elements of an array or the members of a
+    /// structure to `inits`, missing elements and padding are zero. Character arrays can be
+    /// initialized with a string literal, which only keeps its null terminator if there's
+    /// room for it.
+    fn static_aggregate_init(
+        &mut self,
+        name: &str,
+        initializer: &Initializer,
+        var_type: &Type,
+        inits: &mut Vec<StaticInit>,
+        span: &Span,
+    ) -> Result<(), CompileError> {
+        match (initializer, var_type.unqualified()) {
+            (Initializer::Single(Expression::String(s, _)), Type::Array(element_type, size))
+                if element_type.is_character() =>
+            {
+                Self::check_initializer_length(name, s.len(), *size, span)?;
+                inits.push(StaticInit::StringInit(s.clone(), s.len() < *size));
+                if s.len() + 1 < *size {
+                    inits.push(StaticInit::ZeroInit(size - s.len() - 1));
+                }
+            }
+            (Initializer::Compound(initializers), Type::Array(element_type, size)) => {
+                Self::check_initializer_length(name, initializers.len(), *size, span)?;
+                for initializer in initializers {
+                    self.static_aggregate_init(name, initializer, element_type, inits, span)?;
+                }
+                if initializers.len() < *size {
+                    inits.push(StaticInit::ZeroInit(
+                        (size - initializers.len()) * element_type.size(self.symbols.types()),
+                    ));
+                }
+            }
+            (Initializer::Compound(initializers), Type::Structure(tag)) => {
+                let entry = self.struct_entry(tag, span)?.clone();
+                Self::check_member_count(name, initializers.len(), &entry, span)?;
+
+                let mut offset = 0;
+                for (initializer, member) in initializers.iter().zip(&entry.members) {
+                    if member.offset > offset {
+                        inits.push(StaticInit::ZeroInit(member.offset - offset));
+                    }
+                    self.static_aggregate_init(name, initializer, &member.ty, inits, span)?;
+                    offset = member.offset + member.ty.size(self.symbols.types());
+                }
+                if entry.size > offset {
+                    inits.push(StaticInit::ZeroInit(entry.size - offset));
+                }
+            }
+            _ => inits.push(self.static_scalar_init(name, initializer, var_type, span)?),
+        }
+
+        Ok(())
+    }
+
+    fn static_scalar_init(
+        &mut self,
+        name: &str,
+        initializer: &Initializer,
+        var_type: &Type,
+        span: &Span,
+    ) -> Result<StaticInit, CompileError> {
+        if let Initializer::Single(expr) = initializer
+            && expr.has_error()
+        {
+            return Ok(Self::static_init(&Const::zero(var_type), var_type));
+        }
+
+        // the address of a function is a constant as well
+        let var_type = var_type.unqualified();
+        if let (Initializer::Single(e), Type::Pointer(referenced)) = (initializer, var_type)
+            && let Some((function, fun_type)) = self.designated_function(e)
+        {
+            if fun_type != referenced.canonical() {
+                debug!("[semantic] {function} initializing {var_type:?}: {name}");
+
+                return Err(SemanticError::InvalidStaticPointerInitializer(
+                    source_name(name).to_string(),
+                )
+                .at(e.span().clone()));
+            }
+
+            return Ok(StaticInit::PointerInit(function.to_string()));
+        }
+
+        match (initializer, var_type) {
+            (_, Type::Array(..)) => {
+                debug!("[semantic] array initialized with a scalar: {name}");
+
+                Err(
+                    SemanticError::InvalidArrayInitializer(source_name(name).to_string())
+                        .at(span.clone()),
+                )
+            }
+            (_, Type::Structure(_)) => {
+                debug!("[semantic] static structure initialized with a scalar: {name}");
+
+                Err(
+                    SemanticError::InvalidStaticStructureInitializer(source_name(name).to_string())
+                        .at(span.clone()),
+                )
+            }
+            (Initializer::Compound(_), _) => {
+                debug!("[semantic] scalar initialized with a list: {name}");
+
+                Err(
+                    SemanticError::InvalidScalarInitializer(source_name(name).to_string())
+                        .at(span.clone()),
+                )
+            }
+            (Initializer::Single(Expression::String(s, _)), Type::Pointer(referenced))
+                if *referenced.unqualified() == Type::Char =>
+            {
+                Ok(StaticInit::PointerInit(self.symbols.add_string_constant(s)))
+            }
+            (Initializer::Single(Expression::String(_, span)), _) => {
+                debug!("[semantic] string literal initializing {var_type:?}: {name}");
+
+                Err(
+                    SemanticError::InvalidStringInitializer(source_name(name).to_string())
+                        .at(span.clone()),
+                )
+            }
+            (Initializer::Single(e), _) => {
+                // the value is folded like a case label, enumerators and negative values
+                // included
+                let span = e.span().clone();
+                let value = self.fold_expr(e.clone())?;
+                // its error is already reported
+                if value.has_error() {
+                    return Ok(Self::static_init(&Const::zero(var_type), var_type));
+                }
+                let Some(c) = value.arithmetic_constant() else {
+                    debug!("[semantic] non-constant initializer: {name}");
+
+                    return Err(SemanticError::NonConstantStaticInitializer(
+                        source_name(name).to_string(),
+                    )
+                    .at(span));
+                };
+                if var_type.is_pointer() && (c.ty() == Type::Double || c.as_i64() != 0) {
+                    debug!("[semantic] invalid pointer initializer: {name}");
+
+                    return Err(SemanticError::InvalidStaticPointerInitializer(
+                        source_name(name).to_string(),
+                    )
+                    .at(span));
+                }
+
+                Ok(Self::static_init(&c, var_type))
+            }
+        }
+    }
+
+    /// Returns the name and type of the function an expression designates (`f` or `&f`),
+    /// `None` if it doesn't designate one.
+    fn designated_function<'a>(&self, expr: &'a Expression) -> Option<(&'a str, Type)> {
+        let id = match expr {
+            Expression::AddressOf(inner, _) => match &**inner {
+                Expression::Var(id, _) => id,
+                _ => return None,
+            },
+            Expression::Var(id, _) => id,
+            _ => return None,
+        };
+
+        match self.symbols.get(id.value()) {
+            Some(Symbol {
+                ty: fun_type @ Type::FunType(..),
+                ..
+            }) => Some((id.value(), fun_type.clone())),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an enumerator, `None` if the name isn't one.
+    fn enumerator_value(&self, name: &str) -> Option<i32> {
+        match self.symbols.get(name) {
+            Some(Symbol {
+                attrs: IdentifierAttrs::EnumeratorAttr(value),
+                ..
+            }) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn check_initializer_length(
+        name: &str,
+        length: usize,
+        size: usize,
+        span: &Span,
+    ) -> Result<(), CompileError> {
+        if length > size {
+            debug!("[semantic] {length} initializers for an array of {size}: {name}");
+
+            return Err(
+                SemanticError::TooManyArrayElements(source_name(name).to_string()).at(span.clone()),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn check_member_count(
+        name: &str,
+        length: usize,
+        entry: &StructEntry,
+        span: &Span,
+    ) -> Result<(), CompileError> {
+        if length > entry.members.len() {
+            debug!(
+                "[semantic] {length} initializers for a structure of {} members: {name}",
+                entry.members.len()
+            );
+
+            return Err(
+                SemanticError::TooManyStructureElements(source_name(name).to_string())
+                    .at(span.clone()),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the layout of a structure, which must be complete. `span` is where it's
+    /// needed.
+    fn struct_entry(&self, tag: &str, span: &Span) -> Result<&StructEntry, CompileError> {
+        self.symbols.types().get(tag).ok_or_else(|| {
+            debug!("[semantic] incomplete structure type: {tag}");
+
+            SemanticError::IncompleteStructure(source_name(tag).to_string()).at(span.clone())
+        })
+    }
+
+    /// Whether the size of a type is known, i.e. it's not a structure (or an array of
+    /// structures) that has only been declared.
+    fn is_complete(&self, ty: &Type) -> bool {
+        match ty.unqualified() {
+            Type::Structure(tag) => self.symbols.types().contains(tag),
+            Type::Array(element_type, _) => self.is_complete(element_type),
+            _ => true,
+        }
+    }
+
+    fn check_complete(&self, name: &str, ty: &Type, span: &Span) -> Result<(), CompileError> {
+        if !self.is_complete(ty) {
+            debug!("[semantic] {name} has incomplete type {ty:?}");
+
+            return Err(
+                SemanticError::IncompleteType(source_name(name).to_string()).at(span.clone())
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a value can be used as a condition, which compares it against zero.
+    fn check_scalar(&self, expr: &Expression) -> Result<(), CompileError> {
+        if !expr.ty(&self.symbols).is_scalar() {
+            debug!("[semantic] non-scalar condition: {expr:?}");
+
+            return Err(SemanticError::InvalidStructureOperand.at(expr.span().clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Checks a `va_list` operand of a builtin, which decays to a pointer to its structure.
+    fn check_va_list(&mut self, ap: Expression) -> Result<Expression, CompileError> {
+        let ap = self.fold_expr(ap)?;
+        if let Expression::Error(_) = ap {
+            return Ok(ap);
+        }
+
+        match ap.ty(&self.symbols) {
+            Type::Pointer(referenced)
+                if *referenced.unqualified() == Type::Structure(VA_LIST_TAG.to_string()) =>
+            {
+                Ok(ap)
+            }
+            ty => {
+                debug!("[semantic] {ap:?} of type {ty:?} used as a va_list");
+
+                Err(SemanticError::NotAVaList.at(ap.span().clone()))
+            }
+        }
+    }
+
+    /// Checks that a structure type is complete and has a member with the given name. `span`
+    /// is the span of the member access.
+    fn check_member(&self, ty: &Type, member: &str, span: &Span) -> Result<(), CompileError> {
+        let Type::Structure(tag) = ty.unqualified() else {
+            debug!("[semantic] member {member} of a non-structure {ty:?}");
+
+            return Err(SemanticError::MemberOfNonStructure(member.to_string()).at(span.clone()));
+        };
+
+        if self.struct_entry(tag, span)?.member(member).is_none() {
+            debug!("[semantic] structure {tag} has no member {member}");
+
+            return Err(SemanticError::NoSuchMember {
+                tag: source_name(tag).to_string(),
+                member: member.to_string(),
+            }
+            .at(span.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn static_init(constant: &Const, var_type: &Type) -> StaticInit {
+        let var_type = var_type.unqualified();
+        match (constant, var_type) {
+            (Const::ConstDouble(c), Type::Double) => StaticInit::DoubleInit(*c),
+            (Const::ConstULong(c), Type::Double) => StaticInit::DoubleInit(*c as f64),
+            (_, Type::Double) => StaticInit::DoubleInit(constant.as_i64() as f64),
+            // doubles larger than a long are still valid unsigned longs
+            (Const::ConstDouble(c), Type::ULong) => StaticInit::ULongInit(*c as u64),
+            _ => {
+                // conversions between integer types keep the low bits, like `as` does
+                let value = constant.as_i64();
+                match var_type {
+                    Type::Char | Type::SChar => StaticInit::CharInit(value as i8),
+                    Type::UChar => StaticInit::UCharInit(value as u8),
+                    Type::Long => StaticInit::LongInit(value),
+                    Type::UInt => StaticInit::UIntInit(value as u32),
+                    Type::ULong | Type::Pointer(_) => StaticInit::ULongInit(value as u64),
+                    _ => StaticInit::IntInit(value as i32),
+                }
+            }
+        }
+    }
+
+    /// Checks the initializer of an automatic variable and converts its values to the types
+    /// of the objects they initialize. `span` is the span of the declaration.
+    fn check_initializer(
+        &mut self,
+        name: &str,
+        initializer: Initializer,
+        var_type: &Type,
+        span: &Span,
+    ) -> Result<Initializer, CompileError> {
+        match (initializer, var_type.unqualified()) {
+            (
+                Initializer::Single(Expression::String(s, string_span)),
+                Type::Array(element_type, size),
+            ) if element_type.is_character() => {
+                // copied into the array as is, see `static_aggregate_init` for the terminator
+                Self::check_initializer_length(name, s.len(), *size, span)?;
+
+                Ok(Initializer::Single(Expression::String(s, string_span)))
+            }
+            (Initializer::Single(e), Type::Array(..)) => {
+                debug!("[semantic] array initialized with a scalar: {name}");
+
+                Err(
+                    SemanticError::InvalidArrayInitializer(source_name(name).to_string())
+                        .at(e.span().clone()),
+                )
+            }
+            (Initializer::Single(e), _) => {
+                let e = self.fold_expr(e)?;
+                Ok(Initializer::Single(
+                    self.convert_by_assignment(e, var_type)?,
+                ))
+            }
+            (Initializer::Compound(initializers), Type::Array(element_type, size)) => {
+                Self::check_initializer_length(name, initializers.len(), *size, span)?;
+                let mut checked = initializers
+                    .into_iter()
+                    .map(|i| self.check_initializer(name, i, element_type, span))
+                    .collect::<Result<Vec<_>, CompileError>>()?;
+                while checked.len() < *size {
+                    checked.push(self.zero_initializer(element_type));
+                }
+
+                Ok(Initializer::Compound(checked))
+            }
+            (Initializer::Compound(initializers), Type::Structure(tag)) => {
+                let entry = self.struct_entry(tag, span)?.clone();
+                Self::check_member_count(name, initializers.len(), &entry, span)?;
+
+                let mut checked = initializers
+                    .into_iter()
+                    .zip(&entry.members)
+                    .map(|(i, member)| self.check_initializer(name, i, &member.ty, span))
+                    .collect::<Result<Vec<_>, CompileError>>()?;
+                for member in entry.members.iter().skip(checked.len()) {
+                    checked.push(self.zero_initializer(&member.ty));
+                }
+
+                Ok(Initializer::Compound(checked))
+            }
+            (Initializer::Compound(_), _) => {
+                debug!("[semantic] scalar initialized with a list: {name}");
+
+                Err(
+                    SemanticError::InvalidScalarInitializer(source_name(name).to_string())
+                        .at(span.clone()),
+                )
+            }
+        }
+    }
+
+    fn zero_initializer(&self, ty: &Type) -> Initializer {
+        match ty.unqualified() {
+            Type::Array(element_type, size) => {
+                Initializer::Compound(vec![self.zero_initializer(element_type); *size])
+            }
+            Type::Structure(tag) => Initializer::Compound(
+                self.symbols
+                    .types()
+                    .get(tag)
+                    .map(|entry| {
+                        entry
+                            .members
+                            .iter()
+                            .map(|member| self.zero_initializer(&member.ty))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            ),
+            _ => Initializer::Single(
+                self.convert_to(Expression::Constant(Const::zero(ty), Span::default()), ty),
+            ),
+        }
+    }
+
+    /// Wraps the expression in a cast, unless it already has the target type. Values are
+    /// never qualified, so neither is the cast.
+    fn convert_to(&self, expr: Expression, target: &Type) -> Expression {
+        let target = target.unqualified();
+        if expr.ty(&self.symbols) == *target {
+            return expr;
+        }
+        let span = expr.span().clone();
+
+        Expression::Cast(target.clone(), Box::new(expr), span)
+    }
+
+    /// Converts a value to the type of the object it is stored in (an assigned variable, a
+    /// parameter or a return value). Pointers only accept null pointer constants and
+    /// pointers to the same type, which may gain qualifiers but not lose them (`int *`
+    /// converts to `const int *`, not the other way around).
+    fn convert_by_assignment(
+        &self,
+        expr: Expression,
+        target: &Type,
+    ) -> Result<Expression, CompileError> {
+        let expr_type = expr.ty(&self.symbols);
+        let target = target.unqualified();
+        if expr_type == *target || matches!(expr, Expression::Error(_)) {
+            return Ok(expr);
+        }
+
+        if (expr_type.is_arithmetic() && target.is_arithmetic())
+            || (target.is_pointer() && expr.is_null_pointer_constant())
+        {
+            return Ok(self.convert_to(expr, target));
+        }
+
+        if let Some((from, to)) = Self::same_referenced_type(&expr_type, target) {
+            if !to.contains(from) {
+                debug!("[semantic] converting {expr_type:?} to {target:?} discards qualifiers");
+
+                return Err(SemanticError::DiscardsQualifiers.at(expr.span().clone()));
+            }
+
+            return Ok(self.convert_to(expr, target));
+        }
+
+        debug!("[semantic] cannot convert {expr_type:?} to {target:?}");
+
+        Err(SemanticError::InvalidConversion.at(expr.span().clone()))
+    }
+
+    /// Returns the qualifiers of the types referenced by two pointers, if they point to the
+    /// same type once those qualifiers are ignored.
+    fn same_referenced_type(left: &Type, right: &Type) -> Option<(Qualifiers, Qualifiers)> {
+        match (left, right) {
+            (Type::Pointer(l), Type::Pointer(r))
+                if l.unqualified().canonical() == r.unqualified().canonical() =>
+            {
+                Some((l.qualifiers(), r.qualifiers()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the type two pointer operands are compared as: a pointer to their referenced
+    /// type with the qualifiers of both, unless one of them is a null pointer constant.
+    fn common_pointer_type(
+        &self,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Type, CompileError> {
+        let left_type = left.ty(&self.symbols);
+        let right_type = right.ty(&self.symbols);
+
+        if left_type == right_type || right.is_null_pointer_constant() {
+            Ok(left_type)
+        } else if left.is_null_pointer_constant() {
+            Ok(right_type)
+        } else if let (Type::Pointer(referenced), Some((l, r))) = (
+            &left_type,
+            Self::same_referenced_type(&left_type, &right_type),
+        ) {
+            let referenced = referenced.unqualified().clone().qualified(l.union(r));
+            Ok(Type::Pointer(Box::new(referenced)))
+        } else {
+            debug!("[semantic] incompatible pointer types: {left_type:?} and {right_type:?}");
+
+            Err(SemanticError::IncompatiblePointerTypes.at(left.span().to(right.span())))
+        }
+    }
+
+    /// Checks that an lvalue can be modified: it's not `const`, and it's not a structure
+    /// with a `const` member.
+    fn check_modifiable(&self, lvalue: &Expression) -> Result<(), CompileError> {
+        if self.has_const(&lvalue.qualified_ty(&self.symbols)) {
+            debug!("[semantic] {lvalue} is const and can't be modified");
+
+            return Err(
+                SemanticError::ConstLvalue(lvalue.lvalue_spelling()).at(lvalue.span().clone())
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Whether an object of this type is `const` or has a `const` part.
+    fn has_const(&self, ty: &Type) -> bool {
+        if ty.is_const() {
+            return true;
+        }
+
+        match ty.unqualified() {
+            Type::Array(element_type, _) => self.has_const(element_type),
+            Type::Structure(tag) => self
+                .symbols
+                .types()
+                .get(tag)
+                .is_some_and(|entry| entry.members.iter().any(|m| self.has_const(&m.ty))),
+            _ => false,
+        }
+    }
+
+    /// Checks the operands of an addition, a subtraction or a comparison where at least one of
+    /// them is a pointer. Integers added to (or subtracted from) a pointer are converted to
+    /// longs, pointers are only subtracted from or compared with pointers of the same type.
+    fn check_pointer_arithmetic(
+        &self,
+        op: &BinaryOperator,
+        left: Expression,
+        right: Expression,
+    ) -> Result<(Expression, Expression), CompileError> {
+        let left_type = left.ty(&self.symbols);
+        let right_type = right.ty(&self.symbols);
+
+        if let BinaryOperator::Add | BinaryOperator::Subtract = op
+            && (left_type.is_function_pointer() || right_type.is_function_pointer())
+        {
+            debug!("[semantic] arithmetic on a function pointer: {op:?}");
+
+            return Err(SemanticError::InvalidPointerOperand.at(left.span().to(right.span())));
+        }
+
+        match op {
+            BinaryOperator::Add | BinaryOperator::Subtract
+                if left_type.is_pointer() && right_type.is_integer() =>
+            {
+                Ok((left, self.convert_to(right, &Type::Long)))
+            }
+            BinaryOperator::Add if left_type.is_integer() && right_type.is_pointer() => {
+                Ok((self.convert_to(left, &Type::Long), right))
+            }
+            BinaryOperator::Subtract
+            | BinaryOperator::LessThan
+            | BinaryOperator::LessThanOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterThanOrEqual
+                if Self::same_referenced_type(&left_type, &right_type).is_some() =>
+            {
+                Ok((left, right))
+            }
+            _ => {
+                debug!("[semantic] invalid operands for {op:?}: {left_type:?} and {right_type:?}");
+
+                Err(SemanticError::InvalidPointerOperand.at(left.span().to(right.span())))
+            }
+        }
+    }
+
+    /// Checks the operands of a compound assignment and converts the right operand to the
+    /// type the operation is done in: the common type of both operands, the promoted left
+    /// type for shifts, or a long added to (or subtracted from) a pointer. The result is
+    /// converted back to the left type when it's stored.
+    fn check_compound_operands(
+        &self,
+        op: &BinaryOperator,
+        left: &Expression,
+        right: Expression,
+    ) -> Result<Expression, CompileError> {
+        let left_type = left.ty(&self.symbols);
+        let right_type = right.ty(&self.symbols);
+
+        if left_type.is_structure() || right_type.is_structure() {
+            debug!("[semantic] structure operand for {op:?}=");
+
+            return Err(SemanticError::InvalidStructureOperand.at(left.span().to(right.span())));
+        }
+
+        if op.is_integer_only() && (left_type == Type::Double || right_type == Type::Double) {
+            debug!("[semantic] double operand for {op:?}=");
+
+            return Err(SemanticError::InvalidIntegerOperand.at(left.span().to(right.span())));
+        }
+
+        match op {
+            BinaryOperator::Add | BinaryOperator::Subtract
+                if left_type.is_pointer()
+                    && !left_type.is_function_pointer()
+                    && right_type.is_integer() =>
+            {
+                Ok(self.convert_to(right, &Type::Long))
+            }
+            _ if left_type.is_pointer() || right_type.is_pointer() => {
+                debug!("[semantic] invalid operands for {op:?}=: {left_type:?} and {right_type:?}");
+
+                Err(SemanticError::InvalidPointerOperand.at(left.span().to(right.span())))
+            }
+            _ if op.is_shift() => Ok(self.convert_to(right, &left_type.promote())),
+            _ => Ok(self.convert_to(right, &Type::common_type(&left_type, &right_type))),
+        }
+    }
+
+    /// Checks the arguments of a call and converts them to the types of the parameters.
+    /// The arguments matching the `...` of a variadic function are only promoted. `callee`
+    /// is the name of the function, `None` for a call through a pointer, and `span` is the
+    /// span of the call.
+    fn check_args(
+        &mut self,
+        callee: Option<&str>,
+        param_types: Vec<Type>,
+        variadic: bool,
+        args: Vec<Expression>,
+        span: &Span,
+    ) -> Result<Vec<Expression>, CompileError> {
+        let count_matches = if variadic {
+            args.len() >= param_types.len()
+        } else {
+            args.len() == param_types.len()
+        };
+        if !count_matches {
+            debug!(
+                "[semantic] {} expects {}{} arguments, got {}",
+                callee.unwrap_or("function pointer"),
+                if variadic { "at least " } else { "" },
+                param_types.len(),
+                args.len()
+            );
+
+            let (expected, found) = (param_types.len(), args.len());
+            return Err(match callee {
+                Some(name) => SemanticError::WrongArgumentCount {
+                    name: name.to_string(),
+                    expected,
+                    found,
+                },
+                None => SemanticError::WrongPointerArgumentCount { expected, found },
+            }
+            .at(span.clone()));
+        }
+
+        let mut param_types = param_types.into_iter();
+        args.into_iter()
+            .map(|arg| {
+                let arg = self.fold_expr(arg)?;
+                match param_types.next() {
+                    Some(param_type) => self.convert_by_assignment(arg, &param_type),
+                    None => {
+                        let arg_type = arg.ty(&self.symbols).promote();
+                        Ok(self.convert_to(arg, &arg_type))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Checks an expression and makes its implicit conversions explicit, without the decay
+    /// of arrays and functions (so it can be used for the operand of `&`).
+    fn check_expr(&mut self, expr: Expression) -> Result<Expression, CompileError> {
+        if expr.has_error() {
+            return Ok(Expression::Error(expr.span().clone()));
+        }
+
+        match expr {
+            Expression::FunctionCall(name, args, span) => {
+                let Some(symbol) = self.symbols.get(name.value()) else {
+                    debug!("[semantic] unknown function: {}", name.value());
+
+                    return Err(SemanticError::UndeclaredFunction(
+                        source_name(name.value()).to_string(),
+                    )
+                    .at(span));
+                };
+
+                // a variable holding a function pointer is called through it
+                if symbol.ty.is_function_pointer() {
+                    return self.check_expr(Expression::IndirectCall(
+                        Box::new(Expression::Var(name, span.clone())),
+                        args,
+                        span,
+                    ));
+                }
+
+                let Type::FunType(param_types, _, variadic) = symbol.ty.clone() else {
+                    debug!("[semantic] variable used as function: {}", name.value());
+
+                    return Err(SemanticError::VariableCalledAsFunction(
+                        source_name(name.value()).to_string(),
+                    )
+                    .at(span));
+                };
+                let args =
+                    self.check_args(Some(name.value()), param_types, variadic, args, &span)?;
+
+                Ok(Expression::FunctionCall(name, args, span))
+            }
+            Expression::IndirectCall(callee, args, span) => {
+                let callee = self.fold_expr(*callee)?;
+                let Type::Pointer(referenced) = callee.ty(&self.symbols) else {
+                    debug!("[semantic] called object is not a function: {callee:?}");
+
+                    return Err(SemanticError::NotAFunction.at(span));
+                };
+                let Type::FunType(param_types, _, variadic) = *referenced else {
+                    debug!("[semantic] called object is not a function: {callee:?}");
+
+                    return Err(SemanticError::NotAFunction.at(span));
+                };
+                let args = self.check_args(None, param_types, variadic, args, &span)?;
+
+                Ok(Expression::IndirectCall(Box::new(callee), args, span))
+            }
+            Expression::Var(ref id, ref span) => {
+                if let Some(value) = self.enumerator_value(id.value()) {
+                    return Ok(Expression::Constant(Const::ConstInt(value), span.clone()));
+                }
+
+                Ok(expr)
+            }
+            Expression::Unary(op, inner, span) => {
+                let inner = self.fold_expr(*inner)?;
+                let inner_type = inner.ty(&self.symbols);
+
+                if inner_type.is_structure() {
+                    debug!("[semantic] {op:?} of a structure");
+
+                    return Err(SemanticError::InvalidStructureOperand.at(span));
+                }
+
+                if let UnaryOperator::Complement = op
+                    && inner_type == Type::Double
+                {
+                    debug!("[semantic] bitwise complement of a double");
+
+                    return Err(SemanticError::InvalidComplementOperand.at(span));
+                }
+
+                if let UnaryOperator::Complement | UnaryOperator::Negate = op
+                    && inner_type.is_pointer()
+                {
+                    debug!("[semantic] {op:?} of a pointer");
+
+                    return Err(SemanticError::InvalidPointerOperand.at(span));
+                }
+
+                // the operands of `-` and `~` are promoted, `!` only compares against zero
+                let inner = match op {
+                    UnaryOperator::Complement | UnaryOperator::Negate => {
+                        self.convert_to(inner, &inner_type.promote())
+                    }
+                    _ => inner,
+                };
+
+                Ok(Expression::Unary(op, Box::new(inner), span))
+            }
+            Expression::Binary(op, left, right, span) => {
+                let left = self.fold_expr(*left)?;
+                let right = self.fold_expr(*right)?;
+
+                if left.ty(&self.symbols).is_structure() || right.ty(&self.symbols).is_structure() {
+                    debug!("[semantic] structure operand for {op:?}");
+
+                    return Err(SemanticError::InvalidStructureOperand.at(span));
+                }
+
+                if op.is_integer_only()
+                    && (left.ty(&self.symbols) == Type::Double
+                        || right.ty(&self.symbols) == Type::Double)
+                {
+                    debug!("[semantic] double operand for {op:?}");
+
+                    return Err(SemanticError::InvalidIntegerOperand.at(span));
+                }
+
+                let (left, right) = match op {
+                    // logical operators only compare their operands against zero
+                    BinaryOperator::And | BinaryOperator::Or => (left, right),
+                    BinaryOperator::Equal | BinaryOperator::NotEqual
+                        if left.ty(&self.symbols).is_pointer()
+                            || right.ty(&self.symbols).is_pointer() =>
+                    {
+                        let common_type = self.common_pointer_type(&left, &right)?;
+                        (
+                            self.convert_to(left, &common_type),
+                            self.convert_to(right, &common_type),
+                        )
+                    }
+                    BinaryOperator::Add
+                    | BinaryOperator::Subtract
+                    | BinaryOperator::LessThan
+                    | BinaryOperator::LessThanOrEqual
+                    | BinaryOperator::GreaterThan
+                    | BinaryOperator::GreaterThanOrEqual
+                        if left.ty(&self.symbols).is_pointer()
+                            || right.ty(&self.symbols).is_pointer() =>
+                    {
+                        self.check_pointer_arithmetic(&op, left, right)?
+                    }
+                    _ if left.ty(&self.symbols).is_pointer()
+                        || right.ty(&self.symbols).is_pointer() =>
+                    {
+                        debug!("[semantic] pointer operand for {op:?}");
+
+                        return Err(SemanticError::InvalidPointerOperand.at(span));
+                    }
+                    _ if op.is_shift() => {
+                        let left_type = left.ty(&self.symbols).promote();
+                        (
+                            self.convert_to(left, &left_type),
+                            self.convert_to(right, &left_type),
+                        )
+                    }
+                    _ => {
+                        let common_type =
+                            Type::common_type(&left.ty(&self.symbols), &right.ty(&self.symbols));
+                        (
+                            self.convert_to(left, &common_type),
+                            self.convert_to(right, &common_type),
+                        )
+                    }
+                };
+
+                Ok(Expression::Binary(
+                    op,
+                    Box::new(left),
+                    Box::new(right),
+                    span,
+                ))
+            }
+            Expression::Assignment(left, right, span) => {
+                let left = self.fold_expr(*left)?;
+                if !left.is_lvalue() {
+                    debug!("[semantic] invalid lvalue: {left:?}");
+
+                    return Err(SemanticError::InvalidLvalue.at(left.span().clone()));
+                }
+                self.check_modifiable(&left)?;
+
+                let right = self.fold_expr(*right)?;
+                let left_type = left.ty(&self.symbols);
+
+                Ok(Expression::Assignment(
+                    Box::new(left),
+                    Box::new(self.convert_by_assignment(right, &left_type)?),
+                    span,
+                ))
+            }
+            Expression::CompoundAssignment(op, left, right, span) => {
+                let left = self.fold_expr(*left)?;
+                if !left.is_lvalue() {
+                    debug!("[semantic] invalid lvalue: {left:?}");
+
+                    return Err(SemanticError::InvalidLvalue.at(left.span().clone()));
+                }
+                self.check_modifiable(&left)?;
+
+                let right = self.fold_expr(*right)?;
+                let right = self.check_compound_operands(&op, &left, right)?;
+
+                Ok(Expression::CompoundAssignment(
+                    op,
+                    Box::new(left),
+                    Box::new(right),
+                    span,
+                ))
+            }
+            Expression::Postfix(op, inner, span) => {
+                let inner = self.fold_expr(*inner)?;
+                if !inner.is_lvalue() {
+                    debug!("[semantic] invalid lvalue: {inner:?}");
+
+                    return Err(SemanticError::InvalidLvalue.at(inner.span().clone()));
+                }
+                self.check_modifiable(&inner)?;
+                self.check_scalar(&inner)?;
+                if inner.ty(&self.symbols).is_function_pointer() {
+                    debug!("[semantic] {op:?} of a function pointer");
+
+                    return Err(SemanticError::InvalidPointerOperand.at(span));
+                }
+
+                Ok(Expression::Postfix(op, Box::new(inner), span))
+            }
+            Expression::Comma(left, right, span) => Ok(Expression::Comma(
+                Box::new(self.fold_expr(*left)?),
+                Box::new(self.fold_expr(*right)?),
+                span,
+            )),
+            Expression::Conditional(cond, then, el, span) => {
+                let cond = self.fold_expr(*cond)?;
+                self.check_scalar(&cond)?;
+                let then = self.fold_expr(*then)?;
+                let el = self.fold_expr(*el)?;
+                let then_type = then.ty(&self.symbols);
+                let el_type = el.ty(&self.symbols);
+                let common_type = if then_type.is_structure() || el_type.is_structure() {
+                    if then_type != el_type {
+                        debug!("[semantic] conditional of {then_type:?} and {el_type:?}");
+
+                        return Err(SemanticError::IncompatibleStructureTypes.at(span));
+                    }
+                    then_type
+                } else if then_type.is_pointer() || el_type.is_pointer() {
+                    self.common_pointer_type(&then, &el)?
+                } else {
+                    Type::common_type(&then_type, &el_type)
+                };
+
+                Ok(Expression::Conditional(
+                    Box::new(cond),
+                    Box::new(self.convert_to(then, &common_type)),
+                    Box::new(self.convert_to(el, &common_type)),
+                    span,
+                ))
+            }
+            Expression::Cast(target, inner, span) => {
+                let inner = self.fold_expr(*inner)?;
+                let inner_type = inner.ty(&self.symbols);
+                // the result of a cast is a value, so its qualifiers don't matter
+                let target = target.unqualified().clone();
+
+                if target.is_array() {
+                    debug!("[semantic] cast to array type {target:?}");
+
+                    return Err(SemanticError::CastToArray.at(span));
+                }
+
+                if let Type::FunType(..) = target {
+                    debug!("[semantic] cast to function type {target:?}");
+
+                    return Err(SemanticError::CastToFunction.at(span));
+                }
+
+                if target.is_structure() || inner_type.is_structure() {
+                    debug!("[semantic] cast between {inner_type:?} and {target:?}");
+
+                    return Err(SemanticError::StructureCast.at(span));
+                }
+
+                if (target.is_double() && inner_type.is_pointer())
+                    || (target.is_pointer() && inner_type.is_double())
+                {
+                    debug!("[semantic] cast between {inner_type:?} and {target:?}");
+
+                    return Err(SemanticError::DoublePointerCast.at(span));
+                }
+
+                Ok(Expression::Cast(target, Box::new(inner), span))
+            }
+            Expression::Dereference(inner, span) => {
+                let inner = self.fold_expr(*inner)?;
+
+                if !inner.ty(&self.symbols).is_pointer() {
+                    debug!("[semantic] dereference of a non-pointer: {inner:?}");
+
+                    return Err(SemanticError::DereferenceNonPointer.at(span));
+                }
+
+                Ok(Expression::Dereference(Box::new(inner), span))
+            }
+            Expression::AddressOf(inner, span) => {
+                let inner = self.check_expr(*inner)?;
+
+                if !inner.is_lvalue() {
+                    debug!("[semantic] address of a non-lvalue: {inner:?}");
+
+                    return Err(SemanticError::AddressOfNonLvalue.at(span));
+                }
+
+                Ok(Expression::AddressOf(Box::new(inner), span))
+            }
+            Expression::Subscript(left, right, span) => {
+                let left = self.fold_expr(*left)?;
+                let right = self.fold_expr(*right)?;
+                let left_type = left.ty(&self.symbols);
+                let right_type = right.ty(&self.symbols);
+
+                if left_type.is_function_pointer() || right_type.is_function_pointer() {
+                    debug!("[semantic] subscript of a function pointer");
+
+                    return Err(SemanticError::InvalidSubscript.at(span));
+                }
+
+                let (left, right) = if left_type.is_pointer() && right_type.is_integer() {
+                    (left, self.convert_to(right, &Type::Long))
+                } else if left_type.is_integer() && right_type.is_pointer() {
+                    (self.convert_to(left, &Type::Long), right)
+                } else {
+                    debug!("[semantic] invalid subscript: {left_type:?}[{right_type:?}]");
+
+                    return Err(SemanticError::InvalidSubscript.at(span));
+                };
+
+                Ok(Expression::Subscript(Box::new(left), Box::new(right), span))
+            }
+            Expression::Dot(inner, member, span) => {
+                let inner = self.fold_expr(*inner)?;
+                self.check_member(&inner.ty(&self.symbols), member.value(), &span)?;
+
+                Ok(Expression::Dot(Box::new(inner), member, span))
+            }
+            Expression::Arrow(inner, member, span) => {
+                let inner = self.fold_expr(*inner)?;
+                let Type::Pointer(referenced) = inner.ty(&self.symbols) else {
+                    debug!("[semantic] -> on a non-pointer: {inner:?}");
+
+                    return Err(
+                        SemanticError::MemberThroughNonPointer(member.value().to_string()).at(span),
+                    );
+                };
+                self.check_member(&referenced, member.value(), &span)?;
+
+                Ok(Expression::Arrow(Box::new(inner), member, span))
+            }
+            Expression::VaArg(ap, ty, span) => {
+                let ap = self.check_va_list(*ap)?;
+                let ty = ty.unqualified().clone();
+                if !ty.is_scalar() {
+                    debug!("[semantic] va_arg of non-scalar type {ty:?}");
+
+                    return Err(SemanticError::VaArgNonScalar.at(span));
+                }
+
+                Ok(Expression::VaArg(Box::new(ap), ty, span))
+            }
+            _ => self.default_fold_expr(expr),
+        }
+    }
+
+    /// Checks that a variable is redeclared with the same type.
+    fn check_var_type(name: &str, old: &Symbol, var_type: &Type) -> Result<(), SemanticError> {
+        if let Type::FunType(..) = old.ty {
+            debug!("[semantic] function redeclared as variable: {name}");
+
+            return Err(SemanticError::FunctionRedeclaredAsVariable(
+                source_name(name).to_string(),
+            ));
+        }
+
+        if old.ty.canonical() != var_type.canonical() {
+            debug!(
+                "[semantic] {name} redeclared as {var_type:?}, was {:?}",
+                old.ty
+            );
+
+            return Err(SemanticError::ConflictingVariableTypes(
+                source_name(name).to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl FolderC for TypeChecker {
+    fn name(&self) -> &'static str {
+        "type_check"
+    }
+
+    fn recover(&mut self, error: CompileError) -> Result<(), CompileError> {
+        self.errors.push(error);
+        Ok(())
+    }
+
+    fn take_errors(&mut self) -> Vec<CompileError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn fold_fun_decl(
+        &mut self,
+        function: FunctionDeclaration,
+    ) -> Result<FunctionDeclaration, CompileError> {
+        trace!("[semantic] <function> {}", function.name().value());
+
+        // the qualifiers of the parameters only matter inside the definition
+        let fun_type = function.fun_type().canonical();
+        let has_body = function.body().is_some();
+        let mut already_defined = false;
+        let mut global = function.storage_class() != Some(&StorageClass::Static);
+
+        if let Some(old) = self.symbols.get(function.name().value()) {
+            if old.ty != fun_type {
+                debug!(
+                    "[semantic] incompatible declarations: {}",
+                    function.name().value()
+                );
+
+                return Err(SemanticError::IncompatibleFunctionDeclarations(
+                    function.name().value().to_string(),
+                )
+                .at(function.span().clone()));
+            }
+
+            already_defined = old.is_defined();
+            if already_defined && has_body {
+                debug!(
+                    "[semantic] function defined twice: {}",
+                    function.name().value()
+                );
+
+                let previous = self
+                    .definitions
+                    .get(function.name().value())
+                    .unwrap_or(function.span())
+                    .clone();
+                return Err(SemanticError::FunctionRedefined(
+                    function.name().value().to_string(),
+                    Box::new(previous),
+                )
+                .at(function.span().clone()));
+            }
+
+            if old.is_global() && !global {
+                debug!(
+                    "[semantic] static declaration follows non-static: {}",
+                    function.name().value()
+                );
+
+                return Err(SemanticError::StaticAfterNonStatic(
+                    function.name().value().to_string(),
+                )
+                .at(function.span().clone()));
+            }
+            global = old.is_global();
+        }
+
+        debug!(
+            "[semantic] {} : {fun_type:?} (defined: {})",
+            function.name().value(),
+            already_defined || has_body
+        );
+
+        self.symbols.insert(
+            function.name().value(),
+            Symbol::new(
+                fun_type,
+                IdentifierAttrs::FunAttr {
+                    defined: already_defined || has_body,
+                    global,
+                },
+            ),
+        );
+
+        if has_body {
+            self.definitions
+                .insert(function.name().value().to_string(), function.span().clone());
+        }
+
+        if has_body && let Type::FunType(param_types, return_type, _) = function.fun_type() {
+            self.check_complete(function.name().value(), return_type, function.span())?;
+            for (param, param_type) in function.params().iter().zip(param_types) {
+                self.check_complete(param.value(), param_type, function.span())?;
+                self.symbols.insert(
+                    param.value(),
+                    Symbol::new(param_type.clone(), IdentifierAttrs::LocalAttr),
+                );
+            }
+            self.return_type = Some(return_type.unqualified().clone());
+            self.last_param = match function.fun_type() {
+                Type::FunType(_, _, true) => {
+                    function.params().last().map(|p| p.value().to_string())
+                }
+                _ => None,
+            };
+        }
+
+        let span = function.span().clone();
+
+        let (name, params, body, fun_type, storage_class) = function.into_parts();
+        let body = body.map(|b| self.fold_block(b)).transpose()?;
+
+        Ok(FunctionDeclaration::new(name, params, body, fun_type, storage_class).with_span(span))
+    }
+
+    fn fold_file_var_decl(
+        &mut self,
+        declaration: VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompileError> {
+        let name = declaration.name().value();
+        trace!("[semantic] <file_declaration> {name}");
+
+        let is_extern = declaration.storage_class() == Some(&StorageClass::Extern);
+        let default = if is_extern {
+            InitialValue::NoInitializer
+        } else {
+            InitialValue::Tentative
+        };
+        let var_type = declaration.var_type();
+        let checked = if is_extern {
+            Ok(())
+        } else {
+            self.check_complete(name, var_type, declaration.span())
+        };
+        let checked = checked.and_then(|()| {
+            self.static_initial_value(
+                name,
+                declaration.initializer(),
+                var_type,
+                default.clone(),
+                declaration.span(),
+            )
+        });
+        // a variable with an invalid initializer is still declared, so its uses don't fail
+        let mut init = match checked {
+            Ok(init) => init,
+            Err(error) => {
+                self.recover(error)?;
+                default
+            }
+        };
+        let mut global = declaration.storage_class() != Some(&StorageClass::Static);
+
+        if let Some(old) = self.symbols.get(name) {
+            Self::check_var_type(name, old, var_type)
+                .map_err(|error| error.at(declaration.span().clone()))?;
+            let IdentifierAttrs::StaticAttr {
+                init: old_init,
+                global: old_global,
+            } = &old.attrs
+            else {
+                debug!("[semantic] function redeclared as variable: {name}");
+
+                return Err(SemanticError::FunctionRedeclaredAsVariable(
+                    source_name(name).to_string(),
+                )
+                .at(declaration.span().clone()));
+            };
+
+            if is_extern {
+                global = *old_global;
+            } else if *old_global != global {
+                debug!("[semantic] conflicting linkage: {name}");
+
+                return Err(
+                    SemanticError::ConflictingLinkage(source_name(name).to_string())
+                        .at(declaration.span().clone()),
+                );
+            }
+
+            init = match (old_init, init) {
+                (InitialValue::Initial(_), InitialValue::Initial(_)) => {
+                    debug!("[semantic] variable defined twice: {name}");
+
+                    return Err(SemanticError::ConflictingDefinitions(
+                        source_name(name).to_string(),
+                    )
+                    .at(declaration.span().clone()));
+                }
+                (InitialValue::Initial(c), _) => InitialValue::Initial(c.clone()),
+                (InitialValue::Tentative, InitialValue::NoInitializer) => InitialValue::Tentative,
+                (_, init) => init,
+            };
+        }
+
+        debug!("[semantic] {name} : static {init:?} (global: {global})");
+
+        self.symbols.insert(
+            name,
+            Symbol::new(
+                var_type.clone(),
+                IdentifierAttrs::StaticAttr { init, global },
+            ),
+        );
+
+        Ok(declaration)
+    }
+
+    fn fold_var_decl(
+        &mut self,
+        declaration: VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompileError> {
+        let name = declaration.name().value();
+        let var_type = declaration.var_type();
+        trace!("[semantic] <declaration> {name}");
+
+        match declaration.storage_class() {
+            Some(StorageClass::Extern) => {
+                if declaration.initializer().is_some() {
+                    debug!("[semantic] initializer on local extern variable: {name}");
+
+                    return Err(
+                        SemanticError::ExternInitializer(source_name(name).to_string())
+                            .at(declaration.span().clone()),
+                    );
+                }
+
+                match self.symbols.get(name) {
+                    Some(old) => Self::check_var_type(name, old, var_type)
+                        .map_err(|error| error.at(declaration.span().clone()))?,
+                    None => self.symbols.insert(
+                        name,
+                        Symbol::new(
+                            var_type.clone(),
+                            IdentifierAttrs::StaticAttr {
+                                init: InitialValue::NoInitializer,
+                                global: true,
+                            },
+                        ),
+                    ),
+                }
+
+                Ok(declaration)
+            }
+            Some(StorageClass::Static) => {
+                self.check_complete(name, var_type, declaration.span())?;
+                let init = self.static_initial_value(
+                    name,
+                    declaration.initializer(),
+                    var_type,
+                    InitialValue::Initial(StaticInit::zero(var_type, self.symbols.types())),
+                    declaration.span(),
+                )?;
+                self.symbols.insert(
+                    name,
+                    Symbol::new(
+                        var_type.clone(),
+                        IdentifierAttrs::StaticAttr {
+                            init,
+                            global: false,
+                        },
+                    ),
+                );
+
+                Ok(declaration)
+            }
+            None => {
+                self.check_complete(name, var_type, declaration.span())?;
+                self.symbols.insert(
+                    name,
+                    Symbol::new(var_type.clone(), IdentifierAttrs::LocalAttr),
+                );
+
+                let span = declaration.span().clone();
+
+                let (name, initializer, var_type, storage_class) = declaration.into_parts();
+                let initializer = initializer
+                    .map(|i| self.check_initializer(name.value(), i, &var_type, &span))
+                    .transpose()?;
+
+                Ok(
+                    VariableDeclaration::new(name, initializer, var_type, storage_class)
+                        .with_span(span),
+                )
+            }
+        }
+    }
+
+    fn fold_st(&mut self, statement: Statement) -> Result<Statement, CompileError> {
+        match statement {
+            Statement::Return(expr, span) => {
+                let expr = self.fold_expr(expr)?;
+                let return_type = self.return_type.clone().unwrap_or(Type::Int);
+
+                Ok(Statement::Return(
+                    self.convert_by_assignment(expr, &return_type)?,
+                    span,
+                ))
+            }
+            Statement::Switch(cond, body, label, cases, span) => {
+                let cond = self.fold_expr(*cond)?;
+                let cond_type = cond.ty(&self.symbols);
+                if !cond_type.is_integer() {
+                    debug!("[semantic] switch on a value of type {cond_type:?}");
+
+                    return Err(SemanticError::NonIntegerSwitch.at(cond.span().clone()));
+                }
+
+                let switch_type = cond_type.promote();
+                let cond = self.convert_to(cond, &switch_type);
+                let outer = self.switch_type.replace(switch_type);
+                let body = self.fold_st(*body);
+                self.switch_type = outer;
+
+                Ok(Statement::Switch(
+                    Box::new(cond),
+                    Box::new(body?),
+                    label,
+                    cases,
+                    span,
+                ))
+            }
+            Statement::Case(value, st, label, span) => {
+                let value = self.fold_expr(value)?;
+                if let Expression::Error(_) = value {
+                    return Ok(Statement::Case(
+                        value,
+                        Box::new(self.fold_st(*st)?),
+                        label,
+                        span,
+                    ));
+                }
+                let value_span = value.span().clone();
+                let Some(value) = value.integer_constant() else {
+                    debug!("[semantic] case label is not an integer constant: {value}");
+
+                    return Err(SemanticError::NonConstantCase.at(value_span));
+                };
+                // outside of a switch the case is left as it is, the loop labeler rejects it
+                let value = match &self.switch_type {
+                    Some(switch_type) => value.convert_to(switch_type),
+                    None => value,
+                };
+
+                Ok(Statement::Case(
+                    Expression::Constant(value, value_span),
+                    Box::new(self.fold_st(*st)?),
+                    label,
+                    span,
+                ))
+            }
+            Statement::VaStart(ap, last, span) => {
+                let ap = self.check_va_list(ap)?;
+                let Some(last_param) = &self.last_param else {
+                    debug!("[semantic] va_start in a function with fixed arguments");
+
+                    return Err(SemanticError::VaStartFixedArguments.at(span));
+                };
+                if !matches!(&last, Expression::Var(id, _) if id.value() == last_param) {
+                    debug!("[semantic] va_start with {last:?} instead of {last_param}");
+
+                    return Err(SemanticError::VaStartNotLastParameter(
+                        source_name(last_param).to_string(),
+                    )
+                    .at(span));
+                }
+
+                Ok(Statement::VaStart(ap, last, span))
+            }
+            Statement::VaEnd(ap, span) => Ok(Statement::VaEnd(self.check_va_list(ap)?, span)),
+            Statement::VaCopy(dst, src, span) => {
+                let dst = self.check_va_list(dst)?;
+                let src = self.check_va_list(src)?;
+
+                let (dst_span, src_span) = (dst.span().clone(), src.span().clone());
+
+                Ok(Statement::Expression(
+                    Expression::Assignment(
+                        Box::new(Expression::Dereference(Box::new(dst), dst_span)),
+                        Box::new(Expression::Dereference(Box::new(src), src_span)),
+                        span.clone(),
+                    ),
+                    span,
+                ))
+            }
+            _ => {
+                let statement = self.default_fold_st(statement)?;
+
+                match &statement {
+                    Statement::If(cond, ..)
+                    | Statement::While(cond, ..)
+                    | Statement::DoWhile(_, cond, ..)
+                    | Statement::For(_, Some(cond), ..) => self.check_scalar(cond)?,
+                    _ => {}
+                }
+
+                Ok(statement)
+            }
+        }
+    }
+
+    fn fold_struct_decl(
+        &mut self,
+        declaration: StructDeclaration,
+    ) -> Result<StructDeclaration, CompileError> {
+        let tag = declaration.tag().value();
+        trace!("[semantic] <struct_declaration> {tag}");
+
+        // a declaration without members only introduces the tag
+        if declaration.members().is_empty() {
+            return Ok(declaration);
+        }
+
+        if self.symbols.types().contains(tag) {
+            debug!("[semantic] structure defined twice: {tag}");
+
+            return Err(
+                SemanticError::StructureRedefined(source_name(tag).to_string())
+                    .at(declaration.span().clone()),
+            );
+        }
+
+        let mut members: Vec<(String, Type)> = vec![];
+        for member in declaration.members() {
+            let name = member.name().value();
+            if members.iter().any(|(m, _)| m == name) {
+                debug!("[semantic] duplicate member {name} in structure {tag}");
+
+                return Err(SemanticError::DuplicateMember {
+                    tag: source_name(tag).to_string(),
+                    member: name.to_string(),
+                }
+                .at(declaration.span().clone()));
+            }
+            self.check_complete(name, member.member_type(), declaration.span())?;
+
+            members.push((name.to_string(), member.member_type().clone()));
+        }
+
+        let entry = StructEntry::layout(members, self.symbols.types());
+        debug!(
+            "[semantic] struct {tag}: size {}, alignment {}",
+            entry.size, entry.alignment
+        );
+        self.symbols.types_mut().insert(tag, entry);
+
+        Ok(declaration)
+    }
+
+    /// Records the value of every enumerator: its initializer, or one more than the previous
+    /// enumerator (zero for the first one). The values are written back as int constants.
+    fn fold_enum_decl(
+        &mut self,
+        declaration: EnumDeclaration,
+    ) -> Result<EnumDeclaration, CompileError> {
+        trace!("[semantic] <enum_declaration>");
+
+        let span = declaration.span().clone();
+        let (tag, enumerators) = declaration.into_parts();
+        let mut next = 0;
+        let mut checked = vec![];
+        for (name, value) in enumerators {
+            // an implicit value has the span of the whole enumeration
+            let mut value_span = span.clone();
+            // a value with an error is left out, the next enumerators follow the previous one
+            if let Some(value) = value.filter(|value| !value.has_error()) {
+                let value = self.fold_expr(value)?;
+                value_span = value.span().clone();
+                match value.integer_constant() {
+                    Some(value) => next = value.as_i64(),
+                    // its error is already reported
+                    None if value.has_error() => {}
+                    None => {
+                        debug!("[semantic] enumerator value is not an integer constant: {value}");
+
+                        return Err(SemanticError::NonConstantEnumerator(
+                            source_name(name.value()).to_string(),
+                        )
+                        .at(value_span));
+                    }
+                }
+            }
+            let Ok(value) = i32::try_from(next) else {
+                debug!(
+                    "[semantic] enumerator {} out of range: {next}",
+                    name.value()
+                );
+
+                return Err(SemanticError::EnumeratorOutOfRange(
+                    source_name(name.value()).to_string(),
+                )
+                .at(value_span));
+            };
+
+            debug!("[semantic] enumerator {} = {value}", name.value());
+
+            self.symbols.insert(
+                name.value(),
+                Symbol::new(Type::Int, IdentifierAttrs::EnumeratorAttr(value)),
+            );
+            checked.push((
+                name,
+                Some(Expression::Constant(Const::ConstInt(value), value_span)),
+            ));
+            next = i64::from(value) + 1;
+        }
+
+        Ok(EnumDeclaration::new(tag, checked).with_span(span))
+    }
+
+    /// An expression with an error becomes a placeholder, so the rest of its statement is
+    /// still checked. The error of an expression whose operand already failed follows from
+    /// the placeholder and isn't reported.
+    fn fold_expr(&mut self, expr: Expression) -> Result<Expression, CompileError> {
+        let span = expr.span().clone();
+        let reported = self.errors.len();
+        let expr = match self.check_expr(expr) {
+            Ok(expr) => expr,
+            Err(error) => {
+                if self.errors.len() == reported {
+                    self.recover(error)?;
+                }
+                return Ok(Expression::Error(span));
+            }
+        };
+
+        // arrays decay to a pointer to their first element
+        if let Type::Array(element_type, _) = expr.ty(&self.symbols) {
+            return Ok(Expression::Cast(
+                Type::Pointer(element_type),
+                Box::new(expr),
+                span,
+            ));
+        }
+
+        // functions decay to a pointer to themselves
+        if let Type::FunType(..) = expr.ty(&self.symbols) {
+            return Ok(Expression::AddressOf(Box::new(expr), span));
+        }
+
+        Ok(expr)
+    }
+}
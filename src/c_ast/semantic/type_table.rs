@@ -0,0 +1,110 @@
+//! Type table of the structure types declared in a translation unit.
+//!
+//! The type checker records the layout of every structure definition, keyed by the
+//! unique tag `VariableResolver` gave it. Later stages read it to compute the size of
+//! structure values and the offsets of their members.
+//!
+//! It starts with the builtin structure behind `va_list`, which can't clash with the
+//! structures of the program since their unique tags never look like its tag.
+
+use std::collections::HashMap;
+
+use crate::c_ast::ast::Type;
+
+/// Tag of the structure `va_list` is an array of, laid out as the System V ABI requires.
+pub const VA_LIST_TAG: &str = "va_list.tag";
+
+#[derive(Clone, Debug)]
+pub struct TypeTable(HashMap<String, StructEntry>);
+
+impl Default for TypeTable {
+    fn default() -> Self {
+        let mut types = TypeTable(HashMap::new());
+        let va_list_tag = StructEntry::layout(
+            vec![
+                ("gp_offset".to_string(), Type::UInt),
+                ("fp_offset".to_string(), Type::UInt),
+                (
+                    "overflow_arg_area".to_string(),
+                    Type::Pointer(Box::new(Type::Char)),
+                ),
+                (
+                    "reg_save_area".to_string(),
+                    Type::Pointer(Box::new(Type::Char)),
+                ),
+            ],
+            &types,
+        );
+        types.insert(VA_LIST_TAG, va_list_tag);
+
+        types
+    }
+}
+
+/// The layout of a structure type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructEntry {
+    pub alignment: usize,
+    pub size: usize,
+    /// The members in declaration order, which is also the order of their offsets
+    pub members: Vec<MemberEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberEntry {
+    pub name: String,
+    pub ty: Type,
+    /// Offset in bytes from the start of the structure
+    pub offset: usize,
+}
+
+impl TypeTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, tag: &str) -> Option<&StructEntry> {
+        self.0.get(tag)
+    }
+
+    pub fn insert(&mut self, tag: &str, entry: StructEntry) {
+        self.0.insert(tag.to_string(), entry);
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.contains_key(tag)
+    }
+}
+
+impl StructEntry {
+    /// Lays out the members following the System V ABI: each member goes at the next offset
+    /// that is a multiple of its alignment, the structure is aligned like its most aligned
+    /// member and its size is rounded up to a multiple of that alignment.
+    ///
+    /// Every member type must be complete, i.e. structures must be in the type table.
+    pub fn layout(members: Vec<(String, Type)>, types: &TypeTable) -> Self {
+        let mut size: usize = 0;
+        let mut alignment = 1;
+        let members = members
+            .into_iter()
+            .map(|(name, ty)| {
+                let member_alignment = ty.natural_alignment(types);
+                let offset = size.next_multiple_of(member_alignment);
+                size = offset + ty.size(types);
+                alignment = alignment.max(member_alignment);
+
+                MemberEntry { name, ty, offset }
+            })
+            .collect();
+
+        StructEntry {
+            alignment,
+            size: size.next_multiple_of(alignment),
+            members,
+        }
+    }
+
+    pub fn member(&self, name: &str) -> Option<&MemberEntry> {
+        self.members.iter().find(|m| m.name == name)
+    }
+}
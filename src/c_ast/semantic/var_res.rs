@@ -1,22 +1,51 @@
 use std::{collections::HashMap, sync::atomic::AtomicUsize};
 
-use log::{debug, error, trace};
+use log::{debug, trace};
 
 use crate::{
-    c_ast::ast::{Declaration, Expression, Identifier, Statement},
-    common::{folder::FolderC, util::temporary_name},
+    c_ast::{
+        ast::{
+            Block, Declaration, EnumDeclaration, Expression, FunctionDeclaration, Identifier,
+            MemberDeclaration, Statement, StorageClass, StructDeclaration, Type,
+            VariableDeclaration,
+        },
+        semantic::type_table::VA_LIST_TAG,
+    },
+    common::{
+        error::{CompileError, SemanticError},
+        folder::FolderC,
+        span::Span,
+        util::temporary_name,
+    },
 };
 
 pub type UniqueName = String;
 pub type FromCurrentBlock = bool;
-/// A tuple containing the variable unique name and whether it is declared for the current block
-pub type VarValue = (UniqueName, FromCurrentBlock);
+pub type HasLinkage = bool;
+pub type DeclaredAt = Span;
+/// A tuple containing the identifier unique name, whether it is declared for the current block,
+/// whether it refers to an object with linkage (i.e. a function, a file scope or an `extern`
+/// variable) and the span of its latest declaration
+pub type VarValue = (UniqueName, FromCurrentBlock, HasLinkage, DeclaredAt);
 pub type VarName = String;
+/// A tuple containing the unique tag of a structure type and whether it is declared for the
+/// current block
+pub type StructValue = (UniqueName, FromCurrentBlock);
+pub type StructTag = String;
 
 static VAR_RES_COUNT: AtomicUsize = AtomicUsize::new(0);
 
+/// Resolves identifiers to unique names. Structure tags live in their own namespace, with
+/// the same scoping rules as variables, and get unique tags as well.
+///
+/// It goes on after an error: an undeclared variable or function becomes an error
+/// placeholder, and a block item with any other error a null statement.
 #[derive(Default)]
-pub struct VariableResolver(HashMap<VarName, VarValue>);
+pub struct VariableResolver(
+    HashMap<VarName, VarValue>,
+    HashMap<StructTag, StructValue>,
+    Vec<CompileError>,
+);
 
 impl VariableResolver {
     pub fn new() -> Self {
@@ -24,32 +53,39 @@ impl VariableResolver {
     }
 
     pub fn new_with(var_map: HashMap<VarName, VarValue>) -> Self {
-        Self(var_map)
+        Self(var_map, HashMap::new(), vec![])
     }
 
-    /// Wether a variable is already named with the given name
-    fn is_var_named(&self, var_name: &Identifier) -> bool {
-        let Some(_) = self.get_var(var_name) else {
-            return false;
-        };
-        return true;
-    }
+    /// Returns a resolver for a new block, where every identifier and structure tag of the
+    /// enclosing blocks is visible but can be redeclared.
+    fn new_scope(&self) -> Self {
+        let struct_map = self
+            .1
+            .iter()
+            .map(|(tag, (unique_tag, _))| (tag.clone(), (unique_tag.clone(), false)))
+            .collect();
 
-    /// Wether a variable is already declared with the given name
-    fn is_var_declred(&self, var_name: &Identifier) -> bool {
-        let Some(var_value) = self.get_var(var_name) else {
-            return false;
-        };
-        return var_value.1;
+        Self(self.copy_variable_map(), struct_map, vec![])
     }
 
     pub fn get_var(&self, var_name: &Identifier) -> Option<VarValue> {
         self.0.get(var_name.value()).cloned()
     }
 
-    fn track_variable(&mut self, var_name: Identifier, unique_name: String) {
-        self.0
-            .insert(var_name.value().to_string(), (unique_name, true));
+    fn track_variable(&mut self, var_name: Identifier, unique_name: String, span: &Span) {
+        self.0.insert(
+            var_name.value().to_string(),
+            (unique_name, true, false, span.clone()),
+        );
+    }
+
+    /// Tracks an identifier with linkage, it keeps its name so every declaration
+    /// refers to the same object.
+    fn track_with_linkage(&mut self, name: &Identifier, span: &Span) {
+        self.0.insert(
+            name.value().to_string(),
+            (name.value().to_string(), true, true, span.clone()),
+        );
     }
 
     /// Returns a copy of the variables map with all the variables marked as undeclared for the current block.
@@ -65,6 +101,91 @@ impl VariableResolver {
             })
             .collect()
     }
+
+    /// Resolves the parameters and body of a function in a new scope.
+    ///
+    /// Parameters share the scope of the function body, so a parameter can't be redeclared
+    /// by a variable in the outermost block of the function. `span` is the span of the
+    /// function.
+    fn resolve_fun_scope(
+        &mut self,
+        params: Vec<Identifier>,
+        body: Option<Block>,
+        span: &Span,
+    ) -> Result<(Vec<Identifier>, Option<Block>), CompileError> {
+        let mut inner_resolver = self.new_scope();
+        let params = params
+            .into_iter()
+            .map(|p| inner_resolver.resolve_local_var(p, span))
+            .collect::<Result<Vec<_>, SemanticError>>()
+            .map_err(|error| error.at(span.clone()))?;
+        let body = body.map(|b| inner_resolver.fold_block(b)).transpose();
+        self.2.append(&mut inner_resolver.2);
+
+        Ok((params, body?))
+    }
+
+    /// Declares a block-scope variable and returns its unique name. `span` is the span of
+    /// the declaration.
+    fn resolve_local_var(
+        &mut self,
+        name: Identifier,
+        span: &Span,
+    ) -> Result<Identifier, SemanticError> {
+        if let Some((_, true, _, previous)) = self.get_var(&name) {
+            debug!("[semantic] duplicate variable: {}", name.value());
+
+            return Err(SemanticError::DuplicateDeclaration(
+                name.value().to_string(),
+                Box::new(previous),
+            ));
+        }
+
+        let unique_name = temporary_name(name.value(), &VAR_RES_COUNT);
+
+        debug!("[semantic] {} -> {}", name.value(), unique_name);
+
+        self.track_variable(name, unique_name.clone(), span);
+
+        Ok(Identifier::new(unique_name))
+    }
+
+    /// Replaces the tags of the structure types in a type with their unique tags. A tag
+    /// must be declared before it's used, `span` is where the type is written.
+    fn resolve_type(&self, ty: Type, span: &Span) -> Result<Type, CompileError> {
+        match ty {
+            Type::Structure(tag) if tag == VA_LIST_TAG => Ok(Type::Structure(tag)),
+            Type::Structure(tag) => {
+                let Some((unique_tag, _)) = self.1.get(&tag) else {
+                    debug!("[semantic] undeclared structure: {tag}");
+
+                    return Err(SemanticError::UndeclaredStructure(tag).at(span.clone()));
+                };
+
+                Ok(Type::Structure(unique_tag.clone()))
+            }
+            Type::Pointer(referenced) => Ok(Type::Pointer(Box::new(
+                self.resolve_type(*referenced, span)?,
+            ))),
+            Type::Array(element, size) => Ok(Type::Array(
+                Box::new(self.resolve_type(*element, span)?),
+                size,
+            )),
+            Type::FunType(params, ret, variadic) => Ok(Type::FunType(
+                params
+                    .into_iter()
+                    .map(|p| self.resolve_type(p, span))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+                Box::new(self.resolve_type(*ret, span)?),
+                variadic,
+            )),
+            Type::Qualified(inner, qualifiers) => Ok(Type::Qualified(
+                Box::new(self.resolve_type(*inner, span)?),
+                qualifiers,
+            )),
+            ty => Ok(ty),
+        }
+    }
 }
 
 impl FolderC for VariableResolver {
@@ -72,51 +193,241 @@ impl FolderC for VariableResolver {
         "var_res"
     }
 
-    fn fold_decl(&mut self, declaration: Declaration) -> Result<Declaration, String> {
-        trace!("[semantic] <declaration> {}", declaration.name().value());
+    fn recover(&mut self, error: CompileError) -> Result<(), CompileError> {
+        self.2.push(error);
+        Ok(())
+    }
 
-        if self.is_var_named(declaration.name()) && self.is_var_declred(declaration.name()) {
-            error!(
-                "[semantic] duplicate variable: {}",
-                declaration.name().value()
+    fn take_errors(&mut self) -> Vec<CompileError> {
+        std::mem::take(&mut self.2)
+    }
+
+    fn fold_decl(&mut self, declaration: Declaration) -> Result<Declaration, CompileError> {
+        match declaration {
+            Declaration::FunDecl(f) if f.body().is_some() => {
+                debug!(
+                    "[semantic] nested function definition: {}",
+                    f.name().value()
+                );
+
+                Err(
+                    SemanticError::NestedFunctionDefinition(f.name().value().to_string())
+                        .at(f.span().clone()),
+                )
+            }
+            Declaration::FunDecl(f) if f.storage_class() == Some(&StorageClass::Static) => {
+                debug!(
+                    "[semantic] static function declaration at block scope: {}",
+                    f.name().value()
+                );
+
+                Err(
+                    SemanticError::StaticBlockScopeFunction(f.name().value().to_string())
+                        .at(f.span().clone()),
+                )
+            }
+            Declaration::FunDecl(f) => Ok(Declaration::FunDecl(self.fold_fun_decl(f)?)),
+            Declaration::VarDecl(v) => Ok(Declaration::VarDecl(self.fold_var_decl(v)?)),
+            Declaration::StructDecl(s) => Ok(Declaration::StructDecl(self.fold_struct_decl(s)?)),
+            Declaration::EnumDecl(e) => Ok(Declaration::EnumDecl(self.fold_enum_decl(e)?)),
+        }
+    }
+
+    /// Declares the enumerators in the current scope, like variables without linkage. A
+    /// value is resolved before its enumerator is declared, so it can only refer to the
+    /// previous ones.
+    fn fold_enum_decl(
+        &mut self,
+        declaration: EnumDeclaration,
+    ) -> Result<EnumDeclaration, CompileError> {
+        trace!("[semantic] <enum_declaration>");
+
+        let span = declaration.span().clone();
+
+        let (tag, enumerators) = declaration.into_parts();
+        let enumerators = enumerators
+            .into_iter()
+            .map(|(name, value)| {
+                let value = value.map(|v| self.fold_expr(v)).transpose()?;
+
+                let name = self
+                    .resolve_local_var(name, &span)
+                    .map_err(|error| error.at(span.clone()))?;
+
+                Ok((name, value))
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?;
+
+        Ok(EnumDeclaration::new(tag, enumerators).with_span(span))
+    }
+
+    /// Declares a structure tag. A tag already declared in the same block refers to the same
+    /// type (e.g. a definition after a forward declaration), otherwise it's a new type that
+    /// hides any type with the same tag from the enclosing blocks.
+    fn fold_struct_decl(
+        &mut self,
+        declaration: StructDeclaration,
+    ) -> Result<StructDeclaration, CompileError> {
+        trace!(
+            "[semantic] <struct_declaration> {}",
+            declaration.tag().value()
+        );
+
+        let span = declaration.span().clone();
+
+        let (tag, members) = declaration.into_parts();
+        let unique_tag = match self.1.get(tag.value()) {
+            Some((unique_tag, true)) => unique_tag.clone(),
+            _ => {
+                let unique_tag = temporary_name(tag.value(), &VAR_RES_COUNT);
+
+                debug!("[semantic] struct {} -> {}", tag.value(), unique_tag);
+
+                self.1.insert(tag.into_value(), (unique_tag.clone(), true));
+                unique_tag
+            }
+        };
+
+        // the tag is already declared, so members can point to the structure itself
+        let members = members
+            .into_iter()
+            .map(|member| {
+                let (name, member_type) = member.into_parts();
+                Ok(MemberDeclaration::new(
+                    name,
+                    self.resolve_type(member_type, &span)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?;
+
+        Ok(StructDeclaration::new(Identifier::new(unique_tag), members).with_span(span))
+    }
+
+    fn fold_fun_decl(
+        &mut self,
+        function: FunctionDeclaration,
+    ) -> Result<FunctionDeclaration, CompileError> {
+        trace!("[semantic] <function> {}", function.name().value());
+
+        if let Some((_, true, false, previous)) = self.get_var(function.name()) {
+            debug!(
+                "[semantic] duplicate declaration: {}",
+                function.name().value()
             );
 
-            return Err("duplicate variable declaration".to_string());
+            return Err(SemanticError::DuplicateDeclaration(
+                function.name().value().to_string(),
+                Box::new(previous),
+            )
+            .at(function.span().clone()));
         }
 
-        let unique_name = temporary_name(declaration.name().value(), &VAR_RES_COUNT);
+        self.track_with_linkage(function.name(), function.span());
+        let span = function.span().clone();
+        let (name, params, body, fun_type, storage_class) = function.into_parts();
+        let fun_type = self.resolve_type(fun_type, &span)?;
+        let (params, body) = self.resolve_fun_scope(params, body, &span)?;
 
-        debug!(
-            "[semantic] {} -> {}",
-            declaration.name().value(),
-            unique_name
+        Ok(FunctionDeclaration::new(name, params, body, fun_type, storage_class).with_span(span))
+    }
+
+    fn fold_file_var_decl(
+        &mut self,
+        declaration: VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompileError> {
+        trace!(
+            "[semantic] <file_declaration> {}",
+            declaration.name().value()
         );
 
-        self.track_variable(declaration.name().clone(), unique_name.clone());
-        let init = declaration
-            .initializer()
-            .cloned()
-            .map(|e| self.fold_expr(e))
-            .transpose()?;
+        // file scope variables always have linkage, so they can be redeclared, but not as
+        // an enumerator
+        if let Some((_, true, false, previous)) = self.get_var(declaration.name()) {
+            debug!(
+                "[semantic] duplicate declaration: {}",
+                declaration.name().value()
+            );
+
+            return Err(SemanticError::DuplicateDeclaration(
+                declaration.name().value().to_string(),
+                Box::new(previous),
+            )
+            .at(declaration.span().clone()));
+        }
+        self.track_with_linkage(declaration.name(), declaration.span());
+
+        let span = declaration.span().clone();
+
+        let (name, initializer, var_type, storage_class) = declaration.into_parts();
+        // the initializer is a constant, but it can name an enumerator
+        let initializer = initializer.map(|i| self.fold_initializer(i)).transpose()?;
+
+        Ok(VariableDeclaration::new(
+            name,
+            initializer,
+            self.resolve_type(var_type, &span)?,
+            storage_class,
+        )
+        .with_span(span))
+    }
+
+    fn fold_var_decl(
+        &mut self,
+        declaration: VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompileError> {
+        trace!("[semantic] <declaration> {}", declaration.name().value());
+
+        let span = declaration.span().clone();
+
+        let (name, initializer, var_type, storage_class) = declaration.into_parts();
+        let name = if storage_class == Some(StorageClass::Extern) {
+            // an extern declaration refers to an object with linkage, so it can only
+            // conflict with a variable of the same block that has no linkage
+            if let Some((_, true, false, _)) = self.get_var(&name) {
+                debug!(
+                    "[semantic] conflicting local declarations: {}",
+                    name.value()
+                );
+
+                return Err(
+                    SemanticError::ConflictingLocalDeclarations(name.value().to_string()).at(span),
+                );
+            }
+            self.track_with_linkage(&name, &span);
+            name
+        } else {
+            self.resolve_local_var(name, &span)
+                .map_err(|error| error.at(span.clone()))?
+        };
+        let init = initializer.map(|i| self.fold_initializer(i)).transpose()?;
 
-        Ok(Declaration::new(Identifier::new(unique_name), init))
+        Ok(VariableDeclaration::new(
+            name,
+            init,
+            self.resolve_type(var_type, &span)?,
+            storage_class,
+        )
+        .with_span(span))
     }
 
-    fn fold_st(&mut self, statement: Statement) -> Result<Statement, String> {
+    fn fold_st(&mut self, statement: Statement) -> Result<Statement, CompileError> {
         let res = match statement {
-            Statement::Compound(block) => {
+            Statement::Compound(block, span) => {
                 trace!("[semantic] <statement> compound (new scope)");
 
-                let new_var_map = self.copy_variable_map();
-                let mut new_resolver = Self::new_with(new_var_map);
+                let mut new_resolver = self.new_scope();
+                let block = new_resolver.fold_block(*block);
+                self.2.append(&mut new_resolver.2);
 
-                Statement::Compound(Box::new(new_resolver.fold_block(*block)?))
+                Statement::Compound(Box::new(block?), span)
             }
-            Statement::For(for_init, cond, post, body, id) => {
-                let new_var_map = self.copy_variable_map();
-                let mut new_resolver = Self::new_with(new_var_map);
+            Statement::For(for_init, cond, post, body, id, span) => {
+                let mut new_resolver = self.new_scope();
+                let statement =
+                    new_resolver.default_fold_st_for(*for_init, cond, post, *body, id, span);
+                self.2.append(&mut new_resolver.2);
 
-                new_resolver.default_fold_st_for(for_init, cond, post, body, id)?
+                statement?
             }
             _ => self.default_fold_st(statement)?,
         };
@@ -124,40 +435,125 @@ impl FolderC for VariableResolver {
         Ok(res)
     }
 
-    fn fold_expr(&mut self, expr: Expression) -> Result<Expression, String> {
+    fn fold_expr(&mut self, expr: Expression) -> Result<Expression, CompileError> {
         match expr {
-            Expression::Assignment(left, right) => match *left {
-                Expression::Var(_) => Ok(Expression::Assignment(
+            // the target of an assignment, compound assignment or `++`/`--` can be any lvalue
+            // (e.g. `*p`), the type checker validates it
+            Expression::Assignment(left, right, span) => Ok(Expression::Assignment(
+                Box::new(self.fold_expr(*left)?),
+                Box::new(self.fold_expr(*right)?),
+                span,
+            )),
+            Expression::CompoundAssignment(op, left, right, span) => {
+                Ok(Expression::CompoundAssignment(
+                    op,
                     Box::new(self.fold_expr(*left)?),
                     Box::new(self.fold_expr(*right)?),
-                )),
-                _ => {
-                    error!("[semantic] invalid lvalue in assignment");
-
-                    Err("invalid lvalue".to_string())
-                }
-            },
-            Expression::Var(ref id) => {
-                let Some((unique_name, _)) = self.get_var(id) else {
-                    error!("[semantic] undeclared variable: {}", id.value());
+                    span,
+                ))
+            }
+            Expression::Postfix(op, e, span) => {
+                Ok(Expression::Postfix(op, Box::new(self.fold_expr(*e)?), span))
+            }
+            Expression::Var(ref id, span) => {
+                let Some((unique_name, _, _, _)) = self.get_var(id) else {
+                    debug!("[semantic] undeclared variable: {}", id.value());
 
-                    return Err("undeclared variable".to_string());
+                    self.recover(
+                        SemanticError::UndeclaredVariable(id.value().to_string()).at(span.clone()),
+                    )?;
+                    return Ok(Expression::Error(span));
                 };
 
-                Ok(Expression::Var(Identifier::new(unique_name)))
+                Ok(Expression::Var(Identifier::new(unique_name), span))
             }
-            Expression::Unary(op, e) => Ok(Expression::Unary(op, Box::new(self.fold_expr(*e)?))),
-            Expression::Binary(op, l, r) => Ok(Expression::Binary(
+            Expression::Cast(ty, e, span) => Ok(Expression::Cast(
+                self.resolve_type(ty, &span)?,
+                Box::new(self.fold_expr(*e)?),
+                span,
+            )),
+            Expression::Unary(op, e, span) => {
+                Ok(Expression::Unary(op, Box::new(self.fold_expr(*e)?), span))
+            }
+            Expression::Binary(op, l, r, span) => Ok(Expression::Binary(
                 op,
                 Box::new(self.fold_expr(*l)?),
                 Box::new(self.fold_expr(*r)?),
+                span,
             )),
-            Expression::Constant(c) => Ok(Expression::Constant(c)),
-            Expression::Conditional(c, t, e) => Ok(Expression::Conditional(
+            Expression::Constant(c, span) => Ok(Expression::Constant(c, span)),
+            Expression::String(s, span) => Ok(Expression::String(s, span)),
+            Expression::Conditional(c, t, e, span) => Ok(Expression::Conditional(
                 Box::new(self.fold_expr(*c)?),
                 Box::new(self.fold_expr(*t)?),
                 Box::new(self.fold_expr(*e)?),
+                span,
+            )),
+            Expression::Comma(l, r, span) => Ok(Expression::Comma(
+                Box::new(self.fold_expr(*l)?),
+                Box::new(self.fold_expr(*r)?),
+                span,
+            )),
+            Expression::FunctionCall(name, args, span) => {
+                let unique_name = self
+                    .get_var(&name)
+                    .map(|(unique_name, _, _, _)| unique_name);
+                if unique_name.is_none() {
+                    debug!("[semantic] undeclared function: {}", name.value());
+
+                    self.recover(
+                        SemanticError::UndeclaredFunction(name.value().to_string())
+                            .at(span.clone()),
+                    )?;
+                }
+
+                // the arguments are resolved even if the function isn't, for their errors
+                let args = args
+                    .into_iter()
+                    .map(|a| self.fold_expr(a))
+                    .collect::<Result<Vec<_>, CompileError>>()?;
+
+                match unique_name {
+                    Some(unique_name) => Ok(Expression::FunctionCall(
+                        Identifier::new(unique_name),
+                        args,
+                        span,
+                    )),
+                    None => Ok(Expression::Error(span)),
+                }
+            }
+            Expression::IndirectCall(callee, args, span) => Ok(Expression::IndirectCall(
+                Box::new(self.fold_expr(*callee)?),
+                args.into_iter()
+                    .map(|a| self.fold_expr(a))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+                span,
+            )),
+            Expression::Dereference(e, span) => {
+                Ok(Expression::Dereference(Box::new(self.fold_expr(*e)?), span))
+            }
+            Expression::AddressOf(e, span) => {
+                Ok(Expression::AddressOf(Box::new(self.fold_expr(*e)?), span))
+            }
+            Expression::Subscript(e, index, span) => Ok(Expression::Subscript(
+                Box::new(self.fold_expr(*e)?),
+                Box::new(self.fold_expr(*index)?),
+                span,
+            )),
+            Expression::Dot(e, member, span) => {
+                Ok(Expression::Dot(Box::new(self.fold_expr(*e)?), member, span))
+            }
+            Expression::Arrow(e, member, span) => Ok(Expression::Arrow(
+                Box::new(self.fold_expr(*e)?),
+                member,
+                span,
+            )),
+            Expression::VaArg(ap, ty, span) => Ok(Expression::VaArg(
+                Box::new(self.fold_expr(*ap)?),
+                self.resolve_type(ty, &span)?,
+                span,
             )),
+            Expression::Error(span) => Ok(Expression::Error(span)),
         }
     }
 }
@@ -0,0 +1,92 @@
+//! The tokens read by the parser.
+//!
+//! A `TokenStream` iterates over the tokens like a peekable iterator, and also knows their
+//! spans, so the parser can give every node the span from its first to its last token.
+//! It also collects the errors the parser recovered from, to report them all at the end.
+
+use crate::{
+    common::{error::CompileError, span::Span},
+    lexer::{SpannedToken, Token},
+};
+
+/// A cursor over spanned tokens. Cloning it is cheap (there are no errors in the common
+/// case), the parser does it to look ahead.
+#[derive(Clone, Debug)]
+pub struct TokenStream<'a> {
+    tokens: &'a [SpannedToken],
+    position: usize,
+    errors: Vec<CompileError>,
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(tokens: &'a [SpannedToken]) -> Self {
+        TokenStream {
+            tokens,
+            position: 0,
+            errors: vec![],
+        }
+    }
+
+    /// The number of tokens consumed.
+    pub fn offset(&self) -> usize {
+        self.position
+    }
+
+    /// Goes back (or forward) to an offset, keeping the errors recorded.
+    pub fn rewind(&mut self, offset: usize) {
+        self.position = offset.min(self.tokens.len());
+    }
+
+    /// Records an error the parser recovered from. An error that's the same as the previous
+    /// one isn't recorded twice, e.g. the missing `}` at the end of the input is missing for
+    /// every enclosing block.
+    pub fn record(&mut self, error: CompileError) {
+        if self.errors.last() != Some(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    /// Returns the errors recorded, in the order they were found.
+    pub fn take_errors(&mut self) -> Vec<CompileError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position).map(|t| &t.token)
+    }
+
+    /// The span of the next token. At the end it's the span of the last token, so the
+    /// errors about missing tokens point at the end of the input.
+    pub fn peek_span(&self) -> Span {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map(|t| t.span.clone())
+            .unwrap_or_default()
+    }
+
+    /// The span of the last token consumed.
+    pub fn last_span(&self) -> Span {
+        self.position
+            .checked_sub(1)
+            .and_then(|i| self.tokens.get(i))
+            .map(|t| t.span.clone())
+            .unwrap_or_default()
+    }
+
+    /// The span from `start` to the end of the last token consumed.
+    pub fn span_from(&self, start: &Span) -> Span {
+        start.to(&self.last_span())
+    }
+}
+
+impl<'a> Iterator for TokenStream<'a> {
+    type Item = &'a Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.get(self.position).map(|t| &t.token)?;
+        self.position += 1;
+
+        Some(token)
+    }
+}
@@ -0,0 +1,140 @@
+//! Typedef names visible while parsing.
+//!
+//! C can't be parsed without knowing which identifiers name types: `foo * bar;` declares
+//! `bar` when `foo` is a typedef and multiplies `foo` by `bar` otherwise. The parser records
+//! every typedef in a stack of scopes that follows the blocks, along with the ordinary
+//! identifiers (variables, functions, parameters and enumerators), which share the same
+//! namespace and hide the typedefs of the enclosing scopes. The value of an enumerator is
+//! kept when it's known, so it can size an array.
+
+use std::collections::HashMap;
+
+use log::debug;
+
+use crate::c_ast::ast::Type;
+use crate::common::error::ParseError;
+
+/// What a name declared in a scope stands for.
+#[derive(Clone, Debug, PartialEq)]
+enum Name {
+    Typedef(Type),
+    Ordinary,
+    // an enumerator whose value is a constant the parser can evaluate
+    Enumerator(i32),
+}
+
+/// The scopes from the file scope to the innermost block. Each one maps a name to what it
+/// stands for.
+#[derive(Clone, Debug)]
+pub struct TypedefTable(Vec<HashMap<String, Name>>);
+
+impl Default for TypedefTable {
+    fn default() -> Self {
+        TypedefTable(vec![HashMap::new()])
+    }
+}
+
+impl TypedefTable {
+    /// Returns a table with only the file scope.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.0.push(HashMap::new());
+    }
+
+    /// Leaves the innermost scope, the file scope is never left.
+    pub fn exit_scope(&mut self) {
+        if self.0.len() > 1 {
+            let _ = self.0.pop();
+        }
+    }
+
+    /// The number of scopes, the file scope included.
+    pub fn depth(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Leaves the scopes entered since the table had `depth` scopes, e.g. the scopes of a
+    /// declaration that failed to parse.
+    pub fn exit_to(&mut self, depth: usize) {
+        self.0.truncate(depth.max(1));
+    }
+
+    /// Returns the type a name stands for, `None` if it isn't a typedef name in the current
+    /// scope (it's undeclared or the innermost declaration is an ordinary identifier).
+    pub fn get(&self, name: &str) -> Option<&Type> {
+        match self.lookup(name)? {
+            Name::Typedef(ty) => Some(ty),
+            _ => None,
+        }
+    }
+
+    pub fn is_typedef(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns the value of an enumerator, `None` if the name isn't one in the current scope
+    /// or its value isn't known.
+    pub fn enumerator(&self, name: &str) -> Option<i32> {
+        match self.lookup(name)? {
+            Name::Enumerator(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Declares a typedef name in the innermost scope. It can be redeclared in the same
+    /// scope with the same type, but not as an ordinary identifier.
+    pub fn declare_typedef(&mut self, name: &str, ty: Type) -> Result<(), ParseError> {
+        match self.current().get(name) {
+            Some(Name::Ordinary | Name::Enumerator(_)) => {
+                debug!("[parser] {name} redeclared as a typedef");
+
+                Err(ParseError::ConflictingTypedef(name.to_string()))
+            }
+            Some(Name::Typedef(old)) if *old != ty => {
+                debug!("[parser] conflicting types for typedef {name}: {old:?} and {ty:?}");
+
+                Err(ParseError::ConflictingTypedef(name.to_string()))
+            }
+            _ => {
+                self.current().insert(name.to_string(), Name::Typedef(ty));
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Declares an ordinary identifier in the innermost scope, it hides any typedef with
+    /// the same name from the enclosing scopes.
+    pub fn declare_ordinary(&mut self, name: &str) -> Result<(), ParseError> {
+        self.declare(name, Name::Ordinary)
+    }
+
+    /// Declares an enumerator in the innermost scope, with its value if it's known.
+    pub fn declare_enumerator(&mut self, name: &str, value: Option<i32>) -> Result<(), ParseError> {
+        self.declare(name, value.map_or(Name::Ordinary, Name::Enumerator))
+    }
+
+    fn declare(&mut self, name: &str, declared: Name) -> Result<(), ParseError> {
+        if let Some(Name::Typedef(_)) = self.current().get(name) {
+            debug!("[parser] typedef {name} redeclared as an identifier");
+
+            return Err(ParseError::ConflictingTypedef(name.to_string()));
+        }
+        self.current().insert(name.to_string(), declared);
+
+        Ok(())
+    }
+
+    /// Returns what a name stands for in the innermost scope that declares it.
+    fn lookup(&self, name: &str) -> Option<&Name> {
+        self.0.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn current(&mut self) -> &mut HashMap<String, Name> {
+        let last = self.0.len() - 1;
+        &mut self.0[last]
+    }
+}
@@ -3,17 +3,44 @@
 //! This module defines the AST representing x86_64 assembly instructions,
 //! used as the final IR before emitting textual assembly.
 
+use crate::{
+    c_ast::ast::Type,
+    common::symbols::{StaticInit, SymbolTable},
+};
+
 #[derive(Clone)]
 pub struct AsmProgram {
-    pub function_definition: AsmFunctionDefinition,
+    pub function_definitions: Vec<AsmFunctionDefinition>,
+    pub static_variables: Vec<AsmStaticVariable>,
+    /// Read-only constants, the doubles used by the functions and the string literals
+    pub static_constants: Vec<AsmStaticConstant>,
+    pub symbols: SymbolTable,
 }
 
 #[derive(Clone)]
 pub struct AsmFunctionDefinition {
     pub name: AsmIdentifier,
+    pub global: bool,
     pub instructions: Vec<AsmInstruction>,
 }
 
+#[derive(Clone, Debug)]
+pub struct AsmStaticVariable {
+    pub name: AsmIdentifier,
+    pub global: bool,
+    pub alignment: i32,
+    pub init: StaticInit,
+}
+
+/// A constant emitted to the read-only data section, doubles can't be immediate operands
+/// and string literals are arrays.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmStaticConstant {
+    pub name: AsmIdentifier,
+    pub alignment: i32,
+    pub init: StaticInit,
+}
+
 #[derive(Clone, Hash, Eq, PartialEq, Debug)]
 pub struct AsmIdentifier {
     pub value: String,
@@ -22,20 +49,55 @@ pub struct AsmIdentifier {
 #[derive(Clone, Debug, PartialEq)]
 pub enum AsmInstruction {
     Comment(String),
-    Mov(AsmOperand, AsmOperand),
-    Unary(AsmUnaryOperator, AsmOperand),
-    Binary(AsmBinaryOperator, AsmOperand, AsmOperand),
-    Cmp(AsmOperand, AsmOperand),
-    Idiv(AsmOperand),
-    Cdq,
+    Mov(AsmType, AsmOperand, AsmOperand),
+    /// Sign extends a value of the first type into the second, wider one
+    Movsx(AsmType, AsmType, AsmOperand, AsmOperand),
+    /// Zero extends a value of the first type into the second, wider one. A longword is
+    /// extended with a plain `movl`, the instruction fixer takes care of it
+    MovZeroExtend(AsmType, AsmType, AsmOperand, AsmOperand),
+    /// Converts a double to an integer of the given size, truncating towards zero
+    Cvttsd2si(AsmType, AsmOperand, AsmOperand),
+    /// Converts an integer of the given size to a double
+    Cvtsi2sd(AsmType, AsmOperand, AsmOperand),
+    /// Loads the address of the source operand, which must be in memory
+    Lea(AsmOperand, AsmOperand),
+    Unary(AsmUnaryOperator, AsmType, AsmOperand),
+    Binary(AsmBinaryOperator, AsmType, AsmOperand, AsmOperand),
+    Cmp(AsmType, AsmOperand, AsmOperand),
+    Idiv(AsmType, AsmOperand),
+    /// Unsigned division
+    Div(AsmType, AsmOperand),
+    Cdq(AsmType),
     Jmp(AsmIdentifier),
     JmpCC(AsmCondCode, AsmIdentifier),
+    /// Jumps to the target at the index held by the register. The table of targets is
+    /// emitted right after the jump, under the given label, as 4-byte offsets from its start
+    /// so it needs no relocations
+    JmpTable(Reg, AsmIdentifier, Vec<AsmIdentifier>),
     SetCC(AsmCondCode, AsmOperand),
     Label(AsmIdentifier),
     AllocateStack(i32),
+    DeallocateStack(i32),
+    Push(AsmOperand),
+    Call(AsmIdentifier),
+    /// Calls the function whose address is held by the register
+    IndirectCall(Reg),
     Ret,
 }
 
+/// Size of the operands of an instruction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AsmType {
+    /// 1 byte (char)
+    Byte,
+    /// 4 bytes (int)
+    Longword,
+    /// 8 bytes (long)
+    Quadword,
+    /// 8 bytes, kept in XMM registers
+    Double,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum AsmUnaryOperator {
     Neg,
@@ -54,14 +116,32 @@ pub enum AsmBinaryOperator {
     BitwiseXor,
     LeftShift,
     RightShift,
+    /// Right shift of an unsigned value, fills the high bits with zeros
+    LogicalRightShift,
+    /// Division of doubles, integer division has its own instructions
+    DivDouble,
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub enum AsmOperand {
-    Imm(i32),
+    Imm(i64),
     Register(Reg),
     Pseudo(AsmIdentifier),
     Stack(i32),
+    /// A variable with static storage duration (or a part of it, at the given offset from its
+    /// start), accessed relative to RIP
+    Data(AsmIdentifier, i32),
+    /// A double constant (by its bits), read from the read-only data section
+    DoubleConstant(u64),
+    /// The entry of the global offset table that holds the address of a function, which
+    /// may be defined in a shared library
+    GotEntry(AsmIdentifier),
+    /// The object at the given offset from the address in the register
+    Memory(Reg, i32),
+    /// The object at base + index * scale, with the base and the index in registers
+    Indexed(Reg, Reg, i32),
+    /// A part of an aggregate pseudo-register, at the given offset from its start
+    PseudoMem(AsmIdentifier, i32),
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
@@ -70,8 +150,25 @@ pub enum Reg {
     DX,
     CX,
     CL,
+    DI,
+    SI,
+    R8,
+    R9,
     R10,
     R11,
+    /// The stack pointer, structures passed on the stack are copied relative to it
+    SP,
+    // SSE registers, XMM0-7 pass double arguments and XMM14-15 are scratch registers
+    XMM0,
+    XMM1,
+    XMM2,
+    XMM3,
+    XMM4,
+    XMM5,
+    XMM6,
+    XMM7,
+    XMM14,
+    XMM15,
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
@@ -82,18 +179,190 @@ pub enum AsmCondCode {
     GE,
     L,
     LE,
+    // unsigned comparisons
+    A,
+    AE,
+    B,
+    BE,
+    // parity flag, set when a comparison of doubles is unordered (one of them is NaN)
+    P,
+    NP,
 }
 
 impl AsmProgram {
-    pub fn new(function_definition: AsmFunctionDefinition) -> Self {
+    pub fn new(
+        function_definitions: Vec<AsmFunctionDefinition>,
+        static_variables: Vec<AsmStaticVariable>,
+    ) -> Self {
         AsmProgram {
-            function_definition,
+            function_definitions,
+            static_variables,
+            static_constants: vec![],
+            symbols: SymbolTable::new(),
         }
     }
+
+    pub fn with_constants(self, static_constants: Vec<AsmStaticConstant>) -> Self {
+        AsmProgram {
+            static_constants,
+            ..self
+        }
+    }
+
+    pub fn with_symbols(self, symbols: SymbolTable) -> Self {
+        AsmProgram { symbols, ..self }
+    }
 }
 
 impl AsmFunctionDefinition {
-    pub fn new(name: AsmIdentifier, instructions: Vec<AsmInstruction>) -> Self {
-        AsmFunctionDefinition { name, instructions }
+    pub fn new(name: AsmIdentifier, global: bool, instructions: Vec<AsmInstruction>) -> Self {
+        AsmFunctionDefinition {
+            name,
+            global,
+            instructions,
+        }
+    }
+}
+
+impl AsmStaticVariable {
+    pub fn new(name: AsmIdentifier, global: bool, alignment: i32, init: StaticInit) -> Self {
+        AsmStaticVariable {
+            name,
+            global,
+            alignment,
+            init,
+        }
+    }
+}
+
+impl AsmStaticConstant {
+    /// Returns the constant holding a double. `-0.0` is 16-byte aligned because it's used
+    /// as the mask of `xorpd`, which reads 16 bytes.
+    pub fn double(bits: u64) -> Self {
+        let alignment = if bits == (-0.0f64).to_bits() { 16 } else { 8 };
+        AsmStaticConstant {
+            name: AsmIdentifier::double_constant(bits),
+            alignment,
+            init: StaticInit::DoubleInit(f64::from_bits(bits)),
+        }
+    }
+
+    /// Returns every double constant used by the functions, without duplicates.
+    pub fn collect(functions: &[AsmFunctionDefinition]) -> Vec<Self> {
+        let mut bits = vec![];
+        for operand in functions
+            .iter()
+            .flat_map(|f| &f.instructions)
+            .flat_map(|i| i.operands())
+        {
+            if let AsmOperand::DoubleConstant(b) = operand
+                && !bits.contains(b)
+            {
+                bits.push(*b);
+            }
+        }
+
+        bits.into_iter().map(AsmStaticConstant::double).collect()
+    }
+}
+
+impl AsmIdentifier {
+    /// Name of the constant holding a double, the same value always gets the same name.
+    pub fn double_constant(bits: u64) -> Self {
+        AsmIdentifier {
+            value: format!("double.{bits:x}"),
+        }
+    }
+}
+
+impl AsmInstruction {
+    /// Returns the operands of the instruction, in order.
+    pub fn operands(&self) -> Vec<&AsmOperand> {
+        use AsmInstruction::*;
+        match self {
+            Mov(_, src, dst)
+            | Movsx(_, _, src, dst)
+            | MovZeroExtend(_, _, src, dst)
+            | Cvttsd2si(_, src, dst)
+            | Cvtsi2sd(_, src, dst)
+            | Lea(src, dst)
+            | Binary(_, _, src, dst)
+            | Cmp(_, src, dst) => vec![src, dst],
+            Unary(_, _, operand)
+            | Idiv(_, operand)
+            | Div(_, operand)
+            | SetCC(_, operand)
+            | Push(operand) => vec![operand],
+            Comment(_) | Cdq(_) | Jmp(_) | JmpCC(..) | JmpTable(..) | Label(_)
+            | AllocateStack(_) | DeallocateStack(_) | Call(_) | IndirectCall(_) | Ret => vec![],
+        }
+    }
+}
+
+impl AsmType {
+    /// Size in bytes, values are aligned to their size as well
+    pub fn size(&self) -> i32 {
+        match self {
+            AsmType::Byte => 1,
+            AsmType::Longword => 4,
+            AsmType::Quadword | AsmType::Double => 8,
+        }
+    }
+}
+
+impl From<&Type> for AsmType {
+    fn from(ty: &Type) -> Self {
+        match ty.unqualified() {
+            Type::Long | Type::ULong | Type::Pointer(_) => AsmType::Quadword,
+            Type::Double => AsmType::Double,
+            Type::Char | Type::SChar | Type::UChar => AsmType::Byte,
+            _ => AsmType::Longword,
+        }
+    }
+}
+
+impl AsmCondCode {
+    /// Returns the condition code of the same comparison between unsigned values.
+    pub fn to_unsigned(self) -> Self {
+        match self {
+            AsmCondCode::G => AsmCondCode::A,
+            AsmCondCode::GE => AsmCondCode::AE,
+            AsmCondCode::L => AsmCondCode::B,
+            AsmCondCode::LE => AsmCondCode::BE,
+            other => other,
+        }
+    }
+}
+
+impl AsmOperand {
+    /// Whether the operand lives in memory (x86_64 allows at most one per instruction)
+    pub fn is_memory(&self) -> bool {
+        matches!(
+            self,
+            AsmOperand::Stack(_)
+                | AsmOperand::Data(..)
+                | AsmOperand::DoubleConstant(_)
+                | AsmOperand::GotEntry(_)
+                | AsmOperand::Memory(..)
+                | AsmOperand::Indexed(..)
+                | AsmOperand::PseudoMem(..)
+        )
+    }
+
+    pub fn is_register(&self) -> bool {
+        matches!(self, AsmOperand::Register(_))
+    }
+
+    /// Returns the operand `bytes` further in memory, to access a part of an aggregate.
+    /// A pseudo-register becomes the part of it at that offset.
+    pub fn offset_by(&self, bytes: i32) -> AsmOperand {
+        match self {
+            AsmOperand::Pseudo(id) => AsmOperand::PseudoMem(id.clone(), bytes),
+            AsmOperand::PseudoMem(id, offset) => AsmOperand::PseudoMem(id.clone(), offset + bytes),
+            AsmOperand::Data(id, offset) => AsmOperand::Data(id.clone(), offset + bytes),
+            AsmOperand::Memory(reg, offset) => AsmOperand::Memory(reg.clone(), offset + bytes),
+            AsmOperand::Stack(offset) => AsmOperand::Stack(offset + bytes),
+            _ => panic!("offset of an operand that isn't in memory: {self:?}"),
+        }
     }
 }
@@ -1,9 +1,10 @@
 use std::fmt::{self};
 
 use crate::codegen::x64::ast::{
-    AsmBinaryOperator, AsmCondCode, AsmFunctionDefinition, AsmInstruction, AsmOperand, AsmProgram,
-    AsmUnaryOperator, Reg,
+    AsmBinaryOperator, AsmCondCode, AsmFunctionDefinition, AsmIdentifier, AsmInstruction,
+    AsmOperand, AsmProgram, AsmStaticConstant, AsmStaticVariable, AsmType, AsmUnaryOperator, Reg,
 };
+use crate::common::symbols::StaticInit;
 
 const FUNCTION_PROLOGUE: &[&str] = &[
     "pushq %rbp # initialize stack frame with base pointer",
@@ -12,6 +13,36 @@ const FUNCTION_PROLOGUE: &[&str] = &[
 
 const FUNCTION_EPILOGUE: &[&str] = &["movq %rbp, %rsp", "popq %rbp", "ret"];
 
+/// Returns the assembly name of a global symbol.
+/// macOS prefixes every symbol with an underscore, Linux uses them as they are.
+fn symbol_name(name: &str) -> String {
+    if cfg!(target_os = "macos") {
+        format!("_{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns the assembly name of a local label.
+fn local_label(name: &str) -> String {
+    if cfg!(target_os = "macos") {
+        format!("L{name}")
+    } else {
+        format!(".L{name}")
+    }
+}
+
+/// Returns the operand of a call instruction.
+/// On Linux we go through the PLT so the linker can resolve functions from shared libraries
+/// (e.g. libc), it works for functions defined in the same file as well.
+fn call_target(name: &str) -> String {
+    if cfg!(target_os = "macos") {
+        symbol_name(name)
+    } else {
+        format!("{name}@PLT")
+    }
+}
+
 pub struct Emitter<W: fmt::Write> {
     out: W,
     indent: usize,
@@ -58,7 +89,20 @@ impl<W: fmt::Write> Emitter<W> {
 
 impl AsmProgram {
     pub fn emit_to<W: fmt::Write>(&self, em: &mut Emitter<W>) -> fmt::Result {
-        self.function_definition.emit_to(em)
+        for function in &self.function_definitions {
+            function.emit_to(em)?;
+        }
+        for variable in &self.static_variables {
+            variable.emit_to(em)?;
+        }
+        for constant in &self.static_constants {
+            constant.emit_to(em)?;
+        }
+        if !cfg!(target_os = "macos") {
+            // tell the linker this code doesn't need an executable stack
+            em.line(".section .note.GNU-stack,\"\",@progbits")?;
+        }
+        Ok(())
     }
 
     pub fn to_string_asm(&self) -> Result<String, fmt::Error> {
@@ -70,8 +114,11 @@ impl AsmProgram {
 
 impl AsmFunctionDefinition {
     pub fn emit_to<W: fmt::Write>(&self, em: &mut Emitter<W>) -> fmt::Result {
-        em.line(&format!(".globl _{}", self.name.value))?;
-        em.line(&format!("_{}:", self.name.value))?;
+        if self.global {
+            em.line(&format!(".globl {}", symbol_name(&self.name.value)))?;
+        }
+        em.line(".text")?;
+        em.line(&format!("{}:", symbol_name(&self.name.value)))?;
         em.indented(|em| {
             for line in FUNCTION_PROLOGUE {
                 em.line(line)?;
@@ -84,28 +131,163 @@ impl AsmFunctionDefinition {
     }
 }
 
+impl AsmStaticVariable {
+    pub fn emit_to<W: fmt::Write>(&self, em: &mut Emitter<W>) -> fmt::Result {
+        if self.global {
+            em.line(&format!(".globl {}", symbol_name(&self.name.value)))?;
+        }
+        // zero-initialized variables go to .bss, so they don't take space in the object file
+        em.line(if self.init.is_zero() { ".bss" } else { ".data" })?;
+        em.line(&format!(".balign {}", self.alignment))?;
+        em.line(&format!("{}:", symbol_name(&self.name.value)))?;
+        em.indented(|em| emit_static_init(em, &self.init))
+    }
+}
+
+fn emit_static_init<W: fmt::Write>(em: &mut Emitter<W>, init: &StaticInit) -> fmt::Result {
+    match init {
+        // all-zero values of any size are emitted as zero bytes
+        _ if init.is_zero() => em.line(&format!(".zero {}", init.size())),
+        // doubles are written by their bits, so they're exactly the value we computed
+        StaticInit::DoubleInit(d) => em.line(&format!(".quad {}", d.to_bits())),
+        StaticInit::IntInit(i) => em.line(&format!(".long {i}")),
+        StaticInit::UIntInit(i) => em.line(&format!(".long {i}")),
+        StaticInit::LongInit(i) => em.line(&format!(".quad {i}")),
+        StaticInit::ULongInit(i) => em.line(&format!(".quad {i}")),
+        StaticInit::CharInit(c) => em.line(&format!(".byte {c}")),
+        StaticInit::UCharInit(c) => em.line(&format!(".byte {c}")),
+        StaticInit::ZeroInit(bytes) => em.line(&format!(".zero {bytes}")),
+        // .asciz adds the null terminator
+        StaticInit::StringInit(s, true) => em.line(&format!(".asciz \"{}\"", escape_string(s))),
+        StaticInit::StringInit(s, false) => em.line(&format!(".ascii \"{}\"", escape_string(s))),
+        StaticInit::PointerInit(name) => em.line(&format!(".quad {}", symbol_name(name))),
+        StaticInit::ArrayInit(inits) => inits.iter().try_for_each(|i| emit_static_init(em, i)),
+    }
+}
+
+/// Escapes a string for the `.ascii` and `.asciz` directives, characters that aren't
+/// printable are written as octal escapes.
+fn escape_string(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'"' => "\\\"".to_string(),
+            b'\\' => "\\\\".to_string(),
+            b' '..=b'~' => (b as char).to_string(),
+            _ => format!("\\{b:03o}"),
+        })
+        .collect()
+}
+
+impl AsmStaticConstant {
+    pub fn emit_to<W: fmt::Write>(&self, em: &mut Emitter<W>) -> fmt::Result {
+        // string literals are accessed like static variables, through their symbol name
+        if let StaticInit::StringInit(..) = self.init {
+            em.line(if cfg!(target_os = "macos") {
+                ".cstring"
+            } else {
+                ".section .rodata"
+            })?;
+            em.line(&format!(".balign {}", self.alignment))?;
+            em.line(&format!("{}:", symbol_name(&self.name.value)))?;
+            return em.indented(|em| emit_static_init(em, &self.init));
+        }
+
+        // macOS keeps constants in literal sections, sized by the alignment
+        if cfg!(target_os = "macos") {
+            em.line(&format!(".literal{}", self.alignment))?;
+        } else {
+            em.line(".section .rodata")?;
+        }
+        em.line(&format!(".balign {}", self.alignment))?;
+        em.line(&format!("{}:", local_label(&self.name.value)))?;
+        let StaticInit::DoubleInit(d) = self.init else {
+            panic!("unsupported static constant: {:?}", self.init);
+        };
+        em.indented(|em| {
+            em.line(&format!(".quad {}", d.to_bits()))?;
+            if cfg!(target_os = "macos") && self.alignment == 16 {
+                em.line(".quad 0")?;
+            }
+            Ok(())
+        })
+    }
+}
+
 impl AsmInstruction {
     pub fn emit_to<W: fmt::Write>(&self, em: &mut Emitter<W>) -> fmt::Result {
         use AsmInstruction::*;
         use AsmOperand::*;
         match self {
             Comment(s) => em.line(&format!("# {s}")),
-            Mov(src, dst) => self.emit_binary_op(em, "movl", src, dst),
-            Unary(op, x) => em.line(&format!("{op} {x}")),
+            // doubles use SSE instructions, with their own opcodes
+            Binary(op, AsmType::Double, src, dst) => {
+                self.emit_binary_op(em, op.double_opcode(), AsmType::Double, src, dst)
+            }
+            Cmp(AsmType::Double, op_1, op_2) => {
+                self.emit_binary_op(em, "comisd", AsmType::Double, op_1, op_2)
+            }
+            Cvttsd2si(ty, src, dst) => {
+                em.line(&format!("cvttsd2si{ty} {src}, {}", dst.fmt_sized(*ty)))
+            }
+            Cvtsi2sd(ty, src, dst) => {
+                em.line(&format!("cvtsi2sd{ty} {}, {dst}", src.fmt_sized(*ty)))
+            }
+            Mov(ty, src, dst) => self.emit_binary_op(em, &format!("mov{ty}"), *ty, src, dst),
+            Lea(src, dst) => self.emit_binary_op(em, "leaq", AsmType::Quadword, src, dst),
+            Movsx(src_ty, dst_ty, src, dst) => em.line(&format!(
+                "movs{src_ty}{dst_ty} {}, {}",
+                src.fmt_sized(*src_ty),
+                dst.fmt_sized(*dst_ty)
+            )),
+            // movl zeroes the upper half of the destination register
+            MovZeroExtend(AsmType::Longword, _, src, dst) => {
+                self.emit_binary_op(em, "movl", AsmType::Longword, src, dst)
+            }
+            MovZeroExtend(src_ty, dst_ty, src, dst) => em.line(&format!(
+                "movz{src_ty}{dst_ty} {}, {}",
+                src.fmt_sized(*src_ty),
+                dst.fmt_sized(*dst_ty)
+            )),
+            Unary(op, ty, x) => em.line(&format!("{op}{ty} {}", x.fmt_sized(*ty))),
             AllocateStack(n) => em.line(&format!("subq ${n}, %rsp # allocate stack space")),
+            DeallocateStack(n) => em.line(&format!("addq ${n}, %rsp # deallocate stack space")),
+            Push(Register(reg)) => em.line(&format!("pushq {}", reg.fmt_64bit())),
+            Push(op) => em.line(&format!("pushq {op}")),
+            Call(name) => em.line(&format!("call {}", call_target(&name.value))),
+            IndirectCall(reg) => em.line(&format!("call *{}", reg.fmt_64bit())),
             Ret => self.emit_lines(em, FUNCTION_EPILOGUE),
-            Binary(op, src, dst) => self.emit_binary_op(em, &op.to_string(), src, dst),
-            Idiv(op) => em.line(&format!("idivl {op}")),
-            Cdq => em.line("cdq"),
-            Cmp(op_1, op_2) => self.emit_binary_op(em, "cmpl", op_1, op_2),
-            Jmp(label) => em.line(&format!("jmp L{}", label.value)),
-            JmpCC(cond_code, id) => em.line(&format!("j{} L{}", cond_code, id.value)),
+            Binary(op, ty, src, dst) => {
+                self.emit_binary_op(em, &format!("{op}{ty}"), *ty, src, dst)
+            }
+            Idiv(ty, op) => em.line(&format!("idiv{ty} {}", op.fmt_sized(*ty))),
+            Div(ty, op) => em.line(&format!("div{ty} {}", op.fmt_sized(*ty))),
+            Cdq(AsmType::Longword) => em.line("cdq"),
+            Cdq(AsmType::Quadword) => em.line("cqo"),
+            Cdq(ty @ (AsmType::Byte | AsmType::Double)) => panic!("cdq on a {ty:?}"),
+            Cmp(ty, op_1, op_2) => self.emit_binary_op(em, &format!("cmp{ty}"), *ty, op_1, op_2),
+            Jmp(label) => em.line(&format!("jmp {}", local_label(&label.value))),
+            JmpCC(cond_code, id) => em.line(&format!("j{} {}", cond_code, local_label(&id.value))),
+            JmpTable(reg, table, targets) => {
+                let (index, table) = (reg.fmt_64bit(), local_label(&table.value));
+                em.line(&format!("leaq {table}(%rip), %r11"))?;
+                em.line(&format!("movslq (%r11, {index}, 4), {index}"))?;
+                em.line(&format!("addq %r11, {index}"))?;
+                em.line(&format!("jmp *{index}"))?;
+                em.line(".balign 4")?;
+                em.line(&format!("{table}:"))?;
+                for target in targets {
+                    em.line(&format!(".long {} - {table}", local_label(&target.value)))?;
+                }
+                Ok(())
+            }
             SetCC(cond_code, op) => match op {
-                Stack(_) => em.line(&format!("set{cond_code} {op}")),
+                Stack(_) | Data(..) | Memory(..) | Indexed(..) => {
+                    em.line(&format!("set{cond_code} {op}"))
+                }
                 Register(_) => em.line(&format!("set{} {}", cond_code, op.byte_fmt())),
                 _ => panic!("this should never happen"),
             },
-            Label(label) => em.line(&format!("L{}: # LABEL", label.value)),
+            Label(label) => em.line(&format!("{}: # LABEL", local_label(&label.value))),
         }
     }
 
@@ -113,10 +295,15 @@ impl AsmInstruction {
         &self,
         em: &mut Emitter<W>,
         opcode: &str,
+        ty: AsmType,
         src: &AsmOperand,
         dst: &AsmOperand,
     ) -> fmt::Result {
-        em.line(&format!("{opcode} {src}, {dst}"))
+        em.line(&format!(
+            "{opcode} {}, {}",
+            src.fmt_sized(ty),
+            dst.fmt_sized(ty)
+        ))
     }
 
     fn emit_lines<W: fmt::Write>(&self, em: &mut Emitter<W>, lines: &[&str]) -> fmt::Result {
@@ -130,8 +317,8 @@ impl AsmInstruction {
 impl std::fmt::Display for AsmUnaryOperator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AsmUnaryOperator::Neg => write!(f, "negl"),
-            AsmUnaryOperator::Not => write!(f, "notl"),
+            AsmUnaryOperator::Neg => write!(f, "neg"),
+            AsmUnaryOperator::Not => write!(f, "not"),
         }
     }
 }
@@ -139,19 +326,46 @@ impl std::fmt::Display for AsmUnaryOperator {
 impl std::fmt::Display for AsmBinaryOperator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            // instructions operate on 32-bit values, so they get l suffixes
-            AsmBinaryOperator::Add => write!(f, "addl"),
-            AsmBinaryOperator::Sub => write!(f, "subl"),
-            AsmBinaryOperator::Mult => write!(f, "imull"),
+            // the size suffix is added by the emitter, based on the operand type
+            AsmBinaryOperator::Add => write!(f, "add"),
+            AsmBinaryOperator::Sub => write!(f, "sub"),
+            AsmBinaryOperator::Mult => write!(f, "imul"),
             // bitwise operators
-            AsmBinaryOperator::BitwiseAnd => write!(f, "andl"),
-            AsmBinaryOperator::BitwiseOr => write!(f, "orl"),
-            AsmBinaryOperator::BitwiseXor => write!(f, "xorl"),
-            AsmBinaryOperator::LeftShift => write!(f, "shll"),
+            AsmBinaryOperator::BitwiseAnd => write!(f, "and"),
+            AsmBinaryOperator::BitwiseOr => write!(f, "or"),
+            AsmBinaryOperator::BitwiseXor => write!(f, "xor"),
+            AsmBinaryOperator::LeftShift => write!(f, "shl"),
             // right bitshift of negative value is implementation-defined;
             // we follow GCC and use sign extension
             // (see https://gcc.gnu.org/onlinedocs/gcc/Integers-implementation.html)
-            AsmBinaryOperator::RightShift => write!(f, "sarl"),
+            AsmBinaryOperator::RightShift => write!(f, "sar"),
+            AsmBinaryOperator::LogicalRightShift => write!(f, "shr"),
+            AsmBinaryOperator::DivDouble => write!(f, "div"),
+        }
+    }
+}
+
+impl AsmBinaryOperator {
+    /// Returns the SSE opcode of the operator on doubles.
+    fn double_opcode(&self) -> &'static str {
+        match self {
+            AsmBinaryOperator::Add => "addsd",
+            AsmBinaryOperator::Sub => "subsd",
+            AsmBinaryOperator::Mult => "mulsd",
+            AsmBinaryOperator::DivDouble => "divsd",
+            AsmBinaryOperator::BitwiseXor => "xorpd",
+            _ => panic!("invalid operator on doubles: {self:?}"),
+        }
+    }
+}
+
+impl std::fmt::Display for AsmType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmType::Byte => write!(f, "b"),
+            AsmType::Longword => write!(f, "l"),
+            AsmType::Quadword => write!(f, "q"),
+            AsmType::Double => write!(f, "sd"),
         }
     }
 }
@@ -161,13 +375,38 @@ impl std::fmt::Display for AsmOperand {
         match self {
             AsmOperand::Register(reg) => write!(f, "{}", reg.fmt_32bit()),
             AsmOperand::Stack(offset) => write!(f, "{offset}(%rbp)"),
+            AsmOperand::Memory(reg, offset) => write!(f, "{offset}({})", reg.fmt_64bit()),
+            AsmOperand::Indexed(base, index, scale) => {
+                write!(f, "({}, {}, {scale})", base.fmt_64bit(), index.fmt_64bit())
+            }
+            AsmOperand::PseudoMem(id, offset) => write!(f, "{}+{offset}", id.value),
             AsmOperand::Imm(num) => write!(f, "${num}"),
+            // static variables are addressed relative to the instruction pointer
+            AsmOperand::Data(id, 0) => write!(f, "{}(%rip)", symbol_name(&id.value)),
+            AsmOperand::Data(id, offset) => {
+                write!(f, "{}+{offset}(%rip)", symbol_name(&id.value))
+            }
+            AsmOperand::GotEntry(id) => write!(f, "{}@GOTPCREL(%rip)", symbol_name(&id.value)),
             AsmOperand::Pseudo(id) => write!(f, "{}", id.value),
+            AsmOperand::DoubleConstant(bits) => write!(
+                f,
+                "{}(%rip)",
+                local_label(&AsmIdentifier::double_constant(*bits).value)
+            ),
         }
     }
 }
 
 impl AsmOperand {
+    /// Formats the operand for an instruction of the given size, only registers differ.
+    pub fn fmt_sized(&self, ty: AsmType) -> String {
+        match (self, ty) {
+            (AsmOperand::Register(reg), AsmType::Quadword) => reg.fmt_64bit().to_string(),
+            (AsmOperand::Register(reg), AsmType::Byte) => reg.fmt_8bit().to_string(),
+            _ => self.to_string(),
+        }
+    }
+
     pub fn byte_fmt(&self) -> &str {
         match self {
             AsmOperand::Register(reg) => reg.fmt_8bit(),
@@ -183,8 +422,50 @@ impl Reg {
             Reg::DX => "%edx",
             Reg::CX => "%ecx",
             Reg::CL => "%cl",
+            Reg::DI => "%edi",
+            Reg::SI => "%esi",
+            Reg::R8 => "%r8d",
+            Reg::R9 => "%r9d",
             Reg::R10 => "%r10d",
             Reg::R11 => "%r11d",
+            Reg::SP => "%esp",
+            Reg::XMM0 => "%xmm0",
+            Reg::XMM1 => "%xmm1",
+            Reg::XMM2 => "%xmm2",
+            Reg::XMM3 => "%xmm3",
+            Reg::XMM4 => "%xmm4",
+            Reg::XMM5 => "%xmm5",
+            Reg::XMM6 => "%xmm6",
+            Reg::XMM7 => "%xmm7",
+            Reg::XMM14 => "%xmm14",
+            Reg::XMM15 => "%xmm15",
+        }
+    }
+
+    pub fn fmt_64bit(&self) -> &'static str {
+        match self {
+            Reg::AX => "%rax",
+            Reg::DX => "%rdx",
+            Reg::CX => "%rcx",
+            Reg::DI => "%rdi",
+            Reg::SI => "%rsi",
+            Reg::R8 => "%r8",
+            Reg::R9 => "%r9",
+            Reg::R10 => "%r10",
+            Reg::R11 => "%r11",
+            Reg::SP => "%rsp",
+            // shift counts are always read from CL
+            Reg::CL => "%cl",
+            Reg::XMM0 => "%xmm0",
+            Reg::XMM1 => "%xmm1",
+            Reg::XMM2 => "%xmm2",
+            Reg::XMM3 => "%xmm3",
+            Reg::XMM4 => "%xmm4",
+            Reg::XMM5 => "%xmm5",
+            Reg::XMM6 => "%xmm6",
+            Reg::XMM7 => "%xmm7",
+            Reg::XMM14 => "%xmm14",
+            Reg::XMM15 => "%xmm15",
         }
     }
 
@@ -192,8 +473,14 @@ impl Reg {
         match self {
             Reg::AX => "%al",
             Reg::DX => "%dl",
+            Reg::CX | Reg::CL => "%cl",
+            Reg::DI => "%dil",
+            Reg::SI => "%sil",
+            Reg::R8 => "%r8b",
+            Reg::R9 => "%r9b",
             Reg::R10 => "%r10b",
             Reg::R11 => "%r11b",
+            Reg::SP => "%spl",
             _ => panic!("fmt_8bit() called on non-byte register: {self:?}"),
         }
     }
@@ -208,6 +495,12 @@ impl std::fmt::Display for AsmCondCode {
             AsmCondCode::LE => write!(f, "le"),
             AsmCondCode::G => write!(f, "g"),
             AsmCondCode::GE => write!(f, "ge"),
+            AsmCondCode::A => write!(f, "a"),
+            AsmCondCode::AE => write!(f, "ae"),
+            AsmCondCode::B => write!(f, "b"),
+            AsmCondCode::BE => write!(f, "be"),
+            AsmCondCode::P => write!(f, "p"),
+            AsmCondCode::NP => write!(f, "np"),
         }
     }
 }
@@ -1,8 +1,16 @@
+use std::collections::HashMap;
+
+use log::debug;
+
 use crate::{
     codegen::x64::ast::{
-        AsmBinaryOperator, AsmFunctionDefinition, AsmInstruction, AsmOperand, Reg,
+        AsmBinaryOperator, AsmFunctionDefinition, AsmIdentifier, AsmInstruction, AsmOperand,
+        AsmType, Reg,
+    },
+    common::{
+        error::{CodegenError, CompileError},
+        folder::FolderAsm,
     },
-    common::folder::FolderAsm,
 };
 
 /// This pass fixes instructions that violate x86_64 encoding constraints.
@@ -13,23 +21,60 @@ use crate::{
 /// may violate. This pass rewrites those patterns into valid instruction sequences.
 ///
 /// ## Memory-to-memory operations
-/// Most x86_64 instructions cannot have both operands in memory. We use R10 as a
-/// scratch register to split these into two instructions:
+/// Most x86_64 instructions cannot have both operands in memory (a stack slot or a
+/// static variable). We use R10 as a scratch register to split these into two instructions:
 /// - `mov mem, mem` → `mov mem, R10` + `mov R10, mem`
 /// - `add/sub mem, mem` → `mov mem, R10` + `add/sub R10, mem`
 /// - `and/or/xor mem, mem` → `mov mem, R10` + `and/or/xor R10, mem`
 /// - `cmp mem, mem` → `mov mem, R10` + `cmp R10, mem`
 ///
-/// ## Division (`idiv`)
-/// The `idiv` instruction cannot take an immediate operand:
+/// Doubles use XMM14 instead of R10: `movsd mem, mem` → `movsd mem, XMM14` + `movsd XMM14, mem`
+///
+/// ## Doubles
+/// SSE arithmetic and `comisd` need a register as destination (or second operand), we use
+/// XMM15 as scratch. Conversions can't write to memory and `cvtsi2sd` can't read an
+/// immediate:
+/// - `addsd/subsd/mulsd/divsd/xorpd src, mem` → `movsd mem, XMM15` + `op src, XMM15` + `movsd XMM15, mem`
+/// - `comisd op, mem` → `movsd mem, XMM15` + `comisd op, XMM15`
+/// - `cvttsd2si src, mem` → `cvttsd2si src, R11` + `mov R11, mem`
+/// - `cvtsi2sd imm, dst` → `mov imm, R10` + `cvtsi2sd R10, dst`
+/// - `cvtsi2sd src, mem` → `cvtsi2sd src, XMM15` + `movsd XMM15, mem`
+///
+/// ## Large immediates
+/// Apart from `mov` into a register, quadword instructions only take 32-bit immediates:
+/// - `movq imm64, mem` → `movq imm64, R10` + `movq R10, mem`
+/// - `addq/subq/imulq/andq/orq/xorq imm64, dst` → `movq imm64, R10` + `op R10, dst`
+/// - `cmpq imm64, op` → `movq imm64, R10` + `cmpq R10, op`
+/// - `push imm64` → `movq imm64, R10` + `push R10`
+///
+/// A longword (or byte) `mov` only keeps the low 32 (or 8) bits of an immediate, so it's
+/// truncated up front to keep the assembler from complaining.
+///
+/// ## Sign and zero extension (`movsx`/`movzx`)
+/// `movsx` and `movzx` cannot take an immediate source and their destination must be a
+/// register:
+/// - `movsx imm, mem` → `mov imm, R10` + `movsx R10, R11` + `mov R11, mem`
+///
+/// There's no instruction to zero extend a longword, but a longword `mov` into a register
+/// zeroes its upper half:
+/// - `movzx src, reg` → `movl src, reg`
+/// - `movzx src, mem` → `movl src, R11` + `movq R11, mem`
+///
+/// ## Load effective address (`lea`)
+/// The destination of `lea` must be a register:
+/// - `lea src, mem` → `lea src, R11` + `movq R11, mem`
+///
+/// ## Division (`idiv`/`div`)
+/// The `idiv` and `div` instructions cannot take an immediate operand:
 /// - `idiv imm` → `mov imm, R10` + `idiv R10`
+/// - `div imm` → `mov imm, R10` + `div R10`
 ///
 /// ## Multiplication (`imul`)
 /// The two-operand `imul` instruction requires the destination to be a register.
 /// We use R11 as scratch because the source might already be in R10:
 /// - `imul src, mem` → `mov mem, R11` + `imul src, R11` + `mov R11, mem`
 ///
-/// ## Shifts (`shl`/`shr`)
+/// ## Shifts (`shl`/`sar`/`shr`)
 /// Shift instructions require the count to be in the CL register (low byte of CX):
 /// - `shl R10, mem` → `mov R10, CX` + `shl CL, mem`
 /// - `shl mem, mem` → `mov mem, CX` + `shl CL, mem`
@@ -38,19 +83,34 @@ use crate::{
 /// The `cmp` instruction cannot have an immediate as the second operand:
 /// - `cmp op, imm` → `mov imm, R11` + `cmp op, R11`
 ///
+/// # Stack Allocation
+/// Each function starts by allocating the space reserved for its pseudo-registers,
+/// rounded up to a multiple of 16 so RSP stays 16-byte aligned at call sites.
+///
 /// # Scratch Register Policy
 /// - **R10**: Primary scratch register for most rewrites
 /// - **R11**: Used when R10 might conflict (e.g., `imul`, `cmp` with immediate)
 /// - **CX/CL**: Used exclusively for shift counts
+/// - **XMM14**: Scratch register for moving doubles
+/// - **XMM15**: Scratch register for SSE instructions that need a register destination
 #[derive(Default)]
 pub struct InstructionFixer {
-    last_offset: Option<i32>, // space reserved for stack
+    last_offset: Option<i32>,                      // space reserved for stack
+    function_offsets: HashMap<AsmIdentifier, i32>, // space reserved for each function stack
 }
 
 impl InstructionFixer {
     pub fn with(&self, last_offset: i32) -> Self {
         Self {
             last_offset: Some(last_offset),
+            function_offsets: HashMap::new(),
+        }
+    }
+
+    pub fn with_function_offsets(&self, function_offsets: HashMap<AsmIdentifier, i32>) -> Self {
+        Self {
+            last_offset: None,
+            function_offsets,
         }
     }
 }
@@ -67,18 +127,29 @@ impl FolderAsm for InstructionFixer {
     fn fold_fun_def(
         &mut self,
         function_definition: AsmFunctionDefinition,
-    ) -> Result<AsmFunctionDefinition, String> {
-        let Some(last_offset) = self.last_offset else {
-            return Err("last_offset should be set".to_string());
+    ) -> Result<AsmFunctionDefinition, CompileError> {
+        let Some(last_offset) = self
+            .function_offsets
+            .get(&function_definition.name)
+            .copied()
+            .or(self.last_offset)
+        else {
+            debug!(
+                "[codegen] no stack size for {}",
+                function_definition.name.value
+            );
+
+            return Err(CodegenError::MissingFunctionOffset(function_definition.name.value).into());
         };
 
-        let mut instructions = vec![AsmInstruction::AllocateStack(last_offset.abs())];
+        let stack_size = (last_offset.abs() + 15) / 16 * 16;
+        let mut instructions = vec![AsmInstruction::AllocateStack(stack_size)];
 
-        let fixed_instructions: Result<Vec<_>, String> = function_definition
+        let fixed_instructions: Result<Vec<_>, CompileError> = function_definition
             .instructions
             .into_iter()
             .map(|i| self.fold_ins(i))
-            .collect::<Result<Vec<_>, String>>()
+            .collect::<Result<Vec<_>, CompileError>>()
             .map(|v| v.into_iter().flatten().collect());
 
         let mut fixed_instructions = fixed_instructions?;
@@ -86,65 +157,205 @@ impl FolderAsm for InstructionFixer {
 
         Ok(AsmFunctionDefinition::new(
             function_definition.name,
+            function_definition.global,
             instructions,
         ))
     }
 
-    fn fold_ins(&mut self, instruction: AsmInstruction) -> Result<Vec<AsmInstruction>, String> {
+    fn fold_ins(
+        &mut self,
+        instruction: AsmInstruction,
+    ) -> Result<Vec<AsmInstruction>, CompileError> {
         use AsmBinaryOperator::*;
         use AsmInstruction::*;
         use AsmOperand::*;
 
         let result = match instruction {
+            // doubles: SSE instructions need a register destination (uses XMM15)
+            Binary(bin_op, AsmType::Double, src, dst) if !dst.is_register() => vec![
+                Comment(
+                    "fix: op src,mem -> movsd mem,XMM15 + op src,XMM15 + movsd XMM15,mem"
+                        .to_string(),
+                ),
+                Mov(AsmType::Double, dst.clone(), Register(Reg::XMM15)),
+                Binary(bin_op, AsmType::Double, src, Register(Reg::XMM15)),
+                Mov(AsmType::Double, Register(Reg::XMM15), dst),
+            ],
+            Cmp(AsmType::Double, op_1, op_2) if !op_2.is_register() => vec![
+                Comment("fix: comisd op,mem -> movsd mem,XMM15 + comisd op,XMM15".to_string()),
+                Mov(AsmType::Double, op_2, Register(Reg::XMM15)),
+                Cmp(AsmType::Double, op_1, Register(Reg::XMM15)),
+            ],
+            Cvttsd2si(ty, src, dst) if !dst.is_register() => vec![
+                Comment("fix: cvttsd2si src,mem -> cvttsd2si src,R11 + mov R11,mem".to_string()),
+                Cvttsd2si(ty, src, Register(Reg::R11)),
+                Mov(ty, Register(Reg::R11), dst),
+            ],
+            Cvtsi2sd(ty, src, dst) if matches!(src, Imm(_)) || !dst.is_register() => {
+                let mut fixed = vec![Comment(
+                    "fix: cvtsi2sd imm,mem -> mov imm,R10 + cvtsi2sd R10,XMM15 + movsd XMM15,mem"
+                        .to_string(),
+                )];
+                let src = match src {
+                    Imm(i) => {
+                        fixed.push(Mov(ty, Imm(i), Register(Reg::R10)));
+                        Register(Reg::R10)
+                    }
+                    src => src,
+                };
+                if dst.is_register() {
+                    fixed.push(Cvtsi2sd(ty, src, dst));
+                } else {
+                    fixed.push(Cvtsi2sd(ty, src, Register(Reg::XMM15)));
+                    fixed.push(Mov(AsmType::Double, Register(Reg::XMM15), dst));
+                }
+                fixed
+            }
+
+            // quadword instructions can only encode 32-bit immediates, mov to a register is
+            // the only exception
+            Mov(AsmType::Quadword, Imm(i), dst) if is_large(i) && dst.is_memory() => vec![
+                Comment("fix: movq imm64,mem -> movq imm64,R10 + movq R10,mem".to_string()),
+                Mov(AsmType::Quadword, Imm(i), Register(Reg::R10)),
+                Mov(AsmType::Quadword, Register(Reg::R10), dst),
+            ],
+            Mov(AsmType::Longword, Imm(i), dst) if is_large(i) => vec![
+                Comment("fix: movl imm64 -> movl truncated imm".to_string()),
+                Mov(AsmType::Longword, Imm(i as i32 as i64), dst),
+            ],
+            Mov(AsmType::Byte, Imm(i), dst) if i8::try_from(i).is_err() => vec![
+                Comment("fix: movb imm -> movb truncated imm".to_string()),
+                Mov(AsmType::Byte, Imm(i as i8 as i64), dst),
+            ],
+            Binary(
+                bin_op @ (Add | Sub | Mult | BitwiseAnd | BitwiseOr | BitwiseXor),
+                AsmType::Quadword,
+                Imm(i),
+                dst,
+            ) if is_large(i) => {
+                let mut fixed = vec![
+                    Comment("fix: op imm64,dst -> movq imm64,R10 + op R10,dst".to_string()),
+                    Mov(AsmType::Quadword, Imm(i), Register(Reg::R10)),
+                ];
+                fixed.extend(self.fold_ins(Binary(
+                    bin_op,
+                    AsmType::Quadword,
+                    Register(Reg::R10),
+                    dst,
+                ))?);
+                fixed
+            }
+            Cmp(AsmType::Quadword, Imm(i), op_2) if is_large(i) => {
+                let mut fixed = vec![
+                    Comment("fix: cmpq imm64,op -> movq imm64,R10 + cmpq R10,op".to_string()),
+                    Mov(AsmType::Quadword, Imm(i), Register(Reg::R10)),
+                ];
+                fixed.extend(self.fold_ins(Cmp(AsmType::Quadword, Register(Reg::R10), op_2))?);
+                fixed
+            }
+            Push(Imm(i)) if is_large(i) => vec![
+                Comment("fix: push imm64 -> movq imm64,R10 + push R10".to_string()),
+                Mov(AsmType::Quadword, Imm(i), Register(Reg::R10)),
+                Push(Register(Reg::R10)),
+            ],
+
             // generic mem-mem patterns (use helpers)
-            Mov(Stack(src), Stack(dst)) => fix_mov_mem_mem(src, dst),
-            Binary(bin_op @ (Add | Sub), Stack(src), Stack(dst)) => {
-                fix_binary_mem_mem(bin_op, src, dst)
+            Mov(ty, src, dst) if src.is_memory() && dst.is_memory() => {
+                fix_mov_mem_mem(ty, src, dst)
+            }
+            Binary(bin_op @ (Add | Sub), ty, src, dst) if src.is_memory() && dst.is_memory() => {
+                fix_binary_mem_mem(bin_op, ty, src, dst)
             }
-            Binary(bin_op @ (BitwiseAnd | BitwiseOr | BitwiseXor), Stack(src), Stack(dst)) => {
-                fix_binary_mem_mem(bin_op, src, dst)
+            Binary(bin_op @ (BitwiseAnd | BitwiseOr | BitwiseXor), ty, src, dst)
+                if src.is_memory() && dst.is_memory() =>
+            {
+                fix_binary_mem_mem(bin_op, ty, src, dst)
+            }
+            Cmp(ty, op_1, op_2) if op_1.is_memory() && op_2.is_memory() => {
+                fix_cmp_mem_mem(ty, op_1, op_2)
+            }
+
+            // special case: movsx cannot take an immediate source (uses R10) and its
+            // destination must be a register (uses R11)
+            Movsx(src_ty, dst_ty, src, dst) if matches!(src, Imm(_)) || dst.is_memory() => {
+                fix_extension(Movsx, src_ty, dst_ty, src, dst)
             }
-            Cmp(Stack(op_1), Stack(op_2)) => fix_cmp_mem_mem(op_1, op_2),
 
-            // special case: idiv cannot take an immediate operand
-            Idiv(Imm(num)) => vec![
+            // special case: zero extension of a longword is a longword mov, which zeroes the
+            // upper half of a register (uses R11 when the destination is in memory)
+            MovZeroExtend(AsmType::Longword, _, src, dst) if dst.is_memory() => vec![
+                Comment("fix: movzx src,mem -> movl src,R11 + movq R11,mem".to_string()),
+                Mov(AsmType::Longword, src, Register(Reg::R11)),
+                Mov(AsmType::Quadword, Register(Reg::R11), dst),
+            ],
+            MovZeroExtend(AsmType::Longword, _, src, dst) => {
+                vec![Mov(AsmType::Longword, src, dst)]
+            }
+            // movzx has the same constraints as movsx
+            MovZeroExtend(src_ty, dst_ty, src, dst) if matches!(src, Imm(_)) || dst.is_memory() => {
+                fix_extension(MovZeroExtend, src_ty, dst_ty, src, dst)
+            }
+
+            // special case: lea needs a register as destination (uses R11)
+            Lea(src, dst) if dst.is_memory() => vec![
+                Comment("fix: lea src,mem -> lea src,R11 + movq R11,mem".to_string()),
+                Lea(src, Register(Reg::R11)),
+                Mov(AsmType::Quadword, Register(Reg::R11), dst),
+            ],
+
+            // special case: idiv and div cannot take an immediate operand
+            Idiv(ty, Imm(num)) => vec![
                 Comment("fix: idiv imm -> mov imm,R10 + idiv R10".to_string()),
-                Mov(Imm(num), Register(Reg::R10)),
-                Idiv(Register(Reg::R10)),
+                Mov(ty, Imm(num), Register(Reg::R10)),
+                Idiv(ty, Register(Reg::R10)),
+            ],
+            Div(ty, Imm(num)) => vec![
+                Comment("fix: div imm -> mov imm,R10 + div R10".to_string()),
+                Mov(ty, Imm(num), Register(Reg::R10)),
+                Div(ty, Register(Reg::R10)),
             ],
 
             // special case: imul destination must be a register (uses R11)
-            Binary(Mult, src, Stack(dst)) => {
+            Binary(Mult, ty, src, dst) if dst.is_memory() => {
                 vec![
                     Comment(
                         "fix: imul src,mem -> mov mem,R11 + imul src,R11 + mov R11,mem".to_string(),
                     ),
-                    Mov(Stack(dst), Register(Reg::R11)),
-                    Binary(Mult, src, Register(Reg::R11)),
-                    Mov(Register(Reg::R11), Stack(dst)),
+                    Mov(ty, dst.clone(), Register(Reg::R11)),
+                    Binary(Mult, ty, src, Register(Reg::R11)),
+                    Mov(ty, Register(Reg::R11), dst),
                 ]
             }
 
             // special case: shift count must be in CL register
-            Binary(bin_op @ (LeftShift | RightShift), Register(Reg::R10), Stack(dst)) => vec![
-                Comment("fix: shl/shr R10,mem -> mov R10,CX + op CL,mem".to_string()),
-                Mov(Register(Reg::R10), Register(Reg::CX)),
-                Binary(bin_op, Register(Reg::CL), Stack(dst)),
-            ],
-            Binary(bin_op @ (LeftShift | RightShift), Stack(src), Stack(dst)) => {
+            Binary(
+                bin_op @ (LeftShift | RightShift | LogicalRightShift),
+                ty,
+                Register(Reg::R10),
+                dst,
+            ) if dst.is_memory() => {
+                vec![
+                    Comment("fix: shl/shr R10,mem -> mov R10,CX + op CL,mem".to_string()),
+                    Mov(ty, Register(Reg::R10), Register(Reg::CX)),
+                    Binary(bin_op, ty, Register(Reg::CL), dst),
+                ]
+            }
+            Binary(bin_op @ (LeftShift | RightShift | LogicalRightShift), ty, src, dst)
+                if src.is_memory() && dst.is_memory() =>
+            {
                 vec![
                     Comment("fix: shl/shr mem,mem -> mov mem,CX + op CL,mem".to_string()),
-                    Mov(Stack(src), Register(Reg::CX)),
-                    Binary(bin_op, Register(Reg::CL), Stack(dst)),
+                    Mov(ty, src, Register(Reg::CX)),
+                    Binary(bin_op, ty, Register(Reg::CL), dst),
                 ]
             }
 
             // special case: cmp second operand cannot be an immediate (uses R11)
-            Cmp(op_1, Imm(constant)) => {
+            Cmp(ty, op_1, Imm(constant)) => {
                 vec![
                     Comment("fix: cmp op,imm -> mov imm,R11 + cmp op,R11".to_string()),
-                    Mov(Imm(constant), Register(Reg::R11)),
-                    Cmp(op_1, Register(Reg::R11)),
+                    Mov(ty, Imm(constant), Register(Reg::R11)),
+                    Cmp(ty, op_1, Register(Reg::R11)),
                 ]
             }
 
@@ -155,35 +366,90 @@ impl FolderAsm for InstructionFixer {
     }
 }
 
-/// Splits a memory-to-memory mov into two instructions using R10 as scratch.
+/// Whether an immediate doesn't fit in the 32 bits most instructions can encode
+fn is_large(value: i64) -> bool {
+    i32::try_from(value).is_err()
+}
+
+/// Moves an immediate source of `movsx`/`movzx` to R10 and writes the result to R11 when the
+/// destination is in memory.
+/// Pattern: `movsx imm, mem` → `mov imm, R10` + `movsx R10, R11` + `mov R11, mem`
+fn fix_extension(
+    extension: fn(AsmType, AsmType, AsmOperand, AsmOperand) -> AsmInstruction,
+    src_ty: AsmType,
+    dst_ty: AsmType,
+    src: AsmOperand,
+    dst: AsmOperand,
+) -> Vec<AsmInstruction> {
+    let mut fixed = vec![AsmInstruction::Comment(
+        "fix: movsx/movzx imm,mem -> mov imm,R10 + movsx/movzx R10,R11 + mov R11,mem".to_string(),
+    )];
+    let src = match src {
+        AsmOperand::Imm(i) => {
+            fixed.push(AsmInstruction::Mov(
+                src_ty,
+                AsmOperand::Imm(i),
+                AsmOperand::Register(Reg::R10),
+            ));
+            AsmOperand::Register(Reg::R10)
+        }
+        src => src,
+    };
+    if dst.is_memory() {
+        fixed.push(extension(
+            src_ty,
+            dst_ty,
+            src,
+            AsmOperand::Register(Reg::R11),
+        ));
+        fixed.push(AsmInstruction::Mov(
+            dst_ty,
+            AsmOperand::Register(Reg::R11),
+            dst,
+        ));
+    } else {
+        fixed.push(extension(src_ty, dst_ty, src, dst));
+    }
+    fixed
+}
+
+/// Splits a memory-to-memory mov into two instructions using R10 (XMM14 for doubles)
+/// as scratch.
 /// Pattern: `mov mem, mem` → `mov mem, R10` + `mov R10, mem`
-fn fix_mov_mem_mem(src: i32, dst: i32) -> Vec<AsmInstruction> {
+fn fix_mov_mem_mem(ty: AsmType, src: AsmOperand, dst: AsmOperand) -> Vec<AsmInstruction> {
+    let scratch = match ty {
+        AsmType::Double => Reg::XMM14,
+        _ => Reg::R10,
+    };
     vec![
         AsmInstruction::Comment("fix: mov mem,mem -> mov mem,R10 + mov R10,mem".to_string()),
-        AsmInstruction::Mov(AsmOperand::Stack(src), AsmOperand::Register(Reg::R10)),
-        AsmInstruction::Mov(AsmOperand::Register(Reg::R10), AsmOperand::Stack(dst)),
+        AsmInstruction::Mov(ty, src, AsmOperand::Register(scratch.clone())),
+        AsmInstruction::Mov(ty, AsmOperand::Register(scratch), dst),
     ]
 }
 
 /// Splits a memory-to-memory binary op into two instructions using R10 as scratch.
 /// Pattern: `op mem, mem` → `mov mem, R10` + `op R10, mem`
 /// Used for: add, sub, and, or, xor
-fn fix_binary_mem_mem(op: AsmBinaryOperator, src: i32, dst: i32) -> Vec<AsmInstruction> {
+fn fix_binary_mem_mem(
+    op: AsmBinaryOperator,
+    ty: AsmType,
+    src: AsmOperand,
+    dst: AsmOperand,
+) -> Vec<AsmInstruction> {
     vec![
         AsmInstruction::Comment("fix: op mem,mem -> mov mem,R10 + op R10,mem".to_string()),
-        AsmInstruction::Mov(AsmOperand::Stack(src), AsmOperand::Register(Reg::R10)),
-        AsmInstruction::Binary(op, AsmOperand::Register(Reg::R10), AsmOperand::Stack(dst)),
+        AsmInstruction::Mov(ty, src, AsmOperand::Register(Reg::R10)),
+        AsmInstruction::Binary(op, ty, AsmOperand::Register(Reg::R10), dst),
     ]
 }
 
 /// Splits a memory-to-memory cmp into two instructions using R10 as scratch.
 /// Pattern: `cmp mem, mem` → `mov mem, R10` + `cmp R10, mem`
-fn fix_cmp_mem_mem(op_1: i32, op_2: i32) -> Vec<AsmInstruction> {
+fn fix_cmp_mem_mem(ty: AsmType, op_1: AsmOperand, op_2: AsmOperand) -> Vec<AsmInstruction> {
     vec![
         AsmInstruction::Comment("fix: cmp mem,mem -> mov mem,R10 + cmp R10,mem".to_string()),
-        AsmInstruction::Mov(AsmOperand::Stack(op_1), AsmOperand::Register(Reg::R10)),
-        AsmInstruction::Cmp(AsmOperand::Register(Reg::R10), AsmOperand::Stack(op_2)),
+        AsmInstruction::Mov(ty, op_1, AsmOperand::Register(Reg::R10)),
+        AsmInstruction::Cmp(ty, AsmOperand::Register(Reg::R10), op_2),
     ]
 }
-
-
@@ -1,10 +1,14 @@
 use std::collections::HashMap;
 
-use log::{debug, error, info};
+use log::{debug, info};
 
 use crate::{
-    codegen::x64::ast::{AsmFunctionDefinition, AsmInstruction, AsmOperand},
-    common::folder::FolderAsm,
+    codegen::x64::ast::{AsmFunctionDefinition, AsmIdentifier, AsmInstruction, AsmOperand},
+    common::{
+        error::{CodegenError, CompileError},
+        folder::FolderAsm,
+        symbols::SymbolTable,
+    },
 };
 
 /// This pass replaces pseudo-registers with stack offsets.
@@ -17,18 +21,20 @@ use crate::{
 ///
 /// # Stack Layout
 ///
-/// Each pseudo-register gets a unique 4-byte slot on the stack. The stack grows
-/// downward (toward lower addresses), so offsets are negative relative to RBP:
+/// Each pseudo-register gets a unique slot on the stack, sized after its type: 4 bytes
+/// for an `int`, 8 bytes for a `long`, a `double` or a pointer, and the size of the whole
+/// object for an array or a structure. The stack grows downward (toward lower
+/// addresses), so offsets are negative relative to RBP:
 ///
 /// ```text
 ///     ┌─────────────────┐  Higher addresses
 ///     │  Return addr    │
 ///     ├─────────────────┤  ← RBP (frame pointer)
-///     │  var_1          │  -4(%rbp)
+///     │  var_1 (int)    │  -4(%rbp)
 ///     ├─────────────────┤
-///     │  var_2          │  -8(%rbp)
+///     │  var_2 (long)   │  -16(%rbp)
 ///     ├─────────────────┤
-///     │  tmp.0          │  -12(%rbp)
+///     │  tmp.0 (int)    │  -20(%rbp)
 ///     ├─────────────────┤
 ///     │  ...            │
 ///     └─────────────────┘  ← RSP (stack pointer)
@@ -41,20 +47,33 @@ use crate::{
 /// 2. **`last_offset`**: The total stack space needed (used by `InstructionFixer` to emit `AllocateStack`)
 ///
 /// The `InstructionFixer` pass **must** run after this pass and use `last_offset`
-/// to allocate the correct amount of stack space.
+/// to allocate the correct amount of stack space. Since every function has its own
+/// stack frame, the `last_offset` of each function is kept in `function_offsets`.
 ///
 /// # Allocation Strategy
 ///
-/// - First pseudo-register seen → offset -4
-/// - Second pseudo-register → offset -8
-/// - And so on...
-/// - `last_offset` is the next available offset (e.g., -16 if 3 variables allocated)
+/// - First pseudo-register seen → offset -4 (or -8 for a `long` or a `double`)
+/// - Each following pseudo-register goes right below the previous one
+/// - Slots are aligned to the alignment of their type, leaving a gap when needed (arrays of
+///   16 bytes or more are 16-byte aligned)
+/// - An aggregate's offset is the address of its first byte, `PseudoMem(id, offset)` operands
+///   become `Stack(aggregate_offset + offset)`
+/// - `last_offset` is the lowest offset in use (e.g., -12 if 3 ints allocated)
 ///
-/// Note: Currently allocates 4 bytes per variable regardless of type (int = 4 bytes)
+/// The type of each pseudo-register comes from the symbol table. Names missing from
+/// it are treated as ints.
+///
+/// # Static Variables
+///
+/// Pseudo-registers that refer to variables with static storage duration don't get a
+/// stack slot, they're replaced with `Data(id, offset)` operands instead. The symbol table is
+/// needed to tell them apart, so it must be set with `with_symbols`.
 #[derive(Default)]
 pub struct PseudoRegisterReplacer {
     pub offset_map: Option<HashMap<AsmOperand, i32>>,
     pub last_offset: Option<i32>,
+    pub function_offsets: HashMap<AsmIdentifier, i32>,
+    symbols: SymbolTable,
 }
 
 impl PseudoRegisterReplacer {
@@ -64,6 +83,15 @@ impl PseudoRegisterReplacer {
         };
         last_offset
     }
+
+    pub fn with_symbols(self, symbols: SymbolTable) -> Self {
+        Self { symbols, ..self }
+    }
+
+    /// Returns the `last_offset` of every function replaced so far.
+    pub fn function_offsets(&self) -> HashMap<AsmIdentifier, i32> {
+        self.function_offsets.clone()
+    }
 }
 
 impl FolderAsm for PseudoRegisterReplacer {
@@ -75,16 +103,20 @@ impl FolderAsm for PseudoRegisterReplacer {
         Self {
             offset_map: None,
             last_offset: None,
+            function_offsets: HashMap::new(),
+            symbols: SymbolTable::new(),
         }
     }
 
     fn fold_fun_def(
         &mut self,
         function: AsmFunctionDefinition,
-    ) -> Result<AsmFunctionDefinition, String> {
-        let (pseudo_reg_map, last_offset) = ids_offset_map(&function);
+    ) -> Result<AsmFunctionDefinition, CompileError> {
+        let (pseudo_reg_map, last_offset) = ids_offset_map(&function, &self.symbols);
         self.last_offset = Some(last_offset);
         self.offset_map = Some(pseudo_reg_map.clone());
+        self.function_offsets
+            .insert(function.name.clone(), last_offset);
 
         info!(
             "[codegen] {} pseudo registers, stack size {}",
@@ -94,84 +126,131 @@ impl FolderAsm for PseudoRegisterReplacer {
 
         debug!("[codegen] pseudo register map: {pseudo_reg_map:?}");
 
-        let instructions: Result<Vec<_>, String> = function
+        let instructions: Result<Vec<_>, CompileError> = function
             .instructions
             .into_iter()
             .map(|i| self.fold_ins(i))
-            .collect::<Result<Vec<_>, String>>()
+            .collect::<Result<Vec<_>, CompileError>>()
             .map(|v| v.into_iter().flatten().collect());
 
-        Ok(AsmFunctionDefinition::new(function.name, instructions?))
+        Ok(AsmFunctionDefinition::new(
+            function.name,
+            function.global,
+            instructions?,
+        ))
     }
 
-    fn fold_ins(&mut self, instruction: AsmInstruction) -> Result<Vec<AsmInstruction>, String> {
+    fn fold_ins(
+        &mut self,
+        instruction: AsmInstruction,
+    ) -> Result<Vec<AsmInstruction>, CompileError> {
         use AsmInstruction::*;
 
         let res = match instruction {
-            Mov(src, dst) => Mov(self.fold_op(src)?, self.fold_op(dst)?),
-            Unary(op, operand) => Unary(op, self.fold_op(operand)?),
-            Binary(op, src, dst) => Binary(op, self.fold_op(src)?, self.fold_op(dst)?),
-            Idiv(op) => Idiv(self.fold_op(op)?),
-            SetCC(cc, op) => SetCC(cc, self.fold_op(op)?),
-            Cmp(op1, op2) => Cmp(self.fold_op(op1)?, self.fold_op(op2)?),
-            JmpCC(_, _) | Label(_) | AllocateStack(_) | Comment(_) | Jmp(_) | Ret | Cdq => {
-                instruction
+            Mov(ty, src, dst) => Mov(ty, self.fold_op(src)?, self.fold_op(dst)?),
+            Movsx(src_ty, dst_ty, src, dst) => {
+                Movsx(src_ty, dst_ty, self.fold_op(src)?, self.fold_op(dst)?)
+            }
+            MovZeroExtend(src_ty, dst_ty, src, dst) => {
+                MovZeroExtend(src_ty, dst_ty, self.fold_op(src)?, self.fold_op(dst)?)
             }
+            Cvttsd2si(ty, src, dst) => Cvttsd2si(ty, self.fold_op(src)?, self.fold_op(dst)?),
+            Cvtsi2sd(ty, src, dst) => Cvtsi2sd(ty, self.fold_op(src)?, self.fold_op(dst)?),
+            Lea(src, dst) => Lea(self.fold_op(src)?, self.fold_op(dst)?),
+            Unary(op, ty, operand) => Unary(op, ty, self.fold_op(operand)?),
+            Binary(op, ty, src, dst) => Binary(op, ty, self.fold_op(src)?, self.fold_op(dst)?),
+            Idiv(ty, op) => Idiv(ty, self.fold_op(op)?),
+            Div(ty, op) => Div(ty, self.fold_op(op)?),
+            SetCC(cc, op) => SetCC(cc, self.fold_op(op)?),
+            Cmp(ty, op1, op2) => Cmp(ty, self.fold_op(op1)?, self.fold_op(op2)?),
+            Push(op) => Push(self.fold_op(op)?),
+            JmpCC(_, _)
+            | JmpTable(..)
+            | IndirectCall(_)
+            | Label(_)
+            | AllocateStack(_)
+            | DeallocateStack(_)
+            | Comment(_)
+            | Jmp(_)
+            | Call(_)
+            | Ret
+            | Cdq(_) => instruction,
         };
 
         Ok(vec![res])
     }
 
-    fn fold_op(&mut self, operand: AsmOperand) -> Result<AsmOperand, String> {
+    fn fold_op(&mut self, operand: AsmOperand) -> Result<AsmOperand, CompileError> {
         let Some(offset_map) = &self.offset_map else {
-            error!("[codegen] offset_map not initialized");
+            debug!("[codegen] offset_map not initialized");
 
-            return Err("offset_map not initialized".to_string());
+            return Err(CodegenError::MissingStackOffsets.into());
         };
 
+        if let AsmOperand::Pseudo(id) = &operand
+            && is_static(id, &self.symbols)
+        {
+            return Ok(AsmOperand::Data(id.clone(), 0));
+        }
+
+        if let AsmOperand::PseudoMem(id, offset) = &operand {
+            if is_static(id, &self.symbols) {
+                return Ok(AsmOperand::Data(id.clone(), *offset));
+            }
+
+            let Some(base) = offset_map.get(&AsmOperand::Pseudo(id.clone())) else {
+                debug!("[codegen] no stack slot for aggregate: {}", id.value);
+
+                return Err(CodegenError::MissingStackSlot(id.value.clone()).into());
+            };
+
+            return Ok(AsmOperand::Stack(base + offset));
+        }
+
         Ok(offset_map
             .get(&operand)
             .map_or(operand, |i| AsmOperand::Stack(*i)))
     }
 }
 
+/// Whether the pseudo-register refers to a variable with static storage duration
+fn is_static(id: &AsmIdentifier, symbols: &SymbolTable) -> bool {
+    symbols.get(&id.value).is_some_and(|s| s.is_static())
+}
+
 /// Builds a map from pseudo-registers to stack offsets.
+/// Static variables are skipped since they don't live on the stack.
 ///
 /// Returns:
 /// - `HashMap<AsmOperand, i32>`: Maps each `Pseudo(id)` to its stack offset
-/// - `i32`: The next available offset (used to calculate total stack size)
-fn ids_offset_map(function_definition: &AsmFunctionDefinition) -> (HashMap<AsmOperand, i32>, i32) {
-    let (map, last_offset) = function_definition
+/// - `i32`: The lowest offset in use (used to calculate total stack size)
+fn ids_offset_map(
+    function_definition: &AsmFunctionDefinition,
+    symbols: &SymbolTable,
+) -> (HashMap<AsmOperand, i32>, i32) {
+    let (map, used) = function_definition
         .instructions
         .iter()
-        .flat_map(operands)
-        .flatten()
-        .fold((HashMap::new(), -4i32), |(mut acc, mut next), op| {
-            // tag pseudo register
-            if let AsmOperand::Pseudo(_) = op {
-                if !acc.contains_key(&op) {
-                    acc.insert(op.clone(), next);
-                    next -= 4;
-                }
+        .flat_map(|i| i.operands())
+        .fold((HashMap::new(), 0i32), |(mut acc, mut used), op| {
+            // tag pseudo register, parts of an aggregate share the slot of the whole
+            let id = match op {
+                AsmOperand::Pseudo(id) | AsmOperand::PseudoMem(id, _) => id,
+                _ => return (acc, used),
+            };
+            let pseudo = AsmOperand::Pseudo(id.clone());
+            if !is_static(id, symbols) && !acc.contains_key(&pseudo) {
+                let (size, alignment) = symbols.get(&id.value).map_or((4, 4), |s| {
+                    (
+                        s.ty.size(symbols.types()) as i32,
+                        s.ty.alignment(symbols.types()) as i32,
+                    )
+                });
+                used = (used + size + alignment - 1) / alignment * alignment;
+                acc.insert(pseudo, -used);
             }
-            (acc, next)
+            (acc, used)
         });
 
-    (map, last_offset)
-}
-
-/// Extracts all operands from an instruction for pseudo-register discovery.
-/// Returns `None` for instructions that don't have operands (jumps, labels, etc.)
-fn operands(instruction: &AsmInstruction) -> Option<Vec<AsmOperand>> {
-    use AsmInstruction::*;
-    let ops = match instruction {
-        Mov(op_1, op_2) => vec![op_1.clone(), op_2.clone()],
-        Unary(_, op) => vec![op.clone()],
-        Binary(_, op_1, op_2) => vec![op_1.clone(), op_2.clone()],
-        Idiv(op) => vec![op.clone()],
-        Cmp(op_1, op_2) => vec![op_1.clone(), op_2.clone()],
-        SetCC(_, op) => vec![op.clone()],
-        Comment(_) | Cdq | Jmp(_) | JmpCC(_, _) | Label(_) | AllocateStack(_) | Ret => return None,
-    };
-    Some(ops)
+    (map, -used)
 }
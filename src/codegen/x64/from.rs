@@ -1,42 +1,442 @@
 use crate::{
+    c_ast::{
+        ast::{Const, Type},
+        semantic::type_table::TypeTable,
+    },
     codegen::x64::ast::{
         AsmBinaryOperator, AsmCondCode, AsmFunctionDefinition, AsmIdentifier, AsmInstruction,
-        AsmOperand, AsmProgram, AsmUnaryOperator, Reg,
+        AsmOperand, AsmProgram, AsmStaticConstant, AsmStaticVariable, AsmType, AsmUnaryOperator,
+        Reg,
     },
+    common::symbols::{IdentifierAttrs, StaticInit, Symbol, SymbolTable},
     tacky::ast::{
         TackyBinaryOperator, TackyFunctionDefinition, TackyIdentifier, TackyInstruction,
-        TackyProgram, TackyUnaryOperator, TackyValue,
+        TackyProgram, TackyStaticConstant, TackyStaticVariable, TackyUnaryOperator, TackyValue,
     },
 };
 
+/// Registers used to pass the first six integer arguments (System V AMD64 ABI)
+const ARG_REGISTERS: [Reg; 6] = [Reg::DI, Reg::SI, Reg::DX, Reg::CX, Reg::R8, Reg::R9];
+
+/// Registers used to pass the first eight double arguments (System V AMD64 ABI)
+const DOUBLE_ARG_REGISTERS: [Reg; 8] = [
+    Reg::XMM0,
+    Reg::XMM1,
+    Reg::XMM2,
+    Reg::XMM3,
+    Reg::XMM4,
+    Reg::XMM5,
+    Reg::XMM6,
+    Reg::XMM7,
+];
+
+/// Registers holding the integer and the double eightbytes of a returned structure
+const RETURN_REGISTERS: [Reg; 2] = [Reg::AX, Reg::DX];
+const DOUBLE_RETURN_REGISTERS: [Reg; 2] = [Reg::XMM0, Reg::XMM1];
+
+/// Pseudo-register where a function that returns a structure in memory keeps the address
+/// the caller passed in RDI. It can't clash with a C identifier.
+pub const RETURN_PTR: &str = "return.ptr";
+
+fn return_ptr() -> AsmOperand {
+    AsmOperand::Pseudo(AsmIdentifier {
+        value: RETURN_PTR.to_string(),
+    })
+}
+
+/// Aggregate pseudo-register where a function that calls `va_start` saves the argument
+/// registers: the six integer ones, 8 bytes each, followed by the eight XMM ones, 16 bytes
+/// each. It can't clash with a C identifier.
+pub const REG_SAVE_AREA: &str = "reg_save.area";
+const REG_SAVE_AREA_SIZE: usize = 176;
+
+fn reg_save_area(offset: i32) -> AsmOperand {
+    AsmOperand::PseudoMem(
+        AsmIdentifier {
+            value: REG_SAVE_AREA.to_string(),
+        },
+        offset,
+    )
+}
+
+/// Class of an eightbyte of a structure, which decides how it's passed (System V AMD64 ABI)
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ParamClass {
+    /// In a general purpose register
+    Integer,
+    /// In an SSE register, the eightbyte only holds doubles
+    Sse,
+    /// On the stack (or through a hidden pointer when returned), the structure is larger
+    /// than 16 bytes
+    Memory,
+}
+
+/// What a register (or an 8-byte stack slot) holds of a value: a scalar of the given type, or
+/// an eightbyte of a structure with fewer bytes than any `AsmType`, which only happens for
+/// the last one.
+#[derive(Clone, Copy, Debug)]
+enum Part {
+    Scalar(AsmType),
+    Bytes(usize),
+}
+
+/// Returns the type of a TACKY value. Constants carry their own type, variables are
+/// looked up in the symbol table (values missing from it are treated as ints). Qualifiers
+/// don't change how a value is stored, so they're dropped.
+fn value_type(value: &TackyValue, symbols: &SymbolTable) -> Type {
+    match value {
+        TackyValue::Constant(c) => c.ty(),
+        TackyValue::Var(id) => symbols
+            .get(&id.value)
+            .map_or(Type::Int, |symbol| symbol.ty.unqualified().clone()),
+    }
+}
+
+/// Returns the size of a TACKY value.
+fn asm_type(value: &TackyValue, symbols: &SymbolTable) -> AsmType {
+    AsmType::from(&value_type(value, symbols))
+}
+
+fn is_signed(value: &TackyValue, symbols: &SymbolTable) -> bool {
+    value_type(value, symbols).is_signed()
+}
+
+fn is_double(value: &TackyValue, symbols: &SymbolTable) -> bool {
+    value_type(value, symbols) == Type::Double
+}
+
+fn is_structure(value: &TackyValue, symbols: &SymbolTable) -> bool {
+    value_type(value, symbols).is_structure()
+}
+
+/// Size in bytes of a structure value.
+fn structure_size(value: &TackyValue, symbols: &SymbolTable) -> usize {
+    value_type(value, symbols).size(symbols.types())
+}
+
+/// Classifies each eightbyte of a structure. Structures larger than 16 bytes go in memory,
+/// otherwise an eightbyte is SSE when all the scalars in it are doubles and INTEGER if not.
+fn classify_structure(ty: &Type, types: &TypeTable) -> Vec<ParamClass> {
+    let size = ty.size(types);
+    let eightbytes = size.div_ceil(8);
+    if size > 16 {
+        return vec![ParamClass::Memory; eightbytes];
+    }
+
+    let mut scalars = vec![];
+    flatten_scalars(ty, 0, types, &mut scalars);
+    (0..eightbytes)
+        .map(|i| {
+            let only_doubles = scalars
+                .iter()
+                .filter(|(offset, _)| offset / 8 == i)
+                .all(|(_, ty)| ty.is_double());
+            if only_doubles {
+                ParamClass::Sse
+            } else {
+                ParamClass::Integer
+            }
+        })
+        .collect()
+}
+
+/// Collects the scalars of a type with their offsets, going through nested structures and
+/// arrays.
+fn flatten_scalars(ty: &Type, offset: usize, types: &TypeTable, scalars: &mut Vec<(usize, Type)>) {
+    match ty.unqualified() {
+        Type::Structure(tag) => {
+            for member in types.get(tag).map_or(&[][..], |entry| &entry.members) {
+                flatten_scalars(&member.ty, offset + member.offset, types, scalars);
+            }
+        }
+        Type::Array(element_type, size) => {
+            let element_size = element_type.size(types);
+            for i in 0..*size {
+                flatten_scalars(element_type, offset + i * element_size, types, scalars);
+            }
+        }
+        _ => scalars.push((offset, ty.clone())),
+    }
+}
+
+/// Whether a value of this type is returned through a pointer the caller passes in RDI.
+fn returns_in_memory(ty: &Type, types: &TypeTable) -> bool {
+    ty.is_structure() && classify_structure(ty, types).first() == Some(&ParamClass::Memory)
+}
+
+/// Returns the part of a structure of `size` bytes held by its eightbyte `i`.
+fn eightbyte_part(class: ParamClass, size: usize, i: usize) -> Part {
+    match (class, (size - 8 * i).min(8)) {
+        (ParamClass::Sse, _) => Part::Scalar(AsmType::Double),
+        (_, 8) => Part::Scalar(AsmType::Quadword),
+        (_, 4) => Part::Scalar(AsmType::Longword),
+        (_, 1) => Part::Scalar(AsmType::Byte),
+        (_, bytes) => Part::Bytes(bytes),
+    }
+}
+
+/// Returns every eightbyte of a structure value, with its operand and class.
+fn eightbytes(value: &TackyValue, symbols: &SymbolTable) -> Vec<(AsmOperand, Part, ParamClass)> {
+    let ty = value_type(value, symbols);
+    let size = ty.size(symbols.types());
+    let operand = AsmOperand::from(value.clone());
+    classify_structure(&ty, symbols.types())
+        .into_iter()
+        .enumerate()
+        .map(|(i, class)| {
+            (
+                operand.offset_by(8 * i as i32),
+                eightbyte_part(class, size, i),
+                class,
+            )
+        })
+        .collect()
+}
+
+/// Copies `size` bytes from `src` to `dst`, both in memory, 8 (or 4) at a time while there
+/// are enough of them left.
+fn emit_copy_bytes(src: AsmOperand, dst: AsmOperand, size: usize) -> Vec<AsmInstruction> {
+    let mut instructions = vec![];
+    let mut copied = 0;
+    while copied < size {
+        let ty = match size - copied {
+            8.. => AsmType::Quadword,
+            4.. => AsmType::Longword,
+            _ => AsmType::Byte,
+        };
+        instructions.push(AsmInstruction::Mov(
+            ty,
+            src.offset_by(copied as i32),
+            dst.offset_by(copied as i32),
+        ));
+        copied += ty.size() as usize;
+    }
+    instructions
+}
+
+/// Moves a part of a value into a register. Loose bytes are moved one at a time, from the
+/// last one, shifting the register left to make room for the next.
+fn emit_part_to_register(src: AsmOperand, part: Part, reg: Reg) -> Vec<AsmInstruction> {
+    let register = AsmOperand::Register(reg);
+    let bytes = match part {
+        Part::Scalar(ty) => return vec![AsmInstruction::Mov(ty, src, register)],
+        Part::Bytes(bytes) => bytes,
+    };
+
+    let mut instructions = vec![];
+    for i in (0..bytes).rev() {
+        instructions.push(AsmInstruction::Mov(
+            AsmType::Byte,
+            src.offset_by(i as i32),
+            register.clone(),
+        ));
+        if i > 0 {
+            instructions.push(AsmInstruction::Binary(
+                AsmBinaryOperator::LeftShift,
+                AsmType::Quadword,
+                AsmOperand::Imm(8),
+                register.clone(),
+            ));
+        }
+    }
+    instructions
+}
+
+/// Moves the part of a value held by a register to memory. Loose bytes are moved one at a
+/// time, from the first one, shifting the register right to get to the next.
+fn emit_register_to_part(reg: Reg, part: Part, dst: AsmOperand) -> Vec<AsmInstruction> {
+    let register = AsmOperand::Register(reg);
+    let bytes = match part {
+        Part::Scalar(ty) => return vec![AsmInstruction::Mov(ty, register, dst)],
+        Part::Bytes(bytes) => bytes,
+    };
+
+    let mut instructions = vec![];
+    for i in 0..bytes {
+        instructions.push(AsmInstruction::Mov(
+            AsmType::Byte,
+            register.clone(),
+            dst.offset_by(i as i32),
+        ));
+        if i + 1 < bytes {
+            instructions.push(AsmInstruction::Binary(
+                AsmBinaryOperator::LogicalRightShift,
+                AsmType::Quadword,
+                AsmOperand::Imm(8),
+                register.clone(),
+            ));
+        }
+    }
+    instructions
+}
+
+/// Register holding the return value of a function of the given type.
+fn return_register(ty: AsmType) -> AsmOperand {
+    match ty {
+        AsmType::Double => AsmOperand::Register(Reg::XMM0),
+        _ => AsmOperand::Register(Reg::AX),
+    }
+}
+
 // helpers for common asm emission patterns
 
 fn emit_conditional_jump(
     condition: TackyValue,
     target: TackyIdentifier,
     jump_when_zero: bool,
+    symbols: &SymbolTable,
 ) -> Vec<AsmInstruction> {
+    if is_double(&condition, symbols) {
+        return emit_double_conditional_jump(condition, target, jump_when_zero);
+    }
+
     let cond_code = if jump_when_zero {
         AsmCondCode::E
     } else {
         AsmCondCode::NE
     };
     vec![
-        AsmInstruction::Cmp(AsmOperand::Imm(0), AsmOperand::from(condition)),
+        AsmInstruction::Cmp(
+            asm_type(&condition, symbols),
+            AsmOperand::Imm(0),
+            AsmOperand::from(condition),
+        ),
         AsmInstruction::JmpCC(cond_code, AsmIdentifier::from(target)),
     ]
 }
 
+/// Compares a double against zero. NaN is not zero, so when the comparison is unordered
+/// (the parity flag is set) JumpIfZero falls through and JumpIfNotZero jumps.
+fn emit_double_conditional_jump(
+    condition: TackyValue,
+    target: TackyIdentifier,
+    jump_when_zero: bool,
+) -> Vec<AsmInstruction> {
+    let target = AsmIdentifier::from(target);
+    let mut instructions = emit_compare_to_zero(condition);
+    if jump_when_zero {
+        // R10 = equal && ordered, `and` sets ZF when it's false
+        instructions.extend(emit_double_equality(true, AsmOperand::Register(Reg::R10)));
+        instructions.push(AsmInstruction::JmpCC(AsmCondCode::NE, target));
+    } else {
+        instructions.push(AsmInstruction::JmpCC(AsmCondCode::NE, target.clone()));
+        instructions.push(AsmInstruction::JmpCC(AsmCondCode::P, target));
+    }
+    instructions
+}
+
+/// Compares a double against 0.0, using XMM0 to hold the zero.
+fn emit_compare_to_zero(value: TackyValue) -> Vec<AsmInstruction> {
+    let zero = AsmOperand::Register(Reg::XMM0);
+    vec![
+        AsmInstruction::Binary(
+            AsmBinaryOperator::BitwiseXor,
+            AsmType::Double,
+            zero.clone(),
+            zero.clone(),
+        ),
+        AsmInstruction::Cmp(AsmType::Double, AsmOperand::from(value), zero),
+    ]
+}
+
+/// Stores the result of the last comparison of doubles in a longword. `==` is true only
+/// when ZF is set and PF is clear, `!=` when ZF is clear or PF is set, so an unordered
+/// comparison (NaN) is never equal.
+fn emit_double_equality(is_equal: bool, dst: AsmOperand) -> Vec<AsmInstruction> {
+    let (cond_code, parity_code, combine) = if is_equal {
+        (
+            AsmCondCode::E,
+            AsmCondCode::NP,
+            AsmBinaryOperator::BitwiseAnd,
+        )
+    } else {
+        (
+            AsmCondCode::NE,
+            AsmCondCode::P,
+            AsmBinaryOperator::BitwiseOr,
+        )
+    };
+    let parity = AsmOperand::Register(Reg::R11);
+    vec![
+        AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(0), dst.clone()),
+        AsmInstruction::SetCC(cond_code, dst.clone()),
+        AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(0), parity.clone()),
+        AsmInstruction::SetCC(parity_code, parity.clone()),
+        AsmInstruction::Binary(combine, AsmType::Longword, parity, dst),
+    ]
+}
+
+/// Comparisons of doubles (`comisd`) set the flags like unsigned comparisons, and set ZF,
+/// PF and CF when they are unordered. `a < b` and `a <= b` are emitted as `b > a` and
+/// `b >= a`, which only look at CF and ZF being clear, so they are false for NaN.
+fn emit_double_relational(
+    op: TackyBinaryOperator,
+    src_1: TackyValue,
+    src_2: TackyValue,
+    dst: TackyValue,
+) -> Vec<AsmInstruction> {
+    let (left, right, cond_code) = match op {
+        TackyBinaryOperator::Equal | TackyBinaryOperator::NotEqual => {
+            let mut instructions = vec![AsmInstruction::Cmp(
+                AsmType::Double,
+                AsmOperand::from(src_2),
+                AsmOperand::from(src_1),
+            )];
+            instructions.extend(emit_double_equality(
+                op == TackyBinaryOperator::Equal,
+                AsmOperand::from(dst),
+            ));
+            return instructions;
+        }
+        TackyBinaryOperator::GreaterThan => (src_1, src_2, AsmCondCode::A),
+        TackyBinaryOperator::GreaterThanOrEqual => (src_1, src_2, AsmCondCode::AE),
+        TackyBinaryOperator::LessThan => (src_2, src_1, AsmCondCode::A),
+        TackyBinaryOperator::LessThanOrEqual => (src_2, src_1, AsmCondCode::AE),
+        _ => panic!("invalid relational operator for doubles: {op:?}"),
+    };
+    vec![
+        AsmInstruction::Cmp(
+            AsmType::Double,
+            AsmOperand::from(right),
+            AsmOperand::from(left),
+        ),
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(0),
+            AsmOperand::from(dst.clone()),
+        ),
+        AsmInstruction::SetCC(cond_code, AsmOperand::from(dst)),
+    ]
+}
+
 fn emit_relational(
     op: TackyBinaryOperator,
     src_1: TackyValue,
     src_2: TackyValue,
     dst: TackyValue,
+    symbols: &SymbolTable,
 ) -> Vec<AsmInstruction> {
+    if is_double(&src_1, symbols) {
+        return emit_double_relational(op, src_1, src_2, dst);
+    }
+
+    let cond_code = if is_signed(&src_1, symbols) {
+        AsmCondCode::from(op)
+    } else {
+        AsmCondCode::from(op).to_unsigned()
+    };
     vec![
-        AsmInstruction::Cmp(AsmOperand::from(src_2), AsmOperand::from(src_1)),
-        AsmInstruction::Mov(AsmOperand::Imm(0), AsmOperand::from(dst.clone())),
-        AsmInstruction::SetCC(AsmCondCode::from(op), AsmOperand::from(dst)),
+        AsmInstruction::Cmp(
+            asm_type(&src_1, symbols),
+            AsmOperand::from(src_2),
+            AsmOperand::from(src_1),
+        ),
+        AsmInstruction::Mov(
+            asm_type(&dst, symbols),
+            AsmOperand::Imm(0),
+            AsmOperand::from(dst.clone()),
+        ),
+        AsmInstruction::SetCC(cond_code, AsmOperand::from(dst)),
     ]
 }
 
@@ -45,53 +445,645 @@ fn emit_div_rem(
     src_1: TackyValue,
     src_2: TackyValue,
     dst: TackyValue,
+    symbols: &SymbolTable,
 ) -> Vec<AsmInstruction> {
+    let asm_type = asm_type(&src_1, symbols);
     let result_reg = if is_div { Reg::AX } else { Reg::DX };
+    // signed division sign extends AX into DX, unsigned division zeroes DX
+    let division = if is_signed(&src_1, symbols) {
+        vec![
+            AsmInstruction::Cdq(asm_type),
+            AsmInstruction::Idiv(asm_type, AsmOperand::from(src_2)),
+        ]
+    } else {
+        vec![
+            AsmInstruction::Mov(asm_type, AsmOperand::Imm(0), AsmOperand::Register(Reg::DX)),
+            AsmInstruction::Div(asm_type, AsmOperand::from(src_2)),
+        ]
+    };
+
+    let mut instructions = vec![AsmInstruction::Mov(
+        asm_type,
+        AsmOperand::from(src_1),
+        AsmOperand::Register(Reg::AX),
+    )];
+    instructions.extend(division);
+    instructions.push(AsmInstruction::Mov(
+        asm_type,
+        AsmOperand::Register(result_reg),
+        AsmOperand::from(dst),
+    ));
+    instructions
+}
+
+/// Splits arguments by how they are passed: the first six integers in `ARG_REGISTERS`,
+/// the first eight doubles in `DOUBLE_ARG_REGISTERS` and the rest on the stack, in order.
+/// A structure is split in eightbytes, which go in registers according to their class
+/// only if there are registers left for all of them, and on the stack otherwise. When
+/// the function returns a structure in memory RDI holds its address, so the integer
+/// arguments start from RSI.
+/// Returns (register arguments with their register, stack arguments), both split in parts.
+#[allow(clippy::type_complexity)]
+fn classify_args(
+    args: Vec<TackyValue>,
+    return_in_memory: bool,
+    symbols: &SymbolTable,
+) -> (Vec<(Reg, AsmOperand, Part)>, Vec<(AsmOperand, Part)>) {
+    let int_registers = &ARG_REGISTERS[usize::from(return_in_memory)..];
+    let mut next_int = 0;
+    let mut next_double = 0;
+    let mut register_args = vec![];
+    let mut stack_args = vec![];
+    for arg in args {
+        if is_structure(&arg, symbols) {
+            let parts = eightbytes(&arg, symbols);
+            let needed_ints = parts
+                .iter()
+                .filter(|(_, _, class)| *class == ParamClass::Integer)
+                .count();
+            let needed_doubles = parts
+                .iter()
+                .filter(|(_, _, class)| *class == ParamClass::Sse)
+                .count();
+            let fits = parts
+                .iter()
+                .all(|(_, _, class)| *class != ParamClass::Memory)
+                && next_int + needed_ints <= int_registers.len()
+                && next_double + needed_doubles <= DOUBLE_ARG_REGISTERS.len();
+
+            for (operand, part, class) in parts {
+                if !fits {
+                    stack_args.push((operand, part));
+                } else if class == ParamClass::Sse {
+                    register_args.push((DOUBLE_ARG_REGISTERS[next_double].clone(), operand, part));
+                    next_double += 1;
+                } else {
+                    register_args.push((int_registers[next_int].clone(), operand, part));
+                    next_int += 1;
+                }
+            }
+            continue;
+        }
+
+        let register = if is_double(&arg, symbols) {
+            next_double += 1;
+            DOUBLE_ARG_REGISTERS.get(next_double - 1)
+        } else {
+            next_int += 1;
+            int_registers.get(next_int - 1)
+        };
+        let part = Part::Scalar(asm_type(&arg, symbols));
+        match register {
+            Some(reg) => register_args.push((reg.clone(), AsmOperand::from(arg), part)),
+            None => stack_args.push((AsmOperand::from(arg), part)),
+        }
+    }
+
+    (register_args, stack_args)
+}
+
+/// Copies each parameter from where the caller left it into its pseudo-register.
+/// The first six integers and the first eight doubles come in registers, the rest are
+/// on the stack above the saved RBP and the return address, 8 bytes each (structures
+/// take as many slots as they have eightbytes). A function that returns a structure in
+/// memory saves the address it gets in RDI first.
+fn emit_param_copies(
+    params: Vec<TackyIdentifier>,
+    return_in_memory: bool,
+    symbols: &SymbolTable,
+) -> Vec<AsmInstruction> {
+    let mut instructions = vec![];
+    if return_in_memory {
+        instructions.push(AsmInstruction::Mov(
+            AsmType::Quadword,
+            AsmOperand::Register(Reg::DI),
+            return_ptr(),
+        ));
+    }
+
+    let params = params.into_iter().map(TackyValue::Var).collect();
+    let (register_params, stack_params) = classify_args(params, return_in_memory, symbols);
+    for (reg, param, part) in register_params {
+        instructions.extend(emit_register_to_part(reg, part, param));
+    }
+    for (i, (param, part)) in stack_params.into_iter().enumerate() {
+        let src = AsmOperand::Stack(16 + 8 * i as i32);
+        match part {
+            Part::Scalar(ty) => instructions.push(AsmInstruction::Mov(ty, src, param)),
+            Part::Bytes(bytes) => instructions.extend(emit_copy_bytes(src, param, bytes)),
+        }
+    }
+    instructions
+}
+
+/// Where the variadic arguments of a function start, after its named parameters: the
+/// offsets in the register save area of the first integer and the first XMM register left,
+/// and the offset from RBP of the first stack argument left.
+fn va_start_offsets(
+    params: Vec<TackyIdentifier>,
+    return_in_memory: bool,
+    symbols: &SymbolTable,
+) -> (i64, i64, i32) {
+    let params = params.into_iter().map(TackyValue::Var).collect();
+    let (register_params, stack_params) = classify_args(params, return_in_memory, symbols);
+    let double_registers = register_params
+        .iter()
+        .filter(|(reg, _, _)| DOUBLE_ARG_REGISTERS.contains(reg))
+        .count();
+    let int_registers = register_params.len() - double_registers + usize::from(return_in_memory);
+
+    (
+        8 * int_registers as i64,
+        8 * ARG_REGISTERS.len() as i64 + 16 * double_registers as i64,
+        16 + 8 * stack_params.len() as i32,
+    )
+}
+
+/// Saves every argument register in the register save area, `va_arg` reads the variadic
+/// arguments passed in registers from there.
+fn emit_register_save() -> Vec<AsmInstruction> {
+    let int_saves = ARG_REGISTERS.iter().enumerate().map(|(i, reg)| {
+        AsmInstruction::Mov(
+            AsmType::Quadword,
+            AsmOperand::Register(reg.clone()),
+            reg_save_area(8 * i as i32),
+        )
+    });
+    let double_saves = DOUBLE_ARG_REGISTERS.iter().enumerate().map(|(i, reg)| {
+        AsmInstruction::Mov(
+            AsmType::Double,
+            AsmOperand::Register(reg.clone()),
+            reg_save_area(8 * ARG_REGISTERS.len() as i32 + 16 * i as i32),
+        )
+    });
+
+    int_saves.chain(double_saves).collect()
+}
+
+/// Initializes the `va_list` structure `ap` points to: the offsets of the next integer and
+/// XMM registers in the save area, the address of the next stack argument and the address of
+/// the save area. The pointer goes through AX and the addresses through R11.
+fn emit_va_start(
+    ap: TackyValue,
+    (gp_offset, fp_offset, overflow): (i64, i64, i32),
+) -> Vec<AsmInstruction> {
+    let ax = AsmOperand::Register(Reg::AX);
+    let r11 = AsmOperand::Register(Reg::R11);
+    vec![
+        AsmInstruction::Mov(AsmType::Quadword, AsmOperand::from(ap), ax),
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(gp_offset),
+            AsmOperand::Memory(Reg::AX, 0),
+        ),
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(fp_offset),
+            AsmOperand::Memory(Reg::AX, 4),
+        ),
+        AsmInstruction::Lea(AsmOperand::Stack(overflow), r11.clone()),
+        AsmInstruction::Mov(
+            AsmType::Quadword,
+            r11.clone(),
+            AsmOperand::Memory(Reg::AX, 8),
+        ),
+        AsmInstruction::Lea(reg_save_area(0), r11.clone()),
+        AsmInstruction::Mov(AsmType::Quadword, r11, AsmOperand::Memory(Reg::AX, 16)),
+    ]
+}
+
+/// Returns a structure. One larger than 16 bytes is copied to the address the caller passed,
+/// which is returned in RAX, a smaller one is returned in RAX and RDX (the INTEGER
+/// eightbytes) and XMM0 and XMM1 (the SSE ones).
+fn emit_structure_return(value: TackyValue, symbols: &SymbolTable) -> Vec<AsmInstruction> {
+    let mut instructions = vec![];
+    if returns_in_memory(&value_type(&value, symbols), symbols.types()) {
+        let ax = AsmOperand::Register(Reg::AX);
+        instructions.push(AsmInstruction::Mov(AsmType::Quadword, return_ptr(), ax));
+        instructions.extend(emit_copy_bytes(
+            AsmOperand::from(value.clone()).offset_by(0),
+            AsmOperand::Memory(Reg::AX, 0),
+            structure_size(&value, symbols),
+        ));
+    } else {
+        let mut int_registers = RETURN_REGISTERS.iter();
+        let mut double_registers = DOUBLE_RETURN_REGISTERS.iter();
+        for (operand, part, class) in eightbytes(&value, symbols) {
+            let reg = match class {
+                ParamClass::Sse => double_registers.next(),
+                _ => int_registers.next(),
+            };
+            if let Some(reg) = reg {
+                instructions.extend(emit_part_to_register(operand, part, reg.clone()));
+            }
+        }
+    }
+    instructions.push(AsmInstruction::Ret);
+
+    instructions
+}
+
+/// Lowers a function call following the System V calling convention.
+///
+/// Arguments that don't fit in registers are pushed in reverse order. Each one takes
+/// 8 bytes, so we pad the stack when there's an odd number of them to keep RSP 16-byte
+/// aligned at the call instruction. A structure returned in memory is written by the
+/// callee straight into `dst`, whose address is passed in RDI. `call` holds the instructions
+/// that make the call itself once the arguments are in place. A variadic function also
+/// gets the number of XMM registers holding arguments in AL.
+fn emit_fun_call(
+    call: Vec<AsmInstruction>,
+    variadic: bool,
+    args: Vec<TackyValue>,
+    dst: TackyValue,
+    symbols: &SymbolTable,
+) -> Vec<AsmInstruction> {
+    let return_in_memory = returns_in_memory(&value_type(&dst, symbols), symbols.types());
+    let (register_args, stack_args) = classify_args(args, return_in_memory, symbols);
+    let double_registers = register_args
+        .iter()
+        .filter(|(reg, _, _)| DOUBLE_ARG_REGISTERS.contains(reg))
+        .count();
+    let stack_padding = if stack_args.len() % 2 == 1 { 8 } else { 0 };
+    let bytes_to_remove = 8 * stack_args.len() as i32 + stack_padding;
+
+    let mut instructions = vec![];
+    if stack_padding != 0 {
+        instructions.push(AsmInstruction::AllocateStack(stack_padding));
+    }
+
+    if return_in_memory {
+        instructions.push(AsmInstruction::Lea(
+            AsmOperand::from(dst.clone()),
+            AsmOperand::Register(Reg::DI),
+        ));
+    }
+
+    for (reg, arg, part) in register_args {
+        instructions.extend(emit_part_to_register(arg, part, reg));
+    }
+
+    for (arg, part) in stack_args.into_iter().rev() {
+        match (arg, part) {
+            (op @ (AsmOperand::Imm(_) | AsmOperand::Register(_)), _) => {
+                instructions.push(AsmInstruction::Push(op))
+            }
+            (op, Part::Scalar(AsmType::Quadword | AsmType::Double)) => {
+                instructions.push(AsmInstruction::Push(op))
+            }
+            // pushq always reads 8 bytes, so smaller operands in memory go through AX first
+            (op, Part::Scalar(arg_type)) => {
+                instructions.push(AsmInstruction::Mov(
+                    arg_type,
+                    op,
+                    AsmOperand::Register(Reg::AX),
+                ));
+                instructions.push(AsmInstruction::Push(AsmOperand::Register(Reg::AX)));
+            }
+            // loose bytes of a structure are copied into a slot of their own
+            (op, Part::Bytes(bytes)) => {
+                instructions.push(AsmInstruction::AllocateStack(8));
+                instructions.extend(emit_copy_bytes(op, AsmOperand::Memory(Reg::SP, 0), bytes));
+            }
+        }
+    }
+
+    if variadic {
+        instructions.push(AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(double_registers as i64),
+            AsmOperand::Register(Reg::AX),
+        ));
+    }
+    instructions.extend(call);
+
+    if bytes_to_remove != 0 {
+        instructions.push(AsmInstruction::DeallocateStack(bytes_to_remove));
+    }
+
+    if is_structure(&dst, symbols) {
+        if !return_in_memory {
+            let mut int_registers = RETURN_REGISTERS.iter();
+            let mut double_registers = DOUBLE_RETURN_REGISTERS.iter();
+            for (operand, part, class) in eightbytes(&dst, symbols) {
+                let reg = match class {
+                    ParamClass::Sse => double_registers.next(),
+                    _ => int_registers.next(),
+                };
+                if let Some(reg) = reg {
+                    instructions.extend(emit_register_to_part(reg.clone(), part, operand));
+                }
+            }
+        }
+        return instructions;
+    }
+
+    let dst_type = asm_type(&dst, symbols);
+    instructions.push(AsmInstruction::Mov(
+        dst_type,
+        return_register(dst_type),
+        AsmOperand::from(dst),
+    ));
+
+    instructions
+}
+
+/// Converts an unsigned integer to a double. An unsigned char or int is zero extended and
+/// converted as a long. An unsigned long is split in its high and low 32 bits, both are
+/// converted exactly and combined as `high * 2^32 + low`, which rounds only once.
+fn emit_uint_to_double(
+    src: TackyValue,
+    dst: TackyValue,
+    symbols: &SymbolTable,
+) -> Vec<AsmInstruction> {
+    let r10 = AsmOperand::Register(Reg::R10);
+    let src_type = asm_type(&src, symbols);
+    if src_type != AsmType::Quadword {
+        return vec![
+            AsmInstruction::MovZeroExtend(
+                src_type,
+                AsmType::Quadword,
+                AsmOperand::from(src),
+                r10.clone(),
+            ),
+            AsmInstruction::Cvtsi2sd(AsmType::Quadword, r10, AsmOperand::from(dst)),
+        ];
+    }
+
+    let r11 = AsmOperand::Register(Reg::R11);
+    let xmm14 = AsmOperand::Register(Reg::XMM14);
+    let xmm15 = AsmOperand::Register(Reg::XMM15);
     vec![
-        AsmInstruction::Mov(AsmOperand::from(src_1), AsmOperand::Register(Reg::AX)),
-        AsmInstruction::Cdq,
-        AsmInstruction::Idiv(AsmOperand::from(src_2)),
-        AsmInstruction::Mov(AsmOperand::Register(result_reg), AsmOperand::from(dst)),
+        AsmInstruction::Mov(AsmType::Quadword, AsmOperand::from(src), r10.clone()),
+        AsmInstruction::Mov(AsmType::Quadword, r10.clone(), r11.clone()),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::LogicalRightShift,
+            AsmType::Quadword,
+            AsmOperand::Imm(32),
+            r11.clone(),
+        ),
+        // movl clears the high 32 bits
+        AsmInstruction::Mov(AsmType::Longword, r10.clone(), r10.clone()),
+        AsmInstruction::Cvtsi2sd(AsmType::Quadword, r11, xmm14.clone()),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::Mult,
+            AsmType::Double,
+            AsmOperand::DoubleConstant(4294967296.0f64.to_bits()),
+            xmm14.clone(),
+        ),
+        AsmInstruction::Cvtsi2sd(AsmType::Quadword, r10, xmm15.clone()),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::Add,
+            AsmType::Double,
+            xmm15,
+            xmm14.clone(),
+        ),
+        AsmInstruction::Mov(AsmType::Double, xmm14, AsmOperand::from(dst)),
+    ]
+}
+
+/// Converts a double to an unsigned integer. An unsigned char or int is converted as a long
+/// and truncated. For an unsigned long, values from 2^63 don't fit in a long, `cvttsd2si`
+/// returns 2^63 for them (the "integer indefinite" value): in that case the result is
+/// 2^63 plus the conversion of `value - 2^63`.
+fn emit_double_to_uint(
+    src: TackyValue,
+    dst: TackyValue,
+    symbols: &SymbolTable,
+) -> Vec<AsmInstruction> {
+    let r10 = AsmOperand::Register(Reg::R10);
+    let dst_type = asm_type(&dst, symbols);
+    if dst_type != AsmType::Quadword {
+        return vec![
+            AsmInstruction::Cvttsd2si(AsmType::Quadword, AsmOperand::from(src), r10.clone()),
+            AsmInstruction::Mov(dst_type, r10, AsmOperand::from(dst)),
+        ];
+    }
+
+    let r11 = AsmOperand::Register(Reg::R11);
+    let ax = AsmOperand::Register(Reg::AX);
+    let xmm14 = AsmOperand::Register(Reg::XMM14);
+    vec![
+        AsmInstruction::Cvttsd2si(
+            AsmType::Quadword,
+            AsmOperand::from(src.clone()),
+            r10.clone(),
+        ),
+        AsmInstruction::Mov(AsmType::Double, AsmOperand::from(src), xmm14.clone()),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::Sub,
+            AsmType::Double,
+            AsmOperand::DoubleConstant(9223372036854775808.0f64.to_bits()),
+            xmm14.clone(),
+        ),
+        AsmInstruction::Cvttsd2si(AsmType::Quadword, xmm14, r11.clone()),
+        // AX is all ones when the first conversion overflowed, zero otherwise
+        AsmInstruction::Mov(AsmType::Quadword, r10.clone(), ax.clone()),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::RightShift,
+            AsmType::Quadword,
+            AsmOperand::Imm(63),
+            ax.clone(),
+        ),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::BitwiseAnd,
+            AsmType::Quadword,
+            ax,
+            r11.clone(),
+        ),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::BitwiseOr,
+            AsmType::Quadword,
+            r11,
+            r10.clone(),
+        ),
+        AsmInstruction::Mov(AsmType::Quadword, r10, AsmOperand::from(dst)),
     ]
 }
 
 impl From<TackyProgram> for AsmProgram {
     fn from(tacky_program: TackyProgram) -> Self {
+        let mut symbols = tacky_program.symbols;
+        // needs a stack slot of 8 bytes in the functions that return a structure in memory
+        symbols.insert(
+            RETURN_PTR,
+            Symbol::new(
+                Type::Pointer(Box::new(Type::Char)),
+                IdentifierAttrs::LocalAttr,
+            ),
+        );
+        // and a register save area in the functions that call `va_start`
+        symbols.insert(
+            REG_SAVE_AREA,
+            Symbol::new(
+                Type::Array(Box::new(Type::Char), REG_SAVE_AREA_SIZE),
+                IdentifierAttrs::LocalAttr,
+            ),
+        );
+        let symbols = &symbols;
+        let function_definitions: Vec<_> = tacky_program
+            .function_definitions
+            .into_iter()
+            .map(|f| AsmFunctionDefinition::from(f, symbols))
+            .collect();
+        let mut static_constants = AsmStaticConstant::collect(&function_definitions);
+        static_constants.extend(
+            tacky_program
+                .static_constants
+                .into_iter()
+                .map(|c| AsmStaticConstant::from(c, symbols)),
+        );
         AsmProgram {
-            function_definition: AsmFunctionDefinition::from(tacky_program.function_definition),
+            function_definitions,
+            static_variables: tacky_program
+                .static_variables
+                .into_iter()
+                .map(|v| AsmStaticVariable::from(v, symbols))
+                .collect(),
+            static_constants,
+            symbols: symbols.clone(),
+        }
+    }
+}
+
+impl AsmStaticVariable {
+    /// The alignment comes from the type of the variable, arrays can't tell it from their
+    /// initial value.
+    fn from(static_variable: TackyStaticVariable, symbols: &SymbolTable) -> Self {
+        let alignment = match (
+            symbols.get(&static_variable.name.value),
+            &static_variable.init,
+        ) {
+            (Some(symbol), _) => symbol.ty.alignment(symbols.types()) as i32,
+            (None, StaticInit::CharInit(_) | StaticInit::UCharInit(_)) => AsmType::Byte.size(),
+            (None, StaticInit::IntInit(_) | StaticInit::UIntInit(_)) => AsmType::Longword.size(),
+            (None, _) => AsmType::Quadword.size(),
+        };
+        AsmStaticVariable {
+            name: AsmIdentifier::from(static_variable.name),
+            global: static_variable.global,
+            alignment,
+            init: static_variable.init,
+        }
+    }
+}
+
+impl AsmStaticConstant {
+    fn from(static_constant: TackyStaticConstant, symbols: &SymbolTable) -> Self {
+        AsmStaticConstant {
+            name: AsmIdentifier::from(static_constant.name),
+            alignment: static_constant.ty.alignment(symbols.types()) as i32,
+            init: static_constant.init,
         }
     }
 }
 
-impl From<TackyFunctionDefinition> for AsmFunctionDefinition {
-    fn from(tacky_function_definition: TackyFunctionDefinition) -> Self {
+impl AsmFunctionDefinition {
+    fn from(tacky_function_definition: TackyFunctionDefinition, symbols: &SymbolTable) -> Self {
+        let return_in_memory = match symbols.get(&tacky_function_definition.name.value) {
+            Some(Symbol {
+                ty: Type::FunType(_, return_type, _),
+                ..
+            }) => returns_in_memory(return_type, symbols.types()),
+            _ => false,
+        };
+        let calls_va_start = tacky_function_definition
+            .instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::VaStart(_)));
+        let va_offsets = va_start_offsets(
+            tacky_function_definition.params.clone(),
+            return_in_memory,
+            symbols,
+        );
+
+        // the argument registers are saved before anything can overwrite them
+        let mut instructions = if calls_va_start {
+            emit_register_save()
+        } else {
+            vec![]
+        };
+        instructions.extend(emit_param_copies(
+            tacky_function_definition.params,
+            return_in_memory,
+            symbols,
+        ));
+        instructions.extend(tacky_function_definition.instructions.into_iter().flat_map(
+            |i| match i {
+                TackyInstruction::VaStart(ap) => emit_va_start(ap, va_offsets),
+                i => AsmInstruction::from(i, symbols),
+            },
+        ));
+
         AsmFunctionDefinition {
             name: AsmIdentifier::from(tacky_function_definition.name),
-            instructions: tacky_function_definition
-                .instructions
-                .into_iter()
-                .flat_map(AsmInstruction::from)
-                .collect::<Vec<AsmInstruction>>(),
+            global: tacky_function_definition.global,
+            instructions,
         }
     }
 }
 
 impl AsmInstruction {
-    fn from(tacky_instruction: TackyInstruction) -> Vec<AsmInstruction> {
+    fn from(tacky_instruction: TackyInstruction, symbols: &SymbolTable) -> Vec<AsmInstruction> {
+        let asm_type = |value: &TackyValue| asm_type(value, symbols);
         match tacky_instruction {
             TackyInstruction::Comment(_) => vec![],
+            TackyInstruction::Return(val) if is_structure(&val, symbols) => {
+                emit_structure_return(val, symbols)
+            }
             TackyInstruction::Return(val) => vec![
-                AsmInstruction::Mov(AsmOperand::from(val), AsmOperand::Register(Reg::AX)),
+                AsmInstruction::Mov(
+                    asm_type(&val),
+                    AsmOperand::from(val.clone()),
+                    return_register(asm_type(&val)),
+                ),
                 AsmInstruction::Ret,
             ],
+            TackyInstruction::Unary(TackyUnaryOperator::Not, src, dst)
+                if is_double(&src, symbols) =>
+            {
+                let mut instructions = emit_compare_to_zero(src);
+                instructions.extend(emit_double_equality(true, AsmOperand::from(dst)));
+                instructions
+            }
             TackyInstruction::Unary(TackyUnaryOperator::Not, src, dst) => vec![
-                AsmInstruction::Cmp(AsmOperand::Imm(0), AsmOperand::from(src)),
-                AsmInstruction::Mov(AsmOperand::Imm(0), AsmOperand::from(dst.clone())),
+                AsmInstruction::Cmp(asm_type(&src), AsmOperand::Imm(0), AsmOperand::from(src)),
+                AsmInstruction::Mov(
+                    asm_type(&dst),
+                    AsmOperand::Imm(0),
+                    AsmOperand::from(dst.clone()),
+                ),
                 AsmInstruction::SetCC(AsmCondCode::E, AsmOperand::from(dst)),
             ],
+            // flipping the sign bit, the mask is read from a 16-byte aligned constant
+            TackyInstruction::Unary(TackyUnaryOperator::Negate, src, dst)
+                if is_double(&src, symbols) =>
+            {
+                vec![
+                    AsmInstruction::Mov(
+                        AsmType::Double,
+                        AsmOperand::from(src),
+                        AsmOperand::from(dst.clone()),
+                    ),
+                    AsmInstruction::Binary(
+                        AsmBinaryOperator::BitwiseXor,
+                        AsmType::Double,
+                        AsmOperand::DoubleConstant((-0.0f64).to_bits()),
+                        AsmOperand::from(dst),
+                    ),
+                ]
+            }
             TackyInstruction::Unary(unary_op, src, dst) => vec![
-                AsmInstruction::Mov(AsmOperand::from(src), AsmOperand::from(dst.clone())),
-                AsmInstruction::Unary(AsmUnaryOperator::from(unary_op), AsmOperand::from(dst)),
+                AsmInstruction::Mov(
+                    asm_type(&src),
+                    AsmOperand::from(src.clone()),
+                    AsmOperand::from(dst.clone()),
+                ),
+                AsmInstruction::Unary(
+                    AsmUnaryOperator::from(unary_op),
+                    asm_type(&src),
+                    AsmOperand::from(dst),
+                ),
             ],
             TackyInstruction::Binary(op, src_1, src_2, dst) => match op {
                 // arithmetic and bitwise
@@ -102,43 +1094,345 @@ impl AsmInstruction {
                 | TackyBinaryOperator::BitwiseOr
                 | TackyBinaryOperator::BitwiseXor
                 | TackyBinaryOperator::LeftShift
-                | TackyBinaryOperator::RightShift => vec![
-                    AsmInstruction::Mov(AsmOperand::from(src_1), AsmOperand::from(dst.clone())),
-                    AsmInstruction::Binary(
-                        AsmBinaryOperator::from(op),
-                        AsmOperand::from(src_2),
-                        AsmOperand::from(dst),
-                    ),
-                ],
+                | TackyBinaryOperator::RightShift => {
+                    let asm_op = match op {
+                        TackyBinaryOperator::RightShift if !is_signed(&src_1, symbols) => {
+                            AsmBinaryOperator::LogicalRightShift
+                        }
+                        op => AsmBinaryOperator::from(op),
+                    };
+                    vec![
+                        AsmInstruction::Mov(
+                            asm_type(&src_1),
+                            AsmOperand::from(src_1.clone()),
+                            AsmOperand::from(dst.clone()),
+                        ),
+                        AsmInstruction::Binary(
+                            asm_op,
+                            asm_type(&src_1),
+                            AsmOperand::from(src_2),
+                            AsmOperand::from(dst),
+                        ),
+                    ]
+                }
                 // relational
                 TackyBinaryOperator::Equal
                 | TackyBinaryOperator::NotEqual
                 | TackyBinaryOperator::GreaterThan
                 | TackyBinaryOperator::LessThan
                 | TackyBinaryOperator::LessThanOrEqual
-                | TackyBinaryOperator::GreaterThanOrEqual => emit_relational(op, src_1, src_2, dst),
+                | TackyBinaryOperator::GreaterThanOrEqual => {
+                    emit_relational(op, src_1, src_2, dst, symbols)
+                }
                 // division and remainder
-                TackyBinaryOperator::Divide => emit_div_rem(true, src_1, src_2, dst),
-                TackyBinaryOperator::Remainder => emit_div_rem(false, src_1, src_2, dst),
+                TackyBinaryOperator::Divide if is_double(&src_1, symbols) => vec![
+                    AsmInstruction::Mov(
+                        AsmType::Double,
+                        AsmOperand::from(src_1),
+                        AsmOperand::from(dst.clone()),
+                    ),
+                    AsmInstruction::Binary(
+                        AsmBinaryOperator::DivDouble,
+                        AsmType::Double,
+                        AsmOperand::from(src_2),
+                        AsmOperand::from(dst),
+                    ),
+                ],
+                TackyBinaryOperator::Divide => emit_div_rem(true, src_1, src_2, dst, symbols),
+                TackyBinaryOperator::Remainder => emit_div_rem(false, src_1, src_2, dst, symbols),
             },
             TackyInstruction::Jump(id) => vec![AsmInstruction::Jmp(AsmIdentifier::from(id))],
-            TackyInstruction::JumpIfZero(cond, target) => emit_conditional_jump(cond, target, true),
+            TackyInstruction::JumpIfZero(cond, target) => {
+                emit_conditional_jump(cond, target, true, symbols)
+            }
             TackyInstruction::JumpIfNotZero(cond, target) => {
-                emit_conditional_jump(cond, target, false)
+                emit_conditional_jump(cond, target, false, symbols)
+            }
+            // the jump itself uses R11 to hold the address of the table
+            TackyInstruction::JumpTable(index, table, targets) => vec![
+                AsmInstruction::Mov(
+                    AsmType::Quadword,
+                    AsmOperand::from(index),
+                    AsmOperand::Register(Reg::R10),
+                ),
+                AsmInstruction::JmpTable(
+                    Reg::R10,
+                    AsmIdentifier::from(table),
+                    targets.into_iter().map(AsmIdentifier::from).collect(),
+                ),
+            ],
+            TackyInstruction::Copy(src, dst) if is_structure(&src, symbols) => {
+                let size = structure_size(&src, symbols);
+                emit_copy_bytes(
+                    AsmOperand::from(src).offset_by(0),
+                    AsmOperand::from(dst).offset_by(0),
+                    size,
+                )
             }
             TackyInstruction::Copy(src, dst) => vec![AsmInstruction::Mov(
+                asm_type(&src),
+                AsmOperand::from(src),
+                AsmOperand::from(dst),
+            )],
+            TackyInstruction::SignExtend(src, dst) => vec![AsmInstruction::Movsx(
+                asm_type(&src),
+                asm_type(&dst),
+                AsmOperand::from(src),
+                AsmOperand::from(dst),
+            )],
+            TackyInstruction::ZeroExtend(src, dst) => vec![AsmInstruction::MovZeroExtend(
+                asm_type(&src),
+                asm_type(&dst),
+                AsmOperand::from(src),
+                AsmOperand::from(dst),
+            )],
+            // moving the low bytes is enough to truncate a value
+            TackyInstruction::Truncate(src, dst) => vec![AsmInstruction::Mov(
+                asm_type(&dst),
+                AsmOperand::from(src),
+                AsmOperand::from(dst),
+            )],
+            // there are no byte conversions, chars are converted as ints
+            TackyInstruction::IntToDouble(src, dst) if asm_type(&src) == AsmType::Byte => {
+                let r10 = AsmOperand::Register(Reg::R10);
+                vec![
+                    AsmInstruction::Movsx(
+                        AsmType::Byte,
+                        AsmType::Longword,
+                        AsmOperand::from(src),
+                        r10.clone(),
+                    ),
+                    AsmInstruction::Cvtsi2sd(AsmType::Longword, r10, AsmOperand::from(dst)),
+                ]
+            }
+            TackyInstruction::IntToDouble(src, dst) => vec![AsmInstruction::Cvtsi2sd(
+                asm_type(&src),
+                AsmOperand::from(src),
+                AsmOperand::from(dst),
+            )],
+            TackyInstruction::DoubleToInt(src, dst) if asm_type(&dst) == AsmType::Byte => {
+                let r10 = AsmOperand::Register(Reg::R10);
+                vec![
+                    AsmInstruction::Cvttsd2si(
+                        AsmType::Longword,
+                        AsmOperand::from(src),
+                        r10.clone(),
+                    ),
+                    AsmInstruction::Mov(AsmType::Byte, r10, AsmOperand::from(dst)),
+                ]
+            }
+            TackyInstruction::DoubleToInt(src, dst) => vec![AsmInstruction::Cvttsd2si(
+                asm_type(&dst),
                 AsmOperand::from(src),
                 AsmOperand::from(dst),
             )],
+            TackyInstruction::UIntToDouble(src, dst) => emit_uint_to_double(src, dst, symbols),
+            TackyInstruction::DoubleToUInt(src, dst) => emit_double_to_uint(src, dst, symbols),
+            // the address of a function is read from the GOT, `lea` can't reach functions
+            // from shared libraries in a position independent executable
+            TackyInstruction::GetAddress(TackyValue::Var(id), dst)
+                if matches!(
+                    value_type(&TackyValue::Var(id.clone()), symbols),
+                    Type::FunType(..)
+                ) =>
+            {
+                vec![AsmInstruction::Mov(
+                    AsmType::Quadword,
+                    AsmOperand::GotEntry(AsmIdentifier::from(id)),
+                    AsmOperand::from(dst),
+                )]
+            }
+            TackyInstruction::GetAddress(src, dst) => vec![AsmInstruction::Lea(
+                AsmOperand::from(src),
+                AsmOperand::from(dst),
+            )],
+            // the pointer goes through AX, which isn't live across TACKY instructions
+            TackyInstruction::Load(src_ptr, dst) if is_structure(&dst, symbols) => {
+                let size = structure_size(&dst, symbols);
+                let mut instructions = vec![AsmInstruction::Mov(
+                    AsmType::Quadword,
+                    AsmOperand::from(src_ptr),
+                    AsmOperand::Register(Reg::AX),
+                )];
+                instructions.extend(emit_copy_bytes(
+                    AsmOperand::Memory(Reg::AX, 0),
+                    AsmOperand::from(dst).offset_by(0),
+                    size,
+                ));
+                instructions
+            }
+            TackyInstruction::Load(src_ptr, dst) => vec![
+                AsmInstruction::Mov(
+                    AsmType::Quadword,
+                    AsmOperand::from(src_ptr),
+                    AsmOperand::Register(Reg::AX),
+                ),
+                AsmInstruction::Mov(
+                    asm_type(&dst),
+                    AsmOperand::Memory(Reg::AX, 0),
+                    AsmOperand::from(dst),
+                ),
+            ],
+            TackyInstruction::Store(src, dst_ptr) if is_structure(&src, symbols) => {
+                let size = structure_size(&src, symbols);
+                let mut instructions = vec![AsmInstruction::Mov(
+                    AsmType::Quadword,
+                    AsmOperand::from(dst_ptr),
+                    AsmOperand::Register(Reg::AX),
+                )];
+                instructions.extend(emit_copy_bytes(
+                    AsmOperand::from(src).offset_by(0),
+                    AsmOperand::Memory(Reg::AX, 0),
+                    size,
+                ));
+                instructions
+            }
+            TackyInstruction::Store(src, dst_ptr) => vec![
+                AsmInstruction::Mov(
+                    AsmType::Quadword,
+                    AsmOperand::from(dst_ptr),
+                    AsmOperand::Register(Reg::AX),
+                ),
+                AsmInstruction::Mov(
+                    asm_type(&src),
+                    AsmOperand::from(src),
+                    AsmOperand::Memory(Reg::AX, 0),
+                ),
+            ],
+            TackyInstruction::AddPtr(ptr, index, scale, dst) => {
+                emit_add_ptr(ptr, index, scale, dst)
+            }
+            TackyInstruction::CopyToOffset(src, dst, offset) if is_structure(&src, symbols) => {
+                let size = structure_size(&src, symbols);
+                emit_copy_bytes(
+                    AsmOperand::from(src).offset_by(0),
+                    AsmOperand::PseudoMem(AsmIdentifier::from(dst), offset as i32),
+                    size,
+                )
+            }
+            TackyInstruction::CopyToOffset(src, dst, offset) => vec![AsmInstruction::Mov(
+                asm_type(&src),
+                AsmOperand::from(src),
+                AsmOperand::PseudoMem(AsmIdentifier::from(dst), offset as i32),
+            )],
+            TackyInstruction::CopyFromOffset(src, offset, dst) if is_structure(&dst, symbols) => {
+                let size = structure_size(&dst, symbols);
+                emit_copy_bytes(
+                    AsmOperand::PseudoMem(AsmIdentifier::from(src), offset as i32),
+                    AsmOperand::from(dst).offset_by(0),
+                    size,
+                )
+            }
+            TackyInstruction::CopyFromOffset(src, offset, dst) => vec![AsmInstruction::Mov(
+                asm_type(&dst),
+                AsmOperand::PseudoMem(AsmIdentifier::from(src), offset as i32),
+                AsmOperand::from(dst),
+            )],
             TackyInstruction::Label(id) => vec![AsmInstruction::Label(AsmIdentifier::from(id))],
+            TackyInstruction::FunCall(name, args, dst) => {
+                let variadic = matches!(
+                    symbols.get(&name.value),
+                    Some(Symbol {
+                        ty: Type::FunType(_, _, true),
+                        ..
+                    })
+                );
+                emit_fun_call(
+                    vec![AsmInstruction::Call(AsmIdentifier::from(name))],
+                    variadic,
+                    args,
+                    dst,
+                    symbols,
+                )
+            }
+            // the pointer is loaded last, R11 isn't used to pass arguments
+            TackyInstruction::IndirectCall(fun_ptr, args, dst) => {
+                let variadic = match value_type(&fun_ptr, symbols) {
+                    Type::Pointer(referenced) => matches!(*referenced, Type::FunType(_, _, true)),
+                    _ => false,
+                };
+                emit_fun_call(
+                    vec![
+                        AsmInstruction::Mov(
+                            AsmType::Quadword,
+                            AsmOperand::from(fun_ptr),
+                            AsmOperand::Register(Reg::R11),
+                        ),
+                        AsmInstruction::IndirectCall(Reg::R11),
+                    ],
+                    variadic,
+                    args,
+                    dst,
+                    symbols,
+                )
+            }
+            TackyInstruction::VaStart(_) => {
+                panic!("va_start, lowered with the parameters of its function")
+            }
         }
     }
 }
 
+/// Lowers `ptr + index * scale`. The pointer goes to AX and the index to DX: a constant index
+/// becomes a displacement, the scales supported by x86 addressing are used directly and any
+/// other scale is applied with a multiplication first.
+fn emit_add_ptr(
+    ptr: TackyValue,
+    index: TackyValue,
+    scale: usize,
+    dst: TackyValue,
+) -> Vec<AsmInstruction> {
+    let mut instructions = vec![AsmInstruction::Mov(
+        AsmType::Quadword,
+        AsmOperand::from(ptr),
+        AsmOperand::Register(Reg::AX),
+    )];
+
+    let displacement = match &index {
+        TackyValue::Constant(c) => c
+            .as_i64()
+            .checked_mul(scale as i64)
+            .and_then(|d| i32::try_from(d).ok()),
+        TackyValue::Var(_) => None,
+    };
+
+    let address = match displacement {
+        Some(displacement) => AsmOperand::Memory(Reg::AX, displacement),
+        None => {
+            instructions.push(AsmInstruction::Mov(
+                AsmType::Quadword,
+                AsmOperand::from(index),
+                AsmOperand::Register(Reg::DX),
+            ));
+            if let 1 | 2 | 4 | 8 = scale {
+                AsmOperand::Indexed(Reg::AX, Reg::DX, scale as i32)
+            } else {
+                instructions.push(AsmInstruction::Binary(
+                    AsmBinaryOperator::Mult,
+                    AsmType::Quadword,
+                    AsmOperand::Imm(scale as i64),
+                    AsmOperand::Register(Reg::DX),
+                ));
+                AsmOperand::Indexed(Reg::AX, Reg::DX, 1)
+            }
+        }
+    };
+    instructions.push(AsmInstruction::Lea(address, AsmOperand::from(dst)));
+
+    instructions
+}
+
 impl From<TackyValue> for AsmOperand {
     fn from(tacky_value: TackyValue) -> Self {
         match tacky_value {
-            TackyValue::Constant(c) => AsmOperand::Imm(c),
+            TackyValue::Constant(Const::ConstInt(c)) => AsmOperand::Imm(c as i64),
+            TackyValue::Constant(Const::ConstLong(c)) => AsmOperand::Imm(c),
+            // keep the bits of the unsigned value, as longword instructions only read 32 of them
+            TackyValue::Constant(Const::ConstUInt(c)) => AsmOperand::Imm(c as i32 as i64),
+            TackyValue::Constant(Const::ConstULong(c)) => AsmOperand::Imm(c as i64),
+            TackyValue::Constant(Const::ConstChar(c)) => AsmOperand::Imm(c as i64),
+            TackyValue::Constant(Const::ConstUChar(c)) => AsmOperand::Imm(c as i8 as i64),
+            TackyValue::Constant(Const::ConstDouble(c)) => AsmOperand::DoubleConstant(c.to_bits()),
             TackyValue::Var(id) => AsmOperand::Pseudo(AsmIdentifier::from(id)),
         }
     }
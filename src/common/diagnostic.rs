@@ -0,0 +1,315 @@
+//! Diagnostics reported to the user.
+//!
+//! A `Diagnostic` is an error (or a warning) with a stable code, the span it's about, and
+//! optional secondary labels and notes. It's rendered like rustc does, with the source
+//! lines and carets under the spans, or as one JSON object per line for tools.
+//!
+//! ```text
+//! error[E0301]: could not parse expression
+//!  --> main.c:3:14
+//!   |
+//! 3 |   return x + ;
+//!   |              ^
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+use crate::common::span::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// A secondary span with a message, e.g. the previous declaration of a redeclared name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A message about the source. The primary span is unknown when the error isn't about a
+/// specific part of the source (e.g. a file that can't be read).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            message: message.into(),
+            span: Span::default(),
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, code, message)
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, code, message)
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Diagnostic { span, ..self }
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Renders the diagnostic for a terminal, with the source lines of its spans. The lines
+    /// that can't be found in `sources` are left out.
+    pub fn render(&self, sources: &mut SourceCache) -> String {
+        let mut out = format!("{}[{}]: {}\n", self.severity, self.code, self.message);
+
+        let mut marks = vec![(&self.span, '^', "")];
+        marks.extend(
+            self.labels
+                .iter()
+                .map(|label| (&label.span, '-', label.message.as_str())),
+        );
+        marks.retain(|(span, _, _)| !span.is_unknown());
+
+        let width = marks
+            .iter()
+            .map(|(span, _, _)| span.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(width);
+
+        let mut previous: Option<&Span> = None;
+        for (span, mark, message) in &marks {
+            let same_file = previous.is_some_and(|p| p.file == span.file);
+            if !same_file {
+                let arrow = if previous.is_none() { "-->" } else { ":::" };
+                out.push_str(&format!("{pad}{arrow} {span}\n{pad} |\n"));
+            }
+            let Some(text) = sources.line(&span.file, span.line) else {
+                previous = Some(span);
+                continue;
+            };
+            if !same_file || previous.is_some_and(|p| p.line != span.line) {
+                out.push_str(&format!("{:>width$} | {text}\n", span.line));
+            }
+
+            // the prefix keeps the tabs of the line, so the marks line up with it
+            let prefix = text
+                .chars()
+                .take(span.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+            let remaining = text.chars().count().saturating_sub(prefix.chars().count());
+            let length = if span.end_line == span.line {
+                span.end_column.saturating_sub(span.column)
+            } else {
+                remaining
+            };
+            let length = length.min(remaining).max(1);
+            let marks = mark.to_string().repeat(length);
+            let line = format!("{pad} | {prefix}{marks} {message}");
+            out.push_str(line.trim_end());
+            out.push('\n');
+
+            previous = Some(span);
+        }
+
+        if !self.notes.is_empty() {
+            if !marks.is_empty() {
+                out.push_str(&format!("{pad} |\n"));
+            }
+            for note in &self.notes {
+                out.push_str(&format!("{pad} = note: {note}\n"));
+            }
+        }
+
+        out
+    }
+
+    /// Renders the diagnostic as a JSON object on a single line. An unknown span is `null`.
+    /// The byte range of a span is in its file, read from `sources`: it's `null` when the
+    /// file can't be read.
+    pub fn to_json(&self, sources: &mut SourceCache) -> String {
+        let labels = self
+            .labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"span\":{},\"message\":{}}}",
+                    span_json(&label.span, sources),
+                    json_string(&label.message)
+                )
+            })
+            .collect::<Vec<_>>();
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| json_string(note))
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"span\":{},\"labels\":[{}],\"notes\":[{}]}}",
+            json_string(&self.severity.to_string()),
+            json_string(self.code),
+            json_string(&self.message),
+            span_json(&self.span, sources),
+            labels.join(","),
+            notes.join(",")
+        )
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// The message, prefixed with the location when it's known.
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.span.is_unknown() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.span, self.message)
+        }
+    }
+}
+
+impl From<Diagnostic> for String {
+    fn from(diagnostic: Diagnostic) -> Self {
+        diagnostic.to_string()
+    }
+}
+
+/// The source files, read the first time a diagnostic needs them.
+#[derive(Debug, Default)]
+pub struct SourceCache(HashMap<String, Option<SourceFile>>);
+
+/// A source with the offsets its lines start at.
+#[derive(Debug)]
+struct SourceFile {
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a source that isn't read from the disk.
+    pub fn insert(&mut self, file: &str, source: &str) {
+        self.0
+            .insert(file.to_string(), Some(SourceFile::new(source.to_string())));
+    }
+
+    /// Returns a line of a file, `None` if the file can't be read or is too short.
+    pub fn line(&mut self, file: &str, line: usize) -> Option<&str> {
+        self.file(file)?.line(line)
+    }
+
+    /// Returns the offset of a line and column in a file. A column past the end of its line
+    /// stands for the end of the line.
+    pub fn offset(&mut self, file: &str, line: usize, column: usize) -> Option<usize> {
+        let source = self.file(file)?;
+        let start = *source.line_starts.get(line.checked_sub(1)?)?;
+        let text = source.line(line)?;
+        let within = text
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map_or(text.len(), |(i, _)| i);
+
+        Some(start + within)
+    }
+
+    fn file(&mut self, file: &str) -> Option<&SourceFile> {
+        self.0
+            .entry(file.to_string())
+            .or_insert_with(|| fs::read_to_string(file).ok().map(SourceFile::new))
+            .as_ref()
+    }
+}
+
+impl SourceFile {
+    fn new(text: String) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        SourceFile { text, line_starts }
+    }
+
+    fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.text.len());
+
+        Some(self.text[start..end].trim_end_matches(['\n', '\r']))
+    }
+}
+
+fn span_json(span: &Span, sources: &mut SourceCache) -> String {
+    if span.is_unknown() {
+        return "null".to_string();
+    }
+
+    let byte = |offset: Option<usize>| offset.map_or("null".to_string(), |o| o.to_string());
+    let start = sources.offset(&span.file, span.line, span.column);
+    let end = sources.offset(&span.file, span.end_line, span.end_column);
+
+    format!(
+        "{{\"file\":{},\"line\":{},\"column\":{},\"byte_start\":{},\"byte_end\":{}}}",
+        json_string(&span.file),
+        span.line,
+        span.column,
+        byte(start),
+        byte(end)
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+
+    out
+}
@@ -0,0 +1,941 @@
+//! Errors of the compiler.
+//!
+//! Every pass fails with a `CompileError`, which says in which phase it failed and why, with
+//! the offending token or identifier as data. The errors about the source also have the
+//! span they're about, unknown when the pass doesn't know it. A pass that goes on after an
+//! error fails with all of them at once. The driver reports them as diagnostics.
+//!
+//! ```text
+//! match lex(code) {
+//!     Err(CompileError::Lex(LexError::UnexpectedCharacter(c), span)) => ...,
+//!     ...
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use crate::common::diagnostic::Diagnostic;
+use crate::common::span::Span;
+use crate::lexer::Token;
+
+/// An error that stops the compilation. The last field of the variants about the source
+/// is its span.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompileError {
+    Preprocess(PreprocessError, Span),
+    Lex(LexError, Span),
+    Parse(ParseError, Span),
+    Semantic(SemanticError, Span),
+    Codegen(CodegenError),
+    Driver(DriverError),
+    // the errors of a pass that recovered from them, in the order they were found
+    Multiple(Vec<CompileError>),
+}
+
+/// The errors of the preprocessor, with the span of the line of the directive or of the
+/// macro invocation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PreprocessError {
+    UnterminatedComment,
+    // the directive, e.g. `else` for an `#else` without `#if`
+    UnmatchedDirective(String),
+    DirectiveAfterElse(String),
+    UnterminatedConditional,
+    InvalidDirective(String),
+    // the message of `#error`
+    ErrorDirective(String),
+    ExpectedMacroName,
+    DefinedAsMacroName,
+    MacroRedefined(String),
+    InvalidMacroParameters,
+    DuplicateMacroParameter(String),
+    // the errors in the body of a macro, with its name
+    PastingAtEdge(String),
+    StringifyingNonParameter(String),
+    VaArgsInNonVariadic(String),
+    UnterminatedInvocation(String),
+    WrongMacroArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    InvalidPasting(String, String),
+    InvalidInclude,
+    IncludeNotFound(String),
+    UnreadableInclude(String),
+    IncludeTooDeep,
+    // InvalidCondition(the unexpected token, `None` at the end of the expression)
+    InvalidCondition(Option<String>),
+    InvalidConditionConstant(String),
+    EmptyCondition,
+    DivisionByZero,
+    InvalidLineDirective,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexError {
+    UnexpectedCharacter(char),
+    // the pattern of a token is invalid, it's a bug of the lexer
+    InvalidPattern(&'static str),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    // Expected(what, the token found instead, if any)
+    Expected(Construct, Option<Token>),
+    UnexpectedToken { expected: Token, found: Box<Token> },
+    UnexpectedEnd(Option<Token>),
+    ExpectedDeclaration(Token),
+    InvalidConstant(String),
+    InvalidCharacterConstant(String),
+    InvalidEscape(String),
+    UnsupportedEscape(String),
+    ArraySizeNotConstant(Option<Token>),
+    ArraySizeNotPositive(i64),
+    FunctionReturningFunction,
+    InvalidStorageClass,
+    InvalidTypeSpecifier(Vec<Token>),
+    TypedefInitialized(String),
+    TypedefFunction(String),
+    ConflictingTypedef(String),
+    ParameterNameOmitted(String),
+    FunctionDefinitionInList(String),
+    EmptyStructure(String),
+    StorageClassInMember,
+    TypeDefinitionInMember,
+    FunctionMember(String),
+    ExpectedStructureTag,
+    ExpectedEnumerationTag,
+    StorageClassInTypeName,
+    TypeDefinitionInTypeName,
+    StorageClassInForInit,
+    FunctionInForInit(String),
+    StructureInForInit(String),
+    EnumerationInForInit(Option<String>),
+}
+
+/// The parts of the grammar the parser expects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Construct {
+    BlockItem,
+    Statement,
+    Expression,
+    Factor,
+    Identifier,
+    UnaryOperator,
+    BinaryOperator,
+    CompoundAssignment,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SemanticError {
+    // declarations, the span is the one of the previous declaration or definition
+    DuplicateDeclaration(String, Box<Span>),
+    ConflictingLocalDeclarations(String),
+    NestedFunctionDefinition(String),
+    StaticBlockScopeFunction(String),
+    FunctionRedeclaredAsVariable(String),
+    ConflictingVariableTypes(String),
+    ConflictingLinkage(String),
+    ConflictingDefinitions(String),
+    IncompatibleFunctionDeclarations(String),
+    FunctionRedefined(String, Box<Span>),
+    StaticAfterNonStatic(String),
+    ExternInitializer(String),
+    UndeclaredVariable(String),
+    UndeclaredFunction(String),
+    // initializers, with the name of the initialized variable
+    InvalidStaticPointerInitializer(String),
+    InvalidStaticStructureInitializer(String),
+    NonConstantStaticInitializer(String),
+    InvalidArrayInitializer(String),
+    InvalidScalarInitializer(String),
+    InvalidStringInitializer(String),
+    TooManyArrayElements(String),
+    TooManyStructureElements(String),
+    // structures and enumerations, with their tag
+    UndeclaredStructure(String),
+    IncompleteStructure(String),
+    // IncompleteType(name of the variable)
+    IncompleteType(String),
+    StructureRedefined(String),
+    DuplicateMember {
+        tag: String,
+        member: String,
+    },
+    NoSuchMember {
+        tag: String,
+        member: String,
+    },
+    MemberOfNonStructure(String),
+    MemberThroughNonPointer(String),
+    NonConstantEnumerator(String),
+    EnumeratorOutOfRange(String),
+    // expressions
+    InvalidStructureOperand,
+    InvalidPointerOperand,
+    InvalidIntegerOperand,
+    InvalidComplementOperand,
+    IncompatiblePointerTypes,
+    IncompatibleStructureTypes,
+    InvalidConversion,
+    DiscardsQualifiers,
+    ConstLvalue(String),
+    InvalidLvalue,
+    AddressOfNonLvalue,
+    DereferenceNonPointer,
+    InvalidSubscript,
+    CastToArray,
+    CastToFunction,
+    StructureCast,
+    DoublePointerCast,
+    WrongArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    // a call through a function pointer
+    WrongPointerArgumentCount {
+        expected: usize,
+        found: usize,
+    },
+    VariableCalledAsFunction(String),
+    NotAFunction,
+    NotAVaList,
+    VaArgNonScalar,
+    VaStartFixedArguments,
+    VaStartNotLastParameter(String),
+    // statements, the span of a duplicate is the one of the first case or label
+    NonIntegerSwitch,
+    NonConstantCase,
+    CaseOutsideSwitch,
+    DuplicateCase(i64, Box<Span>),
+    MultipleDefaults(Box<Span>),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    UndefinedLabel(String),
+    DuplicateLabel(String, Box<Span>),
+}
+
+/// The errors of the assembly passes, which are bugs of the earlier passes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CodegenError {
+    MissingStackOffsets,
+    MissingStackSlot(String),
+    MissingFunctionOffset(String),
+    Emission,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DriverError {
+    // InvalidExtension(file, the extension it should have)
+    InvalidExtension(String, &'static str),
+    FileNotFound(String),
+    // Io(what couldn't be done, the file)
+    Io(&'static str, String),
+    AssemblyFailed(String),
+    MissingExitCode,
+}
+
+impl PreprocessError {
+    pub fn at(self, span: Span) -> CompileError {
+        CompileError::Preprocess(self, span)
+    }
+
+    /// The stable code of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PreprocessError::UnterminatedComment => "E0101",
+            PreprocessError::UnmatchedDirective(..) => "E0102",
+            PreprocessError::DirectiveAfterElse(..) => "E0103",
+            PreprocessError::UnterminatedConditional => "E0104",
+            PreprocessError::InvalidDirective(..) => "E0105",
+            PreprocessError::ErrorDirective(..) => "E0106",
+            PreprocessError::ExpectedMacroName => "E0107",
+            PreprocessError::DefinedAsMacroName => "E0108",
+            PreprocessError::MacroRedefined(..) => "E0109",
+            PreprocessError::InvalidMacroParameters => "E0110",
+            PreprocessError::DuplicateMacroParameter(..) => "E0111",
+            PreprocessError::PastingAtEdge(..) => "E0112",
+            PreprocessError::StringifyingNonParameter(..) => "E0113",
+            PreprocessError::VaArgsInNonVariadic(..) => "E0114",
+            PreprocessError::UnterminatedInvocation(..) => "E0115",
+            PreprocessError::WrongMacroArgumentCount { .. } => "E0116",
+            PreprocessError::InvalidPasting(..) => "E0117",
+            PreprocessError::InvalidInclude => "E0118",
+            PreprocessError::IncludeNotFound(..) => "E0119",
+            PreprocessError::UnreadableInclude(..) => "E0120",
+            PreprocessError::IncludeTooDeep => "E0121",
+            PreprocessError::InvalidCondition(..) => "E0122",
+            PreprocessError::InvalidConditionConstant(..) => "E0123",
+            PreprocessError::EmptyCondition => "E0124",
+            PreprocessError::DivisionByZero => "E0125",
+            PreprocessError::InvalidLineDirective => "E0126",
+        }
+    }
+}
+
+impl LexError {
+    pub fn at(self, span: Span) -> CompileError {
+        CompileError::Lex(self, span)
+    }
+
+    /// The stable code of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::UnexpectedCharacter(..) => "E0201",
+            LexError::InvalidPattern(..) => "E0202",
+        }
+    }
+}
+
+impl ParseError {
+    pub fn at(self, span: Span) -> CompileError {
+        CompileError::Parse(self, span)
+    }
+
+    /// The stable code of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::Expected(..) => "E0301",
+            ParseError::UnexpectedToken { .. } => "E0302",
+            ParseError::UnexpectedEnd(..) => "E0303",
+            ParseError::ExpectedDeclaration(..) => "E0304",
+            ParseError::InvalidConstant(..) => "E0305",
+            ParseError::InvalidCharacterConstant(..) => "E0306",
+            ParseError::InvalidEscape(..) => "E0307",
+            ParseError::UnsupportedEscape(..) => "E0308",
+            ParseError::ArraySizeNotConstant(..) => "E0309",
+            ParseError::ArraySizeNotPositive(..) => "E0310",
+            ParseError::FunctionReturningFunction => "E0311",
+            ParseError::InvalidStorageClass => "E0312",
+            ParseError::InvalidTypeSpecifier(..) => "E0313",
+            ParseError::TypedefInitialized(..) => "E0314",
+            ParseError::TypedefFunction(..) => "E0315",
+            ParseError::ConflictingTypedef(..) => "E0316",
+            ParseError::ParameterNameOmitted(..) => "E0317",
+            ParseError::FunctionDefinitionInList(..) => "E0318",
+            ParseError::EmptyStructure(..) => "E0319",
+            ParseError::StorageClassInMember => "E0320",
+            ParseError::TypeDefinitionInMember => "E0321",
+            ParseError::FunctionMember(..) => "E0322",
+            ParseError::ExpectedStructureTag => "E0323",
+            ParseError::ExpectedEnumerationTag => "E0324",
+            ParseError::StorageClassInTypeName => "E0325",
+            ParseError::TypeDefinitionInTypeName => "E0326",
+            ParseError::StorageClassInForInit => "E0327",
+            ParseError::FunctionInForInit(..) => "E0328",
+            ParseError::StructureInForInit(..) => "E0329",
+            ParseError::EnumerationInForInit(..) => "E0330",
+        }
+    }
+}
+
+impl SemanticError {
+    pub fn at(self, span: Span) -> CompileError {
+        CompileError::Semantic(self, span)
+    }
+
+    /// The span of the earlier occurrence of a duplicate, with what to label it.
+    pub fn previous(&self) -> Option<(&Span, &'static str)> {
+        match self {
+            SemanticError::DuplicateDeclaration(_, span) => {
+                Some((span, "previously declared here"))
+            }
+            SemanticError::FunctionRedefined(_, span) => Some((span, "previously defined here")),
+            SemanticError::DuplicateCase(_, span) => Some((span, "previous case here")),
+            SemanticError::MultipleDefaults(span) => Some((span, "previous default here")),
+            SemanticError::DuplicateLabel(_, span) => Some((span, "previously defined here")),
+            _ => None,
+        }
+    }
+
+    /// The stable code of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemanticError::DuplicateDeclaration(..) => "E0401",
+            SemanticError::ConflictingLocalDeclarations(..) => "E0402",
+            SemanticError::NestedFunctionDefinition(..) => "E0403",
+            SemanticError::StaticBlockScopeFunction(..) => "E0404",
+            SemanticError::FunctionRedeclaredAsVariable(..) => "E0405",
+            SemanticError::ConflictingVariableTypes(..) => "E0406",
+            SemanticError::ConflictingLinkage(..) => "E0407",
+            SemanticError::ConflictingDefinitions(..) => "E0408",
+            SemanticError::IncompatibleFunctionDeclarations(..) => "E0409",
+            SemanticError::FunctionRedefined(..) => "E0410",
+            SemanticError::StaticAfterNonStatic(..) => "E0411",
+            SemanticError::ExternInitializer(..) => "E0412",
+            SemanticError::UndeclaredVariable(..) => "E0413",
+            SemanticError::UndeclaredFunction(..) => "E0414",
+            SemanticError::InvalidStaticPointerInitializer(..) => "E0415",
+            SemanticError::InvalidStaticStructureInitializer(..) => "E0416",
+            SemanticError::NonConstantStaticInitializer(..) => "E0417",
+            SemanticError::InvalidArrayInitializer(..) => "E0418",
+            SemanticError::InvalidScalarInitializer(..) => "E0419",
+            SemanticError::InvalidStringInitializer(..) => "E0420",
+            SemanticError::TooManyArrayElements(..) => "E0421",
+            SemanticError::TooManyStructureElements(..) => "E0422",
+            SemanticError::UndeclaredStructure(..) => "E0423",
+            SemanticError::IncompleteStructure(..) => "E0424",
+            SemanticError::IncompleteType(..) => "E0425",
+            SemanticError::StructureRedefined(..) => "E0426",
+            SemanticError::DuplicateMember { .. } => "E0427",
+            SemanticError::NoSuchMember { .. } => "E0428",
+            SemanticError::MemberOfNonStructure(..) => "E0429",
+            SemanticError::MemberThroughNonPointer(..) => "E0430",
+            SemanticError::NonConstantEnumerator(..) => "E0431",
+            SemanticError::EnumeratorOutOfRange(..) => "E0432",
+            SemanticError::InvalidStructureOperand => "E0433",
+            SemanticError::InvalidPointerOperand => "E0434",
+            SemanticError::InvalidIntegerOperand => "E0435",
+            SemanticError::InvalidComplementOperand => "E0436",
+            SemanticError::IncompatiblePointerTypes => "E0437",
+            SemanticError::IncompatibleStructureTypes => "E0438",
+            SemanticError::InvalidConversion => "E0439",
+            SemanticError::DiscardsQualifiers => "E0440",
+            SemanticError::ConstLvalue(..) => "E0441",
+            SemanticError::InvalidLvalue => "E0442",
+            SemanticError::AddressOfNonLvalue => "E0443",
+            SemanticError::DereferenceNonPointer => "E0444",
+            SemanticError::InvalidSubscript => "E0445",
+            SemanticError::CastToArray => "E0446",
+            SemanticError::CastToFunction => "E0447",
+            SemanticError::StructureCast => "E0448",
+            SemanticError::DoublePointerCast => "E0449",
+            SemanticError::WrongArgumentCount { .. } => "E0450",
+            SemanticError::WrongPointerArgumentCount { .. } => "E0451",
+            SemanticError::VariableCalledAsFunction(..) => "E0452",
+            SemanticError::NotAFunction => "E0453",
+            SemanticError::NotAVaList => "E0454",
+            SemanticError::VaArgNonScalar => "E0455",
+            SemanticError::VaStartFixedArguments => "E0456",
+            SemanticError::VaStartNotLastParameter(..) => "E0457",
+            SemanticError::NonIntegerSwitch => "E0458",
+            SemanticError::NonConstantCase => "E0459",
+            SemanticError::CaseOutsideSwitch => "E0460",
+            SemanticError::DuplicateCase(..) => "E0461",
+            SemanticError::MultipleDefaults(..) => "E0462",
+            SemanticError::BreakOutsideLoop => "E0463",
+            SemanticError::ContinueOutsideLoop => "E0464",
+            SemanticError::UndefinedLabel(..) => "E0465",
+            SemanticError::DuplicateLabel(..) => "E0466",
+        }
+    }
+}
+
+impl CodegenError {
+    /// The stable code of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CodegenError::MissingStackOffsets => "E0501",
+            CodegenError::MissingStackSlot(..) => "E0502",
+            CodegenError::MissingFunctionOffset(..) => "E0503",
+            CodegenError::Emission => "E0504",
+        }
+    }
+}
+
+impl DriverError {
+    /// The stable code of the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DriverError::InvalidExtension(..) => "E0601",
+            DriverError::FileNotFound(..) => "E0602",
+            DriverError::Io(..) => "E0603",
+            DriverError::AssemblyFailed(..) => "E0604",
+            DriverError::MissingExitCode => "E0605",
+        }
+    }
+}
+
+impl CompileError {
+    /// Combines the errors of a pass, `None` if there are none. A single error is returned
+    /// as is.
+    pub fn from_errors(mut errors: Vec<CompileError>) -> Option<Self> {
+        match errors.len() {
+            0 | 1 => errors.pop(),
+            _ => Some(CompileError::Multiple(errors)),
+        }
+    }
+
+    /// The errors to report, the error itself unless it's made of several.
+    pub fn errors(&self) -> &[CompileError] {
+        match self {
+            CompileError::Multiple(errors) => errors,
+            error => std::slice::from_ref(error),
+        }
+    }
+
+    /// The span of the source the error is about, unknown for the errors that aren't. It's
+    /// the span of the first one for several errors.
+    pub fn span(&self) -> Span {
+        match self {
+            CompileError::Preprocess(_, span)
+            | CompileError::Lex(_, span)
+            | CompileError::Parse(_, span)
+            | CompileError::Semantic(_, span) => span.clone(),
+            CompileError::Multiple(errors) => {
+                errors.first().map(CompileError::span).unwrap_or_default()
+            }
+            _ => Span::default(),
+        }
+    }
+
+    /// The stable code of the error, the one of the first error for several errors. Each
+    /// error has its own code in the range of its phase, E0101 and on for the preprocessor,
+    /// E0201 for the lexer and so on: a new error takes the next free code of its phase, so
+    /// tools can rely on the others.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompileError::Preprocess(error, _) => error.code(),
+            CompileError::Lex(error, _) => error.code(),
+            CompileError::Parse(error, _) => error.code(),
+            CompileError::Semantic(error, _) => error.code(),
+            CompileError::Codegen(error) => error.code(),
+            CompileError::Driver(error) => error.code(),
+            CompileError::Multiple(errors) => errors.first().map_or("E0600", Self::code),
+        }
+    }
+}
+
+impl From<CodegenError> for CompileError {
+    fn from(error: CodegenError) -> Self {
+        CompileError::Codegen(error)
+    }
+}
+
+impl From<DriverError> for CompileError {
+    fn from(error: DriverError) -> Self {
+        CompileError::Driver(error)
+    }
+}
+
+/// Several errors become the diagnostic of the first one, with a note about the others. The
+/// driver reports each of them instead. A duplicate is labeled with its earlier occurrence.
+impl From<&CompileError> for Diagnostic {
+    fn from(error: &CompileError) -> Self {
+        let message = match error {
+            CompileError::Multiple(errors) => {
+                let Some((first, others)) = errors.split_first() else {
+                    return Diagnostic::error(error.code(), "no errors");
+                };
+                let plural = if others.len() == 1 { "" } else { "s" };
+                return Diagnostic::from(first)
+                    .with_note(format!("and {} more error{plural}", others.len()));
+            }
+            CompileError::Preprocess(error, _) => error.to_string(),
+            CompileError::Lex(error, _) => error.to_string(),
+            CompileError::Parse(error, _) => error.to_string(),
+            CompileError::Semantic(error, _) => error.to_string(),
+            CompileError::Codegen(error) => error.to_string(),
+            CompileError::Driver(error) => error.to_string(),
+        };
+
+        let diagnostic = Diagnostic::error(error.code(), message).with_span(error.span());
+        match error {
+            CompileError::Semantic(error, _) => match error.previous() {
+                Some((span, label)) => diagnostic.with_label(span.clone(), label),
+                None => diagnostic,
+            },
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<CompileError> for Diagnostic {
+    fn from(error: CompileError) -> Self {
+        Diagnostic::from(&error)
+    }
+}
+
+impl From<CompileError> for String {
+    fn from(error: CompileError) -> Self {
+        error.to_string()
+    }
+}
+
+/// The message, prefixed with the location when it's known. Several errors are on one line
+/// each.
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .errors()
+            .iter()
+            .map(|error| Diagnostic::from(error).to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl Display for PreprocessError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreprocessError::UnterminatedComment => write!(f, "unterminated comment"),
+            PreprocessError::UnmatchedDirective(directive) => {
+                write!(f, "#{directive} without #if")
+            }
+            PreprocessError::DirectiveAfterElse(directive) => {
+                write!(f, "#{directive} after #else")
+            }
+            PreprocessError::UnterminatedConditional => {
+                write!(f, "unterminated conditional directive")
+            }
+            PreprocessError::InvalidDirective(directive) => {
+                write!(f, "invalid preprocessing directive #{directive}")
+            }
+            PreprocessError::ErrorDirective(message) => write!(f, "#error {message}"),
+            PreprocessError::ExpectedMacroName => write!(f, "macro names must be identifiers"),
+            PreprocessError::DefinedAsMacroName => {
+                write!(f, "\"defined\" cannot be used as a macro name")
+            }
+            PreprocessError::MacroRedefined(name) => {
+                write!(f, "macro `{name}` redefined differently")
+            }
+            PreprocessError::InvalidMacroParameters => write!(f, "invalid macro parameters"),
+            PreprocessError::DuplicateMacroParameter(name) => {
+                write!(f, "duplicate macro parameter `{name}`")
+            }
+            PreprocessError::PastingAtEdge(name) => {
+                write!(f, "`##` cannot appear at either end of macro `{name}`")
+            }
+            PreprocessError::StringifyingNonParameter(name) => {
+                write!(f, "`#` is not followed by a parameter in macro `{name}`")
+            }
+            PreprocessError::VaArgsInNonVariadic(name) => {
+                write!(f, "__VA_ARGS__ used in non-variadic macro `{name}`")
+            }
+            PreprocessError::UnterminatedInvocation(name) => {
+                write!(f, "unterminated argument list invoking macro `{name}`")
+            }
+            PreprocessError::WrongMacroArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "macro `{name}` passed {found} arguments, but takes {expected}"
+            ),
+            PreprocessError::InvalidPasting(left, right) => {
+                write!(
+                    f,
+                    "pasting `{left}` and `{right}` does not give a valid token"
+                )
+            }
+            PreprocessError::InvalidInclude => {
+                write!(f, "#include expects \"FILENAME\" or <FILENAME>")
+            }
+            PreprocessError::IncludeNotFound(name) => write!(f, "{name}: no such file"),
+            PreprocessError::UnreadableInclude(name) => write!(f, "couldn't read {name}"),
+            PreprocessError::IncludeTooDeep => write!(f, "#include nested too deeply"),
+            PreprocessError::InvalidCondition(Some(token)) => {
+                write!(f, "unexpected `{token}` in #if expression")
+            }
+            PreprocessError::InvalidCondition(None) => write!(f, "#if expression ends too early"),
+            PreprocessError::InvalidConditionConstant(constant) => {
+                write!(f, "invalid constant `{constant}` in #if expression")
+            }
+            PreprocessError::EmptyCondition => write!(f, "#if with no expression"),
+            PreprocessError::DivisionByZero => write!(f, "division by zero in #if"),
+            PreprocessError::InvalidLineDirective => {
+                write!(
+                    f,
+                    "#line expects a line number and an optional \"FILENAME\""
+                )
+            }
+        }
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexError::UnexpectedCharacter(c) => write!(f, "unexpected character `{c}`"),
+            LexError::InvalidPattern(pattern) => write!(f, "couldn't create regex `{pattern}`"),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Expected(construct, Some(found)) => {
+                write!(f, "could not parse {construct}, found {found:?}")
+            }
+            ParseError::Expected(construct, None) => write!(f, "could not parse {construct}"),
+            ParseError::UnexpectedToken { expected, found } => {
+                write!(f, "expected {expected:?}, got {found:?}")
+            }
+            ParseError::UnexpectedEnd(Some(expected)) => {
+                write!(f, "expected {expected:?} at the end of the input")
+            }
+            ParseError::UnexpectedEnd(None) => write!(f, "unexpected end of the input"),
+            ParseError::ExpectedDeclaration(found) => {
+                write!(f, "expected a declaration, found {found:?}")
+            }
+            ParseError::InvalidConstant(n) => write!(f, "could not parse constant `{n}`"),
+            ParseError::InvalidCharacterConstant(c) => {
+                write!(f, "character constant must be a single character: '{c}'")
+            }
+            ParseError::InvalidEscape(e) => write!(f, "invalid escape sequence `\\{e}`"),
+            ParseError::UnsupportedEscape(e) => write!(f, "unsupported escape sequence `\\{e}`"),
+            ParseError::ArraySizeNotConstant(_) => {
+                write!(f, "array size must be an integer constant")
+            }
+            ParseError::ArraySizeNotPositive(size) => {
+                write!(f, "array size must be positive, got {size}")
+            }
+            ParseError::FunctionReturningFunction => write!(f, "function returning a function"),
+            ParseError::InvalidStorageClass => write!(f, "invalid storage class"),
+            ParseError::InvalidTypeSpecifier(specifiers) => {
+                write!(f, "invalid type specifier: {specifiers:?}")
+            }
+            ParseError::TypedefInitialized(name) => write!(f, "initializer in typedef `{name}`"),
+            ParseError::TypedefFunction(name) => {
+                write!(f, "function `{name}` declared with a typedef type")
+            }
+            ParseError::ConflictingTypedef(name) => {
+                write!(f, "conflicting typedef declaration of `{name}`")
+            }
+            ParseError::ParameterNameOmitted(name) => {
+                write!(f, "parameter name omitted in `{name}`")
+            }
+            ParseError::FunctionDefinitionInList(name) => {
+                write!(f, "function `{name}` defined in a declaration list")
+            }
+            ParseError::EmptyStructure(tag) => {
+                write!(f, "structure `{tag}` must have at least one member")
+            }
+            ParseError::StorageClassInMember => write!(f, "storage class in structure member"),
+            ParseError::TypeDefinitionInMember => write!(f, "type definition in structure member"),
+            ParseError::FunctionMember(name) => {
+                write!(f, "structure member `{name}` with function type")
+            }
+            ParseError::ExpectedStructureTag => write!(f, "expected structure tag"),
+            ParseError::ExpectedEnumerationTag => write!(f, "expected enumeration tag"),
+            ParseError::StorageClassInTypeName => write!(f, "storage class in type name"),
+            ParseError::TypeDefinitionInTypeName => write!(f, "type definition in type name"),
+            ParseError::StorageClassInForInit => write!(f, "storage class in for loop initializer"),
+            ParseError::FunctionInForInit(name) => {
+                write!(f, "function `{name}` declared in for loop initializer")
+            }
+            ParseError::StructureInForInit(tag) => {
+                write!(f, "structure `{tag}` declared in for loop initializer")
+            }
+            ParseError::EnumerationInForInit(Some(tag)) => {
+                write!(f, "enumeration `{tag}` declared in for loop initializer")
+            }
+            ParseError::EnumerationInForInit(None) => {
+                write!(f, "enumeration declared in for loop initializer")
+            }
+        }
+    }
+}
+
+impl Display for Construct {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Construct::BlockItem => write!(f, "block item"),
+            Construct::Statement => write!(f, "statement"),
+            Construct::Expression => write!(f, "expression"),
+            Construct::Factor => write!(f, "factor"),
+            Construct::Identifier => write!(f, "identifier"),
+            Construct::UnaryOperator => write!(f, "unary operator"),
+            Construct::BinaryOperator => write!(f, "binary operator"),
+            Construct::CompoundAssignment => write!(f, "compound assignment"),
+        }
+    }
+}
+
+impl Display for SemanticError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SemanticError::DuplicateDeclaration(name, _) => {
+                write!(f, "duplicate declaration of `{name}`")
+            }
+            SemanticError::ConflictingLocalDeclarations(name) => {
+                write!(f, "conflicting local declarations of `{name}`")
+            }
+            SemanticError::NestedFunctionDefinition(name) => {
+                write!(f, "nested function definition of `{name}`")
+            }
+            SemanticError::StaticBlockScopeFunction(name) => {
+                write!(f, "static function declaration of `{name}` at block scope")
+            }
+            SemanticError::FunctionRedeclaredAsVariable(name) => {
+                write!(f, "function `{name}` redeclared as variable")
+            }
+            SemanticError::ConflictingVariableTypes(name) => {
+                write!(f, "conflicting types for variable `{name}`")
+            }
+            SemanticError::ConflictingLinkage(name) => {
+                write!(f, "conflicting linkage for variable `{name}`")
+            }
+            SemanticError::ConflictingDefinitions(name) => {
+                write!(f, "conflicting file scope definitions of `{name}`")
+            }
+            SemanticError::IncompatibleFunctionDeclarations(name) => {
+                write!(f, "incompatible declarations of function `{name}`")
+            }
+            SemanticError::FunctionRedefined(name, _) => {
+                write!(f, "function `{name}` is defined more than once")
+            }
+            SemanticError::StaticAfterNonStatic(name) => {
+                write!(f, "static declaration of `{name}` follows non-static")
+            }
+            SemanticError::ExternInitializer(name) => {
+                write!(f, "initializer on local extern variable `{name}`")
+            }
+            SemanticError::UndeclaredVariable(name) => write!(f, "undeclared variable `{name}`"),
+            SemanticError::UndeclaredFunction(name) => write!(f, "undeclared function `{name}`"),
+            SemanticError::InvalidStaticPointerInitializer(name) => {
+                write!(f, "invalid static pointer initializer for `{name}`")
+            }
+            SemanticError::InvalidStaticStructureInitializer(name) => {
+                write!(f, "invalid static structure initializer for `{name}`")
+            }
+            SemanticError::NonConstantStaticInitializer(name) => {
+                write!(f, "non-constant initializer for static variable `{name}`")
+            }
+            SemanticError::InvalidArrayInitializer(name) => {
+                write!(f, "invalid array initializer for `{name}`")
+            }
+            SemanticError::InvalidScalarInitializer(name) => {
+                write!(f, "invalid scalar initializer for `{name}`")
+            }
+            SemanticError::InvalidStringInitializer(name) => {
+                write!(f, "invalid string initializer for `{name}`")
+            }
+            SemanticError::TooManyArrayElements(name) => {
+                write!(f, "too many elements in array initializer for `{name}`")
+            }
+            SemanticError::TooManyStructureElements(name) => {
+                write!(f, "too many elements in structure initializer for `{name}`")
+            }
+            SemanticError::UndeclaredStructure(tag) => {
+                write!(f, "undeclared structure type `{tag}`")
+            }
+            SemanticError::IncompleteStructure(tag) => {
+                write!(f, "incomplete structure type `{tag}`")
+            }
+            SemanticError::IncompleteType(name) => write!(f, "`{name}` has an incomplete type"),
+            SemanticError::StructureRedefined(tag) => write!(f, "structure `{tag}` defined twice"),
+            SemanticError::DuplicateMember { tag, member } => {
+                write!(f, "duplicate member `{member}` in structure `{tag}`")
+            }
+            SemanticError::NoSuchMember { tag, member } => {
+                write!(f, "structure `{tag}` has no member `{member}`")
+            }
+            SemanticError::MemberOfNonStructure(member) => {
+                write!(f, "member `{member}` accessed on a non-structure")
+            }
+            SemanticError::MemberThroughNonPointer(member) => {
+                write!(f, "member `{member}` accessed through a non-pointer")
+            }
+            SemanticError::NonConstantEnumerator(name) => {
+                write!(f, "value of enumerator `{name}` is not an integer constant")
+            }
+            SemanticError::EnumeratorOutOfRange(name) => {
+                write!(f, "value of enumerator `{name}` out of range")
+            }
+            SemanticError::InvalidStructureOperand => write!(f, "invalid structure operand"),
+            SemanticError::InvalidPointerOperand => write!(f, "invalid pointer operand"),
+            SemanticError::InvalidIntegerOperand => {
+                write!(f, "invalid operand for integer operator")
+            }
+            SemanticError::InvalidComplementOperand => {
+                write!(f, "invalid operand for bitwise complement")
+            }
+            SemanticError::IncompatiblePointerTypes => write!(f, "incompatible pointer types"),
+            SemanticError::IncompatibleStructureTypes => write!(f, "incompatible structure types"),
+            SemanticError::InvalidConversion => write!(f, "invalid implicit conversion"),
+            SemanticError::DiscardsQualifiers => write!(f, "conversion discards qualifiers"),
+            SemanticError::ConstLvalue(lvalue) => {
+                write!(f, "cannot modify `{lvalue}`, it's const")
+            }
+            SemanticError::InvalidLvalue => write!(f, "invalid lvalue"),
+            SemanticError::AddressOfNonLvalue => {
+                write!(f, "cannot take the address of a non-lvalue")
+            }
+            SemanticError::DereferenceNonPointer => write!(f, "cannot dereference a non-pointer"),
+            SemanticError::InvalidSubscript => {
+                write!(f, "subscript needs a pointer and an integer")
+            }
+            SemanticError::CastToArray => write!(f, "cannot cast to an array type"),
+            SemanticError::CastToFunction => write!(f, "cannot cast to a function type"),
+            SemanticError::StructureCast => write!(f, "cannot cast to or from a structure"),
+            SemanticError::DoublePointerCast => {
+                write!(f, "invalid cast between double and pointer")
+            }
+            SemanticError::WrongArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function `{name}` called with {found} arguments instead of {expected}"
+            ),
+            SemanticError::WrongPointerArgumentCount { expected, found } => write!(
+                f,
+                "function pointer called with {found} arguments instead of {expected}"
+            ),
+            SemanticError::VariableCalledAsFunction(name) => {
+                write!(f, "variable `{name}` used as function")
+            }
+            SemanticError::NotAFunction => write!(f, "called object is not a function"),
+            SemanticError::NotAVaList => write!(f, "argument is not a va_list"),
+            SemanticError::VaArgNonScalar => write!(f, "va_arg of a non-scalar type"),
+            SemanticError::VaStartFixedArguments => {
+                write!(f, "va_start used in function with fixed arguments")
+            }
+            SemanticError::VaStartNotLastParameter(last) => {
+                write!(f, "va_start not given the last parameter `{last}`")
+            }
+            SemanticError::NonIntegerSwitch => write!(f, "switch quantity not an integer"),
+            SemanticError::NonConstantCase => {
+                write!(f, "case label is not an integer constant")
+            }
+            SemanticError::CaseOutsideSwitch => write!(f, "case outside of switch"),
+            SemanticError::DuplicateCase(value, _) => write!(f, "duplicate case value {value}"),
+            SemanticError::MultipleDefaults(_) => write!(f, "multiple default labels"),
+            SemanticError::BreakOutsideLoop => {
+                write!(f, "break statement outside of loop or switch")
+            }
+            SemanticError::ContinueOutsideLoop => {
+                write!(f, "continue statement outside of loop")
+            }
+            SemanticError::UndefinedLabel(label) => write!(f, "undefined label `{label}`"),
+            SemanticError::DuplicateLabel(label, _) => write!(f, "duplicate label `{label}`"),
+        }
+    }
+}
+
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodegenError::MissingStackOffsets => write!(f, "stack offsets not initialized"),
+            CodegenError::MissingStackSlot(name) => {
+                write!(f, "aggregate `{name}` without a stack slot")
+            }
+            CodegenError::MissingFunctionOffset(function) => {
+                write!(f, "stack size of `{function}` should be set")
+            }
+            CodegenError::Emission => write!(f, "couldn't convert to assembly string"),
+        }
+    }
+}
+
+impl Display for DriverError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DriverError::InvalidExtension(file, extension) => {
+                write!(f, "{file} should have a {extension} file extension")
+            }
+            DriverError::FileNotFound(file) => write!(f, "{file} does not exist"),
+            DriverError::Io(action, file) => write!(f, "couldn't {action} {file}"),
+            DriverError::AssemblyFailed(file) => write!(f, "failed to assemble and link {file}"),
+            DriverError::MissingExitCode => write!(f, "failed to get status code"),
+        }
+    }
+}
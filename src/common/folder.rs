@@ -18,7 +18,7 @@
 //! impl FolderC for MyPass {
 //!     fn name(&self) -> &'static str { "my_pass" }
 //!
-//!     fn fold_expr(&mut self, expr: Expression) -> Result<Expression, String> {
+//!     fn fold_expr(&mut self, expr: Expression) -> Result<Expression, CompileError> {
 //!         // custom logic here
 //!     }
 //! }
@@ -27,13 +27,16 @@
 use log::{info, trace};
 
 use crate::c_ast::ast::{
-    BinaryOperator, Block, BlockItem, Declaration, Expression, ForInit, FunctionDefinition,
-    Identifier, Program, Statement, UnaryOperator,
+    BinaryOperator, Block, BlockItem, Declaration, EnumDeclaration, Expression, ForInit,
+    FunctionDeclaration, Identifier, Initializer, Program, Statement, StructDeclaration,
+    UnaryOperator, VariableDeclaration,
 };
 use crate::codegen::x64::ast::{
     AsmBinaryOperator, AsmCondCode, AsmFunctionDefinition, AsmIdentifier, AsmInstruction,
     AsmOperand, AsmProgram, AsmUnaryOperator, Reg,
 };
+use crate::common::error::CompileError;
+use crate::common::span::Span;
 use crate::tacky::ast::{
     TackyBinaryOperator, TackyFunctionDefinition, TackyIdentifier, TackyInstruction, TackyProgram,
     TackyUnaryOperator, TackyValue,
@@ -43,44 +46,92 @@ use crate::tacky::ast::{
 /// In this trait we provide the default implementation to traverse the entire AST and return
 /// another AST. This is useful when we want to traverse the AST and perform some operation on a
 /// specific node type.
+///
+/// A pass can go on after an error by recording it in `recover`: a block item with an error
+/// is replaced by a null statement, and a file scope declaration with an error is left out.
+/// The pass then folds the program successfully, and its errors are in `take_errors`.
 pub trait FolderC {
     /// Name of the pass for logging.
     fn name(&self) -> &'static str;
 
-    fn fold_prog(&mut self, program: Program) -> Result<Program, String> {
+    /// Records an error to go on after it. By default the pass stops at its first error.
+    fn recover(&mut self, error: CompileError) -> Result<(), CompileError> {
+        Err(error)
+    }
+
+    /// Returns the errors recorded by `recover`, in the order they were found.
+    fn take_errors(&mut self) -> Vec<CompileError> {
+        vec![]
+    }
+
+    fn fold_prog(&mut self, program: Program) -> Result<Program, CompileError> {
         info!("[{}] starting pass", self.name());
         trace!("[{}] <program>", self.name());
 
-        let result = Program::new(self.fold_fun_def(program.function_definition().clone())?);
+        let (declarations, symbols) = program.into_parts();
+        let mut folded = vec![];
+        for declaration in declarations {
+            let result = match declaration {
+                Declaration::FunDecl(f) => self.fold_fun_decl(f).map(Declaration::FunDecl),
+                Declaration::VarDecl(v) => self.fold_file_var_decl(v).map(Declaration::VarDecl),
+                Declaration::StructDecl(s) => self.fold_struct_decl(s).map(Declaration::StructDecl),
+                Declaration::EnumDecl(e) => self.fold_enum_decl(e).map(Declaration::EnumDecl),
+            };
+            match result {
+                Ok(declaration) => folded.push(declaration),
+                Err(error) => self.recover(error)?,
+            }
+        }
+        let result = Program::new(folded).with_symbols(symbols);
 
         info!("[{}] pass completed", self.name());
 
         Ok(result)
     }
 
-    fn fold_fun_def(&mut self, function: FunctionDefinition) -> Result<FunctionDefinition, String> {
+    fn fold_fun_decl(
+        &mut self,
+        function: FunctionDeclaration,
+    ) -> Result<FunctionDeclaration, CompileError> {
         trace!("[{}] <function> {}", self.name(), function.name().value());
 
-        Ok(FunctionDefinition::new(
-            self.fold_id(function.name().clone())?,
-            self.fold_block(function.body().clone())?,
-        ))
+        let span = function.span().clone();
+
+        let (name, params, body, fun_type, storage_class) = function.into_parts();
+        let params = params
+            .into_iter()
+            .map(|p| self.fold_id(p))
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        let body = body.map(|b| self.fold_block(b)).transpose()?;
+
+        Ok(
+            FunctionDeclaration::new(self.fold_id(name)?, params, body, fun_type, storage_class)
+                .with_span(span),
+        )
     }
 
-    fn fold_block(&mut self, block: Block) -> Result<Block, String> {
+    fn fold_block(&mut self, block: Block) -> Result<Block, CompileError> {
         trace!("[{}] <block>", self.name());
 
-        let folded: Result<Vec<_>, String> = block
-            .block_items()
-            .clone()
-            .into_iter()
-            .map(|item| self.fold_block_i(item))
-            .collect();
+        let mut folded = vec![];
+        for item in block.block_items().clone() {
+            let span = match &item {
+                BlockItem::D(declaration) => declaration.span().clone(),
+                BlockItem::S(statement) => statement.span().clone(),
+            };
+            match self.fold_block_i(item) {
+                Ok(item) => folded.push(item),
+                Err(error) => {
+                    self.recover(error)?;
+                    folded.push(BlockItem::S(Statement::Null(span)));
+                }
+            }
+        }
 
-        Ok(Block::new(folded?))
+        Ok(Block::new(folded))
     }
 
-    fn fold_block_i(&mut self, item: BlockItem) -> Result<BlockItem, String> {
+    fn fold_block_i(&mut self, item: BlockItem) -> Result<BlockItem, CompileError> {
         trace!("[{}] <block_item>", self.name());
 
         match item {
@@ -89,67 +140,168 @@ pub trait FolderC {
         }
     }
 
-    fn fold_decl(&mut self, declaration: Declaration) -> Result<Declaration, String> {
+    fn fold_decl(&mut self, declaration: Declaration) -> Result<Declaration, CompileError> {
         trace!("[{}] <declaration>", self.name());
 
-        let initializer = match declaration.initializer() {
-            Some(expr) => Some(self.fold_expr(expr.clone())?),
-            None => None,
-        };
+        match declaration {
+            Declaration::FunDecl(f) => Ok(Declaration::FunDecl(self.fold_fun_decl(f)?)),
+            Declaration::VarDecl(v) => Ok(Declaration::VarDecl(self.fold_var_decl(v)?)),
+            Declaration::StructDecl(s) => Ok(Declaration::StructDecl(self.fold_struct_decl(s)?)),
+            Declaration::EnumDecl(e) => Ok(Declaration::EnumDecl(self.fold_enum_decl(e)?)),
+        }
+    }
 
-        Ok(Declaration::new(
-            self.fold_id(declaration.name().clone())?,
-            initializer,
-        ))
+    fn fold_enum_decl(
+        &mut self,
+        declaration: EnumDeclaration,
+    ) -> Result<EnumDeclaration, CompileError> {
+        trace!("[{}] <enum_declaration>", self.name());
+
+        let span = declaration.span().clone();
+
+        let (tag, enumerators) = declaration.into_parts();
+        let enumerators = enumerators
+            .into_iter()
+            .map(|(name, value)| {
+                Ok((
+                    self.fold_id(name)?,
+                    value.map(|v| self.fold_expr(v)).transpose()?,
+                ))
+            })
+            .collect::<Result<Vec<_>, CompileError>>()?;
+
+        Ok(EnumDeclaration::new(tag, enumerators).with_span(span))
     }
 
-    fn fold_for_init(&mut self, init: ForInit) -> Result<ForInit, String> {
+    fn fold_struct_decl(
+        &mut self,
+        declaration: StructDeclaration,
+    ) -> Result<StructDeclaration, CompileError> {
+        trace!("[{}] <struct_declaration>", self.name());
+
+        Ok(declaration)
+    }
+
+    /// Folds a variable declared at file scope. By default it's folded like any other
+    /// variable declaration.
+    fn fold_file_var_decl(
+        &mut self,
+        declaration: VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompileError> {
+        self.fold_var_decl(declaration)
+    }
+
+    fn fold_var_decl(
+        &mut self,
+        declaration: VariableDeclaration,
+    ) -> Result<VariableDeclaration, CompileError> {
+        trace!("[{}] <variable_declaration>", self.name());
+
+        let span = declaration.span().clone();
+
+        let (name, initializer, var_type, storage_class) = declaration.into_parts();
+        let initializer = initializer.map(|i| self.fold_initializer(i)).transpose()?;
+
+        Ok(
+            VariableDeclaration::new(self.fold_id(name)?, initializer, var_type, storage_class)
+                .with_span(span),
+        )
+    }
+
+    fn fold_initializer(&mut self, initializer: Initializer) -> Result<Initializer, CompileError> {
+        trace!("[{}] <initializer>", self.name());
+
+        match initializer {
+            Initializer::Single(expr) => Ok(Initializer::Single(self.fold_expr(expr)?)),
+            Initializer::Compound(initializers) => Ok(Initializer::Compound(
+                initializers
+                    .into_iter()
+                    .map(|i| self.fold_initializer(i))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+            )),
+        }
+    }
+
+    fn fold_for_init(&mut self, init: ForInit) -> Result<ForInit, CompileError> {
         trace!("[{}] <for_init>", self.name());
 
         let res = match init {
-            ForInit::InitDecl(decl) => ForInit::InitDecl(Box::new(self.fold_decl(*decl)?)),
+            ForInit::InitDecl(decls) => ForInit::InitDecl(
+                decls
+                    .into_iter()
+                    .map(|d| self.fold_var_decl(d))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+            ),
             ForInit::InitExp(expr) => ForInit::InitExp(self.fold_opt_expr(expr)?),
         };
 
         Ok(res)
     }
 
-    fn fold_st(&mut self, statement: Statement) -> Result<Statement, String> {
+    fn fold_st(&mut self, statement: Statement) -> Result<Statement, CompileError> {
         trace!("[{}] <statement>", self.name());
 
         self.default_fold_st(statement)
     }
 
-    fn default_fold_st(&mut self, statement: Statement) -> Result<Statement, String> {
+    fn default_fold_st(&mut self, statement: Statement) -> Result<Statement, CompileError> {
         let res = match statement {
-            Statement::Return(expr) => Statement::Return(self.fold_expr(expr)?),
-            Statement::Expression(expr) => Statement::Expression(self.fold_expr(expr)?),
-            Statement::If(expr, then, el) => Statement::If(
+            Statement::Return(expr, span) => Statement::Return(self.fold_expr(expr)?, span),
+            Statement::Expression(expr, span) => Statement::Expression(self.fold_expr(expr)?, span),
+            Statement::If(expr, then, el, span) => Statement::If(
                 Box::new(self.fold_expr(*expr)?),
                 Box::new(self.fold_st(*then)?),
-                if let Some(el) = el {
-                    Some(Box::new(self.fold_st(*el)?))
-                } else {
-                    None
-                },
+                el.map(|el| self.fold_st(*el).map(Box::new)).transpose()?,
+                span,
             ),
-            Statement::Compound(block) => Statement::Compound(Box::new(self.fold_block(*block)?)),
-            Statement::Break(id) => Statement::Break(id),
-            Statement::Continue(id) => Statement::Continue(id),
-            Statement::While(cond, body, id) => Statement::While(
+            Statement::Compound(block, span) => {
+                Statement::Compound(Box::new(self.fold_block(*block)?), span)
+            }
+            Statement::Break(id, span) => Statement::Break(id, span),
+            Statement::Continue(id, span) => Statement::Continue(id, span),
+            Statement::While(cond, body, id, span) => Statement::While(
                 Box::new(self.fold_expr(*cond)?),
                 Box::new(self.fold_st(*body)?),
                 id,
+                span,
             ),
-            Statement::DoWhile(body, cond, id) => Statement::DoWhile(
+            Statement::DoWhile(body, cond, id, span) => Statement::DoWhile(
                 Box::new(self.fold_st(*body)?),
                 Box::new(self.fold_expr(*cond)?),
                 id,
+                span,
+            ),
+            Statement::For(for_init, cond, post, body, id, span) => {
+                self.default_fold_st_for(*for_init, cond, post, *body, id, span)?
+            }
+            Statement::Switch(cond, body, id, cases, span) => Statement::Switch(
+                Box::new(self.fold_expr(*cond)?),
+                Box::new(self.fold_st(*body)?),
+                id,
+                cases,
+                span,
+            ),
+            Statement::Case(value, st, id, span) => Statement::Case(
+                self.fold_expr(value)?,
+                Box::new(self.fold_st(*st)?),
+                id,
+                span,
             ),
-            Statement::For(for_init, cond, post, body, id) => {
-                self.default_fold_st_for(for_init, cond, post, body, id)?
+            Statement::Default(st, id, span) => {
+                Statement::Default(Box::new(self.fold_st(*st)?), id, span)
+            }
+            Statement::Goto(label, span) => Statement::Goto(label, span),
+            Statement::Labeled(label, st, span) => {
+                Statement::Labeled(label, Box::new(self.fold_st(*st)?), span)
+            }
+            Statement::VaStart(ap, last, span) => {
+                Statement::VaStart(self.fold_expr(ap)?, self.fold_expr(last)?, span)
+            }
+            Statement::VaEnd(ap, span) => Statement::VaEnd(self.fold_expr(ap)?, span),
+            Statement::VaCopy(dst, src, span) => {
+                Statement::VaCopy(self.fold_expr(dst)?, self.fold_expr(src)?, span)
             }
-            Statement::Null => Statement::Null,
+            Statement::Null(span) => Statement::Null(span),
         };
 
         Ok(res)
@@ -157,24 +309,25 @@ pub trait FolderC {
 
     fn default_fold_st_for(
         &mut self,
-        for_init: Box<ForInit>,
+        for_init: ForInit,
         cond: Option<Box<Expression>>,
         post: Option<Box<Expression>>,
-        body: Box<Statement>,
+        body: Statement,
         id: Identifier,
-    ) -> Result<Statement, String> {
-        let for_init = Box::new(self.fold_for_init(*for_init)?);
+        span: Span,
+    ) -> Result<Statement, CompileError> {
+        let for_init = Box::new(self.fold_for_init(for_init)?);
         let cond = self.fold_opt_expr(cond)?;
         let post = self.fold_opt_expr(post)?;
-        let body = Box::new(self.fold_st(*body)?);
+        let body = Box::new(self.fold_st(body)?);
 
-        Ok(Statement::For(for_init, cond, post, body, id))
+        Ok(Statement::For(for_init, cond, post, body, id, span))
     }
 
     fn fold_opt_expr(
         &mut self,
         opt_expr: Option<Box<Expression>>,
-    ) -> Result<Option<Box<Expression>>, String> {
+    ) -> Result<Option<Box<Expression>>, CompileError> {
         let res = if let Some(cond) = opt_expr {
             let expr = self.fold_expr(*cond)?;
             Some(Box::new(expr))
@@ -184,42 +337,116 @@ pub trait FolderC {
         Ok(res)
     }
 
-    fn fold_expr(&mut self, expression: Expression) -> Result<Expression, String> {
+    fn fold_expr(&mut self, expression: Expression) -> Result<Expression, CompileError> {
         trace!("[{}] <exp>", self.name());
 
+        self.default_fold_expr(expression)
+    }
+
+    fn default_fold_expr(&mut self, expression: Expression) -> Result<Expression, CompileError> {
         match expression {
-            Expression::Constant(value) => Ok(Expression::Constant(value)),
-            Expression::Var(identifier) => Ok(Expression::Var(self.fold_id(identifier)?)),
-            Expression::Unary(op, expr) => Ok(Expression::Unary(
+            Expression::Constant(value, span) => Ok(Expression::Constant(value, span)),
+            Expression::String(value, span) => Ok(Expression::String(value, span)),
+            Expression::Var(identifier, span) => {
+                Ok(Expression::Var(self.fold_id(identifier)?, span))
+            }
+            Expression::Cast(ty, expr, span) => {
+                Ok(Expression::Cast(ty, Box::new(self.fold_expr(*expr)?), span))
+            }
+            Expression::Unary(op, expr, span) => Ok(Expression::Unary(
                 self.fold_un_op(op)?,
                 Box::new(self.fold_expr(*expr)?),
+                span,
             )),
-            Expression::Binary(op, left, right) => Ok(Expression::Binary(
+            Expression::Binary(op, left, right, span) => Ok(Expression::Binary(
                 self.fold_bin_op(op)?,
                 Box::new(self.fold_expr(*left)?),
                 Box::new(self.fold_expr(*right)?),
+                span,
             )),
-            Expression::Assignment(left, right) => Ok(Expression::Assignment(
+            Expression::Assignment(left, right, span) => Ok(Expression::Assignment(
                 Box::new(self.fold_expr(*left)?),
                 Box::new(self.fold_expr(*right)?),
+                span,
+            )),
+            Expression::CompoundAssignment(op, left, right, span) => {
+                Ok(Expression::CompoundAssignment(
+                    self.fold_bin_op(op)?,
+                    Box::new(self.fold_expr(*left)?),
+                    Box::new(self.fold_expr(*right)?),
+                    span,
+                ))
+            }
+            Expression::Postfix(op, expr, span) => Ok(Expression::Postfix(
+                self.fold_bin_op(op)?,
+                Box::new(self.fold_expr(*expr)?),
+                span,
             )),
-            Expression::Conditional(cond, then, el) => Ok(Expression::Conditional(
+            Expression::Conditional(cond, then, el, span) => Ok(Expression::Conditional(
                 Box::new(self.fold_expr(*cond)?),
                 Box::new(self.fold_expr(*then)?),
                 Box::new(self.fold_expr(*el)?),
+                span,
+            )),
+            Expression::Comma(left, right, span) => Ok(Expression::Comma(
+                Box::new(self.fold_expr(*left)?),
+                Box::new(self.fold_expr(*right)?),
+                span,
+            )),
+            Expression::FunctionCall(name, args, span) => Ok(Expression::FunctionCall(
+                self.fold_id(name)?,
+                args.into_iter()
+                    .map(|a| self.fold_expr(a))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+                span,
+            )),
+            Expression::IndirectCall(callee, args, span) => Ok(Expression::IndirectCall(
+                Box::new(self.fold_expr(*callee)?),
+                args.into_iter()
+                    .map(|a| self.fold_expr(a))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+                span,
+            )),
+            Expression::Dereference(expr, span) => Ok(Expression::Dereference(
+                Box::new(self.fold_expr(*expr)?),
+                span,
+            )),
+            Expression::AddressOf(expr, span) => Ok(Expression::AddressOf(
+                Box::new(self.fold_expr(*expr)?),
+                span,
+            )),
+            Expression::Subscript(expr, index, span) => Ok(Expression::Subscript(
+                Box::new(self.fold_expr(*expr)?),
+                Box::new(self.fold_expr(*index)?),
+                span,
+            )),
+            // member names aren't identifiers in scope, they're kept as they are
+            Expression::Dot(expr, member, span) => Ok(Expression::Dot(
+                Box::new(self.fold_expr(*expr)?),
+                member,
+                span,
             )),
+            Expression::Arrow(expr, member, span) => Ok(Expression::Arrow(
+                Box::new(self.fold_expr(*expr)?),
+                member,
+                span,
+            )),
+            Expression::VaArg(ap, ty, span) => {
+                Ok(Expression::VaArg(Box::new(self.fold_expr(*ap)?), ty, span))
+            }
+            Expression::Error(span) => Ok(Expression::Error(span)),
         }
     }
 
-    fn fold_id(&mut self, identifier: Identifier) -> Result<Identifier, String> {
+    fn fold_id(&mut self, identifier: Identifier) -> Result<Identifier, CompileError> {
         Ok(identifier)
     }
 
-    fn fold_un_op(&mut self, operator: UnaryOperator) -> Result<UnaryOperator, String> {
+    fn fold_un_op(&mut self, operator: UnaryOperator) -> Result<UnaryOperator, CompileError> {
         Ok(operator)
     }
 
-    fn fold_bin_op(&mut self, operator: BinaryOperator) -> Result<BinaryOperator, String> {
+    fn fold_bin_op(&mut self, operator: BinaryOperator) -> Result<BinaryOperator, CompileError> {
         Ok(operator)
     }
 }
@@ -237,11 +464,17 @@ pub trait FolderTacky {
         Self::default()
     }
 
-    fn fold_prog(&mut self, program: TackyProgram) -> Result<TackyProgram, String> {
+    fn fold_prog(&mut self, program: TackyProgram) -> Result<TackyProgram, CompileError> {
         info!("[{}] starting pass", self.name());
         trace!("[{}] <tacky_program>", self.name());
 
-        let result = TackyProgram::new(self.fold_fun_def(program.function_definition)?);
+        let function_definitions = program
+            .function_definitions
+            .into_iter()
+            .map(|f| self.fold_fun_def(f))
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        let result = TackyProgram::new(function_definitions, program.static_variables)
+            .with_symbols(program.symbols);
 
         info!("[{}] pass completed", self.name());
 
@@ -251,18 +484,26 @@ pub trait FolderTacky {
     fn fold_fun_def(
         &mut self,
         function: TackyFunctionDefinition,
-    ) -> Result<TackyFunctionDefinition, String> {
+    ) -> Result<TackyFunctionDefinition, CompileError> {
         trace!("[{}] <tacky_function> {}", self.name(), function.name.value);
 
-        let instructions: Result<Vec<_>, String> = function
+        let instructions: Result<Vec<_>, CompileError> = function
             .instructions
             .into_iter()
             .map(|i| self.fold_instruction(i))
-            .collect::<Result<Vec<_>, String>>()
+            .collect::<Result<Vec<_>, CompileError>>()
             .map(|v| v.into_iter().flatten().collect());
 
+        let params = function
+            .params
+            .into_iter()
+            .map(|p| self.fold_id(p))
+            .collect::<Result<Vec<_>, CompileError>>()?;
+
         Ok(TackyFunctionDefinition::new(
             self.fold_id(function.name)?,
+            function.global,
+            params,
             instructions?,
         ))
     }
@@ -270,7 +511,7 @@ pub trait FolderTacky {
     fn fold_instruction(
         &mut self,
         instruction: TackyInstruction,
-    ) -> Result<Vec<TackyInstruction>, String> {
+    ) -> Result<Vec<TackyInstruction>, CompileError> {
         trace!("[{}] <tacky_instruction>", self.name());
 
         use TackyInstruction::*;
@@ -289,6 +530,28 @@ pub trait FolderTacky {
                 self.fold_val(dst)?,
             ),
             Copy(src, dst) => Copy(self.fold_val(src)?, self.fold_val(dst)?),
+            SignExtend(src, dst) => SignExtend(self.fold_val(src)?, self.fold_val(dst)?),
+            Truncate(src, dst) => Truncate(self.fold_val(src)?, self.fold_val(dst)?),
+            ZeroExtend(src, dst) => ZeroExtend(self.fold_val(src)?, self.fold_val(dst)?),
+            DoubleToInt(src, dst) => DoubleToInt(self.fold_val(src)?, self.fold_val(dst)?),
+            DoubleToUInt(src, dst) => DoubleToUInt(self.fold_val(src)?, self.fold_val(dst)?),
+            IntToDouble(src, dst) => IntToDouble(self.fold_val(src)?, self.fold_val(dst)?),
+            UIntToDouble(src, dst) => UIntToDouble(self.fold_val(src)?, self.fold_val(dst)?),
+            GetAddress(src, dst) => GetAddress(self.fold_val(src)?, self.fold_val(dst)?),
+            Load(src_ptr, dst) => Load(self.fold_val(src_ptr)?, self.fold_val(dst)?),
+            Store(src, dst_ptr) => Store(self.fold_val(src)?, self.fold_val(dst_ptr)?),
+            AddPtr(ptr, index, scale, dst) => AddPtr(
+                self.fold_val(ptr)?,
+                self.fold_val(index)?,
+                scale,
+                self.fold_val(dst)?,
+            ),
+            CopyToOffset(src, dst, offset) => {
+                CopyToOffset(self.fold_val(src)?, self.fold_id(dst)?, offset)
+            }
+            CopyFromOffset(src, offset, dst) => {
+                CopyFromOffset(self.fold_id(src)?, offset, self.fold_val(dst)?)
+            }
             Jump(identifier) => Jump(self.fold_id(identifier)?),
             JumpIfZero(value, identifier) => {
                 JumpIfZero(self.fold_val(value)?, self.fold_id(identifier)?)
@@ -297,30 +560,56 @@ pub trait FolderTacky {
                 JumpIfNotZero(self.fold_val(value)?, self.fold_id(identifier)?)
             }
             Label(identifier) => Label(self.fold_id(identifier)?),
+            JumpTable(index, table, targets) => JumpTable(
+                self.fold_val(index)?,
+                self.fold_id(table)?,
+                targets
+                    .into_iter()
+                    .map(|t| self.fold_id(t))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+            ),
+            FunCall(name, args, dst) => FunCall(
+                self.fold_id(name)?,
+                args.into_iter()
+                    .map(|a| self.fold_val(a))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+                self.fold_val(dst)?,
+            ),
+            IndirectCall(fun_ptr, args, dst) => IndirectCall(
+                self.fold_val(fun_ptr)?,
+                args.into_iter()
+                    .map(|a| self.fold_val(a))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+                self.fold_val(dst)?,
+            ),
+            VaStart(ap) => VaStart(self.fold_val(ap)?),
         };
 
         Ok(vec![res])
     }
 
-    fn fold_val(&mut self, value: TackyValue) -> Result<TackyValue, String> {
+    fn fold_val(&mut self, value: TackyValue) -> Result<TackyValue, CompileError> {
         match value {
             TackyValue::Constant(val) => Ok(TackyValue::Constant(val)),
             TackyValue::Var(identifier) => Ok(TackyValue::Var(self.fold_id(identifier)?)),
         }
     }
 
-    fn fold_id(&mut self, identifier: TackyIdentifier) -> Result<TackyIdentifier, String> {
+    fn fold_id(&mut self, identifier: TackyIdentifier) -> Result<TackyIdentifier, CompileError> {
         Ok(identifier)
     }
 
-    fn fold_un_op(&mut self, operator: TackyUnaryOperator) -> Result<TackyUnaryOperator, String> {
+    fn fold_un_op(
+        &mut self,
+        operator: TackyUnaryOperator,
+    ) -> Result<TackyUnaryOperator, CompileError> {
         Ok(operator)
     }
 
     fn fold_bin_op(
         &mut self,
         operator: TackyBinaryOperator,
-    ) -> Result<TackyBinaryOperator, String> {
+    ) -> Result<TackyBinaryOperator, CompileError> {
         Ok(operator)
     }
 }
@@ -337,11 +626,18 @@ pub trait FolderAsm {
         Self::default()
     }
 
-    fn fold_prog(&mut self, program: AsmProgram) -> Result<AsmProgram, String> {
+    fn fold_prog(&mut self, program: AsmProgram) -> Result<AsmProgram, CompileError> {
         info!("[{}] starting pass", self.name());
         trace!("[{}] <asm_program>", self.name());
 
-        let result = AsmProgram::new(self.fold_fun_def(program.function_definition)?);
+        let function_definitions = program
+            .function_definitions
+            .into_iter()
+            .map(|f| self.fold_fun_def(f))
+            .collect::<Result<Vec<_>, CompileError>>()?;
+        let result = AsmProgram::new(function_definitions, program.static_variables)
+            .with_constants(program.static_constants)
+            .with_symbols(program.symbols);
 
         info!("[{}] pass completed", self.name());
 
@@ -351,76 +647,114 @@ pub trait FolderAsm {
     fn fold_fun_def(
         &mut self,
         function: AsmFunctionDefinition,
-    ) -> Result<AsmFunctionDefinition, String> {
+    ) -> Result<AsmFunctionDefinition, CompileError> {
         trace!("[{}] <asm_function> {}", self.name(), function.name.value);
 
-        let instructions: Result<Vec<_>, String> = function
+        let instructions: Result<Vec<_>, CompileError> = function
             .instructions
             .into_iter()
             .map(|i| self.fold_ins(i))
-            .collect::<Result<Vec<_>, String>>()
+            .collect::<Result<Vec<_>, CompileError>>()
             .map(|v| v.into_iter().flatten().collect());
 
         Ok(AsmFunctionDefinition::new(
             self.fold_id(function.name)?,
+            function.global,
             instructions?,
         ))
     }
 
-    fn fold_ins(&mut self, instruction: AsmInstruction) -> Result<Vec<AsmInstruction>, String> {
+    fn fold_ins(
+        &mut self,
+        instruction: AsmInstruction,
+    ) -> Result<Vec<AsmInstruction>, CompileError> {
         trace!("[{}] <asm_instruction>", self.name());
 
         use AsmInstruction::*;
         let res = match instruction {
             Comment(comment) => Comment(comment),
-            Mov(src, dst) => Mov(self.fold_op(src)?, self.fold_op(dst)?),
-            Unary(op, operand) => Unary(self.fold_un_op(op)?, self.fold_op(operand)?),
-            Binary(op, src, dst) => Binary(
+            Mov(ty, src, dst) => Mov(ty, self.fold_op(src)?, self.fold_op(dst)?),
+            Movsx(src_ty, dst_ty, src, dst) => {
+                Movsx(src_ty, dst_ty, self.fold_op(src)?, self.fold_op(dst)?)
+            }
+            MovZeroExtend(src_ty, dst_ty, src, dst) => {
+                MovZeroExtend(src_ty, dst_ty, self.fold_op(src)?, self.fold_op(dst)?)
+            }
+            Cvttsd2si(ty, src, dst) => Cvttsd2si(ty, self.fold_op(src)?, self.fold_op(dst)?),
+            Cvtsi2sd(ty, src, dst) => Cvtsi2sd(ty, self.fold_op(src)?, self.fold_op(dst)?),
+            Lea(src, dst) => Lea(self.fold_op(src)?, self.fold_op(dst)?),
+            Unary(op, ty, operand) => Unary(self.fold_un_op(op)?, ty, self.fold_op(operand)?),
+            Binary(op, ty, src, dst) => Binary(
                 self.fold_bin_op(op)?,
+                ty,
                 self.fold_op(src)?,
                 self.fold_op(dst)?,
             ),
-            Cmp(src, dst) => Cmp(self.fold_op(src)?, self.fold_op(dst)?),
-            Idiv(operand) => Idiv(self.fold_op(operand)?),
-            Cdq => Cdq,
+            Cmp(ty, src, dst) => Cmp(ty, self.fold_op(src)?, self.fold_op(dst)?),
+            Idiv(ty, operand) => Idiv(ty, self.fold_op(operand)?),
+            Div(ty, operand) => Div(ty, self.fold_op(operand)?),
+            Cdq(ty) => Cdq(ty),
             Jmp(identifier) => Jmp(self.fold_id(identifier)?),
             JmpCC(code, identifier) => JmpCC(self.fold_cond_code(code)?, self.fold_id(identifier)?),
+            JmpTable(reg, table, targets) => JmpTable(
+                reg,
+                self.fold_id(table)?,
+                targets
+                    .into_iter()
+                    .map(|t| self.fold_id(t))
+                    .collect::<Result<Vec<_>, CompileError>>()?,
+            ),
             SetCC(code, operand) => SetCC(self.fold_cond_code(code)?, self.fold_op(operand)?),
             Label(identifier) => Label(self.fold_id(identifier)?),
             AllocateStack(size) => AllocateStack(size),
+            DeallocateStack(size) => DeallocateStack(size),
+            Push(operand) => Push(self.fold_op(operand)?),
+            Call(identifier) => Call(self.fold_id(identifier)?),
+            IndirectCall(reg) => IndirectCall(reg),
             Ret => Ret,
         };
 
         Ok(vec![res])
     }
 
-    fn fold_op(&mut self, operand: AsmOperand) -> Result<AsmOperand, String> {
+    fn fold_op(&mut self, operand: AsmOperand) -> Result<AsmOperand, CompileError> {
         use AsmOperand::*;
         match operand {
             Imm(value) => Ok(Imm(value)),
             Register(reg) => Ok(Register(self.fold_reg(reg)?)),
             Pseudo(identifier) => Ok(Pseudo(self.fold_id(identifier)?)),
             Stack(size) => Ok(Stack(size)),
+            Data(identifier, offset) => Ok(Data(self.fold_id(identifier)?, offset)),
+            DoubleConstant(bits) => Ok(DoubleConstant(bits)),
+            GotEntry(identifier) => Ok(GotEntry(self.fold_id(identifier)?)),
+            Memory(reg, offset) => Ok(Memory(self.fold_reg(reg)?, offset)),
+            Indexed(base, index, scale) => {
+                Ok(Indexed(self.fold_reg(base)?, self.fold_reg(index)?, scale))
+            }
+            PseudoMem(identifier, offset) => Ok(PseudoMem(self.fold_id(identifier)?, offset)),
         }
     }
 
-    fn fold_id(&mut self, identifier: AsmIdentifier) -> Result<AsmIdentifier, String> {
+    fn fold_id(&mut self, identifier: AsmIdentifier) -> Result<AsmIdentifier, CompileError> {
         Ok(identifier)
     }
 
-    fn fold_un_op(&mut self, operator: AsmUnaryOperator) -> Result<AsmUnaryOperator, String> {
+    fn fold_un_op(&mut self, operator: AsmUnaryOperator) -> Result<AsmUnaryOperator, CompileError> {
         Ok(operator)
     }
 
-    fn fold_bin_op(&mut self, operator: AsmBinaryOperator) -> Result<AsmBinaryOperator, String> {
+    fn fold_bin_op(
+        &mut self,
+        operator: AsmBinaryOperator,
+    ) -> Result<AsmBinaryOperator, CompileError> {
         Ok(operator)
     }
 
-    fn fold_cond_code(&mut self, code: AsmCondCode) -> Result<AsmCondCode, String> {
+    fn fold_cond_code(&mut self, code: AsmCondCode) -> Result<AsmCondCode, CompileError> {
         Ok(code)
     }
 
-    fn fold_reg(&mut self, reg: Reg) -> Result<Reg, String> {
+    fn fold_reg(&mut self, reg: Reg) -> Result<Reg, CompileError> {
         Ok(reg)
     }
 }
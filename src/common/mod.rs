@@ -1,2 +1,6 @@
+pub mod diagnostic;
+pub mod error;
 pub mod folder;
+pub mod span;
+pub mod symbols;
 pub mod util;
@@ -0,0 +1,61 @@
+//! Source locations.
+//!
+//! Every token and AST node records the part of the source it comes from. The file and
+//! lines follow the line markers of the preprocessor, so they point at the original files.
+//! The preprocessor keeps the tokens of a line at their columns, so the columns are the ones
+//! of the original files too, while the byte range is in the preprocessed source the lexer
+//! reads: it only orders the spans.
+
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+/// A range of the source. Lines and columns start at 1, a default span is unknown.
+#[derive(Clone, Default, PartialEq)]
+pub struct Span {
+    pub file: Rc<str>,
+    pub line: usize,
+    pub column: usize,
+    /// Line of the last character
+    pub end_line: usize,
+    /// Column past the last character
+    pub end_column: usize,
+    /// Offset of the first byte in the lexed source
+    pub start: usize,
+    /// Offset past the last byte in the lexed source
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns the span from the start of this one to the end of `last`. The span stops at
+    /// the end of this one when `last` is in another file.
+    pub fn to(&self, last: &Span) -> Span {
+        let (end_line, end_column) = if last.file == self.file && last.start >= self.start {
+            (last.end_line, last.end_column)
+        } else {
+            (self.end_line, self.end_column)
+        };
+        Span {
+            end_line,
+            end_column,
+            end: last.end.max(self.start),
+            ..self.clone()
+        }
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.line == 0
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+// spans are everywhere in the AST, the debug output only shows where they start
+impl Debug for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
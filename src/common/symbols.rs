@@ -0,0 +1,224 @@
+//! Symbol table shared by the semantic passes and the later lowering stages.
+//!
+//! Every identifier that survives identifier resolution (unique variable names and
+//! function names) gets an entry describing its type and how it's stored. The table also
+//! owns the type table, so the layout of structures travels along with the symbols.
+
+use std::{collections::HashMap, fmt, sync::atomic::AtomicUsize};
+
+use crate::{
+    c_ast::{ast::Type, semantic::type_table::TypeTable},
+    common::util::temporary_name,
+};
+
+static STRING_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum IdentifierAttrs {
+    FunAttr {
+        defined: bool,
+        global: bool,
+    },
+    /// Variables with static storage duration (file scope or declared `static`/`extern`)
+    StaticAttr {
+        init: InitialValue,
+        global: bool,
+    },
+    LocalAttr,
+    /// Read-only objects created by the compiler, e.g. the characters of string literals
+    ConstantAttr(StaticInit),
+    /// Enumeration constants, every use is replaced by the value
+    EnumeratorAttr(i32),
+}
+
+/// The initial value of a variable with static storage duration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InitialValue {
+    /// Declared without an initializer, it's zero unless another declaration defines it
+    Tentative,
+    Initial(StaticInit),
+    /// Declared `extern` without an initializer, it's defined somewhere else
+    NoInitializer,
+}
+
+/// A constant initial value, already converted to the type of the variable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StaticInit {
+    IntInit(i32),
+    LongInit(i64),
+    UIntInit(u32),
+    ULongInit(u64),
+    DoubleInit(f64),
+    CharInit(i8),
+    UCharInit(u8),
+    /// The characters of a string, followed by a null byte if the flag is set (an array
+    /// exactly as long as the string has no room for it)
+    StringInit(String, bool),
+    /// The address of another object with static storage duration, e.g. a string constant
+    PointerInit(String),
+    /// The given number of zero bytes, e.g. the elements of an array without an initializer
+    ZeroInit(usize),
+    /// The initial values of the elements of an aggregate (an array or a structure),
+    /// flattened and in order, with its padding as `ZeroInit`
+    ArrayInit(Vec<StaticInit>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub ty: Type,
+    pub attrs: IdentifierAttrs,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable(HashMap<String, Symbol>, TypeTable);
+
+impl Symbol {
+    pub fn new(ty: Type, attrs: IdentifierAttrs) -> Self {
+        Symbol { ty, attrs }
+    }
+
+    /// Whether the symbol is a function with a body in this translation unit
+    pub fn is_defined(&self) -> bool {
+        matches!(self.attrs, IdentifierAttrs::FunAttr { defined: true, .. })
+    }
+
+    /// Whether the symbol has external linkage
+    pub fn is_global(&self) -> bool {
+        matches!(
+            self.attrs,
+            IdentifierAttrs::FunAttr { global: true, .. }
+                | IdentifierAttrs::StaticAttr { global: true, .. }
+        )
+    }
+
+    /// Whether the symbol is a variable or a constant with static storage duration
+    pub fn is_static(&self) -> bool {
+        matches!(
+            self.attrs,
+            IdentifierAttrs::StaticAttr { .. } | IdentifierAttrs::ConstantAttr(_)
+        )
+    }
+}
+
+impl StaticInit {
+    /// Returns the zero value of the given type.
+    pub fn zero(ty: &Type, types: &TypeTable) -> Self {
+        match ty.unqualified() {
+            Type::Long => StaticInit::LongInit(0),
+            Type::UInt => StaticInit::UIntInit(0),
+            Type::ULong | Type::Pointer(_) => StaticInit::ULongInit(0),
+            Type::Double => StaticInit::DoubleInit(0.0),
+            Type::Char | Type::SChar => StaticInit::CharInit(0),
+            Type::UChar => StaticInit::UCharInit(0),
+            Type::Array(..) | Type::Structure(_) => StaticInit::ZeroInit(ty.size(types)),
+            _ => StaticInit::IntInit(0),
+        }
+    }
+
+    /// Size in bytes of the initialized value.
+    pub fn size(&self) -> usize {
+        match self {
+            StaticInit::CharInit(_) | StaticInit::UCharInit(_) => 1,
+            StaticInit::IntInit(_) | StaticInit::UIntInit(_) => 4,
+            StaticInit::LongInit(_)
+            | StaticInit::ULongInit(_)
+            | StaticInit::DoubleInit(_)
+            | StaticInit::PointerInit(_) => 8,
+            StaticInit::StringInit(s, null_terminated) => s.len() + usize::from(*null_terminated),
+            StaticInit::ZeroInit(bytes) => *bytes,
+            StaticInit::ArrayInit(inits) => inits.iter().map(StaticInit::size).sum(),
+        }
+    }
+
+    /// Whether all the bits of the value are zero (so `-0.0` is not zero).
+    pub fn is_zero(&self) -> bool {
+        match self {
+            StaticInit::DoubleInit(c) => c.to_bits() == 0,
+            StaticInit::ZeroInit(_) => true,
+            StaticInit::ArrayInit(inits) => inits.iter().all(StaticInit::is_zero),
+            _ => matches!(
+                self,
+                StaticInit::CharInit(0)
+                    | StaticInit::UCharInit(0)
+                    | StaticInit::IntInit(0)
+                    | StaticInit::LongInit(0)
+                    | StaticInit::UIntInit(0)
+                    | StaticInit::ULongInit(0)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for StaticInit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StaticInit::IntInit(c) => write!(f, "{c}"),
+            StaticInit::LongInit(c) => write!(f, "{c}L"),
+            StaticInit::UIntInit(c) => write!(f, "{c}U"),
+            StaticInit::ULongInit(c) => write!(f, "{c}UL"),
+            StaticInit::DoubleInit(c) => write!(f, "{c:?}"),
+            StaticInit::CharInit(c) => write!(f, "{c}C"),
+            StaticInit::UCharInit(c) => write!(f, "{c}UC"),
+            StaticInit::StringInit(s, true) => write!(f, "{s:?}"),
+            StaticInit::StringInit(s, false) => write!(f, "{s:?} (no null)"),
+            StaticInit::PointerInit(name) => write!(f, "&{name}"),
+            StaticInit::ZeroInit(bytes) => write!(f, "zero({bytes})"),
+            StaticInit::ArrayInit(inits) => write!(
+                f,
+                "{{{}}}",
+                inits
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: &str, symbol: Symbol) {
+        self.0.insert(name.to_string(), symbol);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Symbol)> {
+        self.0.iter()
+    }
+
+    /// Returns the layouts of the structure types.
+    pub fn types(&self) -> &TypeTable {
+        &self.1
+    }
+
+    pub fn types_mut(&mut self) -> &mut TypeTable {
+        &mut self.1
+    }
+
+    /// Records a string literal as a constant array of chars (null terminated) and returns
+    /// its name. Names are unique across the whole compilation.
+    pub fn add_string_constant(&mut self, value: &str) -> String {
+        let name = temporary_name("string", &STRING_COUNT);
+        let ty = Type::Array(Box::new(Type::Char), value.len() + 1);
+        self.insert(
+            &name,
+            Symbol::new(
+                ty,
+                IdentifierAttrs::ConstantAttr(StaticInit::StringInit(value.to_string(), true)),
+            ),
+        );
+
+        name
+    }
+}
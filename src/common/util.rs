@@ -21,6 +21,12 @@ pub fn temporary_name(name: &str, counter: &AtomicUsize) -> String {
     format!("{name}.{id}")
 }
 
+/// Returns the name a temporary name was made from, i.e. how a renamed identifier is
+/// written in the source.
+pub fn source_name(name: &str) -> &str {
+    name.split_once('.').map_or(name, |(source, _)| source)
+}
+
 pub fn opt_box<T>(opt: Option<T>) -> Option<Box<T>> {
     if let Some(t) = opt {
         return Some(Box::new(t));
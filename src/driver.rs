@@ -4,7 +4,8 @@
 //!
 //! # Pipeline Stages
 //!
-//! 1. **Preprocessing**: Invokes `gcc -E` to expand macros and includes
+//! 1. **Preprocessing**: Expands includes, macros and conditionals, with line markers
+//!    pointing back at the original files
 //! 2. **Lexing**: Tokenizes the preprocessed source
 //! 3. **Parsing**: Builds the C AST from tokens
 //! 4. **Semantic analysis**: Variable resolution and loop labeling
@@ -12,22 +13,32 @@
 //! 6. **Codegen**: Converts TACKY to x86_64 assembly AST
 //! 7. **Fix-up passes**: Replaces pseudo-registers and fixes instruction constraints
 //! 8. **Emission**: Writes assembly to `.asm` file
-//! 9. **Assemble & link**: Invokes `gcc` to produce the final executable
+//! 9. **Assemble & link**: Invokes `gcc` to produce the final executable (or an object
+//!    file with `-c`)
+//!
+//! Every stage fails with a `CompileError`. It's reported on stderr as a diagnostic,
+//! rendered with the source lines it points at or as JSON lines with
+//! `--diagnostics-format=json`.
 
 use std::{fs, path::Path, process::Command};
 
-use clap::Parser;
-use log::{debug, error, info};
+use clap::{Parser, ValueEnum};
+use log::{debug, info};
 
 use crate::c_ast::ast::Program;
+use crate::c_ast::semantic::goto_lab::GotoLabeler;
 use crate::c_ast::semantic::loop_lab::LoopLabeler;
+use crate::c_ast::semantic::type_check::TypeChecker;
 use crate::c_ast::semantic::var_res::VariableResolver;
 use crate::codegen::x64::ast::AsmProgram;
 use crate::codegen::x64::fixer::instruction_fix::InstructionFixer;
 use crate::codegen::x64::fixer::reg_replace::PseudoRegisterReplacer;
+use crate::common::diagnostic::{Diagnostic, SourceCache};
+use crate::common::error::{CodegenError, CompileError, DriverError};
 use crate::common::folder::{FolderAsm, FolderC};
 use crate::common::util::replace_c_with_i;
-use crate::lexer::lex;
+use crate::lexer::lex_spanned;
+use crate::preprocessor::Preprocessor;
 use crate::tacky::ast::TackyProgram;
 
 #[derive(Parser, Debug)]
@@ -53,6 +64,16 @@ pub struct CompilerDriver {
     #[arg(short, value_name = "S")]
     s: bool,
 
+    #[arg(short, help = "Emits an object file instead of an executable")]
+    c: bool,
+
+    #[arg(
+        short = 'I',
+        value_name = "DIR",
+        help = "Adds a directory to the #include search path"
+    )]
+    include_dirs: Vec<String>,
+
     #[arg(long, help = "Enable debug logging")]
     debug: bool,
 
@@ -64,13 +85,33 @@ pub struct CompilerDriver {
 
     #[arg(long, help = "Prints TACKY AST")]
     print_tacky: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = DiagnosticsFormat::Human,
+        help = "How errors are reported"
+    )]
+    diagnostics_format: DiagnosticsFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum DiagnosticsFormat {
+    /// Messages with the source lines they point at
+    Human,
+    /// One JSON object per line
+    Json,
 }
 
 impl CompilerDriver {
+    /// Sets up the logger on stderr. It stays silent with JSON diagnostics, so that stderr only
+    /// carries JSON lines.
     pub fn init_logging(&self) {
         use std::env;
 
-        let log_level = if self.trace {
+        let log_level = if self.diagnostics_format == DiagnosticsFormat::Json {
+            "off"
+        } else if self.trace {
             "trace"
         } else if self.debug {
             "debug"
@@ -84,80 +125,90 @@ impl CompilerDriver {
         env_logger::init();
     }
 
-    pub fn build_program(&self) -> Result<(), String> {
+    pub fn build_program(&self) -> Result<(), CompileError> {
         info!("[driver] building {}", self.program_path);
 
-        let preprocessed_file = self.preprocess(&self.program_path)?;
-        let assembly_file = self.compile(preprocessed_file.as_str())?;
-        let exit_code = self.assemble_and_link(assembly_file)?;
+        let exit_code = self
+            .preprocess(&self.program_path)
+            .and_then(|preprocessed_file| self.compile(&preprocessed_file))
+            .and_then(|assembly_file| self.assemble_and_link(assembly_file))
+            .inspect_err(|error| self.report(error))?;
 
         info!("[driver] completed with exit code {exit_code}");
 
         std::process::exit(exit_code);
     }
 
-    pub fn preprocess(&self, source_file: &str) -> Result<String, String> {
+    /// Writes the errors on stderr as diagnostics, in the chosen format. The human ones are
+    /// separated by a blank line.
+    pub fn report(&self, error: &CompileError) {
+        let mut sources = SourceCache::new();
+        for (i, error) in error.errors().iter().enumerate() {
+            let diagnostic = Diagnostic::from(error);
+            match self.diagnostics_format {
+                DiagnosticsFormat::Human => {
+                    if i > 0 {
+                        eprintln!();
+                    }
+                    eprint!("{}", diagnostic.render(&mut sources));
+                }
+                DiagnosticsFormat::Json => eprintln!("{}", diagnostic.to_json(&mut sources)),
+            }
+        }
+    }
+
+    pub fn preprocess(&self, source_file: &str) -> Result<String, CompileError> {
         info!("[driver] preprocessing");
 
         if !source_file.ends_with(".c") {
-            error!("[driver] source file must have .c extension");
+            debug!("[driver] source file must have .c extension");
 
-            return Err(String::from("SOURCE_FILE should have a .c file extension"));
+            return Err(DriverError::InvalidExtension(source_file.to_string(), ".c").into());
         }
 
         let preprocessed_file = replace_c_with_i(source_file);
         if !preprocessed_file.ends_with(".i") {
-            error!("[driver] preprocessed file must have .i extension");
+            debug!("[driver] preprocessed file must have .i extension");
 
-            return Err(String::from(
-                "PREPROCESSED_FILE should have a .i file extension",
-            ));
+            return Err(DriverError::InvalidExtension(preprocessed_file, ".i").into());
         }
 
         if !Path::new(source_file).exists() {
-            error!("[driver] source file does not exist: {source_file}");
+            debug!("[driver] source file does not exist: {source_file}");
 
-            return Err(String::from("source file does not exist"));
+            return Err(DriverError::FileNotFound(source_file.to_string()).into());
         }
 
-        if Command::new("gcc")
-            .arg("-E")
-            .arg("-P")
-            .arg(source_file)
-            .arg("-o")
-            .arg(&preprocessed_file)
-            .output()
-            .is_err()
-        {
-            error!("[driver] preprocessor failed");
+        let code = Preprocessor::new()
+            .with_include_dirs(&self.include_dirs)
+            .preprocess_file(source_file)?;
+        if fs::write(&preprocessed_file, code).is_err() {
+            debug!("[driver] couldn't write preprocessed file");
 
-            return Err(String::from("failed to execute preprocessor"));
+            return Err(DriverError::Io("write", preprocessed_file).into());
         }
 
         Ok(preprocessed_file)
     }
 
-    fn compile(&self, preprocessed_file_name: &str) -> Result<String, String> {
+    fn compile(&self, preprocessed_file_name: &str) -> Result<String, CompileError> {
         info!("[driver] compiling");
 
-        let preprocessed_file_path = Path::new(preprocessed_file_name);
-        if !preprocessed_file_path.exists() {
-            error!("[driver] preprocessed file does not exist");
+        if !Path::new(preprocessed_file_name).exists() {
+            debug!("[driver] preprocessed file does not exist");
 
-            return Err(String::from(
-                "couldn't compile, preprocessed file does not exist",
-            ));
+            return Err(DriverError::FileNotFound(preprocessed_file_name.to_string()).into());
         }
 
-        let Ok(code) = fs::read_to_string(preprocessed_file_path) else {
-            error!("[driver] couldn't read preprocessed file");
+        let Ok(code) = fs::read_to_string(preprocessed_file_name) else {
+            debug!("[driver] couldn't read preprocessed file");
 
-            return Err(String::from("couldn't read preprocessed file"));
+            return Err(DriverError::Io("read", preprocessed_file_name.to_string()).into());
         };
 
         info!("[driver] lexing");
 
-        let tokens = lex(code.as_str())?;
+        let tokens = lex_spanned(code.as_str())?;
         if self.lex {
             std::process::exit(0);
         }
@@ -200,70 +251,107 @@ impl CompilerDriver {
         info!("[driver] emitting");
 
         let Ok(code) = assembly_program.to_string_asm() else {
-            error!("[driver] couldn't convert to assembly string");
+            debug!("[driver] couldn't convert to assembly string");
 
-            return Err(String::from("couldn't convert to assembly string"));
+            return Err(CodegenError::Emission.into());
         };
 
         let assembly_file_name = preprocessed_file_name.replace(".i", ".asm");
         if fs::write(&assembly_file_name, &code).is_err() {
-            error!("[driver] couldn't write assembly file");
+            debug!("[driver] couldn't write assembly file");
 
-            return Err(String::from("couldn't write assembly file"));
+            return Err(DriverError::Io("write", assembly_file_name).into());
         }
 
         debug!("[driver] assembly:\n{code}");
 
         if fs::remove_file(preprocessed_file_name).is_err() {
-            error!("[driver] couldn't remove preprocessed file");
+            debug!("[driver] couldn't remove preprocessed file");
 
-            return Err(String::from("couldn't remove preprocessed file"));
+            return Err(DriverError::Io("remove", preprocessed_file_name.to_string()).into());
         }
 
         Ok(assembly_file_name)
     }
 
-    fn do_asm_passes(&self, program: AsmProgram) -> Result<AsmProgram, String> {
-        let mut replacer = PseudoRegisterReplacer::create();
+    fn do_asm_passes(&self, program: AsmProgram) -> Result<AsmProgram, CompileError> {
+        let mut replacer = PseudoRegisterReplacer::create().with_symbols(program.symbols.clone());
         let assembly_program = replacer.fold_prog(program)?;
 
-        let last_offset = replacer.last_offset();
-        let mut fixer = InstructionFixer::create().with(last_offset);
+        let function_offsets = replacer.function_offsets();
+        let mut fixer = InstructionFixer::create().with_function_offsets(function_offsets);
 
         fixer.fold_prog(assembly_program)
     }
 
-    fn assemble_and_link(&self, assembly_file: String) -> Result<i32, String> {
+    fn assemble_and_link(&self, assembly_file: String) -> Result<i32, CompileError> {
         info!("[driver] assembling and linking");
 
         if !Path::new(&assembly_file).exists() {
-            error!("[driver] assembly file does not exist");
+            debug!("[driver] assembly file does not exist");
 
-            return Err(String::from("asm file does not exist"));
+            return Err(DriverError::FileNotFound(assembly_file).into());
         }
 
-        let output_file = assembly_file.replace(".asm", "");
-        let Ok(result) = Command::new("gcc")
+        let mut command = Command::new("gcc");
+        if self.c {
+            command.arg("-c");
+        }
+
+        // `.asm` isn't a known extension for gcc, so the language is set explicitly
+        let output_file = assembly_file.replace(".asm", if self.c { ".o" } else { "" });
+        let Ok(result) = command
+            .arg("-x")
+            .arg("assembler")
             .arg(&assembly_file)
             .arg("-o")
             .arg(&output_file)
             .output()
         else {
-            error!("[driver] failed to assemble and link");
+            debug!("[driver] failed to assemble and link");
 
-            return Err(String::from("failed to assemble and link"));
+            return Err(DriverError::AssemblyFailed(assembly_file).into());
         };
 
         result.status.code().ok_or_else(|| {
-            error!("[driver] failed to get exit code");
+            debug!("[driver] failed to get exit code");
 
-            String::from("failed to get status code")
+            DriverError::MissingExitCode.into()
         })
     }
 }
 
-pub fn validate_semantics(program: Program) -> Result<Program, String> {
-    let program = VariableResolver::new().fold_prog(program)?;
-    let program = LoopLabeler::default().fold_prog(program);
-    program
+/// Runs the semantic passes. They go on after their errors, so every pass runs and the
+/// program fails with all their errors, in the order of the source.
+pub fn validate_semantics(program: Program) -> Result<Program, CompileError> {
+    let mut errors = vec![];
+    let program = run_pass(&mut VariableResolver::new(), program, &mut errors)?;
+    let mut type_checker = TypeChecker::new();
+    let program = run_pass(&mut type_checker, program, &mut errors)?;
+    let program = program.with_symbols(type_checker.into_symbols());
+    let program = run_pass(&mut LoopLabeler::default(), program, &mut errors)?;
+    let program = run_pass(&mut GotoLabeler::new(), program, &mut errors)?;
+
+    // the errors without a span go last
+    errors.sort_by_key(|error| {
+        let span = error.span();
+        (span.is_unknown(), span.start)
+    });
+    if let Some(error) = CompileError::from_errors(errors) {
+        return Err(error);
+    }
+
+    Ok(program)
+}
+
+/// Folds the program with a pass and collects the errors it went on after.
+fn run_pass(
+    pass: &mut impl FolderC,
+    program: Program,
+    errors: &mut Vec<CompileError>,
+) -> Result<Program, CompileError> {
+    let program = pass.fold_prog(program)?;
+    errors.extend(pass.take_errors());
+
+    Ok(program)
 }
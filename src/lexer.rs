@@ -1,22 +1,65 @@
-use log::{debug, error, info};
+use std::rc::Rc;
+
+use log::{debug, info};
 use regex::Regex;
 
+use crate::common::{
+    error::{CompileError, LexError},
+    span::Span,
+};
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Token {
     Identifier(String),
     Constant(String),
+    LongConstant(String),
+    UnsignedConstant(String),
+    UnsignedLongConstant(String),
+    DoubleConstant(String),
+    /// A character constant, without the quotes and with its escape sequences untouched
+    CharConstant(String),
+    /// A string literal, without the quotes and with its escape sequences untouched
+    StringLiteral(String),
 
     // keywords
     Int,
+    Char,
+    Long,
+    Double,
+    Signed,
+    Unsigned,
     Void,
     Return,
+    Static,
+    Extern,
+    Typedef,
+    Struct,
+    Enum,
+    Const,
+    Volatile,
+
+    // variadic arguments builtins, what `<stdarg.h>` expands to
+    VaList,
+    VaStart,
+    VaArg,
+    VaEnd,
+    VaCopy,
 
     // symbols
     OpenParen,
     CloseParen,
     OpenBrace,
     CloseBrace,
+    OpenBracket,
+    CloseBracket,
     Semicolon,
+    Comma,
+
+    // structure member access
+    Dot,
+    Arrow,
+    // `...` ending the parameters of a variadic function
+    Ellipsis,
 
     // unary operators
     Complement,
@@ -24,9 +67,22 @@ pub enum Token {
     Not,
 
     // ops
+    Increment,
     Decrement,
     Assignment,
 
+    // compound assignment operators
+    AddAssign,
+    SubtractAssign,
+    MultiplyAssign,
+    DivideAssign,
+    RemainderAssign,
+    BitwiseAndAssign,
+    BitwiseOrAssign,
+    BitwiseXorAssign,
+    LeftShiftAssign,
+    RightShiftAssign,
+
     // binary operators
     Add,
     Multiply,
@@ -65,38 +121,92 @@ pub enum Token {
     Break,
     Continue,
     // goto statement
-    // Goto,
+    Goto,
     // switch statement
-    // Switch,
-    // Case,
+    Switch,
+    Case,
+    Default,
 }
 
-pub fn lex(mut code: &str) -> Result<Vec<Token>, String> {
-    if code.is_empty() {
-        return Ok(vec![]);
-    }
+/// A token with the part of the source it comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+pub fn lex(code: &str) -> Result<Vec<Token>, CompileError> {
+    let tokens = lex_spanned(code)?;
+
+    Ok(tokens.into_iter().map(|spanned| spanned.token).collect())
+}
+
+/// A line marker of the preprocessor, e.g. `# 12 "main.c" 2`.
+const LINE_MARKER: &str = r#"^#[ \t]*(?P<line>[0-9]+)([ \t]+"(?P<file>([^"\\\n]|\\.)*)")?[^\n]*"#;
+
+/// Lexes the source and records where each token comes from. The line markers left by the
+/// preprocessor (e.g. `# 12 "main.c"`) give the file and line of the line after them.
+pub fn lex_spanned(source: &str) -> Result<Vec<SpannedToken>, CompileError> {
+    let Ok(line_marker) = Regex::new(LINE_MARKER) else {
+        debug!("[lexer] invalid line marker regex");
 
-    code = code.trim_start();
+        return Err(LexError::InvalidPattern(LINE_MARKER).at(Span::default()));
+    };
+
+    let mut cursor = Cursor::new(source);
     let mut tokens = vec![];
 
-    while !code.is_empty() {
+    loop {
+        let code = &source[cursor.offset..];
+        let trimmed = code.trim_start();
+        cursor.advance(code.len() - trimmed.len());
+        if trimmed.is_empty() {
+            break;
+        }
+
+        if cursor.at_line_start()
+            && let Some(marker) = line_marker.captures(trimmed)
+        {
+            let line = marker["line"].parse::<usize>().unwrap_or_default();
+            cursor.advance(marker[0].len());
+            // the newline ending the marker moves on to its line
+            cursor.line = line.saturating_sub(1);
+            if let Some(file) = marker.name("file") {
+                cursor.file = Rc::from(file.as_str().replace("\\\"", "\"").replace("\\\\", "\\"));
+            }
+            continue;
+        }
+
         let mut longest_match: TokenMatch = None;
         for matcher in token_matchers().iter() {
-            if let Some(new_match) = matcher.match_longest(code, &longest_match)? {
+            let new_match = matcher
+                .match_longest(trimmed, &longest_match)
+                .map_err(|error| error.at(cursor.span(0)))?;
+            if let Some(new_match) = new_match {
                 longest_match = Some((matcher.token_builder, String::from(new_match.as_str())));
             }
         }
 
         let Some((constructor, value)) = longest_match else {
-            error!("[lexer] no match for: {}", &code[..code.len().min(20)]);
+            debug!(
+                "[lexer] {}: no match for: {}",
+                cursor.span(0),
+                &trimmed[..trimmed.len().min(20)]
+            );
 
-            return Err(String::from("couldn't find any match"));
+            let unexpected = trimmed.chars().next().unwrap_or_default();
+
+            return Err(
+                LexError::UnexpectedCharacter(unexpected).at(cursor.span(unexpected.len_utf8()))
+            );
         };
 
-        tokens.push(constructor(value.clone()));
-        if let Some(c) = code.strip_prefix(value.as_str()) {
-            code = c.trim_start();
-        }
+        let span = cursor.span(value.len());
+        cursor.advance(value.len());
+        tokens.push(SpannedToken {
+            token: constructor(value),
+            span,
+        });
     }
 
     info!("[lexer] {} tokens", tokens.len());
@@ -106,21 +216,111 @@ pub fn lex(mut code: &str) -> Result<Vec<Token>, String> {
     Ok(tokens)
 }
 
+/// Position of the lexer in the source, with the file and line it stands for.
+struct Cursor<'a> {
+    source: &'a str,
+    offset: usize,
+    file: Rc<str>,
+    line: usize,
+    /// Offset of the first byte of the current line
+    line_start: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str) -> Self {
+        Cursor {
+            source,
+            offset: 0,
+            file: Rc::from(""),
+            line: 1,
+            line_start: 0,
+        }
+    }
+
+    fn advance(&mut self, len: usize) {
+        let consumed = &self.source[self.offset..self.offset + len];
+        for (i, c) in consumed.char_indices() {
+            if c == '\n' {
+                self.line += 1;
+                self.line_start = self.offset + i + 1;
+            }
+        }
+        self.offset += len;
+    }
+
+    /// Whether there's only whitespace before the cursor on its line.
+    fn at_line_start(&self) -> bool {
+        self.source[self.line_start..self.offset].trim().is_empty()
+    }
+
+    fn span(&self, len: usize) -> Span {
+        let column = self.source[self.line_start..self.offset].chars().count() + 1;
+        Span {
+            file: self.file.clone(),
+            line: self.line,
+            column,
+            end_line: self.line,
+            end_column: column + self.source[self.offset..self.offset + len].chars().count(),
+            start: self.offset,
+            end: self.offset + len,
+        }
+    }
+}
+
 type TokenBuilder = fn(String) -> Token;
 type TokenMatch = Option<(TokenBuilder, String)>;
 
 fn token_matchers() -> Vec<TokenMatcher> {
     vec![
         TokenMatcher::new(build_identifier_or_keyword, r"^[a-zA-Z_]\w*\b"),
-        TokenMatcher::new(Token::Constant, r"^[0-9]+\b"),
+        // integer constants are decimal, octal (`017`), hexadecimal (`0x1F`) or binary (`0b101`)
+        // and can't run into a `.`, which would make them doubles. Octal digits are checked
+        // by the parser. `ll` is the same as `l`, both kinds of long are 64 bits
+        TokenMatcher::new(
+            Token::Constant,
+            r"^(?P<token>0[xX][0-9a-fA-F]+|0[bB][01]+|[0-9]+)(?:[^\w.]|$)",
+        ),
+        TokenMatcher::new(
+            build_long_constant,
+            r"^(?P<token>(0[xX][0-9a-fA-F]+|0[bB][01]+|[0-9]+)(ll|LL|[lL]))(?:[^\w.]|$)",
+        ),
+        TokenMatcher::new(
+            build_unsigned_constant,
+            r"^(?P<token>(0[xX][0-9a-fA-F]+|0[bB][01]+|[0-9]+)[uU])(?:[^\w.]|$)",
+        ),
+        TokenMatcher::new(
+            build_unsigned_long_constant,
+            r"^(?P<token>(0[xX][0-9a-fA-F]+|0[bB][01]+|[0-9]+)((ll|LL|[lL])[uU]|[uU](ll|LL|[lL])))(?:[^\w.]|$)",
+        ),
+        // only the `token` group is consumed, the rest makes sure the constant ends here
+        TokenMatcher::new(
+            Token::DoubleConstant,
+            r"^(?P<token>([0-9]*\.[0-9]+|[0-9]+\.?)[eE][+-]?[0-9]+|[0-9]*\.[0-9]+|[0-9]+\.)(?:[^\w.]|$)",
+        ),
+        TokenMatcher::new(
+            build_char_constant,
+            r#"^'([^'\\\n]|\\['"?\\abfnrtv]|\\[0-7]{1,3}|\\x[0-9a-fA-F]+)'"#,
+        ),
+        TokenMatcher::new(
+            build_string_literal,
+            r#"^"([^"\\\n]|\\['"?\\abfnrtv]|\\[0-7]{1,3}|\\x[0-9a-fA-F]+)*""#,
+        ),
         TokenMatcher::new(|_| Token::OpenParen, r"^\("),
         TokenMatcher::new(|_| Token::CloseParen, r"^\)"),
         TokenMatcher::new(|_| Token::OpenBrace, r"^\{"),
         TokenMatcher::new(|_| Token::CloseBrace, r"^\}"),
+        TokenMatcher::new(|_| Token::OpenBracket, r"^\["),
+        TokenMatcher::new(|_| Token::CloseBracket, r"^\]"),
         TokenMatcher::new(|_| Token::Semicolon, r"^;"),
+        TokenMatcher::new(|_| Token::Comma, r"^,"),
+        // a dot followed by digits is a double constant, which is a longer match
+        TokenMatcher::new(|_| Token::Dot, r"^\."),
+        TokenMatcher::new(|_| Token::Ellipsis, r"^\.\.\."),
+        TokenMatcher::new(|_| Token::Arrow, r"^->"),
         TokenMatcher::new(|_| Token::Complement, r"^\~"),
         TokenMatcher::new(|_| Token::Negate, r"^\-"),
         TokenMatcher::new(|_| Token::Decrement, r"^\--"),
+        TokenMatcher::new(|_| Token::Increment, r"^\+\+"),
         TokenMatcher::new(|_| Token::Add, r"^\+"),
         TokenMatcher::new(|_| Token::Multiply, r"^\*"),
         TokenMatcher::new(|_| Token::Divide, r"^\/"),
@@ -140,6 +340,16 @@ fn token_matchers() -> Vec<TokenMatcher> {
         TokenMatcher::new(|_| Token::GreaterThan, r"^>"),
         TokenMatcher::new(|_| Token::GreaterThanOrEqual, r"^>="),
         TokenMatcher::new(|_| Token::Assignment, r"^="),
+        TokenMatcher::new(|_| Token::AddAssign, r"^\+="),
+        TokenMatcher::new(|_| Token::SubtractAssign, r"^-="),
+        TokenMatcher::new(|_| Token::MultiplyAssign, r"^\*="),
+        TokenMatcher::new(|_| Token::DivideAssign, r"^/="),
+        TokenMatcher::new(|_| Token::RemainderAssign, r"^%="),
+        TokenMatcher::new(|_| Token::BitwiseAndAssign, r"^&="),
+        TokenMatcher::new(|_| Token::BitwiseOrAssign, r"^\|="),
+        TokenMatcher::new(|_| Token::BitwiseXorAssign, r"^\^="),
+        TokenMatcher::new(|_| Token::LeftShiftAssign, r"^<<="),
+        TokenMatcher::new(|_| Token::RightShiftAssign, r"^>>="),
         TokenMatcher::new(|_| Token::If, r"^if"),
         TokenMatcher::new(|_| Token::Else, r"^else"),
         TokenMatcher::new(|_| Token::QuestionMark, r"^\?"),
@@ -149,17 +359,35 @@ fn token_matchers() -> Vec<TokenMatcher> {
         TokenMatcher::new(|_| Token::For, r"^for"),
         TokenMatcher::new(|_| Token::Break, r"^break"),
         TokenMatcher::new(|_| Token::Continue, r"^continue"),
-        // TokenMatcher::new(|_| Token::Goto, r"^goto"), for this i also need labeled statements
-        // TokenMatcher::new(|_| Token::Switch, r"^switch"),
-        // TokenMatcher::new(|_| Token::Case, r"^case"),
+        TokenMatcher::new(|_| Token::Goto, r"^goto"),
+        TokenMatcher::new(|_| Token::Switch, r"^switch"),
+        TokenMatcher::new(|_| Token::Case, r"^case"),
+        TokenMatcher::new(|_| Token::Default, r"^default"),
     ]
 }
 
 fn build_identifier_or_keyword(s: String) -> Token {
     match s.as_str() {
         "int" => Token::Int,
+        "char" => Token::Char,
+        "long" => Token::Long,
+        "double" => Token::Double,
+        "signed" => Token::Signed,
+        "unsigned" => Token::Unsigned,
         "void" => Token::Void,
         "return" => Token::Return,
+        "static" => Token::Static,
+        "extern" => Token::Extern,
+        "typedef" => Token::Typedef,
+        "struct" => Token::Struct,
+        "enum" => Token::Enum,
+        "const" => Token::Const,
+        "volatile" => Token::Volatile,
+        "__builtin_va_list" => Token::VaList,
+        "__builtin_va_start" => Token::VaStart,
+        "__builtin_va_arg" => Token::VaArg,
+        "__builtin_va_end" => Token::VaEnd,
+        "__builtin_va_copy" => Token::VaCopy,
         "if" => Token::If,
         "else" => Token::Else,
         "do" => Token::Do,
@@ -167,10 +395,39 @@ fn build_identifier_or_keyword(s: String) -> Token {
         "for" => Token::For,
         "break" => Token::Break,
         "continue" => Token::Continue,
+        "goto" => Token::Goto,
+        "switch" => Token::Switch,
+        "case" => Token::Case,
+        "default" => Token::Default,
         _ => Token::Identifier(s),
     }
 }
 
+/// Builds a long constant without its `l`/`ll` suffix (in any case).
+fn build_long_constant(s: String) -> Token {
+    Token::LongConstant(s.trim_end_matches(['l', 'L']).to_string())
+}
+
+/// Builds an unsigned constant without its `u`/`U` suffix.
+fn build_unsigned_constant(s: String) -> Token {
+    Token::UnsignedConstant(s.trim_end_matches(['u', 'U']).to_string())
+}
+
+/// Builds an unsigned long constant without its `ul`/`lu` suffix (in any case).
+fn build_unsigned_long_constant(s: String) -> Token {
+    Token::UnsignedLongConstant(s.trim_end_matches(['u', 'U', 'l', 'L']).to_string())
+}
+
+/// Builds a character constant without its quotes.
+fn build_char_constant(s: String) -> Token {
+    Token::CharConstant(s[1..s.len() - 1].to_string())
+}
+
+/// Builds a string literal without its quotes.
+fn build_string_literal(s: String) -> Token {
+    Token::StringLiteral(s[1..s.len() - 1].to_string())
+}
+
 #[derive(Clone)]
 pub struct TokenMatcher {
     pub regex: &'static str,
@@ -189,14 +446,18 @@ impl TokenMatcher {
         &self,
         code: &'a str,
         longest_match: &TokenMatch,
-    ) -> Result<Option<regex::Match<'a>>, String> {
+    ) -> Result<Option<regex::Match<'a>>, LexError> {
         let Ok(regex) = Regex::new(self.regex) else {
-            error!("[lexer] invalid regex: {}", self.regex);
+            debug!("[lexer] invalid regex: {}", self.regex);
 
-            return Err(String::from("couldn't create regex"));
+            return Err(LexError::InvalidPattern(self.regex));
         };
 
-        let Some(m) = regex.find(code) else {
+        // when the regex has a `token` group, only the group is part of the token
+        let Some(m) = regex
+            .captures(code)
+            .and_then(|captures| captures.name("token").or_else(|| captures.get(0)))
+        else {
             return Ok(None);
         };
 
@@ -232,9 +493,21 @@ pub fn binary_operators() -> Vec<Token> {
         Token::LessThanOrEqual,
         // assignment
         Token::Assignment,
+        Token::AddAssign,
+        Token::SubtractAssign,
+        Token::MultiplyAssign,
+        Token::DivideAssign,
+        Token::RemainderAssign,
+        Token::BitwiseAndAssign,
+        Token::BitwiseOrAssign,
+        Token::BitwiseXorAssign,
+        Token::LeftShiftAssign,
+        Token::RightShiftAssign,
         // conditional operators
         // this is not a binary oeprator but is useful for parsing
         Token::QuestionMark,
+        // comma operator
+        Token::Comma,
     ]
 }
 
@@ -245,6 +518,6 @@ mod tests {
     #[test]
     fn binary_operator_count_test() {
         let bin_op = binary_operators();
-        assert_eq!(bin_op.len(), 20);
+        assert_eq!(bin_op.len(), 31);
     }
 }
@@ -9,4 +9,5 @@ pub mod codegen;
 pub mod common;
 pub mod driver;
 pub mod lexer;
+pub mod preprocessor;
 pub mod tacky;
@@ -11,6 +11,13 @@
 //!                                   │
 //!                                   ▼
 //!                          ┌────────────────┐
+//!                          │  Preprocessor  │
+//!                          └────────┬───────┘
+//!                                   │
+//!                       Source with line markers
+//!                                   │
+//!                                   ▼
+//!                          ┌────────────────┐
 //!                          │     Lexer      │
 //!                          └────────┬───────┘
 //!                                   │
@@ -70,15 +77,10 @@
 
 #![allow(clippy::uninlined_format_args)]
 
-use crate::driver::CompilerDriver;
-use clap::Parser;
+use std::process::ExitCode;
 
-mod c_ast;
-mod codegen;
-mod common;
-mod driver;
-mod lexer;
-mod tacky;
+use clap::Parser;
+use fcc::driver::CompilerDriver;
 
 pub fn title() -> &'static str {
     r"
@@ -91,9 +93,14 @@ pub fn title() -> &'static str {
 "
 }
 
-fn main() {
+fn main() -> ExitCode {
     let driver = CompilerDriver::parse();
     driver.init_logging();
     println!("{}\n", title());
-    driver.build_program().expect("fcc")
+
+    // the driver exits by itself on success, and has already reported the error otherwise
+    match driver.build_program() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(_) => ExitCode::FAILURE,
+    }
 }
@@ -0,0 +1,314 @@
+//! Controlling expressions of `#if` and `#elif`.
+//!
+//! They're evaluated once `defined` and the macros are replaced. Every identifier left is
+//! 0, and the arithmetic is done on `long` or `unsigned long` with the usual conversions.
+//! Like in C, only the operands that are evaluated can divide by zero.
+
+use log::debug;
+
+use crate::c_ast::parser::unescape;
+use crate::common::{
+    error::{CompileError, PreprocessError},
+    span::Span,
+};
+use crate::preprocessor::tokens::{PpToken, PpTokenKind};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Value {
+    value: i64,
+    is_unsigned: bool,
+}
+
+impl Value {
+    fn signed(value: i64) -> Self {
+        Value {
+            value,
+            is_unsigned: false,
+        }
+    }
+
+    fn is_true(&self) -> bool {
+        self.value != 0
+    }
+}
+
+/// Evaluates a controlling expression, `span` is the span of the line of the directive.
+pub fn evaluate(tokens: &[PpToken], span: &Span) -> Result<bool, CompileError> {
+    let mut evaluator = Evaluator {
+        tokens,
+        position: 0,
+        span,
+    };
+    let value = evaluator.conditional(true)?;
+    if let Some(token) = evaluator.peek() {
+        debug!("[preprocessor] {span}: unexpected {} in #if", token.text);
+
+        return Err(PreprocessError::InvalidCondition(Some(token.text.clone())).at(span.clone()));
+    }
+
+    Ok(value.is_true())
+}
+
+struct Evaluator<'a> {
+    tokens: &'a [PpToken],
+    position: usize,
+    span: &'a Span,
+}
+
+impl Evaluator<'_> {
+    fn peek(&self) -> Option<&PpToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_is(&self, punctuator: &str) -> bool {
+        self.peek()
+            .is_some_and(|token| token.is_punctuator(punctuator))
+    }
+
+    fn expect(&mut self, punctuator: &str) -> Result<(), CompileError> {
+        if !self.next_is(punctuator) {
+            debug!("[preprocessor] {}: expected {punctuator} in #if", self.span);
+
+            let found = self.peek().map(|token| token.text.clone());
+            return Err(PreprocessError::InvalidCondition(found).at(self.span.clone()));
+        }
+        self.position += 1;
+
+        Ok(())
+    }
+
+    /// Evaluates a conditional expression, `live` is false in the operands that aren't
+    /// evaluated.
+    fn conditional(&mut self, live: bool) -> Result<Value, CompileError> {
+        let condition = self.binary(0, live)?;
+        if !self.next_is("?") {
+            return Ok(condition);
+        }
+        self.position += 1;
+        let then = self.conditional(live && condition.is_true())?;
+        self.expect(":")?;
+        let otherwise = self.conditional(live && !condition.is_true())?;
+
+        let is_unsigned = then.is_unsigned || otherwise.is_unsigned;
+        let value = if condition.is_true() { then } else { otherwise };
+
+        Ok(Value {
+            is_unsigned,
+            ..value
+        })
+    }
+
+    fn binary(&mut self, min_precedence: u8, live: bool) -> Result<Value, CompileError> {
+        let mut left = self.unary(live)?;
+        while let Some((operator, precedence)) = self
+            .peek()
+            .filter(|token| token.kind == PpTokenKind::Punctuator)
+            .and_then(|token| precedence(&token.text).map(|p| (token.text.clone(), p)))
+            .filter(|(_, precedence)| *precedence >= min_precedence)
+        {
+            self.position += 1;
+            let right_live = match operator.as_str() {
+                "&&" => live && left.is_true(),
+                "||" => live && !left.is_true(),
+                _ => live,
+            };
+            let right = self.binary(precedence + 1, right_live)?;
+            left = self.apply(&operator, left, right, live)?;
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self, live: bool) -> Result<Value, CompileError> {
+        let Some(token) = self.peek().cloned() else {
+            debug!(
+                "[preprocessor] {}: #if expression ends too early",
+                self.span
+            );
+
+            return Err(PreprocessError::InvalidCondition(None).at(self.span.clone()));
+        };
+        self.position += 1;
+
+        match (token.kind, token.text.as_str()) {
+            (PpTokenKind::Punctuator, "+") => self.unary(live),
+            (PpTokenKind::Punctuator, "-") => {
+                let operand = self.unary(live)?;
+                Ok(Value {
+                    value: operand.value.wrapping_neg(),
+                    ..operand
+                })
+            }
+            (PpTokenKind::Punctuator, "~") => {
+                let operand = self.unary(live)?;
+                Ok(Value {
+                    value: !operand.value,
+                    ..operand
+                })
+            }
+            (PpTokenKind::Punctuator, "!") => {
+                let operand = self.unary(live)?;
+                Ok(Value::signed(i64::from(!operand.is_true())))
+            }
+            (PpTokenKind::Punctuator, "(") => {
+                let value = self.conditional(live)?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            (PpTokenKind::Number, _) => self.number(&token.text),
+            (PpTokenKind::CharConstant, _) => self.character(&token.text),
+            (PpTokenKind::Identifier, _) => Ok(Value::signed(0)),
+            _ => {
+                debug!(
+                    "[preprocessor] {}: unexpected {} in #if",
+                    self.span, token.text
+                );
+
+                Err(PreprocessError::InvalidCondition(Some(token.text)).at(self.span.clone()))
+            }
+        }
+    }
+
+    fn number(&self, text: &str) -> Result<Value, CompileError> {
+        let digits = text.trim_end_matches(['u', 'U', 'l', 'L']);
+        let suffix = &text[digits.len()..];
+        let (digits, radix) =
+            if let Some(hex) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+                (hex, 16)
+            } else if let Some(binary) = digits.strip_prefix("0b").or(digits.strip_prefix("0B")) {
+                (binary, 2)
+            } else if digits.len() > 1 && digits.starts_with('0') {
+                (&digits[1..], 8)
+            } else {
+                (digits, 10)
+            };
+
+        let valid_suffix = matches!(
+            suffix.to_lowercase().as_str(),
+            "" | "u" | "l" | "ll" | "ul" | "lu" | "ull" | "llu"
+        );
+        let Some(value) = u64::from_str_radix(digits, radix)
+            .ok()
+            .filter(|_| valid_suffix)
+        else {
+            debug!(
+                "[preprocessor] {}: invalid integer constant {text} in #if",
+                self.span
+            );
+
+            return Err(
+                PreprocessError::InvalidConditionConstant(text.to_string()).at(self.span.clone())
+            );
+        };
+
+        Ok(Value {
+            value: value as i64,
+            is_unsigned: suffix.contains(['u', 'U']) || value > i64::MAX as u64,
+        })
+    }
+
+    fn character(&self, text: &str) -> Result<Value, CompileError> {
+        let value =
+            unescape(&text[1..text.len() - 1]).map_err(|error| error.at(self.span.clone()))?;
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            // `char` is signed
+            (Some(c), None) => Ok(Value::signed(i64::from(c as u8 as i8))),
+            _ => {
+                debug!(
+                    "[preprocessor] {}: invalid character constant {text} in #if",
+                    self.span
+                );
+
+                Err(PreprocessError::InvalidConditionConstant(text.to_string())
+                    .at(self.span.clone()))
+            }
+        }
+    }
+
+    fn apply(
+        &self,
+        operator: &str,
+        left: Value,
+        right: Value,
+        live: bool,
+    ) -> Result<Value, CompileError> {
+        let is_unsigned = left.is_unsigned || right.is_unsigned;
+        let (l, r) = (left.value, right.value);
+        let (ul, ur) = (l as u64, r as u64);
+        let compare = |ordering: bool| Ok(Value::signed(i64::from(ordering)));
+
+        let value = match operator {
+            "&&" => return compare(left.is_true() && right.is_true()),
+            "||" => return compare(left.is_true() || right.is_true()),
+            "==" => return compare(l == r),
+            "!=" => return compare(l != r),
+            "<" if is_unsigned => return compare(ul < ur),
+            "<=" if is_unsigned => return compare(ul <= ur),
+            ">" if is_unsigned => return compare(ul > ur),
+            ">=" if is_unsigned => return compare(ul >= ur),
+            "<" => return compare(l < r),
+            "<=" => return compare(l <= r),
+            ">" => return compare(l > r),
+            ">=" => return compare(l >= r),
+            "/" | "%" if r == 0 => {
+                if live {
+                    debug!("[preprocessor] {}: division by zero in #if", self.span);
+
+                    return Err(PreprocessError::DivisionByZero.at(self.span.clone()));
+                }
+                0
+            }
+            "/" if is_unsigned => (ul / ur) as i64,
+            "%" if is_unsigned => (ul % ur) as i64,
+            "/" => l.wrapping_div(r),
+            "%" => l.wrapping_rem(r),
+            "*" => l.wrapping_mul(r),
+            "+" => l.wrapping_add(r),
+            "-" => l.wrapping_sub(r),
+            "&" => l & r,
+            "^" => l ^ r,
+            "|" => l | r,
+            // the shifts have the type of their left operand
+            "<<" => {
+                return Ok(Value {
+                    value: l.wrapping_shl(r as u32),
+                    ..left
+                });
+            }
+            ">>" if left.is_unsigned => (ul.wrapping_shr(r as u32)) as i64,
+            ">>" => {
+                return Ok(Value {
+                    value: l.wrapping_shr(r as u32),
+                    ..left
+                });
+            }
+            _ => {
+                debug!("[preprocessor] {}: unexpected {operator} in #if", self.span);
+
+                return Err(
+                    PreprocessError::InvalidCondition(Some(operator.to_string()))
+                        .at(self.span.clone()),
+                );
+            }
+        };
+
+        Ok(Value { value, is_unsigned })
+    }
+}
+
+fn precedence(operator: &str) -> Option<u8> {
+    match operator {
+        "||" => Some(1),
+        "&&" => Some(2),
+        "|" => Some(3),
+        "^" => Some(4),
+        "&" => Some(5),
+        "==" | "!=" => Some(6),
+        "<" | "<=" | ">" | ">=" => Some(7),
+        "<<" | ">>" => Some(8),
+        "+" | "-" => Some(9),
+        "*" | "/" | "%" => Some(10),
+        _ => None,
+    }
+}
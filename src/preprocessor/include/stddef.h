@@ -0,0 +1,32 @@
+/* <stddef.h> of fcc. The C library includes it with __need_size_t, __need_NULL... for
+   some of its definitions only, so each one has its own guard. */
+
+#if !defined __need_size_t && !defined __need_ptrdiff_t && !defined __need_wchar_t \
+    && !defined __need_NULL && !defined __need_offsetof
+#define _STDDEF_H
+#endif
+
+#if (defined _STDDEF_H || defined __need_size_t) && !defined __SIZE_T_DEFINED
+#define __SIZE_T_DEFINED
+typedef unsigned long size_t;
+#endif
+
+#if (defined _STDDEF_H || defined __need_ptrdiff_t) && !defined __PTRDIFF_T_DEFINED
+#define __PTRDIFF_T_DEFINED
+typedef long ptrdiff_t;
+#endif
+
+#if (defined _STDDEF_H || defined __need_wchar_t) && !defined __WCHAR_T_DEFINED
+#define __WCHAR_T_DEFINED
+typedef int wchar_t;
+#endif
+
+#if (defined _STDDEF_H || defined __need_NULL) && !defined NULL
+#define NULL 0
+#endif
+
+#undef __need_size_t
+#undef __need_ptrdiff_t
+#undef __need_wchar_t
+#undef __need_NULL
+#undef __need_offsetof
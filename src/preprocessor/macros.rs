@@ -0,0 +1,513 @@
+//! Macro definitions and their expansion.
+//!
+//! Expansion follows Prosser's algorithm: every token carries the set of macros it comes
+//! from, and a macro name is never expanded again from its own replacement, which is what
+//! stops `#define foo foo + 1` from recursing. Function-like macros take their arguments
+//! fully expanded, except next to `#` and `##`, which use them as written.
+
+use std::collections::{HashMap, VecDeque};
+
+use log::debug;
+
+use crate::common::{
+    error::{CompileError, PreprocessError},
+    span::Span,
+};
+use crate::preprocessor::tokens::{PpToken, PpTokenKind, single_token, stringify, tokenize};
+
+/// Name of the parameter holding the variable arguments of a variadic macro.
+const VA_ARGS: &str = "__VA_ARGS__";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Macro {
+    /// Parameters of a function-like macro, `None` for an object-like macro. The variable
+    /// arguments of a variadic macro are the last parameter, `__VA_ARGS__`
+    pub params: Option<Vec<String>>,
+    pub is_variadic: bool,
+    pub body: Vec<PpToken>,
+}
+
+impl Macro {
+    /// Returns an object-like macro standing for `text`.
+    pub fn object(text: &str) -> Self {
+        Macro {
+            params: None,
+            is_variadic: false,
+            body: tokenize(text),
+        }
+    }
+
+    /// Parses the rest of a `#define` line, after `define`. `span` is the span of the line.
+    pub fn parse_definition(
+        tokens: &[PpToken],
+        span: &Span,
+    ) -> Result<(String, Macro), CompileError> {
+        let Some(name) = tokens.first().filter(|token| token.is_identifier()) else {
+            debug!("[preprocessor] {span}: #define expects a macro name");
+
+            return Err(PreprocessError::ExpectedMacroName.at(span.clone()));
+        };
+        if name.text == "defined" {
+            debug!("[preprocessor] {span}: \"defined\" can't be a macro name");
+
+            return Err(PreprocessError::DefinedAsMacroName.at(span.clone()));
+        }
+
+        // a function-like macro has its `(` right after the name, without any whitespace
+        let mut rest = &tokens[1..];
+        let mut params = None;
+        let mut is_variadic = false;
+        if rest
+            .first()
+            .is_some_and(|token| token.is_punctuator("(") && !token.space_before)
+        {
+            let (names, variadic, after) = parse_params(&rest[1..], span)?;
+            params = Some(names);
+            is_variadic = variadic;
+            rest = after;
+        }
+
+        let mut body = rest.to_vec();
+        if let Some(first) = body.first_mut() {
+            first.space_before = false;
+        }
+
+        let defined = Macro {
+            params,
+            is_variadic,
+            body,
+        };
+        defined.check_body(&name.text, span)?;
+
+        Ok((name.text.clone(), defined))
+    }
+
+    /// Whether a redefinition is allowed, the parameters and the replacement must be the
+    /// same, whitespace separations included.
+    pub fn same_definition(&self, other: &Macro) -> bool {
+        let spelling = |body: &[PpToken]| {
+            body.iter()
+                .map(|token| (token.text.clone(), token.space_before))
+                .collect::<Vec<_>>()
+        };
+
+        self.params == other.params
+            && self.is_variadic == other.is_variadic
+            && spelling(&self.body) == spelling(&other.body)
+    }
+
+    fn param_index(&self, token: &PpToken) -> Option<usize> {
+        let params = self.params.as_ref()?;
+        if !token.is_identifier() {
+            return None;
+        }
+
+        params.iter().position(|param| *param == token.text)
+    }
+
+    fn check_body(&self, name: &str, span: &Span) -> Result<(), CompileError> {
+        let pasting_at_end = |token: Option<&PpToken>| token.is_some_and(|t| t.is_punctuator("##"));
+        if pasting_at_end(self.body.first()) || pasting_at_end(self.body.last()) {
+            debug!("[preprocessor] {span}: ## can't be at either end of macro {name}");
+
+            return Err(PreprocessError::PastingAtEdge(name.to_string()).at(span.clone()));
+        }
+
+        if self.params.is_some() {
+            for (i, token) in self.body.iter().enumerate() {
+                let stringified = self.body.get(i + 1).and_then(|next| self.param_index(next));
+                if token.is_punctuator("#") && stringified.is_none() {
+                    debug!(
+                        "[preprocessor] {span}: # isn't followed by a parameter in macro {name}"
+                    );
+
+                    return Err(PreprocessError::StringifyingNonParameter(name.to_string())
+                        .at(span.clone()));
+                }
+            }
+        }
+        let uses_va_args = self
+            .body
+            .iter()
+            .any(|token| token.is_identifier() && token.text == VA_ARGS);
+        if uses_va_args && !self.is_variadic {
+            debug!("[preprocessor] {span}: __VA_ARGS__ used in non variadic macro {name}");
+
+            return Err(PreprocessError::VaArgsInNonVariadic(name.to_string()).at(span.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the parameters of a function-like macro, after its `(`. Returns their names,
+/// whether the macro is variadic and the tokens after the `)`.
+fn parse_params<'a>(
+    tokens: &'a [PpToken],
+    span: &Span,
+) -> Result<(Vec<String>, bool, &'a [PpToken]), CompileError> {
+    let mut params: Vec<String> = vec![];
+    let mut i = 0;
+    if tokens.first().is_some_and(|token| token.is_punctuator(")")) {
+        return Ok((params, false, &tokens[1..]));
+    }
+
+    loop {
+        match tokens.get(i) {
+            Some(token) if token.is_punctuator("...") => {
+                params.push(VA_ARGS.to_string());
+                if !tokens
+                    .get(i + 1)
+                    .is_some_and(|token| token.is_punctuator(")"))
+                {
+                    debug!("[preprocessor] {span}: ... must be the last macro parameter");
+
+                    return Err(PreprocessError::InvalidMacroParameters.at(span.clone()));
+                }
+
+                return Ok((params, true, &tokens[i + 2..]));
+            }
+            Some(token) if token.is_identifier() && token.text != VA_ARGS => {
+                if params.contains(&token.text) {
+                    debug!(
+                        "[preprocessor] {span}: duplicate macro parameter {}",
+                        token.text
+                    );
+
+                    return Err(PreprocessError::DuplicateMacroParameter(token.text.clone())
+                        .at(span.clone()));
+                }
+                params.push(token.text.clone());
+            }
+            _ => {
+                debug!("[preprocessor] {span}: expected a macro parameter name");
+
+                return Err(PreprocessError::InvalidMacroParameters.at(span.clone()));
+            }
+        }
+
+        match tokens.get(i + 1) {
+            Some(token) if token.is_punctuator(",") => i += 2,
+            Some(token) if token.is_punctuator(")") => {
+                return Ok((params, false, &tokens[i + 2..]));
+            }
+            _ => {
+                debug!("[preprocessor] {span}: expected , or ) in macro parameters");
+
+                return Err(PreprocessError::InvalidMacroParameters.at(span.clone()));
+            }
+        }
+    }
+}
+
+/// Supplies the tokens of the following lines when a macro invocation isn't finished at the
+/// end of a line, `None` when there aren't any more.
+pub type MoreTokens<'a> = dyn FnMut() -> Option<Vec<PpToken>> + 'a;
+
+/// Expands the macros of a line.
+pub struct Expander<'a> {
+    macros: &'a HashMap<String, Macro>,
+    /// Span of the line of the expansion, for `__FILE__`, `__LINE__` and the errors
+    span: &'a Span,
+}
+
+impl<'a> Expander<'a> {
+    pub fn new(macros: &'a HashMap<String, Macro>, span: &'a Span) -> Self {
+        Expander { macros, span }
+    }
+
+    /// Expands `tokens` and returns the result, with whether any macro was expanded.
+    pub fn expand(
+        &self,
+        tokens: Vec<PpToken>,
+        more: &mut MoreTokens,
+    ) -> Result<(Vec<PpToken>, bool), CompileError> {
+        let mut input: VecDeque<PpToken> = tokens.into();
+        let mut output = vec![];
+        let mut expanded = false;
+
+        while let Some(token) = input.pop_front() {
+            if !token.is_identifier() || token.hide_set.contains(&token.text) {
+                output.push(token);
+                continue;
+            }
+
+            let Some(definition) = self.macros.get(&token.text) else {
+                match self.builtin(&token) {
+                    Some(builtin) => {
+                        output.push(builtin);
+                        expanded = true;
+                    }
+                    None => output.push(token),
+                }
+                continue;
+            };
+
+            let (args, mut hide_set) = match definition.params {
+                None => (vec![], token.hide_set.clone()),
+                Some(_) => {
+                    // the name alone isn't an invocation, the `(` can be on a following line
+                    while input.is_empty() {
+                        match more() {
+                            Some(tokens) => input.extend(tokens),
+                            None => break,
+                        }
+                    }
+                    if !input.front().is_some_and(|next| next.is_punctuator("(")) {
+                        output.push(token);
+                        continue;
+                    }
+                    let _ = input.pop_front();
+
+                    let (args, close) = self.collect_args(&token.text, &mut input, more)?;
+                    let args = self.match_args(&token.text, definition, args)?;
+                    let hide_set = token
+                        .hide_set
+                        .iter()
+                        .filter(|name| close.hide_set.contains(name))
+                        .cloned()
+                        .collect();
+
+                    (args, hide_set)
+                }
+            };
+            hide_set.push(token.text.clone());
+
+            // the expansion stands where the invocation is written
+            let mut replacement = self.substitute(definition, &args, &hide_set)?;
+            for replaced in &mut replacement {
+                replaced.position = None;
+            }
+            if let Some(first) = replacement.first_mut() {
+                first.space_before = token.space_before;
+                first.position = token.position;
+            }
+            for replaced in replacement.into_iter().rev() {
+                input.push_front(replaced);
+            }
+            expanded = true;
+        }
+
+        Ok((output, expanded))
+    }
+
+    fn builtin(&self, token: &PpToken) -> Option<PpToken> {
+        let builtin = match token.text.as_str() {
+            "__LINE__" => PpToken::new(PpTokenKind::Number, &self.span.line.to_string()),
+            "__FILE__" => {
+                let file = self.span.file.replace('\\', "\\\\").replace('"', "\\\"");
+                PpToken::new(PpTokenKind::StringLiteral, &format!("\"{file}\""))
+            }
+            _ => return None,
+        };
+
+        Some(PpToken {
+            space_before: token.space_before,
+            position: token.position,
+            ..builtin
+        })
+    }
+
+    /// Collects the arguments of an invocation, after its `(`. Returns them with the `)`
+    /// ending the invocation.
+    fn collect_args(
+        &self,
+        name: &str,
+        input: &mut VecDeque<PpToken>,
+        more: &mut MoreTokens,
+    ) -> Result<(Vec<Vec<PpToken>>, PpToken), CompileError> {
+        let mut args = vec![];
+        let mut arg = vec![];
+        let mut depth = 0;
+        loop {
+            let Some(token) = input.pop_front() else {
+                match more() {
+                    Some(tokens) => {
+                        input.extend(tokens);
+                        continue;
+                    }
+                    None => {
+                        debug!(
+                            "[preprocessor] {}: unterminated argument list invoking macro {name}",
+                            self.span
+                        );
+
+                        return Err(PreprocessError::UnterminatedInvocation(name.to_string())
+                            .at(self.span.clone()));
+                    }
+                }
+            };
+
+            if token.is_punctuator(")") && depth == 0 {
+                args.push(arg);
+
+                return Ok((args, token));
+            }
+            if token.is_punctuator(",") && depth == 0 {
+                args.push(std::mem::take(&mut arg));
+                continue;
+            }
+            if token.is_punctuator("(") {
+                depth += 1;
+            } else if token.is_punctuator(")") {
+                depth -= 1;
+            }
+            arg.push(token);
+        }
+    }
+
+    /// Checks the number of arguments, and gathers the variable arguments of a variadic
+    /// macro, commas included.
+    fn match_args(
+        &self,
+        name: &str,
+        definition: &Macro,
+        mut args: Vec<Vec<PpToken>>,
+    ) -> Result<Vec<Vec<PpToken>>, CompileError> {
+        let params = definition.params.as_ref().map_or(0, Vec::len);
+        // `f()` passes a single empty argument, which is no argument when there's no parameter
+        if params == 0 && args.len() == 1 && args[0].is_empty() {
+            args.clear();
+        }
+
+        if definition.is_variadic && args.len() >= params {
+            let variable = args.split_off(params - 1);
+            let mut joined = vec![];
+            for (i, arg) in variable.into_iter().enumerate() {
+                if i > 0 {
+                    joined.push(PpToken::new(PpTokenKind::Punctuator, ","));
+                }
+                joined.extend(arg);
+            }
+            args.push(joined);
+        } else if definition.is_variadic && args.len() + 1 == params {
+            args.push(vec![]);
+        }
+
+        if args.len() != params {
+            debug!(
+                "[preprocessor] {}: macro {name} passed {} arguments, but takes {params}",
+                self.span,
+                args.len()
+            );
+
+            return Err(PreprocessError::WrongMacroArgumentCount {
+                name: name.to_string(),
+                expected: params,
+                found: args.len(),
+            }
+            .at(self.span.clone()));
+        }
+
+        Ok(args)
+    }
+
+    /// Replaces the parameters in the body of a macro, applies `#` and `##`, and adds
+    /// `hide_set` to the tokens of the replacement.
+    fn substitute(
+        &self,
+        definition: &Macro,
+        args: &[Vec<PpToken>],
+        hide_set: &[String],
+    ) -> Result<Vec<PpToken>, CompileError> {
+        let body = &definition.body;
+        let placemarker = || PpToken::new(PpTokenKind::Placemarker, "");
+        let as_written = |i: usize| match args[i].is_empty() {
+            true => vec![placemarker()],
+            false => args[i].clone(),
+        };
+
+        let mut output: Vec<PpToken> = vec![];
+        let mut i = 0;
+        while i < body.len() {
+            let token = &body[i];
+            let next = body.get(i + 1);
+            let next_param = next.and_then(|next| definition.param_index(next));
+
+            if token.is_punctuator("#")
+                && let Some(param) = next_param
+            {
+                output.push(PpToken {
+                    space_before: token.space_before,
+                    ..stringify(&args[param])
+                });
+                i += 2;
+                continue;
+            }
+
+            if token.is_punctuator("##")
+                && let Some(next) = next
+            {
+                let right = match next_param {
+                    Some(param) => as_written(param),
+                    None => vec![next.clone()],
+                };
+                self.paste(&mut output, right)?;
+                i += 2;
+                continue;
+            }
+
+            let Some(param) = definition.param_index(token) else {
+                output.push(token.clone());
+                i += 1;
+                continue;
+            };
+            let mut arg = if next.is_some_and(|next| next.is_punctuator("##")) {
+                as_written(param)
+            } else {
+                self.expand(args[param].clone(), &mut || None)?.0
+            };
+            if let Some(first) = arg.first_mut() {
+                first.space_before = token.space_before;
+            }
+            output.extend(arg);
+            i += 1;
+        }
+
+        output.retain(|token| token.kind != PpTokenKind::Placemarker);
+        for token in &mut output {
+            for name in hide_set {
+                if !token.hide_set.contains(name) {
+                    token.hide_set.push(name.clone());
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Pastes the last token of `output` with the first one of `right` for `##`.
+    fn paste(&self, output: &mut Vec<PpToken>, right: Vec<PpToken>) -> Result<(), CompileError> {
+        let mut right = right.into_iter();
+        let (Some(left), Some(first)) = (output.pop(), right.next()) else {
+            return Ok(());
+        };
+
+        let pasted = match (left.kind, first.kind) {
+            (PpTokenKind::Placemarker, _) => first,
+            (_, PpTokenKind::Placemarker) => left,
+            _ => {
+                let text = format!("{}{}", left.text, first.text);
+                let Some(pasted) = single_token(&text) else {
+                    debug!(
+                        "[preprocessor] {}: pasting {} and {} doesn't give a valid token",
+                        self.span, left.text, first.text
+                    );
+
+                    return Err(PreprocessError::InvalidPasting(left.text, first.text)
+                        .at(self.span.clone()));
+                };
+
+                PpToken {
+                    space_before: left.space_before,
+                    hide_set: left.hide_set,
+                    ..pasted
+                }
+            }
+        };
+        output.push(pasted);
+        output.extend(right);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,585 @@
+//! C preprocessor.
+//!
+//! Expands a source file and the files it includes into a single translation unit for the
+//! lexer. It supports:
+//! - `#include "file"` and `#include <file>`, searched in the directory of the including
+//!   file (for quoted names only), then in the `-I` directories, the built-in headers
+//!   (`<stdarg.h>` and `<stddef.h>`) and the system directories
+//! - Object-like and function-like macros, variadic ones included, with `#` and `##`
+//! - Conditional compilation with `#if`, `#ifdef`, `#ifndef`, `#elif`, `#else` and `#endif`
+//! - `#error`, `#pragma once` (other pragmas are ignored), `__LINE__` and `__FILE__`
+//! - `#line` and the line markers of preprocessed sources, like `# 12 "main.c" 2`, which
+//!   renumber the following lines
+//!
+//! The output keeps the lines of the source wherever it can, with line markers like
+//! `# 12 "main.c"` every time it moves to another file or skips lines, so the following
+//! stages can tell where each line comes from. Lines without any macro are copied as they
+//! are, which keeps their columns too.
+
+pub mod expression;
+pub mod macros;
+pub mod tokens;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info};
+
+use crate::common::{
+    error::{CompileError, DriverError, PreprocessError},
+    span::Span,
+};
+use crate::preprocessor::expression::evaluate;
+use crate::preprocessor::macros::{Expander, Macro};
+use crate::preprocessor::tokens::{
+    LogicalLine, PpToken, PpTokenKind, layout, logical_lines, spell, tokenize_line,
+};
+
+/// Directories searched for `#include`, after the `-I` ones and the built-in headers.
+const SYSTEM_INCLUDE_DIRS: [&str; 3] = [
+    "/usr/local/include",
+    "/usr/include/x86_64-linux-gnu",
+    "/usr/include",
+];
+/// Directory of the headers that come with the compiler instead of the C library, like the
+/// private include directory of gcc. They're never read from the disk.
+const BUILTIN_INCLUDE_DIR: &str = "<built-in>";
+const BUILTIN_HEADERS: [(&str, &str); 2] = [
+    ("stdarg.h", include_str!("include/stdarg.h")),
+    ("stddef.h", include_str!("include/stddef.h")),
+];
+/// Deepest `#include` nesting, which stops a file including itself forever.
+const MAX_INCLUDE_DEPTH: usize = 200;
+/// Most blank lines written to catch up with the source, a line marker is shorter.
+const MAX_BLANK_LINES: usize = 8;
+
+pub struct Preprocessor {
+    /// Directories given with `-I`, in order
+    include_dirs: Vec<PathBuf>,
+    macros: HashMap<String, Macro>,
+    /// Files with `#pragma once`, which are never included again
+    included_once: HashSet<PathBuf>,
+    output: String,
+    /// File and line the next output line is assumed to come from
+    output_file: String,
+    output_line: usize,
+}
+
+/// What a directive changes in the file being processed, besides its macros and
+/// conditionals.
+enum Effect {
+    /// A file brought in by `#include`, with its path and source
+    Include(PathBuf, String),
+    /// The number of the next line, and the file it's from if it's given
+    Line(usize, Option<String>),
+}
+
+/// A conditional group opened by `#if`, `#ifdef` or `#ifndef`.
+struct Conditional {
+    /// Whether the lines of the current branch are kept
+    is_active: bool,
+    /// Whether a branch was taken already, which skips the following ones. It's also set
+    /// when the enclosing group is skipped
+    is_taken: bool,
+    has_else: bool,
+    /// Span of the directive opening the group
+    span: Span,
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        let macros = [
+            ("__STDC__", "1"),
+            ("__STDC_HOSTED__", "1"),
+            ("__STDC_VERSION__", "201710L"),
+            ("__x86_64__", "1"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), Macro::object(value)))
+        .collect();
+
+        Preprocessor {
+            include_dirs: vec![],
+            macros,
+            included_once: HashSet::new(),
+            output: String::new(),
+            output_file: String::new(),
+            output_line: 0,
+        }
+    }
+}
+
+impl Preprocessor {
+    /// Returns a preprocessor with only the predefined macros.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the directories given with `-I`.
+    pub fn with_include_dirs(self, include_dirs: &[String]) -> Self {
+        Preprocessor {
+            include_dirs: include_dirs.iter().map(PathBuf::from).collect(),
+            ..self
+        }
+    }
+
+    pub fn preprocess_file(self, path: &str) -> Result<String, CompileError> {
+        let Ok(source) = fs::read_to_string(path) else {
+            debug!("[preprocessor] couldn't read {path}");
+
+            return Err(DriverError::Io("read", path.to_string()).into());
+        };
+
+        self.preprocess(&source, path)
+    }
+
+    /// Preprocesses `source`, `file` is the path it's read from, which relative includes
+    /// start from.
+    pub fn preprocess(mut self, source: &str, file: &str) -> Result<String, CompileError> {
+        info!("[preprocessor] preprocessing {file}");
+
+        self.process_file(Path::new(file), source, 0)?;
+
+        debug!("[preprocessor] output:\n{}", self.output);
+
+        Ok(self.output)
+    }
+
+    fn process_file(
+        &mut self,
+        path: &Path,
+        source: &str,
+        depth: usize,
+    ) -> Result<(), CompileError> {
+        let mut file = path.display().to_string();
+        let mut lines = logical_lines(source, &file)?;
+        let mut conditionals: Vec<Conditional> = vec![];
+
+        let mut index = 0;
+        while let Some(line) = lines.get(index) {
+            index += 1;
+            let tokens = tokenize_line(line);
+            let is_active = conditionals.last().is_none_or(|c| c.is_active);
+
+            let span = line.span(&file);
+            if tokens.first().is_some_and(|token| token.is_punctuator("#")) {
+                match self.directive(&tokens[1..], path, &span, &mut conditionals, depth)? {
+                    // the included file is processed here, which keeps the recursion shallow
+                    Some(Effect::Include(included, source)) => {
+                        self.process_file(&included, &source, depth + 1)?;
+                    }
+                    // the following lines are numbered from the next one
+                    Some(Effect::Line(number, name)) => {
+                        if let Some(next) = lines.get(index).map(|line| line.line) {
+                            for line in &mut lines[index..] {
+                                line.line = line.line - next + number;
+                            }
+                        }
+                        if let Some(name) = name {
+                            file = name;
+                        }
+                    }
+                    None => {}
+                }
+                continue;
+            }
+            if !is_active || tokens.is_empty() {
+                continue;
+            }
+
+            index = self.expand_line(&file, &lines, index, tokens, &span)?;
+        }
+
+        if let Some(conditional) = conditionals.pop() {
+            debug!(
+                "[preprocessor] {}: unterminated conditional directive",
+                conditional.span
+            );
+
+            return Err(PreprocessError::UnterminatedConditional.at(conditional.span));
+        }
+
+        Ok(())
+    }
+
+    /// Expands the macros of the line before `index` and writes it to the output. A macro
+    /// invocation can go on over the following lines, up to a directive, so it returns the
+    /// index of the line after the last one it read.
+    fn expand_line(
+        &mut self,
+        file: &str,
+        lines: &[LogicalLine],
+        mut index: usize,
+        tokens: Vec<PpToken>,
+        span: &Span,
+    ) -> Result<usize, CompileError> {
+        let line = &lines[index - 1];
+        let next = index;
+        let mut more = || {
+            let next = lines.get(index)?;
+            let mut tokens = tokenize_line(next);
+            if tokens.first().is_some_and(|token| token.is_punctuator("#")) {
+                return None;
+            }
+            index += 1;
+            if let Some(first) = tokens.first_mut() {
+                first.space_before = true;
+            }
+
+            Some(tokens)
+        };
+        let (expansion, expanded) = Expander::new(&self.macros, span).expand(tokens, &mut more)?;
+
+        // a line left as it is is copied, with its whitespace
+        if expanded || index > next {
+            for (line, text) in layout(&expansion, line.line) {
+                self.emit(file, line, text);
+            }
+        } else {
+            self.emit(file, line.line, line.text.clone());
+        }
+
+        Ok(index)
+    }
+
+    /// Writes a line to the output, after a line marker or blank lines when the output
+    /// isn't at its location yet.
+    fn emit(&mut self, file: &str, line: usize, text: String) {
+        let skipped = line.checked_sub(self.output_line);
+        match skipped {
+            Some(skipped) if file == self.output_file && skipped <= MAX_BLANK_LINES => {
+                self.output.push_str(&"\n".repeat(skipped));
+            }
+            _ => {
+                let escaped = file.replace('\\', "\\\\").replace('"', "\\\"");
+                self.output.push_str(&format!("# {line} \"{escaped}\"\n"));
+                self.output_file = file.to_string();
+            }
+        }
+
+        self.output.push_str(&text);
+        self.output.push('\n');
+        self.output_line = line + 1;
+    }
+
+    /// Runs a directive, `tokens` are the ones after the `#` and `span` is the span of its
+    /// line. Only the conditional directives are looked at in skipped groups. Returns what
+    /// `#include` and `#line` change in the file.
+    fn directive(
+        &mut self,
+        tokens: &[PpToken],
+        path: &Path,
+        span: &Span,
+        conditionals: &mut Vec<Conditional>,
+        depth: usize,
+    ) -> Result<Option<Effect>, CompileError> {
+        // a `#` alone is the null directive
+        let Some(name) = tokens.first() else {
+            return Ok(None);
+        };
+        let args = &tokens[1..];
+        let is_active = conditionals.last().is_none_or(|c| c.is_active);
+
+        match name.text.as_str() {
+            "if" | "ifdef" | "ifndef" => {
+                let is_taken = match name.text.as_str() {
+                    _ if !is_active => true,
+                    "if" => self.condition(args, span)?,
+                    "ifdef" => self.macros.contains_key(macro_name(args, span)?),
+                    _ => !self.macros.contains_key(macro_name(args, span)?),
+                };
+                conditionals.push(Conditional {
+                    is_active: is_active && is_taken,
+                    is_taken,
+                    has_else: false,
+                    span: span.clone(),
+                });
+            }
+            "elif" | "else" => {
+                let Some(conditional) = conditionals.last_mut() else {
+                    debug!("[preprocessor] {span}: #{} without #if", name.text);
+
+                    return Err(
+                        PreprocessError::UnmatchedDirective(name.text.clone()).at(span.clone())
+                    );
+                };
+                if conditional.has_else {
+                    debug!("[preprocessor] {span}: #{} after #else", name.text);
+
+                    return Err(
+                        PreprocessError::DirectiveAfterElse(name.text.clone()).at(span.clone())
+                    );
+                }
+
+                conditional.is_active = match name.text.as_str() {
+                    _ if conditional.is_taken => false,
+                    "elif" => self.condition(args, span)?,
+                    _ => true,
+                };
+                conditional.is_taken |= conditional.is_active;
+                conditional.has_else = name.text == "else";
+            }
+            "endif" => {
+                if conditionals.pop().is_none() {
+                    debug!("[preprocessor] {span}: #endif without #if");
+
+                    return Err(
+                        PreprocessError::UnmatchedDirective(name.text.clone()).at(span.clone())
+                    );
+                }
+            }
+            _ if !is_active => {}
+            "define" => {
+                let (name, definition) = Macro::parse_definition(args, span)?;
+                if let Some(old) = self.macros.get(&name)
+                    && !old.same_definition(&definition)
+                {
+                    debug!("[preprocessor] {span}: macro {name} redefined differently");
+
+                    return Err(PreprocessError::MacroRedefined(name).at(span.clone()));
+                }
+                self.macros.insert(name, definition);
+            }
+            "undef" => {
+                let name = macro_name(args, span)?;
+                let _ = self.macros.remove(name);
+            }
+            "include" => {
+                let included = self.include(args, path, span, depth)?;
+                return Ok(included.map(|(path, source)| Effect::Include(path, source)));
+            }
+            "line" => return self.line(args, false, span).map(Some),
+            // a line marker, like the ones written in the output
+            _ if name.kind == PpTokenKind::Number => {
+                return self.line(tokens, true, span).map(Some);
+            }
+            "error" => {
+                let message = spell(args);
+                debug!("[preprocessor] {span}: #error {message}");
+
+                return Err(PreprocessError::ErrorDirective(message).at(span.clone()));
+            }
+            "pragma" if args.len() == 1 && args[0].text == "once" => {
+                let _ = self.included_once.insert(canonical(path));
+            }
+            "pragma" => debug!("[preprocessor] {span}: ignoring #pragma {}", spell(args)),
+            _ => {
+                debug!("[preprocessor] {span}: invalid directive #{}", name.text);
+
+                return Err(PreprocessError::InvalidDirective(name.text.clone()).at(span.clone()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Evaluates the condition of `#if` or `#elif`.
+    fn condition(&self, tokens: &[PpToken], span: &Span) -> Result<bool, CompileError> {
+        if tokens.is_empty() {
+            debug!("[preprocessor] {span}: #if with no expression");
+
+            return Err(PreprocessError::EmptyCondition.at(span.clone()));
+        }
+
+        // `defined` is replaced before the macros are expanded, it looks at their names
+        let mut replaced = vec![];
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if !(token.is_identifier() && token.text == "defined") {
+                replaced.push(token.clone());
+                i += 1;
+                continue;
+            }
+
+            let name = match (tokens.get(i + 1), tokens.get(i + 2), tokens.get(i + 3)) {
+                (Some(open), Some(name), Some(close))
+                    if open.is_punctuator("(") && close.is_punctuator(")") =>
+                {
+                    i += 4;
+                    name
+                }
+                (Some(name), _, _) => {
+                    i += 2;
+                    name
+                }
+                _ => {
+                    debug!("[preprocessor] {span}: defined expects a macro name");
+
+                    return Err(PreprocessError::ExpectedMacroName.at(span.clone()));
+                }
+            };
+            let name = macro_name(std::slice::from_ref(name), span)?;
+            let value = i64::from(self.macros.contains_key(name));
+            replaced.push(PpToken::new(PpTokenKind::Number, &value.to_string()));
+        }
+
+        let (expanded, _) = Expander::new(&self.macros, span).expand(replaced, &mut || None)?;
+
+        evaluate(&expanded, span)
+    }
+
+    /// Reads the line number and the optional file name of `#line`, which can come from
+    /// macros. A line marker can be followed by flags, which are ignored.
+    fn line(
+        &self,
+        tokens: &[PpToken],
+        is_marker: bool,
+        span: &Span,
+    ) -> Result<Effect, CompileError> {
+        let invalid = || {
+            debug!("[preprocessor] {span}: invalid #line {}", spell(tokens));
+
+            PreprocessError::InvalidLineDirective.at(span.clone())
+        };
+
+        let (expanded, _) =
+            Expander::new(&self.macros, span).expand(tokens.to_vec(), &mut || None)?;
+        let is_flag = |token: &PpToken| is_marker && token.kind == PpTokenKind::Number;
+        let (number, name) = match expanded.as_slice() {
+            [number] => (number, None),
+            [number, name, flags @ ..]
+                if name.kind == PpTokenKind::StringLiteral && flags.iter().all(is_flag) =>
+            {
+                (number, Some(unescape(&name.text[1..name.text.len() - 1])))
+            }
+            _ => return Err(invalid()),
+        };
+        let line = number.text.parse().map_err(|_| invalid())?;
+
+        Ok(Effect::Line(line, name))
+    }
+
+    fn include(
+        &mut self,
+        tokens: &[PpToken],
+        path: &Path,
+        span: &Span,
+        depth: usize,
+    ) -> Result<Option<(PathBuf, String)>, CompileError> {
+        // when the name isn't written directly, it comes from the expansion of the line
+        let header = match header_name(tokens) {
+            Some(header) => header,
+            None => {
+                let (expanded, _) =
+                    Expander::new(&self.macros, span).expand(tokens.to_vec(), &mut || None)?;
+                header_name(&expanded).ok_or_else(|| {
+                    debug!("[preprocessor] {span}: #include expects \"FILENAME\" or <FILENAME>");
+
+                    PreprocessError::InvalidInclude.at(span.clone())
+                })?
+            }
+        };
+
+        let Some(included) = self.find_include(&header, path) else {
+            debug!("[preprocessor] {span}: {} not found", header.name);
+
+            return Err(PreprocessError::IncludeNotFound(header.name).at(span.clone()));
+        };
+        if self.included_once.contains(&canonical(&included)) {
+            return Ok(None);
+        }
+        if depth >= MAX_INCLUDE_DEPTH {
+            debug!("[preprocessor] {span}: #include nested too deeply");
+
+            return Err(PreprocessError::IncludeTooDeep.at(span.clone()));
+        }
+        let Some(source) = builtin_header(&included)
+            .map(String::from)
+            .or_else(|| fs::read_to_string(&included).ok())
+        else {
+            debug!(
+                "[preprocessor] {span}: couldn't read {}",
+                included.display()
+            );
+
+            return Err(PreprocessError::UnreadableInclude(header.name).at(span.clone()));
+        };
+
+        debug!("[preprocessor] {span}: including {}", included.display());
+
+        Ok(Some((included, source)))
+    }
+
+    fn find_include(&self, header: &HeaderName, including: &Path) -> Option<PathBuf> {
+        let name = Path::new(&header.name);
+        if name.is_absolute() {
+            return name.is_file().then(|| name.to_path_buf());
+        }
+
+        let including_dir = including
+            .parent()
+            .filter(|_| header.is_quoted)
+            .map(Path::to_path_buf);
+        including_dir
+            .into_iter()
+            .chain(self.include_dirs.iter().cloned())
+            .chain([BUILTIN_INCLUDE_DIR].iter().map(PathBuf::from))
+            .chain(SYSTEM_INCLUDE_DIRS.iter().map(PathBuf::from))
+            .map(|dir| dir.join(name))
+            .find(|candidate| builtin_header(candidate).is_some() || candidate.is_file())
+    }
+}
+
+/// Name of an included file, `is_quoted` tells `"name"` from `<name>`.
+struct HeaderName {
+    name: String,
+    is_quoted: bool,
+}
+
+fn header_name(tokens: &[PpToken]) -> Option<HeaderName> {
+    match tokens {
+        [literal] if literal.kind == PpTokenKind::StringLiteral => Some(HeaderName {
+            name: literal.text[1..literal.text.len() - 1].to_string(),
+            is_quoted: true,
+        }),
+        [open, name @ .., close] if open.is_punctuator("<") && close.is_punctuator(">") => {
+            Some(HeaderName {
+                name: spell(name),
+                is_quoted: false,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Undoes the escapes of the file name of a line directive, the way the output writes it.
+fn unescape(name: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+/// Returns the macro name `#ifdef`, `#ifndef`, `#undef` and `defined` expect.
+fn macro_name<'a>(tokens: &'a [PpToken], span: &Span) -> Result<&'a str, CompileError> {
+    match tokens {
+        [name] if name.is_identifier() => Ok(&name.text),
+        _ => {
+            debug!("[preprocessor] {span}: expected a macro name");
+
+            Err(PreprocessError::ExpectedMacroName.at(span.clone()))
+        }
+    }
+}
+
+/// Returns the source of a built-in header from its path in `BUILTIN_INCLUDE_DIR`.
+fn builtin_header(path: &Path) -> Option<&'static str> {
+    let name = path.strip_prefix(BUILTIN_INCLUDE_DIR).ok()?;
+    BUILTIN_HEADERS
+        .iter()
+        .find(|(header, _)| name == Path::new(header))
+        .map(|(_, source)| *source)
+}
+
+/// Path used to recognize a file with `#pragma once`, however it's included.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
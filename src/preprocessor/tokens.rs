@@ -0,0 +1,377 @@
+//! Logical lines and preprocessing tokens.
+//!
+//! The preprocessor works line by line: backslash-newlines are spliced and comments are
+//! replaced with spaces first, then each logical line is split into preprocessing tokens.
+//! These are coarser than the tokens of the lexer, keywords are identifiers and every
+//! number is a `Number`, valid or not.
+
+use std::rc::Rc;
+
+use log::debug;
+
+use crate::common::{
+    error::{CompileError, PreprocessError},
+    span::Span,
+};
+
+/// Punctuators, the longest ones first so they're matched before their prefixes.
+const PUNCTUATORS: [&str; 48] = [
+    "...", "<<=", ">>=", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=",
+    "/=", "%=", "+=", "-=", "&=", "^=", "|=", "##", "[", "]", "(", ")", "{", "}", ".", "&", "*",
+    "+", "-", "~", "!", "/", "%", "<", ">", "^", "|", "?", ":", ";", "=", ",", "#",
+];
+
+/// A line once backslash-newlines are spliced and comments are replaced with spaces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogicalLine {
+    pub text: String,
+    /// Physical line it starts on, from 1
+    pub line: usize,
+}
+
+impl LogicalLine {
+    /// The span of the line without its indentation, which the errors about a directive or
+    /// a macro invocation point at.
+    pub fn span(&self, file: &str) -> Span {
+        let text = self.text.trim_end();
+        let indent = text.len() - text.trim_start().len();
+        Span {
+            file: Rc::from(file),
+            line: self.line,
+            column: text[..indent].chars().count() + 1,
+            end_line: self.line,
+            end_column: text.chars().count() + 1,
+            start: indent,
+            end: text.len(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PpTokenKind {
+    Identifier,
+    Number,
+    CharConstant,
+    StringLiteral,
+    Punctuator,
+    /// Any other character, it's left for the lexer to reject
+    Other,
+    /// Stands for an empty macro argument next to `##`, it never reaches the output
+    Placemarker,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PpToken {
+    pub kind: PpTokenKind,
+    pub text: String,
+    /// Whether whitespace comes before the token, it matters for `#` and the output
+    pub space_before: bool,
+    /// Macros the token comes from, which aren't expanded again when it's rescanned
+    pub hide_set: Vec<String>,
+    /// Line and column the token is written at, `None` for the tokens a macro expands to
+    pub position: Option<(usize, usize)>,
+}
+
+impl PpToken {
+    pub fn new(kind: PpTokenKind, text: &str) -> Self {
+        PpToken {
+            kind,
+            text: text.to_string(),
+            space_before: false,
+            hide_set: vec![],
+            position: None,
+        }
+    }
+
+    pub fn is_punctuator(&self, punctuator: &str) -> bool {
+        self.kind == PpTokenKind::Punctuator && self.text == punctuator
+    }
+
+    pub fn is_identifier(&self) -> bool {
+        self.kind == PpTokenKind::Identifier
+    }
+}
+
+/// Splits a source file into logical lines. A comment is replaced with as many spaces when
+/// it's on a single line, so the columns don't move, and with a single space otherwise.
+pub fn logical_lines(source: &str, file: &str) -> Result<Vec<LogicalLine>, CompileError> {
+    let chars: Vec<char> = source.replace("\r\n", "\n").chars().collect();
+    let is_splice = |i: usize| chars[i] == '\\' && chars.get(i + 1) == Some(&'\n');
+
+    let mut lines = vec![];
+    let mut text = String::new();
+    let mut start = 1;
+    let mut line = 1;
+    let mut quote = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if is_splice(i) {
+            i += 2;
+            line += 1;
+            continue;
+        }
+        if c == '\n' {
+            lines.push(LogicalLine {
+                text: std::mem::take(&mut text),
+                line: start,
+            });
+            line += 1;
+            start = line;
+            quote = None;
+            i += 1;
+            continue;
+        }
+
+        // comment delimiters don't count in character constants and string literals
+        if let Some(q) = quote {
+            text.push(c);
+            if c == '\\' && chars.get(i + 1).is_some_and(|&next| next != '\n') {
+                text.push(chars[i + 1]);
+                i += 1;
+            } else if c == q {
+                quote = None;
+            }
+            i += 1;
+            continue;
+        }
+
+        match (c, chars.get(i + 1)) {
+            ('"' | '\'', _) => {
+                quote = Some(c);
+                text.push(c);
+                i += 1;
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    if is_splice(i) {
+                        i += 1;
+                        line += 1;
+                    }
+                    i += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                let comment_line = line;
+                let mut width = 2;
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    width += 1;
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    let column = text.chars().count() + 1;
+                    debug!("[preprocessor] {file}:{comment_line}:{column}: unterminated comment");
+
+                    let span = Span {
+                        file: Rc::from(file),
+                        line: comment_line,
+                        column,
+                        end_line: comment_line,
+                        end_column: column + 2,
+                        start: 0,
+                        end: 2,
+                    };
+                    return Err(PreprocessError::UnterminatedComment.at(span));
+                }
+                i += 2;
+                let width = if line == comment_line { width + 2 } else { 1 };
+                text.push_str(&" ".repeat(width));
+            }
+            _ => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        lines.push(LogicalLine { text, line: start });
+    }
+
+    Ok(lines)
+}
+
+/// Splits a text into preprocessing tokens. An unterminated quote is a token of its own,
+/// the lexer reports it if it isn't skipped.
+pub fn tokenize(text: &str) -> Vec<PpToken> {
+    split(text, None)
+}
+
+/// Splits a logical line into preprocessing tokens, which know their position in it.
+pub fn tokenize_line(line: &LogicalLine) -> Vec<PpToken> {
+    split(&line.text, Some(line.line))
+}
+
+fn split(text: &str, line: Option<usize>) -> Vec<PpToken> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut space_before = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            space_before = true;
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let kind = if c.is_ascii_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            PpTokenKind::Identifier
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            i += 1;
+            while i < chars.len() {
+                match chars[i] {
+                    'e' | 'E' | 'p' | 'P' if matches!(chars.get(i + 1), Some('+' | '-')) => i += 2,
+                    c if c.is_ascii_alphanumeric() || c == '_' || c == '.' => i += 1,
+                    _ => break,
+                }
+            }
+            PpTokenKind::Number
+        } else if let Some(end) = (c == '"' || c == '\'')
+            .then(|| closing_quote(&chars, i))
+            .flatten()
+        {
+            i = end + 1;
+            if c == '"' {
+                PpTokenKind::StringLiteral
+            } else {
+                PpTokenKind::CharConstant
+            }
+        } else if let Some(punctuator) = PUNCTUATORS.iter().find(|p| {
+            p.chars()
+                .enumerate()
+                .all(|(j, pc)| chars.get(i + j) == Some(&pc))
+        }) {
+            i += punctuator.len();
+            PpTokenKind::Punctuator
+        } else {
+            i += 1;
+            PpTokenKind::Other
+        };
+
+        let text: String = chars[start..i].iter().collect();
+        tokens.push(PpToken {
+            space_before,
+            position: line.map(|line| (line, start + 1)),
+            ..PpToken::new(kind, &text)
+        });
+        space_before = false;
+    }
+
+    tokens
+}
+
+/// Returns the index of the quote closing the one at `start`, if it's on the same line.
+fn closing_quote(chars: &[char], start: usize) -> Option<usize> {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => return Some(i),
+            _ => i += 1,
+        }
+    }
+
+    None
+}
+
+/// Returns the token spelled by `text`, if it's exactly one token.
+pub fn single_token(text: &str) -> Option<PpToken> {
+    match tokenize(text).as_slice() {
+        [token] if token.text == text => Some(token.clone()),
+        _ => None,
+    }
+}
+
+/// Spells tokens the way they'd be written, with a space wherever there was whitespace
+/// and wherever two tokens would otherwise run into each other.
+pub fn spell(tokens: &[PpToken]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&PpToken> = None;
+    for token in tokens {
+        if previous.is_some_and(|previous| token.space_before || runs_into(previous, token)) {
+            text.push(' ');
+        }
+        text.push_str(&token.text);
+        previous = Some(token);
+    }
+
+    text
+}
+
+/// Lays out the tokens of an expanded line with the ones written in the source at their
+/// line and column, so the lexer finds them where the user wrote them. The tokens of an
+/// expansion follow the ones before them, and a token that can't be at its column anymore,
+/// after an expansion longer than its invocation, starts a new line. Returns the lines with
+/// the line of the source they stand for, `line` for the first one.
+pub fn layout(tokens: &[PpToken], line: usize) -> Vec<(usize, String)> {
+    let mut lines = vec![];
+    let mut text = String::new();
+    let mut width = 0;
+    let mut current = line;
+    let mut previous: Option<&PpToken> = None;
+    for token in tokens {
+        let runs_into = previous.is_some_and(|previous| runs_into(previous, token));
+        match token.position {
+            Some((at, column)) => {
+                let fits = at == current && column > width + usize::from(runs_into);
+                if !fits && previous.is_some() {
+                    lines.push((current, std::mem::take(&mut text)));
+                    width = 0;
+                }
+                current = at;
+                text.push_str(&" ".repeat(column - 1 - width));
+                width = column - 1;
+            }
+            None if previous.is_some() && (token.space_before || runs_into) => {
+                text.push(' ');
+                width += 1;
+            }
+            None => {}
+        }
+        text.push_str(&token.text);
+        width += token.text.chars().count();
+        previous = Some(token);
+    }
+    lines.push((current, text));
+
+    lines
+}
+
+/// Whether two tokens written one after the other would be read as another token.
+fn runs_into(previous: &PpToken, token: &PpToken) -> bool {
+    let joined = format!("{}{}", previous.text, token.text);
+    tokenize(&joined)
+        .first()
+        .is_some_and(|first| first.text.len() > previous.text.len())
+}
+
+/// Turns a macro argument into a string literal for `#`. Whitespace between tokens becomes
+/// a single space, and the quotes and backslashes of literals are escaped.
+pub fn stringify(tokens: &[PpToken]) -> PpToken {
+    let mut text = String::from("\"");
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 && token.space_before {
+            text.push(' ');
+        }
+        match token.kind {
+            PpTokenKind::StringLiteral | PpTokenKind::CharConstant => {
+                text.push_str(&token.text.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            _ => text.push_str(&token.text),
+        }
+    }
+    text.push('"');
+
+    PpToken::new(PpTokenKind::StringLiteral, &text)
+}
@@ -2,17 +2,53 @@
 //!
 //! This module defines the TACKY IR, a three-address code representation
 //! that sits between the C AST and the final x86_64 assembly.
+//!
+//! Volatile objects keep their qualified type in the symbol table, and so do the pointers
+//! to them. Each read and write of a volatile object in the source is exactly one
+//! instruction (a `Copy` from or to the variable, a `Load` or a `Store` through the
+//! pointer), and any pass working on TACKY must keep those instructions, without merging
+//! or reordering them.
+
+use crate::{
+    c_ast::ast::{Const, Type},
+    common::symbols::{StaticInit, SymbolTable},
+};
 
 pub struct TackyProgram {
-    pub function_definition: TackyFunctionDefinition,
+    pub function_definitions: Vec<TackyFunctionDefinition>,
+    pub static_variables: Vec<TackyStaticVariable>,
+    pub static_constants: Vec<TackyStaticConstant>,
+    /// Symbol table built during semantic analysis (plus the temporaries created while
+    /// lowering), codegen uses it to tell static variables apart from local ones and
+    /// to know the size of each value
+    pub symbols: SymbolTable,
 }
 
 #[derive(Clone, Debug)]
 pub struct TackyFunctionDefinition {
     pub name: TackyIdentifier,
+    /// Whether the function has external linkage
+    pub global: bool,
+    pub params: Vec<TackyIdentifier>,
     pub instructions: Vec<TackyInstruction>,
 }
 
+/// A variable with static storage duration, it lives in the data section instead of the stack.
+#[derive(Clone, Debug)]
+pub struct TackyStaticVariable {
+    pub name: TackyIdentifier,
+    pub global: bool,
+    pub init: StaticInit,
+}
+
+/// A read-only object, like a string literal, that lives in the read-only data section.
+#[derive(Clone, Debug)]
+pub struct TackyStaticConstant {
+    pub name: TackyIdentifier,
+    pub ty: Type,
+    pub init: StaticInit,
+}
+
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub enum TackyInstruction {
@@ -21,10 +57,48 @@ pub enum TackyInstruction {
     Unary(TackyUnaryOperator, TackyValue, TackyValue),
     Binary(TackyBinaryOperator, TackyValue, TackyValue, TackyValue),
     Copy(TackyValue, TackyValue),
+    // SignExtend (src, dst) converts a signed value to a wider type
+    SignExtend(TackyValue, TackyValue),
+    // Truncate (src, dst) keeps the low bytes of a value, to fit a narrower type
+    Truncate(TackyValue, TackyValue),
+    // ZeroExtend (src, dst) converts an unsigned value to a wider type
+    ZeroExtend(TackyValue, TackyValue),
+    // DoubleToInt (src, dst) converts a double to a signed integer, truncating towards zero
+    DoubleToInt(TackyValue, TackyValue),
+    // DoubleToUInt (src, dst) converts a double to an unsigned integer, truncating towards zero
+    DoubleToUInt(TackyValue, TackyValue),
+    // IntToDouble (src, dst) converts a signed integer to the nearest double
+    IntToDouble(TackyValue, TackyValue),
+    // UIntToDouble (src, dst) converts an unsigned integer to the nearest double
+    UIntToDouble(TackyValue, TackyValue),
+    // GetAddress (src, dst) stores the address of the src variable in dst
+    GetAddress(TackyValue, TackyValue),
+    // Load (src_ptr, dst) copies the value src_ptr points to into dst
+    Load(TackyValue, TackyValue),
+    // Store (src, dst_ptr) copies src into the object dst_ptr points to
+    Store(TackyValue, TackyValue),
+    // AddPtr (ptr, index, scale, dst) computes the address ptr + index * scale
+    AddPtr(TackyValue, TackyValue, usize, TackyValue),
+    // CopyToOffset (src, dst, offset) copies src into the aggregate variable dst, offset bytes
+    // from its start
+    CopyToOffset(TackyValue, TackyIdentifier, usize),
+    // CopyFromOffset (src, offset, dst) copies the part of the aggregate variable src that
+    // starts offset bytes from its start into dst
+    CopyFromOffset(TackyIdentifier, usize, TackyValue),
     Jump(TackyIdentifier),
     JumpIfZero(TackyValue, TackyIdentifier),
     JumpIfNotZero(TackyValue, TackyIdentifier),
     Label(TackyIdentifier),
+    // JumpTable (index, table, targets) jumps to the target at the given index, which must be
+    // a ulong in bounds. The table is the label of the list of targets
+    JumpTable(TackyValue, TackyIdentifier, Vec<TackyIdentifier>),
+    // FunCall (fun_name, args, dst)
+    FunCall(TackyIdentifier, Vec<TackyValue>, TackyValue),
+    // IndirectCall (fun_ptr, args, dst) calls the function fun_ptr points to
+    IndirectCall(TackyValue, Vec<TackyValue>, TackyValue),
+    // VaStart (va_list_ptr) sets up the va_list ptr points to for the variadic arguments of
+    // the current function
+    VaStart(TackyValue),
 }
 
 #[derive(Clone, Debug)]
@@ -34,7 +108,7 @@ pub struct TackyIdentifier {
 
 #[derive(Clone, Debug)]
 pub enum TackyValue {
-    Constant(i32),
+    Constant(Const),
     Var(TackyIdentifier),
 }
 
@@ -68,18 +142,56 @@ pub enum TackyBinaryOperator {
     LessThanOrEqual,
 }
 
-
 impl TackyProgram {
-    pub fn new(function_definition: TackyFunctionDefinition) -> Self {
+    pub fn new(
+        function_definitions: Vec<TackyFunctionDefinition>,
+        static_variables: Vec<TackyStaticVariable>,
+    ) -> Self {
+        TackyProgram {
+            function_definitions,
+            static_variables,
+            static_constants: vec![],
+            symbols: SymbolTable::new(),
+        }
+    }
+
+    pub fn with_constants(self, static_constants: Vec<TackyStaticConstant>) -> Self {
         TackyProgram {
-            function_definition,
+            static_constants,
+            ..self
         }
     }
+
+    pub fn with_symbols(self, symbols: SymbolTable) -> Self {
+        TackyProgram { symbols, ..self }
+    }
 }
 
 impl TackyFunctionDefinition {
-    pub fn new(name: TackyIdentifier, instructions: Vec<TackyInstruction>) -> Self {
-        TackyFunctionDefinition { name, instructions }
+    pub fn new(
+        name: TackyIdentifier,
+        global: bool,
+        params: Vec<TackyIdentifier>,
+        instructions: Vec<TackyInstruction>,
+    ) -> Self {
+        TackyFunctionDefinition {
+            name,
+            global,
+            params,
+            instructions,
+        }
+    }
+}
+
+impl TackyStaticVariable {
+    pub fn new(name: TackyIdentifier, global: bool, init: StaticInit) -> Self {
+        TackyStaticVariable { name, global, init }
+    }
+}
+
+impl TackyStaticConstant {
+    pub fn new(name: TackyIdentifier, ty: Type, init: StaticInit) -> Self {
+        TackyStaticConstant { name, ty, init }
     }
 }
 
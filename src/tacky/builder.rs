@@ -3,27 +3,33 @@
 //! This builder encapsulates:
 //! - Name generation for temporaries and labels (replacing the global `static COUNTER`)
 //! - The instruction buffer with ergonomic `emit_*` methods
+//! - The symbol table, where every temporary is recorded with its type
 //!
 //! # Design Principles
 //!
 //! - **Thin**: Does not reorder or optimize; emits exactly what you ask for
 //! - **Explicit**: Control flow patterns remain visible in the calling code
 //! - **Scoped naming**: Each builder instance has its own counter, making names
-//!   predictable within a function
+//!   predictable within a program. A single builder lowers every function so labels
+//!   stay unique in the emitted assembly
 //!
 //! # Example
 //!
 //! ```ignore
-//! let mut builder = TackyBuilder::new();
+//! let mut builder = TackyBuilder::new().with_symbols(symbols);
 //!
-//! let tmp = builder.fresh_temp("x");
-//! builder.emit_copy(TackyValue::Constant(42), tmp.clone());
+//! let tmp = builder.fresh_temp("x", Type::Int);
+//! builder.emit_copy(TackyValue::Constant(Const::ConstInt(42)), tmp.clone());
 //! builder.emit_return(tmp);
 //!
-//! let instructions = builder.finish();
+//! let instructions = builder.take_instructions();
 //! ```
 
-use crate::tacky::ast::{TackyIdentifier, TackyInstruction, TackyValue};
+use crate::{
+    c_ast::ast::Type,
+    common::symbols::{IdentifierAttrs, Symbol, SymbolTable},
+    tacky::ast::{TackyIdentifier, TackyInstruction, TackyValue},
+};
 
 /// Builder for constructing TACKY instruction sequences.
 ///
@@ -33,6 +39,8 @@ pub struct TackyBuilder {
     instructions: Vec<TackyInstruction>,
     /// Counter for generating unique names (temporaries and labels)
     counter: usize,
+    /// Symbol table from semantic analysis, extended with the temporaries
+    symbols: SymbolTable,
 }
 
 impl TackyBuilder {
@@ -41,14 +49,41 @@ impl TackyBuilder {
         TackyBuilder {
             instructions: Vec::new(),
             counter: 0,
+            symbols: SymbolTable::new(),
         }
     }
 
-    /// Generates a fresh temporary variable with a unique suffix.
+    /// Sets the symbol table built during semantic analysis.
+    pub fn with_symbols(self, symbols: SymbolTable) -> Self {
+        TackyBuilder { symbols, ..self }
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Consumes the builder and returns the symbol table, temporaries included.
+    pub fn into_symbols(self) -> SymbolTable {
+        self.symbols
+    }
+
+    /// Generates a fresh temporary variable with a unique suffix and records its type.
     ///
-    /// Example: `fresh_temp("tmp")` → `TackyValue::Var("tmp.0")`, then `"tmp.1"`, etc.
-    pub fn fresh_temp(&mut self, name: &str) -> TackyValue {
-        TackyValue::Var(self.fresh_label(name))
+    /// Example: `fresh_temp("tmp", Type::Int)` → `TackyValue::Var("tmp.0")`, then `"tmp.1"`, etc.
+    pub fn fresh_temp(&mut self, name: &str, ty: Type) -> TackyValue {
+        let id = self.fresh_label(name);
+        self.symbols
+            .insert(&id.value, Symbol::new(ty, IdentifierAttrs::LocalAttr));
+
+        TackyValue::Var(id)
+    }
+
+    /// Records a string literal used as a value as a constant, and returns the variable that
+    /// holds it.
+    pub fn string_constant(&mut self, value: &str) -> TackyValue {
+        let name = self.symbols.add_string_constant(value);
+
+        TackyValue::Var(TackyIdentifier { value: name })
     }
 
     /// Generates a fresh label identifier with a unique suffix.
@@ -66,7 +101,11 @@ impl TackyBuilder {
     /// Does NOT increment the counter (labels come from semantic analysis).
     ///
     /// Example: `label_with_prefix("break_", loop_label)` → `"break_loop.1"`
-    pub fn label_with_prefix(&self, prefix: &str, label: &crate::c_ast::ast::Identifier) -> TackyIdentifier {
+    pub fn label_with_prefix(
+        &self,
+        prefix: &str,
+        label: &crate::c_ast::ast::Identifier,
+    ) -> TackyIdentifier {
         TackyIdentifier {
             value: format!("{}{}", prefix, label.value()),
         }
@@ -112,9 +151,10 @@ impl TackyBuilder {
         self.emit(TackyInstruction::Return(value));
     }
 
-    /// Consumes the builder and returns the accumulated instructions.
-    pub fn finish(self) -> Vec<TackyInstruction> {
-        self.instructions
+    /// Returns the accumulated instructions, leaving the buffer empty.
+    /// The counter is kept so names stay unique for the next function.
+    pub fn take_instructions(&mut self) -> Vec<TackyInstruction> {
+        std::mem::take(&mut self.instructions)
     }
 
     /// Returns the current number of instructions (useful for debugging).
@@ -122,6 +162,12 @@ impl TackyBuilder {
     pub fn len(&self) -> usize {
         self.instructions.len()
     }
+
+    /// Returns true if no instruction has been emitted yet.
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
 }
 
 impl Default for TackyBuilder {
@@ -2,7 +2,7 @@ use crate::{
     common::util::indent,
     tacky::ast::{
         TackyBinaryOperator, TackyFunctionDefinition, TackyInstruction, TackyProgram,
-        TackyUnaryOperator, TackyValue,
+        TackyStaticConstant, TackyStaticVariable, TackyUnaryOperator, TackyValue,
     },
 };
 
@@ -10,7 +10,35 @@ impl TackyProgram {
     pub fn pretty_print(&self) -> String {
         format!(
             "TackyProgram(\n{}\n)",
-            indent(&self.function_definition.pretty_print(), 4)
+            indent(
+                &self
+                    .static_variables
+                    .iter()
+                    .map(|v| v.pretty_print())
+                    .chain(self.static_constants.iter().map(|c| c.pretty_print()))
+                    .chain(self.function_definitions.iter().map(|f| f.pretty_print()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                4
+            )
+        )
+    }
+}
+
+impl TackyStaticVariable {
+    pub fn pretty_print(&self) -> String {
+        format!(
+            "TackyStaticVariable(name=\"{}\", global={}, init={})",
+            self.name.value, self.global, self.init
+        )
+    }
+}
+
+impl TackyStaticConstant {
+    pub fn pretty_print(&self) -> String {
+        format!(
+            "TackyStaticConstant(name=\"{}\", ty={}, init={})",
+            self.name.value, self.ty, self.init
         )
     }
 }
@@ -18,8 +46,20 @@ impl TackyProgram {
 impl TackyFunctionDefinition {
     pub fn pretty_print(&self) -> String {
         format!(
-            "TackyFunction(\n{}\n{}\n)",
+            "TackyFunction(\n{}\n{}\n{}\n{}\n)",
             indent(&format!("name=\"{}\",", self.name.value), 4),
+            indent(&format!("global={},", self.global), 4),
+            indent(
+                &format!(
+                    "params=[{}],",
+                    self.params
+                        .iter()
+                        .map(|p| p.value.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                4
+            ),
             indent(
                 &format!(
                     "instructions=[\n{}\n]",
@@ -58,14 +98,104 @@ impl TackyInstruction {
             TackyInstruction::Copy(src, dst) => {
                 format!("Copy({}, {})", src.pretty_print(), dst.pretty_print())
             }
+            TackyInstruction::SignExtend(src, dst) => {
+                format!("SignExtend({}, {})", src.pretty_print(), dst.pretty_print())
+            }
+            TackyInstruction::Truncate(src, dst) => {
+                format!("Truncate({}, {})", src.pretty_print(), dst.pretty_print())
+            }
+            TackyInstruction::ZeroExtend(src, dst) => {
+                format!("ZeroExtend({}, {})", src.pretty_print(), dst.pretty_print())
+            }
+            TackyInstruction::DoubleToInt(src, dst) => {
+                format!(
+                    "DoubleToInt({}, {})",
+                    src.pretty_print(),
+                    dst.pretty_print()
+                )
+            }
+            TackyInstruction::DoubleToUInt(src, dst) => {
+                format!(
+                    "DoubleToUInt({}, {})",
+                    src.pretty_print(),
+                    dst.pretty_print()
+                )
+            }
+            TackyInstruction::IntToDouble(src, dst) => {
+                format!(
+                    "IntToDouble({}, {})",
+                    src.pretty_print(),
+                    dst.pretty_print()
+                )
+            }
+            TackyInstruction::UIntToDouble(src, dst) => {
+                format!(
+                    "UIntToDouble({}, {})",
+                    src.pretty_print(),
+                    dst.pretty_print()
+                )
+            }
+            TackyInstruction::GetAddress(src, dst) => {
+                format!("GetAddress({}, {})", src.pretty_print(), dst.pretty_print())
+            }
+            TackyInstruction::Load(src, dst) => {
+                format!("Load({}, {})", src.pretty_print(), dst.pretty_print())
+            }
+            TackyInstruction::Store(src, dst) => {
+                format!("Store({}, {})", src.pretty_print(), dst.pretty_print())
+            }
+            TackyInstruction::AddPtr(ptr, index, scale, dst) => format!(
+                "AddPtr({}, {}, {scale}, {})",
+                ptr.pretty_print(),
+                index.pretty_print(),
+                dst.pretty_print()
+            ),
+            TackyInstruction::CopyToOffset(src, dst, offset) => format!(
+                "CopyToOffset({}, {}, {offset})",
+                src.pretty_print(),
+                dst.value
+            ),
+            TackyInstruction::CopyFromOffset(src, offset, dst) => format!(
+                "CopyFromOffset({}, {offset}, {})",
+                src.value,
+                dst.pretty_print()
+            ),
             TackyInstruction::Jump(id) => format!("Jump({})", id.value),
             TackyInstruction::JumpIfZero(val, id) => {
                 format!("JumpIfZero({}, {})", val.pretty_print(), id.value)
             }
+            TackyInstruction::JumpTable(index, table, targets) => {
+                let targets: Vec<&str> = targets.iter().map(|t| t.value.as_str()).collect();
+                format!(
+                    "JumpTable({}, {}, [{}])",
+                    index.pretty_print(),
+                    table.value,
+                    targets.join(", ")
+                )
+            }
             TackyInstruction::JumpIfNotZero(val, id) => {
                 format!("JumpIfNotZero({}, {})", val.pretty_print(), id.value)
             }
             TackyInstruction::Label(id) => format!("Label({})", id.value),
+            TackyInstruction::FunCall(name, args, dst) => format!(
+                "FunCall({}, [{}], {})",
+                name.value,
+                args.iter()
+                    .map(|a| a.pretty_print())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                dst.pretty_print()
+            ),
+            TackyInstruction::IndirectCall(fun_ptr, args, dst) => format!(
+                "IndirectCall({}, [{}], {})",
+                fun_ptr.pretty_print(),
+                args.iter()
+                    .map(|a| a.pretty_print())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                dst.pretty_print()
+            ),
+            TackyInstruction::VaStart(ap) => format!("VaStart({})", ap.pretty_print()),
         }
     }
 }
@@ -1,16 +1,21 @@
 //! This module contains the logic to lower the AST to TACKY IR.
 
+use std::cmp::Ordering;
+
 use log::{debug, info, trace};
 
 use crate::{
     c_ast::ast::{
-        BinaryOperator, Block, BlockItem, Declaration, Expression, ForInit, FunctionDefinition,
-        Identifier, Program, Statement, UnaryOperator,
+        BinaryOperator, Block, BlockItem, Const, Declaration, Expression, ForInit,
+        FunctionDeclaration, Identifier, Initializer, Program, Statement, Type, UnaryOperator,
+        VariableDeclaration,
     },
+    c_ast::semantic::type_table::TypeTable,
+    common::symbols::{IdentifierAttrs, InitialValue, StaticInit, Symbol, SymbolTable},
     tacky::{
         ast::{
             TackyBinaryOperator, TackyFunctionDefinition, TackyIdentifier, TackyInstruction,
-            TackyProgram, TackyUnaryOperator, TackyValue,
+            TackyProgram, TackyStaticConstant, TackyStaticVariable, TackyUnaryOperator, TackyValue,
         },
         builder::TackyBuilder,
     },
@@ -20,29 +25,109 @@ impl From<Program> for TackyProgram {
     fn from(program: Program) -> Self {
         trace!("[tacky] <program>");
 
-        TackyProgram::new(TackyFunctionDefinition::from(
-            program.into_function_definition(),
-        ))
+        let (declarations, symbols) = program.into_parts();
+        let static_variables = emit_static_variables(&symbols);
+        let mut builder = TackyBuilder::new().with_symbols(symbols);
+        let function_definitions = declarations
+            .into_iter()
+            .filter_map(|d| match d {
+                Declaration::FunDecl(f) => emit_function(f, &mut builder),
+                // file scope variables are emitted from the symbol table and structure
+                // layouts are in the type table
+                Declaration::VarDecl(_) | Declaration::StructDecl(_) | Declaration::EnumDecl(_) => {
+                    None
+                }
+            })
+            .collect();
+
+        // string literals become constants while lowering the functions
+        let static_constants = emit_static_constants(builder.symbols());
+
+        TackyProgram::new(function_definitions, static_variables)
+            .with_constants(static_constants)
+            .with_symbols(builder.into_symbols())
     }
 }
 
-impl From<FunctionDefinition> for TackyFunctionDefinition {
-    fn from(fd: FunctionDefinition) -> Self {
-        trace!("[tacky] <function> {}", fd.name().value());
+/// Lowers a function definition to TACKY.
+/// Declarations without a body don't produce any code, so they return `None`.
+fn emit_function(
+    fd: FunctionDeclaration,
+    builder: &mut TackyBuilder,
+) -> Option<TackyFunctionDefinition> {
+    let (name, params, body, _, _) = fd.into_parts();
+    let body = body?;
+    let global = builder
+        .symbols()
+        .get(name.value())
+        .is_none_or(Symbol::is_global);
+
+    trace!("[tacky] <function> {}", name.value());
+
+    emit_block(body, builder);
+
+    // add return 0 as last instruction (it's gonna be fixed in Part III)
+    builder.emit_return(TackyValue::Constant(Const::ConstInt(0)));
+
+    let instructions = builder.take_instructions();
+    info!("[tacky] {} instructions", instructions.len());
+
+    Some(TackyFunctionDefinition::new(
+        TackyIdentifier::from(name),
+        global,
+        params.into_iter().map(TackyIdentifier::from).collect(),
+        instructions,
+    ))
+}
 
-        let (name, body) = fd.into_parts();
+/// Collects the variables with static storage duration defined in this translation unit.
+/// Variables that are only declared `extern` are defined somewhere else, so they're skipped.
+fn emit_static_variables(symbols: &SymbolTable) -> Vec<TackyStaticVariable> {
+    let mut static_variables: Vec<_> = symbols
+        .iter()
+        .filter_map(|(name, symbol)| {
+            let IdentifierAttrs::StaticAttr { init, global } = &symbol.attrs else {
+                return None;
+            };
+            let init = match init {
+                InitialValue::Initial(c) => c.clone(),
+                InitialValue::Tentative => StaticInit::zero(&symbol.ty, symbols.types()),
+                InitialValue::NoInitializer => return None,
+            };
 
-        let mut builder = TackyBuilder::new();
-        emit_block(body, &mut builder);
+            Some(TackyStaticVariable::new(
+                TackyIdentifier::new(name),
+                *global,
+                init,
+            ))
+        })
+        .collect();
 
-        // add return 0 as last instruction (it's gonna be fixed in Part III)
-        builder.emit_return(TackyValue::Constant(0));
+    // symbols aren't ordered, sort them to get a deterministic output
+    static_variables.sort_by(|a, b| a.name.value.cmp(&b.name.value));
+    debug!("[tacky] {} static variables", static_variables.len());
 
-        let instructions = builder.finish();
-        info!("[tacky] {} instructions", instructions.len());
+    static_variables
+}
 
-        TackyFunctionDefinition::new(TackyIdentifier::from(name), instructions)
-    }
+/// Collects the read-only constants, the string literals that aren't copied into an array.
+fn emit_static_constants(symbols: &SymbolTable) -> Vec<TackyStaticConstant> {
+    let mut static_constants: Vec<_> = symbols
+        .iter()
+        .filter_map(|(name, symbol)| match &symbol.attrs {
+            IdentifierAttrs::ConstantAttr(init) => Some(TackyStaticConstant::new(
+                TackyIdentifier::new(name),
+                symbol.ty.clone(),
+                init.clone(),
+            )),
+            _ => None,
+        })
+        .collect();
+
+    static_constants.sort_by(|a, b| a.name.value.cmp(&b.name.value));
+    debug!("[tacky] {} static constants", static_constants.len());
+
+    static_constants
 }
 
 impl From<Identifier> for TackyIdentifier {
@@ -62,30 +147,35 @@ fn emit_block(block: Block, builder: &mut TackyBuilder) {
 fn emit_block_item(block_item: BlockItem, builder: &mut TackyBuilder) {
     match block_item {
         BlockItem::S(s) => emit_statement(s, builder),
-        BlockItem::D(d) => emit_declaration(d, builder),
+        // function and structure declarations inside a block don't produce any code
+        BlockItem::D(
+            Declaration::FunDecl(_) | Declaration::StructDecl(_) | Declaration::EnumDecl(_),
+        ) => {}
+        BlockItem::D(Declaration::VarDecl(d)) => emit_declaration(d, builder),
     }
 }
 
 fn emit_statement(statement: Statement, builder: &mut TackyBuilder) {
     match statement {
-        Statement::Return(expr) => {
+        Statement::Return(expr, _) => {
             trace!("[tacky] <statement> return");
             let v = emit_expr(expr, builder);
             builder.emit_return(v);
         }
-        Statement::Expression(expr) => {
+        Statement::Expression(expr, _) => {
             trace!("[tacky] <statement> expression");
             let _ = emit_expr(expr, builder);
         }
-        Statement::If(cond, then, el) => {
+        Statement::If(cond, then, el, _) => {
             trace!("[tacky] <statement> if");
 
             let else_label = builder.fresh_label("else");
             let end_label = builder.fresh_label("end");
 
             // emit condition
+            let cond_type = cond.ty(builder.symbols());
             let cond_result = emit_expr(*cond, builder);
-            let c = builder.fresh_temp("cond");
+            let c = builder.fresh_temp("cond", cond_type);
             builder.emit_copy(cond_result, c.clone());
             builder.emit_jump_if_zero(c, else_label.clone());
 
@@ -101,21 +191,21 @@ fn emit_statement(statement: Statement, builder: &mut TackyBuilder) {
             }
             builder.emit_label(end_label);
         }
-        Statement::Compound(block) => {
+        Statement::Compound(block, _) => {
             trace!("[tacky] <statement> compound");
             emit_block(*block, builder);
         }
-        Statement::Break(label) => {
+        Statement::Break(label, _) => {
             trace!("[tacky] <statement> break");
             let break_label = builder.label_with_prefix("break_", &label);
             builder.emit_jump(break_label);
         }
-        Statement::Continue(label) => {
+        Statement::Continue(label, _) => {
             trace!("[tacky] <statement> continue");
             let continue_label = builder.label_with_prefix("continue_", &label);
             builder.emit_jump(continue_label);
         }
-        Statement::While(cond, body, label) => {
+        Statement::While(cond, body, label, _) => {
             trace!("[tacky] <statement> while");
 
             let continue_label = builder.label_with_prefix("continue_", &label);
@@ -123,8 +213,9 @@ fn emit_statement(statement: Statement, builder: &mut TackyBuilder) {
 
             builder.emit_label(continue_label.clone());
 
+            let cond_type = cond.ty(builder.symbols());
             let res = emit_expr(*cond, builder);
-            let v = builder.fresh_temp("while_cond");
+            let v = builder.fresh_temp("while_cond", cond_type);
             builder.emit_copy(res, v.clone());
             builder.emit_jump_if_zero(v, break_label.clone());
 
@@ -132,7 +223,7 @@ fn emit_statement(statement: Statement, builder: &mut TackyBuilder) {
             builder.emit_jump(continue_label);
             builder.emit_label(break_label);
         }
-        Statement::DoWhile(body, cond, label) => {
+        Statement::DoWhile(body, cond, label, _) => {
             trace!("[tacky] <statement> do-while");
 
             let start_label = builder.label_with_prefix("start_", &label);
@@ -143,13 +234,14 @@ fn emit_statement(statement: Statement, builder: &mut TackyBuilder) {
             emit_statement(*body, builder);
             builder.emit_label(continue_label);
 
+            let cond_type = cond.ty(builder.symbols());
             let res = emit_expr(*cond, builder);
-            let v = builder.fresh_temp("dowhile_cond");
+            let v = builder.fresh_temp("dowhile_cond", cond_type);
             builder.emit_copy(res, v.clone());
             builder.emit_jump_if_not_zero(v, start_label);
             builder.emit_label(break_label);
         }
-        Statement::For(for_init, cond, post, body, label) => {
+        Statement::For(for_init, cond, post, body, label, _) => {
             trace!("[tacky] <statement> for");
 
             let start_label = builder.label_with_prefix("start_", &label);
@@ -165,8 +257,9 @@ fn emit_statement(statement: Statement, builder: &mut TackyBuilder) {
             // emit condition (if present)
             if let Some(cond) = cond {
                 trace!("[tacky] for cond");
+                let cond_type = cond.ty(builder.symbols());
                 let res = emit_expr(*cond, builder);
-                let v = builder.fresh_temp("for_cond");
+                let v = builder.fresh_temp("for_cond", cond_type);
                 builder.emit_copy(res, v.clone());
                 builder.emit_jump_if_zero(v, break_label.clone());
             }
@@ -184,27 +277,270 @@ fn emit_statement(statement: Statement, builder: &mut TackyBuilder) {
             builder.emit_jump(start_label);
             builder.emit_label(break_label);
         }
-        Statement::Null => {}
+        Statement::Switch(cond, body, label, cases, _) => {
+            trace!("[tacky] <statement> switch");
+
+            let break_label = builder.label_with_prefix("break_", &label);
+            let cond_type = cond.ty(builder.symbols());
+            let value = emit_expr(*cond, builder);
+            let default_label = cases
+                .iter()
+                .find(|(value, _)| value.is_none())
+                .map_or(break_label.clone(), |(_, label)| {
+                    TackyIdentifier::from(label.clone())
+                });
+            let cases: Vec<(Const, TackyIdentifier)> = cases
+                .into_iter()
+                .filter_map(|(value, label)| Some((value?, TackyIdentifier::from(label))))
+                .collect();
+
+            if is_dense(&cases) {
+                emit_jump_table(value, &cond_type, cases, default_label, builder);
+            } else {
+                emit_compare_chain(value, cases, builder);
+                builder.emit_jump(default_label);
+            }
+
+            emit_statement(*body, builder);
+            builder.emit_label(break_label);
+        }
+        Statement::Case(_, st, label, _) | Statement::Default(st, label, _) => {
+            trace!("[tacky] <statement> case");
+            builder.emit_label(TackyIdentifier::from(label));
+            emit_statement(*st, builder);
+        }
+        Statement::Goto(label, _) => {
+            trace!("[tacky] <statement> goto");
+            let goto_label = builder.label_with_prefix("goto_", &label);
+            builder.emit_jump(goto_label);
+        }
+        Statement::Labeled(label, st, _) => {
+            trace!("[tacky] <statement> labeled");
+            let goto_label = builder.label_with_prefix("goto_", &label);
+            builder.emit_label(goto_label);
+            emit_statement(*st, builder);
+        }
+        Statement::VaStart(ap, _, _) => {
+            trace!("[tacky] <statement> va_start");
+            let ap = emit_expr(ap, builder);
+            builder.emit(TackyInstruction::VaStart(ap));
+        }
+        // there's nothing to release, only the side effects of the operand are left
+        Statement::VaEnd(ap, _) => {
+            trace!("[tacky] <statement> va_end");
+            let _ = emit_expr(ap, builder);
+        }
+        Statement::VaCopy(..) => {
+            panic!("va_copy, turned into an assignment by the type checker")
+        }
+        Statement::Null(_) => {}
+    }
+}
+
+/// Smallest switch lowered to a jump table, fewer cases are compared one by one.
+const JUMP_TABLE_MIN_CASES: usize = 4;
+
+/// Returns the value of a case as a number that orders the cases the way their type does:
+/// unsigned longs don't fit in an i64.
+fn case_key(value: &Const) -> i128 {
+    match value {
+        Const::ConstULong(c) => *c as i128,
+        _ => value.as_i64() as i128,
     }
 }
 
-fn emit_declaration(declaration: Declaration, builder: &mut TackyBuilder) {
-    let (name, initializer) = declaration.into_parts();
+/// Whether the cases are dense enough for a jump table: there are enough of them and at
+/// least half of the entries of the table would be cases.
+fn is_dense(cases: &[(Const, TackyIdentifier)]) -> bool {
+    if cases.len() < JUMP_TABLE_MIN_CASES {
+        return false;
+    }
 
-    let Some(initializer) = initializer else {
+    let keys = cases.iter().map(|(value, _)| case_key(value));
+    let (Some(min), Some(max)) = (keys.clone().min(), keys.max()) else {
+        return false;
+    };
+    max - min < 2 * cases.len() as i128
+}
+
+/// Compares the value with every case in turn and jumps to the first that matches.
+fn emit_compare_chain(
+    value: TackyValue,
+    cases: Vec<(Const, TackyIdentifier)>,
+    builder: &mut TackyBuilder,
+) {
+    for (case, label) in cases {
+        let matches = builder.fresh_temp("switch_cmp", Type::Int);
+        builder.emit(TackyInstruction::Binary(
+            TackyBinaryOperator::Equal,
+            value.clone(),
+            TackyValue::Constant(case),
+            matches.clone(),
+        ));
+        builder.emit_jump_if_not_zero(matches, label);
+    }
+}
+
+/// Jumps through a table indexed by the value minus the smallest case. The value is
+/// extended to a ulong first, so a single unsigned comparison catches the values below the
+/// smallest case and above the largest one, which go to the default.
+fn emit_jump_table(
+    value: TackyValue,
+    cond_type: &Type,
+    cases: Vec<(Const, TackyIdentifier)>,
+    default_label: TackyIdentifier,
+    builder: &mut TackyBuilder,
+) {
+    let Some(min) = cases.iter().map(|(value, _)| case_key(value)).min() else {
+        return;
+    };
+    let mut targets = vec![];
+    for (case, label) in cases {
+        let index = (case_key(&case) - min) as usize;
+        if targets.len() <= index {
+            targets.resize(index + 1, default_label.clone());
+        }
+        targets[index] = label;
+    }
+
+    let extended = builder.fresh_temp("switch_value", Type::ULong);
+    builder.emit(match cond_type {
+        Type::Int => TackyInstruction::SignExtend(value, extended.clone()),
+        Type::UInt => TackyInstruction::ZeroExtend(value, extended.clone()),
+        _ => TackyInstruction::Copy(value, extended.clone()),
+    });
+    let index = builder.fresh_temp("switch_index", Type::ULong);
+    builder.emit(TackyInstruction::Binary(
+        TackyBinaryOperator::Subtract,
+        extended,
+        TackyValue::Constant(Const::ConstULong(min as u64)),
+        index.clone(),
+    ));
+    let out_of_range = builder.fresh_temp("switch_range", Type::Int);
+    builder.emit(TackyInstruction::Binary(
+        TackyBinaryOperator::GreaterThan,
+        index.clone(),
+        TackyValue::Constant(Const::ConstULong(targets.len() as u64 - 1)),
+        out_of_range.clone(),
+    ));
+    builder.emit_jump_if_not_zero(out_of_range, default_label);
+
+    let table = builder.fresh_label("switch_table");
+    builder.emit(TackyInstruction::JumpTable(index, table, targets));
+}
+
+fn emit_declaration(declaration: VariableDeclaration, builder: &mut TackyBuilder) {
+    let (name, initializer, var_type, storage_class) = declaration.into_parts();
+
+    // static variables are initialized in the data section and extern ones are defined
+    // somewhere else, neither of them produces any code
+    let (Some(initializer), None) = (initializer, storage_class) else {
         return;
     };
 
-    let v = emit_expr(initializer, builder);
-    let dst = TackyValue::Var(TackyIdentifier::from(name));
-    builder.emit_copy(v, dst);
+    match (initializer, &var_type) {
+        (Initializer::Single(e), _) if !var_type.is_array() => {
+            let v = emit_expr(e, builder);
+            let dst = TackyValue::Var(TackyIdentifier::from(name));
+            builder.emit_copy(v, dst);
+        }
+        (initializer, _) => {
+            emit_aggregate_initializer(
+                initializer,
+                &var_type,
+                &TackyIdentifier::from(name),
+                0,
+                builder,
+            );
+        }
+    }
+}
+
+/// Copies every element of an array initializer (or member of a structure initializer) into
+/// the variable, at the offset of the element. Nested aggregates are flattened, so `offset`
+/// is where the current one starts.
+fn emit_aggregate_initializer(
+    initializer: Initializer,
+    ty: &Type,
+    name: &TackyIdentifier,
+    offset: usize,
+    builder: &mut TackyBuilder,
+) {
+    match (initializer, ty.unqualified()) {
+        (Initializer::Single(Expression::String(s, _)), Type::Array(_, size)) => {
+            emit_string_initializer(&s, *size, name, offset, builder);
+        }
+        (Initializer::Single(e), _) => {
+            let v = emit_expr(e, builder);
+            builder.emit(TackyInstruction::CopyToOffset(v, name.clone(), offset));
+        }
+        (Initializer::Compound(initializers), Type::Array(element_type, _)) => {
+            let element_size = element_type.size(builder.symbols().types());
+            for (i, initializer) in initializers.into_iter().enumerate() {
+                let offset = offset + i * element_size;
+                emit_aggregate_initializer(initializer, element_type, name, offset, builder);
+            }
+        }
+        (Initializer::Compound(initializers), Type::Structure(tag)) => {
+            let members = builder
+                .symbols()
+                .types()
+                .get(tag)
+                .map(|entry| entry.members.clone())
+                .unwrap_or_default();
+            for (initializer, member) in initializers.into_iter().zip(members) {
+                let offset = offset + member.offset;
+                emit_aggregate_initializer(initializer, &member.ty, name, offset, builder);
+            }
+        }
+        (Initializer::Compound(_), _) => {
+            panic!("compound initializer for {ty:?}, rejected by the type checker")
+        }
+    }
+}
+
+/// Copies a string literal into a character array of `size` bytes, padded with zeros. The
+/// bytes are copied 8 (or 4) at a time while there are enough of them left.
+fn emit_string_initializer(
+    s: &str,
+    size: usize,
+    name: &TackyIdentifier,
+    offset: usize,
+    builder: &mut TackyBuilder,
+) {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.resize(size, 0);
+    // x86_64 is little endian, the first byte is the least significant one
+    let little_endian = |chunk: &[u8]| {
+        chunk
+            .iter()
+            .rev()
+            .fold(0u64, |value, byte| (value << 8) | u64::from(*byte))
+    };
+
+    let mut i = 0;
+    while i < size {
+        let (constant, len) = match size - i {
+            8.. => (Const::ConstLong(little_endian(&bytes[i..i + 8]) as i64), 8),
+            4.. => (Const::ConstInt(little_endian(&bytes[i..i + 4]) as i32), 4),
+            _ => (Const::ConstChar(bytes[i] as i8), 1),
+        };
+        builder.emit(TackyInstruction::CopyToOffset(
+            TackyValue::Constant(constant),
+            name.clone(),
+            offset + i,
+        ));
+        i += len;
+    }
 }
 
 fn emit_for_init(for_init: ForInit, builder: &mut TackyBuilder) {
     match for_init {
-        ForInit::InitDecl(declaration) => {
-            trace!("[tacky] for init with declaration");
-            emit_declaration(*declaration, builder);
+        ForInit::InitDecl(declarations) => {
+            trace!("[tacky] for init with declarations");
+            for declaration in declarations {
+                emit_declaration(declaration, builder);
+            }
         }
         ForInit::InitExp(expression) => {
             let Some(expression) = expression else {
@@ -217,20 +553,140 @@ fn emit_for_init(for_init: ForInit, builder: &mut TackyBuilder) {
     }
 }
 
+/// The result of an lvalue: either an operand that can be written directly, the
+/// pointer to the object that has to be written through `Store`, or a member of a
+/// structure variable, at an offset from its start.
+enum ExpResult {
+    PlainOperand(TackyValue),
+    DereferencedPointer(TackyValue),
+    SubObject(TackyIdentifier, usize),
+}
+
+/// Lowers the target of an assignment or of `&` to TACKY, without reading the object.
+fn emit_lvalue(expr: Expression, builder: &mut TackyBuilder) -> ExpResult {
+    match expr {
+        Expression::Var(id, _) => {
+            ExpResult::PlainOperand(TackyValue::Var(TackyIdentifier::from(id)))
+        }
+        Expression::Dereference(inner, _) => {
+            ExpResult::DereferencedPointer(emit_expr(*inner, builder))
+        }
+        // `a[i]` is `*(a + i)`
+        Expression::Subscript(left, right, _) => {
+            let ptr_type = match left.ty(builder.symbols()) {
+                ptr_type @ Type::Pointer(_) => ptr_type,
+                _ => right.ty(builder.symbols()),
+            };
+            ExpResult::DereferencedPointer(emit_pointer_addition(*left, *right, ptr_type, builder))
+        }
+        Expression::Dot(inner, member, _) => {
+            let (offset, member_type) = member_of(&inner.ty(builder.symbols()), &member, builder);
+            match emit_lvalue(*inner, builder) {
+                ExpResult::PlainOperand(TackyValue::Var(id)) => ExpResult::SubObject(id, offset),
+                ExpResult::PlainOperand(TackyValue::Constant(_)) => {
+                    panic!("member of a constant, rejected by the type checker")
+                }
+                ExpResult::SubObject(id, base) => ExpResult::SubObject(id, base + offset),
+                ExpResult::DereferencedPointer(ptr) => ExpResult::DereferencedPointer(
+                    emit_offset_pointer(ptr, offset, Type::Pointer(Box::new(member_type)), builder),
+                ),
+            }
+        }
+        // `p->m` is `(*p).m`
+        Expression::Arrow(inner, member, _) => {
+            let struct_type = match inner.ty(builder.symbols()) {
+                Type::Pointer(referenced) => *referenced,
+                ty => ty,
+            };
+            let (offset, member_type) = member_of(&struct_type, &member, builder);
+            let ptr = emit_expr(*inner, builder);
+            ExpResult::DereferencedPointer(emit_offset_pointer(
+                ptr,
+                offset,
+                Type::Pointer(Box::new(member_type)),
+                builder,
+            ))
+        }
+        _ => ExpResult::PlainOperand(emit_expr(expr, builder)),
+    }
+}
+
+/// Returns the offset and the type of a member of a structure type.
+fn member_of(struct_type: &Type, member: &Identifier, builder: &TackyBuilder) -> (usize, Type) {
+    let Some(entry) = struct_type.member(member.value(), builder.symbols().types()) else {
+        panic!(
+            "unknown member {}, rejected by the type checker",
+            member.value()
+        )
+    };
+
+    (entry.offset, entry.ty.clone())
+}
+
+/// Adds a constant number of bytes to a pointer.
+fn emit_offset_pointer(
+    ptr: TackyValue,
+    offset: usize,
+    ptr_type: Type,
+    builder: &mut TackyBuilder,
+) -> TackyValue {
+    if offset == 0 {
+        return ptr;
+    }
+
+    let dst = builder.fresh_temp("member_ptr", ptr_type);
+    builder.emit(TackyInstruction::AddPtr(
+        ptr,
+        TackyValue::Constant(Const::ConstLong(offset as i64)),
+        1,
+        dst.clone(),
+    ));
+
+    dst
+}
+
+/// Computes the address of an lvalue, for `&` and for the decay of arrays.
+fn emit_address(
+    lvalue: ExpResult,
+    ptr_type: Type,
+    prefix: &str,
+    builder: &mut TackyBuilder,
+) -> TackyValue {
+    match lvalue {
+        ExpResult::PlainOperand(src) => {
+            let dst = builder.fresh_temp(prefix, ptr_type);
+            builder.emit(TackyInstruction::GetAddress(src, dst.clone()));
+            dst
+        }
+        ExpResult::SubObject(id, offset) => {
+            let base = builder.fresh_temp(prefix, ptr_type.clone());
+            builder.emit(TackyInstruction::GetAddress(
+                TackyValue::Var(id),
+                base.clone(),
+            ));
+            emit_offset_pointer(base, offset, ptr_type, builder)
+        }
+        // `&*p` is just `p`
+        ExpResult::DereferencedPointer(ptr) => ptr,
+    }
+}
+
 /// Lowers an Expression to TACKY.
 /// Emits instructions via builder and returns a `TackyValue` identifying the result.
 fn emit_expr(expr: Expression, builder: &mut TackyBuilder) -> TackyValue {
+    let ty = expr.ty(builder.symbols());
     match expr {
-        Expression::Conditional(cond, then, el) => {
+        Expression::Conditional(cond, then, el, _) => {
             trace!("[tacky] <exp> conditional");
 
-            let result = builder.fresh_temp("ternary_result");
+            let result = builder.fresh_temp("ternary_result", ty);
             let else_label = builder.fresh_label("ternary_else");
             let end_label = builder.fresh_label("ternary_end");
 
             // emit condition
+            let cond_type = cond.ty(builder.symbols());
             let cond_val = emit_expr(*cond, builder);
-            let c_result = builder.fresh_temp("ternary_cond");
+            let c_result = builder.fresh_temp("ternary_cond", cond_type);
             builder.emit_copy(cond_val, c_result.clone());
             builder.emit_jump_if_zero(c_result, else_label.clone());
 
@@ -247,25 +703,105 @@ fn emit_expr(expr: Expression, builder: &mut TackyBuilder) -> TackyValue {
 
             result
         }
-        Expression::Assignment(left, right) => {
+        Expression::Comma(left, right, _) => {
+            trace!("[tacky] <exp> comma");
+
+            let _ = emit_expr(*left, builder);
+            emit_expr(*right, builder)
+        }
+        Expression::Assignment(left, right, _) => {
             trace!("[tacky] <exp> assignment");
 
+            let lvalue = emit_lvalue(*left, builder);
             let res = emit_expr(*right, builder);
-            let left_var = match *left {
-                Expression::Var(id) => TackyValue::Var(TackyIdentifier::from(id)),
-                _ => panic!("invalid lvalue in assignment"),
+            emit_store(lvalue, res, builder)
+        }
+        Expression::CompoundAssignment(op, left, right, _) => {
+            trace!("[tacky] <exp> compound assignment {op:?}");
+
+            // the right operand already has the type the operation is done in, except for
+            // pointers where it's the index
+            let op_type = if ty.is_pointer() {
+                ty.clone()
+            } else {
+                right.ty(builder.symbols())
+            };
+            let lvalue = emit_lvalue(*left, builder);
+            let v2 = emit_expr(*right, builder);
+            let current = emit_read(&lvalue, ty.clone(), builder);
+            let res = emit_compound_op(op, current, v2, &ty, op_type, builder);
+            emit_store(lvalue, res, builder)
+        }
+        Expression::Postfix(op, inner, _) => {
+            trace!("[tacky] <exp> postfix {op:?}");
+
+            let lvalue = emit_lvalue(*inner, builder);
+            let current = emit_read(&lvalue, ty.clone(), builder);
+            // a variable is about to change, so its old value is kept in a temporary
+            let old = match lvalue {
+                ExpResult::PlainOperand(_) => {
+                    let old = builder.fresh_temp("postfix_old", ty.clone());
+                    builder.emit_copy(current.clone(), old.clone());
+                    old
+                }
+                _ => current.clone(),
             };
-            builder.emit_copy(res, left_var.clone());
 
-            left_var
+            let (one_type, op_type) = if ty.is_pointer() {
+                (Type::Long, ty.clone())
+            } else {
+                let common_type = Type::common_type(&ty, &Type::Int);
+                (common_type.clone(), common_type)
+            };
+            let one = TackyValue::Constant(Const::ConstInt(1).convert_to(&one_type));
+            let res = emit_compound_op(op, current, one, &ty, op_type, builder);
+            emit_store(lvalue, res, builder);
+
+            old
+        }
+        Expression::Dereference(..)
+        | Expression::Subscript(..)
+        | Expression::Dot(..)
+        | Expression::Arrow(..) => {
+            trace!("[tacky] <exp> dereference");
+
+            let lvalue = emit_lvalue(expr, builder);
+            emit_read(&lvalue, ty, builder)
         }
-        Expression::Var(id) => TackyValue::Var(TackyIdentifier::from(id)),
-        Expression::Constant(c) => TackyValue::Constant(c),
-        Expression::Unary(op, inner) => {
+        Expression::AddressOf(inner, _) => {
+            trace!("[tacky] <exp> address of");
+
+            let lvalue = emit_lvalue(*inner, builder);
+            emit_address(lvalue, ty, "addr", builder)
+        }
+        // every read of a volatile variable is an instruction of its own, even when the value
+        // is discarded (`x;`), so that no later pass can drop or merge it
+        Expression::Var(id, _) if is_volatile_var(&id, builder) => {
+            let dst = builder.fresh_temp("volatile", ty);
+            builder.emit_copy(TackyValue::Var(TackyIdentifier::from(id)), dst.clone());
+            dst
+        }
+        Expression::Var(id, _) => TackyValue::Var(TackyIdentifier::from(id)),
+        Expression::Constant(c, _) => TackyValue::Constant(c),
+        Expression::String(s, _) => builder.string_constant(&s),
+        Expression::Cast(target, inner, _) => {
+            trace!("[tacky] <exp> cast to {target:?}");
+
+            let inner_type = inner.ty(builder.symbols());
+            // an array decays to the address of its first element
+            if inner_type.is_array() {
+                let lvalue = emit_lvalue(*inner, builder);
+                return emit_address(lvalue, target, "decay", builder);
+            }
+
+            let src = emit_expr(*inner, builder);
+            emit_cast(src, &inner_type, &target, builder)
+        }
+        Expression::Unary(op, inner, _) => {
             trace!("[tacky] <exp> unary {op:?}");
 
             let src = emit_expr(*inner, builder);
-            let dst = builder.fresh_temp("unary");
+            let dst = builder.fresh_temp("unary", ty);
             builder.emit(TackyInstruction::Unary(
                 TackyUnaryOperator::from(op),
                 src,
@@ -274,23 +810,301 @@ fn emit_expr(expr: Expression, builder: &mut TackyBuilder) -> TackyValue {
 
             dst
         }
-        Expression::Binary(op, left, right) => {
+        Expression::Binary(op, left, right, _) => {
             trace!("[tacky] <exp> binary {op:?}");
-            emit_binary_op(op, *left, *right, builder)
+            emit_binary_op(op, *left, *right, ty, builder)
+        }
+        Expression::FunctionCall(name, args, _) => {
+            trace!("[tacky] <exp> call {}", name.value());
+
+            let args = args.into_iter().map(|a| emit_expr(a, builder)).collect();
+            let dst = builder.fresh_temp("call", ty);
+            builder.emit(TackyInstruction::FunCall(
+                TackyIdentifier::from(name),
+                args,
+                dst.clone(),
+            ));
+
+            dst
+        }
+        Expression::IndirectCall(callee, args, _) => {
+            trace!("[tacky] <exp> indirect call");
+
+            let fun_ptr = emit_expr(*callee, builder);
+            let args = args.into_iter().map(|a| emit_expr(a, builder)).collect();
+            let dst = builder.fresh_temp("call", ty);
+            builder.emit(TackyInstruction::IndirectCall(fun_ptr, args, dst.clone()));
+
+            dst
+        }
+        Expression::VaArg(ap, _, _) => {
+            trace!("[tacky] <exp> va_arg");
+
+            let ap = emit_expr(*ap, builder);
+            emit_va_arg(ap, ty, builder)
         }
+        Expression::Error(_) => panic!("error placeholder, rejected by the semantic passes"),
     }
 }
 
+/// Reads the next variadic argument of the given type from the `va_list` `ap` points to
+/// and moves past it (System V AMD64 ABI).
+///
+/// While the offset of the next register (`gp_offset` for integers and pointers, `fp_offset`
+/// for doubles) is still inside the register save area the argument is read from there and
+/// the offset moves to the next register, otherwise it's read from the overflow area on the
+/// stack, where every argument takes 8 bytes.
+fn emit_va_arg(ap: TackyValue, ty: Type, builder: &mut TackyBuilder) -> TackyValue {
+    let char_ptr = Type::Pointer(Box::new(Type::Char));
+    let (offset_member, save_area_end, register_size) = if ty == Type::Double {
+        (4, 176, 16)
+    } else {
+        (0, 48, 8)
+    };
+    let dst = builder.fresh_temp("va_arg", ty.clone());
+    let stack_label = builder.fresh_label("va_arg_stack");
+    let end_label = builder.fresh_label("va_arg_end");
+
+    let offset_ptr = emit_offset_pointer(
+        ap.clone(),
+        offset_member,
+        Type::Pointer(Box::new(Type::UInt)),
+        builder,
+    );
+    let offset = builder.fresh_temp("va_offset", Type::UInt);
+    builder.emit(TackyInstruction::Load(offset_ptr.clone(), offset.clone()));
+    let in_stack = builder.fresh_temp("va_in_stack", Type::Int);
+    builder.emit(TackyInstruction::Binary(
+        TackyBinaryOperator::GreaterThanOrEqual,
+        offset.clone(),
+        TackyValue::Constant(Const::ConstUInt(save_area_end)),
+        in_stack.clone(),
+    ));
+    builder.emit_jump_if_not_zero(in_stack, stack_label.clone());
+
+    // in the register save area
+    let save_area_ptr = emit_offset_pointer(
+        ap.clone(),
+        16,
+        Type::Pointer(Box::new(char_ptr.clone())),
+        builder,
+    );
+    let save_area = builder.fresh_temp("va_save_area", char_ptr.clone());
+    builder.emit(TackyInstruction::Load(save_area_ptr, save_area.clone()));
+    let long_offset = builder.fresh_temp("va_offset", Type::ULong);
+    builder.emit(TackyInstruction::ZeroExtend(
+        offset.clone(),
+        long_offset.clone(),
+    ));
+    let arg_ptr = builder.fresh_temp("va_arg_ptr", Type::Pointer(Box::new(ty.clone())));
+    builder.emit(TackyInstruction::AddPtr(
+        save_area,
+        long_offset,
+        1,
+        arg_ptr.clone(),
+    ));
+    builder.emit(TackyInstruction::Load(arg_ptr, dst.clone()));
+    let next_offset = builder.fresh_temp("va_offset", Type::UInt);
+    builder.emit(TackyInstruction::Binary(
+        TackyBinaryOperator::Add,
+        offset,
+        TackyValue::Constant(Const::ConstUInt(register_size)),
+        next_offset.clone(),
+    ));
+    builder.emit(TackyInstruction::Store(next_offset, offset_ptr));
+    builder.emit_jump(end_label.clone());
+
+    // in the overflow area
+    builder.emit_label(stack_label);
+    let overflow_ptr =
+        emit_offset_pointer(ap, 8, Type::Pointer(Box::new(char_ptr.clone())), builder);
+    let overflow = builder.fresh_temp("va_overflow", char_ptr.clone());
+    builder.emit(TackyInstruction::Load(
+        overflow_ptr.clone(),
+        overflow.clone(),
+    ));
+    builder.emit(TackyInstruction::Load(overflow.clone(), dst.clone()));
+    let next_overflow = builder.fresh_temp("va_overflow", char_ptr);
+    builder.emit(TackyInstruction::AddPtr(
+        overflow,
+        TackyValue::Constant(Const::ConstLong(8)),
+        1,
+        next_overflow.clone(),
+    ));
+    builder.emit(TackyInstruction::Store(next_overflow, overflow_ptr));
+    builder.emit_label(end_label);
+
+    dst
+}
+
+fn is_volatile_var(id: &Identifier, builder: &TackyBuilder) -> bool {
+    builder
+        .symbols()
+        .get(id.value())
+        .is_some_and(|symbol| symbol.ty.is_volatile())
+}
+
+/// Reads the current value of an lvalue.
+fn emit_read(lvalue: &ExpResult, ty: Type, builder: &mut TackyBuilder) -> TackyValue {
+    match lvalue {
+        ExpResult::PlainOperand(v) => v.clone(),
+        ExpResult::DereferencedPointer(ptr) => {
+            let dst = builder.fresh_temp("deref", ty);
+            builder.emit(TackyInstruction::Load(ptr.clone(), dst.clone()));
+            dst
+        }
+        ExpResult::SubObject(id, offset) => {
+            let dst = builder.fresh_temp("member", ty);
+            builder.emit(TackyInstruction::CopyFromOffset(
+                id.clone(),
+                *offset,
+                dst.clone(),
+            ));
+            dst
+        }
+    }
+}
+
+/// Writes a value to an lvalue and returns the value of the assignment.
+fn emit_store(lvalue: ExpResult, value: TackyValue, builder: &mut TackyBuilder) -> TackyValue {
+    match lvalue {
+        ExpResult::PlainOperand(dst) => {
+            builder.emit_copy(value, dst.clone());
+            dst
+        }
+        ExpResult::DereferencedPointer(ptr) => {
+            builder.emit(TackyInstruction::Store(value.clone(), ptr));
+            value
+        }
+        ExpResult::SubObject(id, offset) => {
+            builder.emit(TackyInstruction::CopyToOffset(value.clone(), id, offset));
+            value
+        }
+    }
+}
+
+/// Converts a value from one type to another.
+fn emit_cast(
+    src: TackyValue,
+    inner_type: &Type,
+    target: &Type,
+    builder: &mut TackyBuilder,
+) -> TackyValue {
+    if target == inner_type {
+        return src;
+    }
+
+    let dst = builder.fresh_temp("cast", target.clone());
+    // between types of the same size the bits stay the same, only their meaning changes
+    let instruction = match (target, inner_type) {
+        (Type::Double, _) if inner_type.is_signed() => {
+            TackyInstruction::IntToDouble(src, dst.clone())
+        }
+        (Type::Double, _) => TackyInstruction::UIntToDouble(src, dst.clone()),
+        (_, Type::Double) if target.is_signed() => TackyInstruction::DoubleToInt(src, dst.clone()),
+        (_, Type::Double) => TackyInstruction::DoubleToUInt(src, dst.clone()),
+        _ => match target
+            .size(builder.symbols().types())
+            .cmp(&inner_type.size(builder.symbols().types()))
+        {
+            Ordering::Equal => TackyInstruction::Copy(src, dst.clone()),
+            Ordering::Less => TackyInstruction::Truncate(src, dst.clone()),
+            Ordering::Greater if inner_type.is_signed() => {
+                TackyInstruction::SignExtend(src, dst.clone())
+            }
+            Ordering::Greater => TackyInstruction::ZeroExtend(src, dst.clone()),
+        },
+    };
+    builder.emit(instruction);
+
+    dst
+}
+
+/// Computes the new value of a compound assignment or of `++`/`--`: the current value is
+/// converted to the type of the operation and the result is converted back. A pointer is
+/// moved by the (negated, for `-=`) index instead.
+fn emit_compound_op(
+    op: BinaryOperator,
+    current: TackyValue,
+    right: TackyValue,
+    left_type: &Type,
+    op_type: Type,
+    builder: &mut TackyBuilder,
+) -> TackyValue {
+    if left_type.is_pointer() {
+        let index = match op {
+            BinaryOperator::Subtract => {
+                let negated = builder.fresh_temp("neg_index", Type::Long);
+                builder.emit(TackyInstruction::Unary(
+                    TackyUnaryOperator::Negate,
+                    right,
+                    negated.clone(),
+                ));
+                negated
+            }
+            _ => right,
+        };
+        let dst = builder.fresh_temp("ptr_add", left_type.clone());
+        builder.emit(TackyInstruction::AddPtr(
+            current,
+            index,
+            referenced_size(left_type, builder.symbols().types()),
+            dst.clone(),
+        ));
+
+        return dst;
+    }
+
+    let current = emit_cast(current, left_type, &op_type, builder);
+    let dst = builder.fresh_temp("compound", op_type.clone());
+    builder.emit(TackyInstruction::Binary(
+        TackyBinaryOperator::from(op),
+        current,
+        right,
+        dst.clone(),
+    ));
+
+    emit_cast(dst, &op_type, left_type, builder)
+}
+
+/// Size of the objects a pointer points to, used to scale the integer in pointer arithmetic.
+fn referenced_size(ptr_type: &Type, types: &TypeTable) -> usize {
+    match ptr_type {
+        Type::Pointer(referenced) => referenced.size(types),
+        _ => 1,
+    }
+}
+
+/// Lowers the addition of a pointer and an integer, in any order, to an `AddPtr`.
+fn emit_pointer_addition(
+    left: Expression,
+    right: Expression,
+    ptr_type: Type,
+    builder: &mut TackyBuilder,
+) -> TackyValue {
+    let left_is_pointer = left.ty(builder.symbols()).is_pointer();
+    let v1 = emit_expr(left, builder);
+    let v2 = emit_expr(right, builder);
+    let (ptr, index) = if left_is_pointer { (v1, v2) } else { (v2, v1) };
+
+    let scale = referenced_size(&ptr_type, builder.symbols().types());
+    let dst = builder.fresh_temp("ptr_add", ptr_type);
+    builder.emit(TackyInstruction::AddPtr(ptr, index, scale, dst.clone()));
+
+    dst
+}
+
 fn emit_binary_op(
     op: BinaryOperator,
     left: Expression,
     right: Expression,
+    ty: Type,
     builder: &mut TackyBuilder,
 ) -> TackyValue {
     match op {
         // short-circuit AND
         BinaryOperator::And => {
-            let result = builder.fresh_temp("and_result");
+            let result = builder.fresh_temp("and_result", Type::Int);
             let false_label = builder.fresh_label("and_false");
             let end_label = builder.fresh_label("and_end");
 
@@ -300,11 +1114,11 @@ fn emit_binary_op(
             let v2 = emit_expr(right, builder);
             builder.emit_jump_if_zero(v2, false_label.clone());
 
-            builder.emit_copy(TackyValue::Constant(1), result.clone());
+            builder.emit_copy(TackyValue::Constant(Const::ConstInt(1)), result.clone());
             builder.emit_jump(end_label.clone());
 
             builder.emit_label(false_label);
-            builder.emit_copy(TackyValue::Constant(0), result.clone());
+            builder.emit_copy(TackyValue::Constant(Const::ConstInt(0)), result.clone());
 
             builder.emit_label(end_label);
 
@@ -312,7 +1126,7 @@ fn emit_binary_op(
         }
         // short-circuit OR
         BinaryOperator::Or => {
-            let result = builder.fresh_temp("or_result");
+            let result = builder.fresh_temp("or_result", Type::Int);
             let true_label = builder.fresh_label("or_true");
             let end_label = builder.fresh_label("or_end");
 
@@ -322,21 +1136,64 @@ fn emit_binary_op(
             let v2 = emit_expr(right, builder);
             builder.emit_jump_if_not_zero(v2, true_label.clone());
 
-            builder.emit_copy(TackyValue::Constant(0), result.clone());
+            builder.emit_copy(TackyValue::Constant(Const::ConstInt(0)), result.clone());
             builder.emit_jump(end_label.clone());
 
             builder.emit_label(true_label);
-            builder.emit_copy(TackyValue::Constant(1), result.clone());
+            builder.emit_copy(TackyValue::Constant(Const::ConstInt(1)), result.clone());
 
             builder.emit_label(end_label);
 
             result
         }
+        BinaryOperator::Add if ty.is_pointer() => emit_pointer_addition(left, right, ty, builder),
+        BinaryOperator::Subtract if ty.is_pointer() => {
+            // p - i is p + (-i)
+            let ptr = emit_expr(left, builder);
+            let index = emit_expr(right, builder);
+            let negated = builder.fresh_temp("neg_index", Type::Long);
+            builder.emit(TackyInstruction::Unary(
+                TackyUnaryOperator::Negate,
+                index,
+                negated.clone(),
+            ));
+            let dst = builder.fresh_temp("ptr_sub", ty.clone());
+            builder.emit(TackyInstruction::AddPtr(
+                ptr,
+                negated,
+                referenced_size(&ty, builder.symbols().types()),
+                dst.clone(),
+            ));
+
+            dst
+        }
+        BinaryOperator::Subtract if left.ty(builder.symbols()).is_pointer() => {
+            // the difference in bytes divided by the size of an element
+            let scale = referenced_size(&left.ty(builder.symbols()), builder.symbols().types());
+            let v1 = emit_expr(left, builder);
+            let v2 = emit_expr(right, builder);
+            let diff = builder.fresh_temp("ptr_diff", Type::Long);
+            builder.emit(TackyInstruction::Binary(
+                TackyBinaryOperator::Subtract,
+                v1,
+                v2,
+                diff.clone(),
+            ));
+            let dst = builder.fresh_temp("binop", ty);
+            builder.emit(TackyInstruction::Binary(
+                TackyBinaryOperator::Divide,
+                diff,
+                TackyValue::Constant(Const::ConstLong(scale as i64)),
+                dst.clone(),
+            ));
+
+            dst
+        }
         // regular binary operators
         _ => {
             let v1 = emit_expr(left, builder);
             let v2 = emit_expr(right, builder);
-            let dst = builder.fresh_temp("binop");
+            let dst = builder.fresh_temp("binop", ty);
 
             builder.emit(TackyInstruction::Binary(
                 TackyBinaryOperator::from(op),
@@ -386,4 +1243,3 @@ impl From<BinaryOperator> for TackyBinaryOperator {
         }
     }
 }
-
@@ -1,7 +1,13 @@
 use fcc::c_ast::ast::{
-    BinaryOperator, Block, BlockItem, Declaration, Expression, FunctionDefinition, Identifier,
-    Program, Statement, UnaryOperator,
+    BinaryOperator, Block, BlockItem, Const, Declaration, Expression, FunctionDeclaration,
+    Identifier, Initializer, Program, Statement, Type, UnaryOperator, VariableDeclaration,
 };
+use fcc::common::span::Span;
+
+// Helper: type of a function taking no parameters and returning int
+fn int_fun() -> Type {
+    Type::FunType(vec![], Box::new(Type::Int), false)
+}
 
 #[test]
 fn test_identifier_creation() {
@@ -15,18 +21,40 @@ fn test_identifier_creation() {
 #[test]
 fn test_program_creation() {
     let identifier = Identifier::new("main".to_string());
-    let function_def = FunctionDefinition::new(identifier, Block::new(vec![]));
-    let program = Program::new(function_def);
-
-    assert_eq!(program.function_definition().name().value(), "main");
+    let function_def = FunctionDeclaration::new(
+        identifier,
+        vec![],
+        Some(Block::new(vec![])),
+        int_fun(),
+        None,
+    );
+    let program = Program::new(vec![Declaration::FunDecl(function_def)]);
+
+    assert_eq!(
+        program
+            .function_definition("main")
+            .expect("main is defined")
+            .name()
+            .value(),
+        "main"
+    );
 }
 
 #[test]
 fn test_function_definition_creation() {
     let identifier = Identifier::new("test_func".to_string());
-    let return_stmt = Statement::Return(Expression::Constant(42));
+    let return_stmt = Statement::Return(
+        Expression::Constant(Const::ConstInt(42), Span::default()),
+        Span::default(),
+    );
     let block_item = BlockItem::S(return_stmt);
-    let function_def = FunctionDefinition::new(identifier, Block::new(vec![block_item]));
+    let function_def = FunctionDeclaration::new(
+        identifier,
+        vec![],
+        Some(Block::new(vec![block_item])),
+        int_fun(),
+        None,
+    );
 
     assert_eq!(function_def.name().value(), "test_func");
 }
@@ -34,8 +62,16 @@ fn test_function_definition_creation() {
 #[test]
 fn test_block_creation() {
     let items = vec![
-        BlockItem::S(Statement::Return(Expression::Constant(0))),
-        BlockItem::D(Declaration::new(Identifier::new("x".to_string()), None)),
+        BlockItem::S(Statement::Return(
+            Expression::Constant(Const::ConstInt(0), Span::default()),
+            Span::default(),
+        )),
+        BlockItem::D(Declaration::VarDecl(VariableDeclaration::new(
+            Identifier::new("x".to_string()),
+            None,
+            Type::Int,
+            None,
+        ))),
     ];
     let block = Block::new(items);
 
@@ -47,7 +83,7 @@ fn test_block_creation() {
 #[test]
 fn test_declaration_without_initializer() {
     let name = Identifier::new("x".to_string());
-    let declaration = Declaration::new(name, None);
+    let declaration = VariableDeclaration::new(name, None, Type::Int, None);
 
     assert_eq!(declaration.name().value(), "x");
     assert!(declaration.initializer().is_none());
@@ -56,13 +92,16 @@ fn test_declaration_without_initializer() {
 #[test]
 fn test_declaration_with_initializer() {
     let name = Identifier::new("y".to_string());
-    let init_expr = Expression::Constant(10);
-    let declaration = Declaration::new(name, Some(init_expr));
+    let init_expr = Expression::Constant(Const::ConstInt(10), Span::default());
+    let declaration =
+        VariableDeclaration::new(name, Some(Initializer::Single(init_expr)), Type::Int, None);
 
     assert_eq!(declaration.name().value(), "y");
     assert!(declaration.initializer().is_some());
 
-    if let Some(Expression::Constant(value)) = declaration.initializer() {
+    if let Some(Initializer::Single(Expression::Constant(Const::ConstInt(value), _))) =
+        declaration.initializer()
+    {
         assert_eq!(*value, 10);
     } else {
         panic!("Expected constant expression");
@@ -71,9 +110,9 @@ fn test_declaration_with_initializer() {
 
 #[test]
 fn test_expression_constant() {
-    let expr = Expression::Constant(123);
+    let expr = Expression::Constant(Const::ConstInt(123), Span::default());
 
-    if let Expression::Constant(value) = expr {
+    if let Expression::Constant(Const::ConstInt(value), _) = expr {
         assert_eq!(value, 123);
     } else {
         panic!("Expected constant expression");
@@ -83,9 +122,9 @@ fn test_expression_constant() {
 #[test]
 fn test_expression_variable() {
     let id = Identifier::new("var".to_string());
-    let expr = Expression::Var(id);
+    let expr = Expression::Var(id, Span::default());
 
-    if let Expression::Var(identifier) = expr {
+    if let Expression::Var(identifier, _) = expr {
         assert_eq!(identifier.value(), "var");
     } else {
         panic!("Expected variable expression");
@@ -94,12 +133,13 @@ fn test_expression_variable() {
 
 #[test]
 fn test_unary_expression() {
-    let inner_expr = Expression::Constant(5);
-    let unary_expr = Expression::Unary(UnaryOperator::Negate, Box::new(inner_expr));
+    let inner_expr = Expression::Constant(Const::ConstInt(5), Span::default());
+    let unary_expr =
+        Expression::Unary(UnaryOperator::Negate, Box::new(inner_expr), Span::default());
 
-    if let Expression::Unary(op, expr) = unary_expr {
+    if let Expression::Unary(op, expr, _) = unary_expr {
         assert!(matches!(op, UnaryOperator::Negate));
-        if let Expression::Constant(value) = *expr {
+        if let Expression::Constant(Const::ConstInt(value), _) = *expr {
             assert_eq!(value, 5);
         } else {
             panic!("Expected constant expression inside unary");
@@ -111,20 +151,25 @@ fn test_unary_expression() {
 
 #[test]
 fn test_binary_expression() {
-    let left = Expression::Constant(10);
-    let right = Expression::Constant(20);
-    let binary_expr = Expression::Binary(BinaryOperator::Add, Box::new(left), Box::new(right));
+    let left = Expression::Constant(Const::ConstInt(10), Span::default());
+    let right = Expression::Constant(Const::ConstInt(20), Span::default());
+    let binary_expr = Expression::Binary(
+        BinaryOperator::Add,
+        Box::new(left),
+        Box::new(right),
+        Span::default(),
+    );
 
-    if let Expression::Binary(op, left_expr, right_expr) = binary_expr {
+    if let Expression::Binary(op, left_expr, right_expr, _) = binary_expr {
         assert!(matches!(op, BinaryOperator::Add));
 
-        if let Expression::Constant(left_val) = *left_expr {
+        if let Expression::Constant(Const::ConstInt(left_val), _) = *left_expr {
             assert_eq!(left_val, 10);
         } else {
             panic!("Expected constant in left operand");
         }
 
-        if let Expression::Constant(right_val) = *right_expr {
+        if let Expression::Constant(Const::ConstInt(right_val), _) = *right_expr {
             assert_eq!(right_val, 20);
         } else {
             panic!("Expected constant in right operand");
@@ -136,18 +181,19 @@ fn test_binary_expression() {
 
 #[test]
 fn test_assignment_expression() {
-    let var_expr = Expression::Var(Identifier::new("x".to_string()));
-    let value_expr = Expression::Constant(42);
-    let assign_expr = Expression::Assignment(Box::new(var_expr), Box::new(value_expr));
+    let var_expr = Expression::Var(Identifier::new("x".to_string()), Span::default());
+    let value_expr = Expression::Constant(Const::ConstInt(42), Span::default());
+    let assign_expr =
+        Expression::Assignment(Box::new(var_expr), Box::new(value_expr), Span::default());
 
-    if let Expression::Assignment(left, right) = assign_expr {
-        if let Expression::Var(id) = *left {
+    if let Expression::Assignment(left, right, _) = assign_expr {
+        if let Expression::Var(id, _) = *left {
             assert_eq!(id.value(), "x");
         } else {
             panic!("Expected variable on left side of assignment");
         }
 
-        if let Expression::Constant(value) = *right {
+        if let Expression::Constant(Const::ConstInt(value), _) = *right {
             assert_eq!(value, 42);
         } else {
             panic!("Expected constant on right side of assignment");
@@ -159,13 +205,13 @@ fn test_assignment_expression() {
 
 #[test]
 fn test_nested_unary_expressions() {
-    let inner = Expression::Constant(5);
-    let neg = Expression::Unary(UnaryOperator::Negate, Box::new(inner));
-    let not = Expression::Unary(UnaryOperator::Not, Box::new(neg));
+    let inner = Expression::Constant(Const::ConstInt(5), Span::default());
+    let neg = Expression::Unary(UnaryOperator::Negate, Box::new(inner), Span::default());
+    let not = Expression::Unary(UnaryOperator::Not, Box::new(neg), Span::default());
 
-    if let Expression::Unary(UnaryOperator::Not, inner_expr) = not {
-        if let Expression::Unary(UnaryOperator::Negate, innermost) = *inner_expr {
-            if let Expression::Constant(value) = *innermost {
+    if let Expression::Unary(UnaryOperator::Not, inner_expr, _) = not {
+        if let Expression::Unary(UnaryOperator::Negate, innermost, _) = *inner_expr {
+            if let Expression::Constant(Const::ConstInt(value), _) = *innermost {
                 assert_eq!(value, 5);
             } else {
                 panic!("Expected constant at innermost level");
@@ -181,21 +227,31 @@ fn test_nested_unary_expressions() {
 #[test]
 fn test_complex_binary_expression() {
     // (a + b) * c
-    let a = Expression::Var(Identifier::new("a".to_string()));
-    let b = Expression::Var(Identifier::new("b".to_string()));
-    let c = Expression::Var(Identifier::new("c".to_string()));
+    let a = Expression::Var(Identifier::new("a".to_string()), Span::default());
+    let b = Expression::Var(Identifier::new("b".to_string()), Span::default());
+    let c = Expression::Var(Identifier::new("c".to_string()), Span::default());
 
-    let add_expr = Expression::Binary(BinaryOperator::Add, Box::new(a), Box::new(b));
-    let mult_expr = Expression::Binary(BinaryOperator::Multiply, Box::new(add_expr), Box::new(c));
+    let add_expr = Expression::Binary(
+        BinaryOperator::Add,
+        Box::new(a),
+        Box::new(b),
+        Span::default(),
+    );
+    let mult_expr = Expression::Binary(
+        BinaryOperator::Multiply,
+        Box::new(add_expr),
+        Box::new(c),
+        Span::default(),
+    );
 
-    if let Expression::Binary(BinaryOperator::Multiply, left, right) = mult_expr {
-        if let Expression::Binary(BinaryOperator::Add, _, _) = *left {
+    if let Expression::Binary(BinaryOperator::Multiply, left, right, _) = mult_expr {
+        if let Expression::Binary(BinaryOperator::Add, _, _, _) = *left {
             // Expected structure
         } else {
             panic!("Expected addition on left side of multiplication");
         }
 
-        if let Expression::Var(id) = *right {
+        if let Expression::Var(id, _) = *right {
             assert_eq!(id.value(), "c");
         } else {
             panic!("Expected variable 'c' on right side");
@@ -207,27 +263,35 @@ fn test_complex_binary_expression() {
 
 #[test]
 fn test_all_unary_operators() {
-    let expr = Expression::Constant(1);
-
-    let complement = Expression::Unary(UnaryOperator::Complement, Box::new(expr.clone()));
-    let negate = Expression::Unary(UnaryOperator::Negate, Box::new(expr.clone()));
-    let not = Expression::Unary(UnaryOperator::Not, Box::new(expr));
+    let expr = Expression::Constant(Const::ConstInt(1), Span::default());
+
+    let complement = Expression::Unary(
+        UnaryOperator::Complement,
+        Box::new(expr.clone()),
+        Span::default(),
+    );
+    let negate = Expression::Unary(
+        UnaryOperator::Negate,
+        Box::new(expr.clone()),
+        Span::default(),
+    );
+    let not = Expression::Unary(UnaryOperator::Not, Box::new(expr), Span::default());
 
     assert!(matches!(
         complement,
-        Expression::Unary(UnaryOperator::Complement, _)
+        Expression::Unary(UnaryOperator::Complement, _, _)
     ));
     assert!(matches!(
         negate,
-        Expression::Unary(UnaryOperator::Negate, _)
+        Expression::Unary(UnaryOperator::Negate, _, _)
     ));
-    assert!(matches!(not, Expression::Unary(UnaryOperator::Not, _)));
+    assert!(matches!(not, Expression::Unary(UnaryOperator::Not, _, _)));
 }
 
 #[test]
 fn test_all_binary_operators() {
-    let left = Expression::Constant(1);
-    let right = Expression::Constant(2);
+    let left = Expression::Constant(Const::ConstInt(1), Span::default());
+    let right = Expression::Constant(Const::ConstInt(2), Span::default());
 
     let operators = vec![
         BinaryOperator::Add,
@@ -251,31 +315,45 @@ fn test_all_binary_operators() {
     ];
 
     for op in operators {
-        let expr = Expression::Binary(op, Box::new(left.clone()), Box::new(right.clone()));
-        assert!(matches!(expr, Expression::Binary(_, _, _)));
+        let expr = Expression::Binary(
+            op,
+            Box::new(left.clone()),
+            Box::new(right.clone()),
+            Span::default(),
+        );
+        assert!(matches!(expr, Expression::Binary(_, _, _, _)));
     }
 }
 
 #[test]
 fn test_statement_types() {
-    let return_stmt = Statement::Return(Expression::Constant(0));
-    let expr_stmt = Statement::Expression(Expression::Constant(42));
+    let return_stmt = Statement::Return(
+        Expression::Constant(Const::ConstInt(0), Span::default()),
+        Span::default(),
+    );
+    let expr_stmt = Statement::Expression(
+        Expression::Constant(Const::ConstInt(42), Span::default()),
+        Span::default(),
+    );
     let null_stmt = Statement::Null;
-    let compound_stmt = Statement::Compound(Box::new(Block::new(vec![])));
+    let compound_stmt = Statement::Compound(Box::new(Block::new(vec![])), Span::default());
 
-    assert!(matches!(return_stmt, Statement::Return(_)));
-    assert!(matches!(expr_stmt, Statement::Expression(_)));
-    assert!(matches!(null_stmt, Statement::Null));
-    assert!(matches!(compound_stmt, Statement::Compound(_)));
+    assert!(matches!(return_stmt, Statement::Return(_, _)));
+    assert!(matches!(expr_stmt, Statement::Expression(_, _)));
+    assert!(matches!(null_stmt(Span::default()), Statement::Null(_)));
+    assert!(matches!(compound_stmt, Statement::Compound(_, _)));
 }
 
 #[test]
 fn test_block_item_types() {
-    let stmt = Statement::Return(Expression::Constant(0));
-    let decl = Declaration::new(Identifier::new("x".to_string()), None);
+    let stmt = Statement::Return(
+        Expression::Constant(Const::ConstInt(0), Span::default()),
+        Span::default(),
+    );
+    let decl = VariableDeclaration::new(Identifier::new("x".to_string()), None, Type::Int, None);
 
     let stmt_block = BlockItem::S(stmt);
-    let decl_block = BlockItem::D(decl);
+    let decl_block = BlockItem::D(Declaration::VarDecl(decl));
 
     assert!(matches!(stmt_block, BlockItem::S(_)));
     assert!(matches!(decl_block, BlockItem::D(_)));
@@ -283,15 +361,16 @@ fn test_block_item_types() {
 
 #[test]
 fn test_compound_statement() {
-    let inner_block = Block::new(vec![BlockItem::S(Statement::Return(Expression::Constant(
-        1,
-    )))]);
-    let compound = Statement::Compound(Box::new(inner_block));
+    let inner_block = Block::new(vec![BlockItem::S(Statement::Return(
+        Expression::Constant(Const::ConstInt(1), Span::default()),
+        Span::default(),
+    ))]);
+    let compound = Statement::Compound(Box::new(inner_block), Span::default());
 
-    if let Statement::Compound(block) = compound {
+    if let Statement::Compound(block, _) = compound {
         let items: Vec<_> = block.block_items().clone().into_iter().collect();
         assert_eq!(items.len(), 1);
-        assert!(matches!(items[0], BlockItem::S(Statement::Return(_))));
+        assert!(matches!(items[0], BlockItem::S(Statement::Return(_, _))));
     } else {
         panic!("Expected compound statement");
     }
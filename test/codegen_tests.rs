@@ -1,16 +1,23 @@
 /*!
 This file covers: Lowering Tacky IR to x64 ASM (codegen/x64/from.rs).
-Tests instruction conversion, operator mapping, condition codes.
+Tests instruction conversion, operator mapping, condition codes, calling convention.
 Does NOT cover: assembly string emission (emit.rs), fixers (already in folder_tests).
 Suggestions: add tests for edge cases in register allocation.
 */
 
-use fcc::codegen::x64::ast::{
-    AsmBinaryOperator, AsmCondCode, AsmInstruction, AsmOperand, AsmProgram,
+use fcc::c_ast::ast::{Const, Type};
+use fcc::c_ast::semantic::type_table::{StructEntry, VA_LIST_TAG};
+use fcc::codegen::x64::{
+    ast::{
+        AsmBinaryOperator, AsmCondCode, AsmIdentifier, AsmInstruction, AsmOperand, AsmProgram,
+        AsmType, Reg,
+    },
+    from::REG_SAVE_AREA,
 };
+use fcc::common::symbols::{IdentifierAttrs, StaticInit, Symbol, SymbolTable};
 use fcc::tacky::ast::{
     TackyBinaryOperator, TackyFunctionDefinition, TackyIdentifier, TackyInstruction, TackyProgram,
-    TackyUnaryOperator, TackyValue,
+    TackyStaticConstant, TackyStaticVariable, TackyUnaryOperator, TackyValue,
 };
 
 // Helper: convert a Tacky program to ASM
@@ -20,12 +27,17 @@ fn lower_to_asm(tacky: TackyProgram) -> AsmProgram {
 
 // Helper: create a minimal Tacky program with given instructions
 fn make_tacky_program(instructions: Vec<TackyInstruction>) -> TackyProgram {
-    TackyProgram::new(TackyFunctionDefinition::new(
-        TackyIdentifier {
-            value: "main".to_string(),
-        },
-        instructions,
-    ))
+    TackyProgram::new(
+        vec![TackyFunctionDefinition::new(
+            TackyIdentifier {
+                value: "main".to_string(),
+            },
+            true,
+            vec![],
+            instructions,
+        )],
+        vec![],
+    )
 }
 
 // Helper: check if ASM instructions contain Ret
@@ -39,28 +51,28 @@ fn has_ret(instructions: &[AsmInstruction]) -> bool {
 fn has_mov(instructions: &[AsmInstruction]) -> bool {
     instructions
         .iter()
-        .any(|i| matches!(i, AsmInstruction::Mov(_, _)))
+        .any(|i| matches!(i, AsmInstruction::Mov(_, _, _)))
 }
 
 // Helper: check if ASM instructions contain Cdq
 fn has_cdq(instructions: &[AsmInstruction]) -> bool {
     instructions
         .iter()
-        .any(|i| matches!(i, AsmInstruction::Cdq))
+        .any(|i| matches!(i, AsmInstruction::Cdq(_)))
 }
 
 // Helper: check if ASM instructions contain Idiv
 fn has_idiv(instructions: &[AsmInstruction]) -> bool {
     instructions
         .iter()
-        .any(|i| matches!(i, AsmInstruction::Idiv(_)))
+        .any(|i| matches!(i, AsmInstruction::Idiv(_, _)))
 }
 
 // Helper: check if ASM instructions contain Cmp
 fn has_cmp(instructions: &[AsmInstruction]) -> bool {
     instructions
         .iter()
-        .any(|i| matches!(i, AsmInstruction::Cmp(_, _)))
+        .any(|i| matches!(i, AsmInstruction::Cmp(_, _, _)))
 }
 
 // Helper: check if ASM instructions contain SetCC with specific code
@@ -94,7 +106,7 @@ fn has_label(instructions: &[AsmInstruction]) -> bool {
 // Helper: check if ASM instructions contain Binary with specific operator
 fn has_binary_op(instructions: &[AsmInstruction], op: AsmBinaryOperator) -> bool {
     instructions.iter().any(|i| {
-        matches!(i, AsmInstruction::Binary(o, _, _) if std::mem::discriminant(o) == std::mem::discriminant(&op))
+        matches!(i, AsmInstruction::Binary(o, _, _, _) if std::mem::discriminant(o) == std::mem::discriminant(&op))
     })
 }
 
@@ -102,7 +114,20 @@ fn has_binary_op(instructions: &[AsmInstruction], op: AsmBinaryOperator) -> bool
 fn has_unary(instructions: &[AsmInstruction]) -> bool {
     instructions
         .iter()
-        .any(|i| matches!(i, AsmInstruction::Unary(_, _)))
+        .any(|i| matches!(i, AsmInstruction::Unary(_, _, _)))
+}
+
+// Helper: build a FunCall to `name` with the given arguments
+fn make_fun_call(name: &str, args: Vec<TackyValue>) -> TackyInstruction {
+    TackyInstruction::FunCall(
+        TackyIdentifier {
+            value: name.to_string(),
+        },
+        args,
+        TackyValue::Var(TackyIdentifier {
+            value: "result".to_string(),
+        }),
+    )
 }
 
 // =============================================================================
@@ -111,10 +136,12 @@ fn has_unary(instructions: &[AsmInstruction]) -> bool {
 
 #[test]
 fn test_codegen_return_constant() {
-    let tacky = make_tacky_program(vec![TackyInstruction::Return(TackyValue::Constant(0))]);
+    let tacky = make_tacky_program(vec![TackyInstruction::Return(TackyValue::Constant(
+        Const::ConstInt(0),
+    ))]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_mov(instructions), "Return should generate Mov to AX");
     assert!(has_ret(instructions), "Return should generate Ret");
@@ -129,7 +156,7 @@ fn test_codegen_return_variable() {
     ))]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_mov(instructions), "Return var should generate Mov");
     assert!(has_ret(instructions), "Return should generate Ret");
@@ -144,16 +171,16 @@ fn test_codegen_unary_negate() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Unary(
             TackyUnaryOperator::Negate,
-            TackyValue::Constant(5),
+            TackyValue::Constant(Const::ConstInt(5)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_mov(instructions), "Unary should generate Mov");
     assert!(
@@ -167,16 +194,16 @@ fn test_codegen_unary_complement() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Unary(
             TackyUnaryOperator::Complement,
-            TackyValue::Constant(5),
+            TackyValue::Constant(Const::ConstInt(5)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(
         has_unary(instructions),
@@ -190,16 +217,16 @@ fn test_codegen_unary_not_special_case() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Unary(
             TackyUnaryOperator::Not,
-            TackyValue::Constant(5),
+            TackyValue::Constant(Const::ConstInt(5)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     // Not should use Cmp + SetCC pattern
     assert!(has_cmp(instructions), "Not should use Cmp");
@@ -218,17 +245,17 @@ fn test_codegen_binary_add() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::Add,
-            TackyValue::Constant(1),
-            TackyValue::Constant(2),
+            TackyValue::Constant(Const::ConstInt(1)),
+            TackyValue::Constant(Const::ConstInt(2)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_mov(instructions), "Binary should generate Mov");
     assert!(
@@ -242,17 +269,17 @@ fn test_codegen_binary_subtract() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::Subtract,
-            TackyValue::Constant(5),
-            TackyValue::Constant(3),
+            TackyValue::Constant(Const::ConstInt(5)),
+            TackyValue::Constant(Const::ConstInt(3)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(
         has_binary_op(instructions, AsmBinaryOperator::Sub),
@@ -265,17 +292,17 @@ fn test_codegen_binary_multiply() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::Multiply,
-            TackyValue::Constant(2),
-            TackyValue::Constant(3),
+            TackyValue::Constant(Const::ConstInt(2)),
+            TackyValue::Constant(Const::ConstInt(3)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(
         has_binary_op(instructions, AsmBinaryOperator::Mult),
@@ -292,17 +319,17 @@ fn test_codegen_binary_divide() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::Divide,
-            TackyValue::Constant(6),
-            TackyValue::Constant(2),
+            TackyValue::Constant(Const::ConstInt(6)),
+            TackyValue::Constant(Const::ConstInt(2)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     // Division uses Cdq + Idiv pattern
     assert!(has_cdq(instructions), "Division should use Cdq");
@@ -314,17 +341,17 @@ fn test_codegen_binary_remainder() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::Remainder,
-            TackyValue::Constant(7),
-            TackyValue::Constant(3),
+            TackyValue::Constant(Const::ConstInt(7)),
+            TackyValue::Constant(Const::ConstInt(3)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     // Remainder uses same pattern as division
     assert!(has_cdq(instructions), "Remainder should use Cdq");
@@ -358,17 +385,17 @@ fn test_codegen_bitwise_operators() {
         let tacky = make_tacky_program(vec![
             TackyInstruction::Binary(
                 tacky_op.clone(),
-                TackyValue::Constant(1),
-                TackyValue::Constant(2),
+                TackyValue::Constant(Const::ConstInt(1)),
+                TackyValue::Constant(Const::ConstInt(2)),
                 TackyValue::Var(TackyIdentifier {
                     value: "dst".to_string(),
                 }),
             ),
-            TackyInstruction::Return(TackyValue::Constant(0)),
+            TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
         ]);
 
         let asm = lower_to_asm(tacky);
-        let instructions = &asm.function_definition.instructions;
+        let instructions = &asm.function_definitions[0].instructions;
 
         assert!(
             has_binary_op(instructions, expected_asm_op.clone()),
@@ -388,17 +415,17 @@ fn test_codegen_relational_equal() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::Equal,
-            TackyValue::Constant(1),
-            TackyValue::Constant(1),
+            TackyValue::Constant(Const::ConstInt(1)),
+            TackyValue::Constant(Const::ConstInt(1)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_cmp(instructions), "Equal should use Cmp");
     assert!(
@@ -412,17 +439,17 @@ fn test_codegen_relational_not_equal() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::NotEqual,
-            TackyValue::Constant(1),
-            TackyValue::Constant(2),
+            TackyValue::Constant(Const::ConstInt(1)),
+            TackyValue::Constant(Const::ConstInt(2)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_cmp(instructions), "NotEqual should use Cmp");
     assert!(
@@ -436,17 +463,17 @@ fn test_codegen_relational_less_than() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::LessThan,
-            TackyValue::Constant(1),
-            TackyValue::Constant(2),
+            TackyValue::Constant(Const::ConstInt(1)),
+            TackyValue::Constant(Const::ConstInt(2)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_cmp(instructions), "LessThan should use Cmp");
     assert!(
@@ -460,17 +487,17 @@ fn test_codegen_relational_less_than_or_equal() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::LessThanOrEqual,
-            TackyValue::Constant(1),
-            TackyValue::Constant(2),
+            TackyValue::Constant(Const::ConstInt(1)),
+            TackyValue::Constant(Const::ConstInt(2)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_cmp(instructions), "LessThanOrEqual should use Cmp");
     assert!(
@@ -484,17 +511,17 @@ fn test_codegen_relational_greater_than() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::GreaterThan,
-            TackyValue::Constant(2),
-            TackyValue::Constant(1),
+            TackyValue::Constant(Const::ConstInt(2)),
+            TackyValue::Constant(Const::ConstInt(1)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_cmp(instructions), "GreaterThan should use Cmp");
     assert!(
@@ -508,17 +535,17 @@ fn test_codegen_relational_greater_than_or_equal() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Binary(
             TackyBinaryOperator::GreaterThanOrEqual,
-            TackyValue::Constant(2),
-            TackyValue::Constant(1),
+            TackyValue::Constant(Const::ConstInt(2)),
+            TackyValue::Constant(Const::ConstInt(1)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_cmp(instructions), "GreaterThanOrEqual should use Cmp");
     assert!(
@@ -540,11 +567,11 @@ fn test_codegen_jump() {
         TackyInstruction::Label(TackyIdentifier {
             value: "label1".to_string(),
         }),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_jmp(instructions), "Jump should generate Jmp");
     assert!(has_label(instructions), "Label should generate Label");
@@ -554,7 +581,7 @@ fn test_codegen_jump() {
 fn test_codegen_jump_if_zero() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::JumpIfZero(
-            TackyValue::Constant(0),
+            TackyValue::Constant(Const::ConstInt(0)),
             TackyIdentifier {
                 value: "zero_label".to_string(),
             },
@@ -562,11 +589,11 @@ fn test_codegen_jump_if_zero() {
         TackyInstruction::Label(TackyIdentifier {
             value: "zero_label".to_string(),
         }),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_cmp(instructions), "JumpIfZero should use Cmp");
     assert!(has_jmpcc(instructions), "JumpIfZero should generate JmpCC");
@@ -576,7 +603,7 @@ fn test_codegen_jump_if_zero() {
 fn test_codegen_jump_if_not_zero() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::JumpIfNotZero(
-            TackyValue::Constant(1),
+            TackyValue::Constant(Const::ConstInt(1)),
             TackyIdentifier {
                 value: "nonzero_label".to_string(),
             },
@@ -584,11 +611,11 @@ fn test_codegen_jump_if_not_zero() {
         TackyInstruction::Label(TackyIdentifier {
             value: "nonzero_label".to_string(),
         }),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_cmp(instructions), "JumpIfNotZero should use Cmp");
     assert!(
@@ -605,16 +632,16 @@ fn test_codegen_jump_if_not_zero() {
 fn test_codegen_copy() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Copy(
-            TackyValue::Constant(42),
+            TackyValue::Constant(Const::ConstInt(42)),
             TackyValue::Var(TackyIdentifier {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_mov(instructions), "Copy should generate Mov");
 }
@@ -630,11 +657,11 @@ fn test_codegen_copy_var_to_var() {
                 value: "dst".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     assert!(has_mov(instructions), "Copy var to var should generate Mov");
 }
@@ -645,15 +672,17 @@ fn test_codegen_copy_var_to_var() {
 
 #[test]
 fn test_codegen_operand_immediate() {
-    let tacky = make_tacky_program(vec![TackyInstruction::Return(TackyValue::Constant(42))]);
+    let tacky = make_tacky_program(vec![TackyInstruction::Return(TackyValue::Constant(
+        Const::ConstInt(42),
+    ))]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     // Check that immediate operand is generated
     let has_imm = instructions
         .iter()
-        .any(|i| matches!(i, AsmInstruction::Mov(AsmOperand::Imm(42), _)));
+        .any(|i| matches!(i, AsmInstruction::Mov(_, AsmOperand::Imm(42), _)));
     assert!(has_imm, "Constant should become Imm operand");
 }
 
@@ -661,20 +690,1288 @@ fn test_codegen_operand_immediate() {
 fn test_codegen_operand_pseudo_register() {
     let tacky = make_tacky_program(vec![
         TackyInstruction::Copy(
-            TackyValue::Constant(1),
+            TackyValue::Constant(Const::ConstInt(1)),
             TackyValue::Var(TackyIdentifier {
                 value: "my_var".to_string(),
             }),
         ),
-        TackyInstruction::Return(TackyValue::Constant(0)),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
     ]);
 
     let asm = lower_to_asm(tacky);
-    let instructions = &asm.function_definition.instructions;
+    let instructions = &asm.function_definitions[0].instructions;
 
     // Check that pseudo register is generated
     let has_pseudo = instructions
         .iter()
-        .any(|i| matches!(i, AsmInstruction::Mov(_, AsmOperand::Pseudo(_))));
+        .any(|i| matches!(i, AsmInstruction::Mov(_, _, AsmOperand::Pseudo(_))));
     assert!(has_pseudo, "Var should become Pseudo operand");
 }
+
+// =============================================================================
+// FUNCTION CALLS
+// =============================================================================
+
+#[test]
+fn test_codegen_call_register_args() {
+    let tacky = make_tacky_program(vec![
+        make_fun_call(
+            "foo",
+            vec![
+                TackyValue::Constant(Const::ConstInt(1)),
+                TackyValue::Constant(Const::ConstInt(2)),
+            ],
+        ),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
+    ]);
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(matches!(
+        instructions[0],
+        AsmInstruction::Mov(_, AsmOperand::Imm(1), AsmOperand::Register(Reg::DI))
+    ));
+    assert!(matches!(
+        instructions[1],
+        AsmInstruction::Mov(_, AsmOperand::Imm(2), AsmOperand::Register(Reg::SI))
+    ));
+    assert!(matches!(&instructions[2], AsmInstruction::Call(id) if id.value == "foo"));
+    // The result is read from AX, no stack cleanup is needed
+    assert!(matches!(
+        instructions[3],
+        AsmInstruction::Mov(_, AsmOperand::Register(Reg::AX), AsmOperand::Pseudo(_))
+    ));
+}
+
+#[test]
+fn test_codegen_call_stack_args() {
+    let args = (1..=7)
+        .map(|i| TackyValue::Constant(Const::ConstInt(i)))
+        .collect();
+    let tacky = make_tacky_program(vec![
+        make_fun_call("foo", args),
+        TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(0))),
+    ]);
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    // One stack argument needs 8 bytes of padding to keep RSP aligned
+    assert!(matches!(instructions[0], AsmInstruction::AllocateStack(8)));
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, AsmInstruction::Push(AsmOperand::Imm(7))))
+    );
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, AsmInstruction::DeallocateStack(16)))
+    );
+}
+
+#[test]
+fn test_codegen_params_copied_from_registers_and_stack() {
+    let params = (0..7)
+        .map(|i| TackyIdentifier {
+            value: format!("p{i}"),
+        })
+        .collect();
+    let tacky = TackyProgram::new(
+        vec![TackyFunctionDefinition::new(
+            TackyIdentifier {
+                value: "foo".to_string(),
+            },
+            true,
+            params,
+            vec![TackyInstruction::Return(TackyValue::Constant(
+                Const::ConstInt(0),
+            ))],
+        )],
+        vec![],
+    );
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(matches!(
+        instructions[0],
+        AsmInstruction::Mov(_, AsmOperand::Register(Reg::DI), AsmOperand::Pseudo(_))
+    ));
+    assert!(matches!(
+        instructions[5],
+        AsmInstruction::Mov(_, AsmOperand::Register(Reg::R9), AsmOperand::Pseudo(_))
+    ));
+    // The seventh parameter lives above the return address
+    assert!(matches!(
+        instructions[6],
+        AsmInstruction::Mov(_, AsmOperand::Stack(16), AsmOperand::Pseudo(_))
+    ));
+}
+
+// =============================================================================
+// LONG INTEGERS
+// =============================================================================
+
+// Helper: symbol table with the given local variables and their types
+fn make_symbols(vars: &[(&str, Type)]) -> SymbolTable {
+    let mut symbols = SymbolTable::new();
+    for (name, ty) in vars {
+        symbols.insert(name, Symbol::new(ty.clone(), IdentifierAttrs::LocalAttr));
+    }
+    symbols
+}
+
+// Helper: create a Tacky variable
+fn var(name: &str) -> TackyValue {
+    TackyValue::Var(TackyIdentifier {
+        value: name.to_string(),
+    })
+}
+
+#[test]
+fn test_codegen_long_copy_is_quadword() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::Copy(TackyValue::Constant(Const::ConstLong(1)), var("l")),
+        TackyInstruction::Copy(TackyValue::Constant(Const::ConstInt(1)), var("i")),
+    ])
+    .with_symbols(make_symbols(&[("l", Type::Long), ("i", Type::Int)]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(matches!(
+        instructions[0],
+        AsmInstruction::Mov(AsmType::Quadword, AsmOperand::Imm(1), _)
+    ));
+    assert!(matches!(
+        instructions[1],
+        AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(1), _)
+    ));
+}
+
+#[test]
+fn test_codegen_sign_extend_and_truncate() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::SignExtend(var("i"), var("l")),
+        TackyInstruction::Truncate(var("l"), var("i")),
+    ])
+    .with_symbols(make_symbols(&[("l", Type::Long), ("i", Type::Int)]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(matches!(
+        instructions[0],
+        AsmInstruction::Movsx(AsmType::Longword, AsmType::Quadword, _, _)
+    ));
+    // truncating only keeps the low 4 bytes
+    assert!(matches!(
+        instructions[1],
+        AsmInstruction::Mov(AsmType::Longword, _, _)
+    ));
+}
+
+#[test]
+fn test_codegen_long_division() {
+    let tacky = make_tacky_program(vec![TackyInstruction::Binary(
+        TackyBinaryOperator::Divide,
+        var("a"),
+        var("b"),
+        var("c"),
+    )])
+    .with_symbols(make_symbols(&[
+        ("a", Type::Long),
+        ("b", Type::Long),
+        ("c", Type::Long),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(matches!(
+        instructions[1],
+        AsmInstruction::Cdq(AsmType::Quadword)
+    ));
+    assert!(matches!(
+        instructions[2],
+        AsmInstruction::Idiv(AsmType::Quadword, _)
+    ));
+}
+
+#[test]
+fn test_codegen_long_static_variable_alignment() {
+    let tacky = TackyProgram::new(
+        vec![],
+        vec![
+            TackyStaticVariable::new(TackyIdentifier::new("i"), true, StaticInit::IntInit(1)),
+            TackyStaticVariable::new(TackyIdentifier::new("l"), true, StaticInit::LongInit(1)),
+        ],
+    );
+
+    let asm = lower_to_asm(tacky);
+
+    assert_eq!(asm.static_variables[0].alignment, 4);
+    assert_eq!(asm.static_variables[1].alignment, 8);
+}
+
+// =============================================================================
+// UNSIGNED INTEGERS
+// =============================================================================
+
+#[test]
+fn test_codegen_unsigned_division() {
+    let tacky = make_tacky_program(vec![TackyInstruction::Binary(
+        TackyBinaryOperator::Remainder,
+        var("a"),
+        var("b"),
+        var("c"),
+    )])
+    .with_symbols(make_symbols(&[
+        ("a", Type::UInt),
+        ("b", Type::UInt),
+        ("c", Type::UInt),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    // DX is zeroed instead of sign extended
+    assert!(matches!(
+        instructions[1],
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(0),
+            AsmOperand::Register(Reg::DX)
+        )
+    ));
+    assert!(matches!(
+        instructions[2],
+        AsmInstruction::Div(AsmType::Longword, _)
+    ));
+    assert!(
+        !instructions
+            .iter()
+            .any(|i| matches!(i, AsmInstruction::Cdq(_)))
+    );
+}
+
+#[test]
+fn test_codegen_unsigned_comparison_and_shift() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::Binary(TackyBinaryOperator::LessThan, var("a"), var("b"), var("c")),
+        TackyInstruction::Binary(
+            TackyBinaryOperator::RightShift,
+            var("a"),
+            var("b"),
+            var("d"),
+        ),
+    ])
+    .with_symbols(make_symbols(&[
+        ("a", Type::ULong),
+        ("b", Type::ULong),
+        ("c", Type::Int),
+        ("d", Type::ULong),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, AsmInstruction::SetCC(AsmCondCode::B, _)))
+    );
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Binary(
+            AsmBinaryOperator::LogicalRightShift,
+            AsmType::Quadword,
+            _,
+            _
+        )
+    )));
+}
+
+#[test]
+fn test_codegen_unsigned_constants_keep_their_bits() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::Copy(TackyValue::Constant(Const::ConstUInt(u32::MAX)), var("u")),
+        TackyInstruction::Copy(TackyValue::Constant(Const::ConstULong(u64::MAX)), var("ul")),
+    ])
+    .with_symbols(make_symbols(&[("u", Type::UInt), ("ul", Type::ULong)]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(matches!(
+        instructions[0],
+        AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(-1), _)
+    ));
+    assert!(matches!(
+        instructions[1],
+        AsmInstruction::Mov(AsmType::Quadword, AsmOperand::Imm(-1), _)
+    ));
+}
+
+// =============================================================================
+// FLOATING-POINT NUMBERS
+// =============================================================================
+
+#[test]
+fn test_codegen_double_return_uses_xmm0() {
+    let tacky = make_tacky_program(vec![TackyInstruction::Return(TackyValue::Constant(
+        Const::ConstDouble(1.5),
+    ))]);
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert_eq!(
+        instructions[0],
+        AsmInstruction::Mov(
+            AsmType::Double,
+            AsmOperand::DoubleConstant(1.5f64.to_bits()),
+            AsmOperand::Register(Reg::XMM0)
+        )
+    );
+}
+
+#[test]
+fn test_codegen_double_constants_are_collected() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::Copy(TackyValue::Constant(Const::ConstDouble(2.0)), var("a")),
+        TackyInstruction::Copy(TackyValue::Constant(Const::ConstDouble(2.0)), var("b")),
+        TackyInstruction::Unary(TackyUnaryOperator::Negate, var("a"), var("b")),
+    ])
+    .with_symbols(make_symbols(&[("a", Type::Double), ("b", Type::Double)]));
+
+    let asm = lower_to_asm(tacky);
+
+    // each value is emitted once, the sign mask of the negation is 16-byte aligned
+    assert_eq!(asm.static_constants.len(), 2);
+    assert_eq!(asm.static_constants[0].init, StaticInit::DoubleInit(2.0));
+    assert_eq!(asm.static_constants[0].alignment, 8);
+    assert_eq!(asm.static_constants[1].init, StaticInit::DoubleInit(-0.0));
+    assert_eq!(asm.static_constants[1].alignment, 16);
+}
+
+#[test]
+fn test_codegen_double_equality_checks_parity() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::Binary(TackyBinaryOperator::Equal, var("a"), var("b"), var("c")),
+        TackyInstruction::Binary(TackyBinaryOperator::NotEqual, var("a"), var("b"), var("d")),
+    ])
+    .with_symbols(make_symbols(&[
+        ("a", Type::Double),
+        ("b", Type::Double),
+        ("c", Type::Int),
+        ("d", Type::Int),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    // NaN is unordered: == must also check that PF is clear, != that PF is set
+    assert!(has_setcc(instructions, AsmCondCode::NP));
+    assert!(has_setcc(instructions, AsmCondCode::P));
+    assert!(has_binary_op(instructions, AsmBinaryOperator::BitwiseAnd));
+    assert!(has_binary_op(instructions, AsmBinaryOperator::BitwiseOr));
+}
+
+#[test]
+fn test_codegen_double_less_than_swaps_operands() {
+    let tacky = make_tacky_program(vec![TackyInstruction::Binary(
+        TackyBinaryOperator::LessThan,
+        var("a"),
+        var("b"),
+        var("c"),
+    )])
+    .with_symbols(make_symbols(&[
+        ("a", Type::Double),
+        ("b", Type::Double),
+        ("c", Type::Int),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    // a < b is emitted as b > a, which is false when the comparison is unordered
+    assert!(matches!(
+        &instructions[0],
+        AsmInstruction::Cmp(AsmType::Double, AsmOperand::Pseudo(a), AsmOperand::Pseudo(b))
+            if a.value == "a" && b.value == "b"
+    ));
+    assert!(has_setcc(instructions, AsmCondCode::A));
+    assert!(!has_setcc(instructions, AsmCondCode::B));
+}
+
+#[test]
+fn test_codegen_double_jump_if_not_zero_on_nan() {
+    let tacky = make_tacky_program(vec![TackyInstruction::JumpIfNotZero(
+        var("d"),
+        TackyIdentifier {
+            value: "target".to_string(),
+        },
+    )])
+    .with_symbols(make_symbols(&[("d", Type::Double)]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, AsmInstruction::JmpCC(AsmCondCode::P, _)))
+    );
+}
+
+#[test]
+fn test_codegen_double_arguments_use_xmm_registers() {
+    let args = vec![var("d1"), var("i1"), var("d2")];
+    let tacky = make_tacky_program(vec![make_fun_call("f", args)]).with_symbols(make_symbols(&[
+        ("d1", Type::Double),
+        ("i1", Type::Int),
+        ("d2", Type::Double),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+    let destination = |name: &str| {
+        instructions.iter().find_map(|i| match i {
+            AsmInstruction::Mov(_, AsmOperand::Pseudo(id), AsmOperand::Register(reg))
+                if id.value == name =>
+            {
+                Some(reg.clone())
+            }
+            _ => None,
+        })
+    };
+
+    assert_eq!(destination("d1"), Some(Reg::XMM0));
+    assert_eq!(destination("i1"), Some(Reg::DI));
+    assert_eq!(destination("d2"), Some(Reg::XMM1));
+}
+
+#[test]
+fn test_codegen_double_conversions() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::IntToDouble(var("l"), var("d")),
+        TackyInstruction::DoubleToInt(var("d"), var("i")),
+    ])
+    .with_symbols(make_symbols(&[
+        ("l", Type::Long),
+        ("d", Type::Double),
+        ("i", Type::Int),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(matches!(
+        instructions[0],
+        AsmInstruction::Cvtsi2sd(AsmType::Quadword, _, _)
+    ));
+    assert!(matches!(
+        instructions[1],
+        AsmInstruction::Cvttsd2si(AsmType::Longword, _, _)
+    ));
+}
+
+// =============================================================================
+// POINTERS
+// =============================================================================
+
+#[test]
+fn test_codegen_get_address_uses_lea() {
+    let tacky =
+        make_tacky_program(vec![TackyInstruction::GetAddress(var("x"), var("p"))]).with_symbols(
+            make_symbols(&[("x", Type::Int), ("p", Type::Pointer(Box::new(Type::Int)))]),
+        );
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Lea(AsmOperand::Pseudo(src), AsmOperand::Pseudo(dst))
+            if src.value == "x" && dst.value == "p"
+    )));
+}
+
+#[test]
+fn test_codegen_load_and_store_go_through_memory_operands() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::Load(var("p"), var("d")),
+        TackyInstruction::Store(var("d"), var("p")),
+    ])
+    .with_symbols(make_symbols(&[
+        ("p", Type::Pointer(Box::new(Type::Double))),
+        ("d", Type::Double),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    // pointers are quadwords, the pointed-to object keeps its own type
+    let ptr_movs = instructions
+        .iter()
+        .filter(|i| {
+            matches!(
+                i,
+                AsmInstruction::Mov(AsmType::Quadword, _, AsmOperand::Register(Reg::AX))
+            )
+        })
+        .count();
+    assert_eq!(ptr_movs, 2);
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Mov(AsmType::Double, AsmOperand::Memory(Reg::AX, 0), _)
+    )));
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Mov(AsmType::Double, _, AsmOperand::Memory(Reg::AX, 0))
+    )));
+}
+
+// =============================================================================
+// ARRAYS
+// =============================================================================
+
+// Helper: symbols for an int pointer `p`, a long index `i` and a result pointer `q`
+fn add_ptr_symbols() -> SymbolTable {
+    make_symbols(&[
+        ("p", Type::Pointer(Box::new(Type::Int))),
+        ("i", Type::Long),
+        ("q", Type::Pointer(Box::new(Type::Int))),
+    ])
+}
+
+#[test]
+fn test_codegen_add_ptr_constant_index_uses_displacement() {
+    let tacky = make_tacky_program(vec![TackyInstruction::AddPtr(
+        var("p"),
+        TackyValue::Constant(Const::ConstLong(3)),
+        4,
+        var("q"),
+    )])
+    .with_symbols(add_ptr_symbols());
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Lea(AsmOperand::Memory(Reg::AX, 12), AsmOperand::Pseudo(dst))
+            if dst.value == "q"
+    )));
+}
+
+#[test]
+fn test_codegen_add_ptr_variable_index_uses_indexed_operand() {
+    let tacky = make_tacky_program(vec![TackyInstruction::AddPtr(
+        var("p"),
+        var("i"),
+        8,
+        var("q"),
+    )])
+    .with_symbols(add_ptr_symbols());
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Mov(AsmType::Quadword, AsmOperand::Pseudo(src), AsmOperand::Register(Reg::DX))
+            if src.value == "i"
+    )));
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Lea(AsmOperand::Indexed(Reg::AX, Reg::DX, 8), _)
+    )));
+}
+
+#[test]
+fn test_codegen_add_ptr_other_scales_multiply_the_index() {
+    let tacky = make_tacky_program(vec![TackyInstruction::AddPtr(
+        var("p"),
+        var("i"),
+        12,
+        var("q"),
+    )])
+    .with_symbols(add_ptr_symbols());
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(has_binary_op(instructions, AsmBinaryOperator::Mult));
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Lea(AsmOperand::Indexed(Reg::AX, Reg::DX, 1), _)
+    )));
+}
+
+#[test]
+fn test_codegen_copy_to_offset_writes_into_the_array() {
+    let tacky = make_tacky_program(vec![TackyInstruction::CopyToOffset(
+        TackyValue::Constant(Const::ConstLong(7)),
+        TackyIdentifier::new("arr"),
+        16,
+    )])
+    .with_symbols(make_symbols(&[(
+        "arr",
+        Type::Array(Box::new(Type::Long), 3),
+    )]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        AsmInstruction::Mov(AsmType::Quadword, AsmOperand::Imm(7), AsmOperand::PseudoMem(id, 16))
+            if id.value == "arr"
+    )));
+}
+
+#[test]
+fn test_codegen_static_array_alignment() {
+    let mut symbols = SymbolTable::new();
+    symbols.insert(
+        "small",
+        Symbol::new(
+            Type::Array(Box::new(Type::Int), 2),
+            IdentifierAttrs::LocalAttr,
+        ),
+    );
+    symbols.insert(
+        "big",
+        Symbol::new(
+            Type::Array(Box::new(Type::Int), 4),
+            IdentifierAttrs::LocalAttr,
+        ),
+    );
+    let tacky = TackyProgram::new(
+        vec![],
+        vec![
+            TackyStaticVariable::new(
+                TackyIdentifier::new("small"),
+                true,
+                StaticInit::ArrayInit(vec![StaticInit::IntInit(1), StaticInit::IntInit(2)]),
+            ),
+            TackyStaticVariable::new(TackyIdentifier::new("big"), true, StaticInit::ZeroInit(16)),
+        ],
+    )
+    .with_symbols(symbols);
+
+    let asm = lower_to_asm(tacky);
+
+    assert_eq!(asm.static_variables[0].alignment, 4);
+    assert_eq!(asm.static_variables[1].alignment, 16);
+}
+
+// =============================================================================
+// CHARACTERS AND STRINGS
+// =============================================================================
+
+#[test]
+fn test_codegen_char_extension_and_truncation() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::SignExtend(var("c"), var("i")),
+        TackyInstruction::ZeroExtend(var("u"), var("l")),
+        TackyInstruction::Truncate(var("i"), var("c")),
+    ])
+    .with_symbols(make_symbols(&[
+        ("c", Type::Char),
+        ("u", Type::UChar),
+        ("i", Type::Int),
+        ("l", Type::Long),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert!(matches!(
+        instructions[0],
+        AsmInstruction::Movsx(AsmType::Byte, AsmType::Longword, _, _)
+    ));
+    assert!(matches!(
+        instructions[1],
+        AsmInstruction::MovZeroExtend(AsmType::Byte, AsmType::Quadword, _, _)
+    ));
+    // truncating only keeps the low byte
+    assert!(matches!(
+        instructions[2],
+        AsmInstruction::Mov(AsmType::Byte, _, _)
+    ));
+}
+
+#[test]
+fn test_codegen_char_double_conversions_go_through_ints() {
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::IntToDouble(var("c"), var("d")),
+        TackyInstruction::DoubleToInt(var("d"), var("c")),
+    ])
+    .with_symbols(make_symbols(&[("c", Type::Char), ("d", Type::Double)]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    let r10 = AsmOperand::Register(Reg::R10);
+    let pseudo = |name: &str| {
+        AsmOperand::Pseudo(AsmIdentifier {
+            value: name.to_string(),
+        })
+    };
+    assert_eq!(
+        instructions[..4],
+        [
+            AsmInstruction::Movsx(AsmType::Byte, AsmType::Longword, pseudo("c"), r10.clone(),),
+            AsmInstruction::Cvtsi2sd(AsmType::Longword, r10.clone(), pseudo("d"),),
+            AsmInstruction::Cvttsd2si(AsmType::Longword, pseudo("d"), r10.clone(),),
+            AsmInstruction::Mov(AsmType::Byte, r10, pseudo("c"),),
+        ]
+    );
+}
+
+#[test]
+fn test_codegen_string_constants() {
+    let tacky = make_tacky_program(vec![])
+        .with_constants(vec![
+            TackyStaticConstant::new(
+                TackyIdentifier::new("string.0"),
+                Type::Array(Box::new(Type::Char), 3),
+                StaticInit::StringInit("hi".to_string(), true),
+            ),
+            TackyStaticConstant::new(
+                TackyIdentifier::new("string.1"),
+                Type::Array(Box::new(Type::Char), 20),
+                StaticInit::StringInit("a longer string....".to_string(), true),
+            ),
+        ])
+        .with_symbols(SymbolTable::new());
+
+    let asm = lower_to_asm(tacky);
+
+    // arrays of 16 bytes or more are 16-byte aligned
+    let alignments: Vec<_> = asm
+        .static_constants
+        .iter()
+        .map(|c| (c.name.value.as_str(), c.alignment))
+        .collect();
+    assert_eq!(alignments, vec![("string.0", 1), ("string.1", 16)]);
+}
+
+// =============================================================================
+// STRUCTURES
+// =============================================================================
+
+// Helper: symbols for local variables, with the given structures laid out in the type table
+fn make_struct_symbols(structs: &[(&str, Vec<Type>)], vars: &[(&str, Type)]) -> SymbolTable {
+    let mut symbols = make_symbols(vars);
+    for (tag, members) in structs {
+        let members = members
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| (format!("m{i}"), ty.clone()))
+            .collect();
+        let entry = StructEntry::layout(members, symbols.types());
+        symbols.types_mut().insert(tag, entry);
+    }
+    symbols
+}
+
+// Helper: a structure type
+fn structure(tag: &str) -> Type {
+    Type::Structure(tag.to_string())
+}
+
+// Helper: a part of a pseudo register holding an aggregate
+fn pseudo_mem(name: &str, offset: i32) -> AsmOperand {
+    AsmOperand::PseudoMem(
+        AsmIdentifier {
+            value: name.to_string(),
+        },
+        offset,
+    )
+}
+
+#[test]
+fn test_codegen_structure_copy_moves_chunks() {
+    let tacky = make_tacky_program(vec![TackyInstruction::Copy(var("a"), var("b"))]).with_symbols(
+        make_struct_symbols(
+            &[("s", vec![Type::Int, Type::Int, Type::Int])],
+            &[("a", structure("s")), ("b", structure("s"))],
+        ),
+    );
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    // 12 bytes are copied as a quadword and a longword
+    assert_eq!(
+        instructions[..2],
+        [
+            AsmInstruction::Mov(AsmType::Quadword, pseudo_mem("a", 0), pseudo_mem("b", 0)),
+            AsmInstruction::Mov(AsmType::Longword, pseudo_mem("a", 8), pseudo_mem("b", 8)),
+        ]
+    );
+}
+
+#[test]
+fn test_codegen_structure_arguments_are_classified() {
+    let tacky = make_tacky_program(vec![make_fun_call("f", vec![var("mixed"), var("chars")])])
+        .with_symbols(make_struct_symbols(
+            &[
+                ("mixed", vec![Type::Double, Type::Int]),
+                ("chars", vec![Type::Char, Type::Char, Type::Char]),
+            ],
+            &[
+                ("mixed", structure("mixed")),
+                ("chars", structure("chars")),
+                ("result", Type::Int),
+            ],
+        ));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    // the double eightbyte goes in an SSE register, the int one in a general purpose one
+    assert_eq!(
+        instructions[..2],
+        [
+            AsmInstruction::Mov(
+                AsmType::Double,
+                pseudo_mem("mixed", 0),
+                AsmOperand::Register(Reg::XMM0)
+            ),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                pseudo_mem("mixed", 8),
+                AsmOperand::Register(Reg::DI)
+            ),
+        ]
+    );
+    // three loose bytes are assembled in the register from the last one
+    let si = AsmOperand::Register(Reg::SI);
+    assert_eq!(
+        instructions[2..7],
+        [
+            AsmInstruction::Mov(AsmType::Byte, pseudo_mem("chars", 2), si.clone()),
+            AsmInstruction::Binary(
+                AsmBinaryOperator::LeftShift,
+                AsmType::Quadword,
+                AsmOperand::Imm(8),
+                si.clone()
+            ),
+            AsmInstruction::Mov(AsmType::Byte, pseudo_mem("chars", 1), si.clone()),
+            AsmInstruction::Binary(
+                AsmBinaryOperator::LeftShift,
+                AsmType::Quadword,
+                AsmOperand::Imm(8),
+                si.clone()
+            ),
+            AsmInstruction::Mov(AsmType::Byte, pseudo_mem("chars", 0), si),
+        ]
+    );
+}
+
+#[test]
+fn test_codegen_large_structure_argument_is_pushed() {
+    let tacky = make_tacky_program(vec![make_fun_call("f", vec![var("big")])]).with_symbols(
+        make_struct_symbols(
+            &[("big", vec![Type::Long, Type::Long, Type::Long])],
+            &[("big", structure("big")), ("result", Type::Int)],
+        ),
+    );
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    // the eightbytes are pushed last to first, with padding for the odd count
+    assert_eq!(
+        instructions[..4],
+        [
+            AsmInstruction::AllocateStack(8),
+            AsmInstruction::Push(pseudo_mem("big", 16)),
+            AsmInstruction::Push(pseudo_mem("big", 8)),
+            AsmInstruction::Push(pseudo_mem("big", 0)),
+        ]
+    );
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, AsmInstruction::DeallocateStack(32)))
+    );
+}
+
+#[test]
+fn test_codegen_structure_returned_in_memory() {
+    let big = vec![Type::Long, Type::Long, Type::Long];
+    let mut symbols = make_struct_symbols(
+        &[("big", big)],
+        &[("x", structure("big")), ("result", structure("big"))],
+    );
+    symbols.insert(
+        "main",
+        Symbol::new(
+            Type::FunType(vec![], Box::new(structure("big")), false),
+            IdentifierAttrs::FunAttr {
+                defined: true,
+                global: true,
+            },
+        ),
+    );
+    let tacky = make_tacky_program(vec![
+        make_fun_call("f", vec![]),
+        TackyInstruction::Return(var("x")),
+    ])
+    .with_symbols(symbols);
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+    let return_ptr = AsmOperand::Pseudo(AsmIdentifier {
+        value: "return.ptr".to_string(),
+    });
+
+    // the callee keeps the address the caller passed in RDI
+    assert_eq!(
+        instructions[0],
+        AsmInstruction::Mov(
+            AsmType::Quadword,
+            AsmOperand::Register(Reg::DI),
+            return_ptr.clone()
+        )
+    );
+    // the caller passes the address of the destination
+    assert_eq!(
+        instructions[1],
+        AsmInstruction::Lea(
+            AsmOperand::Pseudo(AsmIdentifier {
+                value: "result".to_string(),
+            }),
+            AsmOperand::Register(Reg::DI)
+        )
+    );
+    // the value is copied through the saved address, which is returned in RAX
+    let ax = AsmOperand::Register(Reg::AX);
+    let ret = instructions
+        .iter()
+        .position(|i| matches!(i, AsmInstruction::Mov(_, src, _) if *src == return_ptr))
+        .expect("return copies through the pointer");
+    assert_eq!(
+        instructions[ret..],
+        [
+            AsmInstruction::Mov(AsmType::Quadword, return_ptr, ax.clone()),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                pseudo_mem("x", 0),
+                AsmOperand::Memory(Reg::AX, 0)
+            ),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                pseudo_mem("x", 8),
+                AsmOperand::Memory(Reg::AX, 8)
+            ),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                pseudo_mem("x", 16),
+                AsmOperand::Memory(Reg::AX, 16)
+            ),
+            AsmInstruction::Ret,
+        ]
+    );
+}
+
+// =============================================================================
+// SWITCH STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_codegen_jump_table_loads_the_index() {
+    let label = |name: &str| TackyIdentifier {
+        value: name.to_string(),
+    };
+    let tacky = make_tacky_program(vec![TackyInstruction::JumpTable(
+        var("index"),
+        label("table.0"),
+        vec![label("case.1"), label("case.2")],
+    )])
+    .with_symbols(make_symbols(&[("index", Type::ULong)]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    let asm_label = |name: &str| AsmIdentifier {
+        value: name.to_string(),
+    };
+    assert_eq!(
+        instructions[..2],
+        [
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                AsmOperand::Pseudo(asm_label("index")),
+                AsmOperand::Register(Reg::R10)
+            ),
+            AsmInstruction::JmpTable(
+                Reg::R10,
+                asm_label("table.0"),
+                vec![asm_label("case.1"), asm_label("case.2")]
+            ),
+        ]
+    );
+}
+
+// =============================================================================
+// FUNCTION POINTERS
+// =============================================================================
+
+// Helper: a pointer to `int (int)`
+fn int_fun_pointer() -> Type {
+    Type::Pointer(Box::new(Type::FunType(
+        vec![Type::Int],
+        Box::new(Type::Int),
+        false,
+    )))
+}
+
+#[test]
+fn test_codegen_indirect_call_loads_the_pointer_last() {
+    let tacky = make_tacky_program(vec![TackyInstruction::IndirectCall(
+        var("fp"),
+        vec![var("x")],
+        var("result"),
+    )])
+    .with_symbols(make_symbols(&[
+        ("fp", int_fun_pointer()),
+        ("x", Type::Int),
+        ("result", Type::Int),
+    ]));
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    let fp = AsmOperand::Pseudo(AsmIdentifier {
+        value: "fp".to_string(),
+    });
+    let Some(call) = instructions
+        .iter()
+        .position(|i| matches!(i, AsmInstruction::IndirectCall(_)))
+    else {
+        panic!("no indirect call in {instructions:?}");
+    };
+    assert_eq!(
+        instructions[call - 1..=call],
+        [
+            AsmInstruction::Mov(AsmType::Quadword, fp, AsmOperand::Register(Reg::R11)),
+            AsmInstruction::IndirectCall(Reg::R11),
+        ]
+    );
+    assert!(
+        instructions[..call].iter().any(|i| matches!(
+            i,
+            AsmInstruction::Mov(AsmType::Longword, _, AsmOperand::Register(Reg::DI))
+        )),
+        "the argument should be passed in DI"
+    );
+    assert!(
+        instructions[call..].iter().any(|i| matches!(
+            i,
+            AsmInstruction::Mov(AsmType::Longword, AsmOperand::Register(Reg::AX), _)
+        )),
+        "the result should be read from AX"
+    );
+}
+
+#[test]
+fn test_codegen_function_address_is_read_from_the_got() {
+    let mut symbols = make_symbols(&[("fp", int_fun_pointer())]);
+    symbols.insert(
+        "f",
+        Symbol::new(
+            Type::FunType(vec![Type::Int], Box::new(Type::Int), false),
+            IdentifierAttrs::FunAttr {
+                defined: false,
+                global: true,
+            },
+        ),
+    );
+    let tacky = make_tacky_program(vec![TackyInstruction::GetAddress(var("f"), var("fp"))])
+        .with_symbols(symbols);
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    assert_eq!(
+        instructions[0],
+        AsmInstruction::Mov(
+            AsmType::Quadword,
+            AsmOperand::GotEntry(AsmIdentifier {
+                value: "f".to_string(),
+            }),
+            AsmOperand::Pseudo(AsmIdentifier {
+                value: "fp".to_string(),
+            })
+        )
+    );
+}
+
+// =============================================================================
+// VARIADIC FUNCTIONS
+// =============================================================================
+
+// Helper: a function symbol with the given type
+fn fun_symbol(ty: Type) -> Symbol {
+    Symbol::new(
+        ty,
+        IdentifierAttrs::FunAttr {
+            defined: true,
+            global: true,
+        },
+    )
+}
+
+#[test]
+fn test_codegen_variadic_call_sets_al() {
+    let char_ptr = Type::Pointer(Box::new(Type::Char));
+    let mut symbols = make_symbols(&[
+        ("fmt", char_ptr.clone()),
+        ("d", Type::Double),
+        ("x", Type::Int),
+        ("result", Type::Int),
+    ]);
+    symbols.insert(
+        "printf",
+        fun_symbol(Type::FunType(
+            vec![char_ptr.clone()],
+            Box::new(Type::Int),
+            true,
+        )),
+    );
+    symbols.insert(
+        "puts",
+        fun_symbol(Type::FunType(vec![char_ptr], Box::new(Type::Int), false)),
+    );
+    let tacky = make_tacky_program(vec![
+        TackyInstruction::FunCall(
+            TackyIdentifier {
+                value: "printf".to_string(),
+            },
+            vec![var("fmt"), var("d"), var("x"), var("d")],
+            var("result"),
+        ),
+        TackyInstruction::FunCall(
+            TackyIdentifier {
+                value: "puts".to_string(),
+            },
+            vec![var("fmt")],
+            var("result"),
+        ),
+    ])
+    .with_symbols(symbols);
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+
+    let calls: Vec<_> = instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, i)| matches!(i, AsmInstruction::Call(_)))
+        .map(|(position, _)| position)
+        .collect();
+    assert_eq!(calls.len(), 2);
+    // AL holds the number of XMM registers used, only for the variadic function
+    assert_eq!(
+        instructions[calls[0] - 1],
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(2),
+            AsmOperand::Register(Reg::AX)
+        )
+    );
+    assert!(!matches!(
+        &instructions[calls[1] - 1],
+        AsmInstruction::Mov(_, AsmOperand::Imm(_), AsmOperand::Register(Reg::AX))
+    ));
+}
+
+#[test]
+fn test_codegen_va_start_saves_registers_and_sets_offsets() {
+    let va_list_ptr = Type::Pointer(Box::new(Type::Structure(VA_LIST_TAG.to_string())));
+    let mut symbols = make_symbols(&[("n", Type::Int), ("d", Type::Double), ("ap", va_list_ptr)]);
+    symbols.insert(
+        "f",
+        fun_symbol(Type::FunType(
+            vec![Type::Int, Type::Double],
+            Box::new(Type::Int),
+            true,
+        )),
+    );
+    let tacky = TackyProgram::new(
+        vec![TackyFunctionDefinition::new(
+            TackyIdentifier {
+                value: "f".to_string(),
+            },
+            true,
+            vec![
+                TackyIdentifier {
+                    value: "n".to_string(),
+                },
+                TackyIdentifier {
+                    value: "d".to_string(),
+                },
+            ],
+            vec![TackyInstruction::VaStart(var("ap"))],
+        )],
+        vec![],
+    )
+    .with_symbols(symbols);
+
+    let asm = lower_to_asm(tacky);
+    let instructions = &asm.function_definitions[0].instructions;
+    let save_area = |offset| {
+        AsmOperand::PseudoMem(
+            AsmIdentifier {
+                value: REG_SAVE_AREA.to_string(),
+            },
+            offset,
+        )
+    };
+
+    // the registers are saved before the parameters are copied
+    assert_eq!(
+        instructions[0],
+        AsmInstruction::Mov(
+            AsmType::Quadword,
+            AsmOperand::Register(Reg::DI),
+            save_area(0)
+        )
+    );
+    assert_eq!(
+        instructions[13],
+        AsmInstruction::Mov(
+            AsmType::Double,
+            AsmOperand::Register(Reg::XMM7),
+            save_area(160)
+        )
+    );
+    // one integer and one double register are taken by the named parameters
+    for (value, offset) in [(8, 0), (64, 4)] {
+        assert!(
+            instructions.contains(&AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(value),
+                AsmOperand::Memory(Reg::AX, offset)
+            )),
+            "missing offset {value} in {instructions:?}"
+        );
+    }
+    assert!(instructions.contains(&AsmInstruction::Lea(
+        AsmOperand::Stack(16),
+        AsmOperand::Register(Reg::R11)
+    )));
+    assert!(instructions.contains(&AsmInstruction::Lea(
+        save_area(0),
+        AsmOperand::Register(Reg::R11)
+    )));
+}
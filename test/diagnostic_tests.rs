@@ -0,0 +1,324 @@
+use fcc::c_ast::ast::Program;
+use fcc::common::diagnostic::{Diagnostic, Severity, SourceCache};
+use fcc::common::span::Span;
+use fcc::driver::validate_semantics;
+use fcc::lexer::lex_spanned;
+use fcc::preprocessor::Preprocessor;
+
+// Helper: a span of `length` characters at a line and column of a file, `start` is its
+// offset in the lexed source
+fn span(file: &str, line: usize, column: usize, start: usize, length: usize) -> Span {
+    Span {
+        file: file.into(),
+        line,
+        column,
+        end_line: line,
+        end_column: column + length,
+        start,
+        end: start + length,
+    }
+}
+
+// Helper: a cache with the source of main.c
+fn main_source() -> SourceCache {
+    let mut sources = SourceCache::new();
+    sources.insert(
+        "main.c",
+        "int main(void) {\n  int x = 1;\n  return x + ;\n}\n",
+    );
+    sources
+}
+
+// =============================================================================
+// RENDERING
+// =============================================================================
+
+#[test]
+fn test_render_with_caret() {
+    let diagnostic = Diagnostic::error("E0301", "could not parse expression")
+        .with_span(span("main.c", 3, 14, 41, 1));
+
+    assert_eq!(
+        diagnostic.render(&mut main_source()),
+        "error[E0301]: could not parse expression\n \
+         --> main.c:3:14\n  \
+         |\n\
+         3 |   return x + ;\n  \
+         |              ^\n"
+    );
+}
+
+#[test]
+fn test_render_underlines_the_whole_span() {
+    let diagnostic = Diagnostic::error("E0413", "undeclared variable")
+        .with_span(span("main.c", 2, 7, 23, 1))
+        .with_label(span("main.c", 3, 10, 37, 5), "used here");
+
+    assert_eq!(
+        diagnostic.render(&mut main_source()),
+        "error[E0413]: undeclared variable\n \
+         --> main.c:2:7\n  \
+         |\n\
+         2 |   int x = 1;\n  \
+         |       ^\n\
+         3 |   return x + ;\n  \
+         |          ----- used here\n"
+    );
+}
+
+#[test]
+fn test_render_label_in_another_file() {
+    let mut sources = main_source();
+    sources.insert("inc/a.h", "int x;\n");
+    let diagnostic = Diagnostic::error("E0413", "conflicting declarations")
+        .with_span(span("main.c", 2, 7, 23, 1))
+        .with_label(span("inc/a.h", 1, 5, 4, 1), "previous declaration");
+
+    let rendered = diagnostic.render(&mut sources);
+    assert!(rendered.contains(" --> main.c:2:7\n"));
+    assert!(rendered.contains(" ::: inc/a.h:1:5\n"));
+    assert!(rendered.contains("1 | int x;\n  |     - previous declaration\n"));
+}
+
+#[test]
+fn test_render_notes() {
+    let diagnostic = Diagnostic::warning("E0413", "unused variable")
+        .with_span(span("main.c", 2, 7, 23, 1))
+        .with_note("remove it");
+
+    let rendered = diagnostic.render(&mut main_source());
+    assert!(rendered.starts_with("warning[E0413]: unused variable\n"));
+    assert!(rendered.ends_with("  |       ^\n  |\n  = note: remove it\n"));
+}
+
+#[test]
+fn test_render_unknown_span() {
+    let diagnostic = Diagnostic::new(Severity::Error, "E0600", "source file does not exist")
+        .with_note("check the path");
+
+    assert_eq!(
+        diagnostic.render(&mut SourceCache::new()),
+        "error[E0600]: source file does not exist\n = note: check the path\n"
+    );
+}
+
+#[test]
+fn test_render_without_source() {
+    let diagnostic =
+        Diagnostic::error("E0301", "expected `;`").with_span(span("missing.c", 4, 2, 0, 1));
+
+    assert_eq!(
+        diagnostic.render(&mut SourceCache::new()),
+        "error[E0301]: expected `;`\n --> missing.c:4:2\n  |\n"
+    );
+}
+
+#[test]
+fn test_display_prefixes_location() {
+    let diagnostic =
+        Diagnostic::error("E0301", "expected `;`").with_span(span("main.c", 3, 14, 41, 1));
+    assert_eq!(diagnostic.to_string(), "main.c:3:14: expected `;`");
+
+    let diagnostic = Diagnostic::error("E0301", "expected `;`");
+    assert_eq!(String::from(diagnostic), "expected `;`");
+}
+
+// =============================================================================
+// JSON
+// =============================================================================
+
+#[test]
+fn test_json_output() {
+    let diagnostic = Diagnostic::error("E0301", "could not parse expression")
+        .with_span(span("main.c", 3, 14, 41, 1))
+        .with_label(span("main.c", 3, 10, 37, 1), "operand")
+        .with_note("a note");
+
+    assert_eq!(
+        diagnostic.to_json(&mut main_source()),
+        "{\"severity\":\"error\",\"code\":\"E0301\",\"message\":\"could not parse expression\",\
+         \"span\":{\"file\":\"main.c\",\"line\":3,\"column\":14,\"byte_start\":43,\"byte_end\":44},\
+         \"labels\":[{\"span\":{\"file\":\"main.c\",\"line\":3,\"column\":10,\"byte_start\":39,\
+         \"byte_end\":40},\"message\":\"operand\"}],\"notes\":[\"a note\"]}"
+    );
+}
+
+#[test]
+fn test_json_unknown_span_and_escaping() {
+    let diagnostic = Diagnostic::error("E0201", "bad \"token\"\tin a\\b\n\u{1}");
+
+    assert_eq!(
+        diagnostic.to_json(&mut SourceCache::new()),
+        "{\"severity\":\"error\",\"code\":\"E0201\",\
+         \"message\":\"bad \\\"token\\\"\\tin a\\\\b\\n\\u0001\",\
+         \"span\":null,\"labels\":[],\"notes\":[]}"
+    );
+}
+
+#[test]
+fn test_json_byte_range_needs_the_source() {
+    let diagnostic = Diagnostic::error("E0301", "could not parse expression").with_span(span(
+        "missing/main.c",
+        3,
+        14,
+        41,
+        1,
+    ));
+
+    assert!(
+        diagnostic
+            .to_json(&mut SourceCache::new())
+            .contains("\"column\":14,\"byte_start\":null,\"byte_end\":null}")
+    );
+}
+
+// =============================================================================
+// PHASES
+// =============================================================================
+
+#[test]
+fn test_lexer_error_has_code_and_span() {
+    let result = lex_spanned("# 1 \"main.c\"\nint main(void) {\n  return $;\n}");
+
+    assert!(result.is_err());
+    if let Err(error) = result {
+        let diagnostic = Diagnostic::from(error);
+        assert_eq!(diagnostic.code, "E0201");
+        assert_eq!(
+            diagnostic.to_string(),
+            "main.c:2:10: unexpected character `$`"
+        );
+    }
+}
+
+#[test]
+fn test_parser_error_has_code_and_span() {
+    let tokens =
+        lex_spanned("# 1 \"main.c\"\nint main(void) {\n  return 1 + ;\n}").expect("should lex");
+    let result = Program::try_from(tokens);
+
+    assert!(result.is_err());
+    if let Err(error) = result {
+        let diagnostic = Diagnostic::from(error);
+        assert_eq!(diagnostic.code, "E0301");
+        assert_eq!(diagnostic.span.file.as_ref(), "main.c");
+        assert_eq!(diagnostic.span.line, 2);
+    }
+}
+
+#[test]
+fn test_parser_errors_are_reported_together() {
+    let tokens = lex_spanned("# 1 \"main.c\"\nint main(void) {\n  int x = ;\n  return x\n}")
+        .expect("should lex");
+    let result = Program::try_from(tokens);
+
+    assert!(result.is_err());
+    if let Err(error) = result {
+        let diagnostics = error
+            .errors()
+            .iter()
+            .map(|error| Diagnostic::from(error).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            vec![
+                "main.c:2:11: could not parse expression, found Semicolon",
+                "main.c:4:1: expected Semicolon, got CloseBrace",
+            ]
+        );
+
+        // as a single diagnostic, the first error notes the others
+        let diagnostic = Diagnostic::from(error);
+        assert_eq!(diagnostic.code, "E0301");
+        assert_eq!(diagnostic.notes, vec!["and 1 more error"]);
+    }
+}
+
+#[test]
+fn test_each_error_has_its_own_code() {
+    let codes = [
+        "int main(void) { return x; }",
+        "int main(void) { int a; int a; return 0; }",
+        "int main(void) { switch (1) { case 1: case 1: return 0; } }",
+        "int main(void) { switch (1) { default: default: return 0; } }",
+        "int main(void) { a: a: return 0; }",
+        "int f(void) { return 1; } int f(void) { return 2; }",
+    ]
+    .map(|source| compile_error(source).code);
+
+    for (i, code) in codes.iter().enumerate() {
+        assert!(code.starts_with("E04"), "{code}");
+        assert!(!codes[..i].contains(code), "{codes:?}");
+    }
+}
+
+#[test]
+fn test_duplicate_is_labeled_with_the_first_occurrence() {
+    let source = "int main(void) {\n  int a = 1;\n  int a = 2;\n  return a;\n}\n";
+    let mut sources = SourceCache::new();
+    sources.insert("main.c", source);
+
+    let diagnostic = compile_error(source);
+    assert_eq!(diagnostic.span.line, 3);
+    assert_eq!(diagnostic.labels.len(), 1);
+    assert_eq!(diagnostic.labels[0].span.line, 2);
+    assert_eq!(diagnostic.labels[0].message, "previously declared here");
+
+    let rendered = diagnostic.render(&mut sources);
+    assert!(rendered.contains("2 |   int a = 1;\n"), "{rendered}");
+    assert!(rendered.contains("previously declared here"), "{rendered}");
+}
+
+// =============================================================================
+// PREPROCESSED SOURCES
+// =============================================================================
+
+// Helper: the diagnostic for a source that fails to compile, as if it were read from main.c
+fn compile_error(source: &str) -> Diagnostic {
+    let result = Preprocessor::new()
+        .preprocess(source, "main.c")
+        .and_then(|preprocessed| lex_spanned(&preprocessed))
+        .and_then(Program::try_from)
+        .and_then(validate_semantics);
+
+    match result {
+        Ok(_) => panic!("should fail to compile: {source}"),
+        Err(error) => Diagnostic::from(error),
+    }
+}
+
+#[test]
+fn test_json_byte_range_is_in_the_original_file() {
+    let source = "int main(void) { return 1 +; }";
+    let mut sources = SourceCache::new();
+    sources.insert("main.c", source);
+
+    let json = compile_error(source).to_json(&mut sources);
+    assert!(
+        json.contains("\"line\":1,\"column\":28,\"byte_start\":27,\"byte_end\":28}"),
+        "{json}"
+    );
+}
+
+#[test]
+fn test_columns_after_a_macro_are_in_the_original_line() {
+    let line = "int main(void) { int v = LONGMACRONAME(1); return v + undeclared; }";
+    let source = format!("#define LONGMACRONAME(x) (x)\n{line}\n");
+    let column = line.find("undeclared").map(|i| i + 1);
+
+    let diagnostic = compile_error(&source);
+    assert_eq!(diagnostic.span.line, 2);
+    assert_eq!(Some(diagnostic.span.column), column);
+    assert_eq!(diagnostic.span.end_column - diagnostic.span.column, 10);
+}
+
+#[test]
+fn test_columns_after_a_longer_expansion() {
+    let line = "  return ZERO + undeclared;";
+    let source = format!("#define ZERO (1 - 1 + 0 - 0 + 0)\nint main(void) {{\n{line}\n}}\n");
+    let column = line.find("undeclared").map(|i| i + 1);
+
+    let diagnostic = compile_error(&source);
+    assert_eq!(diagnostic.span.line, 3);
+    assert_eq!(Some(diagnostic.span.column), column);
+}
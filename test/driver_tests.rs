@@ -1,4 +1,5 @@
 use clap::Parser;
+use fcc::common::error::{CompileError, DriverError};
 use fcc::driver::CompilerDriver;
 
 #[test]
@@ -88,15 +89,67 @@ fn test_compiler_driver_multiple_flags() {
     assert!(debug_str.contains("program_path: \"test.c\""));
 }
 
+#[test]
+fn test_compiler_driver_include_dirs() {
+    let args = vec!["fcc", "-I", "include", "-Ilib/include", "test.c"];
+    let driver = CompilerDriver::parse_from(args);
+
+    let debug_str = format!("{driver:?}");
+    assert!(debug_str.contains("include_dirs: [\"include\", \"lib/include\"]"));
+}
+
+#[test]
+fn test_compiler_driver_diagnostics_format() {
+    let driver = CompilerDriver::parse_from(vec!["fcc", "test.c"]);
+    let debug_str = format!("{driver:?}");
+    assert!(debug_str.contains("diagnostics_format: Human"));
+
+    let args = vec!["fcc", "--diagnostics-format=json", "test.c"];
+    let driver = CompilerDriver::parse_from(args);
+    let debug_str = format!("{driver:?}");
+    assert!(debug_str.contains("diagnostics_format: Json"));
+}
+
+#[test]
+fn test_compiler_driver_invalid_diagnostics_format() {
+    let args = vec!["fcc", "--diagnostics-format=xml", "test.c"];
+    assert!(CompilerDriver::try_parse_from(args).is_err());
+}
+
+#[test]
+fn test_preprocess_writes_expanded_source() {
+    let dir = std::env::temp_dir().join("fcc_driver_preprocess");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("include")).expect("should create dir");
+    std::fs::write(dir.join("include/answer.h"), "#define ANSWER 42").expect("should write");
+    let source = dir.join("main.c").display().to_string();
+    std::fs::write(
+        &source,
+        "#include <answer.h>\nint main(void) { return ANSWER; }",
+    )
+    .expect("should write");
+
+    let include = dir.join("include").display().to_string();
+    let driver = CompilerDriver::parse_from(vec!["fcc", "-I", &include, &source]);
+    let preprocessed = driver.preprocess(&source).expect("should preprocess");
+
+    assert!(preprocessed.ends_with("main.i"));
+    let code = std::fs::read_to_string(preprocessed).expect("should read");
+    assert!(code.ends_with("int main(void) { return 42    ; }\n"));
+}
+
 #[test]
 fn test_preprocess_invalid_extension() {
     let driver = CompilerDriver::parse_from(vec!["fcc", "test.txt"]);
     let result = driver.preprocess("test.txt");
 
-    assert!(result.is_err());
-    if let Err(error) = result {
-        assert_eq!(error, "SOURCE_FILE should have a .c file extension");
-    }
+    assert_eq!(
+        result,
+        Err(CompileError::Driver(DriverError::InvalidExtension(
+            "test.txt".to_string(),
+            ".c"
+        )))
+    );
 }
 
 #[test]
@@ -104,10 +157,12 @@ fn test_preprocess_nonexistent_file() {
     let driver = CompilerDriver::parse_from(vec!["fcc", "nonexistent.c"]);
     let result = driver.preprocess("nonexistent.c");
 
-    assert!(result.is_err());
-    if let Err(error) = result {
-        assert_eq!(error, "source file does not exist");
-    }
+    assert_eq!(
+        result,
+        Err(CompileError::Driver(DriverError::FileNotFound(
+            "nonexistent.c".to_string()
+        )))
+    );
 }
 
 #[test]
@@ -116,10 +171,12 @@ fn test_preprocess_valid_extension_generates_correct_output() {
 
     let result = driver.preprocess("nonexistent.c");
 
-    assert!(result.is_err());
-    if let Err(error) = result {
-        assert_eq!(error, "source file does not exist");
-    }
+    assert_eq!(
+        result,
+        Err(CompileError::Driver(DriverError::FileNotFound(
+            "nonexistent.c".to_string()
+        )))
+    );
 }
 
 #[test]
@@ -151,3 +208,171 @@ fn test_compiler_driver_debug_formatting() {
     assert!(debug_str.contains("lex: false"));
     assert!(debug_str.contains("parse: false"));
 }
+
+// =============================================================================
+// SYSTEM HEADERS
+// =============================================================================
+
+// Helper: writes a source file in a fresh temporary directory and returns its path
+fn write_source(dir: &str, source: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(dir);
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::create_dir_all(&dir);
+    let path = dir.join("main.c");
+    let _ = std::fs::write(&path, source);
+
+    path
+}
+
+#[test]
+fn test_preprocess_finds_c_library_headers() {
+    let source = write_source(
+        "fcc_driver_system_headers",
+        "#include <stdio.h>\n#include <stdlib.h>\n#include <stdarg.h>\n#include <stddef.h>\n\
+         int main(void) { return 0; }",
+    )
+    .display()
+    .to_string();
+
+    let driver = CompilerDriver::parse_from(vec!["fcc", &source]);
+    let preprocessed = driver.preprocess(&source).expect("should preprocess");
+
+    let code = std::fs::read_to_string(preprocessed).expect("should read");
+    assert!(code.contains("typedef __builtin_va_list __gnuc_va_list;"));
+    assert!(code.contains("typedef __gnuc_va_list va_list;"));
+    assert!(code.contains("typedef unsigned long size_t;"));
+}
+
+#[test]
+fn test_compile_and_run_with_stdarg() {
+    let source = write_source(
+        "fcc_driver_stdarg",
+        "#include <stdarg.h>\n\
+         #include <stddef.h>\n\
+         int printf(const char *format, ...);\n\
+         int sum(int n, ...) {\n\
+             va_list ap;\n\
+             va_start(ap, n);\n\
+             int total = 0;\n\
+             for (int i = 0; i < n; i++) total += va_arg(ap, int);\n\
+             va_end(ap);\n\
+             return total;\n\
+         }\n\
+         int main(void) {\n\
+             size_t three = 3;\n\
+             printf(\"%d\\n\", sum(three, 1, 2, 3));\n\
+             return sum(2, 20, 22) + (NULL == 0);\n\
+         }\n",
+    );
+
+    let compiled = std::process::Command::new(env!("CARGO_BIN_EXE_fcc"))
+        .arg(&source)
+        .output()
+        .expect("should run fcc");
+    assert!(
+        compiled.status.success(),
+        "{}",
+        String::from_utf8_lossy(&compiled.stderr)
+    );
+
+    let run = std::process::Command::new(source.with_extension(""))
+        .output()
+        .expect("should run the program");
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "6\n");
+    assert_eq!(run.status.code(), Some(43));
+}
+
+// =============================================================================
+// PREPROCESSED INPUT
+// =============================================================================
+
+#[test]
+fn test_compile_source_with_line_markers() {
+    // as written by `gcc -E`, with the flags after the file names
+    let source = write_source(
+        "fcc_driver_line_markers",
+        "# 0 \"gen.c\"\n\
+         # 0 \"<built-in>\"\n\
+         # 1 \"/usr/include/stdc-predef.h\" 1 3 4\n\
+         # 0 \"<command-line>\" 2\n\
+         # 1 \"gen.c\"\n\
+         int main(void) {\n\
+         #line 40 \"orig.c\"\n\
+             return __LINE__ + 2;\n\
+         }\n",
+    );
+
+    let compiled = std::process::Command::new(env!("CARGO_BIN_EXE_fcc"))
+        .arg(&source)
+        .output()
+        .expect("should run fcc");
+    assert!(
+        compiled.status.success(),
+        "{}",
+        String::from_utf8_lossy(&compiled.stderr)
+    );
+    let run = std::process::Command::new(source.with_extension(""))
+        .output()
+        .expect("should run the program");
+    assert_eq!(run.status.code(), Some(42));
+}
+
+#[test]
+fn test_errors_point_at_the_lines_of_the_markers() {
+    let source = write_source(
+        "fcc_driver_line_marker_errors",
+        "# 50 \"gen.c\"\nint main(void) {\n#line 100 \"orig.c\"\n  return x;\n}\n",
+    );
+
+    let compiled = std::process::Command::new(env!("CARGO_BIN_EXE_fcc"))
+        .arg(&source)
+        .output()
+        .expect("should run fcc");
+    assert!(!compiled.status.success());
+    let stderr = String::from_utf8_lossy(&compiled.stderr);
+    assert!(stderr.contains("--> orig.c:100:10"), "{stderr}");
+}
+
+// =============================================================================
+// REPORTED DIAGNOSTICS
+// =============================================================================
+
+#[test]
+fn test_json_diagnostics_are_the_only_lines_on_stderr() {
+    let source = write_source(
+        "fcc_driver_json_stderr",
+        "int main(void) {\n  const int k = 1;\n  k = 2;\n  return x;\n}\n",
+    );
+
+    let compiled = std::process::Command::new(env!("CARGO_BIN_EXE_fcc"))
+        .arg("--diagnostics-format=json")
+        .arg(&source)
+        .output()
+        .expect("should run fcc");
+    assert!(!compiled.status.success());
+    let stderr = String::from_utf8_lossy(&compiled.stderr);
+    assert_eq!(stderr.lines().count(), 2, "{stderr}");
+    assert!(
+        stderr
+            .lines()
+            .all(|line| line.starts_with('{') && line.ends_with('}')),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_human_diagnostics_are_reported_once() {
+    let source = write_source(
+        "fcc_driver_human_stderr",
+        "int main(void) {\n  return x;\n}\n",
+    );
+
+    let compiled = std::process::Command::new(env!("CARGO_BIN_EXE_fcc"))
+        .arg(&source)
+        .output()
+        .expect("should run fcc");
+    assert!(!compiled.status.success());
+    let stderr = String::from_utf8_lossy(&compiled.stderr);
+    assert_eq!(stderr.matches("error[").count(), 1, "{stderr}");
+    assert!(!stderr.contains(" ERROR "), "{stderr}");
+}
@@ -1,10 +1,13 @@
+use fcc::c_ast::ast::Type;
+use fcc::c_ast::semantic::type_table::StructEntry;
 use fcc::codegen::x64::ast::{
     AsmBinaryOperator, AsmCondCode, AsmFunctionDefinition, AsmIdentifier, AsmInstruction,
-    AsmOperand, AsmProgram, AsmUnaryOperator, Reg,
+    AsmOperand, AsmProgram, AsmType, AsmUnaryOperator, Reg,
 };
 use fcc::codegen::x64::fixer::instruction_fix::InstructionFixer;
 use fcc::codegen::x64::fixer::reg_replace::PseudoRegisterReplacer;
 use fcc::common::folder::FolderAsm;
+use fcc::common::symbols::{IdentifierAttrs, InitialValue, StaticInit, Symbol, SymbolTable};
 
 #[test]
 fn test_basic_folder_trait() {
@@ -24,14 +27,14 @@ fn test_basic_folder_trait() {
         value: "test".to_string(),
     };
     let instructions = vec![AsmInstruction::Ret];
-    let function = AsmFunctionDefinition::new(identifier, instructions);
-    let program = AsmProgram::new(function);
+    let function = AsmFunctionDefinition::new(identifier, true, instructions);
+    let program = AsmProgram::new(vec![function], vec![]);
 
     let mut basic_folder = folder;
     if let Ok(folded_program) = basic_folder.fold_prog(program) {
-        assert_eq!(folded_program.function_definition.name.value, "test");
-        assert_eq!(folded_program.function_definition.instructions.len(), 1);
-        match &folded_program.function_definition.instructions[0] {
+        assert_eq!(folded_program.function_definitions[0].name.value, "test");
+        assert_eq!(folded_program.function_definitions[0].instructions.len(), 1);
+        match &folded_program.function_definitions[0].instructions[0] {
             AsmInstruction::Ret => {}
             _ => panic!("Expected Ret instruction"),
         }
@@ -55,16 +58,29 @@ fn test_folder_preserves_all_instruction_types() {
 
     let instructions = vec![
         AsmInstruction::Comment("test comment".to_string()),
-        AsmInstruction::Mov(AsmOperand::Imm(42), AsmOperand::Register(Reg::AX)),
-        AsmInstruction::Unary(AsmUnaryOperator::Neg, AsmOperand::Register(Reg::AX)),
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(42),
+            AsmOperand::Register(Reg::AX),
+        ),
+        AsmInstruction::Unary(
+            AsmUnaryOperator::Neg,
+            AsmType::Longword,
+            AsmOperand::Register(Reg::AX),
+        ),
         AsmInstruction::Binary(
             AsmBinaryOperator::Add,
+            AsmType::Longword,
             AsmOperand::Imm(1),
             AsmOperand::Register(Reg::AX),
         ),
-        AsmInstruction::Cmp(AsmOperand::Register(Reg::AX), AsmOperand::Imm(0)),
-        AsmInstruction::Idiv(AsmOperand::Register(Reg::DX)),
-        AsmInstruction::Cdq,
+        AsmInstruction::Cmp(
+            AsmType::Longword,
+            AsmOperand::Register(Reg::AX),
+            AsmOperand::Imm(0),
+        ),
+        AsmInstruction::Idiv(AsmType::Longword, AsmOperand::Register(Reg::DX)),
+        AsmInstruction::Cdq(AsmType::Longword),
         AsmInstruction::Jmp(AsmIdentifier {
             value: "label1".to_string(),
         }),
@@ -88,38 +104,44 @@ fn test_folder_preserves_all_instruction_types() {
         AsmIdentifier {
             value: "main".to_string(),
         },
+        true,
         instructions,
     );
-    let program = AsmProgram::new(function);
+    let program = AsmProgram::new(vec![function], vec![]);
 
     if let Ok(folded_program) = folder.fold_prog(program) {
         assert_eq!(
-            folded_program.function_definition.instructions.len(),
+            folded_program.function_definitions[0].instructions.len(),
             instructions_clone.len()
         );
 
         for (original, folded) in instructions_clone
             .iter()
-            .zip(&folded_program.function_definition.instructions)
+            .zip(&folded_program.function_definitions[0].instructions)
         {
             match (original, folded) {
                 (AsmInstruction::Comment(orig), AsmInstruction::Comment(folded)) => {
                     assert_eq!(orig, folded)
                 }
-                (AsmInstruction::Mov(o1, o2), AsmInstruction::Mov(f1, f2)) => {
-                    assert_eq!((o1, o2), (f1, f2))
+                (AsmInstruction::Mov(t1, o1, o2), AsmInstruction::Mov(t2, f1, f2)) => {
+                    assert_eq!((t1, o1, o2), (t2, f1, f2))
                 }
-                (AsmInstruction::Unary(op1, o1), AsmInstruction::Unary(op2, o2)) => {
-                    assert_eq!((op1, o1), (op2, o2))
+                (AsmInstruction::Unary(op1, t1, o1), AsmInstruction::Unary(op2, t2, o2)) => {
+                    assert_eq!((op1, t1, o1), (op2, t2, o2))
                 }
-                (AsmInstruction::Binary(op1, o1, o2), AsmInstruction::Binary(op2, f1, f2)) => {
-                    assert_eq!((op1, o1, o2), (op2, f1, f2))
+                (
+                    AsmInstruction::Binary(op1, t1, o1, o2),
+                    AsmInstruction::Binary(op2, t2, f1, f2),
+                ) => {
+                    assert_eq!((op1, t1, o1, o2), (op2, t2, f1, f2))
                 }
-                (AsmInstruction::Cmp(o1, o2), AsmInstruction::Cmp(f1, f2)) => {
-                    assert_eq!((o1, o2), (f1, f2))
+                (AsmInstruction::Cmp(t1, o1, o2), AsmInstruction::Cmp(t2, f1, f2)) => {
+                    assert_eq!((t1, o1, o2), (t2, f1, f2))
                 }
-                (AsmInstruction::Idiv(o), AsmInstruction::Idiv(f)) => assert_eq!(o, f),
-                (AsmInstruction::Cdq, AsmInstruction::Cdq) => {}
+                (AsmInstruction::Idiv(t1, o), AsmInstruction::Idiv(t2, f)) => {
+                    assert_eq!((t1, o), (t2, f))
+                }
+                (AsmInstruction::Cdq(o), AsmInstruction::Cdq(f)) => assert_eq!(o, f),
                 (AsmInstruction::Jmp(o), AsmInstruction::Jmp(f)) => assert_eq!(o.value, f.value),
                 (AsmInstruction::JmpCC(oc, ol), AsmInstruction::JmpCC(fc, fl)) => {
                     assert_eq!((oc, &ol.value), (fc, &fl.value))
@@ -300,7 +322,11 @@ fn test_folder_with_all_condition_codes() {
 #[test]
 fn test_instruction_fixer_basic_functionality() {
     let instructions = vec![
-        AsmInstruction::Mov(AsmOperand::Stack(-4), AsmOperand::Stack(-8)),
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Stack(-4),
+            AsmOperand::Stack(-8),
+        ),
         AsmInstruction::Ret,
     ];
 
@@ -308,6 +334,7 @@ fn test_instruction_fixer_basic_functionality() {
         AsmIdentifier {
             value: "main".to_string(),
         },
+        true,
         instructions,
     );
 
@@ -317,7 +344,7 @@ fn test_instruction_fixer_basic_functionality() {
         assert_eq!(fixed_function.instructions.len(), 5);
 
         match &fixed_function.instructions[0] {
-            AsmInstruction::AllocateStack(12) => {}
+            AsmInstruction::AllocateStack(16) => {}
             _ => panic!("Expected AllocateStack instruction"),
         }
 
@@ -332,12 +359,13 @@ fn test_instruction_fixer_basic_functionality() {
 
 #[test]
 fn test_instruction_fixer_idiv_immediate() {
-    let instructions = vec![AsmInstruction::Idiv(AsmOperand::Imm(42))];
+    let instructions = vec![AsmInstruction::Idiv(AsmType::Longword, AsmOperand::Imm(42))];
 
     let function = AsmFunctionDefinition::new(
         AsmIdentifier {
             value: "main".to_string(),
         },
+        true,
         instructions,
     );
 
@@ -354,27 +382,81 @@ fn test_instruction_fixer_idiv_immediate() {
         }
 
         match &fixed_function.instructions[2] {
-            AsmInstruction::Mov(AsmOperand::Imm(42), AsmOperand::Register(Reg::R10)) => {}
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(42),
+                AsmOperand::Register(Reg::R10),
+            ) => {}
             _ => panic!("Expected Mov instruction with immediate to R10"),
         }
 
         match &fixed_function.instructions[3] {
-            AsmInstruction::Idiv(AsmOperand::Register(Reg::R10)) => {}
+            AsmInstruction::Idiv(AsmType::Longword, AsmOperand::Register(Reg::R10)) => {}
             _ => panic!("Expected Idiv instruction with R10"),
         }
     }
 }
 
+#[test]
+fn test_instruction_fixer_per_function_stack() {
+    let make_function = |name: &str, pseudos: usize| {
+        let instructions = (0..pseudos)
+            .map(|i| {
+                AsmInstruction::Mov(
+                    AsmType::Longword,
+                    AsmOperand::Imm(1),
+                    AsmOperand::Pseudo(AsmIdentifier {
+                        value: format!("var{i}"),
+                    }),
+                )
+            })
+            .chain(std::iter::once(AsmInstruction::Ret))
+            .collect();
+        AsmFunctionDefinition::new(
+            AsmIdentifier {
+                value: name.to_string(),
+            },
+            true,
+            instructions,
+        )
+    };
+    let program = AsmProgram::new(
+        vec![make_function("foo", 1), make_function("main", 5)],
+        vec![],
+    );
+
+    let mut replacer = PseudoRegisterReplacer::create();
+    let Ok(program) = replacer.fold_prog(program) else {
+        panic!("replacer should succeed");
+    };
+    let mut fixer = InstructionFixer::create().with_function_offsets(replacer.function_offsets());
+    let Ok(program) = fixer.fold_prog(program) else {
+        panic!("fixer should succeed");
+    };
+
+    // Each function allocates its own frame, rounded up to 16 bytes
+    assert!(matches!(
+        program.function_definitions[0].instructions[0],
+        AsmInstruction::AllocateStack(16)
+    ));
+    assert!(matches!(
+        program.function_definitions[1].instructions[0],
+        AsmInstruction::AllocateStack(32)
+    ));
+}
+
 #[test]
 fn test_pseudo_register_replacer_basic_functionality() {
     let instructions = vec![
         AsmInstruction::Mov(
+            AsmType::Longword,
             AsmOperand::Imm(42),
             AsmOperand::Pseudo(AsmIdentifier {
                 value: "var1".to_string(),
             }),
         ),
         AsmInstruction::Mov(
+            AsmType::Longword,
             AsmOperand::Pseudo(AsmIdentifier {
                 value: "var1".to_string(),
             }),
@@ -387,6 +469,7 @@ fn test_pseudo_register_replacer_basic_functionality() {
         AsmIdentifier {
             value: "main".to_string(),
         },
+        true,
         instructions,
     );
 
@@ -395,12 +478,16 @@ fn test_pseudo_register_replacer_basic_functionality() {
         assert_eq!(replaced_function.instructions.len(), 3);
 
         match &replaced_function.instructions[0] {
-            AsmInstruction::Mov(AsmOperand::Imm(42), AsmOperand::Stack(-4)) => {}
+            AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(42), AsmOperand::Stack(-4)) => {}
             _ => panic!("Expected Mov with stack operand"),
         }
 
         match &replaced_function.instructions[1] {
-            AsmInstruction::Mov(AsmOperand::Stack(-4), AsmOperand::Register(Reg::AX)) => {}
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Stack(-4),
+                AsmOperand::Register(Reg::AX),
+            ) => {}
             _ => panic!("Expected Mov from stack to register"),
         }
     }
@@ -410,12 +497,14 @@ fn test_pseudo_register_replacer_basic_functionality() {
 fn test_pseudo_register_replacer_multiple_variables() {
     let instructions = vec![
         AsmInstruction::Mov(
+            AsmType::Longword,
             AsmOperand::Imm(1),
             AsmOperand::Pseudo(AsmIdentifier {
                 value: "var1".to_string(),
             }),
         ),
         AsmInstruction::Mov(
+            AsmType::Longword,
             AsmOperand::Imm(2),
             AsmOperand::Pseudo(AsmIdentifier {
                 value: "var2".to_string(),
@@ -423,6 +512,7 @@ fn test_pseudo_register_replacer_multiple_variables() {
         ),
         AsmInstruction::Binary(
             AsmBinaryOperator::Add,
+            AsmType::Longword,
             AsmOperand::Pseudo(AsmIdentifier {
                 value: "var1".to_string(),
             }),
@@ -437,6 +527,7 @@ fn test_pseudo_register_replacer_multiple_variables() {
         AsmIdentifier {
             value: "main".to_string(),
         },
+        true,
         instructions,
     );
 
@@ -445,18 +536,19 @@ fn test_pseudo_register_replacer_multiple_variables() {
         assert_eq!(replaced_function.instructions.len(), 4);
 
         match &replaced_function.instructions[0] {
-            AsmInstruction::Mov(AsmOperand::Imm(1), AsmOperand::Stack(-4)) => {}
+            AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(1), AsmOperand::Stack(-4)) => {}
             _ => panic!("Expected first variable at stack -4"),
         }
 
         match &replaced_function.instructions[1] {
-            AsmInstruction::Mov(AsmOperand::Imm(2), AsmOperand::Stack(-8)) => {}
+            AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(2), AsmOperand::Stack(-8)) => {}
             _ => panic!("Expected second variable at stack -8"),
         }
 
         match &replaced_function.instructions[2] {
             AsmInstruction::Binary(
                 AsmBinaryOperator::Add,
+                AsmType::Longword,
                 AsmOperand::Stack(-4),
                 AsmOperand::Stack(-8),
             ) => {}
@@ -520,3 +612,829 @@ fn test_folder_preserves_registers() {
         }
     }
 }
+
+#[test]
+fn test_pseudo_register_replacer_static_variable() {
+    let instructions = vec![
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Pseudo(AsmIdentifier {
+                value: "counter".to_string(),
+            }),
+            AsmOperand::Pseudo(AsmIdentifier {
+                value: "tmp".to_string(),
+            }),
+        ),
+        AsmInstruction::Ret,
+    ];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut symbols = SymbolTable::new();
+    symbols.insert(
+        "counter",
+        Symbol::new(
+            Type::Int,
+            IdentifierAttrs::StaticAttr {
+                init: InitialValue::Initial(StaticInit::IntInit(1)),
+                global: false,
+            },
+        ),
+    );
+    let mut replacer = PseudoRegisterReplacer::create().with_symbols(symbols);
+    let Ok(function) = replacer.fold_fun_def(function) else {
+        panic!("replacer should succeed");
+    };
+
+    // static variables live in the data section, only `tmp` gets a stack slot
+    assert_eq!(
+        function.instructions[0],
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Data(
+                AsmIdentifier {
+                    value: "counter".to_string(),
+                },
+                0
+            ),
+            AsmOperand::Stack(-4),
+        )
+    );
+}
+
+#[test]
+fn test_instruction_fixer_data_to_stack_mov() {
+    let instructions = vec![
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Data(
+                AsmIdentifier {
+                    value: "counter".to_string(),
+                },
+                0,
+            ),
+            AsmOperand::Stack(-4),
+        ),
+        AsmInstruction::Ret,
+    ];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut fixer = InstructionFixer::create().with(4);
+    let Ok(function) = fixer.fold_fun_def(function) else {
+        panic!("fixer should succeed");
+    };
+
+    assert_eq!(
+        function.instructions[2],
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Data(
+                AsmIdentifier {
+                    value: "counter".to_string(),
+                },
+                0
+            ),
+            AsmOperand::Register(Reg::R10),
+        )
+    );
+}
+
+// =============================================================================
+// LONG INTEGERS
+// =============================================================================
+
+#[test]
+fn test_pseudo_register_replacer_long_slots_are_aligned() {
+    let pseudo = |name: &str| {
+        AsmOperand::Pseudo(AsmIdentifier {
+            value: name.to_string(),
+        })
+    };
+    let instructions = vec![
+        AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(1), pseudo("i")),
+        AsmInstruction::Mov(AsmType::Quadword, AsmOperand::Imm(2), pseudo("l")),
+        AsmInstruction::Ret,
+    ];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut symbols = SymbolTable::new();
+    symbols.insert("i", Symbol::new(Type::Int, IdentifierAttrs::LocalAttr));
+    symbols.insert("l", Symbol::new(Type::Long, IdentifierAttrs::LocalAttr));
+    let mut replacer = PseudoRegisterReplacer::create().with_symbols(symbols);
+    let Ok(function) = replacer.fold_fun_def(function) else {
+        panic!("replacer should succeed");
+    };
+
+    // the long skips 4 bytes so it's 8-byte aligned
+    assert!(matches!(
+        function.instructions[0],
+        AsmInstruction::Mov(_, _, AsmOperand::Stack(-4))
+    ));
+    assert!(matches!(
+        function.instructions[1],
+        AsmInstruction::Mov(_, _, AsmOperand::Stack(-16))
+    ));
+    assert_eq!(replacer.last_offset(), -16);
+}
+
+#[test]
+fn test_instruction_fixer_large_immediates() {
+    let instructions = vec![
+        AsmInstruction::Mov(
+            AsmType::Quadword,
+            AsmOperand::Imm(4294967296),
+            AsmOperand::Stack(-8),
+        ),
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(4294967301),
+            AsmOperand::Stack(-12),
+        ),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::Add,
+            AsmType::Quadword,
+            AsmOperand::Imm(4294967296),
+            AsmOperand::Stack(-8),
+        ),
+    ];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut fixer = InstructionFixer::create().with(-16);
+    let Ok(function) = fixer.fold_fun_def(function) else {
+        panic!("fixer should succeed");
+    };
+    let instructions: Vec<_> = function
+        .instructions
+        .into_iter()
+        .filter(|i| !matches!(i, AsmInstruction::Comment(_)))
+        .collect();
+
+    assert_eq!(
+        instructions[1..],
+        [
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                AsmOperand::Imm(4294967296),
+                AsmOperand::Register(Reg::R10),
+            ),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                AsmOperand::Register(Reg::R10),
+                AsmOperand::Stack(-8),
+            ),
+            // only the low 32 bits are kept
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(5),
+                AsmOperand::Stack(-12)
+            ),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                AsmOperand::Imm(4294967296),
+                AsmOperand::Register(Reg::R10),
+            ),
+            AsmInstruction::Binary(
+                AsmBinaryOperator::Add,
+                AsmType::Quadword,
+                AsmOperand::Register(Reg::R10),
+                AsmOperand::Stack(-8),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_instruction_fixer_movsx_to_memory() {
+    let instructions = vec![AsmInstruction::Movsx(
+        AsmType::Longword,
+        AsmType::Quadword,
+        AsmOperand::Imm(-1),
+        AsmOperand::Stack(-8),
+    )];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut fixer = InstructionFixer::create().with(-8);
+    let Ok(function) = fixer.fold_fun_def(function) else {
+        panic!("fixer should succeed");
+    };
+
+    assert_eq!(
+        function.instructions[2..],
+        [
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(-1),
+                AsmOperand::Register(Reg::R10),
+            ),
+            AsmInstruction::Movsx(
+                AsmType::Longword,
+                AsmType::Quadword,
+                AsmOperand::Register(Reg::R10),
+                AsmOperand::Register(Reg::R11),
+            ),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                AsmOperand::Register(Reg::R11),
+                AsmOperand::Stack(-8),
+            ),
+        ]
+    );
+}
+
+// =============================================================================
+// UNSIGNED INTEGERS
+// =============================================================================
+
+#[test]
+fn test_instruction_fixer_zero_extend() {
+    let instructions = vec![
+        AsmInstruction::MovZeroExtend(
+            AsmType::Longword,
+            AsmType::Quadword,
+            AsmOperand::Stack(-4),
+            AsmOperand::Register(Reg::AX),
+        ),
+        AsmInstruction::MovZeroExtend(
+            AsmType::Longword,
+            AsmType::Quadword,
+            AsmOperand::Stack(-4),
+            AsmOperand::Stack(-16),
+        ),
+        AsmInstruction::Div(AsmType::Longword, AsmOperand::Imm(3)),
+    ];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut fixer = InstructionFixer::create().with(-16);
+    let Ok(function) = fixer.fold_fun_def(function) else {
+        panic!("fixer should succeed");
+    };
+    let instructions: Vec<_> = function
+        .instructions
+        .into_iter()
+        .filter(|i| !matches!(i, AsmInstruction::Comment(_)))
+        .collect();
+
+    assert_eq!(
+        instructions[1..],
+        [
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Stack(-4),
+                AsmOperand::Register(Reg::AX),
+            ),
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Stack(-4),
+                AsmOperand::Register(Reg::R11),
+            ),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                AsmOperand::Register(Reg::R11),
+                AsmOperand::Stack(-16),
+            ),
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(3),
+                AsmOperand::Register(Reg::R10),
+            ),
+            AsmInstruction::Div(AsmType::Longword, AsmOperand::Register(Reg::R10)),
+        ]
+    );
+}
+
+// =============================================================================
+// FLOATING-POINT NUMBERS
+// =============================================================================
+
+#[test]
+fn test_instruction_fixer_doubles() {
+    let constant = AsmOperand::DoubleConstant(2.0f64.to_bits());
+    let instructions = vec![
+        AsmInstruction::Mov(AsmType::Double, constant.clone(), AsmOperand::Stack(-8)),
+        AsmInstruction::Binary(
+            AsmBinaryOperator::Add,
+            AsmType::Double,
+            constant.clone(),
+            AsmOperand::Stack(-8),
+        ),
+        AsmInstruction::Cmp(AsmType::Double, constant, AsmOperand::Stack(-8)),
+        AsmInstruction::Cvtsi2sd(AsmType::Longword, AsmOperand::Imm(1), AsmOperand::Stack(-8)),
+        AsmInstruction::Cvttsd2si(
+            AsmType::Quadword,
+            AsmOperand::Stack(-8),
+            AsmOperand::Stack(-16),
+        ),
+    ];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut fixer = InstructionFixer::create().with(-16);
+    let Ok(function) = fixer.fold_fun_def(function) else {
+        panic!("fixer should succeed");
+    };
+    let instructions: Vec<_> = function
+        .instructions
+        .into_iter()
+        .filter(|i| !matches!(i, AsmInstruction::Comment(_)))
+        .collect();
+
+    let constant = AsmOperand::DoubleConstant(2.0f64.to_bits());
+    let xmm14 = AsmOperand::Register(Reg::XMM14);
+    let xmm15 = AsmOperand::Register(Reg::XMM15);
+    assert_eq!(
+        instructions[1..],
+        [
+            AsmInstruction::Mov(AsmType::Double, constant.clone(), xmm14.clone()),
+            AsmInstruction::Mov(AsmType::Double, xmm14, AsmOperand::Stack(-8)),
+            AsmInstruction::Mov(AsmType::Double, AsmOperand::Stack(-8), xmm15.clone()),
+            AsmInstruction::Binary(
+                AsmBinaryOperator::Add,
+                AsmType::Double,
+                constant.clone(),
+                xmm15.clone(),
+            ),
+            AsmInstruction::Mov(AsmType::Double, xmm15.clone(), AsmOperand::Stack(-8)),
+            AsmInstruction::Mov(AsmType::Double, AsmOperand::Stack(-8), xmm15.clone()),
+            AsmInstruction::Cmp(AsmType::Double, constant, xmm15.clone()),
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(1),
+                AsmOperand::Register(Reg::R10),
+            ),
+            AsmInstruction::Cvtsi2sd(
+                AsmType::Longword,
+                AsmOperand::Register(Reg::R10),
+                xmm15.clone(),
+            ),
+            AsmInstruction::Mov(AsmType::Double, xmm15, AsmOperand::Stack(-8)),
+            AsmInstruction::Cvttsd2si(
+                AsmType::Quadword,
+                AsmOperand::Stack(-8),
+                AsmOperand::Register(Reg::R11),
+            ),
+            AsmInstruction::Mov(
+                AsmType::Quadword,
+                AsmOperand::Register(Reg::R11),
+                AsmOperand::Stack(-16),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_pseudo_register_replacer_double_slots() {
+    let mut symbols = SymbolTable::new();
+    symbols.insert("i", Symbol::new(Type::Int, IdentifierAttrs::LocalAttr));
+    symbols.insert("d", Symbol::new(Type::Double, IdentifierAttrs::LocalAttr));
+
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        vec![
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(1),
+                AsmOperand::Pseudo(AsmIdentifier {
+                    value: "i".to_string(),
+                }),
+            ),
+            AsmInstruction::Mov(
+                AsmType::Double,
+                AsmOperand::DoubleConstant(1.0f64.to_bits()),
+                AsmOperand::Pseudo(AsmIdentifier {
+                    value: "d".to_string(),
+                }),
+            ),
+        ],
+    );
+
+    let mut replacer = PseudoRegisterReplacer::create().with_symbols(symbols);
+    let Ok(function) = replacer.fold_fun_def(function) else {
+        panic!("replacer should succeed");
+    };
+
+    // the double is 8-byte aligned, below the int
+    assert_eq!(
+        function.instructions[1],
+        AsmInstruction::Mov(
+            AsmType::Double,
+            AsmOperand::DoubleConstant(1.0f64.to_bits()),
+            AsmOperand::Stack(-16)
+        )
+    );
+    assert_eq!(replacer.last_offset(), -16);
+}
+
+// =============================================================================
+// POINTERS
+// =============================================================================
+
+#[test]
+fn test_instruction_fixer_pointers() {
+    let instructions = vec![
+        AsmInstruction::Lea(AsmOperand::Stack(-4), AsmOperand::Stack(-16)),
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Memory(Reg::AX, 0),
+            AsmOperand::Stack(-4),
+        ),
+    ];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut fixer = InstructionFixer::create().with(-16);
+    let Ok(function) = fixer.fold_fun_def(function) else {
+        panic!("fixer should succeed");
+    };
+    let instructions: Vec<_> = function
+        .instructions
+        .into_iter()
+        .filter(|i| !matches!(i, AsmInstruction::Comment(_)))
+        .collect();
+
+    let r10 = AsmOperand::Register(Reg::R10);
+    let r11 = AsmOperand::Register(Reg::R11);
+    assert_eq!(
+        instructions[1..],
+        [
+            // lea can't write to memory
+            AsmInstruction::Lea(AsmOperand::Stack(-4), r11.clone()),
+            AsmInstruction::Mov(AsmType::Quadword, r11, AsmOperand::Stack(-16)),
+            // memory operands through a pointer count as memory as well
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Memory(Reg::AX, 0),
+                r10.clone()
+            ),
+            AsmInstruction::Mov(AsmType::Longword, r10, AsmOperand::Stack(-4)),
+        ]
+    );
+}
+
+// =============================================================================
+// ARRAYS
+// =============================================================================
+
+#[test]
+fn test_pseudo_register_replacer_array_slots() {
+    let mut symbols = SymbolTable::new();
+    symbols.insert("i", Symbol::new(Type::Int, IdentifierAttrs::LocalAttr));
+    symbols.insert(
+        "arr",
+        Symbol::new(
+            Type::Array(Box::new(Type::Int), 5),
+            IdentifierAttrs::LocalAttr,
+        ),
+    );
+    symbols.insert(
+        "small",
+        Symbol::new(
+            Type::Array(Box::new(Type::Int), 3),
+            IdentifierAttrs::LocalAttr,
+        ),
+    );
+
+    let pseudo_mem = |name: &str, offset| {
+        AsmOperand::PseudoMem(
+            AsmIdentifier {
+                value: name.to_string(),
+            },
+            offset,
+        )
+    };
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        vec![
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(1),
+                AsmOperand::Pseudo(AsmIdentifier {
+                    value: "i".to_string(),
+                }),
+            ),
+            AsmInstruction::Mov(AsmType::Longword, AsmOperand::Imm(2), pseudo_mem("arr", 8)),
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(3),
+                pseudo_mem("small", 4),
+            ),
+        ],
+    );
+
+    let mut replacer = PseudoRegisterReplacer::create().with_symbols(symbols);
+    let Ok(function) = replacer.fold_fun_def(function) else {
+        panic!("replacer should succeed");
+    };
+
+    // int[5] takes 20 bytes and is 16-byte aligned: it starts at -32
+    assert_eq!(
+        function.instructions[1],
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(2),
+            AsmOperand::Stack(-24)
+        )
+    );
+    // int[3] only needs the alignment of its elements: it starts at -44
+    assert_eq!(
+        function.instructions[2],
+        AsmInstruction::Mov(
+            AsmType::Longword,
+            AsmOperand::Imm(3),
+            AsmOperand::Stack(-40)
+        )
+    );
+    assert_eq!(replacer.last_offset(), -44);
+}
+
+#[test]
+fn test_instruction_fixer_indexed_operands() {
+    let indexed = AsmOperand::Indexed(Reg::AX, Reg::DX, 4);
+    let instructions = vec![AsmInstruction::Mov(
+        AsmType::Longword,
+        indexed.clone(),
+        AsmOperand::Stack(-4),
+    )];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut fixer = InstructionFixer::create().with(-4);
+    let Ok(function) = fixer.fold_fun_def(function) else {
+        panic!("fixer should succeed");
+    };
+    let instructions: Vec<_> = function
+        .instructions
+        .into_iter()
+        .filter(|i| !matches!(i, AsmInstruction::Comment(_)))
+        .collect();
+
+    // indexed operands are memory operands too
+    let r10 = AsmOperand::Register(Reg::R10);
+    assert_eq!(
+        instructions[1..],
+        [
+            AsmInstruction::Mov(AsmType::Longword, indexed, r10.clone()),
+            AsmInstruction::Mov(AsmType::Longword, r10, AsmOperand::Stack(-4)),
+        ]
+    );
+}
+
+// =============================================================================
+// CHARACTERS AND STRINGS
+// =============================================================================
+
+#[test]
+fn test_pseudo_register_replacer_char_slots_and_string_constants() {
+    let mut symbols = SymbolTable::new();
+    symbols.insert("c", Symbol::new(Type::Char, IdentifierAttrs::LocalAttr));
+    symbols.insert("i", Symbol::new(Type::Int, IdentifierAttrs::LocalAttr));
+    symbols.insert(
+        "string.0",
+        Symbol::new(
+            Type::Array(Box::new(Type::Char), 3),
+            IdentifierAttrs::ConstantAttr(StaticInit::StringInit("hi".to_string(), true)),
+        ),
+    );
+
+    let pseudo = |name: &str| {
+        AsmOperand::Pseudo(AsmIdentifier {
+            value: name.to_string(),
+        })
+    };
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        vec![
+            AsmInstruction::Mov(AsmType::Byte, AsmOperand::Imm(1), pseudo("c")),
+            AsmInstruction::Movsx(AsmType::Byte, AsmType::Longword, pseudo("c"), pseudo("i")),
+            AsmInstruction::Lea(pseudo("string.0"), AsmOperand::Register(Reg::AX)),
+        ],
+    );
+
+    let mut replacer = PseudoRegisterReplacer::create().with_symbols(symbols);
+    let Ok(function) = replacer.fold_fun_def(function) else {
+        panic!("replacer should succeed");
+    };
+
+    // a char takes a single byte, the int after it is aligned to 4 bytes
+    assert_eq!(
+        function.instructions,
+        vec![
+            AsmInstruction::Mov(AsmType::Byte, AsmOperand::Imm(1), AsmOperand::Stack(-1)),
+            AsmInstruction::Movsx(
+                AsmType::Byte,
+                AsmType::Longword,
+                AsmOperand::Stack(-1),
+                AsmOperand::Stack(-8),
+            ),
+            // string constants are static, like variables
+            AsmInstruction::Lea(
+                AsmOperand::Data(
+                    AsmIdentifier {
+                        value: "string.0".to_string(),
+                    },
+                    0
+                ),
+                AsmOperand::Register(Reg::AX),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_instruction_fixer_byte_operands() {
+    let instructions = vec![
+        AsmInstruction::Mov(AsmType::Byte, AsmOperand::Imm(300), AsmOperand::Stack(-1)),
+        AsmInstruction::MovZeroExtend(
+            AsmType::Byte,
+            AsmType::Longword,
+            AsmOperand::Imm(200),
+            AsmOperand::Stack(-8),
+        ),
+    ];
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        instructions,
+    );
+
+    let mut fixer = InstructionFixer::create().with(-8);
+    let Ok(function) = fixer.fold_fun_def(function) else {
+        panic!("fixer should succeed");
+    };
+
+    let fixed: Vec<_> = function
+        .instructions
+        .into_iter()
+        .filter(|i| !matches!(i, AsmInstruction::Comment(_)))
+        .collect();
+    assert_eq!(
+        fixed[1..],
+        [
+            // movb only keeps the low byte of the immediate
+            AsmInstruction::Mov(AsmType::Byte, AsmOperand::Imm(44), AsmOperand::Stack(-1)),
+            // movzb can't read an immediate or write to memory
+            AsmInstruction::Mov(
+                AsmType::Byte,
+                AsmOperand::Imm(200),
+                AsmOperand::Register(Reg::R10),
+            ),
+            AsmInstruction::MovZeroExtend(
+                AsmType::Byte,
+                AsmType::Longword,
+                AsmOperand::Register(Reg::R10),
+                AsmOperand::Register(Reg::R11),
+            ),
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Register(Reg::R11),
+                AsmOperand::Stack(-8),
+            ),
+        ]
+    );
+}
+
+// =============================================================================
+// STRUCTURES
+// =============================================================================
+
+#[test]
+fn test_pseudo_register_replacer_structure_slots() {
+    let mut symbols = SymbolTable::new();
+    let entry = StructEntry::layout(
+        vec![("c".to_string(), Type::Char), ("l".to_string(), Type::Long)],
+        symbols.types(),
+    );
+    symbols.types_mut().insert("s", entry);
+    let structure = Type::Structure("s".to_string());
+    symbols.insert("i", Symbol::new(Type::Int, IdentifierAttrs::LocalAttr));
+    symbols.insert(
+        "x",
+        Symbol::new(structure.clone(), IdentifierAttrs::LocalAttr),
+    );
+    symbols.insert(
+        "g",
+        Symbol::new(
+            structure,
+            IdentifierAttrs::StaticAttr {
+                init: InitialValue::Tentative,
+                global: true,
+            },
+        ),
+    );
+
+    let pseudo_mem = |name: &str, offset| {
+        AsmOperand::PseudoMem(
+            AsmIdentifier {
+                value: name.to_string(),
+            },
+            offset,
+        )
+    };
+    let function = AsmFunctionDefinition::new(
+        AsmIdentifier {
+            value: "main".to_string(),
+        },
+        true,
+        vec![
+            AsmInstruction::Mov(
+                AsmType::Longword,
+                AsmOperand::Imm(1),
+                AsmOperand::Pseudo(AsmIdentifier {
+                    value: "i".to_string(),
+                }),
+            ),
+            AsmInstruction::Mov(AsmType::Quadword, AsmOperand::Imm(2), pseudo_mem("x", 8)),
+            AsmInstruction::Mov(AsmType::Quadword, AsmOperand::Imm(3), pseudo_mem("g", 8)),
+        ],
+    );
+
+    let mut replacer = PseudoRegisterReplacer::create().with_symbols(symbols);
+    let Ok(function) = replacer.fold_fun_def(function) else {
+        panic!("replacer should succeed");
+    };
+
+    // the structure takes 16 bytes and is 8-byte aligned: it starts at -24
+    assert_eq!(
+        function.instructions[1],
+        AsmInstruction::Mov(
+            AsmType::Quadword,
+            AsmOperand::Imm(2),
+            AsmOperand::Stack(-16)
+        )
+    );
+    // members of static structures are addressed relative to their label
+    assert_eq!(
+        function.instructions[2],
+        AsmInstruction::Mov(
+            AsmType::Quadword,
+            AsmOperand::Imm(3),
+            AsmOperand::Data(
+                AsmIdentifier {
+                    value: "g".to_string(),
+                },
+                8
+            )
+        )
+    );
+    assert_eq!(replacer.last_offset(), -24);
+}
@@ -1,4 +1,5 @@
-use fcc::lexer::{Token, lex};
+use fcc::common::error::{CompileError, LexError};
+use fcc::lexer::{Token, lex, lex_spanned};
 
 #[test]
 fn test_empty_input() {
@@ -239,8 +240,11 @@ fn test_operator_precedence_tokens() {
 fn test_invalid_character() {
     let result = lex("@");
     assert!(result.is_err());
-    if let Err(error) = result {
-        assert_eq!(error, "couldn't find any match");
+    if let Err(CompileError::Lex(error, span)) = result {
+        assert_eq!(error, LexError::UnexpectedCharacter('@'));
+        assert_eq!((span.line, span.column), (1, 1));
+    } else {
+        panic!("Expected a lexer error");
     }
 }
 
@@ -501,18 +505,15 @@ fn test_consecutive_shift_no_space() {
 
 #[test]
 fn test_triple_plus() {
-    // a+++b should tokenize as a, ++, +, b or a, +, +, +, b depending on lexer
-    // Since we have Decrement (--) but no Increment (++), this should be +, +, +
+    // the longest match wins, so a+++b is a, ++, +, b
     let result = lex("a+++b");
     assert!(result.is_ok());
     if let Ok(tokens) = result {
-        // Without ++ token, should be: a, +, +, +, b
         assert_eq!(
             tokens,
             vec![
                 Token::Identifier("a".to_string()),
-                Token::Add,
-                Token::Add,
+                Token::Increment,
                 Token::Add,
                 Token::Identifier("b".to_string())
             ]
@@ -730,3 +731,631 @@ fn test_for_identifier_not_keyword() {
         assert_eq!(tokens, vec![Token::Identifier("format".to_string())]);
     }
 }
+
+// =============================================================================
+// FUNCTIONS
+// =============================================================================
+
+#[test]
+fn test_comma() {
+    let result = lex(",");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(tokens, vec![Token::Comma]);
+    }
+}
+
+#[test]
+fn test_function_call() {
+    let result = lex("foo(a, 1)");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("foo".to_string()),
+                Token::OpenParen,
+                Token::Identifier("a".to_string()),
+                Token::Comma,
+                Token::Constant("1".to_string()),
+                Token::CloseParen,
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// STORAGE CLASS KEYWORDS
+// =============================================================================
+
+#[test]
+fn test_storage_class_keywords() {
+    let result = lex("static extern");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(tokens, vec![Token::Static, Token::Extern]);
+    }
+}
+
+#[test]
+fn test_storage_class_keyword_prefix_is_identifier() {
+    let result = lex("statics externs");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("statics".to_string()),
+                Token::Identifier("externs".to_string()),
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// LONG INTEGERS
+// =============================================================================
+
+#[test]
+fn test_long_keyword() {
+    let result = lex("long int");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(tokens, vec![Token::Long, Token::Int]);
+    }
+}
+
+#[test]
+fn test_long_constant_suffix() {
+    let result = lex("123L 456l 789");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::LongConstant("123".to_string()),
+                Token::LongConstant("456".to_string()),
+                Token::Constant("789".to_string()),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_long_constant_invalid_suffix() {
+    // the two letters of `ll` must have the same case
+    assert!(lex("123lL").is_err());
+    assert!(lex("123lll").is_err());
+}
+
+// =============================================================================
+// UNSIGNED INTEGERS
+// =============================================================================
+
+#[test]
+fn test_signed_unsigned_keywords() {
+    let result = lex("signed unsigned");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(tokens, vec![Token::Signed, Token::Unsigned]);
+    }
+}
+
+#[test]
+fn test_unsigned_constant_suffixes() {
+    let result = lex("1u 2U 3ul 4LU 5uL");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::UnsignedConstant("1".to_string()),
+                Token::UnsignedConstant("2".to_string()),
+                Token::UnsignedLongConstant("3".to_string()),
+                Token::UnsignedLongConstant("4".to_string()),
+                Token::UnsignedLongConstant("5".to_string()),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_unsigned_constant_invalid_suffix() {
+    assert!(lex("1uu").is_err());
+    assert!(lex("1ulu").is_err());
+}
+
+// =============================================================================
+// FLOATING-POINT NUMBERS
+// =============================================================================
+
+#[test]
+fn test_double_keyword() {
+    let result = lex("double d");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![Token::Double, Token::Identifier("d".to_string())]
+        );
+    }
+}
+
+#[test]
+fn test_double_constants() {
+    let result = lex("1.0 .5 3. 1e10 2.5E-3 .1e+2 4.e1");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            ["1.0", ".5", "3.", "1e10", "2.5E-3", ".1e+2", "4.e1"]
+                .iter()
+                .map(|s| Token::DoubleConstant(s.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn test_double_constant_followed_by_operator() {
+    let result = lex("1.5+x;");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::DoubleConstant("1.5".to_string()),
+                Token::Add,
+                Token::Identifier("x".to_string()),
+                Token::Semicolon,
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_double_constant_invalid() {
+    assert!(lex("1.0.0").is_err());
+    assert!(lex("1e").is_err());
+    assert!(lex("1.5x").is_err());
+    assert!(lex("1..2").is_err());
+}
+
+#[test]
+fn test_brackets() {
+    let result = lex("a[1][i]");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::OpenBracket,
+                Token::Constant("1".to_string()),
+                Token::CloseBracket,
+                Token::OpenBracket,
+                Token::Identifier("i".to_string()),
+                Token::CloseBracket,
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// CHARACTERS AND STRINGS
+// =============================================================================
+
+#[test]
+fn test_char_constants_and_string_literals() {
+    let result = lex(r#"char c = '\n'; puts("a \"b\"\t" "c");"#);
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        // the quotes are dropped, escape sequences are kept as they are
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Char,
+                Token::Identifier("c".to_string()),
+                Token::Assignment,
+                Token::CharConstant(r"\n".to_string()),
+                Token::Semicolon,
+                Token::Identifier("puts".to_string()),
+                Token::OpenParen,
+                Token::StringLiteral(r#"a \"b\"\t"#.to_string()),
+                Token::StringLiteral("c".to_string()),
+                Token::CloseParen,
+                Token::Semicolon,
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_char_constants_and_string_literals_invalid() {
+    assert!(lex("''").is_err());
+    assert!(lex(r"'\'").is_err());
+    assert!(lex(r"'\q'").is_err());
+    assert!(lex("\"unterminated").is_err());
+    assert!(lex("\"new\nline\"").is_err());
+    assert!(lex(r#""\xg""#).is_err());
+}
+
+// =============================================================================
+// STRUCTURES
+// =============================================================================
+
+#[test]
+fn test_structure_tokens() {
+    let result = lex("struct s x; p->a.b = .5;");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        // a dot followed by a digit is still a floating-point constant
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Struct,
+                Token::Identifier("s".to_string()),
+                Token::Identifier("x".to_string()),
+                Token::Semicolon,
+                Token::Identifier("p".to_string()),
+                Token::Arrow,
+                Token::Identifier("a".to_string()),
+                Token::Dot,
+                Token::Identifier("b".to_string()),
+                Token::Assignment,
+                Token::DoubleConstant(".5".to_string()),
+                Token::Semicolon,
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// GOTO AND LABELED STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_goto_and_labels() {
+    let result = lex("goto end; end: gotox;");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Goto,
+                Token::Identifier("end".to_string()),
+                Token::Semicolon,
+                Token::Identifier("end".to_string()),
+                Token::DoubleDot,
+                Token::Identifier("gotox".to_string()),
+                Token::Semicolon,
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// SWITCH STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_switch_case_default() {
+    let result = lex("switch (x) { case 1: default: ; } defaults");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Switch,
+                Token::OpenParen,
+                Token::Identifier("x".to_string()),
+                Token::CloseParen,
+                Token::OpenBrace,
+                Token::Case,
+                Token::Constant("1".to_string()),
+                Token::DoubleDot,
+                Token::Default,
+                Token::DoubleDot,
+                Token::Semicolon,
+                Token::CloseBrace,
+                Token::Identifier("defaults".to_string()),
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// COMPOUND ASSIGNMENT AND INCREMENT
+// =============================================================================
+
+#[test]
+fn test_compound_assignment_operators() {
+    let result = lex("+= -= *= /= %= &= |= ^= <<= >>=");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::AddAssign,
+                Token::SubtractAssign,
+                Token::MultiplyAssign,
+                Token::DivideAssign,
+                Token::RemainderAssign,
+                Token::BitwiseAndAssign,
+                Token::BitwiseOrAssign,
+                Token::BitwiseXorAssign,
+                Token::LeftShiftAssign,
+                Token::RightShiftAssign,
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_increment_decrement_longest_match() {
+    // `a---b` is `a-- - b`
+    let result = lex("a---b ++x");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier("a".to_string()),
+                Token::Decrement,
+                Token::Negate,
+                Token::Identifier("b".to_string()),
+                Token::Increment,
+                Token::Identifier("x".to_string()),
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// ENUMERATIONS
+// =============================================================================
+
+#[test]
+fn test_enum_keyword() {
+    let result = lex("enum color { RED = 1, enumerator };");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Enum,
+                Token::Identifier("color".to_string()),
+                Token::OpenBrace,
+                Token::Identifier("RED".to_string()),
+                Token::Assignment,
+                Token::Constant("1".to_string()),
+                Token::Comma,
+                Token::Identifier("enumerator".to_string()),
+                Token::CloseBrace,
+                Token::Semicolon,
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// TYPEDEFS
+// =============================================================================
+
+#[test]
+fn test_typedef_keyword() {
+    let result = lex("typedef int typedefs;");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Typedef,
+                Token::Int,
+                Token::Identifier("typedefs".to_string()),
+                Token::Semicolon,
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// VARIADIC FUNCTIONS
+// =============================================================================
+
+#[test]
+fn test_ellipsis_and_dots() {
+    let result = lex("int f(int n, ...); s.a");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Int,
+                Token::Identifier("f".to_string()),
+                Token::OpenParen,
+                Token::Int,
+                Token::Identifier("n".to_string()),
+                Token::Comma,
+                Token::Ellipsis,
+                Token::CloseParen,
+                Token::Semicolon,
+                Token::Identifier("s".to_string()),
+                Token::Dot,
+                Token::Identifier("a".to_string()),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_va_builtin_keywords() {
+    let result = lex(
+        "__builtin_va_list __builtin_va_start __builtin_va_arg __builtin_va_end \
+         __builtin_va_copy __builtin_va_lists",
+    );
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::VaList,
+                Token::VaStart,
+                Token::VaArg,
+                Token::VaEnd,
+                Token::VaCopy,
+                Token::Identifier("__builtin_va_lists".to_string()),
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// INTEGER CONSTANT FORMS
+// =============================================================================
+
+#[test]
+fn test_hex_octal_binary_constants() {
+    let result = lex("0x1F 0XaB 017 0 0b101 0B0");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            ["0x1F", "0XaB", "017", "0", "0b101", "0B0"].map(|n| Token::Constant(n.to_string()))
+        );
+    }
+}
+
+#[test]
+fn test_integer_constant_suffix_combinations() {
+    let result = lex("1ll 0x2LL 3ull 0b100LLu 5Ull 06lu 0xFu");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::LongConstant("1".to_string()),
+                Token::LongConstant("0x2".to_string()),
+                Token::UnsignedLongConstant("3".to_string()),
+                Token::UnsignedLongConstant("0b100".to_string()),
+                Token::UnsignedLongConstant("5".to_string()),
+                Token::UnsignedLongConstant("06".to_string()),
+                Token::UnsignedConstant("0xF".to_string()),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_invalid_integer_constant_forms() {
+    for src in ["0x", "0xG", "0b", "0b12", "0x1.5", "1uLl"] {
+        assert!(lex(src).is_err(), "Should fail to lex: {src}");
+    }
+}
+
+#[test]
+fn test_hex_escape_in_char_and_string() {
+    let result = lex(r#"'\x41' "\x7e!""#);
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::CharConstant(r"\x41".to_string()),
+                Token::StringLiteral(r"\x7e!".to_string()),
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// TYPE QUALIFIERS
+// =============================================================================
+
+#[test]
+fn test_qualifier_keywords() {
+    let result = lex("const volatile int *const constant");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Const,
+                Token::Volatile,
+                Token::Int,
+                Token::Multiply,
+                Token::Const,
+                Token::Identifier("constant".to_string()),
+            ]
+        );
+    }
+}
+
+// =============================================================================
+// LINE MARKERS
+// =============================================================================
+
+#[test]
+fn test_line_markers_are_skipped() {
+    let result = lex("# 1 \"main.c\"\nint x;\n# 12 \"inc/a \\\"b\\\".h\" 2\n  #3\nreturn");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Int,
+                Token::Identifier("x".to_string()),
+                Token::Semicolon,
+                Token::Return,
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_other_directives_are_invalid() {
+    assert!(lex("#define X 1\nint x;").is_err());
+    assert!(lex("int x; # y").is_err());
+}
+
+// =============================================================================
+// SPANS
+// =============================================================================
+
+#[test]
+fn test_spans_of_tokens() {
+    let result = lex_spanned("int x;\n  return 42;");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        let spans = tokens
+            .iter()
+            .map(|t| (t.span.line, t.span.column, t.span.start, t.span.end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            vec![
+                (1, 1, 0, 3),
+                (1, 5, 4, 5),
+                (1, 6, 5, 6),
+                (2, 3, 9, 15),
+                (2, 10, 16, 18),
+                (2, 12, 18, 19),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_spans_follow_line_markers() {
+    let result = lex_spanned("# 1 \"main.c\"\nint x;\n# 7 \"inc/a.h\"\n\nlong y;");
+    assert!(result.is_ok());
+    if let Ok(tokens) = result {
+        let x = &tokens[1];
+        assert_eq!(x.token, Token::Identifier("x".to_string()));
+        assert_eq!(x.span.to_string(), "main.c:1:5");
+
+        let y = &tokens[4];
+        assert_eq!(y.token, Token::Identifier("y".to_string()));
+        assert_eq!(y.span.to_string(), "inc/a.h:8:6");
+    }
+}
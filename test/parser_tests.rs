@@ -1,21 +1,40 @@
 /*!
 This file covers: Parser (happy/error paths), precedence, if/else, ternary, unary ops.
+Also covers compound assignment, `++`/`--`, the comma operator and declaration lists.
+Also covers enumeration declarations and specifiers.
+Also covers typedef names: scopes, shadowing and `foo * bar;` ambiguity.
+Also covers function pointer declarators, abstract function declarators and indirect calls.
+Also covers variadic parameter lists, `__builtin_va_list` and the `__builtin_va_*` builtins.
+Also covers hexadecimal, octal and binary constants, `ll` suffixes and `\x` escapes.
+Also covers the `const` and `volatile` qualifiers in specifiers, declarators and casts.
+Also covers the spans of declarations, statements and expressions.
 Does NOT cover: pretty print / display, exact error messages.
 Suggestions: add property tests if proptest is incorporated.
 */
 
-use fcc::c_ast::ast::{BinaryOperator, BlockItem, Expression, Program, Statement, UnaryOperator};
-use fcc::lexer::lex;
+use fcc::c_ast::{
+    ast::{
+        BinaryOperator, BlockItem, Const, Declaration, Expression, Initializer, Program,
+        Qualifiers, Statement, StorageClass, Type, UnaryOperator,
+    },
+    semantic::type_table::VA_LIST_TAG,
+};
+use fcc::common::error::{CompileError, Construct, ParseError};
+use fcc::lexer::{Token, lex, lex_spanned};
 
 // Helper: lex and parse source code into a Program
-fn parse_program(src: &str) -> Result<Program, String> {
+fn parse_program(src: &str) -> Result<Program, CompileError> {
     let tokens = lex(src)?;
     Program::try_from(tokens)
 }
 
 // Helper: get block items as a vector from a parsed program
 fn get_body_items(program: &Program) -> Vec<&BlockItem> {
-    program.function_definition().body().iter().collect()
+    program
+        .function_definition("main")
+        .and_then(|main| main.body())
+        .map(|body| body.iter().collect())
+        .unwrap_or_default()
 }
 
 // =============================================================================
@@ -27,13 +46,20 @@ fn test_parser_simple_return() {
     let src = "int main(void){ return 0; }";
     let program = parse_program(src).expect("should parse");
 
-    assert_eq!(program.function_definition().name().value(), "main");
+    assert_eq!(
+        program
+            .function_definition("main")
+            .expect("main is defined")
+            .name()
+            .value(),
+        "main"
+    );
 
     let items = get_body_items(&program);
     assert_eq!(items.len(), 1);
 
     match items[0] {
-        BlockItem::S(Statement::Return(Expression::Constant(0))) => {}
+        BlockItem::S(Statement::Return(Expression::Constant(Const::ConstInt(0), _), _)) => {}
         _ => panic!("Expected return 0 statement"),
     }
 }
@@ -47,7 +73,7 @@ fn test_parser_decl_without_initializer() {
     assert_eq!(items.len(), 2);
 
     match items[0] {
-        BlockItem::D(decl) => {
+        BlockItem::D(Declaration::VarDecl(decl)) => {
             assert_eq!(decl.name().value(), "x");
             assert!(decl.initializer().is_none());
         }
@@ -64,15 +90,21 @@ fn test_parser_decl_with_initializer() {
     assert_eq!(items.len(), 2);
 
     match items[0] {
-        BlockItem::D(decl) => {
+        BlockItem::D(Declaration::VarDecl(decl)) => {
             assert_eq!(decl.name().value(), "x");
-            assert!(matches!(decl.initializer(), Some(Expression::Constant(1))));
+            assert!(matches!(
+                decl.initializer(),
+                Some(Initializer::Single(Expression::Constant(
+                    Const::ConstInt(1),
+                    _
+                )))
+            ));
         }
         _ => panic!("Expected declaration with initializer"),
     }
 
     match items[1] {
-        BlockItem::S(Statement::Return(Expression::Var(id))) => {
+        BlockItem::S(Statement::Return(Expression::Var(id, _), _)) => {
             assert_eq!(id.value(), "x");
         }
         _ => panic!("Expected return x statement"),
@@ -88,9 +120,9 @@ fn test_parser_if_without_else() {
     assert_eq!(items.len(), 3);
 
     match items[1] {
-        BlockItem::S(Statement::If(cond, then_branch, else_branch)) => {
-            assert!(matches!(**cond, Expression::Var(_)));
-            assert!(matches!(**then_branch, Statement::Return(_)));
+        BlockItem::S(Statement::If(cond, then_branch, else_branch, _)) => {
+            assert!(matches!(**cond, Expression::Var(_, _)));
+            assert!(matches!(**then_branch, Statement::Return(_, _)));
             assert!(else_branch.is_none());
         }
         _ => panic!("Expected if statement without else"),
@@ -106,10 +138,10 @@ fn test_parser_if_with_else() {
     assert_eq!(items.len(), 2);
 
     match items[1] {
-        BlockItem::S(Statement::If(_, _, else_branch)) => {
+        BlockItem::S(Statement::If(_, _, else_branch, _)) => {
             assert!(else_branch.is_some());
             match else_branch.as_ref().map(|b| b.as_ref()) {
-                Some(Statement::Return(Expression::Constant(2))) => {}
+                Some(Statement::Return(Expression::Constant(Const::ConstInt(2), _), _)) => {}
                 _ => panic!("Expected else branch with return 2"),
             }
         }
@@ -127,13 +159,13 @@ fn test_parser_dangling_else() {
 
     // Find the outer if
     match items[1] {
-        BlockItem::S(Statement::If(_, then_branch, outer_else)) => {
+        BlockItem::S(Statement::If(_, then_branch, outer_else, _)) => {
             // Outer if should NOT have else (dangling else goes to inner)
             assert!(outer_else.is_none(), "Outer if should not have else");
 
             // Inner if should have else
             match then_branch.as_ref() {
-                Statement::If(_, _, inner_else) => {
+                Statement::If(_, _, inner_else, _) => {
                     assert!(inner_else.is_some(), "Inner if should have else");
                 }
                 _ => panic!("Expected inner if statement"),
@@ -151,10 +183,19 @@ fn test_parser_ternary() {
     let items = get_body_items(&program);
 
     match items[1] {
-        BlockItem::S(Statement::Return(Expression::Conditional(cond, then_expr, else_expr))) => {
-            assert!(matches!(**cond, Expression::Var(_)));
-            assert!(matches!(**then_expr, Expression::Constant(1)));
-            assert!(matches!(**else_expr, Expression::Constant(2)));
+        BlockItem::S(Statement::Return(
+            Expression::Conditional(cond, then_expr, else_expr, _),
+            _,
+        )) => {
+            assert!(matches!(**cond, Expression::Var(_, _)));
+            assert!(matches!(
+                **then_expr,
+                Expression::Constant(Const::ConstInt(1), _)
+            ));
+            assert!(matches!(
+                **else_expr,
+                Expression::Constant(Const::ConstInt(2), _)
+            ));
         }
         _ => panic!("Expected return with ternary expression"),
     }
@@ -169,13 +210,19 @@ fn test_parser_precedence_mult_before_add() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::Return(Expression::Binary(BinaryOperator::Add, left, right))) => {
-            assert!(matches!(**left, Expression::Constant(1)));
+        BlockItem::S(Statement::Return(
+            Expression::Binary(BinaryOperator::Add, left, right, _),
+            _,
+        )) => {
+            assert!(matches!(
+                **left,
+                Expression::Constant(Const::ConstInt(1), _)
+            ));
             // right should be 2 * 3
             match right.as_ref() {
-                Expression::Binary(BinaryOperator::Multiply, l, r) => {
-                    assert!(matches!(**l, Expression::Constant(2)));
-                    assert!(matches!(**r, Expression::Constant(3)));
+                Expression::Binary(BinaryOperator::Multiply, l, r, _) => {
+                    assert!(matches!(**l, Expression::Constant(Const::ConstInt(2), _)));
+                    assert!(matches!(**r, Expression::Constant(Const::ConstInt(3), _)));
                 }
                 _ => panic!("Expected multiplication on right side"),
             }
@@ -193,20 +240,22 @@ fn test_parser_precedence_parens() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::Return(Expression::Binary(
-            BinaryOperator::Multiply,
-            left,
-            right,
-        ))) => {
+        BlockItem::S(Statement::Return(
+            Expression::Binary(BinaryOperator::Multiply, left, right, _),
+            _,
+        )) => {
             // left should be 1 + 2
             match left.as_ref() {
-                Expression::Binary(BinaryOperator::Add, l, r) => {
-                    assert!(matches!(**l, Expression::Constant(1)));
-                    assert!(matches!(**r, Expression::Constant(2)));
+                Expression::Binary(BinaryOperator::Add, l, r, _) => {
+                    assert!(matches!(**l, Expression::Constant(Const::ConstInt(1), _)));
+                    assert!(matches!(**r, Expression::Constant(Const::ConstInt(2), _)));
                 }
                 _ => panic!("Expected addition on left side"),
             }
-            assert!(matches!(**right, Expression::Constant(3)));
+            assert!(matches!(
+                **right,
+                Expression::Constant(Const::ConstInt(3), _)
+            ));
         }
         _ => panic!("Expected return with binary expression"),
     }
@@ -220,8 +269,8 @@ fn test_parser_unary_negate() {
     let items = get_body_items(&program);
 
     match items[1] {
-        BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Negate, inner))) => {
-            assert!(matches!(**inner, Expression::Var(_)));
+        BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Negate, inner, _), _)) => {
+            assert!(matches!(**inner, Expression::Var(_, _)));
         }
         _ => panic!("Expected return with unary negate"),
     }
@@ -235,7 +284,7 @@ fn test_parser_unary_complement() {
     let items = get_body_items(&program);
 
     match items[1] {
-        BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Complement, _))) => {}
+        BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Complement, _, _), _)) => {}
         _ => panic!("Expected return with unary complement"),
     }
 }
@@ -248,7 +297,7 @@ fn test_parser_unary_not() {
     let items = get_body_items(&program);
 
     match items[1] {
-        BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Not, _))) => {}
+        BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Not, _, _), _)) => {}
         _ => panic!("Expected return with unary not"),
     }
 }
@@ -261,10 +310,10 @@ fn test_parser_unary_chained() {
     let items = get_body_items(&program);
 
     match items[1] {
-        BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Negate, inner))) => {
+        BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Negate, inner, _), _)) => {
             assert!(matches!(
                 **inner,
-                Expression::Unary(UnaryOperator::Complement, _)
+                Expression::Unary(UnaryOperator::Complement, _, _)
             ));
         }
         _ => panic!("Expected return with chained unary operators"),
@@ -279,7 +328,7 @@ fn test_parser_logical_and() {
     let items = get_body_items(&program);
 
     match items[2] {
-        BlockItem::S(Statement::Return(Expression::Binary(BinaryOperator::And, _, _))) => {}
+        BlockItem::S(Statement::Return(Expression::Binary(BinaryOperator::And, _, _, _), _)) => {}
         _ => panic!("Expected return with AND expression"),
     }
 }
@@ -292,7 +341,7 @@ fn test_parser_logical_or() {
     let items = get_body_items(&program);
 
     match items[2] {
-        BlockItem::S(Statement::Return(Expression::Binary(BinaryOperator::Or, _, _))) => {}
+        BlockItem::S(Statement::Return(Expression::Binary(BinaryOperator::Or, _, _, _), _)) => {}
         _ => panic!("Expected return with OR expression"),
     }
 }
@@ -322,7 +371,7 @@ fn test_parser_relational_operators() {
         let items = get_body_items(&program);
 
         match items[0] {
-            BlockItem::S(Statement::Return(Expression::Binary(op, _, _))) => {
+            BlockItem::S(Statement::Return(Expression::Binary(op, _, _, _), _)) => {
                 assert!(
                     std::mem::discriminant(op) == std::mem::discriminant(&expected_op),
                     "Expected {:?}, got {:?}",
@@ -394,9 +443,9 @@ fn test_parser_while_statement() {
     assert_eq!(items.len(), 3);
 
     match items[1] {
-        BlockItem::S(Statement::While(cond, body, _label)) => {
-            assert!(matches!(**cond, Expression::Var(_)));
-            assert!(matches!(**body, Statement::Expression(_)));
+        BlockItem::S(Statement::While(cond, body, _label, _)) => {
+            assert!(matches!(**cond, Expression::Var(_, _)));
+            assert!(matches!(**body, Statement::Expression(_, _)));
         }
         _ => panic!("Expected while statement"),
     }
@@ -410,8 +459,8 @@ fn test_parser_while_with_block() {
     let items = get_body_items(&program);
 
     match items[1] {
-        BlockItem::S(Statement::While(_, body, _)) => {
-            assert!(matches!(**body, Statement::Compound(_)));
+        BlockItem::S(Statement::While(_, body, _, _)) => {
+            assert!(matches!(**body, Statement::Compound(_, _)));
         }
         _ => panic!("Expected while statement with block body"),
     }
@@ -426,11 +475,11 @@ fn test_parser_do_while_statement() {
     assert_eq!(items.len(), 3);
 
     match items[1] {
-        BlockItem::S(Statement::DoWhile(body, cond, _label)) => {
-            assert!(matches!(**body, Statement::Expression(_)));
+        BlockItem::S(Statement::DoWhile(body, cond, _label, _)) => {
+            assert!(matches!(**body, Statement::Expression(_, _)));
             assert!(matches!(
                 **cond,
-                Expression::Binary(BinaryOperator::LessThan, _, _)
+                Expression::Binary(BinaryOperator::LessThan, _, _, _)
             ));
         }
         _ => panic!("Expected do-while statement"),
@@ -445,8 +494,8 @@ fn test_parser_do_while_with_block() {
     let items = get_body_items(&program);
 
     match items[1] {
-        BlockItem::S(Statement::DoWhile(body, _, _)) => {
-            assert!(matches!(**body, Statement::Compound(_)));
+        BlockItem::S(Statement::DoWhile(body, _, _, _)) => {
+            assert!(matches!(**body, Statement::Compound(_, _)));
         }
         _ => panic!("Expected do-while statement with block body"),
     }
@@ -460,7 +509,7 @@ fn test_parser_for_statement_full() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::For(init, cond, post, body, _label)) => {
+        BlockItem::S(Statement::For(init, cond, post, body, _label, _)) => {
             // init should be a declaration
             assert!(matches!(**init, fcc::c_ast::ast::ForInit::InitDecl(_)));
             // cond should be present
@@ -468,7 +517,7 @@ fn test_parser_for_statement_full() {
             // post should be present
             assert!(post.is_some());
             // body
-            assert!(matches!(**body, Statement::Return(_)));
+            assert!(matches!(**body, Statement::Return(_, _)));
         }
         _ => panic!("Expected for statement"),
     }
@@ -482,7 +531,7 @@ fn test_parser_for_statement_empty_init() {
     let items = get_body_items(&program);
 
     match items[1] {
-        BlockItem::S(Statement::For(init, cond, post, _, _)) => {
+        BlockItem::S(Statement::For(init, cond, post, _, _, _)) => {
             // init should be empty expression
             match init.as_ref() {
                 fcc::c_ast::ast::ForInit::InitExp(exp) => {
@@ -505,7 +554,7 @@ fn test_parser_for_statement_empty_cond() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::For(_, cond, post, _, _)) => {
+        BlockItem::S(Statement::For(_, cond, post, _, _, _)) => {
             assert!(cond.is_none(), "Condition should be None");
             assert!(post.is_some());
         }
@@ -521,7 +570,7 @@ fn test_parser_for_statement_empty_post() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::For(_, cond, post, _, _)) => {
+        BlockItem::S(Statement::For(_, cond, post, _, _, _)) => {
             assert!(cond.is_some());
             assert!(post.is_none(), "Post expression should be None");
         }
@@ -537,7 +586,7 @@ fn test_parser_for_statement_infinite() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::For(init, cond, post, _, _)) => {
+        BlockItem::S(Statement::For(init, cond, post, _, _, _)) => {
             match init.as_ref() {
                 fcc::c_ast::ast::ForInit::InitExp(exp) => {
                     assert!(exp.is_none(), "Init should be None");
@@ -559,8 +608,8 @@ fn test_parser_break_statement() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::While(_, body, _)) => {
-            assert!(matches!(**body, Statement::Break(_)));
+        BlockItem::S(Statement::While(_, body, _, _)) => {
+            assert!(matches!(**body, Statement::Break(_, _)));
         }
         _ => panic!("Expected while with break"),
     }
@@ -574,8 +623,8 @@ fn test_parser_continue_statement() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::While(_, body, _)) => {
-            assert!(matches!(**body, Statement::Continue(_)));
+        BlockItem::S(Statement::While(_, body, _, _)) => {
+            assert!(matches!(**body, Statement::Continue(_, _)));
         }
         _ => panic!("Expected while with continue"),
     }
@@ -589,8 +638,8 @@ fn test_parser_nested_loops() {
     let items = get_body_items(&program);
 
     match items[0] {
-        BlockItem::S(Statement::While(_, body, _)) => {
-            assert!(matches!(**body, Statement::While(_, _, _)));
+        BlockItem::S(Statement::While(_, body, _, _)) => {
+            assert!(matches!(**body, Statement::While(_, _, _, _)));
         }
         _ => panic!("Expected nested while statements"),
     }
@@ -630,3 +679,1997 @@ fn test_parser_error_for_missing_semicolons() {
     let result = parse_program(src);
     assert!(result.is_err(), "Should fail without semicolons in for");
 }
+
+// =============================================================================
+// FUNCTIONS
+// =============================================================================
+
+#[test]
+fn test_parser_function_params_and_call() {
+    let src = "int add(int a, int b) { return a + b; } int main(void){ return add(1, 2); }";
+    let program = parse_program(src).expect("should parse");
+
+    assert_eq!(program.functions().len(), 2);
+    let params: Vec<_> = program.functions()[0]
+        .params()
+        .iter()
+        .map(|p| p.value())
+        .collect();
+    assert_eq!(params, vec!["a", "b"]);
+
+    let items = get_body_items(&program);
+    match items[0] {
+        BlockItem::S(Statement::Return(Expression::FunctionCall(name, args, _), _)) => {
+            assert_eq!(name.value(), "add");
+            assert_eq!(args.len(), 2);
+        }
+        _ => panic!("Expected return with function call"),
+    }
+}
+
+#[test]
+fn test_parser_function_prototype() {
+    let src = "int foo(int x); int main(void){ return foo(3); }";
+    let program = parse_program(src).expect("should parse");
+
+    assert!(program.functions()[0].body().is_none());
+    assert!(program.function_definition("foo").is_none());
+}
+
+#[test]
+fn test_parser_local_function_declaration() {
+    let src = "int main(void){ int foo(void); return foo(); }";
+    let program = parse_program(src).expect("should parse");
+
+    let items = get_body_items(&program);
+    assert!(matches!(items[0], BlockItem::D(Declaration::FunDecl(_))));
+}
+
+#[test]
+fn test_parser_error_missing_comma_in_args() {
+    let src = "int main(void){ return foo(1 2); }";
+    let result = parse_program(src);
+    assert!(
+        result.is_err(),
+        "Should fail without comma between arguments"
+    );
+}
+
+#[test]
+fn test_parser_error_function_in_for_init() {
+    let src = "int main(void){ for (int f(void); ;) return 0; }";
+    let result = parse_program(src);
+    assert!(
+        result.is_err(),
+        "Should fail with a function declaration in for init"
+    );
+}
+
+// =============================================================================
+// FILE SCOPE DECLARATIONS AND STORAGE CLASSES
+// =============================================================================
+
+#[test]
+fn test_parser_file_scope_variable() {
+    let src = "int x = 3; int main(void){ return x; }";
+    let program = parse_program(src).expect("should parse");
+
+    assert_eq!(program.declarations().len(), 2);
+    match &program.declarations()[0] {
+        Declaration::VarDecl(decl) => {
+            assert_eq!(decl.name().value(), "x");
+            assert!(matches!(
+                decl.initializer(),
+                Some(Initializer::Single(Expression::Constant(
+                    Const::ConstInt(3),
+                    _
+                )))
+            ));
+            assert!(decl.storage_class().is_none());
+        }
+        _ => panic!("Expected file scope variable declaration"),
+    }
+}
+
+#[test]
+fn test_parser_storage_class_specifiers() {
+    let src = "static int x; int extern y; extern int foo(void); int main(void){ static int z = 1; return 0; }";
+    let program = parse_program(src).expect("should parse");
+
+    let declarations = program.declarations();
+    assert!(matches!(
+        &declarations[0],
+        Declaration::VarDecl(decl) if decl.storage_class() == Some(&StorageClass::Static)
+    ));
+    assert!(matches!(
+        &declarations[1],
+        Declaration::VarDecl(decl) if decl.storage_class() == Some(&StorageClass::Extern)
+    ));
+    assert!(matches!(
+        &declarations[2],
+        Declaration::FunDecl(decl) if decl.storage_class() == Some(&StorageClass::Extern)
+    ));
+
+    let items = get_body_items(&program);
+    assert!(matches!(
+        items[0],
+        BlockItem::D(Declaration::VarDecl(decl)) if decl.storage_class() == Some(&StorageClass::Static)
+    ));
+}
+
+#[test]
+fn test_parser_error_multiple_storage_classes() {
+    let src = "static extern int x; int main(void){ return 0; }";
+    let result = parse_program(src);
+    assert!(result.is_err(), "Should fail with two storage classes");
+}
+
+#[test]
+fn test_parser_error_missing_type_specifier() {
+    let src = "static x; int main(void){ return 0; }";
+    let result = parse_program(src);
+    assert!(result.is_err(), "Should fail without a type specifier");
+}
+
+#[test]
+fn test_parser_error_repeated_type_specifier() {
+    let src = "int static int x; int main(void){ return 0; }";
+    let result = parse_program(src);
+    assert!(
+        result.is_err(),
+        "Should fail with a repeated type specifier"
+    );
+}
+
+#[test]
+fn test_parser_error_storage_class_in_for_init() {
+    let src = "int main(void){ for (static int i = 0; i < 3; i = i + 1) ; return 0; }";
+    let result = parse_program(src);
+    assert!(
+        result.is_err(),
+        "Should fail with a storage class in for init"
+    );
+}
+
+// =============================================================================
+// LONG INTEGERS
+// =============================================================================
+
+#[test]
+fn test_parser_long_declarations() {
+    let src = "long a; long int b; int long c; static long d; int main(void){ return 0; }";
+    let program = parse_program(src).expect("should parse");
+
+    for declaration in &program.declarations()[..4] {
+        match declaration {
+            Declaration::VarDecl(decl) => assert_eq!(decl.var_type(), &Type::Long),
+            _ => panic!("Expected variable declaration"),
+        }
+    }
+}
+
+#[test]
+fn test_parser_long_function_type() {
+    let src = "long foo(int a, long b);";
+    let program = parse_program(src).expect("should parse");
+
+    match &program.declarations()[0] {
+        Declaration::FunDecl(decl) => assert_eq!(
+            decl.fun_type(),
+            &Type::FunType(vec![Type::Int, Type::Long], Box::new(Type::Long), false)
+        ),
+        _ => panic!("Expected function declaration"),
+    }
+}
+
+#[test]
+fn test_parser_cast() {
+    let src = "int main(void){ return (long) 1; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    assert!(matches!(
+        items[0],
+        BlockItem::S(Statement::Return(Expression::Cast(Type::Long, _, _), _))
+    ));
+}
+
+#[test]
+fn test_parser_long_constants() {
+    let src = "int main(void){ return 2147483648 + 1L + 2147483647; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    // constants too large for an int become longs
+    let BlockItem::S(Statement::Return(Expression::Binary(_, left, right, _), _)) = items[0] else {
+        panic!("Expected return of a binary expression");
+    };
+    assert!(matches!(
+        right.as_ref(),
+        Expression::Constant(Const::ConstInt(2147483647), _)
+    ));
+    assert!(matches!(
+        left.as_ref(),
+        Expression::Binary(_, l, r, _)
+            if matches!(l.as_ref(), Expression::Constant(Const::ConstLong(2147483648), _))
+                && matches!(r.as_ref(), Expression::Constant(Const::ConstLong(1), _))
+    ));
+}
+
+#[test]
+fn test_parser_error_constant_too_large() {
+    let src = "int main(void){ return 9223372036854775808; }";
+    let result = parse_program(src);
+    assert!(result.is_err(), "Should fail with a constant too large");
+}
+
+#[test]
+fn test_parser_error_invalid_type_specifiers() {
+    for src in [
+        "long long x;",
+        "int int x;",
+        "long int long x;",
+        "int main(void){ return (static long) 1; }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail to parse: {src}");
+    }
+}
+
+// =============================================================================
+// UNSIGNED INTEGERS
+// =============================================================================
+
+#[test]
+fn test_parser_unsigned_declarations() {
+    let src = "unsigned a; unsigned int b; long unsigned c; unsigned long int d; signed e; \
+               signed long f;";
+    let program = parse_program(src).expect("should parse");
+    let types: Vec<_> = program
+        .declarations()
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::VarDecl(decl) => decl.var_type().clone(),
+            _ => panic!("Expected variable declaration"),
+        })
+        .collect();
+
+    assert_eq!(
+        types,
+        vec![
+            Type::UInt,
+            Type::UInt,
+            Type::ULong,
+            Type::ULong,
+            Type::Int,
+            Type::Long
+        ]
+    );
+}
+
+#[test]
+fn test_parser_unsigned_constants() {
+    let src = "int main(void){ return 4294967295u + 4294967296u + 1ul; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    // unsigned constants too large for an unsigned int become unsigned longs
+    let BlockItem::S(Statement::Return(Expression::Binary(_, left, right, _), _)) = items[0] else {
+        panic!("Expected return of a binary expression");
+    };
+    assert!(matches!(
+        right.as_ref(),
+        Expression::Constant(Const::ConstULong(1), _)
+    ));
+    assert!(matches!(
+        left.as_ref(),
+        Expression::Binary(_, l, r, _)
+            if matches!(l.as_ref(), Expression::Constant(Const::ConstUInt(4294967295), _))
+                && matches!(r.as_ref(), Expression::Constant(Const::ConstULong(4294967296), _))
+    ));
+}
+
+#[test]
+fn test_parser_unsigned_cast() {
+    let src = "int main(void){ return (unsigned long) 1; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    assert!(matches!(
+        items[0],
+        BlockItem::S(Statement::Return(Expression::Cast(Type::ULong, _, _), _))
+    ));
+}
+
+#[test]
+fn test_parser_error_invalid_signedness() {
+    for src in [
+        "signed unsigned x;",
+        "unsigned unsigned x;",
+        "signed signed int x;",
+        "unsigned long long x;",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail to parse: {src}");
+    }
+}
+
+// =============================================================================
+// FLOATING-POINT NUMBERS
+// =============================================================================
+
+#[test]
+fn test_parser_double_declaration() {
+    let program = parse_program("double d; static double e;").expect("should parse");
+    let types: Vec<_> = program
+        .declarations()
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::VarDecl(decl) => decl.var_type().clone(),
+            _ => panic!("Expected variable declaration"),
+        })
+        .collect();
+
+    assert_eq!(types, vec![Type::Double, Type::Double]);
+}
+
+#[test]
+fn test_parser_double_constants() {
+    let src = "int main(void){ return 1.5 + 1e3; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Return(Expression::Binary(_, left, right, _), _)) = items[0] else {
+        panic!("Expected return of a binary expression");
+    };
+    assert!(matches!(
+        left.as_ref(),
+        Expression::Constant(Const::ConstDouble(1.5), _)
+    ));
+    assert!(matches!(
+        right.as_ref(),
+        Expression::Constant(Const::ConstDouble(1000.0), _)
+    ));
+}
+
+#[test]
+fn test_parser_double_cast() {
+    let src = "int main(void){ return (double) 1; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    assert!(matches!(
+        items[0],
+        BlockItem::S(Statement::Return(Expression::Cast(Type::Double, _, _), _))
+    ));
+}
+
+#[test]
+fn test_parser_error_double_with_other_specifiers() {
+    for src in [
+        "long double x;",
+        "unsigned double x;",
+        "double int x;",
+        "double double x;",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail to parse: {src}");
+    }
+}
+
+// =============================================================================
+// POINTERS
+// =============================================================================
+
+#[test]
+fn test_parser_pointer_declarators() {
+    let program =
+        parse_program("int *p; long **q; double (*d); unsigned int *(u);").expect("should parse");
+    let types: Vec<_> = program
+        .declarations()
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::VarDecl(decl) => decl.var_type().clone(),
+            _ => panic!("Expected variable declaration"),
+        })
+        .collect();
+
+    let ptr = |t: Type| Type::Pointer(Box::new(t));
+    assert_eq!(
+        types,
+        vec![
+            ptr(Type::Int),
+            ptr(ptr(Type::Long)),
+            ptr(Type::Double),
+            ptr(Type::UInt)
+        ]
+    );
+}
+
+#[test]
+fn test_parser_function_returning_pointer() {
+    let program = parse_program("int *f(long *a, int b);").expect("should parse");
+    let Declaration::FunDecl(f) = &program.declarations()[0] else {
+        panic!("Expected function declaration");
+    };
+
+    let ptr = |t: Type| Type::Pointer(Box::new(t));
+    assert_eq!(
+        f.fun_type(),
+        &Type::FunType(
+            vec![ptr(Type::Long), Type::Int],
+            Box::new(ptr(Type::Int)),
+            false
+        )
+    );
+    assert_eq!(f.params().len(), 2);
+}
+
+#[test]
+fn test_parser_dereference_and_address_of() {
+    let src = "int main(void){ int x = 1; int *p = &x; *p = 2; return **&p; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    assert!(matches!(
+        items[2],
+        BlockItem::S(Statement::Expression(Expression::Assignment(left, _, _), _))
+            if matches!(left.as_ref(), Expression::Dereference(_, _))
+    ));
+    let BlockItem::S(Statement::Return(Expression::Dereference(inner, _), _)) = items[3] else {
+        panic!("Expected return of a dereference");
+    };
+    let Expression::Dereference(inner, _) = inner.as_ref() else {
+        panic!("Expected nested dereference");
+    };
+    assert!(matches!(inner.as_ref(), Expression::AddressOf(_, _)));
+}
+
+#[test]
+fn test_parser_pointer_cast() {
+    let src = "int main(void){ return (long **) 0; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Return(Expression::Cast(ty, _, _), _)) = items[0] else {
+        panic!("Expected return of a cast");
+    };
+    assert_eq!(
+        ty,
+        &Type::Pointer(Box::new(Type::Pointer(Box::new(Type::Long))))
+    );
+}
+
+#[test]
+fn test_parser_error_invalid_declarators() {
+    for src in [
+        "int *;",
+        "int main(void){ return (int *x) 0; }",
+        "int main(void){ return (*int) 0; }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail to parse: {src}");
+    }
+}
+
+// =============================================================================
+// ARRAYS
+// =============================================================================
+
+#[test]
+fn test_parser_array_declarators() {
+    let program = parse_program("int a[3]; long m[2][4]; int *ptrs[5]; int (*row)[3];")
+        .expect("should parse");
+    let types: Vec<_> = program
+        .declarations()
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::VarDecl(decl) => decl.var_type().clone(),
+            _ => panic!("Expected variable declaration"),
+        })
+        .collect();
+
+    let ptr = |t: Type| Type::Pointer(Box::new(t));
+    let arr = |t: Type, n| Type::Array(Box::new(t), n);
+    assert_eq!(
+        types,
+        vec![
+            arr(Type::Int, 3),
+            arr(arr(Type::Long, 4), 2),
+            arr(ptr(Type::Int), 5),
+            ptr(arr(Type::Int, 3)),
+        ]
+    );
+}
+
+#[test]
+fn test_parser_array_parameters_become_pointers() {
+    let program = parse_program("int f(int a[4], long m[][3]);");
+    // only the outermost dimension may be left out in C, but we require sizes everywhere
+    assert!(program.is_err());
+
+    let program = parse_program("int f(int a[4], long m[2][3]);").expect("should parse");
+    let Declaration::FunDecl(f) = &program.declarations()[0] else {
+        panic!("Expected function declaration");
+    };
+
+    let ptr = |t: Type| Type::Pointer(Box::new(t));
+    assert_eq!(
+        f.fun_type(),
+        &Type::FunType(
+            vec![ptr(Type::Int), ptr(Type::Array(Box::new(Type::Long), 3))],
+            Box::new(Type::Int),
+            false
+        )
+    );
+}
+
+#[test]
+fn test_parser_compound_initializers() {
+    let src = "int main(void){ int a[2][2] = {{1, 2}, {3,},}; return 0; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[0] else {
+        panic!("Expected variable declaration");
+    };
+    let Some(Initializer::Compound(rows)) = decl.initializer() else {
+        panic!("Expected compound initializer");
+    };
+    assert_eq!(rows.len(), 2);
+    assert!(matches!(&rows[0], Initializer::Compound(inits) if inits.len() == 2));
+    assert!(matches!(&rows[1], Initializer::Compound(inits) if inits.len() == 1));
+}
+
+#[test]
+fn test_parser_subscripts() {
+    let src = "int main(void){ int a[2][3]; a[1][2] = 4; return a[0][1 + 1]; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    // subscripts are left-associative: a[1][2] is (a[1])[2]
+    let BlockItem::S(Statement::Expression(Expression::Assignment(left, _, _), _)) = items[1]
+    else {
+        panic!("Expected assignment");
+    };
+    let Expression::Subscript(inner, index, _) = left.as_ref() else {
+        panic!("Expected subscript");
+    };
+    assert!(matches!(inner.as_ref(), Expression::Subscript(..)));
+    assert!(matches!(
+        index.as_ref(),
+        Expression::Constant(Const::ConstInt(2), _)
+    ));
+}
+
+#[test]
+fn test_parser_abstract_array_cast() {
+    let src = "int main(void){ int a[2][3]; return *((int (*)[3]) a)[1]; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Return(Expression::Dereference(inner, _), _)) = items[1] else {
+        panic!("Expected return of a dereference");
+    };
+    let Expression::Subscript(cast, _, _) = inner.as_ref() else {
+        panic!("Expected subscript");
+    };
+    let Expression::Cast(ty, _, _) = cast.as_ref() else {
+        panic!("Expected cast");
+    };
+    assert_eq!(
+        ty,
+        &Type::Pointer(Box::new(Type::Array(Box::new(Type::Int), 3)))
+    );
+}
+
+#[test]
+fn test_parser_error_invalid_arrays() {
+    for src in [
+        "int a[];",
+        "int a[0];",
+        "int a[-1];",
+        "int a[1.0];",
+        "int a[3;",
+        "int main(void){ int a[2] = {}; return 0; }",
+        "int main(void){ int a[2] = {1 2}; return 0; }",
+        "int main(void){ int a[2]; return a[1; }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail to parse: {src}");
+    }
+}
+
+// =============================================================================
+// CHARACTERS AND STRINGS
+// =============================================================================
+
+#[test]
+fn test_parser_char_specifiers() {
+    let program =
+        parse_program("char a; signed char b; char signed c; unsigned char d; char unsigned e;")
+            .expect("should parse");
+    let types: Vec<_> = program
+        .declarations()
+        .iter()
+        .map(|declaration| match declaration {
+            Declaration::VarDecl(decl) => decl.var_type().clone(),
+            _ => panic!("Expected variable declaration"),
+        })
+        .collect();
+
+    assert_eq!(
+        types,
+        vec![
+            Type::Char,
+            Type::SChar,
+            Type::SChar,
+            Type::UChar,
+            Type::UChar
+        ]
+    );
+}
+
+#[test]
+fn test_parser_char_constants() {
+    let src = r"int main(void){ return 'a' + '\n' + '\0' + '\101' + '\\'; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Return(expr, _)) = items[0] else {
+        panic!("Expected return statement");
+    };
+    // character constants are ints, collect them from the left-nested additions
+    let mut values = vec![];
+    let mut expr = expr;
+    while let Expression::Binary(BinaryOperator::Add, left, right, _) = expr {
+        values.push(right.as_ref());
+        expr = left;
+    }
+    values.push(expr);
+    values.reverse();
+
+    let values: Vec<_> = values
+        .into_iter()
+        .map(|e| match e {
+            Expression::Constant(c, _) => c.clone(),
+            _ => panic!("Expected constant"),
+        })
+        .collect();
+    assert_eq!(values, [97, 10, 0, 65, 92].map(Const::ConstInt));
+}
+
+#[test]
+fn test_parser_adjacent_string_literals_are_concatenated() {
+    let src = r#"int main(void){ char s[10] = "ab" "\tc\"" "\101"; return 0; }"#;
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[0] else {
+        panic!("Expected declaration");
+    };
+    assert!(matches!(
+        decl.initializer(),
+        Some(Initializer::Single(Expression::String(s, _))) if s == "ab\tc\"A"
+    ));
+}
+
+#[test]
+fn test_parser_invalid_chars_and_strings() {
+    for src in [
+        // a character constant holds a single character
+        "int main(void){ return 'ab'; }",
+        // only the ASCII range can be written with octal escapes
+        r"int main(void){ return '\377'; }",
+        // char can't be combined with other type specifiers
+        "int main(void){ long char c; return 0; }",
+        "int main(void){ char double c; return 0; }",
+        "int main(void){ char char c; return 0; }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// INTEGER CONSTANT FORMS
+// =============================================================================
+
+// Helper: parse `return <expr>;` in main and get the returned constant
+fn parse_returned_const(expr: &str) -> Result<Const, CompileError> {
+    let program = parse_program(&format!("int main(void){{ return {expr}; }}"))?;
+    match get_body_items(&program)[..] {
+        [BlockItem::S(Statement::Return(Expression::Constant(c, _), _))] => Ok(c.clone()),
+        _ => panic!("Expected return of a constant"),
+    }
+}
+
+#[test]
+fn test_parser_hex_octal_binary_values() {
+    for (src, value) in [
+        ("0x1F", Const::ConstInt(31)),
+        ("0XfF", Const::ConstInt(255)),
+        ("017", Const::ConstInt(15)),
+        ("0", Const::ConstInt(0)),
+        ("00", Const::ConstInt(0)),
+        ("0b101", Const::ConstInt(5)),
+        ("0x10l", Const::ConstLong(16)),
+        ("010LL", Const::ConstLong(8)),
+        ("0b11u", Const::ConstUInt(3)),
+        ("0xAull", Const::ConstULong(10)),
+    ] {
+        assert_eq!(parse_returned_const(src), Ok(value), "for {src}");
+    }
+}
+
+#[test]
+fn test_parser_constant_type_depends_on_base() {
+    for (src, value) in [
+        // decimal constants without `u` are never unsigned
+        ("2147483648", Const::ConstLong(2147483648)),
+        ("9223372036854775807", Const::ConstLong(i64::MAX)),
+        // other bases try the unsigned type of each size too
+        ("0x7FFFFFFF", Const::ConstInt(i32::MAX)),
+        ("0x80000000", Const::ConstUInt(2147483648)),
+        ("037777777777", Const::ConstUInt(u32::MAX)),
+        ("0x100000000", Const::ConstLong(4294967296)),
+        ("0x8000000000000000", Const::ConstULong(1 << 63)),
+        ("0xFFFFFFFFl", Const::ConstLong(4294967295)),
+        ("0xFFFFFFFFFFFFFFFFL", Const::ConstULong(u64::MAX)),
+        ("18446744073709551615u", Const::ConstULong(u64::MAX)),
+    ] {
+        assert_eq!(parse_returned_const(src), Ok(value), "for {src}");
+    }
+}
+
+#[test]
+fn test_parser_hex_escapes() {
+    assert_eq!(parse_returned_const(r"'\x41'"), Ok(Const::ConstInt(65)));
+    assert_eq!(parse_returned_const(r"'\x7f'"), Ok(Const::ConstInt(127)));
+}
+
+#[test]
+fn test_parser_invalid_integer_constants() {
+    for src in [
+        // octal constants only have octal digits
+        "09",
+        "0178",
+        // too large for any integer type
+        "0x10000000000000000",
+        "18446744073709551616u",
+        "9223372036854775808L",
+        // hex escapes are limited to ASCII like octal ones
+        r"'\x80'",
+    ] {
+        assert!(parse_returned_const(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// STRUCTURES
+// =============================================================================
+
+#[test]
+fn test_parser_structure_declarations() {
+    let program = parse_program("struct s; struct s { int a; long *b[2]; }; struct s x;")
+        .expect("should parse");
+    let declarations = program.declarations();
+
+    // a declaration without members only declares the tag
+    let Declaration::StructDecl(forward) = &declarations[0] else {
+        panic!("Expected structure declaration");
+    };
+    assert_eq!(forward.tag().value(), "s");
+    assert!(forward.members().is_empty());
+
+    let Declaration::StructDecl(definition) = &declarations[1] else {
+        panic!("Expected structure declaration");
+    };
+    let members: Vec<_> = definition
+        .members()
+        .iter()
+        .map(|m| (m.name().value().to_string(), m.member_type().clone()))
+        .collect();
+    assert_eq!(
+        members,
+        vec![
+            ("a".to_string(), Type::Int),
+            (
+                "b".to_string(),
+                Type::Array(Box::new(Type::Pointer(Box::new(Type::Long))), 2)
+            ),
+        ]
+    );
+
+    let Declaration::VarDecl(decl) = &declarations[2] else {
+        panic!("Expected variable declaration");
+    };
+    assert_eq!(decl.var_type(), &Type::Structure("s".to_string()));
+}
+
+#[test]
+fn test_parser_member_access_is_postfix() {
+    let src = "int main(void){ return -p->a.b[1]; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    // -(((p->a).b)[1])
+    let BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Negate, inner, _), _)) =
+        items[0]
+    else {
+        panic!("Expected return of a negation");
+    };
+    let Expression::Subscript(array, _, _) = inner.as_ref() else {
+        panic!("Expected subscript");
+    };
+    let Expression::Dot(structure, member, _) = array.as_ref() else {
+        panic!("Expected member access");
+    };
+    assert_eq!(member.value(), "b");
+    assert!(matches!(
+        structure.as_ref(),
+        Expression::Arrow(ptr, member, _)
+            if member.value() == "a" && matches!(ptr.as_ref(), Expression::Var(_, _))
+    ));
+}
+
+#[test]
+fn test_parser_structure_declarations_invalid() {
+    for src in [
+        // at least one member
+        "struct s {};",
+        // only tagged structures are supported
+        "struct { int a; } x;",
+        // members have no storage class and aren't functions
+        "struct s { static int a; };",
+        "struct s { int f(void); };",
+        "struct int x;",
+        "long struct s x;",
+        // a for loop initializer only declares variables
+        "int main(void){ for (struct s { int a; }; ;) ; return 0; }",
+        "int main(void){ return x.1; }",
+        "int main(void){ return p->; }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// GOTO AND LABELED STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_parser_goto_and_labeled_statements() {
+    let src = "int main(void){ goto end; a: b: return x ? y : z; end: ; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    assert!(matches!(items[0], BlockItem::S(Statement::Goto(label, _)) if label.value() == "end"));
+
+    // labels nest, and a colon after an identifier in an expression isn't a label
+    let BlockItem::S(Statement::Labeled(a, st, _)) = items[1] else {
+        panic!("Expected labeled statement");
+    };
+    assert_eq!(a.value(), "a");
+    let Statement::Labeled(b, st, _) = st.as_ref() else {
+        panic!("Expected labeled statement");
+    };
+    assert_eq!(b.value(), "b");
+    assert!(matches!(
+        st.as_ref(),
+        Statement::Return(Expression::Conditional(..), _)
+    ));
+
+    assert!(matches!(
+        items[2],
+        BlockItem::S(Statement::Labeled(end, st, _)) if end.value() == "end" && matches!(st.as_ref(), Statement::Null(_))
+    ));
+}
+
+#[test]
+fn test_parser_goto_and_labeled_statements_invalid() {
+    for src in [
+        // a label must be followed by a statement
+        "int main(void){ a: }",
+        "int main(void){ a: int x; return 0; }",
+        "int main(void){ goto; }",
+        "int main(void){ goto 1; }",
+        "int main(void){ goto a }",
+        "int main(void){ 1: return 0; }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// SWITCH STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_parser_switch_statement() {
+    let src = "int main(void){ switch (x) { case 1: case 2: return 0; default: break; } }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Switch(cond, body, _, cases, _)) = items[0] else {
+        panic!("Expected switch statement");
+    };
+    assert!(matches!(cond.as_ref(), Expression::Var(_, _)));
+    // cases are only collected by the semantic analysis
+    assert!(cases.is_empty());
+
+    let Statement::Compound(block, _) = body.as_ref() else {
+        panic!("Expected compound body");
+    };
+    let statements: Vec<_> = block.iter().collect();
+    let BlockItem::S(Statement::Case(Expression::Constant(Const::ConstInt(1), _), st, _, _)) =
+        statements[0]
+    else {
+        panic!("Expected case");
+    };
+    assert!(matches!(
+        st.as_ref(),
+        Statement::Case(Expression::Constant(Const::ConstInt(2), _), _, _, _)
+    ));
+    assert!(matches!(
+        statements[1],
+        BlockItem::S(Statement::Default(st, _, _)) if matches!(st.as_ref(), Statement::Break(_, _))
+    ));
+}
+
+#[test]
+fn test_parser_switch_statement_invalid() {
+    for src in [
+        "int main(void){ switch x { default: ; } }",
+        "int main(void){ switch (x) }",
+        "int main(void){ switch (x) { case: ; } }",
+        "int main(void){ switch (x) { case 1 ; } }",
+        "int main(void){ switch (x) { default ; } }",
+        // a case must label a statement
+        "int main(void){ switch (x) { case 1: } }",
+        "int main(void){ switch (x) { default: int y; } }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// COMPOUND ASSIGNMENT AND INCREMENT
+// =============================================================================
+
+#[test]
+fn test_parser_compound_assignment_is_right_associative() {
+    let src = "int main(void){ a += b -= c * 2; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Expression(
+        Expression::CompoundAssignment(BinaryOperator::Add, left, right, _),
+        _,
+    )) = items[0]
+    else {
+        panic!("Expected compound assignment");
+    };
+    assert!(matches!(left.as_ref(), Expression::Var(_, _)));
+    let Expression::CompoundAssignment(BinaryOperator::Subtract, _, value, _) = right.as_ref()
+    else {
+        panic!("Expected nested compound assignment");
+    };
+    assert!(matches!(
+        value.as_ref(),
+        Expression::Binary(BinaryOperator::Multiply, _, _, _)
+    ));
+}
+
+#[test]
+fn test_parser_all_compound_operators() {
+    for (op, expected) in [
+        ("+=", BinaryOperator::Add),
+        ("-=", BinaryOperator::Subtract),
+        ("*=", BinaryOperator::Multiply),
+        ("/=", BinaryOperator::Divide),
+        ("%=", BinaryOperator::Remainder),
+        ("&=", BinaryOperator::BitwiseAnd),
+        ("|=", BinaryOperator::BitwiseOr),
+        ("^=", BinaryOperator::BitwiseXor),
+        ("<<=", BinaryOperator::LeftShift),
+        (">>=", BinaryOperator::RightShift),
+    ] {
+        let src = format!("int main(void){{ a {op} 1; }}");
+        let program = parse_program(&src).expect("should parse");
+        let items = get_body_items(&program);
+
+        assert!(
+            matches!(
+                items[0],
+                BlockItem::S(Statement::Expression(Expression::CompoundAssignment(parsed, _, _, _), _))
+                    if std::mem::discriminant(parsed) == std::mem::discriminant(&expected)
+            ),
+            "Wrong operator for {op}"
+        );
+    }
+}
+
+#[test]
+fn test_parser_prefix_increment_is_compound_assignment() {
+    let src = "int main(void){ ++a; --*p; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    assert!(matches!(
+        items[0],
+        BlockItem::S(Statement::Expression(Expression::CompoundAssignment(
+            BinaryOperator::Add,
+            target,
+            one, _,
+        ), _)) if matches!(target.as_ref(), Expression::Var(_, _))
+            && matches!(one.as_ref(), Expression::Constant(Const::ConstInt(1), _))
+    ));
+    assert!(matches!(
+        items[1],
+        BlockItem::S(Statement::Expression(Expression::CompoundAssignment(
+            BinaryOperator::Subtract,
+            target,
+            _, _,
+        ), _)) if matches!(target.as_ref(), Expression::Dereference(_, _))
+    ));
+}
+
+#[test]
+fn test_parser_postfix_binds_tighter_than_prefix() {
+    // `*p++` increments the pointer, `-a--` negates the old value
+    let src = "int main(void){ return *p++ + -a[i]--; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Return(Expression::Binary(BinaryOperator::Add, left, right, _), _)) =
+        items[0]
+    else {
+        panic!("Expected addition");
+    };
+    let Expression::Dereference(inner, _) = left.as_ref() else {
+        panic!("Expected dereference");
+    };
+    assert!(matches!(
+        inner.as_ref(),
+        Expression::Postfix(BinaryOperator::Add, _, _)
+    ));
+    let Expression::Unary(UnaryOperator::Negate, inner, _) = right.as_ref() else {
+        panic!("Expected negation");
+    };
+    assert!(matches!(
+        inner.as_ref(),
+        Expression::Postfix(BinaryOperator::Subtract, target, _)
+            if matches!(target.as_ref(), Expression::Subscript(..))
+    ));
+}
+
+#[test]
+fn test_parser_compound_assignment_invalid() {
+    for src in [
+        "int main(void){ a += ; }",
+        "int main(void){ a <<== 1; }",
+        "int main(void){ ++; }",
+        "int main(void){ a++ b; }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// COMMA OPERATOR AND DECLARATION LISTS
+// =============================================================================
+
+#[test]
+fn test_parser_declaration_list_in_block() {
+    let src = "int main(void){ int a = 1, *p, b[2] = {1, 2}; return a; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    // one block item per declarator, all with the same base type
+    let declared: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            BlockItem::D(Declaration::VarDecl(decl)) => {
+                Some((decl.name().value().to_string(), decl.var_type().clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        declared,
+        vec![
+            ("a".to_string(), Type::Int),
+            ("p".to_string(), Type::Pointer(Box::new(Type::Int))),
+            ("b".to_string(), Type::Array(Box::new(Type::Int), 2)),
+        ]
+    );
+    assert!(matches!(items[3], BlockItem::S(Statement::Return(_, _))));
+}
+
+#[test]
+fn test_parser_declaration_list_at_file_scope() {
+    let src = "static long x = 1, f(int a), y; int f(int a) { return a; }";
+    let program = parse_program(src).expect("should parse");
+    let declarations = program.declarations();
+
+    assert_eq!(declarations.len(), 4);
+    assert!(matches!(
+        &declarations[0],
+        Declaration::VarDecl(decl) if decl.storage_class() == Some(&StorageClass::Static)
+    ));
+    // every declarator shares the storage class
+    assert!(matches!(
+        &declarations[1],
+        Declaration::FunDecl(f) if f.body().is_none() && f.storage_class() == Some(&StorageClass::Static)
+    ));
+    assert!(matches!(&declarations[2], Declaration::VarDecl(decl) if decl.name().value() == "y"));
+    assert!(matches!(&declarations[3], Declaration::FunDecl(f) if f.body().is_some()));
+}
+
+#[test]
+fn test_parser_for_init_declaration_list() {
+    let src = "int main(void){ for (int i = 0, j = 10; i < j; i++, j--) ; return 0; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::For(init, _, post, _, _, _)) = items[0] else {
+        panic!("Expected for statement");
+    };
+    let fcc::c_ast::ast::ForInit::InitDecl(decls) = init.as_ref() else {
+        panic!("Expected declarations");
+    };
+    let names: Vec<_> = decls.iter().map(|d| d.name().value()).collect();
+    assert_eq!(names, vec!["i", "j"]);
+    assert!(matches!(
+        post.as_deref(),
+        Some(Expression::Comma(left, right, _))
+            if matches!(left.as_ref(), Expression::Postfix(BinaryOperator::Add, _, _))
+                && matches!(right.as_ref(), Expression::Postfix(BinaryOperator::Subtract, _, _))
+    ));
+}
+
+#[test]
+fn test_parser_comma_has_lowest_precedence() {
+    // `a = 1, b = 2, c` is `((a = 1), (b = 2)), c`
+    let src = "int main(void){ a = 1, b = 2, c; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Expression(Expression::Comma(left, right, _), _)) = items[0] else {
+        panic!("Expected comma expression");
+    };
+    assert!(matches!(right.as_ref(), Expression::Var(_, _)));
+    let Expression::Comma(first, second, _) = left.as_ref() else {
+        panic!("Expected left associative comma");
+    };
+    assert!(matches!(first.as_ref(), Expression::Assignment(..)));
+    assert!(matches!(second.as_ref(), Expression::Assignment(..)));
+}
+
+#[test]
+fn test_parser_comma_in_arguments_and_initializers() {
+    let src = "int main(void){ int a[2] = {1, (2, 3)}; return f(a, (b, c)); }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[0] else {
+        panic!("Expected declaration");
+    };
+    let Some(Initializer::Compound(inits)) = decl.initializer() else {
+        panic!("Expected compound initializer");
+    };
+    assert_eq!(inits.len(), 2);
+    assert!(matches!(
+        &inits[1],
+        Initializer::Single(Expression::Comma(..))
+    ));
+
+    let BlockItem::S(Statement::Return(Expression::FunctionCall(_, args, _), _)) = items[1] else {
+        panic!("Expected function call");
+    };
+    assert_eq!(args.len(), 2);
+    assert!(matches!(&args[1], Expression::Comma(..)));
+}
+
+#[test]
+fn test_parser_declaration_list_invalid() {
+    for src in [
+        "int main(void){ int a = 1,; return a; }",
+        "int main(void){ int a, b = 2 int c; return 0; }",
+        "int main(void){ int , a; return 0; }",
+        // a function definition is alone in its declaration
+        "int f(void) { return 0; }, x;",
+        "int x, f(void) { return 0; }",
+        "int main(void){ for (int i = 0, static j = 1; ;) ; return 0; }",
+        "int main(void){ for (int i = 0, f(void); ;) ; return 0; }",
+        "int main(void){ a = (1, ); }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// ENUMERATIONS
+// =============================================================================
+
+#[test]
+fn test_parser_enum_declaration() {
+    let src = "enum color { RED, GREEN = 4, BLUE, };";
+    let program = parse_program(src).expect("should parse");
+
+    let [Declaration::EnumDecl(decl)] = program.declarations().as_slice() else {
+        panic!("Expected a single enumeration declaration");
+    };
+    assert_eq!(decl.tag().map(|tag| tag.value()), Some("color"));
+    let names: Vec<_> = decl
+        .enumerators()
+        .iter()
+        .map(|(name, _)| name.value())
+        .collect();
+    assert_eq!(names, vec!["RED", "GREEN", "BLUE"]);
+    // only explicit values are kept, the type checker computes the others
+    assert!(decl.enumerators()[0].1.is_none());
+    assert!(matches!(
+        decl.enumerators()[1].1,
+        Some(Expression::Constant(Const::ConstInt(4), _))
+    ));
+}
+
+#[test]
+fn test_parser_enum_specifier_with_declarators() {
+    let src = "int main(void){ enum { A, B } x = B, *p; enum state s; return (enum state)x; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    // the definition comes first, then one declaration per declarator
+    assert!(matches!(
+        items[0],
+        BlockItem::D(Declaration::EnumDecl(decl)) if decl.tag().is_none()
+    ));
+    let declared: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            BlockItem::D(Declaration::VarDecl(decl)) => {
+                Some((decl.name().value().to_string(), decl.var_type().clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    // enumeration types are int
+    assert_eq!(
+        declared,
+        vec![
+            ("x".to_string(), Type::Int),
+            ("p".to_string(), Type::Pointer(Box::new(Type::Int))),
+            ("s".to_string(), Type::Int),
+        ]
+    );
+    assert!(matches!(
+        items[4],
+        BlockItem::S(Statement::Return(Expression::Cast(Type::Int, _, _), _))
+    ));
+}
+
+#[test]
+fn test_parser_enum_invalid() {
+    for src in [
+        "enum {};",
+        "enum;",
+        "enum e { A B };",
+        "enum e { A = };",
+        "enum e { , };",
+        "long enum e x;",
+        "enum e { A } enum f { B } x;",
+        "int main(void){ for (enum { A } i = A; ;) ; return 0; }",
+        "int main(void){ return (enum { A })0; }",
+        "struct s { enum { A } x; };",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+#[test]
+fn test_parser_array_size_constant_expressions() {
+    let program = parse_program(
+        "enum { N = 4 }; int a[N]; \
+         int b[2 * 4]; \
+         enum { A = 2, B = A * 3, C }; long c[C - 1][A ? B : 1];",
+    )
+    .expect("should parse");
+    let types: Vec<Type> = program
+        .declarations()
+        .iter()
+        .filter_map(|declaration| match declaration {
+            Declaration::VarDecl(decl) => Some(decl.var_type().clone()),
+            _ => None,
+        })
+        .collect();
+
+    let arr = |t: Type, n| Type::Array(Box::new(t), n);
+    assert_eq!(
+        types,
+        vec![
+            arr(Type::Int, 4),
+            arr(Type::Int, 8),
+            arr(arr(Type::Long, 6), 6),
+        ]
+    );
+
+    // a variable hiding an enumerator isn't a constant
+    for src in [
+        "enum { N = 4 }; int main(void) { int N = 2; int a[N]; return 0; }",
+        "int n; int a[n];",
+    ] {
+        assert!(
+            matches!(
+                parse_program(src),
+                Err(CompileError::Parse(ParseError::ArraySizeNotConstant(_), _))
+            ),
+            "Should fail: {src}"
+        );
+    }
+}
+
+// =============================================================================
+// TYPEDEFS
+// =============================================================================
+
+#[test]
+fn test_parser_typedef_name_starts_a_declaration() {
+    let src = "typedef long foo; int main(void){ foo * bar; foo (baz)[2]; return 0; }";
+    let program = parse_program(src).expect("should parse");
+
+    // the typedef itself doesn't become a declaration
+    assert_eq!(program.declarations().len(), 1);
+    let declared: Vec<_> = get_body_items(&program)
+        .iter()
+        .filter_map(|item| match item {
+            BlockItem::D(Declaration::VarDecl(decl)) => {
+                Some((decl.name().value().to_string(), decl.var_type().clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        declared,
+        vec![
+            ("bar".to_string(), Type::Pointer(Box::new(Type::Long))),
+            ("baz".to_string(), Type::Array(Box::new(Type::Long), 2)),
+        ]
+    );
+}
+
+#[test]
+fn test_parser_identifier_without_typedef_is_an_expression() {
+    let src = "int main(void){ foo * bar; return 0; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    assert!(matches!(
+        items[0],
+        BlockItem::S(Statement::Expression(
+            Expression::Binary(BinaryOperator::Multiply, ..),
+            _
+        ))
+    ));
+}
+
+#[test]
+fn test_parser_typedef_scopes_and_shadowing() {
+    let src = "typedef int foo; \
+               int main(void){ \
+                   { int foo = 2; foo * bar; } \
+                   { typedef foo *ptr; ptr p; } \
+                   foo * baz; \
+                   for (int foo = 0; foo < 1; foo++) foo * baz; \
+                   return (foo)baz; \
+               }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    // the variable hides the typedef until the end of its block
+    let BlockItem::S(Statement::Compound(shadowed, _)) = items[0] else {
+        panic!("Expected block");
+    };
+    assert!(matches!(
+        shadowed.iter().nth(1),
+        Some(BlockItem::S(Statement::Expression(
+            Expression::Binary(BinaryOperator::Multiply, ..),
+            _
+        )))
+    ));
+    let BlockItem::S(Statement::Compound(inner, _)) = items[1] else {
+        panic!("Expected block");
+    };
+    assert!(matches!(
+        inner.iter().next(),
+        Some(BlockItem::D(Declaration::VarDecl(decl)))
+            if decl.var_type() == &Type::Pointer(Box::new(Type::Int))
+    ));
+    assert!(matches!(items[2], BlockItem::D(Declaration::VarDecl(_))));
+    let BlockItem::S(Statement::For(_, _, _, body, _, _)) = items[3] else {
+        panic!("Expected for loop");
+    };
+    assert!(matches!(
+        body.as_ref(),
+        Statement::Expression(Expression::Binary(BinaryOperator::Multiply, ..), _)
+    ));
+    assert!(matches!(
+        items[4],
+        BlockItem::S(Statement::Return(Expression::Cast(Type::Int, _, _), _))
+    ));
+}
+
+#[test]
+fn test_parser_typedef_of_structure_definition() {
+    let src = "typedef struct node { int v; struct node *next; } node_t, *list; \
+               list f(node_t n);";
+    let program = parse_program(src).expect("should parse");
+    let declarations = program.declarations();
+
+    // the structure is still defined, the typedef names only refer to it
+    assert_eq!(declarations.len(), 2);
+    assert!(matches!(
+        &declarations[0],
+        Declaration::StructDecl(s) if s.tag().value() == "node" && s.members().len() == 2
+    ));
+    let Declaration::FunDecl(f) = &declarations[1] else {
+        panic!("Expected function declaration");
+    };
+    let node = Type::Structure("node".to_string());
+    assert_eq!(
+        f.fun_type(),
+        &Type::FunType(
+            vec![node.clone()],
+            Box::new(Type::Pointer(Box::new(node))),
+            false
+        )
+    );
+}
+
+#[test]
+fn test_parser_typedef_invalid() {
+    for src in [
+        "typedef int T; int T;",
+        "int T; typedef int T;",
+        "typedef int T; typedef long T;",
+        "typedef int T = 1;",
+        "static typedef int T;",
+        "typedef int T; T long x;",
+        "typedef int T; unsigned T x;",
+        "struct s { typedef int T; };",
+        "int main(void){ { typedef int T; } T x; return 0; }",
+        "typedef int T; int main(void){ int T = 1; T x; return 0; }",
+        "int main(void){ for (typedef int T; ;) ; return 0; }",
+        "typedef int fn_t(int a); fn_t f;",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// FUNCTION POINTERS
+// =============================================================================
+
+// Helper: the type `int (*)(int, int)`
+fn binop_pointer() -> Type {
+    Type::Pointer(Box::new(Type::FunType(
+        vec![Type::Int, Type::Int],
+        Box::new(Type::Int),
+        false,
+    )))
+}
+
+#[test]
+fn test_parser_function_pointer_declarators() {
+    let src = "int (*fp)(int a, int b); \
+               int (*table[3])(int, int); \
+               int (*pick(int i))(int, int); \
+               int apply(int op(int, int), int x);";
+    let program = parse_program(src).expect("should parse");
+    let declarations = program.declarations();
+
+    let Declaration::VarDecl(fp) = &declarations[0] else {
+        panic!("Expected variable declaration");
+    };
+    assert_eq!(fp.var_type(), &binop_pointer());
+    let Declaration::VarDecl(table) = &declarations[1] else {
+        panic!("Expected variable declaration");
+    };
+    assert_eq!(table.var_type(), &Type::Array(Box::new(binop_pointer()), 3));
+
+    // the parameter names come from the declarator of the function itself
+    let Declaration::FunDecl(pick) = &declarations[2] else {
+        panic!("Expected function declaration");
+    };
+    assert_eq!(
+        pick.fun_type(),
+        &Type::FunType(vec![Type::Int], Box::new(binop_pointer()), false)
+    );
+    assert_eq!(pick.params().len(), 1);
+    assert_eq!(pick.params()[0].value(), "i");
+
+    // a function parameter is adjusted to a pointer
+    let Declaration::FunDecl(apply) = &declarations[3] else {
+        panic!("Expected function declaration");
+    };
+    assert_eq!(
+        apply.fun_type(),
+        &Type::FunType(vec![binop_pointer(), Type::Int], Box::new(Type::Int), false)
+    );
+}
+
+#[test]
+fn test_parser_cast_to_function_pointer() {
+    let src = "int main(void){ return ((int (*)(int, int)) 0)(1, 2); }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Return(Expression::IndirectCall(callee, args, _), _)) = items[0]
+    else {
+        panic!("Expected indirect call");
+    };
+    assert!(matches!(callee.as_ref(), Expression::Cast(ty, _, _) if *ty == binop_pointer()));
+    assert_eq!(args.len(), 2);
+}
+
+#[test]
+fn test_parser_calls_through_expressions() {
+    let src = "int main(void){ (*fp)(1); ops[2](3); f(1)(2); return 0; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    assert!(matches!(
+        items[0],
+        BlockItem::S(Statement::Expression(Expression::IndirectCall(callee, _, _), _))
+            if matches!(callee.as_ref(), Expression::Dereference(_, _))
+    ));
+    assert!(matches!(
+        items[1],
+        BlockItem::S(Statement::Expression(Expression::IndirectCall(callee, _, _), _))
+            if matches!(callee.as_ref(), Expression::Subscript(..))
+    ));
+    // a call returning a function pointer can be called in turn
+    assert!(matches!(
+        items[2],
+        BlockItem::S(Statement::Expression(Expression::IndirectCall(callee, _, _), _))
+            if matches!(callee.as_ref(), Expression::FunctionCall(..))
+    ));
+}
+
+#[test]
+fn test_parser_function_pointers_invalid() {
+    for src in [
+        "int (f(void))(int);",
+        "int (*fp)(int, int) = 0, (*);",
+        "int f(int, int) { return 0; }",
+        "int (*)(int);",
+        "int main(void){ return (int (*)(int, )) 0; }",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// VARIADIC FUNCTIONS
+// =============================================================================
+
+#[test]
+fn test_parser_variadic_declarations() {
+    let src = "int printf(char *, ...); \
+               int sum(int n, ...) { return n; } \
+               int (*fp)(char *fmt, ...);";
+    let program = parse_program(src).expect("should parse");
+    let declarations = program.declarations();
+
+    // a declaration may omit the parameter names
+    let Declaration::FunDecl(printf) = &declarations[0] else {
+        panic!("Expected function declaration");
+    };
+    assert_eq!(
+        printf.fun_type(),
+        &Type::FunType(
+            vec![Type::Pointer(Box::new(Type::Char))],
+            Box::new(Type::Int),
+            true
+        )
+    );
+    assert!(printf.params().is_empty());
+
+    let Declaration::FunDecl(sum) = &declarations[1] else {
+        panic!("Expected function declaration");
+    };
+    assert_eq!(
+        sum.fun_type(),
+        &Type::FunType(vec![Type::Int], Box::new(Type::Int), true)
+    );
+    assert_eq!(sum.params()[0].value(), "n");
+
+    let Declaration::VarDecl(fp) = &declarations[2] else {
+        panic!("Expected variable declaration");
+    };
+    assert_eq!(
+        fp.var_type(),
+        &Type::Pointer(Box::new(Type::FunType(
+            vec![Type::Pointer(Box::new(Type::Char))],
+            Box::new(Type::Int),
+            true
+        )))
+    );
+}
+
+#[test]
+fn test_parser_va_builtins() {
+    let src = "int f(int n, ...) { \
+                   __builtin_va_list ap, copy; \
+                   __builtin_va_start(ap, n); \
+                   __builtin_va_copy(copy, ap); \
+                   n = __builtin_va_arg(ap, long *)[0]; \
+                   __builtin_va_end(ap); \
+                   return n; \
+               }";
+    let program = parse_program(src).expect("should parse");
+    let items: Vec<_> = program
+        .function_definition("f")
+        .and_then(|f| f.body())
+        .map(|body| body.iter().collect())
+        .unwrap_or_default();
+
+    // `va_list` is an array of one builtin structure
+    let BlockItem::D(Declaration::VarDecl(ap)) = items[0] else {
+        panic!("Expected variable declaration");
+    };
+    assert_eq!(
+        ap.var_type(),
+        &Type::Array(Box::new(Type::Structure(VA_LIST_TAG.to_string())), 1)
+    );
+    assert!(matches!(
+        items[2],
+        BlockItem::S(Statement::VaStart(Expression::Var(_, _), Expression::Var(last, _), _))
+            if last.value() == "n"
+    ));
+    assert!(matches!(items[3], BlockItem::S(Statement::VaCopy(..))));
+    let BlockItem::S(Statement::Expression(Expression::Assignment(_, right, _), _)) = items[4]
+    else {
+        panic!("Expected assignment");
+    };
+    let Expression::Subscript(va_arg, _, _) = right.as_ref() else {
+        panic!("Expected subscript");
+    };
+    assert!(matches!(
+        va_arg.as_ref(),
+        Expression::VaArg(_, ty, _) if *ty == Type::Pointer(Box::new(Type::Long))
+    ));
+    assert!(matches!(items[5], BlockItem::S(Statement::VaEnd(_, _))));
+}
+
+#[test]
+fn test_parser_variadic_invalid() {
+    for src in [
+        "int f(...);",
+        "int f(int, ..., int);",
+        "int f(int, ...,);",
+        "int f(int n, ...) { __builtin_va_list ap; __builtin_va_start(ap); return 0; }",
+        "int f(int n, ...) { __builtin_va_list ap; return __builtin_va_arg(ap); }",
+        "int f(int n, ...) { __builtin_va_list ap; __builtin_va_end(ap) return 0; }",
+        "long __builtin_va_list x;",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// TYPE QUALIFIERS
+// =============================================================================
+
+// Helper: the types of the file scope variable declarations of a program
+fn var_types(program: &Program) -> Vec<Type> {
+    program
+        .declarations()
+        .iter()
+        .filter_map(|declaration| match declaration {
+            Declaration::VarDecl(decl) => Some(decl.var_type().clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_parser_qualified_specifiers() {
+    let src = "const int a; int const b; volatile long c; const volatile unsigned d; \
+               const const int e;";
+    let program = parse_program(src).expect("should parse");
+    let qualified = |ty: Type, qualifiers: Qualifiers| Type::Qualified(Box::new(ty), qualifiers);
+    let both = Qualifiers {
+        is_const: true,
+        is_volatile: true,
+    };
+
+    assert_eq!(
+        var_types(&program),
+        vec![
+            qualified(Type::Int, Qualifiers::CONST),
+            qualified(Type::Int, Qualifiers::CONST),
+            qualified(Type::Long, Qualifiers::VOLATILE),
+            qualified(Type::UInt, both),
+            qualified(Type::Int, Qualifiers::CONST),
+        ]
+    );
+}
+
+#[test]
+fn test_parser_qualified_pointers_and_arrays() {
+    let src = "const int *a; int *const b; int *volatile *const c; const int d[2]; \
+               typedef int pair[2]; const pair e;";
+    let program = parse_program(src).expect("should parse");
+    let const_int = Type::Int.qualified(Qualifiers::CONST);
+
+    assert_eq!(
+        var_types(&program),
+        vec![
+            // the qualifiers before the `*` are the ones of the referenced type
+            Type::Pointer(Box::new(const_int.clone())),
+            Type::Pointer(Box::new(Type::Int)).qualified(Qualifiers::CONST),
+            Type::Pointer(Box::new(
+                Type::Pointer(Box::new(Type::Int)).qualified(Qualifiers::VOLATILE)
+            ))
+            .qualified(Qualifiers::CONST),
+            // arrays aren't qualified, their elements are
+            Type::Array(Box::new(const_int.clone()), 2),
+            Type::Array(Box::new(const_int), 2),
+        ]
+    );
+}
+
+#[test]
+fn test_parser_qualified_cast() {
+    let src = "int main(void){ return (const char *const) 0; }";
+    let program = parse_program(src).expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(Statement::Return(Expression::Cast(ty, _, _), _)) = items[0] else {
+        panic!("Expected return of a cast");
+    };
+    assert_eq!(
+        *ty,
+        Type::Pointer(Box::new(Type::Char.qualified(Qualifiers::CONST)))
+            .qualified(Qualifiers::CONST)
+    );
+}
+
+#[test]
+fn test_parser_invalid_qualifiers() {
+    for src in [
+        // a qualifier isn't a type
+        "const x;",
+        "int main(void){ volatile v = 1; return v; }",
+        // qualifiers go after the `*`
+        "int const * * x const;",
+    ] {
+        assert!(parse_program(src).is_err(), "Should fail to parse: {src}");
+    }
+}
+
+// =============================================================================
+// SPANS
+// =============================================================================
+
+// Helper: parse source code with the spans of its tokens
+fn parse_spanned(src: &str) -> Result<Program, CompileError> {
+    let tokens = lex_spanned(src)?;
+    Program::try_from(tokens)
+}
+
+#[test]
+fn test_parser_spans_of_statements_and_expressions() {
+    let src = "# 1 \"main.c\"\nint main(void) {\n  int x = 1;\n  return x + 2 * 3;\n}";
+    let program = parse_spanned(src).expect("should parse");
+
+    let main = program
+        .function_definition("main")
+        .expect("main is defined");
+    assert_eq!(main.span().to_string(), "main.c:1:5");
+
+    let items = get_body_items(&program);
+    let BlockItem::D(Declaration::VarDecl(x)) = items[0] else {
+        panic!("Expected a declaration of x");
+    };
+    assert_eq!(x.span().to_string(), "main.c:2:7");
+
+    let BlockItem::S(statement @ Statement::Return(Expression::Binary(_, left, right, span), _)) =
+        items[1]
+    else {
+        panic!("Expected return of a binary expression");
+    };
+    assert_eq!(statement.span().to_string(), "main.c:3:3");
+    assert_eq!(span.to_string(), "main.c:3:10");
+    assert_eq!(left.span().to_string(), "main.c:3:10");
+    assert_eq!(right.span().to_string(), "main.c:3:14");
+
+    // the byte range covers `x + 2 * 3`
+    assert_eq!(&src[span.start..span.end], "x + 2 * 3");
+    let statement_span = statement.span();
+    assert_eq!(
+        &src[statement_span.start..statement_span.end],
+        "return x + 2 * 3;"
+    );
+}
+
+#[test]
+fn test_parser_spans_unknown_without_locations() {
+    let program = parse_program("int main(void) { return 0; }").expect("should parse");
+    let items = get_body_items(&program);
+
+    let BlockItem::S(statement) = items[0] else {
+        panic!("Expected a statement");
+    };
+    assert!(statement.span().is_unknown());
+}
+
+// =============================================================================
+// ERROR KINDS
+// =============================================================================
+
+#[test]
+fn test_parser_typed_unexpected_token() {
+    let result = parse_spanned("# 1 \"main.c\"\nint main(void) {\n  return 1\n}");
+
+    let Err(CompileError::Parse(error, span)) = result else {
+        panic!("Expected a parse error");
+    };
+    assert_eq!(
+        error,
+        ParseError::UnexpectedToken {
+            expected: Token::Semicolon,
+            found: Box::new(Token::CloseBrace),
+        }
+    );
+    assert_eq!(span.to_string(), "main.c:3:1");
+}
+
+#[test]
+fn test_parser_typed_error_kinds() {
+    for (src, expected) in [
+        (
+            "int main(void) { return 1 + ; }",
+            ParseError::Expected(Construct::Expression, Some(Token::Semicolon)),
+        ),
+        (
+            "int main(void) { int a[0]; return 0; }",
+            ParseError::ArraySizeNotPositive(0),
+        ),
+        (
+            "int main(void) { return 0;",
+            ParseError::UnexpectedEnd(Some(Token::CloseBrace)),
+        ),
+        (
+            "typedef int t = 1;",
+            ParseError::TypedefInitialized("t".to_string()),
+        ),
+        ("return 0;", ParseError::ExpectedDeclaration(Token::Return)),
+    ] {
+        match parse_program(src) {
+            Err(CompileError::Parse(error, _)) => assert_eq!(error, expected, "{src}"),
+            result => panic!("Expected a parse error for {src}, got {result:?}"),
+        }
+    }
+}
+
+// =============================================================================
+// ERROR RECOVERY
+// =============================================================================
+
+// Helper: the locations and messages of the errors of a source that doesn't parse
+fn parse_errors(src: &str) -> Vec<String> {
+    match parse_spanned(src) {
+        Ok(_) => vec![],
+        Err(error) => error.errors().iter().map(ToString::to_string).collect(),
+    }
+}
+
+#[test]
+fn test_parser_reports_every_error() {
+    let src = "# 1 \"main.c\"\n\
+               int f(int a, ) {\n\
+                 return a;\n\
+               }\n\
+               int main(void) {\n\
+                 int x = 1 +;\n\
+                 int y = 2;\n\
+                 if (y == 2 {\n\
+                   y = 3;\n\
+                 }\n\
+                 return x\n\
+               }\n\
+               int g(void) { return 1 2; }";
+
+    assert_eq!(
+        parse_errors(src),
+        vec![
+            "main.c:1:12: invalid type specifier: []",
+            "main.c:5:12: could not parse expression, found Semicolon",
+            "main.c:7:12: expected CloseParen, got OpenBrace",
+            "main.c:11:1: expected Semicolon, got CloseBrace",
+            "main.c:12:24: expected Semicolon, got Constant(\"2\")",
+        ]
+    );
+}
+
+#[test]
+fn test_parser_single_error_isnt_wrapped() {
+    let result = parse_program("int main(void) { int x = ; return 0; }");
+
+    assert!(
+        matches!(result, Err(CompileError::Parse(..))),
+        "Expected a single parse error, got {result:?}"
+    );
+}
+
+#[test]
+fn test_parser_recovery_keeps_the_enclosing_block() {
+    // the `}` found instead of a `;` still closes the block, and so do the braces skipped
+    for src in [
+        "int main(void) { return 1 } int g(void) { return 2 + ; }",
+        "int main(void) { int a[2] = {1 2}; return a[0] +; }",
+        "int main(void) { while (1 { break; } return 1 +; }",
+        "int main(void) { for (int i = 0; i < 3; i++) i = i * ; return 0 +; }",
+    ] {
+        assert_eq!(parse_errors(src).len(), 2, "Expected two errors in {src}");
+    }
+}
+
+#[test]
+fn test_parser_recovery_stops_at_statements_and_declarations() {
+    let src = "int main(void) { int x = 1 + \n int y = 2; if (y) return y; return x +; }";
+    assert_eq!(parse_errors(src).len(), 2);
+
+    // the statement after a missing `)` is parsed
+    let src = "int main(void) { if (1 return 0; return 1 +; }";
+    assert_eq!(parse_errors(src).len(), 2);
+
+    // an unclosed parenthesis ends at the `;`, unless it's a `for` header
+    let src = "int f(int a, ; int main(void) { for (int i = 0; i < ; i++) ; return 0 +; }";
+    assert_eq!(parse_errors(src).len(), 3);
+}
+
+#[test]
+fn test_parser_recovery_after_unclosed_declarator() {
+    // the body of the function closes the parenthesis or bracket left open in its declarator
+    let src = "int g(void { return 1; } int main(void) { return 1 +; }";
+    assert_eq!(parse_errors(src).len(), 2, "{:?}", parse_errors(src));
+
+    let src = "int g(int a[3) { return a[0]; } \
+               int h(void) { return 2 +; } \
+               int main(void) { return 1 +; }";
+    assert_eq!(parse_errors(src).len(), 3, "{:?}", parse_errors(src));
+}
+
+#[test]
+fn test_parser_recovery_keeps_typedefs() {
+    // the typedef names stay visible after an error in a declaration that opened scopes
+    let src = "typedef int T; \
+               int f(int a, ; \
+               int main(void) { int x = ; T y = 1; return y +; }";
+    assert_eq!(parse_errors(src).len(), 3);
+}
+
+#[test]
+fn test_parser_missing_brace_at_end_reported_once() {
+    let result = parse_program("int main(void) { if (1) { while (1) { return 0;");
+
+    assert_eq!(
+        result.map(|_| ()),
+        Err(ParseError::UnexpectedEnd(Some(Token::CloseBrace)).at(Default::default()))
+    );
+}
+
+#[test]
+fn test_parser_skips_tokens_outside_declarations() {
+    // the stray tokens are skipped up to the next declaration
+    let src = "int a; ) ] int b = ; int c;";
+    assert_eq!(
+        parse_errors(src)
+            .iter()
+            .map(|error| error.split(": ").last().unwrap_or_default())
+            .collect::<Vec<_>>(),
+        vec![
+            "expected a declaration, found CloseParen",
+            "could not parse expression, found Semicolon",
+        ]
+    );
+}
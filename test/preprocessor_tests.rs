@@ -0,0 +1,590 @@
+use std::fs;
+use std::path::PathBuf;
+
+use fcc::common::error::{CompileError, PreprocessError};
+use fcc::lexer::{Token, lex};
+use fcc::preprocessor::Preprocessor;
+
+// Helper: preprocesses a source as if it were read from main.c
+fn preprocess(src: &str) -> Result<String, CompileError> {
+    Preprocessor::new().preprocess(src, "main.c")
+}
+
+// Helper: the lines of code in the output, without line markers and blank lines. The
+// pieces of a source line the output splits, after a long expansion, are joined by a space
+fn code_lines(src: &str) -> Vec<String> {
+    let output = preprocess(src).unwrap_or_else(|error| panic!("should preprocess: {error}"));
+    let mut lines: Vec<(usize, String)> = vec![];
+    let mut number = 1;
+    for line in output.lines() {
+        if let Some(marker) = line.strip_prefix("# ") {
+            number = marker
+                .split(' ')
+                .next()
+                .and_then(|n| n.parse().ok())
+                .unwrap_or_else(|| panic!("bad line marker: {line}"));
+            continue;
+        }
+        let code = line.trim().to_string();
+        match lines.last_mut() {
+            Some((last, text)) if *last == number && !code.is_empty() => {
+                text.push(' ');
+                text.push_str(&code);
+            }
+            _ if !code.is_empty() => lines.push((number, code)),
+            _ => {}
+        }
+        number += 1;
+    }
+
+    lines.into_iter().map(|(_, text)| text).collect()
+}
+
+// Helper: a fresh directory for the files a test includes
+fn test_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fcc_preprocessor_{name}"));
+    let _ = fs::remove_dir_all(&dir);
+    for (file, contents) in files {
+        let path = dir.join(file);
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, contents));
+        if let Err(error) = written {
+            panic!("couldn't write {}: {error}", path.display());
+        }
+    }
+    dir
+}
+
+// =============================================================================
+// LINES AND COMMENTS
+// =============================================================================
+
+#[test]
+fn test_plain_source_is_unchanged() {
+    let output = preprocess("int main(void) {\n    return 0;\n}\n").expect("should preprocess");
+    assert_eq!(
+        output,
+        "# 1 \"main.c\"\nint main(void) {\n    return 0;\n}\n"
+    );
+}
+
+#[test]
+fn test_comments_and_splices() {
+    let src = "int a; /* one */ int b; // two\nint /* multi\nline */ c;\nint \\\nd; char *s = \"/* kept */\";";
+    assert_eq!(
+        code_lines(src),
+        vec![
+            "int a;           int b;",
+            "int   c;",
+            "int d; char *s = \"/* kept */\";",
+        ]
+    );
+}
+
+#[test]
+fn test_unterminated_comment() {
+    assert!(preprocess("int a; /* never closed\nint b;").is_err());
+}
+
+#[test]
+fn test_line_markers_follow_skipped_lines() {
+    let src = "#define A 1\nint a;\n#if 0\n\n\n\n\n\n\n\n\n\n#endif\nint b;\n\n\nint c;";
+    let output = preprocess(src).expect("should preprocess");
+    assert_eq!(
+        output,
+        "# 2 \"main.c\"\nint a;\n# 14 \"main.c\"\nint b;\n\n\nint c;\n"
+    );
+}
+
+// =============================================================================
+// OBJECT-LIKE MACROS
+// =============================================================================
+
+#[test]
+fn test_object_like_macros() {
+    let src = "#define SIZE 10\n#define DOUBLE_SIZE (SIZE * 2)\nint a = DOUBLE_SIZE;\n#undef SIZE\nint b = SIZE;";
+    // the tokens after an expansion stay at their columns
+    assert_eq!(
+        code_lines(src),
+        vec!["int a = (10 * 2)   ;", "int b = SIZE;"]
+    );
+}
+
+#[test]
+fn test_self_referential_macros_stop() {
+    let src = "#define foo foo + 1\n#define a b\n#define b a\nint x = foo; int y = a;";
+    assert_eq!(code_lines(src), vec!["int x = foo + 1 ; int y = a;"]);
+}
+
+#[test]
+fn test_expansion_doesnt_merge_tokens() {
+    let src = "#define MINUS -\nint x = -MINUS 1;";
+    assert_eq!(code_lines(src), vec!["int x = - -     1;"]);
+}
+
+#[test]
+fn test_macro_redefinition() {
+    assert!(preprocess("#define A 1 + 2\n#define A 1  +  2\nint a = A;").is_ok());
+    assert!(preprocess("#define A 1\n#define A 2").is_err());
+    assert!(preprocess("#define A(x) x\n#define A 1").is_err());
+}
+
+#[test]
+fn test_invalid_defines() {
+    for src in [
+        "#define",
+        "#define 1 2",
+        "#define defined 1",
+        "#define F(x, x) x",
+        "#define F(x y) x",
+        "#define F(..., x) x",
+        "#define F(x) # y",
+        "#define F(x) ## x",
+        "#define F(x) x ##",
+        "#define F(x) __VA_ARGS__",
+    ] {
+        assert!(preprocess(src).is_err(), "Should fail to preprocess: {src}");
+    }
+}
+
+// =============================================================================
+// FUNCTION-LIKE MACROS
+// =============================================================================
+
+#[test]
+fn test_function_like_macros() {
+    let src = "#define MAX(a, b) ((a) > (b) ? (a) : (b))\n#define ID(x) x\n#define NONE() 0\n\
+               int m = MAX(ID(1), (2, 3)); int n = NONE(); int (*p)(int) = ID;";
+    assert_eq!(
+        code_lines(src),
+        vec!["int m = ((1) > ((2, 3)) ? (1) : ((2, 3))) ; int n = 0     ; int (*p)(int) = ID;"]
+    );
+}
+
+#[test]
+fn test_space_before_paren_makes_object_like_macro() {
+    let src = "#define F (x) + 1\nint a = F;";
+    assert_eq!(code_lines(src), vec!["int a = (x) + 1 ;"]);
+}
+
+#[test]
+fn test_invocation_over_several_lines() {
+    let src = "#define ADD(a, b) a + b\nint x = ADD(1,\n            2);\nint y;";
+    let output = preprocess(src).expect("should preprocess");
+    // the `;` stays on its line, at its column
+    assert_eq!(
+        output,
+        "# 2 \"main.c\"\nint x = 1 + 2\n              ;\nint y;\n"
+    );
+}
+
+#[test]
+fn test_stringification() {
+    let src = "#define STR(x) #x\n#define XSTR(x) STR(x)\n#define N 42\n\
+               char *a = STR(  a  +   \"q\\n\" ); char *b = STR(N); char *c = XSTR(N);";
+    assert_eq!(
+        code_lines(src),
+        vec![r#"char *a = "a + \"q\\n\""      ; char *b = "N"   ; char *c = "42"   ;"#]
+    );
+}
+
+#[test]
+fn test_token_pasting() {
+    let src = "#define CAT(a, b) a ## b\n#define N 1\n\
+               int CAT(var, 1) = CAT(0x, 1F) + CAT(, 2) + CAT(3,) + CAT(N, N); CAT(+, =)";
+    assert_eq!(
+        code_lines(src),
+        vec!["int var1        = 0x1F        + 2        + 3       + NN       ; +="]
+    );
+}
+
+#[test]
+fn test_invalid_token_pasting() {
+    assert!(preprocess("#define CAT(a, b) a ## b\nint x = CAT(+, -);").is_err());
+}
+
+#[test]
+fn test_variadic_macros() {
+    let src = "#define LOG(fmt, ...) printf(fmt, __VA_ARGS__)\n#define ALL(...) f(__VA_ARGS__)\n\
+               LOG(\"%d %d\", 1, (2, 3)); ALL(); ALL(a, b);";
+    assert_eq!(
+        code_lines(src),
+        vec![r#"printf("%d %d", 1, (2, 3)) ; f()  ; f(a, b)  ;"#]
+    );
+}
+
+#[test]
+fn test_wrong_number_of_arguments() {
+    for src in [
+        "#define F(a, b) a\nF(1)",
+        "#define F(a) a\nF(1, 2)",
+        "#define F() 0\nF(1)",
+        "#define F(a, b, ...) a\nF(1)",
+        "#define F(a) a\nF(1",
+    ] {
+        assert!(preprocess(src).is_err(), "Should fail to preprocess: {src}");
+    }
+}
+
+#[test]
+fn test_rescanning_finds_later_arguments() {
+    let src = "#define F(x) x + 1\n#define G F\nint a = G(2);\n#define H() F\nint b = H()(3);";
+    assert_eq!(code_lines(src), vec!["int a = 2 + 1 ;", "int b = 3 + 1 ;"]);
+}
+
+// =============================================================================
+// CONDITIONAL COMPILATION
+// =============================================================================
+
+#[test]
+fn test_conditional_branches() {
+    let src = "#define TWO 2\n\
+               #if TWO == 1\nint one;\n#elif TWO == 2\nint two;\n#elif 1\nint other;\n#else\nint none;\n#endif";
+    assert_eq!(code_lines(src), vec!["int two;"]);
+}
+
+#[test]
+fn test_ifdef_and_ifndef() {
+    let src = "#define A\n#ifdef A\nint a;\n#endif\n#ifndef A\nint not_a;\n#else\nint else_a;\n#endif\n\
+               #ifdef B\nint b;\n#endif";
+    assert_eq!(code_lines(src), vec!["int a;", "int else_a;"]);
+}
+
+#[test]
+fn test_nested_conditionals() {
+    let src = "#if 0\n#if 1\nint a;\n#else\nint b;\n#endif\n#elif 1\n#if 0\nint c;\n#else\nint d;\n#endif\n#endif";
+    assert_eq!(code_lines(src), vec!["int d;"]);
+}
+
+#[test]
+fn test_skipped_groups_ignore_other_directives() {
+    let src =
+        "#if 0\n#error not reached\n#include \"missing.h\"\n#unknown\nit's skipped\n#endif\nint a;";
+    assert_eq!(code_lines(src), vec!["int a;"]);
+}
+
+#[test]
+fn test_if_expressions() {
+    for (condition, expected) in [
+        ("defined(A) && !defined B", true),
+        ("defined A || defined(B)", true),
+        ("A + 1 == 3 && UNDEFINED == 0", true),
+        ("(1 << 4) - 0x10 + 010 == 8", true),
+        ("-1 < 0", true),
+        ("-1 < 0u", false),
+        ("0 ? 1 / 0 : 7 % 4 == 3", true),
+        ("0 && 1 / 0", false),
+        ("'a' == 97 && '\\n' == 10", true),
+        ("~0 == -1 && !0 && 6 / -4 == -1 && (3 ^ 5 | 8) == 14", true),
+        ("0xFFFFFFFFFFFFFFFF > 0", true),
+    ] {
+        let src = format!("#define A 2\n#if {condition}\nyes\n#else\nno\n#endif");
+        let expected = if expected { "yes" } else { "no" };
+        assert_eq!(code_lines(&src), vec![expected], "#if {condition}");
+    }
+}
+
+#[test]
+fn test_invalid_conditionals() {
+    for src in [
+        "#if\n#endif",
+        "#if 1 +\n#endif",
+        "#if (1\n#endif",
+        "#if 1 / 0\n#endif",
+        "#if 1.0\n#endif",
+        "#if defined\n#endif",
+        "#ifdef\n#endif",
+        "#if 1\nint a;",
+        "#endif",
+        "#else",
+        "#elif 1",
+        "#if 1\n#else\n#elif 1\n#endif",
+        "#if 1\n#else\n#else\n#endif",
+    ] {
+        assert!(preprocess(src).is_err(), "Should fail to preprocess: {src}");
+    }
+}
+
+// =============================================================================
+// OTHER DIRECTIVES AND PREDEFINED MACROS
+// =============================================================================
+
+#[test]
+fn test_error_directive() {
+    let result = preprocess("#if 1\n#error unsupported  platform\n#endif");
+    match result {
+        Err(CompileError::Preprocess(PreprocessError::ErrorDirective(message), span)) => {
+            assert_eq!(message, "unsupported platform");
+            assert_eq!(span.to_string(), "main.c:2:1");
+        }
+        result => panic!("Expected an #error, got {result:?}"),
+    }
+}
+
+#[test]
+fn test_pragmas_and_null_directive() {
+    let src = "#\n#pragma pack(1)\nint a;";
+    assert_eq!(code_lines(src), vec!["int a;"]);
+}
+
+#[test]
+fn test_unknown_directive() {
+    assert!(preprocess("#warning not supported").is_err());
+}
+
+#[test]
+fn test_line_and_file_macros() {
+    let src = "int a = __LINE__;\n\nchar *f = __FILE__; int b = __LINE__;\n#if __LINE__ == 4\nint c;\n#endif";
+    assert_eq!(
+        code_lines(src),
+        vec![
+            "int a = 1       ;",
+            "char *f = \"main.c\"; int b = 3       ;",
+            "int c;"
+        ]
+    );
+}
+
+#[test]
+fn test_line_directives_renumber_the_following_lines() {
+    let src =
+        "int a;\n#line 100 \"orig.c\"\nint b = __LINE__;\nchar *f = __FILE__;\n#line 7\nint c;";
+    let output = preprocess(src).expect("should preprocess");
+    assert_eq!(
+        output,
+        "# 1 \"main.c\"\nint a;\n# 100 \"orig.c\"\nint b = 100     ;\nchar *f = \"orig.c\";\n\
+         # 7 \"orig.c\"\nint c;\n"
+    );
+
+    // the markers of a preprocessed source, with their flags
+    let src = "# 1 \"gen.c\"\n# 1 \"<built-in>\" 1 3 4\n# 50 \"gen.c\" 2\nint x;";
+    let output = preprocess(src).expect("should preprocess");
+    assert_eq!(output, "# 50 \"gen.c\"\nint x;\n");
+
+    for src in [
+        "#line",
+        "#line x",
+        "#line 10 main.c",
+        "#line 10 \"main.c\" 2",
+        "# 10 \"main.c\" x",
+    ] {
+        assert!(
+            matches!(
+                preprocess(src),
+                Err(CompileError::Preprocess(
+                    PreprocessError::InvalidLineDirective,
+                    _
+                ))
+            ),
+            "Should fail: {src}"
+        );
+    }
+}
+
+#[test]
+fn test_predefined_macros() {
+    let src = "#if __STDC__ && __STDC_VERSION__ >= 201112L\nint a;\n#endif";
+    assert_eq!(code_lines(src), vec!["int a;"]);
+}
+
+// =============================================================================
+// INCLUDES
+// =============================================================================
+
+#[test]
+fn test_include_with_search_paths() {
+    let dir = test_dir(
+        "search",
+        &[
+            (
+                "src/main.c",
+                "#include \"local.h\"\n#include <lib.h>\nint main;",
+            ),
+            ("src/local.h", "int local;"),
+            ("include/lib.h", "#include \"sub/nested.h\"\nint lib;"),
+            ("include/sub/nested.h", "int nested;"),
+        ],
+    );
+    let main = dir.join("src/main.c");
+    let include = dir.join("include").display().to_string();
+
+    let output = Preprocessor::new()
+        .with_include_dirs(std::slice::from_ref(&include))
+        .preprocess_file(&main.display().to_string())
+        .expect("should preprocess");
+    let code: Vec<_> = output
+        .lines()
+        .filter(|line| !line.starts_with("# "))
+        .collect();
+    assert_eq!(
+        code,
+        vec!["int local;", "int nested;", "int lib;", "int main;"]
+    );
+    assert!(output.contains(&format!("# 1 \"{include}/sub/nested.h\"")));
+    assert!(output.contains(&format!("# 3 \"{}\"", main.display())));
+
+    // the directory of the including file is only searched for quoted names
+    let result = Preprocessor::new().preprocess_file(&main.display().to_string());
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_include_guards_and_pragma_once() {
+    let dir = test_dir(
+        "once",
+        &[
+            (
+                "main.c",
+                "#include \"guarded.h\"\n#include \"once.h\"\n#include \"guarded.h\"\n#include \"./once.h\"",
+            ),
+            (
+                "guarded.h",
+                "#ifndef GUARDED_H\n#define GUARDED_H\nint guarded;\n#endif",
+            ),
+            ("once.h", "#pragma once\nint once;"),
+        ],
+    );
+
+    let output = Preprocessor::new()
+        .preprocess_file(&dir.join("main.c").display().to_string())
+        .expect("should preprocess");
+    assert_eq!(output.matches("int guarded;").count(), 1);
+    assert_eq!(output.matches("int once;").count(), 1);
+}
+
+#[test]
+fn test_computed_include_and_macros_across_files() {
+    let dir = test_dir(
+        "computed",
+        &[
+            (
+                "main.c",
+                "#define HEADER \"defs.h\"\n#include HEADER\nint x = VALUE;",
+            ),
+            ("defs.h", "#define VALUE 7"),
+        ],
+    );
+
+    let output = Preprocessor::new()
+        .preprocess_file(&dir.join("main.c").display().to_string())
+        .expect("should preprocess");
+    assert!(output.ends_with("int x = 7    ;\n"));
+}
+
+#[test]
+fn test_include_errors() {
+    let dir = test_dir(
+        "errors",
+        &[
+            ("missing.c", "#include \"missing.h\""),
+            ("invalid.c", "#include missing.h"),
+            ("recursive.c", "#include \"recursive.c\""),
+            ("error.c", "#include \"error.h\""),
+            ("error.h", "#error from a header"),
+        ],
+    );
+
+    for file in ["missing.c", "invalid.c", "recursive.c", "error.c"] {
+        let result = Preprocessor::new().preprocess_file(&dir.join(file).display().to_string());
+        assert!(result.is_err(), "Should fail to preprocess: {file}");
+    }
+}
+
+#[test]
+fn test_errors_point_at_their_directive() {
+    let dir = test_dir(
+        "error_spans",
+        &[
+            ("main.c", "int a;\n  #include \"missing.h\"\n"),
+            ("header.c", "#include \"header.h\"\n"),
+            ("header.h", "int b;\n#if 1 +\n#endif\n"),
+        ],
+    );
+
+    let main = dir.join("main.c").display().to_string();
+    match Preprocessor::new().preprocess_file(&main) {
+        Err(CompileError::Preprocess(PreprocessError::IncludeNotFound(name), span)) => {
+            assert_eq!(name, "missing.h");
+            assert_eq!(span.to_string(), format!("{main}:2:3"));
+            assert_eq!(span.end - span.start, "#include \"missing.h\"".len());
+        }
+        result => panic!("Expected a missing include, got {result:?}"),
+    }
+
+    let header = dir.join("header.h").display().to_string();
+    let result = Preprocessor::new().preprocess_file(&dir.join("header.c").display().to_string());
+    match result {
+        Err(CompileError::Preprocess(PreprocessError::InvalidCondition(None), span)) => {
+            assert_eq!(span.to_string(), format!("{header}:2:1"));
+        }
+        result => panic!("Expected an invalid #if, got {result:?}"),
+    }
+}
+
+#[test]
+fn test_error_kinds() {
+    for (src, expected) in [
+        (
+            "int a; /* never closed",
+            PreprocessError::UnterminatedComment,
+        ),
+        (
+            "#else\n#endif",
+            PreprocessError::UnmatchedDirective("else".to_string()),
+        ),
+        ("#if 1\nint a;", PreprocessError::UnterminatedConditional),
+        (
+            "#define A 1\n#define A 2",
+            PreprocessError::MacroRedefined("A".to_string()),
+        ),
+        (
+            "#define F(a, b) a\nint x = F(1);",
+            PreprocessError::WrongMacroArgumentCount {
+                name: "F".to_string(),
+                expected: 2,
+                found: 1,
+            },
+        ),
+        ("#if 1 / 0\n#endif", PreprocessError::DivisionByZero),
+        (
+            "#warning no",
+            PreprocessError::InvalidDirective("warning".to_string()),
+        ),
+    ] {
+        match preprocess(src) {
+            Err(CompileError::Preprocess(error, span)) => {
+                assert_eq!(error, expected, "{src}");
+                assert!(!span.is_unknown(), "{src}");
+            }
+            result => panic!("Expected a preprocessor error for {src}, got {result:?}"),
+        }
+    }
+}
+
+// =============================================================================
+// LEXING THE OUTPUT
+// =============================================================================
+
+#[test]
+fn test_output_lexes() {
+    let src = "#define RET(x) return x;\nint main(void) {\n    RET(__LINE__)\n}";
+    let output = preprocess(src).expect("should preprocess");
+    let tokens = lex(&output).expect("should lex");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Int,
+            Token::Identifier("main".to_string()),
+            Token::OpenParen,
+            Token::Void,
+            Token::CloseParen,
+            Token::OpenBrace,
+            Token::Return,
+            Token::Constant("3".to_string()),
+            Token::Semicolon,
+            Token::CloseBrace,
+        ]
+    );
+}
@@ -3,33 +3,59 @@ This file covers: Semantic analysis - VariableResolver and LoopLabeler (happy/er
 Tests variable resolution, duplicate detection, undeclared variables, invalid lvalues.
 Tests loop labeling for while, do-while, for loops and break/continue statements.
 Does NOT cover: exact renamed identifier format.
-Suggestions: add tests for future semantic features (type checking, etc).
+Also covers function declarations and the TypeChecker checks on calls.
+Also covers file scope variables and storage class linkage rules.
+Also covers the TypeChecker rules on pointers and lvalues, and array decay and initializers.
+Also covers character promotions and string literal initializers.
+Also covers structure tags, layouts and member access.
+Also covers the GotoLabeler checks and renaming of labels.
+Also covers switch statements: case values, their labels and break targets.
+Also covers the TypeChecker rules on compound assignments and `++`/`--`.
+Also covers declaration lists and the comma operator.
+Also covers enumerations: enumerator values, scopes and uses as constants.
+Also covers typedef names standing for structure types.
+Also covers function pointers: decay of function names, indirect calls and signatures.
+Also covers variadic calls and the TypeChecker rules on the `__builtin_va_*` builtins.
+Also covers the TypeChecker rules on `const` lvalues and qualified pointer conversions.
 */
 
-use fcc::c_ast::ast::{BlockItem, Expression, Identifier, Program, Statement};
+use fcc::c_ast::ast::{
+    BlockItem, Const, Declaration, Expression, Identifier, Initializer, Program, Statement, Type,
+    UnaryOperator,
+};
 use fcc::c_ast::semantic::loop_lab::LoopLabeler;
+use fcc::common::error::{CompileError, SemanticError};
 use fcc::common::folder::FolderC;
+use fcc::common::symbols::{IdentifierAttrs, InitialValue, StaticInit};
 use fcc::driver::validate_semantics;
-use fcc::lexer::lex;
+use fcc::lexer::{lex, lex_spanned};
 
 // Helper: lex, parse, and validate source code
-fn validate_program(src: &str) -> Result<Program, String> {
+fn validate_program(src: &str) -> Result<Program, CompileError> {
     let tokens = lex(src)?;
     let program = Program::try_from(tokens)?;
     validate_semantics(program)
 }
 
 // Helper: lex, parse, and apply loop labeling
-fn label_loops(src: &str) -> Result<Program, String> {
+fn label_loops(src: &str) -> Result<Program, CompileError> {
     let tokens = lex(src)?;
     let program = Program::try_from(tokens)?;
     let mut labeler = LoopLabeler::new(Identifier::new("dummy".to_string()));
-    labeler.fold_prog(program)
+    let program = labeler.fold_prog(program)?;
+    match CompileError::from_errors(labeler.take_errors()) {
+        Some(error) => Err(error),
+        None => Ok(program),
+    }
 }
 
 // Helper: get block items as a vector from a validated program
 fn get_body_items(program: &Program) -> Vec<&BlockItem> {
-    program.function_definition().body().iter().collect()
+    program
+        .function_definition("main")
+        .and_then(|main| main.body())
+        .map(|body| body.iter().collect())
+        .unwrap_or_default()
 }
 
 // =============================================================================
@@ -52,7 +78,7 @@ fn test_semantic_variable_resolution_basic() {
 
     // Get the return variable name
     let return_var_name = match items[1] {
-        BlockItem::S(Statement::Return(Expression::Var(id))) => id.value().to_string(),
+        BlockItem::S(Statement::Return(Expression::Var(id, _), _)) => id.value().to_string(),
         _ => panic!("Expected return with variable"),
     };
 
@@ -243,7 +269,7 @@ fn test_loop_labeling_while_gets_label() {
     let items = get_body_items(&labeled);
 
     match items[0] {
-        BlockItem::S(Statement::While(_, _, label)) => {
+        BlockItem::S(Statement::While(_, _, label, _)) => {
             assert!(
                 label.value().starts_with("loop_st"),
                 "While loop should have a loop label"
@@ -260,7 +286,7 @@ fn test_loop_labeling_do_while_gets_label() {
     let items = get_body_items(&labeled);
 
     match items[0] {
-        BlockItem::S(Statement::DoWhile(_, _, label)) => {
+        BlockItem::S(Statement::DoWhile(_, _, label, _)) => {
             assert!(
                 label.value().starts_with("loop_st"),
                 "Do-while loop should have a loop label"
@@ -277,7 +303,7 @@ fn test_loop_labeling_for_gets_label() {
     let items = get_body_items(&labeled);
 
     match items[0] {
-        BlockItem::S(Statement::For(_, _, _, _, label)) => {
+        BlockItem::S(Statement::For(_, _, _, _, label, _)) => {
             assert!(
                 label.value().starts_with("loop_st"),
                 "For loop should have a loop label"
@@ -294,13 +320,13 @@ fn test_loop_labeling_break_inherits_label() {
     let items = get_body_items(&labeled);
 
     let loop_label = match items[0] {
-        BlockItem::S(Statement::While(_, _, label)) => label.value().to_string(),
+        BlockItem::S(Statement::While(_, _, label, _)) => label.value().to_string(),
         _ => panic!("Expected while statement"),
     };
 
     let break_label = match items[0] {
-        BlockItem::S(Statement::While(_, body, _)) => match body.as_ref() {
-            Statement::Break(label) => label.value().to_string(),
+        BlockItem::S(Statement::While(_, body, _, _)) => match body.as_ref() {
+            Statement::Break(label, _) => label.value().to_string(),
             _ => panic!("Expected break statement in body"),
         },
         _ => panic!("Expected while statement"),
@@ -319,13 +345,13 @@ fn test_loop_labeling_continue_inherits_label() {
     let items = get_body_items(&labeled);
 
     let loop_label = match items[0] {
-        BlockItem::S(Statement::While(_, _, label)) => label.value().to_string(),
+        BlockItem::S(Statement::While(_, _, label, _)) => label.value().to_string(),
         _ => panic!("Expected while statement"),
     };
 
     let continue_label = match items[0] {
-        BlockItem::S(Statement::While(_, body, _)) => match body.as_ref() {
-            Statement::Continue(label) => label.value().to_string(),
+        BlockItem::S(Statement::While(_, body, _, _)) => match body.as_ref() {
+            Statement::Continue(label, _) => label.value().to_string(),
             _ => panic!("Expected continue statement in body"),
         },
         _ => panic!("Expected while statement"),
@@ -344,13 +370,13 @@ fn test_loop_labeling_nested_loops_different_labels() {
     let items = get_body_items(&labeled);
 
     let outer_label = match items[0] {
-        BlockItem::S(Statement::While(_, _, label)) => label.value().to_string(),
+        BlockItem::S(Statement::While(_, _, label, _)) => label.value().to_string(),
         _ => panic!("Expected outer while statement"),
     };
 
     let inner_label = match items[0] {
-        BlockItem::S(Statement::While(_, body, _)) => match body.as_ref() {
-            Statement::While(_, _, label) => label.value().to_string(),
+        BlockItem::S(Statement::While(_, body, _, _)) => match body.as_ref() {
+            Statement::While(_, _, label, _) => label.value().to_string(),
             _ => panic!("Expected inner while statement"),
         },
         _ => panic!("Expected outer while statement"),
@@ -370,8 +396,8 @@ fn test_loop_labeling_break_in_nested_loop() {
 
     // Get inner loop label
     let inner_label = match items[0] {
-        BlockItem::S(Statement::While(_, body, _)) => match body.as_ref() {
-            Statement::While(_, _, label) => label.value().to_string(),
+        BlockItem::S(Statement::While(_, body, _, _)) => match body.as_ref() {
+            Statement::While(_, _, label, _) => label.value().to_string(),
             _ => panic!("Expected inner while"),
         },
         _ => panic!("Expected outer while"),
@@ -379,9 +405,9 @@ fn test_loop_labeling_break_in_nested_loop() {
 
     // Get break label
     let break_label = match items[0] {
-        BlockItem::S(Statement::While(_, outer_body, _)) => match outer_body.as_ref() {
-            Statement::While(_, inner_body, _) => match inner_body.as_ref() {
-                Statement::Break(label) => label.value().to_string(),
+        BlockItem::S(Statement::While(_, outer_body, _, _)) => match outer_body.as_ref() {
+            Statement::While(_, inner_body, _, _) => match inner_body.as_ref() {
+                Statement::Break(label, _) => label.value().to_string(),
                 _ => panic!("Expected break"),
             },
             _ => panic!("Expected inner while"),
@@ -402,7 +428,7 @@ fn test_loop_labeling_for_with_break_continue() {
     let items = get_body_items(&labeled);
 
     let for_label = match items[0] {
-        BlockItem::S(Statement::For(_, _, _, _, label)) => label.value().to_string(),
+        BlockItem::S(Statement::For(_, _, _, _, label, _)) => label.value().to_string(),
         _ => panic!("Expected for statement"),
     };
 
@@ -419,13 +445,13 @@ fn test_loop_labeling_do_while_with_break() {
     let items = get_body_items(&labeled);
 
     let loop_label = match items[0] {
-        BlockItem::S(Statement::DoWhile(_, _, label)) => label.value().to_string(),
+        BlockItem::S(Statement::DoWhile(_, _, label, _)) => label.value().to_string(),
         _ => panic!("Expected do-while statement"),
     };
 
     let break_label = match items[0] {
-        BlockItem::S(Statement::DoWhile(body, _, _)) => match body.as_ref() {
-            Statement::Break(label) => label.value().to_string(),
+        BlockItem::S(Statement::DoWhile(body, _, _, _)) => match body.as_ref() {
+            Statement::Break(label, _) => label.value().to_string(),
             _ => panic!("Expected break statement"),
         },
         _ => panic!("Expected do-while statement"),
@@ -465,7 +491,7 @@ fn test_semantic_for_loop_init_shadows_outer() {
 
     // Get the return statement after for loop - should use outer i
     let return_name = match items[2] {
-        BlockItem::S(Statement::Return(Expression::Var(id))) => id.value().to_string(),
+        BlockItem::S(Statement::Return(Expression::Var(id, _), _)) => id.value().to_string(),
         _ => panic!("Expected return with variable"),
     };
 
@@ -570,3 +596,2009 @@ fn test_semantic_error_undeclared_in_do_while_condition() {
         "Undeclared variable in do-while condition should fail"
     );
 }
+
+// =============================================================================
+// FUNCTIONS
+// =============================================================================
+
+#[test]
+fn test_semantic_function_call_keeps_name() {
+    let src = "int foo(int a); int main(void){ return foo(1); } int foo(int a) { return a; }";
+    let validated = validate_program(src).expect("should validate");
+
+    let items = get_body_items(&validated);
+    match items[0] {
+        BlockItem::S(Statement::Return(Expression::FunctionCall(name, _, _), _)) => {
+            assert_eq!(name.value(), "foo");
+        }
+        _ => panic!("Expected return with function call"),
+    }
+}
+
+#[test]
+fn test_semantic_params_are_renamed() {
+    let src = "int foo(int a) { return a; } int main(void){ return foo(1); }";
+    let validated = validate_program(src).expect("should validate");
+
+    let foo = validated
+        .function_definition("foo")
+        .expect("foo is defined");
+    let param = foo.params()[0].value().to_string();
+    assert_ne!(param, "a", "Parameters should be renamed");
+
+    match foo.body().expect("foo has a body").iter().next() {
+        Some(BlockItem::S(Statement::Return(Expression::Var(id, _), _))) => {
+            assert_eq!(id.value(), param);
+        }
+        _ => panic!("Expected return with parameter"),
+    }
+}
+
+#[test]
+fn test_semantic_error_wrong_argument_count() {
+    let src = "int foo(int a, int b); int main(void){ return foo(1); }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Wrong number of arguments should fail");
+}
+
+#[test]
+fn test_semantic_error_incompatible_declarations() {
+    let src = "int foo(int a); int foo(int a, int b); int main(void){ return 0; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Incompatible declarations should fail");
+}
+
+#[test]
+fn test_semantic_error_function_defined_twice() {
+    let src = "int foo(void) { return 1; } int foo(void) { return 2; } int main(void){ return 0; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Function defined twice should fail");
+}
+
+#[test]
+fn test_semantic_error_nested_function_definition() {
+    let src = "int main(void){ int foo(void) { return 1; } return foo(); }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Nested function definition should fail");
+}
+
+#[test]
+fn test_semantic_error_undeclared_function() {
+    let src = "int main(void){ return foo(); }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Undeclared function should fail");
+}
+
+#[test]
+fn test_semantic_error_variable_called() {
+    let src = "int main(void){ int x = 1; return x(); }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Calling a variable should fail");
+}
+
+#[test]
+fn test_semantic_error_function_used_as_variable() {
+    let src = "int foo(void); int main(void){ return foo + 1; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Using a function as variable should fail");
+}
+
+#[test]
+fn test_semantic_error_duplicate_parameter() {
+    let src = "int foo(int a, int a) { return a; } int main(void){ return 0; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Duplicate parameter should fail");
+}
+
+#[test]
+fn test_semantic_error_local_variable_shadows_param() {
+    let src = "int foo(int a) { int a = 1; return a; } int main(void){ return 0; }";
+    let result = validate_program(src);
+    assert!(
+        result.is_err(),
+        "Redeclaring a parameter in the body should fail"
+    );
+}
+
+// =============================================================================
+// FILE SCOPE VARIABLES AND STORAGE CLASSES
+// =============================================================================
+
+#[test]
+fn test_semantic_file_scope_variable_keeps_name() {
+    let src = "int x = 1; int main(void){ return x; }";
+    let validated = validate_program(src).expect("should validate");
+
+    let items = get_body_items(&validated);
+    match items[0] {
+        BlockItem::S(Statement::Return(Expression::Var(id, _), _)) => {
+            assert_eq!(id.value(), "x");
+        }
+        _ => panic!("Expected return with file scope variable"),
+    }
+}
+
+#[test]
+fn test_semantic_local_extern_refers_to_file_scope() {
+    let src = "int main(void){ int x = 1; { extern int x; return x; } } int x = 3;";
+    let validated = validate_program(src).expect("should validate");
+
+    let symbol = validated
+        .symbols()
+        .get("x")
+        .expect("x is in the symbol table");
+    assert!(symbol.is_static());
+    assert!(symbol.is_global());
+}
+
+#[test]
+fn test_semantic_local_static_is_not_global() {
+    let src = "int main(void){ static int x = 1; return x; }";
+    let validated = validate_program(src).expect("should validate");
+
+    let items = get_body_items(&validated);
+    match items[1] {
+        BlockItem::S(Statement::Return(Expression::Var(id, _), _)) => {
+            let symbol = validated.symbols().get(id.value()).expect("x is tracked");
+            assert!(symbol.is_static());
+            assert!(!symbol.is_global());
+        }
+        _ => panic!("Expected return with static variable"),
+    }
+}
+
+#[test]
+fn test_semantic_tentative_definitions() {
+    let src = "int x; int x; int x = 2; int main(void){ return x; }";
+    let result = validate_program(src);
+    assert!(result.is_ok(), "Tentative definitions can be repeated");
+}
+
+#[test]
+fn test_semantic_error_conflicting_linkage() {
+    let src = "static int x; int x; int main(void){ return x; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Conflicting variable linkage should fail");
+}
+
+#[test]
+fn test_semantic_error_file_scope_variable_defined_twice() {
+    let src = "int x = 1; int x = 2; int main(void){ return x; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Variable defined twice should fail");
+}
+
+#[test]
+fn test_semantic_error_function_redeclared_as_variable() {
+    let src = "int foo(void); int foo; int main(void){ return 0; }";
+    let result = validate_program(src);
+    assert!(
+        result.is_err(),
+        "Function redeclared as variable should fail"
+    );
+}
+
+#[test]
+fn test_semantic_error_non_constant_static_initializer() {
+    let src = "int main(void){ int a = 1; static int b = a; return b; }";
+    let result = validate_program(src);
+    assert!(
+        result.is_err(),
+        "Non-constant static initializer should fail"
+    );
+}
+
+#[test]
+fn test_semantic_folded_static_initializers() {
+    let src = "static int s = -1; long l = -3; static unsigned long u = -1; int g = 1 + 2; \
+               double d = -1.5; enum { K = 4 }; int k = K * 2; int *p = 1 - 1; \
+               int main(void){ static int local = -(2 + 3); return local; }";
+    let validated = validate_program(src).expect("should validate");
+
+    let init = |name: &str| {
+        let symbol = validated
+            .symbols()
+            .iter()
+            .find(|(key, _)| *key == name || key.starts_with(&format!("{name}.")))
+            .map(|(_, symbol)| symbol)
+            .expect("tracked");
+        match &symbol.attrs {
+            IdentifierAttrs::StaticAttr {
+                init: InitialValue::Initial(init),
+                ..
+            } => init.clone(),
+            _ => panic!("Expected initialized static variable"),
+        }
+    };
+    assert_eq!(init("s"), StaticInit::IntInit(-1));
+    assert_eq!(init("l"), StaticInit::LongInit(-3));
+    assert_eq!(init("u"), StaticInit::ULongInit(u64::MAX));
+    assert_eq!(init("g"), StaticInit::IntInit(3));
+    assert_eq!(init("d"), StaticInit::DoubleInit(-1.5));
+    assert_eq!(init("k"), StaticInit::IntInit(8));
+    assert_eq!(init("p"), StaticInit::ULongInit(0));
+    assert_eq!(init("local"), StaticInit::IntInit(-5));
+}
+
+#[test]
+fn test_semantic_error_invalid_static_pointer_initializer() {
+    let src = "int *p = 2 - 1; int main(void){ return 0; }";
+    let result = validate_program(src);
+    assert!(
+        matches!(
+            result,
+            Err(CompileError::Semantic(
+                SemanticError::InvalidStaticPointerInitializer(_),
+                _
+            ))
+        ),
+        "Non-null pointer constant should fail, got {result:?}"
+    );
+}
+
+#[test]
+fn test_semantic_error_local_extern_with_initializer() {
+    let src = "int main(void){ extern int x = 1; return x; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Local extern with initializer should fail");
+}
+
+#[test]
+fn test_semantic_error_local_extern_conflicts_with_local() {
+    let src = "int main(void){ int x = 1; extern int x; return x; }";
+    let result = validate_program(src);
+    assert!(
+        result.is_err(),
+        "Extern declaration conflicting with a local should fail"
+    );
+}
+
+#[test]
+fn test_semantic_error_static_function_follows_non_static() {
+    let src = "int foo(void); static int foo(void) { return 1; } int main(void){ return foo(); }";
+    let result = validate_program(src);
+    assert!(
+        result.is_err(),
+        "Static function after non-static declaration should fail"
+    );
+}
+
+#[test]
+fn test_semantic_error_static_function_at_block_scope() {
+    let src = "int main(void){ static int foo(void); return foo(); }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Block scope static function should fail");
+}
+
+// =============================================================================
+// LONG INTEGERS
+// =============================================================================
+
+#[test]
+fn test_semantic_implicit_conversions() {
+    let src = "int main(void){ long l = 1; return l + 2; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // the int initializer is converted to long
+    match items[0] {
+        BlockItem::D(Declaration::VarDecl(decl)) => assert!(matches!(
+            decl.initializer(),
+            Some(Initializer::Single(Expression::Cast(Type::Long, _, _)))
+        )),
+        _ => panic!("Expected variable declaration"),
+    }
+    // the int operand is converted to long, then the long result is converted back to int
+    match items[1] {
+        BlockItem::S(Statement::Return(Expression::Cast(Type::Int, inner, _), _)) => {
+            assert!(matches!(
+                inner.as_ref(),
+                Expression::Binary(_, _, right, _) if matches!(right.as_ref(), Expression::Cast(Type::Long, _, _))
+            ));
+        }
+        _ => panic!("Expected return converted to int"),
+    }
+}
+
+#[test]
+fn test_semantic_argument_conversions() {
+    let src = "long foo(long a); int main(void){ return foo(1); }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    match items[0] {
+        BlockItem::S(Statement::Return(Expression::Cast(Type::Int, call, _), _)) => {
+            match call.as_ref() {
+                Expression::FunctionCall(_, args, _) => {
+                    assert!(matches!(args[0], Expression::Cast(Type::Long, _, _)))
+                }
+                _ => panic!("Expected function call"),
+            }
+        }
+        _ => panic!("Expected return converted to int"),
+    }
+}
+
+#[test]
+fn test_semantic_long_static_initializer() {
+    let src = "static long x = 3; int main(void){ return x; }";
+    let validated = validate_program(src).expect("should validate");
+    let symbol = validated.symbols().get("x").expect("x is tracked");
+
+    assert_eq!(symbol.ty, Type::Long);
+    assert!(matches!(
+        symbol.attrs,
+        IdentifierAttrs::StaticAttr {
+            init: InitialValue::Initial(StaticInit::LongInit(3)),
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_semantic_error_conflicting_variable_types() {
+    let src = "int x; long x; int main(void){ return 0; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Conflicting variable types should fail");
+}
+
+#[test]
+fn test_semantic_error_conflicting_function_types() {
+    let src = "int foo(int a); int foo(long a); int main(void){ return 0; }";
+    let result = validate_program(src);
+    assert!(result.is_err(), "Conflicting function types should fail");
+}
+
+// =============================================================================
+// UNSIGNED INTEGERS
+// =============================================================================
+
+#[test]
+fn test_semantic_common_type() {
+    assert_eq!(Type::common_type(&Type::Int, &Type::UInt), Type::UInt);
+    assert_eq!(Type::common_type(&Type::Long, &Type::UInt), Type::Long);
+    assert_eq!(Type::common_type(&Type::ULong, &Type::Long), Type::ULong);
+    assert_eq!(Type::common_type(&Type::ULong, &Type::Int), Type::ULong);
+}
+
+#[test]
+fn test_semantic_unsigned_conversions() {
+    let src = "int main(void){ unsigned u = 1; return u < -1; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // the signed operand is converted to unsigned
+    match items[1] {
+        BlockItem::S(Statement::Return(Expression::Binary(_, _, right, _), _)) => {
+            assert!(matches!(right.as_ref(), Expression::Cast(Type::UInt, _, _)))
+        }
+        _ => panic!("Expected return of a comparison"),
+    }
+}
+
+#[test]
+fn test_semantic_unsigned_static_initializer() {
+    let src = "unsigned int x = 4294967297; int y = 4294967295u; int main(void){ return 0; }";
+    let validated = validate_program(src).expect("should validate");
+
+    let init = |name: &str| match &validated.symbols().get(name).expect("tracked").attrs {
+        IdentifierAttrs::StaticAttr {
+            init: InitialValue::Initial(init),
+            ..
+        } => init.clone(),
+        _ => panic!("Expected initialized static variable"),
+    };
+    // conversions keep the low bits of the constant
+    assert_eq!(init("x"), StaticInit::UIntInit(1));
+    assert_eq!(init("y"), StaticInit::IntInit(-1));
+}
+
+// =============================================================================
+// FLOATING-POINT NUMBERS
+// =============================================================================
+
+#[test]
+fn test_semantic_double_common_type() {
+    assert_eq!(Type::common_type(&Type::Double, &Type::Int), Type::Double);
+    assert_eq!(Type::common_type(&Type::ULong, &Type::Double), Type::Double);
+    assert_eq!(Type::common_type(&Type::Long, &Type::Double), Type::Double);
+}
+
+#[test]
+fn test_semantic_double_conversions() {
+    let src = "int main(void){ double d = 1; return d + 2; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // the int operand is converted to double, the result is converted back to int
+    match items[1] {
+        BlockItem::S(Statement::Return(Expression::Cast(Type::Int, inner, _), _)) => {
+            assert!(matches!(
+                inner.as_ref(),
+                Expression::Binary(_, _, right, _)
+                    if matches!(right.as_ref(), Expression::Cast(Type::Double, _, _))
+            ))
+        }
+        _ => panic!("Expected return of a converted sum"),
+    }
+}
+
+#[test]
+fn test_semantic_double_static_initializer() {
+    let src = "double x = 3; long y = 2.9; unsigned long z = 1e19; double w = 18446744073709551615ul; \
+               int main(void){ return 0; }";
+    let validated = validate_program(src).expect("should validate");
+
+    let init = |name: &str| match &validated.symbols().get(name).expect("tracked").attrs {
+        IdentifierAttrs::StaticAttr {
+            init: InitialValue::Initial(init),
+            ..
+        } => init.clone(),
+        _ => panic!("Expected initialized static variable"),
+    };
+    assert_eq!(init("x"), StaticInit::DoubleInit(3.0));
+    // doubles are truncated towards zero
+    assert_eq!(init("y"), StaticInit::LongInit(2));
+    assert_eq!(init("z"), StaticInit::ULongInit(10000000000000000000));
+    assert_eq!(init("w"), StaticInit::DoubleInit(18446744073709551616.0));
+}
+
+#[test]
+fn test_semantic_error_double_integer_operators() {
+    for src in [
+        "int main(void){ double d = 1.0; return ~d; }",
+        "int main(void){ double d = 1.0; return d % 2; }",
+        "int main(void){ double d = 1.0; return 3 & d; }",
+        "int main(void){ double d = 1.0; return d << 1; }",
+        "int main(void){ double d = 1.0; return 1 >> d; }",
+        "int main(void){ double d = 1.0; return d ^ 1; }",
+    ] {
+        assert!(
+            validate_program(src).is_err(),
+            "Integer operator on a double should fail: {src}"
+        );
+    }
+}
+
+// =============================================================================
+// POINTERS
+// =============================================================================
+
+#[test]
+fn test_semantic_pointer_types() {
+    let src = "int main(void){ int x = 0; int *p = &x; int **pp = &p; return **pp; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::S(Statement::Return(expr, _)) = items[3] else {
+        panic!("Expected return statement");
+    };
+    assert_eq!(expr.ty(validated.symbols()), Type::Int);
+    let Expression::Dereference(inner, _) = expr else {
+        panic!("Expected dereference");
+    };
+    assert_eq!(
+        inner.ty(validated.symbols()),
+        Type::Pointer(Box::new(Type::Int))
+    );
+}
+
+#[test]
+fn test_semantic_null_pointer_conversions() {
+    let src =
+        "long *p = 0; int main(void){ int *q = 0; double *d; d = 0; return q == 0 && p != 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // null pointer constants are converted to the pointer type
+    match items[0] {
+        BlockItem::D(Declaration::VarDecl(decl)) => assert!(matches!(
+            decl.initializer(),
+            Some(Initializer::Single(Expression::Cast(
+                Type::Pointer(_),
+                _,
+                _
+            )))
+        )),
+        _ => panic!("Expected declaration"),
+    }
+    match &validated.symbols().get("p").expect("tracked").attrs {
+        IdentifierAttrs::StaticAttr {
+            init: InitialValue::Initial(init),
+            ..
+        } => assert_eq!(init, &StaticInit::ULongInit(0)),
+        _ => panic!("Expected initialized static variable"),
+    }
+}
+
+#[test]
+fn test_semantic_assignment_through_pointer() {
+    let src = "int main(void){ int x = 0; int *p = &x; *p = 3; return x; }";
+    assert!(validate_program(src).is_ok());
+}
+
+#[test]
+fn test_semantic_error_pointer_misuse() {
+    for src in [
+        // only pointers can be dereferenced
+        "int main(void){ int x = 0; return *x; }",
+        // only lvalues have an address
+        "int main(void){ int *p = &3; return 0; }",
+        "int main(void){ int x = 0; int *p = &(x + 1); return 0; }",
+        // integers other than null constants don't convert to pointers
+        "int main(void){ int *p = 1; return 0; }",
+        "int main(void){ long x = 0; int *p = x; return 0; }",
+        // pointers of different types don't convert to each other
+        "int main(void){ int *p = 0; long *q = p; return 0; }",
+        "int main(void){ int *p = 0; long *q = 0; return p == q; }",
+        "int main(void){ int *p = 0; long *q = 0; return *(1 ? p : q); }",
+        // arithmetic and bitwise operators don't accept pointers
+        "int main(void){ int *p = 0; return -p; }",
+        "int main(void){ int *p = 0; return ~p; }",
+        "int main(void){ int *p = 0; return p * 2; }",
+        "int main(void){ int *p = 0; return p & 1; }",
+        // doubles and pointers can't be cast to each other
+        "int main(void){ double *p = 0; return (double) p; }",
+        "int main(void){ double d = 0; return (int *) d == 0; }",
+        // static pointers are initialized with null constants
+        "int *p = 1; int main(void){ return 0; }",
+        // arguments and return values are converted as if by assignment
+        "int f(int *p); int main(void){ return f(1); }",
+        "int *f(void){ return 1; } int main(void){ return 0; }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// ARRAYS
+// =============================================================================
+
+#[test]
+fn test_semantic_arrays_decay_to_pointers() {
+    let src = "int main(void){ int a[3]; int *p = a; return *(a + 1) + p[2]; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // the array is converted to a pointer to its first element
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[1] else {
+        panic!("Expected declaration");
+    };
+    let Some(Initializer::Single(Expression::Cast(ty, inner, _))) = decl.initializer() else {
+        panic!("Expected decayed array");
+    };
+    assert_eq!(ty, &Type::Pointer(Box::new(Type::Int)));
+    assert_eq!(
+        inner.ty(validated.symbols()),
+        Type::Array(Box::new(Type::Int), 3)
+    );
+}
+
+#[test]
+fn test_semantic_address_of_array_does_not_decay() {
+    let src = "int main(void){ int a[2][3]; int (*p)[2][3] = &a; int (*row)[3] = a; return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[1] else {
+        panic!("Expected declaration");
+    };
+    let Some(Initializer::Single(Expression::AddressOf(inner, _))) = decl.initializer() else {
+        panic!("Expected address of the array");
+    };
+    assert!(matches!(inner.as_ref(), Expression::Var(_, _)));
+}
+
+#[test]
+fn test_semantic_subscript_and_pointer_arithmetic_types() {
+    let src = "int main(void){ long m[2][3]; long *p = m[1]; return (p + 1) - p; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // the difference of two pointers is a long, converted back to int
+    let BlockItem::S(Statement::Return(Expression::Cast(Type::Int, diff, _), _)) = items[2] else {
+        panic!("Expected return of a converted difference");
+    };
+    assert_eq!(diff.ty(validated.symbols()), Type::Long);
+}
+
+#[test]
+fn test_semantic_compound_initializers_are_padded() {
+    let src = "int main(void){ long a[2][2] = {{1}}; return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[0] else {
+        panic!("Expected declaration");
+    };
+    let Some(Initializer::Compound(rows)) = decl.initializer() else {
+        panic!("Expected compound initializer");
+    };
+    assert_eq!(rows.len(), 2);
+    for row in rows {
+        let Initializer::Compound(elements) = row else {
+            panic!("Expected nested compound initializer");
+        };
+        assert_eq!(elements.len(), 2);
+        // every element is converted to the element type
+        for element in elements {
+            let Initializer::Single(e) = element else {
+                panic!("Expected single initializer");
+            };
+            assert_eq!(e.ty(validated.symbols()), Type::Long);
+        }
+    }
+}
+
+#[test]
+fn test_semantic_static_array_initializers() {
+    let src = "int a[4] = {1, 2}; long z[3]; int main(void){ return 0; }";
+    let validated = validate_program(src).expect("should validate");
+
+    match &validated.symbols().get("a").expect("tracked").attrs {
+        IdentifierAttrs::StaticAttr {
+            init: InitialValue::Initial(init),
+            ..
+        } => assert_eq!(
+            init,
+            &StaticInit::ArrayInit(vec![
+                StaticInit::IntInit(1),
+                StaticInit::IntInit(2),
+                StaticInit::ZeroInit(8),
+            ])
+        ),
+        _ => panic!("Expected initialized static array"),
+    }
+    assert!(matches!(
+        &validated.symbols().get("z").expect("tracked").attrs,
+        IdentifierAttrs::StaticAttr {
+            init: InitialValue::Tentative,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_semantic_error_array_misuse() {
+    for src in [
+        // initializers must match the shape of the array
+        "int main(void){ int a[2] = 1; return 0; }",
+        "int main(void){ int a[2] = {1, 2, 3}; return 0; }",
+        "int main(void){ int x = {1}; return 0; }",
+        "int a[2] = {1, 2, 3}; int main(void){ return 0; }",
+        // static initializers must be constant
+        "int x = 1; int a[2] = {x, 2}; int main(void){ return 0; }",
+        // arrays aren't assignable and can't be cast to
+        "int main(void){ int a[2]; int b[2]; a = b; return 0; }",
+        "int main(void){ int a[2]; return (int[2]) a == 0; }",
+        // pointer arithmetic needs an integer and a pointer to a complete type
+        "int main(void){ int a[2]; long *p = 0; return a - p; }",
+        "int main(void){ int a[2]; return (1 - a) == 0; }",
+        "int main(void){ int a[2]; return (a + a) == 0; }",
+        "int main(void){ int a[2]; return a < 1; }",
+        "int main(void){ int a[2]; return a[1.0]; }",
+        "int main(void){ int x = 0; return x[1]; }",
+        // pointers to arrays of different sizes are different types
+        "int main(void){ int a[2][3]; int (*p)[4] = a; return 0; }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// CHARACTERS AND STRINGS
+// =============================================================================
+
+#[test]
+fn test_semantic_characters_are_promoted() {
+    let src = "int main(void){ char c = 'a'; return -c; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // the character constant is an int, converted to the type of the variable
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[0] else {
+        panic!("Expected declaration");
+    };
+    assert!(matches!(
+        decl.initializer(),
+        Some(Initializer::Single(Expression::Cast(Type::Char, _, _)))
+    ));
+
+    let BlockItem::S(Statement::Return(Expression::Unary(UnaryOperator::Negate, inner, _), _)) =
+        items[1]
+    else {
+        panic!("Expected return of a negation");
+    };
+    assert!(matches!(inner.as_ref(), Expression::Cast(Type::Int, _, _)));
+}
+
+#[test]
+fn test_semantic_string_initializers() {
+    let src = r#"int main(void){ char s[4] = "ab"; char *p = "cd"; return 0; }"#;
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // a character array is initialized with the string itself
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[0] else {
+        panic!("Expected declaration");
+    };
+    assert!(matches!(
+        decl.initializer(),
+        Some(Initializer::Single(Expression::String(s, _))) if s == "ab"
+    ));
+
+    // anywhere else the string decays to a pointer
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[1] else {
+        panic!("Expected declaration");
+    };
+    let Some(Initializer::Single(Expression::Cast(ty, inner, _))) = decl.initializer() else {
+        panic!("Expected decayed string");
+    };
+    assert_eq!(ty, &Type::Pointer(Box::new(Type::Char)));
+    assert_eq!(
+        inner.ty(validated.symbols()),
+        Type::Array(Box::new(Type::Char), 3)
+    );
+}
+
+#[test]
+fn test_semantic_static_string_initializers() {
+    let src = r#"char s[4] = "ab"; unsigned char t[2] = "ab"; char *p = "x";
+        int main(void){ return 0; }"#;
+    let validated = validate_program(src).expect("should validate");
+    let init = |name: &str| match &validated.symbols().get(name).expect("tracked").attrs {
+        IdentifierAttrs::StaticAttr {
+            init: InitialValue::Initial(init),
+            ..
+        } => init.clone(),
+        _ => panic!("Expected initialized static variable"),
+    };
+
+    assert_eq!(
+        init("s"),
+        StaticInit::ArrayInit(vec![
+            StaticInit::StringInit("ab".to_string(), true),
+            StaticInit::ZeroInit(1),
+        ])
+    );
+    // there's no room for the null terminator
+    assert_eq!(
+        init("t"),
+        StaticInit::ArrayInit(vec![StaticInit::StringInit("ab".to_string(), false)])
+    );
+
+    // the pointer points to a new constant holding the string
+    let StaticInit::PointerInit(constant) = init("p") else {
+        panic!("Expected pointer initializer");
+    };
+    assert_eq!(
+        validated.symbols().get(&constant).expect("tracked").attrs,
+        IdentifierAttrs::ConstantAttr(StaticInit::StringInit("x".to_string(), true))
+    );
+}
+
+#[test]
+fn test_semantic_error_string_misuse() {
+    for src in [
+        // the string must fit in the array, the null terminator can be left out
+        r#"int main(void){ char s[2] = "abc"; return 0; }"#,
+        r#"char s[2] = "abc"; int main(void){ return 0; }"#,
+        // only character arrays can be initialized with a string
+        r#"int main(void){ int a[3] = "ab"; return 0; }"#,
+        r#"long a[3] = "ab"; int main(void){ return 0; }"#,
+        // a string is a char array, it doesn't convert to other pointers
+        r#"int main(void){ long *p = "ab"; return 0; }"#,
+        r#"signed char *p = "ab"; int main(void){ return 0; }"#,
+        r#"char c = "a"; int main(void){ return 0; }"#,
+        // strings are lvalues, but arrays aren't assignable
+        r#"int main(void){ "ab" = 0; return 0; }"#,
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// STRUCTURES
+// =============================================================================
+
+// Helper: the unique tags of the file scope structure declarations, in order
+fn struct_tags(program: &Program) -> Vec<String> {
+    program
+        .declarations()
+        .iter()
+        .filter_map(|d| match d {
+            Declaration::StructDecl(s) => Some(s.tag().value().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_semantic_structure_layout() {
+    let src = "struct s { char c; long l; int i; char a[3]; };
+        int main(void){ return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let tag = &struct_tags(&validated)[0];
+    let entry = validated.symbols().types().get(tag).expect("laid out");
+
+    // every member is aligned to its own alignment, the size to the largest one
+    let offsets: Vec<usize> = entry.members.iter().map(|m| m.offset).collect();
+    assert_eq!(offsets, vec![0, 8, 16, 20]);
+    assert_eq!(entry.alignment, 8);
+    assert_eq!(entry.size, 24);
+}
+
+#[test]
+fn test_semantic_nested_structure_layout() {
+    let src =
+        "struct inner { char c; int i; }; struct outer { char c; struct inner in; double d; };
+        int main(void){ return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let tags = struct_tags(&validated);
+    let entry = validated.symbols().types().get(&tags[1]).expect("laid out");
+
+    assert_eq!(entry.members[1].ty, Type::Structure(tags[0].clone()));
+    assert_eq!(entry.members[1].offset, 4);
+    assert_eq!(entry.members[2].offset, 16);
+    assert_eq!(entry.size, 24);
+}
+
+#[test]
+fn test_semantic_structure_tags_are_scoped() {
+    let src = "struct s { int a; };
+        int main(void){ struct s { long b; }; struct s x; x.b = 1; return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let outer = &struct_tags(&validated)[0];
+
+    let items = get_body_items(&validated);
+    let BlockItem::D(Declaration::StructDecl(inner)) = items[0] else {
+        panic!("Expected structure declaration");
+    };
+    assert_ne!(inner.tag().value(), outer);
+
+    // the variable gets the innermost structure type
+    let BlockItem::D(Declaration::VarDecl(decl)) = items[1] else {
+        panic!("Expected declaration");
+    };
+    assert_eq!(
+        decl.var_type(),
+        &Type::Structure(inner.tag().value().to_string())
+    );
+}
+
+#[test]
+fn test_semantic_static_structure_initializer_padding() {
+    let src = "struct s { char c; long l; int i; }; struct s x = {'a', 2};
+        int main(void){ return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let IdentifierAttrs::StaticAttr {
+        init: InitialValue::Initial(init),
+        ..
+    } = &validated.symbols().get("x").expect("tracked").attrs
+    else {
+        panic!("Expected initialized static variable");
+    };
+
+    // padding and missing members are zero filled
+    assert_eq!(
+        init,
+        &StaticInit::ArrayInit(vec![
+            StaticInit::CharInit(97),
+            StaticInit::ZeroInit(7),
+            StaticInit::LongInit(2),
+            StaticInit::ZeroInit(8),
+        ])
+    );
+}
+
+#[test]
+fn test_semantic_member_access_types() {
+    let src = "struct s { int a; double d; };
+        int main(void){ struct s x; struct s *p = &x; return p->a + (int) x.d; }";
+    assert!(validate_program(src).is_ok());
+}
+
+#[test]
+fn test_semantic_error_structure_misuse() {
+    for src in [
+        // tags must be declared before use, even through a pointer
+        "int main(void){ struct s *p; return 0; }",
+        // a structure can only be defined once per scope
+        "struct s { int a; }; struct s { int a; }; int main(void){ return 0; }",
+        "struct s { int a; int a; }; int main(void){ return 0; }",
+        // members must be complete
+        "struct s; struct t { struct s x; }; int main(void){ return 0; }",
+        "struct s { struct s x; }; int main(void){ return 0; }",
+        // variables need complete types unless they're declared extern
+        "struct s; int main(void){ struct s x; return 0; }",
+        "struct s; struct s x; int main(void){ return 0; }",
+        "struct s; struct s f(void){ struct s *p = 0; return *p; } int main(void){ return 0; }",
+        // members are looked up in the right structure type
+        "struct s { int a; }; int main(void){ struct s x; return x.b; }",
+        "struct s { int a; }; int main(void){ struct s x; return x->a; }",
+        "struct s { int a; }; int main(void){ struct s *p; return p.a; }",
+        "int main(void){ int x; return x.a; }",
+        // structures aren't scalars
+        "struct s { int a; }; int main(void){ struct s x; return !x; }",
+        "struct s { int a; }; int main(void){ struct s x; if (x) return 1; return 0; }",
+        "struct s { int a; }; int main(void){ struct s x; return x == x; }",
+        "struct s { int a; }; int main(void){ struct s x; return (int) x; }",
+        // structures of different types aren't compatible
+        "struct s { int a; }; struct t { int a; };
+            int main(void){ struct s x; struct t y; x = y; return 0; }",
+        // too many initializers
+        "struct s { int a; }; int main(void){ struct s x = {1, 2}; return 0; }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// GOTO AND LABELED STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_semantic_labels_are_renamed() {
+    let src = "int main(void){ goto end; int end = 0; end: return end; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // a forward goto gets the same unique name as its label
+    let BlockItem::S(Statement::Goto(target, _)) = items[0] else {
+        panic!("Expected goto");
+    };
+    let BlockItem::S(Statement::Labeled(label, st, _)) = items[2] else {
+        panic!("Expected labeled statement");
+    };
+    assert_eq!(target.value(), label.value());
+    assert_ne!(label.value(), "end");
+
+    // labels don't clash with variables of the same name
+    let BlockItem::D(decl) = items[1] else {
+        panic!("Expected declaration");
+    };
+    assert!(matches!(
+        st.as_ref(),
+        Statement::Return(Expression::Var(id, _), _) if id.value() == decl.name().value()
+    ));
+}
+
+#[test]
+fn test_semantic_labels_have_function_scope() {
+    let src = "int f(void){ { a: ; } goto a; }
+        int main(void){ a: goto a; }";
+    let validated = validate_program(src).expect("should validate");
+
+    // the same label in two functions gets two unique names
+    let labels: Vec<String> = validated
+        .functions()
+        .into_iter()
+        .filter_map(|f| f.body())
+        .filter_map(|body| {
+            body.iter().find_map(|item| match item {
+                BlockItem::S(Statement::Goto(label, _)) => Some(label.value().to_string()),
+                BlockItem::S(Statement::Labeled(_, st, _)) => match st.as_ref() {
+                    Statement::Goto(label, _) => Some(label.value().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+        })
+        .collect();
+    assert_eq!(labels.len(), 2);
+    assert_ne!(labels[0], labels[1]);
+}
+
+#[test]
+fn test_semantic_error_label_misuse() {
+    for src in [
+        "int main(void){ a: ; a: return 0; }",
+        "int main(void){ a: { a: return 0; } }",
+        "int main(void){ goto b; return 0; }",
+        // labels don't leak out of their function
+        "int f(void){ b: return 0; } int main(void){ goto b; }",
+        "int main(void){ { int x; x: ; } goto y; return 0; }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// SWITCH STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_semantic_switch_collects_cases() {
+    let src = "int main(void){ char c = 'a';
+        switch (c) { case 'a': case -1 + 3: while (1) { default: ; } } return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+    let BlockItem::S(Statement::Switch(cond, _, _, cases, _)) = items[1] else {
+        panic!("Expected switch statement");
+    };
+
+    // the controlling expression is promoted, and the cases converted to its type
+    assert_eq!(cond.ty(validated.symbols()), Type::Int);
+    let values: Vec<_> = cases.iter().map(|(value, _)| value.clone()).collect();
+    assert_eq!(
+        values,
+        vec![Some(Const::ConstInt(97)), Some(Const::ConstInt(2)), None]
+    );
+    // the default in the loop still belongs to the switch, and every case has its own label
+    assert_ne!(cases[0].1.value(), cases[1].1.value());
+    assert_ne!(cases[1].1.value(), cases[2].1.value());
+}
+
+#[test]
+fn test_semantic_case_values_are_converted() {
+    let src = "int main(void){ long l = 0; switch (l) { case 1: return 1; } return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+    let BlockItem::S(Statement::Switch(_, _, _, cases, _)) = items[1] else {
+        panic!("Expected switch statement");
+    };
+    assert_eq!(cases[0].0, Some(Const::ConstLong(1)));
+}
+
+#[test]
+fn test_semantic_break_targets_switch_and_continue_the_loop() {
+    let src = "int main(void){ while (1) { switch (1) { case 1: continue; default: break; } } }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::S(Statement::While(_, body, loop_label, _)) = items[0] else {
+        panic!("Expected while loop");
+    };
+    let Statement::Compound(block, _) = body.as_ref() else {
+        panic!("Expected compound body");
+    };
+    let Some(BlockItem::S(Statement::Switch(_, body, switch_label, _, _))) = block.iter().next()
+    else {
+        panic!("Expected switch statement");
+    };
+    let Statement::Compound(block, _) = body.as_ref() else {
+        panic!("Expected compound body");
+    };
+    let statements: Vec<_> = block.iter().collect();
+
+    assert!(matches!(
+        statements[0],
+        BlockItem::S(Statement::Case(_, st, _, _))
+            if matches!(st.as_ref(), Statement::Continue(label, _) if label.value() == loop_label.value())
+    ));
+    assert!(matches!(
+        statements[1],
+        BlockItem::S(Statement::Default(st, _, _))
+            if matches!(st.as_ref(), Statement::Break(label, _) if label.value() == switch_label.value())
+    ));
+}
+
+#[test]
+fn test_semantic_error_switch_misuse() {
+    for src in [
+        "int main(void){ switch (1) { case 1: case 1: return 0; } return 1; }",
+        // case values are compared once evaluated and converted to the type of the switch
+        "int main(void){ switch (1) { case 1: case 2 - 1: return 0; } return 1; }",
+        "int main(void){ switch (1) { case 1: case 4294967297: return 0; } return 1; }",
+        "int main(void){ switch (1) { default: default: return 0; } return 1; }",
+        // case and default only appear in a switch
+        "int main(void){ case 1: return 0; }",
+        "int main(void){ default: return 0; }",
+        // cases are integer constant expressions
+        "int main(void){ int x = 1; switch (1) { case x: return 0; } return 1; }",
+        "int main(void){ switch (1) { case 1.5: return 0; } return 1; }",
+        "int main(void){ switch (1) { case 1 / 0: return 0; } return 1; }",
+        // the controlling expression is an integer
+        "int main(void){ switch (1.0) { case 1: return 0; } return 1; }",
+        "int main(void){ int *p = 0; switch (p) { default: return 0; } }",
+        // continue still needs a loop
+        "int main(void){ switch (1) { case 1: continue; } return 1; }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// COMPOUND ASSIGNMENT AND INCREMENT
+// =============================================================================
+
+#[test]
+fn test_semantic_compound_assignment_converts_to_common_type() {
+    let src = "int main(void){ char c = 1; c *= 2.5; c <<= 1L; return c; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // the operation is done in the common type, the result keeps the type of the lvalue
+    let BlockItem::S(Statement::Expression(mul, _)) = items[1] else {
+        panic!("Expected expression statement");
+    };
+    let Expression::CompoundAssignment(_, _, right, _) = mul else {
+        panic!("Expected compound assignment");
+    };
+    assert_eq!(right.ty(validated.symbols()), Type::Double);
+    assert_eq!(mul.ty(validated.symbols()), Type::Char);
+
+    // a shift is done in the promoted type of the lvalue, whatever the right operand
+    let BlockItem::S(Statement::Expression(Expression::CompoundAssignment(_, _, right, _), _)) =
+        items[2]
+    else {
+        panic!("Expected compound assignment");
+    };
+    assert_eq!(right.ty(validated.symbols()), Type::Int);
+}
+
+#[test]
+fn test_semantic_pointer_compound_assignment_and_increment() {
+    let src = "int main(void){ int a[3] = {1, 2, 3}; int *p = a; p += 2; p--; return *p++; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // the index of a pointer is a long
+    let BlockItem::S(Statement::Expression(Expression::CompoundAssignment(_, _, index, _), _)) =
+        items[2]
+    else {
+        panic!("Expected compound assignment");
+    };
+    assert_eq!(index.ty(validated.symbols()), Type::Long);
+
+    let BlockItem::S(Statement::Expression(dec, _)) = items[3] else {
+        panic!("Expected expression statement");
+    };
+    assert_eq!(
+        dec.ty(validated.symbols()),
+        Type::Pointer(Box::new(Type::Int))
+    );
+}
+
+#[test]
+fn test_semantic_error_compound_assignment_misuse() {
+    for src in [
+        // the target must be an lvalue
+        "int main(void){ 5 += 1; return 0; }",
+        "int main(void){ int a = 1; int b = 2; (a + b)++; return 0; }",
+        "int main(void){ int a = 1; --(a++); return 0; }",
+        "int main(void){ int a[2] = {1, 2}; a += 1; return 0; }",
+        "int main(void){ int a[2] = {1, 2}; a++; return 0; }",
+        // integer only operators
+        "int main(void){ double d = 1.0; d %= 2; return 0; }",
+        "int main(void){ int i = 1; i <<= 1.0; return 0; }",
+        // pointers only move by an integer
+        "int main(void){ int *p = 0; p *= 2; return 0; }",
+        "int main(void){ int *p = 0; int *q = 0; p -= q; return 0; }",
+        "int main(void){ int i = 0; int *p = 0; i += p; return 0; }",
+        "int main(void){ int *p = 0; p += 1.0; return 0; }",
+        // structures aren't arithmetic
+        "struct s { int a; }; int main(void){ struct s x; x += 1; return 0; }",
+        "struct s { int a; }; int main(void){ struct s x; x++; return 0; }",
+        "struct s { int a; }; int main(void){ struct s x; int i = 0; i += x; return 0; }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// COMMA OPERATOR AND DECLARATION LISTS
+// =============================================================================
+
+#[test]
+fn test_semantic_declarator_is_in_scope_for_the_next_one() {
+    let src = "int main(void){ int a = 1, b = a + 1; return b; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::D(Declaration::VarDecl(a)) = items[0] else {
+        panic!("Expected declaration of a");
+    };
+    let BlockItem::D(Declaration::VarDecl(b)) = items[1] else {
+        panic!("Expected declaration of b");
+    };
+    let Some(Initializer::Single(Expression::Binary(_, left, _, _))) = b.initializer() else {
+        panic!("Expected initializer of b");
+    };
+    assert!(matches!(left.as_ref(), Expression::Var(id, _) if id.value() == a.name().value()));
+}
+
+#[test]
+fn test_semantic_comma_has_type_of_right_operand() {
+    let src = "int main(void){ int a[2] = {1, 2}; long l = 1; return *(l, a); }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::S(Statement::Return(Expression::Dereference(inner, _), _)) = items[2] else {
+        panic!("Expected return of a dereference");
+    };
+    // the array on the right decays, the comma isn't an lvalue
+    assert_eq!(
+        inner.ty(validated.symbols()),
+        Type::Pointer(Box::new(Type::Int))
+    );
+}
+
+#[test]
+fn test_semantic_error_declaration_list_and_comma() {
+    for src in [
+        "int main(void){ int a, a; return 0; }",
+        "int main(void){ int a = b, b = 1; return a; }",
+        "int main(void){ for (int i = 0, i = 1; ;) ; return 0; }",
+        // the result of a comma isn't an lvalue
+        "int main(void){ int a = 1; (a, a) = 2; return 0; }",
+        "int main(void){ int a = 1; (0, a)++; return 0; }",
+        "int main(void){ int a = 1; int *p = &(0, a); return 0; }",
+        // static variables are still initialized with constants
+        "int main(void){ static int a = (1, 2); return a; }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// ENUMERATIONS
+// =============================================================================
+
+// Helper: the value of the initializer of a validated declaration
+fn int_initializer(item: &BlockItem) -> Option<i32> {
+    match item {
+        BlockItem::D(Declaration::VarDecl(decl)) => match decl.initializer() {
+            Some(Initializer::Single(Expression::Constant(Const::ConstInt(value), _))) => {
+                Some(*value)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn test_semantic_enumerator_values() {
+    let src = "int main(void){ enum { A, B = 5, C, D = B * 2 - 1, E }; \
+               int a = A, c = C, d = D, e = E; return 0; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    // enumerators are replaced by their values
+    let values: Vec<_> = items[1..5]
+        .iter()
+        .filter_map(|i| int_initializer(i))
+        .collect();
+    assert_eq!(values, vec![0, 6, 9, 10]);
+
+    let BlockItem::D(Declaration::EnumDecl(decl)) = items[0] else {
+        panic!("Expected enumeration declaration");
+    };
+    let checked: Vec<_> = decl
+        .enumerators()
+        .iter()
+        .map(|(name, value)| {
+            let symbol = validated.symbols().get(name.value()).expect("tracked");
+            assert_eq!(symbol.ty, Type::Int);
+            (symbol.attrs.clone(), value.clone())
+        })
+        .collect();
+    assert!(matches!(
+        checked[3],
+        (
+            IdentifierAttrs::EnumeratorAttr(9),
+            Some(Expression::Constant(Const::ConstInt(9), _))
+        )
+    ));
+}
+
+#[test]
+fn test_semantic_enumerators_are_scoped() {
+    let src = "enum { RED = 1 }; int main(void){ int a = RED; { int RED = 7; enum { GREEN = 3 }; } \
+               { enum { RED = 2 }; a = RED; } return a; }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    assert_eq!(int_initializer(items[0]), Some(1));
+    let BlockItem::S(Statement::Compound(inner, _)) = items[2] else {
+        panic!("Expected inner block");
+    };
+    // the enumerator of the inner block hides the one at file scope
+    let assigned = inner.iter().find_map(|item| match item {
+        BlockItem::S(Statement::Expression(Expression::Assignment(_, right, _), _)) => {
+            Some(right.as_ref())
+        }
+        _ => None,
+    });
+    assert!(matches!(
+        assigned,
+        Some(Expression::Constant(Const::ConstInt(2), _))
+    ));
+}
+
+#[test]
+fn test_semantic_enum_constants_in_case_and_static_initializers() {
+    let src = "enum state { IDLE, RUN }; enum state current = RUN; \
+               int main(void){ static int s = IDLE; switch (current) { case RUN: return 1; } return s; }";
+    let validated = validate_program(src).expect("should validate");
+
+    match &validated.symbols().get("current").expect("tracked").attrs {
+        IdentifierAttrs::StaticAttr { init, .. } => {
+            assert_eq!(*init, InitialValue::Initial(StaticInit::IntInit(1)));
+        }
+        attrs => panic!("Expected static attributes, got {attrs:?}"),
+    }
+}
+
+#[test]
+fn test_semantic_error_enumerations() {
+    for src in [
+        // enumerators aren't lvalues
+        "enum { A }; int main(void){ A = 1; return 0; }",
+        "enum { A }; int main(void){ A++; return 0; }",
+        "enum { A }; int main(void){ int *p = &A; return 0; }",
+        // values are integer constants that fit in an int
+        "int main(void){ int x = 1; enum { A = x }; return A; }",
+        "enum { A = 1.5 };",
+        "enum { A = 2147483647, B };",
+        "enum { A = 2147483648L };",
+        // enumerators share the namespace of variables and functions
+        "int main(void){ enum { A, A }; return 0; }",
+        "int main(void){ int A; enum { A }; return 0; }",
+        "int A; enum { A };",
+        "enum { A }; int A;",
+        "enum { A }; int A(void);",
+        "int main(void){ enum { A }; return B; }",
+        "int main(void){ enum { A = B, B }; return 0; }",
+        "enum { A }; int main(void){ return A(); }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// TYPEDEFS
+// =============================================================================
+
+#[test]
+fn test_semantic_typedef_names_resolve_to_their_types() {
+    let src = "typedef struct pair { int a; long b; } pair_t; typedef pair_t *pair_ptr; \
+               int main(void){ pair_t p = {1, 2}; pair_ptr q = &p; \
+               { int pair_t = 3; return q->a + pair_t; } }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::D(Declaration::VarDecl(q)) = items[1] else {
+        panic!("Expected declaration of q");
+    };
+    // the structure tag is resolved like any other one
+    let Type::Pointer(referenced) = q.var_type() else {
+        panic!("Expected pointer type");
+    };
+    let Type::Structure(tag) = referenced.as_ref() else {
+        panic!("Expected pointer to a structure");
+    };
+    let entry = validated
+        .symbols()
+        .types()
+        .get(tag)
+        .expect("pair is defined");
+    assert_eq!(entry.size, 16);
+}
+
+// =============================================================================
+// FUNCTION POINTERS
+// =============================================================================
+
+#[test]
+fn test_semantic_function_names_decay_to_pointers() {
+    let src = "int add(int a, int b) { return a + b; } \
+               int main(void){ int (*fp)(int, int) = add; fp = &add; return fp(1, 2); }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::D(Declaration::VarDecl(fp)) = items[0] else {
+        panic!("Expected declaration of fp");
+    };
+    assert!(matches!(
+        fp.initializer(),
+        Some(Initializer::Single(Expression::AddressOf(inner, _)))
+            if matches!(inner.as_ref(), Expression::Var(name, _) if name.value() == "add")
+    ));
+    // a variable holding a function pointer is called indirectly
+    assert!(matches!(
+        items[2],
+        BlockItem::S(Statement::Return(Expression::IndirectCall(callee, _, _), _))
+            if matches!(callee.as_ref(), Expression::Var(_, _))
+    ));
+}
+
+#[test]
+fn test_semantic_indirect_call_converts_arguments() {
+    let src = "long twice(long x) { return 2 * x; } \
+               int main(void){ long (*fp)(long) = twice; return (*fp)(21); }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::S(Statement::Return(Expression::Cast(Type::Int, call, _), _)) = items[1] else {
+        panic!("Expected the result to be converted to int");
+    };
+    let Expression::IndirectCall(_, args, _) = call.as_ref() else {
+        panic!("Expected indirect call");
+    };
+    assert!(matches!(&args[0], Expression::Cast(Type::Long, _, _)));
+}
+
+#[test]
+fn test_semantic_function_pointers_invalid() {
+    for src in [
+        // wrong signatures
+        "int f(int a) { return a; } int main(void){ int (*p)(long) = f; return 0; }",
+        "int f(int a) { return a; } int main(void){ int (*p)(int) = f; return p(1, 2); }",
+        "int f(int a) { return a; } int (*p)(int, int) = &f;",
+        // only functions and function pointers are called
+        "int main(void){ int x = 3; return x(1); }",
+        "int main(void){ int *p = 0; return (*p)(1); }",
+        // no arithmetic, subscripts or assignments to functions
+        "int f(int a) { return a; } int main(void){ int (*p)(int) = f; p = p + 1; return 0; }",
+        "int f(int a) { return a; } int main(void){ int (*p)(int) = f; p++; return 0; }",
+        "int f(int a) { return a; } int main(void){ int (*p)(int) = f; p -= 1; return 0; }",
+        "int f(int a) { return a; } int main(void){ int (*p)(int) = f; return p[0](1); }",
+        "int f(int a) { return a; } int g(int a) { return a; } int main(void){ f = g; return 0; }",
+        "int f(int a) { return a; } int main(void){ return (&&f)(1); }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// VARIADIC FUNCTIONS
+// =============================================================================
+
+#[test]
+fn test_semantic_variadic_arguments_are_promoted() {
+    let src = "int printf(char *fmt, ...); \
+               int main(void){ char c = 'a'; long l = 2; return printf(\"%c %ld %f\", c, l, 1.5); }";
+    let validated = validate_program(src).expect("should validate");
+    let items = get_body_items(&validated);
+
+    let BlockItem::S(Statement::Return(Expression::FunctionCall(_, args, _), _)) = items[2] else {
+        panic!("Expected call to printf");
+    };
+    assert_eq!(args.len(), 4);
+    // the char is promoted to int, the other arguments keep their type
+    assert!(matches!(&args[1], Expression::Cast(Type::Int, _, _)));
+    assert!(matches!(&args[2], Expression::Var(_, _)));
+    assert!(matches!(
+        &args[3],
+        Expression::Constant(Const::ConstDouble(_), _)
+    ));
+}
+
+#[test]
+fn test_semantic_va_builtins() {
+    let src = "int f(int n, ...) { \
+                   __builtin_va_list ap, copy; \
+                   __builtin_va_start(ap, n); \
+                   __builtin_va_copy(copy, ap); \
+                   n = __builtin_va_arg(copy, int); \
+                   __builtin_va_end(copy); \
+                   __builtin_va_end(ap); \
+                   return n; \
+               }";
+    let validated = validate_program(src).expect("should validate");
+    let items: Vec<_> = validated
+        .function_definition("f")
+        .and_then(|f| f.body())
+        .map(|body| body.iter().collect())
+        .unwrap_or_default();
+
+    // the va_list decays to a pointer to its structure
+    assert!(matches!(
+        items[2],
+        BlockItem::S(Statement::VaStart(
+            Expression::Cast(Type::Pointer(_), _, _),
+            _,
+            _
+        ))
+    ));
+    // va_copy copies the structure
+    assert!(matches!(
+        items[3],
+        BlockItem::S(Statement::Expression(Expression::Assignment(left, right, _), _))
+            if matches!(left.as_ref(), Expression::Dereference(_, _))
+                && matches!(right.as_ref(), Expression::Dereference(_, _))
+    ));
+}
+
+#[test]
+fn test_semantic_variadic_invalid() {
+    for src in [
+        // too few arguments for the named parameters
+        "int f(int a, int b, ...); int main(void){ return f(1); }",
+        "int (*p)(int, ...); int main(void){ return p(); }",
+        // va_start only in a variadic function and with its last parameter
+        "int f(int n) { __builtin_va_list ap; __builtin_va_start(ap, n); return 0; }",
+        "int f(int a, int b, ...) { __builtin_va_list ap; __builtin_va_start(ap, a); return 0; }",
+        // the builtins only take a va_list
+        "int f(int n, ...) { int ap[1]; __builtin_va_start(ap, n); return 0; }",
+        "int f(int n, ...) { __builtin_va_end(n); return 0; }",
+        "int f(int n, ...) { return __builtin_va_arg(n, int); }",
+        // va_arg only reads scalars
+        "struct s { int a; }; \
+         int f(int n, ...) { __builtin_va_list ap; __builtin_va_arg(ap, struct s); return 0; }",
+        "int printf(char *fmt, ...); int printf(char *fmt);",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+}
+
+// =============================================================================
+// TYPE QUALIFIERS
+// =============================================================================
+
+#[test]
+fn test_semantic_const_initialization_and_reads() {
+    let src = "struct s { const int a; int b; }; \
+               const int limit = 10; \
+               int f(const int n, const int *p) { return n + *p; } \
+               int main(void) { \
+                   const int x = 1; \
+                   int y = x + limit; \
+                   struct s v = {1, 2}; \
+                   v.b = 3; \
+                   int *const q = &y; \
+                   *q = 4; \
+                   const int *r = q; \
+                   r = &x; \
+                   return f(x, r) + (r == q) + v.a; \
+               }";
+    let validated = validate_program(src).expect("should validate");
+
+    // reading a const variable gives an unqualified value
+    let symbols = validated.symbols();
+    let items = get_body_items(&validated);
+    let BlockItem::D(Declaration::VarDecl(y)) = items[1] else {
+        panic!("Expected declaration of y");
+    };
+    let Some(Initializer::Single(init)) = y.initializer() else {
+        panic!("Expected initializer of y");
+    };
+    assert_eq!(init.ty(symbols), Type::Int);
+    // the declared types keep their qualifiers
+    let BlockItem::D(Declaration::VarDecl(x)) = items[0] else {
+        panic!("Expected declaration of x");
+    };
+    assert!(x.var_type().is_const());
+    assert!(
+        symbols
+            .get(x.name().value())
+            .is_some_and(|symbol| symbol.ty.is_const())
+    );
+}
+
+#[test]
+fn test_semantic_const_lvalues_are_not_modifiable() {
+    for src in [
+        "int main(void){ const int x = 1; x = 2; return x; }",
+        "int main(void){ const int x = 1; x += 2; return x; }",
+        "int main(void){ const int x = 1; x++; return x; }",
+        "int main(void){ const int x = 1; --x; return x; }",
+        "const int g = 1; int main(void){ g = 2; return g; }",
+        "int main(void){ int x = 1; const int *p = &x; *p = 2; return x; }",
+        "int main(void){ int a[2] = {1, 2}; const int *p = a; p[1] = 3; return a[1]; }",
+        "int main(void){ const int a[2] = {1, 2}; a[0] = 3; return a[0]; }",
+        "int main(void){ int x = 1; int *const p = &x; p = 0; return x; }",
+        "int f(const int n) { n = 2; return n; }",
+        // a member of a const structure is const, and so is a structure with a const member
+        "struct s { int a; }; int main(void){ const struct s v = {1}; v.a = 2; return v.a; }",
+        "struct s { int a; }; \
+         int main(void){ struct s v = {1}; const struct s *p = &v; p->a = 2; return v.a; }",
+        "struct s { const int a; }; int main(void){ struct s v = {1}; v.a = 2; return v.a; }",
+        "struct s { const int a; }; \
+         int main(void){ struct s v = {1}; struct s w = {2}; v = w; return v.a; }",
+        "struct s { const int a; }; struct t { struct s inner; }; \
+         int main(void){ struct t v = {{1}}; struct t w = {{2}}; v = w; return 0; }",
+    ] {
+        let result = validate_program(src);
+        assert!(
+            matches!(
+                result,
+                Err(CompileError::Semantic(SemanticError::ConstLvalue(_), _))
+            ),
+            "Should fail: {src}"
+        );
+    }
+}
+
+#[test]
+fn test_semantic_qualified_pointer_conversions() {
+    // qualifiers can be added to what a pointer points to, and comparisons mix both
+    let src = "int f(const int *p); \
+               int main(void){ \
+                   int x = 1; \
+                   int *p = &x; \
+                   const volatile int *q = p; \
+                   int (*g)(const int *) = f; \
+                   return f(p) + (p == q) + (p < q) + (q - p) + (1 ? p : q) == q + g(p); \
+               }";
+    assert!(validate_program(src).is_ok());
+
+    // but they can't be dropped implicitly
+    for src in [
+        "int main(void){ const int x = 1; int *p = &x; return *p; }",
+        "int f(int *p); int main(void){ const int x = 1; return f(&x); }",
+        "int *f(const int *p) { return p; }",
+        "int main(void){ int x = 1; volatile int *p = &x; int *q; q = p; return 0; }",
+        // the qualifiers of a pointer to a pointer must match
+        "int main(void){ int *p = 0; const int **q = &p; return 0; }",
+    ] {
+        assert!(validate_program(src).is_err(), "Should fail: {src}");
+    }
+    // unless the cast is explicit
+    assert!(
+        validate_program("int main(void){ const int x = 1; int *p = (int *) &x; return *p; }")
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_semantic_qualified_function_types() {
+    // the qualifiers of the parameters and of the return type don't change the type of a
+    // function
+    let src = "int f(const int a, int *const p); \
+               const int f(int a, int *p) { return a + *p; } \
+               int main(void){ int x = 1; return f(1, &x); }";
+    assert!(validate_program(src).is_ok());
+
+    // what the parameters point to does
+    let src = "int f(const int *p); int f(int *p);";
+    assert!(validate_program(src).is_err());
+}
+
+// =============================================================================
+// ERROR KINDS
+// =============================================================================
+
+#[test]
+fn test_semantic_error_kinds() {
+    for (src, expected) in [
+        (
+            "int main(void) { return b; }",
+            SemanticError::UndeclaredVariable("b".to_string()),
+        ),
+        (
+            "int main(void) { 2 = 3; return 0; }",
+            SemanticError::InvalidLvalue,
+        ),
+        (
+            "int main(void) { break; return 0; }",
+            SemanticError::BreakOutsideLoop,
+        ),
+        (
+            "int f(int a); int main(void) { return f(1, 2); }",
+            SemanticError::WrongArgumentCount {
+                name: "f".to_string(),
+                expected: 1,
+                found: 2,
+            },
+        ),
+        (
+            "int f(int a); int main(void) { int (*fp)(int) = f; return fp(1, 2); }",
+            SemanticError::WrongPointerArgumentCount {
+                expected: 1,
+                found: 2,
+            },
+        ),
+        (
+            "int main(void) { goto end; return 0; }",
+            SemanticError::UndefinedLabel("end".to_string()),
+        ),
+    ] {
+        match validate_program(src) {
+            Err(CompileError::Semantic(error, _)) => assert_eq!(error, expected, "{src}"),
+            result => panic!("Expected a semantic error for {src}, got {result:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_semantic_messages_use_source_names() {
+    for (src, expected) in [
+        (
+            "int main(void) { const int x = 1; x = 2; return x; }",
+            "cannot modify `x`, it's const",
+        ),
+        (
+            "int main(void) { const int arr[2] = {1, 2}; arr[0] = 3; return 0; }",
+            "cannot modify `arr[0]`, it's const",
+        ),
+        (
+            "struct S { const int a; }; \
+             int main(void) { struct S s = {1}; struct S *p = &s; p->a++; return 0; }",
+            "cannot modify `p->a`, it's const",
+        ),
+        (
+            "int main(void) { int a[2] = {1, 2, 3}; return 0; }",
+            "too many elements in array initializer for `a`",
+        ),
+        (
+            "int main(void) { struct S { int a; }; struct S s; return s.b; }",
+            "structure `S` has no member `b`",
+        ),
+        (
+            "int main(void) { int f = 1; return f(); }",
+            "variable `f` used as function",
+        ),
+        (
+            "int f(int n, int k, ...) { __builtin_va_list ap; __builtin_va_start(ap, n); \
+             __builtin_va_end(ap); return k; }",
+            "va_start not given the last parameter `k`",
+        ),
+    ] {
+        match validate_program(src) {
+            Err(CompileError::Semantic(error, _)) => assert_eq!(error.to_string(), expected),
+            result => panic!("Expected a semantic error for {src}, got {result:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_semantic_error_has_span() {
+    let tokens =
+        lex_spanned("# 1 \"main.c\"\nint main(void) {\n  return x + 1;\n}").expect("should lex");
+    let program = Program::try_from(tokens).expect("should parse");
+    let result = validate_semantics(program);
+
+    let Err(CompileError::Semantic(error, span)) = result else {
+        panic!("Expected a semantic error");
+    };
+    assert_eq!(error, SemanticError::UndeclaredVariable("x".to_string()));
+    assert_eq!(span.to_string(), "main.c:2:10");
+}
+
+#[test]
+fn test_semantic_errors_point_at_their_code() {
+    for (src, location) in [
+        (
+            "int f(int a);\nint main(void) { return f(1, 2); }",
+            "main.c:2:25",
+        ),
+        (
+            "int f(int a);\nint main(void) { int (*fp)(int) = f; return fp(); }",
+            "main.c:2:45",
+        ),
+        (
+            "int main(void) {\n  int a[2] = {1, 2, 3};\n  return a[0];\n}",
+            "main.c:2:7",
+        ),
+        (
+            "struct S { int a; };\nint main(void) { struct S s; return s.b; }",
+            "main.c:2:37",
+        ),
+        (
+            "int main(void) {\n  int i = 0;\n  return i.a;\n}",
+            "main.c:3:10",
+        ),
+        (
+            "int main(void) {\n  struct T *t;\n  return 0;\n}",
+            "main.c:2:12",
+        ),
+    ] {
+        let errors = semantic_errors(&format!("# 1 \"main.c\"\n{src}"));
+        assert_eq!(errors.len(), 1, "{src}: {errors:?}");
+        assert!(
+            errors[0].starts_with(&format!("{location}: ")),
+            "{src}: {errors:?}"
+        );
+    }
+}
+
+#[test]
+fn test_semantic_duplicates_point_at_the_first_occurrence() {
+    for (src, location, previous) in [
+        (
+            "int main(void) {\n  int a;\n  int a;\n  return 0;\n}",
+            "main.c:3:7",
+            "main.c:2:7",
+        ),
+        (
+            "int f(void) { return 1; }\nint f(void) { return 2; }",
+            "main.c:2:5",
+            "main.c:1:5",
+        ),
+        (
+            "int main(void) {\nend: ;\nend: return 0;\n}",
+            "main.c:3:1",
+            "main.c:2:1",
+        ),
+        (
+            "int main(void) {\n  switch (1) {\n  case 1: ;\n  case 1: ;\n  }\n  return 0;\n}",
+            "main.c:4:3",
+            "main.c:3:3",
+        ),
+        (
+            "int main(void) {\n  switch (1) {\n  default: ;\n  default: ;\n  }\n  return 0;\n}",
+            "main.c:4:3",
+            "main.c:3:3",
+        ),
+    ] {
+        let tokens = lex_spanned(&format!("# 1 \"main.c\"\n{src}")).expect("should lex");
+        let program = Program::try_from(tokens).expect("should parse");
+        let Err(CompileError::Semantic(error, span)) = validate_semantics(program) else {
+            panic!("Expected a single semantic error for {src}");
+        };
+        let Some((first, _)) = error.previous() else {
+            panic!("Expected {error:?} to point at the first occurrence");
+        };
+        assert_eq!(span.to_string(), location, "{src}");
+        assert_eq!(first.to_string(), previous, "{src}");
+    }
+}
+
+// =============================================================================
+// ERROR RECOVERY
+// =============================================================================
+
+// Helper: the locations and messages of the semantic errors of a source
+fn semantic_errors(src: &str) -> Vec<String> {
+    let result = lex_spanned(src)
+        .and_then(Program::try_from)
+        .and_then(validate_semantics);
+    match result {
+        Ok(_) => vec![],
+        Err(error) => error.errors().iter().map(ToString::to_string).collect(),
+    }
+}
+
+#[test]
+fn test_semantic_reports_every_error() {
+    let src = "# 1 \"main.c\"\n\
+               int main(void) {\n\
+                 int x = 1;\n\
+                 int x = 2;\n\
+                 2 = x;\n\
+                 break;\n\
+                 if (x) continue;\n\
+                 return y + z;\n\
+               }";
+
+    assert_eq!(
+        semantic_errors(src),
+        vec![
+            "main.c:3:5: duplicate declaration of `x`",
+            "main.c:4:1: invalid lvalue",
+            "main.c:5:1: break statement outside of loop or switch",
+            "main.c:6:8: continue statement outside of loop",
+            "main.c:7:8: undeclared variable `y`",
+            "main.c:7:12: undeclared variable `z`",
+        ]
+    );
+}
+
+#[test]
+fn test_semantic_single_error_isnt_wrapped() {
+    let result = validate_program("int main(void) { return x; }");
+
+    assert!(
+        matches!(
+            result,
+            Err(CompileError::Semantic(
+                SemanticError::UndeclaredVariable(_),
+                _
+            ))
+        ),
+        "Expected a single semantic error, got {result:?}"
+    );
+}
+
+#[test]
+fn test_semantic_error_placeholder_isnt_reported_again() {
+    // the expressions using an undeclared identifier aren't type checked
+    for src in [
+        "int main(void) { int *p = x; return *p; }",
+        "int main(void) { x = 1; return 0; }",
+        "int main(void) { return x.member; }",
+        "int main(void) { return x->member; }",
+        "int main(void) { return x[0] + 1; }",
+        "int main(void) { return *x; }",
+        "int main(void) { int a = f(1, 2); return a; }",
+        "int main(void) { switch (1) { case x: return 0; } return 1; }",
+        "enum e { A = x, B }; int main(void) { return B; }",
+        "static int s = x; int main(void) { return s; }",
+        "struct s { int a; }; int main(void) { struct s v = x; return v.a; }",
+    ] {
+        assert_eq!(semantic_errors(src).len(), 1, "Expected one error in {src}");
+    }
+}
+
+#[test]
+fn test_semantic_recovers_in_nested_blocks_and_functions() {
+    let src = "int f(void) { { return a; } } \
+               int g(void) { for (int i = 0; i < 3; i++) { i = b; } return c; } \
+               int main(void) { while (1) { int d; int d; } goto end; }";
+
+    assert_eq!(
+        semantic_errors(src)
+            .iter()
+            .map(|error| error.split(": ").last().unwrap_or_default())
+            .collect::<Vec<_>>(),
+        vec![
+            "undeclared variable `a`",
+            "undeclared variable `b`",
+            "undeclared variable `c`",
+            "duplicate declaration of `d`",
+            "undefined label `end`",
+        ]
+    );
+}
+
+#[test]
+fn test_semantic_recovers_from_case_and_label_errors() {
+    let src = "int main(void) { \
+               case 1: ; default: ; \
+               switch (1) { case 2: case 2: default: default: break; } \
+               a: a: return 0; }";
+
+    assert_eq!(
+        semantic_errors(src)
+            .iter()
+            .map(|error| error.split(": ").last().unwrap_or_default())
+            .collect::<Vec<_>>(),
+        vec![
+            "case outside of switch",
+            "case outside of switch",
+            "duplicate case value 2",
+            "multiple default labels",
+            "duplicate label `a`",
+        ]
+    );
+}
+
+#[test]
+fn test_semantic_recovers_inside_a_statement() {
+    // the statement with a type error is still labeled
+    let src = "int main(void) { int a = 1; if ((1 = 2) + a) break; return a; }";
+    assert_eq!(
+        semantic_errors(src)
+            .iter()
+            .map(|error| error.split(": ").last().unwrap_or_default())
+            .collect::<Vec<_>>(),
+        vec![
+            "invalid lvalue",
+            "break statement outside of loop or switch"
+        ]
+    );
+
+    // every operand with an error is reported, but not the expressions using them
+    let src = "int main(void) { int a; a = (1 = 2) + (3 = 4); return *(5 = 6); }";
+    assert_eq!(
+        semantic_errors(src)
+            .iter()
+            .map(|error| error.split(": ").last().unwrap_or_default())
+            .collect::<Vec<_>>(),
+        vec!["invalid lvalue", "invalid lvalue", "invalid lvalue"]
+    );
+}
+
+#[test]
+fn test_semantic_file_variable_with_invalid_initializer_is_declared() {
+    for src in [
+        "int *gp = 5; int main(void) { int *q = gp; return *gp; }",
+        "int a[2] = {1, 2, 3}; int main(void) { return a[0]; }",
+        "struct s v; int main(void) { return 0; } int *f(void) { return (int *) &v; }",
+    ] {
+        assert_eq!(
+            semantic_errors(src).len(),
+            1,
+            "{src}: {:?}",
+            semantic_errors(src)
+        );
+    }
+}
+
+#[test]
+fn test_semantic_type_error_placeholder_isnt_reported_again() {
+    // the values computed from an expression with a type error aren't checked again
+    for src in [
+        "enum e { A = (1 = 2), B }; int main(void) { return B; }",
+        "int main(void) { switch (1) { case (1 = 2): return 0; } return 1; }",
+        "int main(void) { static int s = (1 = 2); return s; }",
+        "int x = (1 = 2); int main(void) { return x; }",
+        "int main(void) { int *p = &(1 = 2); return *p; }",
+        "int f(int a); int main(void) { return f((1 = 2)); }",
+    ] {
+        assert_eq!(semantic_errors(src).len(), 1, "Expected one error in {src}");
+    }
+}
@@ -1,12 +1,16 @@
 /*!
 This file covers: Lowering C AST to Tacky IR (tacky/from.rs).
 Tests control flow generation for if/else, ternary, short-circuit AND/OR,
-while, do-while, for loops, break, and continue statements.
+while, do-while, for loops, break, continue, goto and switch statements, function calls,
+compound assignments, `++`/`--`, the comma operator, enumeration constants,
+pointers and arrays, calls through function pointers, the `__builtin_va_*` builtins
+and the reads of volatile objects.
 Does NOT cover: exact instruction sequences (too fragile), optimization passes.
 Suggestions: add property tests for instruction count bounds.
 */
 
-use fcc::c_ast::ast::Program;
+use fcc::c_ast::ast::{Const, Program, Type};
+use fcc::common::symbols::StaticInit;
 use fcc::driver::validate_semantics;
 use fcc::lexer::lex;
 use fcc::tacky::ast::{
@@ -77,6 +81,13 @@ fn has_unary_op(instructions: &[TackyInstruction], op: &TackyUnaryOperator) -> b
     })
 }
 
+// Helper: check if instructions contain a FunCall
+fn has_fun_call(instructions: &[TackyInstruction]) -> bool {
+    instructions
+        .iter()
+        .any(|i| matches!(i, TackyInstruction::FunCall(_, _, _)))
+}
+
 // =============================================================================
 // BASIC LOWERING
 // =============================================================================
@@ -86,7 +97,7 @@ fn test_tacky_gen_return_constant() {
     let src = "int main(void){ return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_return(instructions),
         "Should contain Return instruction"
@@ -98,7 +109,7 @@ fn test_tacky_gen_return_constant_nonzero() {
     let src = "int main(void){ return 42; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Should have a return, and somewhere there should be constant 42
     assert!(
@@ -107,10 +118,12 @@ fn test_tacky_gen_return_constant_nonzero() {
     );
 
     // Check that 42 appears somewhere in the instructions
-    let has_42 = instructions.iter().any(|i| match i {
-        TackyInstruction::Return(TackyValue::Constant(42)) => true,
-        TackyInstruction::Copy(TackyValue::Constant(42), _) => true,
-        _ => false,
+    let has_42 = instructions.iter().any(|i| {
+        matches!(
+            i,
+            TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(42)))
+                | TackyInstruction::Copy(TackyValue::Constant(Const::ConstInt(42)), _)
+        )
     });
     assert!(has_42, "Should reference constant 42");
 }
@@ -120,7 +133,7 @@ fn test_tacky_gen_unary_negate() {
     let src = "int main(void){ int x=1; return -x; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_unary_op(instructions, &TackyUnaryOperator::Negate),
         "Should contain Negate unary"
@@ -132,7 +145,7 @@ fn test_tacky_gen_unary_complement() {
     let src = "int main(void){ int x=1; return ~x; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_unary_op(instructions, &TackyUnaryOperator::Complement),
         "Should contain Complement unary"
@@ -144,7 +157,7 @@ fn test_tacky_gen_unary_not() {
     let src = "int main(void){ int x=1; return !x; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_unary_op(instructions, &TackyUnaryOperator::Not),
         "Should contain Not unary"
@@ -160,7 +173,7 @@ fn test_tacky_gen_binary_add() {
     let src = "int main(void){ int x=1; int y=2; return x + y; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_binary_op(instructions, &TackyBinaryOperator::Add),
         "Should contain Add binary"
@@ -172,7 +185,7 @@ fn test_tacky_gen_binary_subtract() {
     let src = "int main(void){ int x=5; int y=3; return x - y; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_binary_op(instructions, &TackyBinaryOperator::Subtract),
         "Should contain Subtract binary"
@@ -184,7 +197,7 @@ fn test_tacky_gen_binary_multiply() {
     let src = "int main(void){ int x=2; int y=3; return x * y; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_binary_op(instructions, &TackyBinaryOperator::Multiply),
         "Should contain Multiply binary"
@@ -196,7 +209,7 @@ fn test_tacky_gen_binary_divide() {
     let src = "int main(void){ int x=6; int y=2; return x / y; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_binary_op(instructions, &TackyBinaryOperator::Divide),
         "Should contain Divide binary"
@@ -208,7 +221,7 @@ fn test_tacky_gen_binary_remainder() {
     let src = "int main(void){ int x=7; int y=3; return x % y; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
     assert!(
         has_binary_op(instructions, &TackyBinaryOperator::Remainder),
         "Should contain Remainder binary"
@@ -224,7 +237,7 @@ fn test_tacky_gen_and_short_circuit() {
     let src = "int main(void){ int a=1; int b=0; return a && b; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // AND short-circuit should generate JumpIfZero and labels
     assert!(
@@ -239,7 +252,7 @@ fn test_tacky_gen_or_short_circuit() {
     let src = "int main(void){ int a=0; int b=1; return a || b; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // OR short-circuit should generate JumpIfNotZero and labels
     assert!(
@@ -258,7 +271,7 @@ fn test_tacky_gen_assignment() {
     let src = "int main(void){ int x=1; x = 2; return x; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Assignment should generate Copy instructions
     assert!(has_copy(instructions), "Assignment should generate Copy");
@@ -273,7 +286,7 @@ fn test_tacky_gen_ternary() {
     let src = "int main(void){ int x=0; return x ? 1 : 2; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Ternary should generate control flow
     assert!(
@@ -292,7 +305,7 @@ fn test_tacky_gen_ternary_with_expressions() {
     let src = "int main(void){ int a=1; int b=2; return a > b ? a : b; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Should have relational comparison and control flow
     assert!(
@@ -311,7 +324,7 @@ fn test_tacky_gen_if_without_else() {
     let src = "int main(void){ int x=0; if (x) return 1; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // If without else should generate conditional jump and labels
     assert!(has_jump_if_zero(instructions), "If should use JumpIfZero");
@@ -323,7 +336,7 @@ fn test_tacky_gen_if_with_else() {
     let src = "int main(void){ int x=0; if (x) return 1; else return 2; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // If with else should generate conditional jump, unconditional jump, and labels
     assert!(has_jump_if_zero(instructions), "If should use JumpIfZero");
@@ -350,7 +363,7 @@ fn test_tacky_gen_if_with_complex_condition() {
     let src = "int main(void){ int x=1; int y=2; if (x < y) return 1; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     assert!(
         has_binary_op(instructions, &TackyBinaryOperator::LessThan),
@@ -397,7 +410,7 @@ fn test_tacky_gen_relational_operators() {
 
     for (src, ref expected_op) in cases {
         let tacky = lower_to_tacky(src).expect("should lower");
-        let instructions = &tacky.function_definition.instructions;
+        let instructions = &tacky.function_definitions[0].instructions;
         assert!(
             has_binary_op(instructions, expected_op),
             "Should have {:?} for source: {}",
@@ -438,7 +451,7 @@ fn test_tacky_gen_bitwise_operators() {
 
     for (src, ref expected_op) in cases {
         let tacky = lower_to_tacky(src).expect("should lower");
-        let instructions = &tacky.function_definition.instructions;
+        let instructions = &tacky.function_definitions[0].instructions;
         assert!(
             has_binary_op(instructions, expected_op),
             "Should have {:?} for source: {}",
@@ -457,7 +470,7 @@ fn test_tacky_gen_declaration_with_init() {
     let src = "int main(void){ int x = 42; return x; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Declaration with initializer should generate Copy
     assert!(
@@ -471,7 +484,7 @@ fn test_tacky_gen_declaration_without_init() {
     let src = "int main(void){ int x; x = 5; return x; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Should still work and have copies for assignment
     assert!(has_copy(instructions), "Assignment should generate Copy");
@@ -487,7 +500,7 @@ fn test_tacky_gen_while_loop_basic() {
     let src = "int main(void){ int x = 0; while (x) x = 1; return x; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // While should generate: Label (continue), JumpIfZero (to break), body, Jump (to continue), Label (break)
     assert!(has_label(instructions), "While should generate labels");
@@ -517,7 +530,7 @@ fn test_tacky_gen_while_with_break() {
     let src = "int main(void){ while (1) break; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Break generates a Jump instruction
     let jump_count = instructions
@@ -535,7 +548,7 @@ fn test_tacky_gen_while_with_continue() {
     let src = "int main(void){ int x = 0; while (x) { continue; x = 1; } return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Continue generates a Jump instruction to continue label
     let jump_count = instructions
@@ -557,7 +570,7 @@ fn test_tacky_gen_do_while_basic() {
     let src = "int main(void){ int x = 0; do x = 1; while (x); return x; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Do-while should generate: Label (start), body, Label (continue), JumpIfNotZero (to start), Label (break)
     assert!(has_label(instructions), "Do-while should generate labels");
@@ -583,7 +596,7 @@ fn test_tacky_gen_do_while_with_break() {
     let src = "int main(void){ do break; while (1); return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     assert!(
         has_jump(instructions),
@@ -597,7 +610,7 @@ fn test_tacky_gen_do_while_with_continue() {
     let src = "int main(void){ int x = 1; do { continue; x = 0; } while (x); return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Continue in do-while jumps to the continue label (before condition check)
     assert!(
@@ -615,7 +628,7 @@ fn test_tacky_gen_for_loop_full() {
     let src = "int main(void){ for (int i = 0; i; i) return i; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // For should generate: init, Label (start), cond check, JumpIfZero (to break), body, Label (continue), post, Jump (to start), Label (break)
     assert!(has_label(instructions), "For should generate labels");
@@ -632,7 +645,7 @@ fn test_tacky_gen_for_loop_empty_init() {
     let src = "int main(void){ int i = 0; for (; i; i) return i; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     assert!(has_label(instructions), "For should generate labels");
     assert!(has_jump_if_zero(instructions), "For should check condition");
@@ -643,7 +656,7 @@ fn test_tacky_gen_for_loop_empty_cond() {
     let src = "int main(void){ for (int i = 0;; i) { break; } return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Without condition, there's no JumpIfZero for the condition itself
     assert!(has_label(instructions), "For should generate labels");
@@ -655,7 +668,7 @@ fn test_tacky_gen_for_loop_empty_post() {
     let src = "int main(void){ for (int i = 0; i;) { break; } return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     assert!(has_label(instructions), "For should generate labels");
 }
@@ -665,7 +678,7 @@ fn test_tacky_gen_for_loop_infinite() {
     let src = "int main(void){ for (;;) break; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Infinite for loop still has labels and jump structure
     assert!(has_label(instructions), "Infinite for should have labels");
@@ -677,7 +690,7 @@ fn test_tacky_gen_for_with_break() {
     let src = "int main(void){ for (int i = 0; i; i) break; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Break in for jumps to break label
     let jump_count = instructions
@@ -692,7 +705,7 @@ fn test_tacky_gen_for_with_continue() {
     let src = "int main(void){ for (int i = 0; i; i) continue; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Continue in for jumps to continue label (before post expression)
     let jump_count = instructions
@@ -710,7 +723,7 @@ fn test_tacky_gen_for_init_with_expression() {
     let src = "int main(void){ int i; for (i = 0; i; i) break; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // For with expression init should generate copy
     assert!(
@@ -728,7 +741,7 @@ fn test_tacky_gen_nested_while_loops() {
     let src = "int main(void){ while (1) while (1) break; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Nested loops should have multiple sets of labels
     let label_count = instructions
@@ -747,7 +760,7 @@ fn test_tacky_gen_nested_for_loops() {
     let src = "int main(void){ for (int i = 0; i; i) for (int j = 0; j; j) break; return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     let label_count = instructions
         .iter()
@@ -765,7 +778,7 @@ fn test_tacky_gen_break_in_inner_loop() {
     let src = "int main(void){ while (1) { while (1) break; break; } return 0; }";
     let tacky = lower_to_tacky(src).expect("should lower");
 
-    let instructions = &tacky.function_definition.instructions;
+    let instructions = &tacky.function_definitions[0].instructions;
 
     // Each break should jump to its own loop's break label
     let jump_count = instructions
@@ -777,3 +790,947 @@ fn test_tacky_gen_break_in_inner_loop() {
         "Nested loops with breaks should have multiple jumps"
     );
 }
+
+// =============================================================================
+// FUNCTIONS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_function_call() {
+    let src = "int foo(int a, int b) { return a - b; } int main(void){ return foo(2, 1); }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    assert_eq!(tacky.function_definitions.len(), 2);
+
+    let foo = &tacky.function_definitions[0];
+    assert_eq!(foo.name.value, "foo");
+    assert_eq!(foo.params.len(), 2);
+
+    let instructions = &tacky.function_definitions[1].instructions;
+    assert!(has_fun_call(instructions), "Call should generate FunCall");
+    match instructions
+        .iter()
+        .find(|i| matches!(i, TackyInstruction::FunCall(_, _, _)))
+    {
+        Some(TackyInstruction::FunCall(name, args, _)) => {
+            assert_eq!(name.value, "foo");
+            assert_eq!(args.len(), 2);
+        }
+        _ => panic!("Expected FunCall"),
+    }
+}
+
+#[test]
+fn test_tacky_gen_declarations_are_skipped() {
+    let src = "int foo(void); int main(void){ int bar(void); return foo() + bar(); }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    // Only definitions are lowered
+    assert_eq!(tacky.function_definitions.len(), 1);
+    assert_eq!(tacky.function_definitions[0].name.value, "main");
+}
+
+#[test]
+fn test_tacky_gen_function_without_return() {
+    let src = "int foo(void) { } int main(void){ return foo(); }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    // Every function gets an implicit `return 0`
+    let instructions = &tacky.function_definitions[0].instructions;
+    assert!(matches!(
+        instructions.last(),
+        Some(TackyInstruction::Return(TackyValue::Constant(
+            Const::ConstInt(0)
+        )))
+    ));
+}
+
+// =============================================================================
+// STATIC VARIABLES
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_static_variables() {
+    let src =
+        "int a = 3; int b; extern int c; int main(void){ static int d = 4; return a + b + c + d; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    // extern declarations without a definition aren't emitted
+    let names: Vec<_> = tacky
+        .static_variables
+        .iter()
+        .map(|v| v.name.value.as_str())
+        .collect();
+    assert_eq!(names.len(), 3);
+    assert!(!names.contains(&"c"));
+
+    let a = tacky
+        .static_variables
+        .iter()
+        .find(|v| v.name.value == "a")
+        .expect("a is emitted");
+    assert_eq!(a.init, StaticInit::IntInit(3));
+    assert!(a.global);
+
+    // tentative definitions are initialized to zero
+    let b = tacky
+        .static_variables
+        .iter()
+        .find(|v| v.name.value == "b")
+        .expect("b is emitted");
+    assert_eq!(b.init, StaticInit::IntInit(0));
+
+    // local statics have internal linkage
+    assert!(
+        tacky
+            .static_variables
+            .iter()
+            .any(|v| v.init == StaticInit::IntInit(4) && !v.global)
+    );
+}
+
+#[test]
+fn test_tacky_gen_static_function_is_not_global() {
+    let src = "static int foo(void) { return 1; } int main(void){ return foo(); }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    assert!(!tacky.function_definitions[0].global);
+    assert!(tacky.function_definitions[1].global);
+}
+
+// =============================================================================
+// LONG INTEGERS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_sign_extend_and_truncate() {
+    let src = "int main(void){ int i = 1; long l = i; return l; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::SignExtend(_, _)))
+    );
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Truncate(_, _)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_temporaries_are_typed() {
+    let src = "long foo(long a){ return a * 2; } int main(void){ return 0; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let Some(TackyInstruction::Binary(_, _, _, TackyValue::Var(dst))) =
+        instructions.iter().find(|i| {
+            matches!(
+                i,
+                TackyInstruction::Binary(TackyBinaryOperator::Multiply, ..)
+            )
+        })
+    else {
+        panic!("Expected a multiplication");
+    };
+    let symbol = tacky.symbols.get(&dst.value).expect("temporary is tracked");
+    assert_eq!(symbol.ty, Type::Long);
+}
+
+#[test]
+fn test_tacky_gen_long_static_variable() {
+    let src = "long x = 4294967296; int main(void){ return 0; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    assert_eq!(
+        tacky.static_variables[0].init,
+        StaticInit::LongInit(4294967296)
+    );
+}
+
+// =============================================================================
+// UNSIGNED INTEGERS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_zero_extend() {
+    let src = "int main(void){ unsigned u = 1; long l = u; return 0; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::ZeroExtend(_, _)))
+    );
+    assert!(
+        !instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::SignExtend(_, _)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_same_size_cast_is_copy() {
+    let src = "int main(void){ int i = -1; unsigned u = (unsigned) i; return 0; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    assert!(!instructions.iter().any(|i| matches!(
+        i,
+        TackyInstruction::SignExtend(..)
+            | TackyInstruction::ZeroExtend(..)
+            | TackyInstruction::Truncate(..)
+    )));
+}
+
+// =============================================================================
+// FLOATING-POINT NUMBERS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_double_conversions() {
+    let src = "int main(void){ double d = 1; unsigned long ul = d; d = ul; int i = d; \
+               unsigned u = d; return 0; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+    let count = |f: fn(&TackyInstruction) -> bool| instructions.iter().filter(|i| f(i)).count();
+
+    assert_eq!(count(|i| matches!(i, TackyInstruction::IntToDouble(..))), 1);
+    assert_eq!(
+        count(|i| matches!(i, TackyInstruction::UIntToDouble(..))),
+        1
+    );
+    assert_eq!(count(|i| matches!(i, TackyInstruction::DoubleToInt(..))), 1);
+    assert_eq!(
+        count(|i| matches!(i, TackyInstruction::DoubleToUInt(..))),
+        2
+    );
+}
+
+#[test]
+fn test_tacky_gen_double_temporaries_are_typed() {
+    let src = "int main(void){ double d = 1.5; return d * 2.0 > 1; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let Some(TackyInstruction::Binary(_, _, _, TackyValue::Var(dst))) =
+        instructions.iter().find(|i| {
+            matches!(
+                i,
+                TackyInstruction::Binary(TackyBinaryOperator::Multiply, ..)
+            )
+        })
+    else {
+        panic!("Expected a multiplication");
+    };
+    assert_eq!(
+        tacky.symbols.get(&dst.value).map(|s| s.ty.clone()),
+        Some(Type::Double)
+    );
+}
+
+// =============================================================================
+// POINTERS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_address_of_and_dereference() {
+    let src = "int main(void){ int x = 1; int *p = &x; return *p; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let Some(TackyInstruction::GetAddress(TackyValue::Var(src), TackyValue::Var(addr))) =
+        instructions
+            .iter()
+            .find(|i| matches!(i, TackyInstruction::GetAddress(..)))
+    else {
+        panic!("Expected GetAddress of a variable");
+    };
+    assert!(src.value.starts_with("x"));
+    assert_eq!(
+        tacky.symbols.get(&addr.value).map(|s| s.ty.clone()),
+        Some(Type::Pointer(Box::new(Type::Int)))
+    );
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Load(..)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_assignment_through_pointer() {
+    let src = "int main(void){ int x = 1; int *p = &x; *p = 2; return x; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        TackyInstruction::Store(TackyValue::Constant(Const::ConstInt(2)), _)
+    )));
+    // the assigned object is never read
+    assert!(
+        !instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Load(..)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_address_of_dereference_is_the_pointer() {
+    let src = "int main(void){ int x = 1; int *p = &x; int *q = &*p; return q == p; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+    let count = |f: fn(&TackyInstruction) -> bool| instructions.iter().filter(|i| f(i)).count();
+
+    assert_eq!(count(|i| matches!(i, TackyInstruction::GetAddress(..))), 1);
+    assert_eq!(count(|i| matches!(i, TackyInstruction::Load(..))), 0);
+}
+
+// =============================================================================
+// ARRAYS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_subscript_scales_the_index() {
+    let src = "int main(void){ long a[3][4]; long i = 1; return a[i][2]; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let scales: Vec<_> = instructions
+        .iter()
+        .filter_map(|i| match i {
+            TackyInstruction::AddPtr(_, _, scale, _) => Some(*scale),
+            _ => None,
+        })
+        .collect();
+    // a row of 4 longs, then a single long
+    assert_eq!(scales, vec![32, 8]);
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Load(..)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_pointer_subtraction_divides_by_element_size() {
+    let src = "int main(void){ int a[4]; int *p = a + 3; int *q = p - 2; return p - q; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    // p - 2 negates the index
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Unary(TackyUnaryOperator::Negate, _, _)))
+    );
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        TackyInstruction::Binary(
+            TackyBinaryOperator::Divide,
+            _,
+            TackyValue::Constant(Const::ConstLong(4)),
+            _
+        )
+    )));
+}
+
+#[test]
+fn test_tacky_gen_compound_initializer_copies_to_offsets() {
+    let src = "int main(void){ int a[2][2] = {{1, 2}, {3}}; return a[1][0]; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let offsets: Vec<_> = instructions
+        .iter()
+        .filter_map(|i| match i {
+            TackyInstruction::CopyToOffset(_, _, offset) => Some(*offset),
+            _ => None,
+        })
+        .collect();
+    // the missing element is zeroed
+    assert_eq!(offsets, vec![0, 4, 8, 12]);
+}
+
+#[test]
+fn test_tacky_gen_static_array() {
+    let src = "int main(void){ static long a[3] = {1}; return a[0]; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    assert_eq!(
+        tacky.static_variables[0].init,
+        StaticInit::ArrayInit(vec![StaticInit::LongInit(1), StaticInit::ZeroInit(16)])
+    );
+    // a static array is addressed through GetAddress as well
+    assert!(
+        tacky.function_definitions[0]
+            .instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::GetAddress(..)))
+    );
+}
+
+// =============================================================================
+// CHARACTERS AND STRINGS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_string_initializer_copies_in_chunks() {
+    let src = r#"int main(void){ char s[14] = "hello, world"; return s[0]; }"#;
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let copies: Vec<_> = instructions
+        .iter()
+        .filter_map(|i| match i {
+            TackyInstruction::CopyToOffset(TackyValue::Constant(c), _, offset) => {
+                Some((c.clone(), *offset))
+            }
+            _ => None,
+        })
+        .collect();
+    // 8 bytes, then 4, then what's left one byte at a time, padded with zeros
+    assert_eq!(
+        copies,
+        vec![
+            (Const::ConstLong(i64::from_le_bytes(*b"hello, w")), 0),
+            (Const::ConstInt(i32::from_le_bytes(*b"orld")), 8),
+            (Const::ConstChar(0), 12),
+            (Const::ConstChar(0), 13),
+        ]
+    );
+}
+
+#[test]
+fn test_tacky_gen_string_literal_becomes_constant() {
+    let src = r#"int main(void){ char *p = "hi"; return p[1]; }"#;
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    assert_eq!(tacky.static_constants.len(), 1);
+    let constant = &tacky.static_constants[0];
+    assert_eq!(constant.ty, Type::Array(Box::new(Type::Char), 3));
+    assert_eq!(
+        constant.init,
+        StaticInit::StringInit("hi".to_string(), true)
+    );
+
+    // the pointer is the address of the constant
+    assert!(tacky.function_definitions[0].instructions.iter().any(|i| matches!(
+        i,
+        TackyInstruction::GetAddress(TackyValue::Var(id), _) if id.value == constant.name.value
+    )));
+}
+
+#[test]
+fn test_tacky_gen_char_conversions() {
+    let src = "int main(void){ char c = 1; long l = c; unsigned char u = l; return u; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    // int -> char, char -> long, long -> unsigned char, unsigned char -> int
+    let conversions: Vec<_> = instructions
+        .iter()
+        .filter_map(|i| match i {
+            TackyInstruction::SignExtend(..) => Some("sign_extend"),
+            TackyInstruction::ZeroExtend(..) => Some("zero_extend"),
+            TackyInstruction::Truncate(..) => Some("truncate"),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        conversions,
+        vec!["truncate", "sign_extend", "truncate", "zero_extend"]
+    );
+}
+
+// =============================================================================
+// STRUCTURES
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_member_access_uses_offsets() {
+    let src = "struct s { char a; long b; };
+        int main(void){ struct s x; x.b = 3; return x.b; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    // members of a structure variable are accessed in place
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::CopyToOffset(_, _, 8)))
+    );
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::CopyFromOffset(_, 8, _)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_arrow_offsets_the_pointer() {
+    let src = "struct s { int a; long b; };
+        int main(void){ struct s x; struct s *p = &x; p->b = 1; return p->a; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    // only the second member needs pointer arithmetic
+    let offsets: Vec<_> = instructions
+        .iter()
+        .filter_map(|i| match i {
+            TackyInstruction::AddPtr(_, TackyValue::Constant(offset), 1, _) => Some(offset.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(offsets, vec![Const::ConstLong(8)]);
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Store(..)))
+    );
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Load(..)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_static_structure() {
+    let src = "struct s { int a; char b[3]; double d; }; struct s x = {1, \"ab\", 2.0};
+        int main(void){ return x.a; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    assert_eq!(
+        tacky.static_variables[0].init,
+        StaticInit::ArrayInit(vec![
+            StaticInit::IntInit(1),
+            StaticInit::StringInit("ab".to_string(), true),
+            StaticInit::ZeroInit(1),
+            StaticInit::DoubleInit(2.0),
+        ])
+    );
+}
+
+// =============================================================================
+// GOTO AND LABELED STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_goto_jumps_to_label() {
+    let src = "int main(void){ int x = 0; loop: x = x + 1; if (x < 3) goto loop; return x; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let label = instructions
+        .iter()
+        .find_map(|i| match i {
+            TackyInstruction::Label(label) if label.value.starts_with("goto_") => Some(label),
+            _ => None,
+        })
+        .expect("labeled statement emits a label");
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Jump(target) if target.value == label.value))
+    );
+}
+
+// =============================================================================
+// SWITCH STATEMENTS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_sparse_switch_compares_each_case() {
+    let src = "int main(void){ int x = 7; switch (x) { case 1: return 1; case 100: return 2;
+        case -5: return 3; } return 0; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let compared: Vec<_> = instructions
+        .iter()
+        .filter_map(|i| match i {
+            TackyInstruction::Binary(TackyBinaryOperator::Equal, _, TackyValue::Constant(c), _) => {
+                Some(c.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        compared,
+        vec![
+            Const::ConstInt(1),
+            Const::ConstInt(100),
+            Const::ConstInt(-5)
+        ]
+    );
+    assert!(
+        !instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::JumpTable(..)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_dense_switch_uses_a_jump_table() {
+    let src = "int main(void){ int x = 7; switch (x) { case 3: return 1; case 4: return 2;
+        case 6: return 3; case 7: return 4; default: return 5; } }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    // the index is the value minus the smallest case
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        TackyInstruction::Binary(
+            TackyBinaryOperator::Subtract,
+            _,
+            TackyValue::Constant(Const::ConstULong(3)),
+            _
+        )
+    )));
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        TackyInstruction::Binary(
+            TackyBinaryOperator::GreaterThan,
+            _,
+            TackyValue::Constant(Const::ConstULong(4)),
+            _
+        )
+    )));
+
+    let Some(TackyInstruction::JumpTable(_, _, targets)) = instructions
+        .iter()
+        .find(|i| matches!(i, TackyInstruction::JumpTable(..)))
+    else {
+        panic!("Expected jump table");
+    };
+    // the missing case 5 goes to the default
+    let targets: Vec<_> = targets.iter().map(|t| t.value.as_str()).collect();
+    assert_eq!(targets.len(), 5);
+    assert!(targets[2].starts_with("default_st"));
+    assert!(targets[0].starts_with("case_st"));
+}
+
+// =============================================================================
+// COMPOUND ASSIGNMENT AND INCREMENT
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_postfix_returns_old_value() {
+    let src = "int main(void){ int i = 5; return i++; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    assert!(has_binary_op(instructions, &TackyBinaryOperator::Add));
+    // the variable is updated, but the copy made before is returned
+    let Some(TackyInstruction::Return(TackyValue::Var(returned))) = instructions
+        .iter()
+        .find(|i| matches!(i, TackyInstruction::Return(_)))
+    else {
+        panic!("Expected return of a variable");
+    };
+    assert!(returned.value.starts_with("postfix_old"));
+}
+
+#[test]
+fn test_tacky_gen_compound_assignment_evaluates_lvalue_once() {
+    let src = "int main(void){ int a[3] = {1, 2, 3}; int i = 0; a[i++] += 2; return a[0]; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let count = |f: fn(&TackyInstruction) -> bool| instructions.iter().filter(|i| f(i)).count();
+    // one address for the element, read through it and written back through it
+    assert_eq!(
+        count(|i| matches!(i, TackyInstruction::Store(..))),
+        1,
+        "a[i++] should be stored once"
+    );
+    assert_eq!(
+        count(|i| matches!(
+            i,
+            TackyInstruction::Binary(TackyBinaryOperator::Add, _, _, _)
+        )),
+        2,
+        "one addition for i++ and one for += 2"
+    );
+    // the element for the += and the one returned
+    assert_eq!(count(|i| matches!(i, TackyInstruction::Load(..))), 2);
+}
+
+#[test]
+fn test_tacky_gen_char_compound_assignment_converts_back() {
+    let src = "int main(void){ char c = 1; c += 1; return c; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    // the addition is done as an int and truncated back into the char
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::SignExtend(..)))
+    );
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::Truncate(..)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_pointer_decrement_negates_index() {
+    let src = "int main(void){ long a[2] = {1, 2}; long *p = a + 1; p--; return *p; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    assert!(has_unary_op(instructions, &TackyUnaryOperator::Negate));
+    assert!(
+        instructions
+            .iter()
+            .any(|i| matches!(i, TackyInstruction::AddPtr(_, _, 8, _)))
+    );
+}
+
+// =============================================================================
+// COMMA OPERATOR AND DECLARATION LISTS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_comma_evaluates_left_to_right() {
+    let src = "int main(void){ int a = 0; return (a = 5, a * 2); }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    let assign = instructions.iter().position(|i| {
+        matches!(
+            i,
+            TackyInstruction::Copy(TackyValue::Constant(Const::ConstInt(5)), _)
+        )
+    });
+    let multiply = instructions.iter().position(|i| {
+        matches!(
+            i,
+            TackyInstruction::Binary(TackyBinaryOperator::Multiply, _, _, _)
+        )
+    });
+    assert!(assign.is_some() && multiply.is_some());
+    assert!(assign < multiply, "the left side is evaluated first");
+}
+
+#[test]
+fn test_tacky_gen_for_init_declaration_list() {
+    let src =
+        "int main(void){ int s = 0; for (int i = 1, j = 2; i < j; i++) s = i + j; return s; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    // both declarators are initialized before the loop starts
+    let initialized: Vec<_> = instructions
+        .iter()
+        .take_while(|i| !matches!(i, TackyInstruction::Label(_)))
+        .filter_map(|i| match i {
+            TackyInstruction::Copy(TackyValue::Constant(Const::ConstInt(c)), _) => Some(*c),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(initialized, vec![0, 1, 2]);
+}
+
+// =============================================================================
+// ENUMERATIONS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_enumerators_are_constants() {
+    let src = "enum color { RED = 2, GREEN }; enum color g = GREEN; \
+               int main(void){ enum color c = RED; return c + GREEN; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let instructions = &tacky.function_definitions[0].instructions;
+
+    // no storage is used for the enumerators
+    assert_eq!(tacky.static_variables.len(), 1);
+    assert_eq!(tacky.static_variables[0].init, StaticInit::IntInit(3));
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        TackyInstruction::Copy(TackyValue::Constant(Const::ConstInt(2)), _)
+    )));
+    assert!(instructions.iter().any(|i| matches!(
+        i,
+        TackyInstruction::Binary(
+            TackyBinaryOperator::Add,
+            _,
+            TackyValue::Constant(Const::ConstInt(3)),
+            _
+        )
+    )));
+}
+
+// =============================================================================
+// FUNCTION POINTERS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_indirect_call_through_pointer() {
+    let src = "int add(int a, int b) { return a + b; } \
+               int main(void){ int (*fp)(int, int) = add; return (*fp)(1, 2) + fp(3, 4); }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let main = &tacky.function_definitions[1].instructions;
+
+    // `*fp` is the function itself, so calling it needs no load
+    assert!(
+        main.iter()
+            .any(|i| matches!(i, TackyInstruction::GetAddress(..)))
+    );
+    assert!(!main.iter().any(|i| matches!(i, TackyInstruction::Load(..))));
+    assert!(!has_fun_call(main));
+    let calls: Vec<_> = main
+        .iter()
+        .filter_map(|i| match i {
+            TackyInstruction::IndirectCall(_, args, _) => Some(args.len()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(calls, vec![2, 2]);
+}
+
+#[test]
+fn test_tacky_gen_static_table_of_function_pointers() {
+    let src = "int add(int a, int b) { return a + b; } int sub(int a, int b) { return a - b; } \
+               int (*table[3])(int, int) = {add, &sub};";
+    let tacky = lower_to_tacky(src).expect("should lower");
+
+    assert_eq!(tacky.static_variables.len(), 1);
+    assert_eq!(
+        tacky.static_variables[0].init,
+        StaticInit::ArrayInit(vec![
+            StaticInit::PointerInit("add".to_string()),
+            StaticInit::PointerInit("sub".to_string()),
+            StaticInit::ZeroInit(8),
+        ])
+    );
+}
+
+// =============================================================================
+// VARIADIC FUNCTIONS
+// =============================================================================
+
+#[test]
+fn test_tacky_gen_va_start_and_va_end() {
+    let src = "int f(int n, ...) { \
+                   __builtin_va_list ap; \
+                   __builtin_va_start(ap, n); \
+                   __builtin_va_end(ap); \
+                   return n; \
+               }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let f = &tacky.function_definitions[0].instructions;
+
+    // va_start gets the address of the structure, va_end has nothing to do
+    let starts: Vec<_> = f
+        .iter()
+        .filter(|i| matches!(i, TackyInstruction::VaStart(_)))
+        .collect();
+    assert_eq!(starts.len(), 1);
+    assert!(
+        f.iter()
+            .any(|i| matches!(i, TackyInstruction::GetAddress(..)))
+    );
+}
+
+#[test]
+fn test_tacky_gen_va_arg_reads_registers_or_stack() {
+    let src = "double f(int n, ...) { \
+                   __builtin_va_list ap; \
+                   __builtin_va_start(ap, n); \
+                   return __builtin_va_arg(ap, double); \
+               }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let f = &tacky.function_definitions[0].instructions;
+
+    // fp_offset is compared against the end of the register save area
+    assert!(f.iter().any(|i| matches!(
+        i,
+        TackyInstruction::Binary(
+            TackyBinaryOperator::GreaterThanOrEqual,
+            _,
+            TackyValue::Constant(Const::ConstUInt(176)),
+            _
+        )
+    )));
+    assert!(has_jump_if_not_zero(f));
+    // the next argument is 16 bytes further in the save area or 8 bytes in the stack
+    assert!(f.iter().any(|i| matches!(
+        i,
+        TackyInstruction::Binary(
+            TackyBinaryOperator::Add,
+            _,
+            TackyValue::Constant(Const::ConstUInt(16)),
+            _
+        )
+    )));
+    assert!(f.iter().any(|i| matches!(
+        i,
+        TackyInstruction::AddPtr(_, TackyValue::Constant(Const::ConstLong(8)), 1, _)
+    )));
+    let stores = f
+        .iter()
+        .filter(|i| matches!(i, TackyInstruction::Store(..)))
+        .count();
+    assert_eq!(stores, 2);
+}
+
+// =============================================================================
+// TYPE QUALIFIERS
+// =============================================================================
+
+// Helper: count the Copy instructions reading a variable whose name starts with `prefix`
+fn count_copies_from(instructions: &[TackyInstruction], prefix: &str) -> usize {
+    instructions
+        .iter()
+        .filter(|i| {
+            matches!(i, TackyInstruction::Copy(TackyValue::Var(id), _) if id.value.starts_with(prefix))
+        })
+        .count()
+}
+
+#[test]
+fn test_tacky_gen_volatile_reads_are_kept() {
+    let src = "volatile int status; \
+               int plain; \
+               int main(void) { status; status; plain; return status + status + plain; }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let main = &tacky.function_definitions[0].instructions;
+
+    // every read of a volatile variable is its own instruction, even when it's discarded
+    assert_eq!(count_copies_from(main, "status"), 4);
+    assert_eq!(count_copies_from(main, "plain"), 0);
+}
+
+#[test]
+fn test_tacky_gen_volatile_accesses_through_pointers() {
+    let src = "int main(void) { \
+                   int x = 0; \
+                   volatile int *reg = &x; \
+                   *reg; \
+                   *reg = 1; \
+                   *reg = 1; \
+                   return *reg; \
+               }";
+    let tacky = lower_to_tacky(src).expect("should lower");
+    let main = &tacky.function_definitions[0].instructions;
+
+    // each access through the pointer is a Load or a Store of its own
+    let loads = main
+        .iter()
+        .filter(|i| matches!(i, TackyInstruction::Load(..)))
+        .count();
+    let stores = main
+        .iter()
+        .filter(|i| matches!(i, TackyInstruction::Store(..)))
+        .count();
+    assert_eq!((loads, stores), (2, 2));
+}
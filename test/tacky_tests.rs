@@ -1,3 +1,4 @@
+use fcc::c_ast::ast::Const;
 use fcc::tacky::ast::{
     TackyBinaryOperator, TackyFunctionDefinition, TackyIdentifier, TackyInstruction, TackyProgram,
     TackyUnaryOperator, TackyValue,
@@ -21,9 +22,9 @@ fn test_tacky_identifier_creation() {
 
 #[test]
 fn test_tacky_value_constant() {
-    let val = TackyValue::Constant(42);
+    let val = TackyValue::Constant(Const::ConstInt(42));
 
-    if let TackyValue::Constant(value) = val {
+    if let TackyValue::Constant(Const::ConstInt(value)) = val {
         assert_eq!(value, 42);
     } else {
         panic!("Expected constant value");
@@ -45,15 +46,17 @@ fn test_tacky_value_variable() {
 #[test]
 fn test_tacky_program_creation() {
     let name = TackyIdentifier::new("main");
-    let instructions = vec![TackyInstruction::Return(TackyValue::Constant(0))];
-    let function_def = TackyFunctionDefinition::new(name, instructions);
-    let program = TackyProgram::new(function_def);
+    let instructions = vec![TackyInstruction::Return(TackyValue::Constant(
+        Const::ConstInt(0),
+    ))];
+    let function_def = TackyFunctionDefinition::new(name, true, vec![], instructions);
+    let program = TackyProgram::new(vec![function_def], vec![]);
 
-    assert_eq!(program.function_definition.name.value, "main");
-    assert_eq!(program.function_definition.instructions.len(), 1);
+    assert_eq!(program.function_definitions[0].name.value, "main");
+    assert_eq!(program.function_definitions[0].instructions.len(), 1);
 
-    if let TackyInstruction::Return(TackyValue::Constant(val)) =
-        &program.function_definition.instructions[0]
+    if let TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(val))) =
+        &program.function_definitions[0].instructions[0]
     {
         assert_eq!(*val, 0);
     } else {
@@ -64,8 +67,10 @@ fn test_tacky_program_creation() {
 #[test]
 fn test_tacky_function_definition_creation() {
     let name = TackyIdentifier::new("func");
-    let instructions = vec![TackyInstruction::Return(TackyValue::Constant(42))];
-    let func_def = TackyFunctionDefinition::new(name, instructions);
+    let instructions = vec![TackyInstruction::Return(TackyValue::Constant(
+        Const::ConstInt(42),
+    ))];
+    let func_def = TackyFunctionDefinition::new(name, true, vec![], instructions);
 
     assert_eq!(func_def.name.value, "func");
     assert_eq!(func_def.instructions.len(), 1);
@@ -73,9 +78,9 @@ fn test_tacky_function_definition_creation() {
 
 #[test]
 fn test_tacky_instruction_return() {
-    let ret_inst = TackyInstruction::Return(TackyValue::Constant(10));
+    let ret_inst = TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(10)));
 
-    if let TackyInstruction::Return(TackyValue::Constant(val)) = ret_inst {
+    if let TackyInstruction::Return(TackyValue::Constant(Const::ConstInt(val))) = ret_inst {
         assert_eq!(val, 10);
     } else {
         panic!("Expected return instruction");
@@ -84,14 +89,14 @@ fn test_tacky_instruction_return() {
 
 #[test]
 fn test_tacky_instruction_unary() {
-    let src = TackyValue::Constant(5);
+    let src = TackyValue::Constant(Const::ConstInt(5));
     let dst = TackyValue::Var(TackyIdentifier::new("temp"));
     let unary_inst = TackyInstruction::Unary(TackyUnaryOperator::Negate, src, dst);
 
     if let TackyInstruction::Unary(op, src_val, dst_val) = unary_inst {
         assert!(matches!(op, TackyUnaryOperator::Negate));
 
-        if let TackyValue::Constant(val) = src_val {
+        if let TackyValue::Constant(Const::ConstInt(val)) = src_val {
             assert_eq!(val, 5);
         } else {
             panic!("Expected constant source");
@@ -109,21 +114,21 @@ fn test_tacky_instruction_unary() {
 
 #[test]
 fn test_tacky_instruction_binary() {
-    let src1 = TackyValue::Constant(10);
-    let src2 = TackyValue::Constant(20);
+    let src1 = TackyValue::Constant(Const::ConstInt(10));
+    let src2 = TackyValue::Constant(Const::ConstInt(20));
     let dst = TackyValue::Var(TackyIdentifier::new("result"));
     let binary_inst = TackyInstruction::Binary(TackyBinaryOperator::Add, src1, src2, dst);
 
     if let TackyInstruction::Binary(op, s1, s2, d) = binary_inst {
         assert!(matches!(op, TackyBinaryOperator::Add));
 
-        if let TackyValue::Constant(val1) = s1 {
+        if let TackyValue::Constant(Const::ConstInt(val1)) = s1 {
             assert_eq!(val1, 10);
         } else {
             panic!("Expected constant first source");
         }
 
-        if let TackyValue::Constant(val2) = s2 {
+        if let TackyValue::Constant(Const::ConstInt(val2)) = s2 {
             assert_eq!(val2, 20);
         } else {
             panic!("Expected constant second source");
@@ -141,12 +146,12 @@ fn test_tacky_instruction_binary() {
 
 #[test]
 fn test_tacky_instruction_copy() {
-    let src = TackyValue::Constant(42);
+    let src = TackyValue::Constant(Const::ConstInt(42));
     let dst = TackyValue::Var(TackyIdentifier::new("temp"));
     let copy_inst = TackyInstruction::Copy(src, dst);
 
     if let TackyInstruction::Copy(source, destination) = copy_inst {
-        if let TackyValue::Constant(val) = source {
+        if let TackyValue::Constant(Const::ConstInt(val)) = source {
             assert_eq!(val, 42);
         } else {
             panic!("Expected constant source");
@@ -194,12 +199,12 @@ fn test_tacky_instruction_jump_if_zero() {
 
 #[test]
 fn test_tacky_instruction_jump_if_not_zero() {
-    let val = TackyValue::Constant(1);
+    let val = TackyValue::Constant(Const::ConstInt(1));
     let label = TackyIdentifier::new("nonzero_label");
     let jump_inst = TackyInstruction::JumpIfNotZero(val, label);
 
     if let TackyInstruction::JumpIfNotZero(v, lbl) = jump_inst {
-        if let TackyValue::Constant(value) = v {
+        if let TackyValue::Constant(Const::ConstInt(value)) = v {
             assert_eq!(value, 1);
         } else {
             panic!("Expected constant value");
@@ -230,7 +235,7 @@ fn test_all_tacky_unary_operators() {
         TackyUnaryOperator::Not,
     ];
 
-    let src = TackyValue::Constant(1);
+    let src = TackyValue::Constant(Const::ConstInt(1));
     let dst = TackyValue::Var(TackyIdentifier::new("temp"));
 
     for op in operators {
@@ -260,8 +265,8 @@ fn test_all_tacky_binary_operators() {
         TackyBinaryOperator::LessThanOrEqual,
     ];
 
-    let src1 = TackyValue::Constant(1);
-    let src2 = TackyValue::Constant(2);
+    let src1 = TackyValue::Constant(Const::ConstInt(1));
+    let src2 = TackyValue::Constant(Const::ConstInt(2));
     let dst = TackyValue::Var(TackyIdentifier::new("result"));
 
     for op in operators {
@@ -275,11 +280,11 @@ fn test_complex_tacky_function() {
     let name = TackyIdentifier::new("complex");
     let instructions = vec![
         TackyInstruction::Copy(
-            TackyValue::Constant(10),
+            TackyValue::Constant(Const::ConstInt(10)),
             TackyValue::Var(TackyIdentifier::new("a")),
         ),
         TackyInstruction::Copy(
-            TackyValue::Constant(20),
+            TackyValue::Constant(Const::ConstInt(20)),
             TackyValue::Var(TackyIdentifier::new("b")),
         ),
         TackyInstruction::Binary(
@@ -291,7 +296,7 @@ fn test_complex_tacky_function() {
         TackyInstruction::Return(TackyValue::Var(TackyIdentifier::new("result"))),
     ];
 
-    let func_def = TackyFunctionDefinition::new(name, instructions);
+    let func_def = TackyFunctionDefinition::new(name, true, vec![], instructions);
     assert_eq!(func_def.instructions.len(), 4);
 
     assert!(matches!(
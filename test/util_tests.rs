@@ -1,4 +1,6 @@
-use fcc::common::util::{indent, opt_box, replace_c_with_i};
+use std::sync::atomic::AtomicUsize;
+
+use fcc::common::util::{indent, opt_box, replace_c_with_i, source_name, temporary_name};
 
 #[test]
 fn test_replace_c_with_i_valid_c_file() {
@@ -103,7 +105,7 @@ fn test_indent_complex_multiline() {
 fn test_opt_box_some() {
     let result = opt_box(Some(42));
     assert!(result.is_some());
-    assert_eq!(*result.unwrap(), 42);
+    assert_eq!(result, Some(Box::new(42)));
 }
 
 #[test]
@@ -116,5 +118,18 @@ fn test_opt_box_none() {
 fn test_opt_box_string() {
     let result = opt_box(Some("hello".to_string()));
     assert!(result.is_some());
-    assert_eq!(*result.unwrap(), "hello".to_string());
+    assert_eq!(result, Some(Box::new("hello".to_string())));
+}
+
+#[test]
+fn test_source_name_of_temporary_name() {
+    let counter = AtomicUsize::new(7);
+    let name = temporary_name("count", &counter);
+    assert_eq!(name, "count.7");
+    assert_eq!(source_name(&name), "count");
+}
+
+#[test]
+fn test_source_name_of_plain_name() {
+    assert_eq!(source_name("main"), "main");
 }